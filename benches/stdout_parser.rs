@@ -0,0 +1,46 @@
+//! Decoding throughput for large CLI messages: `cargo bench --bench stdout_parser`.
+
+use std::hint::black_box;
+use std::time::Instant;
+
+use serde_json::json;
+
+use sdk_claude_rust::internal::json_stream::JsonStreamDecoder;
+
+const READ_SIZE: usize = 64 * 1024;
+const ROUNDS: u32 = 5;
+
+/// A `user` message carrying one tool result of `size` bytes.
+fn tool_result(size: usize) -> Vec<u8> {
+    let text = "lorem ipsum \"quoted\" {braces} [brackets]\n";
+    let content = text.repeat(size / text.len() + 1);
+    let message = json!({
+        "type": "user",
+        "message": {"role": "user", "content": [
+            {"type": "tool_result", "tool_use_id": "toolu_bench", "content": &content[..size]}
+        ]}
+    });
+    let mut line = serde_json::to_vec(&message).unwrap();
+    line.push(b'\n');
+    line
+}
+
+fn main() {
+    for megabytes in [1, 4, 16, 64] {
+        let line = tool_result(megabytes * 1024 * 1024);
+        let start = Instant::now();
+        for _ in 0..ROUNDS {
+            let mut decoder = JsonStreamDecoder::new(line.len() + 1);
+            let mut values = 0;
+            for chunk in line.chunks(READ_SIZE) {
+                values += black_box(decoder.push(chunk)).len();
+            }
+            assert_eq!(values, 1);
+        }
+        let per_round = start.elapsed() / ROUNDS;
+        let throughput = line.len() as f64 / per_round.as_secs_f64() / (1024.0 * 1024.0);
+        println!(
+            "{megabytes:>3} MB tool result: {per_round:>10.2?} per message, {throughput:>7.1} MB/s"
+        );
+    }
+}
@@ -2,6 +2,7 @@ use futures::{pin_mut, StreamExt};
 
 use sdk_claude_rust::client::ClaudeSdkClient;
 use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
 use sdk_claude_rust::message::Message;
 
 #[tokio::main]
@@ -21,14 +22,25 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let stream = client.receive_response()?;
     pin_mut!(stream);
     while let Some(message) = stream.next().await {
-        match message? {
-            Message::Result(result) => {
+        match message {
+            Ok(Message::Result(result)) => {
                 println!(
                     "Session {} consumed cost {:?}",
                     result.session_id, result.total_cost_usd
                 );
             }
-            other => println!("{other:?}"),
+            Ok(other) => println!("{other:?}"),
+            Err(SdkError::BudgetExceeded {
+                spent,
+                limit,
+                result,
+            }) => {
+                println!(
+                    "Session {} stopped after spending ${spent:.4} (limit {limit:?})",
+                    result.session_id
+                );
+            }
+            Err(err) => return Err(err.into()),
         }
     }
 
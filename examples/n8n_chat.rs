@@ -60,10 +60,8 @@ When useful, include concrete node configuration guidance (field names and examp
                         }
                     }
                 }
-                Message::Result(result) => {
-                    if result.is_error {
-                        eprintln!("Error: {}", result.subtype);
-                    }
+                Message::Result(result) if result.is_error => {
+                    eprintln!("Error: {}", result.subtype);
                 }
                 _ => {}
             }
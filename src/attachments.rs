@@ -0,0 +1,370 @@
+//! Local files and directories as prompt content blocks.
+//!
+//! [`AttachmentLoader::load`] reads a file and picks the block the Messages
+//! API expects for it: UTF-8 text becomes a `text` block wrapped in a
+//! `<file>` tag, PNG/JPEG/GIF/WebP images become base64 `image` blocks and
+//! PDFs become base64 `document` blocks. The type is detected from the file
+//! contents, falling back to the extension, and every kind has a size limit.
+//! [`AttachmentLoader::summarize_dir`] describes a directory tree, inlining
+//! small text files, as a single text block.
+//!
+//! Content blocks need a streaming prompt; [`prompt_with_attachments`] builds
+//! one for [`query`](crate::query::query), and [`user_message`] builds the
+//! message to stream through
+//! [`ClaudeSdkClient::query`](crate::client::ClaudeSdkClient::query).
+//!
+//! ```no_run
+//! use sdk_claude_rust::attachments::{prompt_with_attachments, AttachmentLoader};
+//!
+//! # async fn run() -> Result<(), sdk_claude_rust::error::SdkError> {
+//! let loader = AttachmentLoader::new();
+//! let attachments = vec![
+//!     loader.load("screenshot.png")?,
+//!     loader.summarize_dir("src")?,
+//! ];
+//! let prompt = prompt_with_attachments("Why does the layout break?", &attachments);
+//! let messages = sdk_claude_rust::query::query(prompt, None, None).await?;
+//! # drop(messages);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt::Write as _;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use futures::stream;
+use serde_json::{json, Value};
+
+use crate::error::SdkError;
+use crate::internal::client::PromptInput;
+
+/// Default limit for text files.
+pub const DEFAULT_MAX_TEXT_BYTES: u64 = 256 * 1024;
+/// Default limit for images; the API rejects larger ones.
+pub const DEFAULT_MAX_IMAGE_BYTES: u64 = 5 * 1024 * 1024;
+/// Default limit for PDFs.
+pub const DEFAULT_MAX_PDF_BYTES: u64 = 32 * 1024 * 1024;
+
+/// Directories [`AttachmentLoader::summarize_dir`] does not descend into.
+pub const SKIPPED_DIRS: &[&str] = &[".git", "target", "node_modules", "__pycache__", ".venv"];
+
+/// A file prepared for a prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Attachment {
+    Text { name: String, text: String },
+    Image { media_type: String, data: String },
+    Pdf { name: String, data: String },
+}
+
+impl Attachment {
+    /// Messages API content block for the attachment.
+    pub fn to_content_block(&self) -> Value {
+        match self {
+            Attachment::Text { name, text } => json!({
+                "type": "text",
+                "text": format!("<file path=\"{name}\">\n{text}\n</file>"),
+            }),
+            Attachment::Image { media_type, data } => json!({
+                "type": "image",
+                "source": { "type": "base64", "media_type": media_type, "data": data },
+            }),
+            Attachment::Pdf { name, data } => json!({
+                "type": "document",
+                "title": name,
+                "source": { "type": "base64", "media_type": "application/pdf", "data": data },
+            }),
+        }
+    }
+
+    /// MIME type of the attached file.
+    pub fn media_type(&self) -> &str {
+        match self {
+            Attachment::Text { .. } => "text/plain",
+            Attachment::Image { media_type, .. } => media_type,
+            Attachment::Pdf { .. } => "application/pdf",
+        }
+    }
+}
+
+/// Reads files into [`Attachment`]s under size limits.
+#[derive(Debug, Clone)]
+pub struct AttachmentLoader {
+    max_text_bytes: u64,
+    max_image_bytes: u64,
+    max_pdf_bytes: u64,
+    max_dir_entries: usize,
+    max_dir_depth: usize,
+    max_inline_bytes: u64,
+}
+
+impl Default for AttachmentLoader {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl AttachmentLoader {
+    pub fn new() -> Self {
+        Self {
+            max_text_bytes: DEFAULT_MAX_TEXT_BYTES,
+            max_image_bytes: DEFAULT_MAX_IMAGE_BYTES,
+            max_pdf_bytes: DEFAULT_MAX_PDF_BYTES,
+            max_dir_entries: 500,
+            max_dir_depth: 6,
+            max_inline_bytes: 0,
+        }
+    }
+
+    pub fn with_max_text_bytes(mut self, bytes: u64) -> Self {
+        self.max_text_bytes = bytes;
+        self
+    }
+
+    pub fn with_max_image_bytes(mut self, bytes: u64) -> Self {
+        self.max_image_bytes = bytes;
+        self
+    }
+
+    pub fn with_max_pdf_bytes(mut self, bytes: u64) -> Self {
+        self.max_pdf_bytes = bytes;
+        self
+    }
+
+    /// Number of files and directories listed by [`Self::summarize_dir`]
+    /// before the listing is cut off (default 500).
+    pub fn with_max_dir_entries(mut self, entries: usize) -> Self {
+        self.max_dir_entries = entries;
+        self
+    }
+
+    /// How deep [`Self::summarize_dir`] descends (default 6).
+    pub fn with_max_dir_depth(mut self, depth: usize) -> Self {
+        self.max_dir_depth = depth;
+        self
+    }
+
+    /// Total size of text files [`Self::summarize_dir`] inlines after the
+    /// listing; 0 (the default) lists names and sizes only.
+    pub fn with_max_inline_bytes(mut self, bytes: u64) -> Self {
+        self.max_inline_bytes = bytes;
+        self
+    }
+
+    /// Reads `path` as text, image or PDF.
+    pub fn load(&self, path: impl AsRef<Path>) -> Result<Attachment, SdkError> {
+        let path = path.as_ref();
+        let size = fs::metadata(path)?.len();
+        let name = path.display().to_string();
+        let head = read_head(path)?;
+
+        match detect_binary(&head, path) {
+            Some("application/pdf") => {
+                self.check_size(&name, size, self.max_pdf_bytes)?;
+                Ok(Attachment::Pdf {
+                    name,
+                    data: STANDARD.encode(fs::read(path)?),
+                })
+            }
+            Some(media_type) => {
+                self.check_size(&name, size, self.max_image_bytes)?;
+                Ok(Attachment::Image {
+                    media_type: media_type.to_string(),
+                    data: STANDARD.encode(fs::read(path)?),
+                })
+            }
+            None => {
+                self.check_size(&name, size, self.max_text_bytes)?;
+                let text = read_text(path)?.ok_or_else(|| {
+                    SdkError::InvalidConfig(format!(
+                        "{name} is neither UTF-8 text nor a supported image or PDF"
+                    ))
+                })?;
+                Ok(Attachment::Text { name, text })
+            }
+        }
+    }
+
+    /// Text attachment listing the tree under `dir` with file sizes.
+    pub fn summarize_dir(&self, dir: impl AsRef<Path>) -> Result<Attachment, SdkError> {
+        let dir = dir.as_ref();
+        if !dir.is_dir() {
+            return Err(SdkError::InvalidConfig(format!(
+                "{} is not a directory",
+                dir.display()
+            )));
+        }
+
+        let mut walk = DirWalk {
+            lines: Vec::new(),
+            files: Vec::new(),
+            entries: 0,
+            truncated: false,
+        };
+        self.walk(dir, 0, &mut walk)?;
+
+        let mut text = walk.lines.join("\n");
+        if walk.truncated {
+            let _ = write!(
+                text,
+                "\n... listing truncated after {} entries",
+                self.max_dir_entries
+            );
+        }
+
+        let mut budget = self.max_inline_bytes;
+        for (path, size) in walk.files {
+            if size > budget {
+                continue;
+            }
+            if let Some(contents) = read_text(&path)? {
+                budget -= size;
+                let relative = path.strip_prefix(dir).unwrap_or(&path);
+                let _ = write!(
+                    text,
+                    "\n\n<file path=\"{}\">\n{contents}\n</file>",
+                    relative.display()
+                );
+            }
+        }
+
+        Ok(Attachment::Text {
+            name: dir.display().to_string(),
+            text,
+        })
+    }
+
+    fn walk(&self, dir: &Path, depth: usize, walk: &mut DirWalk) -> Result<(), SdkError> {
+        let mut entries = fs::read_dir(dir)?.collect::<Result<Vec<_>, _>>()?;
+        entries.sort_by_key(|entry| entry.file_name());
+        let indent = "  ".repeat(depth);
+
+        for entry in entries {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            let file_type = entry.file_type()?;
+            if name.starts_with('.') || (file_type.is_dir() && SKIPPED_DIRS.contains(&&*name)) {
+                continue;
+            }
+            if walk.entries == self.max_dir_entries {
+                walk.truncated = true;
+                return Ok(());
+            }
+            walk.entries += 1;
+
+            if file_type.is_dir() {
+                walk.lines.push(format!("{indent}{name}/"));
+                if depth + 1 < self.max_dir_depth {
+                    self.walk(&entry.path(), depth + 1, walk)?;
+                }
+            } else if file_type.is_file() {
+                let size = entry.metadata()?.len();
+                walk.lines
+                    .push(format!("{indent}{name} ({})", format_size(size)));
+                walk.files.push((entry.path(), size));
+            }
+        }
+        Ok(())
+    }
+
+    fn check_size(&self, name: &str, size: u64, limit: u64) -> Result<(), SdkError> {
+        if size > limit {
+            return Err(SdkError::InvalidConfig(format!(
+                "{name} is {} which exceeds the {} attachment limit",
+                format_size(size),
+                format_size(limit)
+            )));
+        }
+        Ok(())
+    }
+}
+
+struct DirWalk {
+    lines: Vec<String>,
+    files: Vec<(PathBuf, u64)>,
+    entries: usize,
+    truncated: bool,
+}
+
+/// Reads `path` with [`AttachmentLoader::new`]'s limits.
+pub fn load_file(path: impl AsRef<Path>) -> Result<Attachment, SdkError> {
+    AttachmentLoader::new().load(path)
+}
+
+/// Streaming-mode user message with `attachments` before the `prompt` text.
+pub fn user_message(prompt: &str, attachments: &[Attachment]) -> Value {
+    let mut content: Vec<Value> = attachments
+        .iter()
+        .map(Attachment::to_content_block)
+        .collect();
+    content.push(json!({ "type": "text", "text": prompt }));
+    json!({
+        "type": "user",
+        "message": { "role": "user", "content": content },
+        "parent_tool_use_id": Value::Null,
+    })
+}
+
+/// [`PromptInput`] streaming [`user_message`] as the only message.
+pub fn prompt_with_attachments(prompt: &str, attachments: &[Attachment]) -> PromptInput {
+    PromptInput::from_stream(stream::iter([user_message(prompt, attachments)]))
+}
+
+fn read_head(path: &Path) -> Result<Vec<u8>, SdkError> {
+    use std::io::Read;
+
+    let mut head = Vec::with_capacity(16);
+    fs::File::open(path)?.take(16).read_to_end(&mut head)?;
+    Ok(head)
+}
+
+/// `None` for files that are not valid UTF-8 or contain NUL bytes.
+fn read_text(path: &Path) -> Result<Option<String>, SdkError> {
+    let bytes = fs::read(path)?;
+    Ok(String::from_utf8(bytes)
+        .ok()
+        .filter(|text| !text.contains('\0')))
+}
+
+/// Media type of supported binary files: magic bytes first, then extension.
+fn detect_binary(head: &[u8], path: &Path) -> Option<&'static str> {
+    let by_magic = if head.starts_with(b"\x89PNG\r\n\x1a\n") {
+        Some("image/png")
+    } else if head.starts_with(b"\xff\xd8\xff") {
+        Some("image/jpeg")
+    } else if head.starts_with(b"GIF87a") || head.starts_with(b"GIF89a") {
+        Some("image/gif")
+    } else if head.len() >= 12 && &head[..4] == b"RIFF" && &head[8..12] == b"WEBP" {
+        Some("image/webp")
+    } else if head.starts_with(b"%PDF-") {
+        Some("application/pdf")
+    } else {
+        None
+    };
+    if by_magic.is_some() || std::str::from_utf8(head).is_ok() {
+        return by_magic;
+    }
+
+    let extension = path.extension()?.to_str()?.to_ascii_lowercase();
+    match extension.as_str() {
+        "png" => Some("image/png"),
+        "jpg" | "jpeg" => Some("image/jpeg"),
+        "gif" => Some("image/gif"),
+        "webp" => Some("image/webp"),
+        "pdf" => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+fn format_size(bytes: u64) -> String {
+    const KIB: u64 = 1024;
+    const MIB: u64 = 1024 * KIB;
+    if bytes >= MIB {
+        format!("{:.1} MiB", bytes as f64 / MIB as f64)
+    } else if bytes >= KIB {
+        format!("{:.1} KiB", bytes as f64 / KIB as f64)
+    } else {
+        format!("{bytes} B")
+    }
+}
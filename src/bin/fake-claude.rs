@@ -0,0 +1,240 @@
+//! Minimal stand-in for the Claude Code CLI, used by the end-to-end tests.
+//!
+//! Speaks enough of the stream-json and control protocols to drive
+//! `SubprocessCliTransport` without network access:
+//!
+//! * `-v` prints a version string.
+//! * `--print -- <prompt>` answers one prompt and exits.
+//! * `--input-format stream-json` answers control requests and every user
+//!   message on stdin until stdin closes.
+//!
+//! Replies echo the prompt unless `FAKE_CLAUDE_REPLY` is set. Prompts starting
+//! with `slow` wait for an `interrupt` control request before finishing, and
+//! prompts starting with `tool` ask the SDK for permission to run `Bash`
+//! first. `FAKE_CLAUDE_EXIT_CODE` makes the process fail on startup, and
+//! `FAKE_CLAUDE_BANNER` is printed to stdout before anything else.
+
+use std::io::{self, BufRead, Write};
+
+use serde_json::{json, Value};
+
+const VERSION: &str = "2.0.99 (Claude Code)";
+const MODEL: &str = "claude-fake";
+const SESSION_ID: &str = "fake-session";
+
+struct Session {
+    stdout: io::Stdout,
+    initialized: bool,
+    turns: u64,
+    next_request: u64,
+}
+
+impl Session {
+    fn emit(&mut self, value: Value) {
+        let mut out = self.stdout.lock();
+        let _ = writeln!(out, "{value}");
+        let _ = out.flush();
+    }
+
+    fn control_success(&mut self, request_id: &str, response: Value) {
+        self.emit(json!({
+            "type": "control_response",
+            "response": {"subtype": "success", "request_id": request_id, "response": response}
+        }));
+    }
+
+    fn system_init(&mut self) {
+        if !self.initialized {
+            self.initialized = true;
+            self.emit(json!({
+                "type": "system",
+                "subtype": "init",
+                "session_id": SESSION_ID,
+                "model": MODEL,
+                "tools": ["Bash", "Read"]
+            }));
+        }
+    }
+
+    fn assistant(&mut self, text: &str) {
+        self.emit(json!({
+            "type": "assistant",
+            "message": {"model": MODEL, "content": [{"type": "text", "text": text}]},
+            "session_id": SESSION_ID
+        }));
+    }
+
+    fn result(&mut self, subtype: &str, text: &str) {
+        self.turns += 1;
+        self.emit(json!({
+            "type": "result",
+            "subtype": subtype,
+            "duration_ms": 5,
+            "duration_api_ms": 3,
+            "is_error": subtype != "success",
+            "num_turns": self.turns,
+            "session_id": SESSION_ID,
+            "total_cost_usd": 0.0001,
+            "usage": {"input_tokens": 10, "output_tokens": 5},
+            "result": text
+        }));
+    }
+
+    fn reply_text(prompt: &str) -> String {
+        std::env::var("FAKE_CLAUDE_REPLY").unwrap_or_else(|_| format!("fake reply to: {prompt}"))
+    }
+
+    /// Answer a control request; returns whether it was an interrupt.
+    fn handle_control(&mut self, message: &Value) -> bool {
+        let request_id = message["request_id"]
+            .as_str()
+            .unwrap_or_default()
+            .to_string();
+        match message["request"]["subtype"].as_str() {
+            Some("initialize") => self.control_success(
+                &request_id,
+                json!({"commands": [], "output_style": "default"}),
+            ),
+            Some("interrupt") => {
+                self.control_success(&request_id, json!({}));
+                return true;
+            }
+            _ => self.control_success(&request_id, json!({})),
+        }
+        false
+    }
+
+    /// Read stdin until `accept` returns a value, answering control requests
+    /// meanwhile. `None` means stdin closed.
+    fn wait_for<T>(
+        &mut self,
+        lines: &mut impl Iterator<Item = Value>,
+        mut accept: impl FnMut(&mut Self, &Value) -> Option<T>,
+    ) -> Option<T> {
+        for message in lines.by_ref() {
+            if let Some(found) = accept(self, &message) {
+                return Some(found);
+            }
+        }
+        None
+    }
+
+    fn answer(&mut self, prompt: &str, lines: &mut impl Iterator<Item = Value>) {
+        self.system_init();
+        if prompt.starts_with("slow") {
+            self.assistant("working on it...");
+            let interrupted = self.wait_for(lines, |session, message| {
+                (message["type"] == "control_request" && session.handle_control(message))
+                    .then_some(())
+            });
+            if interrupted.is_some() {
+                self.result("error_during_execution", "interrupted");
+            }
+            return;
+        }
+        if prompt.starts_with("tool") {
+            self.next_request += 1;
+            let request_id = format!("fake-req-{}", self.next_request);
+            self.emit(json!({
+                "type": "control_request",
+                "request_id": request_id,
+                "request": {
+                    "subtype": "can_use_tool",
+                    "tool_name": "Bash",
+                    "input": {"command": "echo hi"},
+                    "permission_suggestions": []
+                }
+            }));
+            let behavior =
+                self.wait_for(lines, |session, message| match message["type"].as_str() {
+                    Some("control_response")
+                        if message["response"]["request_id"] == request_id.as_str() =>
+                    {
+                        Some(
+                            message["response"]["response"]["behavior"]
+                                .as_str()
+                                .unwrap_or("deny")
+                                .to_string(),
+                        )
+                    }
+                    Some("control_request") => {
+                        session.handle_control(message);
+                        None
+                    }
+                    _ => None,
+                });
+            let Some(behavior) = behavior else { return };
+            self.assistant(&format!("permission {behavior}"));
+        }
+        let text = Self::reply_text(prompt);
+        self.assistant(&text);
+        self.result("success", &text);
+    }
+}
+
+fn user_text(message: &Value) -> String {
+    match &message["message"]["content"] {
+        Value::String(text) => text.clone(),
+        Value::Array(blocks) => blocks
+            .iter()
+            .filter_map(|block| block["text"].as_str())
+            .collect(),
+        _ => String::new(),
+    }
+}
+
+fn main() {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-v" || arg == "--version") {
+        println!("{VERSION}");
+        return;
+    }
+    if let Some(code) = std::env::var("FAKE_CLAUDE_EXIT_CODE")
+        .ok()
+        .and_then(|code| code.parse::<i32>().ok())
+    {
+        eprintln!("fake-claude: failing on request");
+        std::process::exit(code);
+    }
+    if let Ok(banner) = std::env::var("FAKE_CLAUDE_BANNER") {
+        println!("{banner}");
+    }
+
+    let mut session = Session {
+        stdout: io::stdout(),
+        initialized: false,
+        turns: 0,
+        next_request: 0,
+    };
+
+    if let Some(position) = args.iter().position(|arg| arg == "--print") {
+        let prompt = args
+            .iter()
+            .skip(position + 1)
+            .skip_while(|arg| *arg == "--")
+            .cloned()
+            .collect::<Vec<_>>()
+            .join(" ");
+        session.answer(&prompt, &mut std::iter::empty());
+        return;
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin
+        .lock()
+        .lines()
+        .map_while(Result::ok)
+        .filter_map(|line| serde_json::from_str::<Value>(line.trim()).ok());
+    while let Some(message) = lines.next() {
+        match message["type"].as_str() {
+            Some("control_request") => {
+                session.handle_control(&message);
+            }
+            Some("user") => {
+                let prompt = user_text(&message);
+                session.answer(&prompt, &mut lines);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,217 @@
+//! Client-side soft cost limit.
+//!
+//! `max_budget_usd` is enforced by the CLI, which stops only once the limit
+//! has been spent. A [`BudgetGuard`] estimates what the running query costs
+//! from the token usage in partial-message stream events, adds the cost of
+//! the queries already finished, and reports a [`BudgetWarning`] as soon as
+//! that projection crosses a soft limit, so the query can be interrupted
+//! early. [`ClaudeSdkClient::receive_response_with_budget`] does this for a
+//! connected client.
+//!
+//! Estimates need `include_partial_messages`; without stream events only the
+//! cost reported by each result message is counted.
+//!
+//! [`ClaudeSdkClient::receive_response_with_budget`]: crate::client::ClaudeSdkClient::receive_response_with_budget
+
+use serde_json::Value;
+
+use crate::message::Message;
+
+/// Prices in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TokenPricing {
+    pub input_per_mtok: f64,
+    pub output_per_mtok: f64,
+    pub cache_write_per_mtok: f64,
+    pub cache_read_per_mtok: f64,
+}
+
+impl TokenPricing {
+    /// Claude Sonnet list prices; used when no pricing is configured.
+    pub const SONNET: Self = Self {
+        input_per_mtok: 3.0,
+        output_per_mtok: 15.0,
+        cache_write_per_mtok: 3.75,
+        cache_read_per_mtok: 0.30,
+    };
+
+    /// Claude Opus list prices.
+    pub const OPUS: Self = Self {
+        input_per_mtok: 15.0,
+        output_per_mtok: 75.0,
+        cache_write_per_mtok: 18.75,
+        cache_read_per_mtok: 1.50,
+    };
+
+    /// Claude Haiku list prices.
+    pub const HAIKU: Self = Self {
+        input_per_mtok: 0.80,
+        output_per_mtok: 4.0,
+        cache_write_per_mtok: 1.0,
+        cache_read_per_mtok: 0.08,
+    };
+
+    /// Cost of `usage`, an API usage object with `input_tokens`,
+    /// `output_tokens` and the cache token counts.
+    pub fn cost_usd(&self, usage: &Value) -> f64 {
+        let tokens = |key: &str| usage.get(key).and_then(Value::as_u64).unwrap_or(0) as f64;
+        (tokens("input_tokens") * self.input_per_mtok
+            + tokens("output_tokens") * self.output_per_mtok
+            + tokens("cache_creation_input_tokens") * self.cache_write_per_mtok
+            + tokens("cache_read_input_tokens") * self.cache_read_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+impl Default for TokenPricing {
+    fn default() -> Self {
+        Self::SONNET
+    }
+}
+
+/// Raised once per query when the projected cost reaches the soft limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetWarning {
+    pub soft_limit_usd: f64,
+    /// Cost of finished queries plus the estimate for the running one.
+    pub projected_cost_usd: f64,
+    /// Cost reported by finished queries.
+    pub spent_usd: f64,
+    /// Whether the running query was interrupted because of this warning.
+    pub interrupted: bool,
+}
+
+/// Item of [`ClaudeSdkClient::receive_response_with_budget`](crate::client::ClaudeSdkClient::receive_response_with_budget).
+#[derive(Debug, Clone, PartialEq)]
+pub enum BudgetedMessage {
+    Message(Message),
+    /// Follows the message that pushed the projection over the limit.
+    Warning(BudgetWarning),
+}
+
+/// Tracks spending against a soft limit.
+#[derive(Debug, Clone, PartialEq)]
+pub struct BudgetGuard {
+    soft_limit_usd: f64,
+    pricing: TokenPricing,
+    interrupt: bool,
+    spent_usd: f64,
+    /// Estimated cost of API calls finished in the running query.
+    settled_usd: f64,
+    /// Usage of the API call being streamed, merged across its events.
+    current_usage: Value,
+    warned: bool,
+}
+
+impl BudgetGuard {
+    /// Guard warning, and interrupting, once `soft_limit_usd` is projected.
+    pub fn new(soft_limit_usd: f64) -> Self {
+        Self {
+            soft_limit_usd,
+            pricing: TokenPricing::default(),
+            interrupt: true,
+            spent_usd: 0.0,
+            settled_usd: 0.0,
+            current_usage: Value::Null,
+            warned: false,
+        }
+    }
+
+    /// Prices used for estimates; match them to the configured model.
+    pub fn with_pricing(mut self, pricing: TokenPricing) -> Self {
+        self.pricing = pricing;
+        self
+    }
+
+    /// Only warn instead of interrupting the query.
+    pub fn warn_only(mut self) -> Self {
+        self.interrupt = false;
+        self
+    }
+
+    /// Count `spent_usd` as already spent, e.g. by earlier sessions.
+    pub fn with_spent(mut self, spent_usd: f64) -> Self {
+        self.spent_usd = spent_usd;
+        self
+    }
+
+    pub fn soft_limit_usd(&self) -> f64 {
+        self.soft_limit_usd
+    }
+
+    /// Whether warnings should interrupt the running query.
+    pub fn interrupts(&self) -> bool {
+        self.interrupt
+    }
+
+    /// Cost reported by finished queries.
+    pub fn spent_usd(&self) -> f64 {
+        self.spent_usd
+    }
+
+    /// Spent cost plus the estimate for the running query.
+    pub fn projected_cost_usd(&self) -> f64 {
+        self.spent_usd + self.running_estimate()
+    }
+
+    /// Update the projection with `message`; returns a warning the first
+    /// time a query's projection reaches the soft limit.
+    pub fn observe(&mut self, message: &Message) -> Option<BudgetWarning> {
+        match message {
+            Message::StreamEvent(event) => self.observe_event(&event.event),
+            Message::Result(result) => {
+                // The reported cost replaces the estimate.
+                let estimate = self.running_estimate();
+                self.spent_usd += result.total_cost_usd.unwrap_or(estimate);
+                self.settled_usd = 0.0;
+                self.current_usage = Value::Null;
+                let warning = self.check();
+                self.warned = false;
+                return warning;
+            }
+            _ => {}
+        }
+        self.check()
+    }
+
+    fn running_estimate(&self) -> f64 {
+        self.settled_usd + self.pricing.cost_usd(&self.current_usage)
+    }
+
+    fn observe_event(&mut self, event: &Value) {
+        match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => {
+                self.settled_usd += self.pricing.cost_usd(&self.current_usage);
+                self.current_usage = event
+                    .pointer("/message/usage")
+                    .cloned()
+                    .unwrap_or(Value::Null);
+            }
+            // Counts in `message_delta` are cumulative for the API call.
+            Some("message_delta") => {
+                let Some(usage) = event.get("usage").and_then(Value::as_object) else {
+                    return;
+                };
+                match self.current_usage.as_object_mut() {
+                    Some(current) => current.extend(usage.clone()),
+                    None => self.current_usage = Value::Object(usage.clone()),
+                }
+            }
+            _ => {}
+        }
+    }
+
+    fn check(&mut self) -> Option<BudgetWarning> {
+        let projected = self.projected_cost_usd();
+        if self.warned || projected < self.soft_limit_usd {
+            return None;
+        }
+        self.warned = true;
+        Some(BudgetWarning {
+            soft_limit_usd: self.soft_limit_usd,
+            projected_cost_usd: projected,
+            spent_usd: self.spent_usd,
+            interrupted: false,
+        })
+    }
+}
@@ -0,0 +1,210 @@
+//! Cap on CLI output buffered for consumers that fall behind.
+//!
+//! `max_buffer_size` bounds a single JSON message; a [`BufferLimit`] bounds
+//! the bytes of every message waiting in the subprocess transport's stdout
+//! channel and the query's read-ahead and message channels together. Set it as
+//! [`ClaudeAgentOptions::buffer_limit`]; clones share one budget, so a
+//! service can cap all of its sessions at once.
+//!
+//! When a message does not fit, the [`SlowConsumerPolicy`] decides:
+//! wait for the consumer (the CLI then blocks on its stdout pipe), drop
+//! partial `stream_event` messages first, or fail with
+//! [`SdkError::BufferLimitExceeded`]. A message larger than the whole limit
+//! is still admitted when nothing else is buffered, and while a session waits
+//! for a control response every message of that session is admitted, so the
+//! response is not stuck behind output the consumer has yet to read. Other
+//! sessions sharing the limit stay capped meanwhile.
+//!
+//! [`ClaudeAgentOptions::buffer_limit`]: crate::config::ClaudeAgentOptions::buffer_limit
+
+use std::fmt;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use serde_json::Value;
+use tokio::sync::Notify;
+
+use crate::error::SdkError;
+
+/// What to do when buffered output would exceed a [`BufferLimit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SlowConsumerPolicy {
+    /// Stop reading from the CLI until the consumer catches up.
+    #[default]
+    Backpressure,
+    /// Discard partial-message `stream_event`s; wait for anything else.
+    DropStreamEvents,
+    /// End the session with [`SdkError::BufferLimitExceeded`].
+    Error,
+}
+
+struct State {
+    buffered: AtomicUsize,
+    released: Notify,
+}
+
+/// Shared cap on buffered CLI output, in bytes of JSON.
+#[derive(Clone)]
+pub struct BufferLimit {
+    max_bytes: usize,
+    policy: SlowConsumerPolicy,
+    state: Arc<State>,
+    /// Outstanding [`Overdraft`]s of the session this handle belongs to.
+    overdrafts: Arc<AtomicUsize>,
+}
+
+impl fmt::Debug for BufferLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferLimit")
+            .field("max_bytes", &self.max_bytes)
+            .field("policy", &self.policy)
+            .field("buffered", &self.buffered())
+            .finish()
+    }
+}
+
+impl BufferLimit {
+    /// Limit applying backpressure above `max_bytes`.
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            policy: SlowConsumerPolicy::default(),
+            state: Arc::new(State {
+                buffered: AtomicUsize::new(0),
+                released: Notify::new(),
+            }),
+            overdrafts: Arc::new(AtomicUsize::new(0)),
+        }
+    }
+
+    pub fn with_policy(mut self, policy: SlowConsumerPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    pub fn policy(&self) -> SlowConsumerPolicy {
+        self.policy
+    }
+
+    /// Bytes currently buffered under this limit.
+    pub fn buffered(&self) -> usize {
+        self.state.buffered.load(Ordering::SeqCst)
+    }
+
+    /// Reserve room for `message`. `Ok(None)` means the policy dropped it.
+    pub(crate) async fn admit(&self, message: &Value) -> Result<Option<BufferCharge>, SdkError> {
+        let size = value_size(message);
+        loop {
+            let released = self.state.released.notified();
+            tokio::pin!(released);
+            released.as_mut().enable();
+
+            if self.try_reserve(size) {
+                return Ok(Some(BufferCharge {
+                    limit: self.clone(),
+                    size,
+                }));
+            }
+            match self.policy {
+                SlowConsumerPolicy::DropStreamEvents
+                    if message.get("type").and_then(Value::as_str) == Some("stream_event") =>
+                {
+                    return Ok(None);
+                }
+                SlowConsumerPolicy::Error => {
+                    return Err(SdkError::BufferLimitExceeded {
+                        buffered: self.buffered() + size,
+                        limit: self.max_bytes,
+                    });
+                }
+                _ => released.await,
+            }
+        }
+    }
+
+    /// Handle sharing this budget whose overdrafts only lift the limit for
+    /// itself and its clones; give one to each session's transport and query.
+    pub(crate) fn for_session(&self) -> Self {
+        Self {
+            overdrafts: Arc::new(AtomicUsize::new(0)),
+            ..self.clone()
+        }
+    }
+
+    /// Admit every message of this session, over the limit if need be,
+    /// until the guard is dropped. Held while a control response is
+    /// expected, since the response may be behind messages the consumer has
+    /// not taken yet.
+    pub(crate) fn overdraft(&self) -> Overdraft {
+        self.overdrafts.fetch_add(1, Ordering::SeqCst);
+        self.state.released.notify_waiters();
+        Overdraft {
+            limit: self.clone(),
+        }
+    }
+
+    fn try_reserve(&self, size: usize) -> bool {
+        let overdrawn = self.overdrafts.load(Ordering::SeqCst) > 0;
+        self.state
+            .buffered
+            .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |buffered| {
+                (overdrawn || buffered == 0 || buffered + size <= self.max_bytes)
+                    .then_some(buffered + size)
+            })
+            .is_ok()
+    }
+}
+
+/// Bytes held by one buffered message, released on drop.
+pub struct BufferCharge {
+    limit: BufferLimit,
+    size: usize,
+}
+
+impl fmt::Debug for BufferCharge {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("BufferCharge")
+            .field("size", &self.size)
+            .finish()
+    }
+}
+
+impl Drop for BufferCharge {
+    fn drop(&mut self) {
+        self.limit
+            .state
+            .buffered
+            .fetch_sub(self.size, Ordering::SeqCst);
+        self.limit.state.released.notify_waiters();
+    }
+}
+
+/// Lifts a [`BufferLimit`] while held; see [`BufferLimit::overdraft`].
+pub(crate) struct Overdraft {
+    limit: BufferLimit,
+}
+
+impl Drop for Overdraft {
+    fn drop(&mut self) {
+        self.limit.overdrafts.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Approximate serialized size of `value`.
+fn value_size(value: &Value) -> usize {
+    match value {
+        Value::Null | Value::Bool(_) | Value::Number(_) => 8,
+        Value::String(text) => text.len() + 2,
+        Value::Array(items) => 2 + items.iter().map(|item| value_size(item) + 1).sum::<usize>(),
+        Value::Object(map) => {
+            2 + map
+                .iter()
+                .map(|(key, item)| key.len() + 4 + value_size(item))
+                .sum::<usize>()
+        }
+    }
+}
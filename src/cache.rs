@@ -0,0 +1,319 @@
+//! Content-addressed cache for one-shot query results.
+//!
+//! [`QueryCache::query`] behaves like [`query`](crate::query::query) for a
+//! text prompt, but first looks up the SHA-256 of the prompt and the options
+//! that shape the answer. A hit replays the stored messages without starting
+//! the CLI; a miss runs the query and stores its messages once it finishes
+//! with a successful result. Error results and interrupted streams are never
+//! stored.
+//!
+//! The key covers every serialisable option except `cli_path`,
+//! `max_buffer_size` and `max_budget_usd`. Callbacks, hooks and in-process
+//! MCP servers cannot be hashed, so queries that differ only in those share
+//! an entry.
+//!
+//! Entries live in a [`CacheBackend`]: [`MemoryCache`] for a single process,
+//! [`DirectoryCache`] for one JSON file per entry that survives restarts.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, Stream, StreamExt};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::client::DynTransport;
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::internal::trace::sdk_debug;
+use crate::message::{
+    AssistantMessage, Message, ResultMessage, StreamEvent, SystemMessage, UserMessage,
+};
+
+/// Options left out of cache keys: they do not change the answer.
+const IGNORED_OPTIONS: &[&str] = &["cli_path", "max_buffer_size", "max_budget_usd"];
+
+/// Storage for cached responses, keyed by [`QueryCache::key`].
+#[async_trait]
+pub trait CacheBackend: Send + Sync {
+    async fn get(&self, key: &str) -> Result<Option<Vec<Message>>, SdkError>;
+    async fn put(&self, key: &str, messages: &[Message]) -> Result<(), SdkError>;
+    async fn remove(&self, key: &str) -> Result<(), SdkError>;
+}
+
+/// Process-local backend.
+#[derive(Debug, Default)]
+pub struct MemoryCache {
+    entries: Mutex<HashMap<String, Vec<Message>>>,
+}
+
+impl MemoryCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[async_trait]
+impl CacheBackend for MemoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<Message>>, SdkError> {
+        Ok(self.entries.lock().unwrap().get(key).cloned())
+    }
+
+    async fn put(&self, key: &str, messages: &[Message]) -> Result<(), SdkError> {
+        self.entries
+            .lock()
+            .unwrap()
+            .insert(key.to_string(), messages.to_vec());
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), SdkError> {
+        self.entries.lock().unwrap().remove(key);
+        Ok(())
+    }
+}
+
+/// Backend storing each entry as `<key>.json` in a directory, created on
+/// first write. Files are written to a temporary file and renamed, so
+/// concurrent pipelines never read half an entry.
+#[derive(Debug, Clone)]
+pub struct DirectoryCache {
+    dir: PathBuf,
+}
+
+impl DirectoryCache {
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{key}.json"))
+    }
+}
+
+#[async_trait]
+impl CacheBackend for DirectoryCache {
+    async fn get(&self, key: &str) -> Result<Option<Vec<Message>>, SdkError> {
+        let contents = match std::fs::read_to_string(self.path(key)) {
+            Ok(contents) => contents,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(err) => return Err(err.into()),
+        };
+        let stored: Vec<StoredMessage> = serde_json::from_str(&contents)?;
+        Ok(Some(stored.into_iter().map(Message::from).collect()))
+    }
+
+    async fn put(&self, key: &str, messages: &[Message]) -> Result<(), SdkError> {
+        std::fs::create_dir_all(&self.dir)?;
+        let stored: Vec<StoredMessage> = messages.iter().cloned().map(Into::into).collect();
+        #[cfg(feature = "tempfile")]
+        {
+            let mut file = tempfile::NamedTempFile::new_in(&self.dir)?;
+            serde_json::to_writer(&mut file, &stored)?;
+            file.persist(self.path(key)).map_err(|err| err.error)?;
+        }
+        #[cfg(not(feature = "tempfile"))]
+        {
+            let staging = self
+                .dir
+                .join(format!(".{key}.{}.tmp", crate::internal::unique_id()));
+            std::fs::write(&staging, serde_json::to_vec(&stored)?)?;
+            std::fs::rename(&staging, self.path(key))?;
+        }
+        Ok(())
+    }
+
+    async fn remove(&self, key: &str) -> Result<(), SdkError> {
+        match std::fs::remove_file(self.path(key)) {
+            Err(err) if err.kind() != std::io::ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// [`Message`] in the form stored by [`DirectoryCache`].
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", content = "message", rename_all = "snake_case")]
+enum StoredMessage {
+    User(UserMessage),
+    Assistant(AssistantMessage),
+    System(SystemMessage),
+    Result(ResultMessage),
+    StreamEvent(StreamEvent),
+}
+
+impl From<Message> for StoredMessage {
+    fn from(message: Message) -> Self {
+        match message {
+            Message::User(message) => Self::User(message),
+            Message::Assistant(message) => Self::Assistant(message),
+            Message::System(message) => Self::System(message),
+            Message::Result(message) => Self::Result(message),
+            Message::StreamEvent(message) => Self::StreamEvent(message),
+        }
+    }
+}
+
+impl From<StoredMessage> for Message {
+    fn from(message: StoredMessage) -> Self {
+        match message {
+            StoredMessage::User(message) => Self::User(message),
+            StoredMessage::Assistant(message) => Self::Assistant(message),
+            StoredMessage::System(message) => Self::System(message),
+            StoredMessage::Result(message) => Self::Result(message),
+            StoredMessage::StreamEvent(message) => Self::StreamEvent(message),
+        }
+    }
+}
+
+/// Caches one-shot queries in a [`CacheBackend`].
+#[derive(Clone)]
+pub struct QueryCache {
+    backend: Arc<dyn CacheBackend>,
+}
+
+impl std::fmt::Debug for QueryCache {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryCache").finish_non_exhaustive()
+    }
+}
+
+impl QueryCache {
+    pub fn new(backend: impl CacheBackend + 'static) -> Self {
+        Self {
+            backend: Arc::new(backend),
+        }
+    }
+
+    pub fn in_memory() -> Self {
+        Self::new(MemoryCache::new())
+    }
+
+    pub fn directory(dir: impl Into<PathBuf>) -> Self {
+        Self::new(DirectoryCache::new(dir))
+    }
+
+    /// Hex SHA-256 of `prompt` and the relevant parts of `options`.
+    pub fn key(prompt: &str, options: &ClaudeAgentOptions) -> Result<String, SdkError> {
+        let mut options = serde_json::to_value(options)?;
+        if let Value::Object(fields) = &mut options {
+            for ignored in IGNORED_OPTIONS {
+                fields.remove(*ignored);
+            }
+        }
+        let canonical = serde_json::to_vec(&sorted(serde_json::json!({
+            "prompt": prompt,
+            "options": options,
+        })))?;
+
+        let mut key = String::with_capacity(64);
+        for byte in Sha256::digest(&canonical) {
+            let _ = write!(key, "{byte:02x}");
+        }
+        Ok(key)
+    }
+
+    /// Whether a response for `prompt` is cached.
+    pub async fn contains(
+        &self,
+        prompt: &str,
+        options: Option<&ClaudeAgentOptions>,
+    ) -> Result<bool, SdkError> {
+        let key = Self::key(prompt, options.unwrap_or(&ClaudeAgentOptions::default()))?;
+        Ok(self.backend.get(&key).await?.is_some())
+    }
+
+    /// Drop the cached response for `prompt`, if any.
+    pub async fn invalidate(
+        &self,
+        prompt: &str,
+        options: Option<&ClaudeAgentOptions>,
+    ) -> Result<(), SdkError> {
+        let key = Self::key(prompt, options.unwrap_or(&ClaudeAgentOptions::default()))?;
+        self.backend.remove(&key).await
+    }
+
+    /// [`query`](crate::query::query) through the cache.
+    pub async fn query(
+        &self,
+        prompt: impl Into<String>,
+        options: Option<ClaudeAgentOptions>,
+        transport: Option<DynTransport>,
+    ) -> Result<BoxStream<'static, Result<Message, SdkError>>, SdkError> {
+        let prompt = prompt.into();
+        let options = options.unwrap_or_default();
+        let key = Self::key(&prompt, &options)?;
+
+        if let Some(messages) = self.backend.get(&key).await? {
+            sdk_debug!("query cache hit for {key}");
+            return Ok(stream::iter(messages.into_iter().map(Ok)).boxed());
+        }
+
+        let live = crate::query::query(prompt, Some(options), transport).await?;
+        Ok(record(live, self.backend.clone(), key).boxed())
+    }
+}
+
+/// `value` with object keys in sorted order at every level, so options
+/// holding `HashMap`s hash the same whatever their iteration order.
+fn sorted(value: Value) -> Value {
+    match value {
+        Value::Object(fields) => {
+            let mut fields: Vec<_> = fields.into_iter().collect();
+            fields.sort_by(|(a, _), (b, _)| a.cmp(b));
+            Value::Object(
+                fields
+                    .into_iter()
+                    .map(|(key, value)| (key, sorted(value)))
+                    .collect(),
+            )
+        }
+        Value::Array(items) => Value::Array(items.into_iter().map(sorted).collect()),
+        other => other,
+    }
+}
+
+/// Passes `live` through, storing its messages once a successful result
+/// arrives.
+fn record<S>(
+    live: S,
+    backend: Arc<dyn CacheBackend>,
+    key: String,
+) -> impl Stream<Item = Result<Message, SdkError>>
+where
+    S: Stream<Item = Result<Message, SdkError>> + Send + 'static,
+{
+    stream::unfold(
+        (live.boxed(), Vec::new(), backend, key),
+        |(mut live, mut seen, backend, key)| async move {
+            let item = live.next().await?;
+            if let Ok(message) = &item {
+                seen.push(message.clone());
+                if let Message::Result(result) = message {
+                    if !result.is_error && result.subtype == "success" {
+                        if let Err(err) = backend.put(&key, &seen).await {
+                            return Some((Err(err), (live, seen, backend, key)));
+                        }
+                    }
+                }
+            }
+            Some((item, (live, seen, backend, key)))
+        },
+    )
+}
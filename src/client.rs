@@ -1,24 +1,48 @@
 //! High-level client API for interacting with the Claude Code CLI.
 
+use std::collections::{HashMap, VecDeque};
+use std::path::Path;
 use std::sync::Arc;
+use std::time::{Duration, SystemTime};
 
 use futures::stream::BoxStream;
-use futures::{stream, Stream, StreamExt};
+use futures::{stream, Stream, StreamExt, TryStreamExt};
 use serde_json::{json, Value};
+use tokio::io::AsyncWrite;
+use tokio::sync::broadcast::error::RecvError;
 use tokio::task::JoinHandle;
 
-use crate::config::ClaudeAgentOptions;
+use crate::budget::{BudgetGuard, BudgetWarning, BudgetedMessage};
+use crate::buffer_limit::BufferLimit;
+use crate::config::{server_info_names, ClaudeAgentOptions, TurnLimitAction};
+use crate::context_window::{ContextEvent, ContextWindowTracker, ContextWindowWarning};
+use crate::conversation::SessionMetadata;
+use crate::debug_bundle::{self, DebugBundle};
 use crate::error::{CliConnectionError, SdkError};
 use crate::internal::client::PromptInput;
-use crate::internal::query::Query;
-use crate::message::Message;
-use crate::permission::PermissionMode;
-use crate::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
-use crate::transport::Transport;
+use crate::internal::query::{Query, QueryActivity};
+use crate::internal::trace::{sdk_debug, sdk_warn};
+use crate::mcp::health::{self, McpHealthIssue};
+use crate::message::{Message, ModelFallbackReason, RawMessage, SystemMessage};
+use crate::middleware;
+use crate::models::Model;
+use crate::parallel::{self, ParallelTask, TaskOutcome};
+use crate::permission::{CanUseToolHandle, PermissionMode};
+use crate::progress::{ProgressEvent, ProgressTracker};
+use crate::sinks::{TextRendering, TextSink};
+use crate::subagent::{
+    self, SubagentCollector, SubagentResult, SubagentTask, DEFAULT_MAX_CONCURRENT_SUBAGENTS,
+};
+use crate::tool_events::{ToolEvent, ToolEventTracker};
+use crate::transport::stderr::StderrEvent;
+use crate::transport::{default_transport, ProcessInfo, PromptMode, Transport};
 
 /// Convenience alias for trait-object transports.
 pub type DynTransport = Arc<dyn Transport>;
 
+/// Default [`ClaudeAgentOptions::entrypoint`] for client sessions.
+const CLIENT_ENTRYPOINT: &str = "sdk-rs-client";
+
 /// Public client surface matching the Python SDK behaviour.
 pub struct ClaudeSdkClient {
     options: ClaudeAgentOptions,
@@ -28,6 +52,12 @@ pub struct ClaudeSdkClient {
     prompt_task: Option<JoinHandle<()>>,
     server_info: Option<Value>,
     connected: bool,
+    has_connected: bool,
+    session_metadata: HashMap<String, SessionMetadata>,
+    effective_options: Option<ClaudeAgentOptions>,
+    mcp_health_issues: Vec<McpHealthIssue>,
+    /// Whether the current session was connected with a `can_use_tool` callback.
+    permission_callback_connected: bool,
 }
 
 impl Default for ClaudeSdkClient {
@@ -39,7 +69,6 @@ impl Default for ClaudeSdkClient {
 impl ClaudeSdkClient {
     /// Create a new client with optional configuration and transport override.
     pub fn new(options: Option<ClaudeAgentOptions>, transport: Option<DynTransport>) -> Self {
-        std::env::set_var("CLAUDE_CODE_ENTRYPOINT", "sdk-rs-client");
         Self {
             options: options.unwrap_or_default(),
             custom_transport: transport,
@@ -48,10 +77,19 @@ impl ClaudeSdkClient {
             prompt_task: None,
             server_info: None,
             connected: false,
+            has_connected: false,
+            session_metadata: HashMap::new(),
+            effective_options: None,
+            mcp_health_issues: Vec::new(),
+            permission_callback_connected: false,
         }
     }
 
     /// Connect to Claude Code with an optional initial prompt stream.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "claude_sdk.connect", skip_all)
+    )]
     pub async fn connect(&mut self, prompt: Option<PromptInput>) -> Result<(), SdkError> {
         if self.connected {
             return Ok(());
@@ -62,18 +100,31 @@ impl ClaudeSdkClient {
         let is_streaming = prompt.is_streaming();
 
         Self::validate_permission_options(&mut self.options, is_streaming)?;
+        self.mcp_health_issues = health::preflight(&self.options).await?;
 
         let (prompt_mode, stream_source) = match prompt {
             PromptInput::Text(text) => (PromptMode::Text(text), None),
             PromptInput::Stream(stream) => (PromptMode::Streaming, Some(stream)),
         };
 
+        let session_permit = match &self.options.rate_limiter {
+            Some(limiter) => Some(limiter.acquire_session().await),
+            None => None,
+        };
+
+        let mut effective_options = self.options.clone();
+        effective_options.buffer_limit = self
+            .options
+            .buffer_limit
+            .as_ref()
+            .map(BufferLimit::for_session);
         let transport: DynTransport = if let Some(custom) = &self.custom_transport {
             Arc::clone(custom)
         } else {
-            let transport_options = self.options.clone();
-            let subprocess = SubprocessCliTransport::new(prompt_mode, transport_options)?;
-            Arc::new(subprocess)
+            effective_options
+                .entrypoint
+                .get_or_insert_with(|| CLIENT_ENTRYPOINT.to_string());
+            default_transport(prompt_mode, effective_options.clone())?
         };
 
         transport.connect().await?;
@@ -86,21 +137,68 @@ impl ClaudeSdkClient {
             self.options.sdk_servers.clone(),
         );
 
+        query
+            .set_frame_sink(self.options.control_frame_sink.clone())
+            .await;
+        query.set_metrics(self.options.metrics.clone()).await;
+        query.set_session_permit(session_permit).await;
+        query
+            .set_tool_result_limit(self.options.tool_result_limit)
+            .await;
+        query
+            .set_buffer_limit(effective_options.buffer_limit.clone())
+            .await;
+        query
+            .set_message_spill(self.options.message_spill.clone())
+            .await;
+        query
+            .set_mcp_fallback(self.options.mcp_fallback.clone())
+            .await;
+        query
+            .set_prompt_middleware(self.options.prompt_middleware.clone())
+            .await;
+        query
+            .set_redactor(Some(self.options.effective_redactor()))
+            .await;
+        query.set_clock(self.options.effective_clock()).await;
+        query
+            .record_permission_mode(self.options.permission_mode)
+            .await;
+        query.set_keep_input_open(self.options.keep_input_open);
+        if let Some(config) = self.options.control_watchdog {
+            query.set_control_watchdog(config).await;
+        }
         query.start().await?;
-        self.server_info = query.initialize().await?;
+        let server_info = query.initialize().await?;
+        if let Err(err) = self
+            .options
+            .check_system_prompt_preset(server_info.as_ref())
+        {
+            let _ = query.close().await;
+            return Err(err);
+        }
+        self.server_info = server_info;
 
         if let Some(stream) = stream_source {
             let query_clone = query.clone();
             self.prompt_task = Some(tokio::spawn(async move {
-                if let Err(_err) = query_clone.stream_input(stream).await {
-                    let _ = query_clone.close().await;
+                if let Err(err) = query_clone.stream_input(stream).await {
+                    query_clone.fail_input(err).await;
                 }
             }));
         }
 
         self.transport = Some(transport);
         self.query = Some(query);
+        self.effective_options = Some(effective_options);
+        self.permission_callback_connected = self.options.can_use_tool.is_some();
+        if self.has_connected {
+            if let Some(metrics) = &self.options.metrics {
+                metrics.process_restart();
+            }
+        }
         self.connected = true;
+        self.has_connected = true;
         Ok(())
     }
 
@@ -114,7 +212,47 @@ impl ClaudeSdkClient {
             .ok_or_else(|| CliConnectionError::new("Not connected"))?
             .clone();
 
-        Ok(Self::message_stream(query))
+        let limits = (self.options.max_turns, self.options.max_budget_usd);
+        Ok(Self::message_stream(
+            query,
+            limits,
+            self.options.hide_user_echoes,
+        ))
+    }
+
+    /// Receive every message as the CLI wrote it, with its parsed form.
+    ///
+    /// Draws from the same queue as [`ClaudeSdkClient::receive_messages`],
+    /// so use one or the other. Payloads that fail to parse are yielded with
+    /// `message: None` instead of ending the stream; user echoes are not
+    /// hidden and limit results are not turned into errors.
+    pub fn receive_raw(
+        &self,
+    ) -> Result<impl Stream<Item = Result<RawMessage, SdkError>>, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?
+            .clone();
+        Ok(stream::unfold(
+            (query, false),
+            |(query, finished)| async move {
+                if finished {
+                    return None;
+                }
+                match query.next_raw_message().await {
+                    Ok(Some(raw)) => Some((Ok(raw), (query, false))),
+                    Ok(None) => {
+                        let _ = query.close().await;
+                        None
+                    }
+                    Err(err) => {
+                        let _ = query.close().await;
+                        Some((Err(err), (query, true)))
+                    }
+                }
+            },
+        ))
     }
 
     /// Receive messages until the first [`ResultMessage`] inclusive.
@@ -126,46 +264,605 @@ impl ClaudeSdkClient {
             .as_ref()
             .ok_or_else(|| CliConnectionError::new("Not connected"))?
             .clone();
-        Ok(Self::response_stream(query))
+        let limits = (self.options.max_turns, self.options.max_budget_usd);
+        Ok(Self::response_stream(
+            query,
+            limits,
+            self.options.hide_user_echoes,
+        ))
+    }
+
+    /// [`ClaudeSdkClient::receive_response`] with a client-side cost limit.
+    ///
+    /// Every message is fed to `guard`; when the projected cost reaches its
+    /// soft limit the query is interrupted (unless the guard is
+    /// [`warn_only`](BudgetGuard::warn_only)) and a
+    /// [`BudgetedMessage::Warning`] follows the message that crossed it. The
+    /// guard keeps its totals, so reuse it across queries.
+    pub fn receive_response_with_budget<'a>(
+        &self,
+        guard: &'a mut BudgetGuard,
+    ) -> Result<impl Stream<Item = Result<BudgetedMessage, SdkError>> + 'a, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?
+            .clone();
+        let limits = (self.options.max_turns, self.options.max_budget_usd);
+        let messages =
+            Self::response_stream(query.clone(), limits, self.options.hide_user_echoes).boxed();
+        Ok(stream::unfold(
+            (messages, guard, None::<BudgetWarning>),
+            move |(mut messages, guard, pending)| {
+                let query = query.clone();
+                async move {
+                    if let Some(warning) = pending {
+                        return Some((
+                            Ok(BudgetedMessage::Warning(warning)),
+                            (messages, guard, None),
+                        ));
+                    }
+                    let message = match messages.next().await? {
+                        Ok(message) => message,
+                        Err(err) => return Some((Err(err), (messages, guard, None))),
+                    };
+                    let mut warning = guard.observe(&message);
+                    if let Some(warning) = warning.as_mut() {
+                        if guard.interrupts() && !matches!(message, Message::Result(_)) {
+                            warning.interrupted = query.interrupt().await.is_ok();
+                        }
+                    }
+                    Some((
+                        Ok(BudgetedMessage::Message(message)),
+                        (messages, guard, warning),
+                    ))
+                }
+            },
+        ))
+    }
+
+    /// Write the text of the current response into `writer` as it streams,
+    /// flushing after each piece, and return the response's messages.
+    ///
+    /// Enable `include_partial_messages` for text to arrive delta by delta;
+    /// otherwise it is written a message at a time.
+    pub async fn stream_response_to<W>(&self, writer: W) -> Result<Vec<Message>, SdkError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        self.stream_response_to_with(writer, TextRendering::Plain)
+            .await
+    }
+
+    /// [`ClaudeSdkClient::stream_response_to`] with a choice of rendering.
+    pub async fn stream_response_to_with<W>(
+        &self,
+        writer: W,
+        rendering: TextRendering,
+    ) -> Result<Vec<Message>, SdkError>
+    where
+        W: AsyncWrite + Unpin,
+    {
+        let messages = self.receive_response()?;
+        TextSink::new(writer)
+            .with_rendering(rendering)
+            .consume(messages)
+            .await
+    }
+
+    /// [`ClaudeSdkClient::receive_response`] with context window tracking.
+    ///
+    /// Every message is fed to `tracker`; a [`ContextEvent::Warning`] follows
+    /// the message that pushed usage over its threshold. When the tracker
+    /// [auto-compacts](ContextWindowTracker::with_auto_compact), the
+    /// conversation is compacted after the result: the compaction turn is
+    /// consumed here and the stream ends with [`ContextEvent::Compacted`].
+    pub fn receive_response_with_context<'a>(
+        &'a self,
+        tracker: &'a mut ContextWindowTracker,
+    ) -> Result<impl Stream<Item = Result<ContextEvent, SdkError>> + 'a, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?
+            .clone();
+        let limits = (self.options.max_turns, self.options.max_budget_usd);
+        let messages = Self::response_stream(query, limits, self.options.hide_user_echoes).boxed();
+        Ok(stream::unfold(
+            Some((messages, tracker, None::<ContextWindowWarning>, false)),
+            move |state| async move {
+                let (mut messages, tracker, pending, compact) = state?;
+                if let Some(warning) = pending {
+                    return Some((
+                        Ok(ContextEvent::Warning(warning)),
+                        Some((messages, tracker, None, compact)),
+                    ));
+                }
+                let message = match messages.next().await {
+                    Some(Ok(message)) => message,
+                    Some(Err(err)) => return Some((Err(err), None)),
+                    None if compact => {
+                        let compacted = self.compact_tracked(tracker).await;
+                        return Some((compacted.map(ContextEvent::Compacted), None));
+                    }
+                    None => return None,
+                };
+                let warning = tracker.observe(&message);
+                let compact = compact || warning.as_ref().is_some_and(|w| w.will_compact);
+                Some((
+                    Ok(ContextEvent::Message(message)),
+                    Some((messages, tracker, warning, compact)),
+                ))
+            },
+        ))
+    }
+
+    /// Compact, then read the compaction turn through its result.
+    async fn compact_tracked(
+        &self,
+        tracker: &mut ContextWindowTracker,
+    ) -> Result<SystemMessage, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let session_id = query
+            .last_settled_session()
+            .await
+            .unwrap_or_else(|| "default".to_string());
+        self.compact_observed(None, &session_id, |message| {
+            tracker.observe(message);
+        })
+        .await
+    }
+
+    /// Stream of parsed CLI stderr lines written from now on.
+    ///
+    /// Pass `debug-to-stderr` in [`ClaudeAgentOptions::extra_args`] for the
+    /// CLI to report MCP server and compaction progress; without it or a
+    /// [`ClaudeAgentOptions::stderr`] callback the CLI writes to the host's
+    /// stderr and this fails. The stream ends once the client disconnects;
+    /// events are dropped if the consumer falls behind.
+    pub fn stderr_events(&self) -> Result<impl Stream<Item = StderrEvent>, SdkError> {
+        let transport = self
+            .transport
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let receiver = transport.subscribe_stderr().ok_or_else(|| {
+            SdkError::InvalidConfig("transport does not expose stderr".to_string())
+        })?;
+        Ok(stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(event) => return Some((event, receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }))
+    }
+
+    /// Pid, start time and memory use of the CLI process, so supervisors
+    /// can attach debuggers, enforce limits or correlate OS metrics with
+    /// this session. `None` when disconnected or when the transport is not
+    /// a local process.
+    pub async fn process_info(&self) -> Option<ProcessInfo> {
+        self.transport.as_ref()?.process_info().await
+    }
+
+    /// Stream of [`SdkError::CallbackPanicked`] for every permission
+    /// callback, hook or SDK MCP tool that panics from now on.
+    ///
+    /// The panic is caught and the CLI receives an error response, so the
+    /// session keeps running. The stream ends on disconnect.
+    pub async fn callback_panics(&self) -> Result<impl Stream<Item = SdkError>, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let receiver = query.subscribe_callback_panics().await?;
+        Ok(stream::unfold(receiver, |mut receiver| async move {
+            loop {
+                match receiver.recv().await {
+                    Ok(panic) => return Some((SdkError::CallbackPanicked(panic), receiver)),
+                    Err(RecvError::Lagged(_)) => continue,
+                    Err(RecvError::Closed) => return None,
+                }
+            }
+        }))
+    }
+
+    /// Stream of [`ProgressEvent`]s for messages read from now on.
+    ///
+    /// Running tools are reported again every `tick` as
+    /// [`ProgressEvent::ToolRunning`]. Messages must still be consumed from
+    /// [`ClaudeSdkClient::receive_messages`]; the stream ends on disconnect.
+    pub async fn progress_events(
+        &self,
+        tick: Duration,
+    ) -> Result<impl Stream<Item = ProgressEvent>, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let receiver = query.subscribe_messages().await?;
+        let mut interval = tokio::time::interval(tick.max(Duration::from_millis(1)));
+        interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let state = (receiver, ProgressTracker::new(), interval, VecDeque::new());
+        Ok(stream::unfold(
+            state,
+            |(mut receiver, mut tracker, mut interval, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((event, (receiver, tracker, interval, pending)));
+                    }
+                    tokio::select! {
+                        message = receiver.recv() => match message {
+                            Ok(message) => pending.extend(tracker.observe(&message)),
+                            Err(RecvError::Lagged(_)) => continue,
+                            Err(RecvError::Closed) => return None,
+                        },
+                        _ = interval.tick() => pending.extend(tracker.tick()),
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Stream of [`ToolEvent`]s for messages read from now on.
+    ///
+    /// Like [`ClaudeSdkClient::progress_events`], this is a side channel:
+    /// messages must still be consumed from
+    /// [`ClaudeSdkClient::receive_messages`]; the stream ends on disconnect.
+    pub async fn tool_events(&self) -> Result<impl Stream<Item = ToolEvent>, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let receiver = query.subscribe_messages().await?;
+        let state = (receiver, ToolEventTracker::new(), VecDeque::new());
+        Ok(stream::unfold(
+            state,
+            |(mut receiver, mut tracker, mut pending)| async move {
+                loop {
+                    if let Some(event) = pending.pop_front() {
+                        return Some((event, (receiver, tracker, pending)));
+                    }
+                    match receiver.recv().await {
+                        Ok(message) => pending.extend(tracker.observe(&message)),
+                        Err(RecvError::Lagged(_)) => continue,
+                        Err(RecvError::Closed) => return None,
+                    }
+                }
+            },
+        ))
+    }
+
+    /// Write a debug bundle for bug reports into the directory `path`.
+    ///
+    /// Works before connecting and after failures; sections with nothing to
+    /// report are left empty. See [`debug_bundle`] for the layout.
+    pub async fn capture_debug_bundle(&self, path: impl AsRef<Path>) -> Result<(), SdkError> {
+        let mut bundle = DebugBundle {
+            options: debug_bundle::redact_options(&self.options)?,
+            diagnostics: None,
+            session_id: None,
+            transcript: Vec::new(),
+            control_frames: Vec::new(),
+            stderr: Vec::new(),
+            captured_at: self.options.effective_clock().wall_time(),
+        };
+        if let Some(transport) = &self.transport {
+            bundle.diagnostics = transport.diagnostics().await;
+            bundle.stderr = transport.recent_stderr().await;
+        }
+        if let Some(query) = &self.query {
+            bundle.session_id = query.activity().await.session_id;
+            bundle.transcript = query.transcript().await;
+            let redactor = self.options.effective_redactor();
+            bundle
+                .transcript
+                .iter_mut()
+                .for_each(|message| redactor.redact_value(message));
+            bundle.control_frames = query.recent_frames();
+        }
+        bundle.write_to(path.as_ref())
     }
 
     /// Send a new request in streaming mode.
+    ///
+    /// While an earlier prompt of `session_id` has no result yet,
+    /// [`ClaudeAgentOptions::in_flight_policy`] decides whether this one is
+    /// sent, rejected or held back. A prompt stream counts as one prompt.
+    ///
+    /// Once `session_id` has sent
+    /// [`ClaudeAgentOptions::session_turn_limit`] prompts, this one is
+    /// refused with [`SdkError::MaxTurns`] or preceded by a compaction, which
+    /// waits until every earlier response has been read.
     pub async fn query<Q>(&self, prompt: Q, session_id: &str) -> Result<(), SdkError>
     where
         Q: Into<ClientPrompt>,
     {
         let prompt = prompt.into();
-        let transport = self
-            .transport
+        let query = self
+            .query
             .as_ref()
             .ok_or_else(|| CliConnectionError::new("Not connected"))?;
-        if self.query.is_none() {
-            return Err(CliConnectionError::new("Not connected").into());
+        if let Some(limit) = self.options.session_turn_limit {
+            let turns = query.session_turns(session_id).await;
+            if turns >= limit.max_turns {
+                match limit.on_limit {
+                    TurnLimitAction::Refuse => {
+                        return Err(SdkError::MaxTurns {
+                            turns: i64::from(turns),
+                            limit: Some(limit.max_turns),
+                            result: None,
+                        })
+                    }
+                    TurnLimitAction::Compact => self.compact_session(query, session_id).await?,
+                }
+            }
+        }
+        query
+            .begin_prompt(session_id, self.options.in_flight_policy)
+            .await?;
+        if let Some(limiter) = &self.options.rate_limiter {
+            limiter
+                .acquire_query(self.options.effective_clock().as_ref())
+                .await;
         }
+        if let Err(err) = self.write_prompt(prompt, session_id).await {
+            query.abandon_prompt(session_id).await;
+            return Err(err);
+        }
+
+        query.mark_prompt_sent().await;
+        query.count_session_turn(session_id).await;
+        Ok(())
+    }
 
+    /// Send `/compact` for `session_id` once every earlier response has been
+    /// read, and read the compaction turn through its result.
+    async fn compact_session(
+        &self,
+        query: &Query<dyn Transport>,
+        session_id: &str,
+    ) -> Result<(), SdkError> {
+        sdk_debug!("session {session_id} reached its turn limit; compacting");
+        self.compact_turn(query, "/compact".into(), session_id, |_| {})
+            .await
+            .map(|_| ())
+    }
+
+    async fn write_prompt(&self, prompt: ClientPrompt, session_id: &str) -> Result<(), SdkError> {
+        let transport = self
+            .transport
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
         match prompt {
             ClientPrompt::Text(text) => {
-                let message = json!({
+                let mut message = json!({
                     "type": "user",
                     "message": { "role": "user", "content": text },
                     "parent_tool_use_id": Value::Null,
                     "session_id": session_id,
                 });
-                transport.write(&message).await?
+                middleware::apply(&self.options.prompt_middleware, &mut message)?;
+                transport.write(&message).await?;
+                query.record_message_sent(&message).await;
             }
             ClientPrompt::Stream(mut stream) => {
                 while let Some(mut value) = stream.next().await {
                     if value.get("session_id").is_none() {
                         value["session_id"] = Value::String(session_id.to_string());
                     }
+                    middleware::apply(&self.options.prompt_middleware, &mut value)?;
+                    if let Some(limit) = &self.options.tool_result_limit {
+                        limit.apply_to_message(&mut value);
+                    }
                     transport.write(&value).await?;
+                    query.record_message_sent(&value).await;
                 }
             }
         }
-
         Ok(())
     }
 
+    /// Send a text prompt and collect the response, moving down
+    /// [`ClaudeAgentOptions::model_fallbacks`] while the result reports the
+    /// current model overloaded or unavailable.
+    ///
+    /// Each retry switches the session with [`ClaudeSdkClient::set_model`]
+    /// and re-sends the prompt; the session stays on the model that served
+    /// it. When the chain is exhausted the last failing response is returned.
+    pub async fn query_with_fallbacks(
+        &mut self,
+        prompt: impl Into<String>,
+        session_id: &str,
+    ) -> Result<FallbackResponse, SdkError> {
+        let prompt = prompt.into();
+        let mut fallbacks = self.options.model_fallbacks.clone().into_iter();
+        let mut failed = Vec::new();
+        loop {
+            self.query(prompt.as_str(), session_id).await?;
+            let messages: Vec<Message> = self.receive_response()?.try_collect().await?;
+            let reason = match messages.last() {
+                Some(Message::Result(result)) => result.fallback_reason(),
+                _ => None,
+            };
+            let next = reason
+                .and_then(|_| fallbacks.find(|model| Some(model) != self.options.model.as_ref()));
+            let (Some(reason), Some(next)) = (reason, next) else {
+                return Ok(FallbackResponse {
+                    model: self.options.model.clone(),
+                    messages,
+                    failed,
+                });
+            };
+            sdk_warn!(
+                "model {:?} failed ({reason:?}); falling back to {next}",
+                self.options.model
+            );
+            failed.push(FailedAttempt {
+                model: self.options.model.clone(),
+                reason,
+            });
+            self.set_model(Some(next)).await?;
+        }
+    }
+
+    /// Have Claude run `task` with the configured agent `agent` through the
+    /// Task tool and collect the agent's answer.
+    ///
+    /// The prompt is sent to `session_id` like [`ClaudeSdkClient::query`],
+    /// and the messages of the turn are consumed up to its result, like
+    /// [`ClaudeSdkClient::receive_response`].
+    pub async fn spawn_subagent(
+        &self,
+        agent: &str,
+        task: impl Into<String>,
+        session_id: &str,
+    ) -> Result<SubagentResult, SdkError> {
+        let mut results = self
+            .spawn_subagents(vec![SubagentTask::new(agent, task)], session_id)
+            .await?;
+        results.remove(0)
+    }
+
+    /// Run several agent tasks in parallel in `session_id`, in batches of
+    /// [`ClaudeAgentOptions::max_concurrent_subagents`], returning one
+    /// result per task in order.
+    pub async fn spawn_subagents(
+        &self,
+        tasks: Vec<SubagentTask>,
+        session_id: &str,
+    ) -> Result<Vec<Result<SubagentResult, SdkError>>, SdkError> {
+        let agents = self.options.agents.as_ref();
+        if let Some(unknown) = tasks
+            .iter()
+            .find(|task| !agents.is_some_and(|agents| agents.contains_key(&task.agent)))
+        {
+            return Err(SdkError::InvalidConfig(format!(
+                "agent '{}' is not defined in ClaudeAgentOptions::agents",
+                unknown.agent
+            )));
+        }
+
+        let batch_size = self
+            .options
+            .max_concurrent_subagents
+            .unwrap_or(DEFAULT_MAX_CONCURRENT_SUBAGENTS)
+            .max(1);
+        let mut results = Vec::with_capacity(tasks.len());
+        for batch in tasks.chunks(batch_size) {
+            let mut collector = SubagentCollector::new(batch.to_vec());
+            self.query(subagent::prompt(batch), session_id).await?;
+            let responses = self.receive_response()?;
+            futures::pin_mut!(responses);
+            while let Some(message) = responses.next().await {
+                collector.observe(&message?);
+            }
+            results.extend(collector.finish());
+        }
+        Ok(results)
+    }
+
+    /// Compact the conversation context of `session_id`, optionally steering
+    /// the summary.
+    ///
+    /// Sends the `/compact` command, reads its response through the result
+    /// message and resolves with the `compact_boundary` system message; do
+    /// not consume messages elsewhere meanwhile. Fails with
+    /// [`SdkError::QueryInFlight`] while an earlier response is still
+    /// streaming or unread. The session's turn count starts over afterwards.
+    pub async fn compact(
+        &self,
+        instructions: Option<String>,
+        session_id: &str,
+    ) -> Result<SystemMessage, SdkError> {
+        self.compact_observed(instructions, session_id, |_| {})
+            .await
+    }
+
+    /// [`ClaudeSdkClient::compact`], passing every message of the compaction
+    /// turn to `observe`.
+    async fn compact_observed(
+        &self,
+        instructions: Option<String>,
+        session_id: &str,
+        observe: impl FnMut(&Message),
+    ) -> Result<SystemMessage, SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        if query.has_unread_responses().await {
+            return Err(SdkError::QueryInFlight {
+                session_id: session_id.to_string(),
+            });
+        }
+        let command = match instructions {
+            Some(text) if !text.trim().is_empty() => format!("/compact {}", text.trim()),
+            _ => "/compact".to_string(),
+        };
+        self.compact_turn(query, command, session_id, observe).await
+    }
+
+    /// Send `command` once no other response is pending or unread, read its
+    /// turn through the result and resolve with the `compact_boundary`
+    /// message, resetting the session's turn count.
+    async fn compact_turn(
+        &self,
+        query: &Query<dyn Transport>,
+        command: String,
+        session_id: &str,
+        mut observe: impl FnMut(&Message),
+    ) -> Result<SystemMessage, SdkError> {
+        query.begin_exclusive_prompt(session_id).await?;
+        if let Some(limiter) = &self.options.rate_limiter {
+            limiter
+                .acquire_query(self.options.effective_clock().as_ref())
+                .await;
+        }
+        if let Err(err) = self.write_prompt(command.into(), session_id).await {
+            query.abandon_prompt(session_id).await;
+            return Err(err);
+        }
+        query.mark_prompt_sent().await;
+
+        let turn = self.receive_response()?;
+        futures::pin_mut!(turn);
+        let mut boundary = None;
+        let mut finished = false;
+        while let Some(message) = turn.next().await {
+            let message = message?;
+            observe(&message);
+            match message {
+                Message::System(system) if system.subtype == "compact_boundary" => {
+                    boundary = Some(system)
+                }
+                Message::Result(_) => finished = true,
+                _ => {}
+            }
+        }
+        match boundary {
+            Some(boundary) => {
+                query.reset_session_turns(session_id).await;
+                Ok(boundary)
+            }
+            None if finished => Err(SdkError::Protocol(
+                "compaction finished without a compact_boundary message".into(),
+            )),
+            None => {
+                Err(CliConnectionError::new("Connection closed before compaction completed").into())
+            }
+        }
+    }
+
     /// Interrupt the current conversation.
     pub async fn interrupt(&self) -> Result<(), SdkError> {
         let query = self
@@ -175,6 +872,43 @@ impl ClaudeSdkClient {
         query.interrupt().await
     }
 
+    /// Close the CLI's stdin, telling it no more prompts will follow.
+    ///
+    /// Needed with [`ClaudeAgentOptions::keep_input_open`]; otherwise stdin
+    /// closes when the connect prompt stream ends or on disconnect.
+    pub async fn end_input(&self) -> Result<(), SdkError> {
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        query.end_input().await
+    }
+
+    /// Replace the `can_use_tool` callback for permission requests from now
+    /// on, e.g. when the user toggles auto-approval.
+    ///
+    /// The CLI only asks for permission over the control protocol when the
+    /// session was connected with a callback, so a connected client without
+    /// one cannot gain one. Clearing the callback makes permission requests
+    /// fail, which the CLI treats as a denial. Before connecting this just
+    /// sets [`ClaudeAgentOptions::can_use_tool`].
+    pub async fn set_can_use_tool(
+        &mut self,
+        callback: Option<CanUseToolHandle>,
+    ) -> Result<(), SdkError> {
+        if let Some(query) = &self.query {
+            if callback.is_some() && !self.permission_callback_connected {
+                return Err(SdkError::InvalidConfig(
+                    "can_use_tool can only be swapped in sessions connected with a can_use_tool callback"
+                        .into(),
+                ));
+            }
+            query.set_can_use_tool(callback.clone()).await;
+        }
+        self.options.can_use_tool = callback;
+        Ok(())
+    }
+
     /// Update the permission mode during an active session.
     pub async fn set_permission_mode(&mut self, mode: PermissionMode) -> Result<(), SdkError> {
         let query = self
@@ -186,17 +920,160 @@ impl ClaudeSdkClient {
         Ok(())
     }
 
-    /// Update the active model during an active session.
+    /// Update the active model during an active session. Typos that fail
+    /// [`Model::new`] are rejected before the CLI sees them.
     pub async fn set_model(&mut self, model: Option<String>) -> Result<(), SdkError> {
         let query = self
             .query
             .as_ref()
             .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        if let Some(model) = &model {
+            Model::new(model.as_str())?;
+        }
         query.set_model(model.clone()).await?;
         self.options.model = model;
         Ok(())
     }
 
+    /// Switch the output style during an active session.
+    ///
+    /// Styles missing from [`ClaudeSdkClient::available_output_styles`] are
+    /// rejected when the CLI reported any. CLIs that cannot change the style
+    /// at runtime answer with [`SdkError::Control`]; set
+    /// [`ClaudeAgentOptions::output_style`] before connecting instead.
+    pub async fn set_output_style(&mut self, style: impl Into<String>) -> Result<(), SdkError> {
+        let style = style.into();
+        let query = self
+            .query
+            .as_ref()
+            .ok_or_else(|| CliConnectionError::new("Not connected"))?;
+        let available = self.available_output_styles();
+        if !available.is_empty() && !available.contains(&style) {
+            return Err(SdkError::InvalidConfig(format!(
+                "unknown output style \"{style}\"; available: {}",
+                available.join(", ")
+            )));
+        }
+        query.set_output_style(&style).await?;
+        self.options.output_style = Some(style);
+        Ok(())
+    }
+
+    /// Output styles the CLI reported in its initialize response.
+    pub fn available_output_styles(&self) -> Vec<String> {
+        self.server_info_names("available_output_styles")
+    }
+
+    /// System prompt presets the CLI reported in its initialize response.
+    pub fn available_system_prompt_presets(&self) -> Vec<String> {
+        self.server_info_names("available_system_prompt_presets")
+    }
+
+    fn server_info_names(&self, key: &str) -> Vec<String> {
+        server_info_names(self.server_info.as_ref(), key)
+    }
+
+    /// Current output style: the last one set, else the one the CLI
+    /// reported at initialize.
+    pub fn output_style(&self) -> Option<String> {
+        self.options.output_style.clone().or_else(|| {
+            self.server_info
+                .as_ref()
+                .and_then(|info| info.get("output_style"))
+                .and_then(Value::as_str)
+                .map(str::to_string)
+        })
+    }
+
+    /// Report the health of the current session for status endpoints.
+    ///
+    /// The snapshot is synthesized from locally observed traffic, so it never
+    /// blocks on the CLI.
+    pub async fn status(&self) -> SessionStatus {
+        let activity = match &self.query {
+            Some(query) => query.activity().await,
+            None => QueryActivity::default(),
+        };
+        let model = activity.model.or_else(|| self.options.model.clone());
+        let metadata = activity
+            .session_id
+            .as_deref()
+            .and_then(|session_id| self.session_metadata(session_id))
+            .cloned();
+
+        SessionStatus {
+            connected: self.connected,
+            session_id: activity.session_id,
+            model,
+            permission_mode: self.options.permission_mode,
+            turn_count: activity.completed_turns,
+            query_in_flight: activity.in_flight,
+            last_activity: activity.last_activity,
+            metadata,
+        }
+    }
+
+    /// Label `session_id` with a title and tags, replacing earlier labels.
+    ///
+    /// The labels are kept by this client: [`ClaudeSdkClient::status`]
+    /// reports them for the current session, and
+    /// [`Conversation::with_metadata`](crate::conversation::Conversation::with_metadata)
+    /// puts them in transcript exports. The CLI has no session titles, so
+    /// nothing is sent to it.
+    pub fn set_session_metadata<I>(&mut self, session_id: &str, title: Option<String>, tags: I)
+    where
+        I: IntoIterator,
+        I::Item: Into<String>,
+    {
+        let tags = tags.into_iter().map(Into::into).collect();
+        self.session_metadata
+            .insert(session_id.to_string(), SessionMetadata::new(title, tags));
+    }
+
+    /// Labels set for `session_id` with [`ClaudeSdkClient::set_session_metadata`].
+    pub fn session_metadata(&self, session_id: &str) -> Option<&SessionMetadata> {
+        self.session_metadata.get(session_id)
+    }
+
+    /// Run each task in its own one-shot session, concurrently, with this
+    /// client's options unless the task overrides them.
+    ///
+    /// Tasks do not share this client's transport or session. Outcomes come
+    /// back in task order; failures are reported per task.
+    pub async fn parallel<T>(&self, tasks: Vec<T>) -> Vec<TaskOutcome>
+    where
+        T: Into<ParallelTask>,
+    {
+        let tasks = tasks.into_iter().map(Into::into).collect();
+        parallel::run_tasks(&self.options, tasks).await
+    }
+
+    /// Options of the last successful connect, after validation and the
+    /// client's own adjustments; `None` before the first connect.
+    ///
+    /// [`ClaudeAgentOptions::diff_from_defaults`] on the snapshot shows what
+    /// the session runs with that differs from a plain client.
+    pub fn effective_options(&self) -> Option<&ClaudeAgentOptions> {
+        self.effective_options.as_ref()
+    }
+
+    /// Whether the client is connected over a ready transport whose query
+    /// is still open; a crashed CLI makes this `false`.
+    pub fn is_healthy(&self) -> bool {
+        self.connected
+            && self
+                .transport
+                .as_ref()
+                .is_some_and(|transport| transport.is_ready())
+            && self.query.as_ref().is_some_and(|query| !query.is_closed())
+    }
+
+    /// Unreachable MCP servers found by the last connect's
+    /// [`McpPreflight::Warn`](crate::mcp::health::McpPreflight::Warn) probe.
+    pub fn mcp_health_issues(&self) -> &[McpHealthIssue] {
+        &self.mcp_health_issues
+    }
+
     /// Get initialization metadata returned by the server.
     pub fn get_server_info(&self) -> Option<Value> {
         self.server_info.clone()
@@ -219,51 +1096,88 @@ impl ClaudeSdkClient {
         Ok(())
     }
 
-    fn message_stream<T>(query: Query<T>) -> impl Stream<Item = Result<Message, SdkError>>
+    /// Disconnect after draining in-flight messages and control requests.
+    ///
+    /// Messages are only drained while a stream from
+    /// [`ClaudeSdkClient::receive_messages`] is read in another task; see
+    /// [`Query::close_graceful`] for the drain semantics.
+    pub async fn disconnect_graceful(&mut self, drain_timeout: Duration) -> Result<(), SdkError> {
+        if let Some(handle) = self.prompt_task.take() {
+            handle.abort();
+            let _ = handle.await;
+        }
+
+        if let Some(query) = self.query.take() {
+            query.close_graceful(drain_timeout).await?;
+        }
+
+        self.transport = None;
+        self.server_info = None;
+        self.connected = false;
+        Ok(())
+    }
+
+    fn message_stream<T>(
+        query: Query<T>,
+        limits: (Option<u32>, Option<f64>),
+        hide_user_echoes: bool,
+    ) -> impl Stream<Item = Result<Message, SdkError>>
     where
         T: Transport + ?Sized + 'static,
     {
-        stream::unfold((query, false), |(query, finished)| async move {
+        stream::unfold((query, false), move |(query, finished)| async move {
             if finished {
                 return None;
             }
 
-            match query.next_message().await {
-                Ok(Some(message)) => Some((Ok(message), (query, false))),
-                Ok(None) => {
-                    let _ = query.close().await;
-                    None
-                }
-                Err(err) => {
-                    let _ = query.close().await;
-                    Some((Err(err), (query, true)))
-                }
+            loop {
+                return match query.next_message().await {
+                    Ok(Some(message)) if hide_user_echoes && message.is_user_echo() => continue,
+                    Ok(Some(message)) => {
+                        Some((surface_limit_error(message, limits), (query, false)))
+                    }
+                    Ok(None) => {
+                        let _ = query.close().await;
+                        None
+                    }
+                    Err(err) => {
+                        let _ = query.close().await;
+                        Some((Err(err), (query, true)))
+                    }
+                };
             }
         })
     }
 
-    fn response_stream<T>(query: Query<T>) -> impl Stream<Item = Result<Message, SdkError>>
+    fn response_stream<T>(
+        query: Query<T>,
+        limits: (Option<u32>, Option<f64>),
+        hide_user_echoes: bool,
+    ) -> impl Stream<Item = Result<Message, SdkError>>
     where
         T: Transport + ?Sized + 'static,
     {
-        stream::unfold((query, false), |(query, finished)| async move {
+        stream::unfold((query, false), move |(query, finished)| async move {
             if finished {
                 return None;
             }
 
-            match query.next_message().await {
-                Ok(Some(message)) => {
-                    let done = matches!(message, Message::Result(_));
-                    Some((Ok(message), (query, done)))
-                }
-                Ok(None) => {
-                    let _ = query.close().await;
-                    None
-                }
-                Err(err) => {
-                    let _ = query.close().await;
-                    Some((Err(err), (query, true)))
-                }
+            loop {
+                return match query.next_message().await {
+                    Ok(Some(message)) if hide_user_echoes && message.is_user_echo() => continue,
+                    Ok(Some(message)) => {
+                        let done = matches!(message, Message::Result(_));
+                        Some((surface_limit_error(message, limits), (query, done)))
+                    }
+                    Ok(None) => {
+                        let _ = query.close().await;
+                        None
+                    }
+                    Err(err) => {
+                        let _ = query.close().await;
+                        Some((Err(err), (query, true)))
+                    }
+                };
             }
         })
     }
@@ -274,13 +1188,13 @@ impl ClaudeSdkClient {
     ) -> Result<(), SdkError> {
         if options.can_use_tool.is_some() {
             if !is_streaming {
-                return Err(SdkError::Message(
+                return Err(SdkError::InvalidConfig(
                     "can_use_tool callback requires streaming mode".into(),
                 ));
             }
 
             if options.permission_prompt_tool_name.is_some() {
-                return Err(SdkError::Message(
+                return Err(SdkError::InvalidConfig(
                     "can_use_tool cannot be used with permission_prompt_tool_name".into(),
                 ));
             }
@@ -291,6 +1205,39 @@ impl ClaudeSdkClient {
     }
 }
 
+/// Response collected by [`ClaudeSdkClient::query_with_fallbacks`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct FallbackResponse {
+    /// Model that produced `messages`; `None` is the CLI default.
+    pub model: Option<String>,
+    /// Messages of the final attempt, up to and including its result.
+    pub messages: Vec<Message>,
+    /// Earlier attempts, in order.
+    pub failed: Vec<FailedAttempt>,
+}
+
+/// An attempt [`ClaudeSdkClient::query_with_fallbacks`] moved on from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FailedAttempt {
+    pub model: Option<String>,
+    pub reason: ModelFallbackReason,
+}
+
+/// Health snapshot returned by [`ClaudeSdkClient::status`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct SessionStatus {
+    pub connected: bool,
+    pub session_id: Option<String>,
+    pub model: Option<String>,
+    pub permission_mode: Option<PermissionMode>,
+    /// Number of completed request/response turns in this session.
+    pub turn_count: u64,
+    pub query_in_flight: bool,
+    pub last_activity: Option<SystemTime>,
+    /// Labels set for this session with [`ClaudeSdkClient::set_session_metadata`].
+    pub metadata: Option<SessionMetadata>,
+}
+
 /// Inputs accepted by [`ClaudeSdkClient::query`].
 pub enum ClientPrompt {
     Text(String),
@@ -323,3 +1270,16 @@ impl From<BoxStream<'static, Value>> for ClientPrompt {
         ClientPrompt::Stream(stream)
     }
 }
+
+/// Replace results that ended on `max_turns` / `max_budget_usd` with typed errors.
+pub(crate) fn surface_limit_error(
+    message: Message,
+    (max_turns, max_budget_usd): (Option<u32>, Option<f64>),
+) -> Result<Message, SdkError> {
+    if let Message::Result(result) = &message {
+        if let Some(err) = result.limit_error(max_turns, max_budget_usd) {
+            return Err(err);
+        }
+    }
+    Ok(message)
+}
@@ -0,0 +1,122 @@
+//! Time source for timeouts, the control watchdog and activity timestamps.
+//!
+//! The SDK reads time through a [`Clock`] so tests can drive timeout paths
+//! without sleeping. [`TokioClock`] (the default) already follows
+//! `tokio::time::pause()` for deadlines and elapsed times; [`VirtualClock`]
+//! additionally derives wall-clock timestamps from the tokio clock, so frame
+//! records and `last_activity` values are reproducible in paused tests.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::{Duration, UNIX_EPOCH};
+//! # use sdk_claude_rust::clock::VirtualClock;
+//! # use sdk_claude_rust::config::ClaudeAgentOptions;
+//! # fn example() {
+//! let options = ClaudeAgentOptions {
+//!     clock: Some(Arc::new(VirtualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000)))),
+//!     ..Default::default()
+//! };
+//! # let _ = options;
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use futures::future::BoxFuture;
+use tokio::time::Instant;
+
+/// Source of monotonic and wall-clock time.
+pub trait Clock: Send + Sync {
+    /// Current monotonic time, used for deadlines and latencies.
+    fn now(&self) -> Instant;
+
+    /// Current wall-clock time, used for timestamps.
+    fn wall_time(&self) -> SystemTime;
+
+    /// Future completing once `duration` has passed on this clock.
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()>;
+}
+
+/// Convenient handle for sharing a clock.
+pub type ClockHandle = Arc<dyn Clock>;
+
+/// Clock backed by tokio's timer and the system wall clock.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct TokioClock;
+
+impl Clock for TokioClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::now()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+/// Clock whose wall time starts at a fixed epoch and advances with tokio's
+/// clock, so it stands still while time is paused and jumps with
+/// `tokio::time::advance`.
+#[derive(Clone, Copy)]
+pub struct VirtualClock {
+    epoch: SystemTime,
+    origin: Instant,
+}
+
+impl VirtualClock {
+    /// Clock reporting `epoch` as the current wall time right now.
+    pub fn new(epoch: SystemTime) -> Self {
+        Self {
+            epoch,
+            origin: Instant::now(),
+        }
+    }
+}
+
+impl Clock for VirtualClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        self.epoch + Instant::now().saturating_duration_since(self.origin)
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        Box::pin(tokio::time::sleep(duration))
+    }
+}
+
+impl fmt::Debug for VirtualClock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("VirtualClock")
+            .field("wall_time", &self.wall_time())
+            .finish()
+    }
+}
+
+/// The clock used when none is configured.
+pub fn default_clock() -> ClockHandle {
+    Arc::new(TokioClock)
+}
+
+/// Run `future` until it completes or `duration` passes on `clock`; `None`
+/// means the deadline won.
+pub(crate) async fn timeout<F: Future>(
+    clock: &dyn Clock,
+    duration: Duration,
+    future: F,
+) -> Option<F::Output> {
+    tokio::select! {
+        biased;
+        output = future => Some(output),
+        _ = clock.sleep(duration) => None,
+    }
+}
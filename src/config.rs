@@ -3,13 +3,32 @@
 use std::collections::HashMap;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::time::Duration;
 
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::buffer_limit::BufferLimit;
+use crate::clock::{default_clock, ClockHandle};
+use crate::credentials::CredentialProviderHandle;
+use crate::env::Provider;
+use crate::error::SdkError;
+use crate::frame_log::ControlFrameSinkHandle;
 use crate::hooks::{HookEvent, HookMatcher};
-use crate::mcp::SdkMcpServer;
-use crate::permission::{CanUseToolHandle, PermissionMode, PermissionUpdate};
+use crate::mcp::health::{McpPreflight, McpProbeHandle};
+use crate::mcp::{McpFallbackHandle, SdkMcpServer};
+use crate::metrics::SdkMetricsHandle;
+use crate::middleware::PromptMiddlewareHandle;
+use crate::models::Model;
+use crate::permission::{
+    self, CanUseToolCallback, CanUseToolHandle, PermissionMode, PermissionUpdate,
+};
+use crate::rate_limit::RateLimiter;
+use crate::recovery::CrashRecovery;
+use crate::redact::RedactorHandle;
+use crate::settings::Settings;
+use crate::spill::MessageSpill;
+use crate::truncation::ToolResultLimit;
 
 /// Source of configuration settings.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -20,6 +39,74 @@ pub enum SettingSource {
     Local,
 }
 
+/// How [`ClaudeAgentOptions::extra_args`] flags are checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FlagMode {
+    /// Pass every flag through verbatim.
+    #[default]
+    Permissive,
+    /// Reject flags the CLI is not known to accept, suggesting the closest
+    /// known one.
+    Strict,
+}
+
+/// Handling of CLI stdout lines that cannot start a JSON message, such as
+/// banners printed by some CLI and plugin combinations.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StdoutMode {
+    /// Buffer them as the start of a message, which fails to decode once
+    /// it outgrows `max_buffer_size` or reaches its end.
+    #[default]
+    Strict,
+    /// Discard lines not starting with `{`, passing them to the `stderr`
+    /// callback.
+    Tolerant,
+}
+
+/// What [`ClaudeSdkClient::query`] does when the session it names still has
+/// a response streaming.
+///
+/// [`ClaudeSdkClient::query`]: crate::client::ClaudeSdkClient::query
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InFlightPolicy {
+    /// Send the prompt anyway; the CLI answers prompts in order.
+    #[default]
+    Allow,
+    /// Fail with [`SdkError::QueryInFlight`].
+    Error,
+    /// Wait for the previous response's result, serializing prompts.
+    Queue,
+}
+
+/// SDK-enforced ceiling on the prompts [`ClaudeSdkClient::query`] sends per
+/// session, on top of the per-query `max_turns` the CLI enforces.
+///
+/// [`ClaudeSdkClient::query`]: crate::client::ClaudeSdkClient::query
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct SessionTurnLimit {
+    /// Prompts allowed per session id before `on_limit` applies.
+    pub max_turns: u32,
+    #[serde(default)]
+    pub on_limit: TurnLimitAction,
+}
+
+/// What happens to a prompt past a [`SessionTurnLimit`].
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TurnLimitAction {
+    /// Fail with [`SdkError::MaxTurns`].
+    #[default]
+    Refuse,
+    /// Compact the session first and start counting again.
+    ///
+    /// The `/compact` response is consumed by `query`, so earlier responses
+    /// must have been read.
+    Compact,
+}
+
 /// Preset system prompt configuration.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct SystemPromptPreset {
@@ -37,11 +124,63 @@ pub enum SystemPromptPresetType {
     Preset,
 }
 
-/// Supported preset names.
-#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+/// Preset names, serialized as the CLI spells them.
+///
+/// Presets newer than this SDK can be named with
+/// [`SystemPromptPresetName::Other`]; [`ClaudeSdkClient::connect`] checks
+/// the name against the presets the CLI advertises, when it advertises any.
+///
+/// [`ClaudeSdkClient::connect`]: crate::client::ClaudeSdkClient::connect
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub enum SystemPromptPresetName {
-    #[serde(rename = "claude_code")]
+    /// `claude_code`, the Claude Code system prompt.
     ClaudeCode,
+    Other(String),
+}
+
+impl SystemPromptPresetName {
+    pub fn as_str(&self) -> &str {
+        match self {
+            SystemPromptPresetName::ClaudeCode => "claude_code",
+            SystemPromptPresetName::Other(name) => name,
+        }
+    }
+}
+
+impl From<&str> for SystemPromptPresetName {
+    fn from(name: &str) -> Self {
+        match name {
+            "claude_code" => SystemPromptPresetName::ClaudeCode,
+            other => SystemPromptPresetName::Other(other.to_string()),
+        }
+    }
+}
+
+impl From<String> for SystemPromptPresetName {
+    fn from(name: String) -> Self {
+        match name.as_str() {
+            "claude_code" => SystemPromptPresetName::ClaudeCode,
+            _ => SystemPromptPresetName::Other(name),
+        }
+    }
+}
+
+impl std::fmt::Display for SystemPromptPresetName {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl Serialize for SystemPromptPresetName {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.as_str())
+    }
+}
+
+impl<'de> Deserialize<'de> for SystemPromptPresetName {
+    fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        String::deserialize(deserializer).map(Self::from)
+    }
 }
 
 /// Representation of the system prompt option supplied to the SDK.
@@ -63,6 +202,140 @@ pub struct AgentDefinition {
     pub model: Option<String>,
 }
 
+impl AgentDefinition {
+    /// Start an agent definition checked by [`AgentDefinitionBuilder::build`].
+    pub fn builder(
+        description: impl Into<String>,
+        prompt: impl Into<String>,
+    ) -> AgentDefinitionBuilder {
+        AgentDefinitionBuilder {
+            description: description.into(),
+            prompt: prompt.into(),
+            tools: None,
+            model: None,
+            mcp_tools: Vec::new(),
+        }
+    }
+}
+
+/// Tools built into the CLI, as named in `tools` and permission rules.
+pub const BUILTIN_TOOLS: &[&str] = &[
+    "Bash",
+    "BashOutput",
+    "Edit",
+    "ExitPlanMode",
+    "Glob",
+    "Grep",
+    "KillShell",
+    "ListMcpResourcesTool",
+    "MultiEdit",
+    "NotebookEdit",
+    "Read",
+    "ReadMcpResourceTool",
+    "SlashCommand",
+    "Task",
+    "TodoWrite",
+    "WebFetch",
+    "WebSearch",
+    "Write",
+];
+
+/// Model value that makes an agent use the main conversation's model.
+pub const INHERIT_MODEL: &str = "inherit";
+
+/// Builder for an [`AgentDefinition`] that is checked before it reaches
+/// `--agents`.
+///
+/// Tools must be one of [`BUILTIN_TOOLS`] or an MCP tool registered with
+/// [`AgentDefinitionBuilder::with_mcp_tools`]; a rule such as `Bash(git:*)`
+/// is checked by its tool name. The model must be [`INHERIT_MODEL`] or pass
+/// [`Model::new`], so aliases like `sonnet` work.
+#[derive(Debug, Clone)]
+pub struct AgentDefinitionBuilder {
+    description: String,
+    prompt: String,
+    tools: Option<Vec<String>>,
+    model: Option<String>,
+    mcp_tools: Vec<String>,
+}
+
+impl AgentDefinitionBuilder {
+    /// Restrict the agent to `tools`; without this it gets every tool.
+    pub fn with_tools<I, S>(mut self, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: Into<String>,
+    {
+        self.tools = Some(tools.into_iter().map(Into::into).collect());
+        self
+    }
+
+    pub fn with_model(mut self, model: impl Into<String>) -> Self {
+        self.model = Some(model.into());
+        self
+    }
+
+    /// Accept `tools` of MCP server `server` as `mcp__<server>__<tool>`.
+    pub fn with_mcp_tools<I, S>(mut self, server: &str, tools: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.mcp_tools.extend(
+            tools
+                .into_iter()
+                .map(|tool| format!("mcp__{server}__{}", tool.as_ref())),
+        );
+        self
+    }
+
+    /// Check the definition, failing with [`SdkError::InvalidConfig`] on an
+    /// empty description or prompt, an unknown tool or an unknown model.
+    pub fn build(self) -> Result<AgentDefinition, SdkError> {
+        if self.description.trim().is_empty() {
+            return Err(SdkError::InvalidConfig(
+                "agent description must not be empty".into(),
+            ));
+        }
+        if self.prompt.trim().is_empty() {
+            return Err(SdkError::InvalidConfig(
+                "agent prompt must not be empty".into(),
+            ));
+        }
+        for tool in self.tools.iter().flatten() {
+            let name = tool.split('(').next().unwrap_or_default().trim();
+            if BUILTIN_TOOLS.contains(&name) || self.mcp_tools.iter().any(|known| known == name) {
+                continue;
+            }
+            let hint = BUILTIN_TOOLS
+                .iter()
+                .copied()
+                .chain(self.mcp_tools.iter().map(String::as_str))
+                .map(|known| (edit_distance(name, known), known))
+                .filter(|(distance, known)| *distance <= (known.len() / 3).max(2))
+                .min_by_key(|(distance, _)| *distance)
+                .map(|(_, known)| format!("; did you mean {known}?"))
+                .unwrap_or_default();
+            return Err(SdkError::InvalidConfig(format!(
+                "unknown tool \"{tool}\" for agent{hint}"
+            )));
+        }
+        if let Some(model) = self
+            .model
+            .as_deref()
+            .filter(|model| *model != INHERIT_MODEL)
+        {
+            Model::new(model)?;
+        }
+        Ok(AgentDefinition {
+            description: self.description,
+            prompt: self.prompt,
+            tools: self.tools,
+            model: self.model,
+        })
+    }
+}
+
 /// Destination behaviour for SDK MCP servers.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct McpStdioServerConfig {
@@ -128,6 +401,83 @@ pub enum McpServerConfig {
     Sdk(McpSdkServerConfig),
 }
 
+impl McpServerConfig {
+    /// Copy with `${VAR}` and `${VAR:-default}` expanded in the command,
+    /// arguments, environment values, URL and header values, as Claude Code
+    /// does for its own MCP config files.
+    ///
+    /// `lookup` resolves variable names; a variable it does not know and
+    /// that has no default fails with [`SdkError::InvalidConfig`].
+    pub fn interpolate_env(
+        &self,
+        lookup: impl Fn(&str) -> Option<String>,
+    ) -> Result<Self, SdkError> {
+        let expand = |text: &String| interpolate(text, &lookup);
+        let expand_values = |map: &Option<HashMap<String, String>>| {
+            map.as_ref()
+                .map(|map| {
+                    map.iter()
+                        .map(|(key, value)| Ok((key.clone(), expand(value)?)))
+                        .collect::<Result<HashMap<_, _>, SdkError>>()
+                })
+                .transpose()
+        };
+        Ok(match self {
+            McpServerConfig::Stdio(config) => McpServerConfig::Stdio(McpStdioServerConfig {
+                r#type: config.r#type.clone(),
+                command: expand(&config.command)?,
+                args: config
+                    .args
+                    .as_ref()
+                    .map(|args| args.iter().map(expand).collect())
+                    .transpose()?,
+                env: expand_values(&config.env)?,
+            }),
+            McpServerConfig::Sse(config) => McpServerConfig::Sse(McpSseServerConfig {
+                kind: config.kind,
+                url: expand(&config.url)?,
+                headers: expand_values(&config.headers)?,
+            }),
+            McpServerConfig::Http(config) => McpServerConfig::Http(McpHttpServerConfig {
+                kind: config.kind,
+                url: expand(&config.url)?,
+                headers: expand_values(&config.headers)?,
+            }),
+            McpServerConfig::Sdk(config) => McpServerConfig::Sdk(config.clone()),
+        })
+    }
+}
+
+/// Expand `${VAR}` and `${VAR:-default}` references in `text`.
+fn interpolate(text: &str, lookup: &impl Fn(&str) -> Option<String>) -> Result<String, SdkError> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        expanded.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            return Err(SdkError::InvalidConfig(format!(
+                "unterminated ${{ in MCP server config value \"{text}\""
+            )));
+        };
+        let reference = &rest[start + 2..start + end];
+        let (name, default) = match reference.split_once(":-") {
+            Some((name, default)) => (name, Some(default)),
+            None => (reference, None),
+        };
+        match lookup(name).or_else(|| default.map(str::to_string)) {
+            Some(value) => expanded.push_str(&value),
+            None => {
+                return Err(SdkError::InvalidConfig(format!(
+                    "environment variable {name} used in MCP server config is not set"
+                )))
+            }
+        }
+        rest = &rest[start + end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
 /// Local plugin configuration supported by the SDK.
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct SdkPluginConfig {
@@ -157,6 +507,77 @@ impl Default for McpServers {
     }
 }
 
+/// Settings for the background sweep that watches pending control requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ControlWatchdogConfig {
+    /// How often pending requests are inspected.
+    pub interval: Duration,
+    /// Pending time after which a warning naming the request subtype is logged.
+    pub warn_after: Duration,
+    /// Pending time after which the request is failed, if set.
+    pub fail_after: Option<Duration>,
+}
+
+impl Default for ControlWatchdogConfig {
+    fn default() -> Self {
+        Self {
+            interval: Duration::from_secs(5),
+            warn_after: Duration::from_secs(30),
+            fail_after: None,
+        }
+    }
+}
+
+/// Extended thinking settings, sent as `--max-thinking-tokens`.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub struct ThinkingConfig {
+    pub enabled: bool,
+    /// Tokens Claude may spend thinking; [`DEFAULT_THINKING_BUDGET`] when
+    /// unset. At least [`MIN_THINKING_BUDGET`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub budget_tokens: Option<u32>,
+}
+
+/// Thinking budget used when [`ThinkingConfig::budget_tokens`] is unset.
+pub const DEFAULT_THINKING_BUDGET: u32 = 32_000;
+
+/// Smallest thinking budget the API accepts.
+pub const MIN_THINKING_BUDGET: u32 = 1_024;
+
+impl ThinkingConfig {
+    /// Thinking with the default budget.
+    pub fn enabled() -> Self {
+        Self {
+            enabled: true,
+            budget_tokens: None,
+        }
+    }
+
+    /// Thinking with a budget of `budget_tokens`.
+    pub fn with_budget(budget_tokens: u32) -> Self {
+        Self {
+            enabled: true,
+            budget_tokens: Some(budget_tokens),
+        }
+    }
+
+    pub fn disabled() -> Self {
+        Self {
+            enabled: false,
+            budget_tokens: None,
+        }
+    }
+
+    /// Value for `--max-thinking-tokens`; zero turns thinking off.
+    pub fn max_thinking_tokens(&self) -> u32 {
+        if self.enabled {
+            self.budget_tokens.unwrap_or(DEFAULT_THINKING_BUDGET)
+        } else {
+            0
+        }
+    }
+}
+
 /// Callback invoked when the CLI writes to stderr.
 pub type StderrCallback = Arc<dyn Fn(&str) + Send + Sync + 'static>;
 
@@ -182,6 +603,12 @@ pub struct ClaudeAgentOptions {
     pub disallowed_tools: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub model: Option<String>,
+    /// Models tried in order by [`ClaudeSdkClient::query_with_fallbacks`]
+    /// when the current one is overloaded or unavailable.
+    ///
+    /// [`ClaudeSdkClient::query_with_fallbacks`]: crate::client::ClaudeSdkClient::query_with_fallbacks
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub model_fallbacks: Vec<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_prompt_tool_name: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -190,14 +617,47 @@ pub struct ClaudeAgentOptions {
     pub cli_path: Option<PathBuf>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub settings: Option<String>,
+    /// Output style to start the session with, e.g. `Explanatory`; merged
+    /// into `settings` as `outputStyle`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub add_dirs: Vec<PathBuf>,
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub env: HashMap<String, String>,
+    /// Model provider; sets `CLAUDE_CODE_USE_BEDROCK` / `CLAUDE_CODE_USE_VERTEX`
+    /// and checks its required variables on connect.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    /// `CLAUDE_CODE_ENTRYPOINT` reported by the CLI process; defaults to
+    /// `sdk-rs` for one-shot queries and `sdk-rs-client` for
+    /// [`ClaudeSdkClient`](crate::client::ClaudeSdkClient).
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<String>,
+    /// Credentials resolved on every connect and layered over `env`.
+    #[serde(skip)]
+    pub credential_provider: Option<CredentialProviderHandle>,
     #[serde(skip_serializing_if = "HashMap::is_empty")]
     pub extra_args: HashMap<String, Option<String>>,
+    /// Whether [`validate`](Self::validate) rejects unknown `extra_args` flags.
+    pub flag_mode: FlagMode,
+    /// Handling of prompts sent while the session's previous response is
+    /// still streaming.
+    pub in_flight_policy: InFlightPolicy,
+    /// Ceiling on prompts per session, enforced by the client.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_turn_limit: Option<SessionTurnLimit>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_buffer_size: Option<usize>,
+    /// Whether stdout lines that are not JSON end the session or are
+    /// discarded.
+    pub stdout_mode: StdoutMode,
+    /// Command-line length in bytes past which the largest arguments
+    /// (`--agents`, `--mcp-config`, `--settings`, system prompts) are passed
+    /// through temporary files. Defaults to the platform limit; `Some(0)`
+    /// always uses files.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arg_file_threshold: Option<usize>,
     #[serde(skip)]
     pub debug_stderr: Option<StderrCallback>,
     #[serde(skip)]
@@ -208,22 +668,88 @@ pub struct ClaudeAgentOptions {
     pub hooks: Option<HashMap<HookEvent, Vec<HookMatcher>>>,
     #[serde(skip)]
     pub sdk_servers: HashMap<String, Arc<dyn SdkMcpServer>>,
+    /// Answers `mcp_message` requests for servers missing from `sdk_servers`.
+    #[serde(skip)]
+    pub mcp_fallback: Option<McpFallbackHandle>,
+    /// Probing of `sse` and `http` servers in `mcp_servers` before connect.
+    pub mcp_preflight: McpPreflight,
+    /// Probe used by `mcp_preflight`; defaults to
+    /// [`TcpProbe`](crate::mcp::health::TcpProbe).
+    #[serde(skip)]
+    pub mcp_probe: Option<McpProbeHandle>,
+    #[serde(skip)]
+    pub control_frame_sink: Option<ControlFrameSinkHandle>,
+    #[serde(skip)]
+    pub control_watchdog: Option<ControlWatchdogConfig>,
+    /// Restart the CLI with `--resume` when it crashes mid-turn; see
+    /// [`RecoveringTransport`](crate::recovery::RecoveringTransport).
+    #[serde(skip)]
+    pub crash_recovery: Option<CrashRecovery>,
+    #[serde(skip)]
+    pub metrics: Option<SdkMetricsHandle>,
+    /// Extra secrets and patterns to mask; see [`ClaudeAgentOptions::effective_redactor`].
+    #[serde(skip)]
+    pub redactor: Option<RedactorHandle>,
+    /// Time source for timeouts and timestamps; defaults to [`TokioClock`](crate::clock::TokioClock).
+    #[serde(skip)]
+    pub clock: Option<ClockHandle>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub user: Option<String>,
     pub include_partial_messages: bool,
+    /// Have the CLI echo each streamed user message back (`--replay-user-messages`);
+    /// only passed in streaming mode.
+    pub replay_user_messages: bool,
+    /// Drop user messages without tool results, such as replayed prompts,
+    /// from received message streams.
+    pub hide_user_echoes: bool,
+    /// Leave the CLI's stdin open when the prompt stream passed to
+    /// [`ClaudeSdkClient::connect`] ends, so later prompts can follow; close
+    /// it with [`ClaudeSdkClient::end_input`]. One-shot queries always close it.
+    ///
+    /// [`ClaudeSdkClient::connect`]: crate::client::ClaudeSdkClient::connect
+    /// [`ClaudeSdkClient::end_input`]: crate::client::ClaudeSdkClient::end_input
+    pub keep_input_open: bool,
     pub fork_session: bool,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub agents: Option<HashMap<String, AgentDefinition>>,
+    /// Subagents [`ClaudeSdkClient::spawn_subagents`] starts per prompt;
+    /// defaults to [`DEFAULT_MAX_CONCURRENT_SUBAGENTS`].
+    ///
+    /// [`ClaudeSdkClient::spawn_subagents`]: crate::client::ClaudeSdkClient::spawn_subagents
+    /// [`DEFAULT_MAX_CONCURRENT_SUBAGENTS`]: crate::subagent::DEFAULT_MAX_CONCURRENT_SUBAGENTS
+    #[serde(skip)]
+    pub max_concurrent_subagents: Option<usize>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub setting_sources: Option<Vec<SettingSource>>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub plugins: Vec<SdkPluginConfig>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub max_thinking_tokens: Option<u32>,
+    /// Typed thinking settings; take precedence over `max_thinking_tokens`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+    /// Limits shared with every client and query holding a clone of the
+    /// same limiter.
+    #[serde(skip)]
+    pub rate_limiter: Option<RateLimiter>,
+    /// Cap on CLI output buffered for a slow consumer, shared with every
+    /// clone of the same limit.
+    #[serde(skip)]
+    pub buffer_limit: Option<BufferLimit>,
+    /// Write CLI output the consumer has not caught up with to disk instead
+    /// of holding it in memory or stalling the CLI.
+    #[serde(skip)]
+    pub message_spill: Option<MessageSpill>,
+    /// Cap on tool result text sent back to the CLI; unlimited when unset.
+    #[serde(skip)]
+    pub tool_result_limit: Option<ToolResultLimit>,
+    /// Rewrites applied, in order, to every outgoing user message.
+    #[serde(skip)]
+    pub prompt_middleware: Vec<PromptMiddlewareHandle>,
 }
 
 /// Helper to convert permission suggestions to CLI payloads.
-pub fn serialize_permission_updates(updates: &[PermissionUpdate]) -> Vec<Value> {
+pub fn serialize_permission_updates(updates: &[PermissionUpdate]) -> Result<Vec<Value>, SdkError> {
     updates
         .iter()
         .map(PermissionUpdate::to_control_payload)
@@ -252,44 +778,373 @@ impl ClaudeAgentOptions {
 
         self.mcp_servers = McpServers::Map(map);
     }
+
+    /// Answer permission prompts through an in-process MCP tool backed by
+    /// `callback`, passed as `--permission-prompt-tool`.
+    ///
+    /// An alternative to [`ClaudeAgentOptions::can_use_tool`] for workflows
+    /// that need the MCP approval tool; the two cannot be combined.
+    pub fn set_permission_prompt_tool<C>(&mut self, callback: C)
+    where
+        C: CanUseToolCallback + 'static,
+    {
+        let server = permission::permission_prompt_server(Arc::new(callback));
+        self.add_sdk_server(permission::PERMISSION_PROMPT_SERVER, server);
+        self.permission_prompt_tool_name = Some(format!(
+            "mcp__{}__{}",
+            permission::PERMISSION_PROMPT_SERVER,
+            permission::PERMISSION_PROMPT_TOOL
+        ));
+    }
+
+    /// [`ClaudeAgentOptions::redactor`] extended with the built-in secret
+    /// detection (known credential variables and Anthropic key prefixes).
+    pub fn effective_redactor(&self) -> RedactorHandle {
+        let mut redactor = self.redactor.as_deref().cloned().unwrap_or_default();
+        redactor.extend_defaults(&self.env);
+        Arc::new(redactor)
+    }
+
+    /// Add `text` after the system prompt, separated by a blank line. Without
+    /// a system prompt it is appended to the `claude_code` preset.
+    pub fn append_system_prompt(&mut self, text: &str) {
+        self.system_prompt = Some(match self.system_prompt.take() {
+            Some(SystemPrompt::Text(prompt)) => SystemPrompt::Text(format!("{prompt}\n\n{text}")),
+            Some(SystemPrompt::Preset(mut preset)) => {
+                preset.append = Some(match preset.append {
+                    Some(append) => format!("{append}\n\n{text}"),
+                    None => text.to_string(),
+                });
+                SystemPrompt::Preset(preset)
+            }
+            None => SystemPrompt::Preset(SystemPromptPreset {
+                kind: SystemPromptPresetType::Preset,
+                preset: SystemPromptPresetName::ClaudeCode,
+                append: Some(text.to_string()),
+            }),
+        });
+    }
+
+    /// Check the options before the CLI is started.
+    ///
+    /// With [`FlagMode::Strict`], `extra_args` keys must name a known CLI flag
+    /// (without the leading `--`); typos fail with the closest match, e.g.
+    /// `unknown CLI flag --max-trns in extra_args; did you mean --max-turns?`.
+    /// `model` and `model_fallbacks` must pass [`Model::new`], and a thinking
+    /// budget must be at least [`MIN_THINKING_BUDGET`].
+    pub fn validate(&self) -> Result<(), SdkError> {
+        if self.flag_mode == FlagMode::Strict {
+            let mut flags: Vec<&String> = self.extra_args.keys().collect();
+            flags.sort();
+            for flag in flags {
+                if KNOWN_FLAGS.contains(&flag.as_str()) {
+                    continue;
+                }
+                let hint = closest_flag(flag)
+                    .map(|known| format!("; did you mean --{known}?"))
+                    .unwrap_or_default();
+                return Err(SdkError::InvalidConfig(format!(
+                    "unknown CLI flag --{flag} in extra_args{hint}"
+                )));
+            }
+        }
+        for model in self.model.iter().chain(&self.model_fallbacks) {
+            Model::new(model.as_str())?;
+        }
+        if let Some(budget) = self.thinking.and_then(|thinking| thinking.budget_tokens) {
+            if budget < MIN_THINKING_BUDGET {
+                return Err(SdkError::InvalidConfig(format!(
+                    "thinking budget_tokens must be at least {MIN_THINKING_BUDGET}, got {budget}"
+                )));
+            }
+        }
+        Ok(())
+    }
+
+    /// Fail with [`SdkError::InvalidConfig`] when the system prompt names a
+    /// preset missing from the `available_system_prompt_presets` of
+    /// `server_info`, the CLI's initialize response. Nothing is checked when
+    /// the CLI advertised no presets.
+    pub(crate) fn check_system_prompt_preset(
+        &self,
+        server_info: Option<&Value>,
+    ) -> Result<(), SdkError> {
+        let Some(SystemPrompt::Preset(preset)) = &self.system_prompt else {
+            return Ok(());
+        };
+        let available = server_info_names(server_info, "available_system_prompt_presets");
+        let name = preset.preset.as_str();
+        if available.is_empty() || available.iter().any(|known| known == name) {
+            return Ok(());
+        }
+        let hint = available
+            .iter()
+            .map(|known| (edit_distance(name, known), known))
+            .filter(|(distance, known)| *distance <= (known.len() / 3).max(2))
+            .min_by_key(|(distance, _)| *distance)
+            .map(|(_, known)| format!("; did you mean {known}?"))
+            .unwrap_or_default();
+        Err(SdkError::InvalidConfig(format!(
+            "unknown system prompt preset \"{name}\"{hint} (available: {})",
+            available.join(", ")
+        )))
+    }
+
+    /// `--max-thinking-tokens` value from [`ClaudeAgentOptions::thinking`],
+    /// falling back to [`ClaudeAgentOptions::max_thinking_tokens`].
+    pub fn effective_max_thinking_tokens(&self) -> Option<u32> {
+        self.thinking
+            .map(|thinking| thinking.max_thinking_tokens())
+            .or(self.max_thinking_tokens)
+    }
+
+    /// Value for `--settings`: [`ClaudeAgentOptions::settings`] with
+    /// [`ClaudeAgentOptions::output_style`] merged in as `outputStyle`.
+    ///
+    /// A settings file path is read and passed on as inline JSON when a
+    /// style has to be added.
+    pub fn effective_settings(&self) -> Result<Option<String>, SdkError> {
+        let Some(style) = &self.output_style else {
+            return Ok(self.settings.clone());
+        };
+        let mut settings = match self.settings.as_deref().map(str::trim) {
+            Some(json) if json.starts_with('{') => Settings::from_json(json)?,
+            Some(path) => Settings::load(path)?,
+            None => Settings::default(),
+        };
+        settings
+            .other
+            .insert("outputStyle".into(), Value::String(style.clone()));
+        Ok(Some(serde_json::to_string(&settings)?))
+    }
+
+    /// Serializable fields where `self` differs from `base`, by name.
+    ///
+    /// Values are masked by [`ClaudeAgentOptions::effective_redactor`];
+    /// runtime handles such as callbacks and hooks are not compared.
+    pub fn diff(&self, base: &ClaudeAgentOptions) -> Vec<OptionChange> {
+        let redactor = self.effective_redactor();
+        let fields = |options: &ClaudeAgentOptions| match serde_json::to_value(options) {
+            Ok(Value::Object(mut fields)) => {
+                fields
+                    .values_mut()
+                    .for_each(|value| redactor.redact_value(value));
+                fields
+            }
+            _ => serde_json::Map::new(),
+        };
+        let (before, after) = (fields(base), fields(self));
+        let mut names: Vec<&String> = before.keys().chain(after.keys()).collect();
+        names.sort();
+        names.dedup();
+        names
+            .into_iter()
+            .filter_map(|name| {
+                let old = before.get(name).cloned().unwrap_or(Value::Null);
+                let new = after.get(name).cloned().unwrap_or(Value::Null);
+                (old != new).then(|| OptionChange {
+                    field: name.clone(),
+                    before: old,
+                    after: new,
+                })
+            })
+            .collect()
+    }
+
+    /// [`ClaudeAgentOptions::diff`] against the defaults.
+    pub fn diff_from_defaults(&self) -> Vec<OptionChange> {
+        self.diff(&ClaudeAgentOptions::default())
+    }
+
+    /// [`ClaudeAgentOptions::clock`], or the default clock when unset.
+    pub fn effective_clock(&self) -> ClockHandle {
+        self.clock.clone().unwrap_or_else(default_clock)
+    }
+}
+
+/// A field that differs between two [`ClaudeAgentOptions`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct OptionChange {
+    /// Field name, as serialized.
+    pub field: String,
+    /// Value in the options compared against; `Null` when unset.
+    pub before: Value,
+    pub after: Value,
+}
+
+/// Flags the CLI accepts, without the leading `--`.
+pub const KNOWN_FLAGS: &[&str] = &[
+    "add-dir",
+    "agents",
+    "allow-dangerously-skip-permissions",
+    "allowedTools",
+    "append-system-prompt",
+    "betas",
+    "continue",
+    "dangerously-skip-permissions",
+    "debug",
+    "debug-to-stderr",
+    "disallowedTools",
+    "fallback-model",
+    "fork-session",
+    "include-partial-messages",
+    "input-format",
+    "json-schema",
+    "max-budget-usd",
+    "max-thinking-tokens",
+    "max-turns",
+    "mcp-config",
+    "mcp-debug",
+    "model",
+    "output-format",
+    "permission-mode",
+    "permission-prompt-tool",
+    "plugin-dir",
+    "print",
+    "replay-user-messages",
+    "resume",
+    "session-id",
+    "setting-sources",
+    "settings",
+    "strict-mcp-config",
+    "system-prompt",
+    "verbose",
+];
+
+/// Known flag within a few edits of `flag`.
+fn closest_flag(flag: &str) -> Option<&'static str> {
+    let flag = flag.trim_start_matches('-');
+    KNOWN_FLAGS
+        .iter()
+        .map(|known| (edit_distance(flag, known), *known))
+        .filter(|(distance, known)| *distance <= (known.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
+
+/// Strings listed under `key` in an initialize response.
+pub(crate) fn server_info_names(server_info: Option<&Value>, key: &str) -> Vec<String> {
+    server_info
+        .and_then(|info| info.get(key))
+        .and_then(Value::as_array)
+        .map(|names| {
+            names
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+pub(crate) fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut previous = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = previous + usize::from(ca != *cb);
+            previous = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(previous + 1);
+        }
+    }
+    row[b.len()]
 }
 
 impl std::fmt::Debug for ClaudeAgentOptions {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let fields = OptionsDebugFields(self);
+        let rendered = if f.alternate() {
+            format!("{fields:#?}")
+        } else {
+            format!("{fields:?}")
+        };
+        f.write_str(&self.effective_redactor().redact(&rendered))
+    }
+}
+
+/// Unredacted field listing used by the `Debug` impl of [`ClaudeAgentOptions`].
+struct OptionsDebugFields<'a>(&'a ClaudeAgentOptions);
+
+impl std::fmt::Debug for OptionsDebugFields<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let options = self.0;
         f.debug_struct("ClaudeAgentOptions")
-            .field("allowed_tools", &self.allowed_tools)
-            .field("system_prompt", &self.system_prompt)
-            .field("mcp_servers", &self.mcp_servers)
-            .field("permission_mode", &self.permission_mode)
-            .field("continue_conversation", &self.continue_conversation)
-            .field("resume", &self.resume)
-            .field("max_turns", &self.max_turns)
-            .field("max_budget_usd", &self.max_budget_usd)
-            .field("disallowed_tools", &self.disallowed_tools)
-            .field("model", &self.model)
+            .field("allowed_tools", &options.allowed_tools)
+            .field("system_prompt", &options.system_prompt)
+            .field("mcp_servers", &options.mcp_servers)
+            .field("permission_mode", &options.permission_mode)
+            .field("continue_conversation", &options.continue_conversation)
+            .field("resume", &options.resume)
+            .field("max_turns", &options.max_turns)
+            .field("max_budget_usd", &options.max_budget_usd)
+            .field("disallowed_tools", &options.disallowed_tools)
+            .field("model", &options.model)
+            .field("model_fallbacks", &options.model_fallbacks)
             .field(
                 "permission_prompt_tool_name",
-                &self.permission_prompt_tool_name,
+                &options.permission_prompt_tool_name,
+            )
+            .field("cwd", &options.cwd)
+            .field("cli_path", &options.cli_path)
+            .field("settings", &options.settings)
+            .field("add_dirs", &options.add_dirs)
+            .field("env", &options.env)
+            .field("provider", &options.provider)
+            .field("entrypoint", &options.entrypoint)
+            .field(
+                "has_credential_provider",
+                &options.credential_provider.is_some(),
+            )
+            .field("extra_args", &options.extra_args)
+            .field("flag_mode", &options.flag_mode)
+            .field("in_flight_policy", &options.in_flight_policy)
+            .field("session_turn_limit", &options.session_turn_limit)
+            .field("max_buffer_size", &options.max_buffer_size)
+            .field("stdout_mode", &options.stdout_mode)
+            .field("arg_file_threshold", &options.arg_file_threshold)
+            .field("has_debug_stderr", &options.debug_stderr.is_some())
+            .field("has_stderr", &options.stderr.is_some())
+            .field("has_can_use_tool", &options.can_use_tool.is_some())
+            .field("hooks_registered", &options.hooks.as_ref().map(|h| h.len()))
+            .field("sdk_servers", &options.sdk_servers.len())
+            .field("has_mcp_fallback", &options.mcp_fallback.is_some())
+            .field("mcp_preflight", &options.mcp_preflight)
+            .field("has_mcp_probe", &options.mcp_probe.is_some())
+            .field(
+                "has_control_frame_sink",
+                &options.control_frame_sink.is_some(),
+            )
+            .field("control_watchdog", &options.control_watchdog)
+            .field("crash_recovery", &options.crash_recovery)
+            .field("has_metrics", &options.metrics.is_some())
+            .field("redactor", &options.redactor)
+            .field("has_clock", &options.clock.is_some())
+            .field("user", &options.user)
+            .field(
+                "include_partial_messages",
+                &options.include_partial_messages,
+            )
+            .field("replay_user_messages", &options.replay_user_messages)
+            .field("hide_user_echoes", &options.hide_user_echoes)
+            .field("keep_input_open", &options.keep_input_open)
+            .field("fork_session", &options.fork_session)
+            .field("agents", &options.agents)
+            .field(
+                "max_concurrent_subagents",
+                &options.max_concurrent_subagents,
             )
-            .field("cwd", &self.cwd)
-            .field("cli_path", &self.cli_path)
-            .field("settings", &self.settings)
-            .field("add_dirs", &self.add_dirs)
-            .field("env", &self.env)
-            .field("extra_args", &self.extra_args)
-            .field("max_buffer_size", &self.max_buffer_size)
-            .field("has_debug_stderr", &self.debug_stderr.is_some())
-            .field("has_stderr", &self.stderr.is_some())
-            .field("has_can_use_tool", &self.can_use_tool.is_some())
-            .field("hooks_registered", &self.hooks.as_ref().map(|h| h.len()))
-            .field("sdk_servers", &self.sdk_servers.len())
-            .field("user", &self.user)
-            .field("include_partial_messages", &self.include_partial_messages)
-            .field("fork_session", &self.fork_session)
-            .field("agents", &self.agents)
-            .field("setting_sources", &self.setting_sources)
-            .field("plugins", &self.plugins)
-            .field("max_thinking_tokens", &self.max_thinking_tokens)
+            .field("setting_sources", &options.setting_sources)
+            .field("plugins", &options.plugins)
+            .field("max_thinking_tokens", &options.max_thinking_tokens)
+            .field("thinking", &options.thinking)
+            .field("output_style", &options.output_style)
+            .field("rate_limiter", &options.rate_limiter)
+            .field("buffer_limit", &options.buffer_limit)
+            .field("message_spill", &options.message_spill)
+            .field("tool_result_limit", &options.tool_result_limit)
+            .field("prompt_middleware", &options.prompt_middleware.len())
             .finish()
     }
 }
@@ -0,0 +1,216 @@
+//! Context window usage tracking.
+//!
+//! A session's context grows with every turn until the CLI compacts it or
+//! the model refuses the request. A [`ContextWindowTracker`] follows the
+//! token usage reported by the CLI and raises a [`ContextWindowWarning`]
+//! once it reaches a fraction of the model's window, so the app can compact
+//! or start over before answers degrade.
+//! [`ClaudeSdkClient::receive_response_with_context`] does this for a
+//! connected client and can compact automatically after the response.
+//!
+//! With `include_partial_messages` the usage of each API call is read from
+//! its `message_start` and `message_delta` events. Without them only the
+//! usage of result messages is known, which sums every API call of the
+//! query and so overstates the context of tool-heavy turns.
+//!
+//! [`ClaudeSdkClient::receive_response_with_context`]: crate::client::ClaudeSdkClient::receive_response_with_context
+
+use serde_json::{Map, Value};
+
+use crate::message::{Message, SystemMessage};
+
+/// Context window of most Claude models, in tokens.
+pub const DEFAULT_CONTEXT_WINDOW: u64 = 200_000;
+
+/// Context window of the 1M-token variants (`sonnet[1m]`).
+pub const EXTENDED_CONTEXT_WINDOW: u64 = 1_000_000;
+
+/// Context window of `model`.
+pub fn context_window_for(model: &str) -> u64 {
+    if model.ends_with("[1m]") {
+        EXTENDED_CONTEXT_WINDOW
+    } else {
+        DEFAULT_CONTEXT_WINDOW
+    }
+}
+
+/// Raised once when usage reaches the tracker's threshold, and again only
+/// after a compaction.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextWindowWarning {
+    pub used_tokens: u64,
+    pub window_tokens: u64,
+    /// Fraction of the window that triggers the warning.
+    pub threshold: f64,
+    /// Whether the response will be followed by a compaction.
+    pub will_compact: bool,
+}
+
+impl ContextWindowWarning {
+    /// Fraction of the window in use.
+    pub fn usage_fraction(&self) -> f64 {
+        self.used_tokens as f64 / self.window_tokens as f64
+    }
+}
+
+/// Item of [`ClaudeSdkClient::receive_response_with_context`](crate::client::ClaudeSdkClient::receive_response_with_context).
+#[derive(Debug, Clone, PartialEq)]
+pub enum ContextEvent {
+    Message(Message),
+    /// Follows the message that pushed usage over the threshold.
+    Warning(ContextWindowWarning),
+    /// Last item after an automatic compaction: its `compact_boundary`.
+    Compacted(SystemMessage),
+}
+
+/// Follows context usage against a window.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContextWindowTracker {
+    window_tokens: u64,
+    threshold: f64,
+    auto_compact: bool,
+    used_tokens: u64,
+    /// Input and output tokens of the API call being streamed.
+    call_input: u64,
+    call_output: u64,
+    /// Whether the running query reported usage through stream events.
+    streamed_usage: bool,
+    warned: bool,
+}
+
+impl ContextWindowTracker {
+    /// Tracker warning at 80% of `window_tokens`.
+    pub fn new(window_tokens: u64) -> Self {
+        Self {
+            window_tokens,
+            threshold: 0.8,
+            auto_compact: false,
+            used_tokens: 0,
+            call_input: 0,
+            call_output: 0,
+            streamed_usage: false,
+            warned: false,
+        }
+    }
+
+    /// Tracker for the window of `model`.
+    pub fn for_model(model: &str) -> Self {
+        Self::new(context_window_for(model))
+    }
+
+    /// Warn once usage reaches `threshold` of the window.
+    pub fn with_threshold(mut self, threshold: f64) -> Self {
+        self.threshold = threshold;
+        self
+    }
+
+    /// Compact the conversation after the response that raised a warning.
+    pub fn with_auto_compact(mut self, auto_compact: bool) -> Self {
+        self.auto_compact = auto_compact;
+        self
+    }
+
+    pub fn window_tokens(&self) -> u64 {
+        self.window_tokens
+    }
+
+    pub fn auto_compacts(&self) -> bool {
+        self.auto_compact
+    }
+
+    /// Tokens in the context as of the last reported usage.
+    pub fn used_tokens(&self) -> u64 {
+        self.used_tokens
+    }
+
+    /// Update usage with `message`; returns a warning the first time usage
+    /// reaches the threshold.
+    pub fn observe(&mut self, message: &Message) -> Option<ContextWindowWarning> {
+        match message {
+            Message::StreamEvent(event) => self.observe_event(&event.event),
+            Message::Result(result) => {
+                if !self.streamed_usage {
+                    if let Some(usage) = &result.usage {
+                        self.used_tokens = context_tokens(usage);
+                    }
+                }
+                self.streamed_usage = false;
+            }
+            Message::System(system) if system.subtype == "compact_boundary" => {
+                self.used_tokens = system
+                    .data
+                    .get("compact_metadata")
+                    .and_then(|metadata| metadata.get("post_tokens"))
+                    .and_then(Value::as_u64)
+                    .unwrap_or(0);
+                self.warned = false;
+                return None;
+            }
+            _ => {}
+        }
+        self.check()
+    }
+
+    fn observe_event(&mut self, event: &Value) {
+        let usage = match event.get("type").and_then(Value::as_str) {
+            Some("message_start") => event.pointer("/message/usage"),
+            Some("message_delta") => event.get("usage"),
+            _ => None,
+        };
+        let Some(usage) = usage.and_then(Value::as_object) else {
+            return;
+        };
+        // Counts in `message_delta` are cumulative for the API call; input
+        // counts are usually left out of it.
+        let input = input_tokens(usage);
+        let output = usage.get("output_tokens").and_then(Value::as_u64);
+        if event.get("type").and_then(Value::as_str) == Some("message_start") {
+            self.call_input = input;
+            self.call_output = output.unwrap_or(0);
+        } else {
+            if input > 0 {
+                self.call_input = input;
+            }
+            if let Some(output) = output {
+                self.call_output = output;
+            }
+        }
+        self.used_tokens = self.call_input + self.call_output;
+        self.streamed_usage = true;
+    }
+
+    fn check(&mut self) -> Option<ContextWindowWarning> {
+        let threshold_tokens = (self.window_tokens as f64 * self.threshold) as u64;
+        if self.warned || self.used_tokens < threshold_tokens {
+            return None;
+        }
+        self.warned = true;
+        Some(ContextWindowWarning {
+            used_tokens: self.used_tokens,
+            window_tokens: self.window_tokens,
+            threshold: self.threshold,
+            will_compact: self.auto_compact,
+        })
+    }
+}
+
+/// Input tokens of a usage object, cached or not.
+fn input_tokens(usage: &Map<String, Value>) -> u64 {
+    [
+        "input_tokens",
+        "cache_creation_input_tokens",
+        "cache_read_input_tokens",
+    ]
+    .iter()
+    .filter_map(|key| usage.get(*key).and_then(Value::as_u64))
+    .sum()
+}
+
+/// Tokens a usage object puts in the context: input plus output.
+fn context_tokens(usage: &Map<String, Value>) -> u64 {
+    input_tokens(usage)
+        + usage
+            .get("output_tokens")
+            .and_then(Value::as_u64)
+            .unwrap_or(0)
+}
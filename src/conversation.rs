@@ -0,0 +1,428 @@
+//! Session transcripts rendered for people.
+//!
+//! A [`Conversation`] collects the messages of a session, either as they are
+//! received or from a JSONL transcript, and exports them as Markdown or a
+//! self-contained HTML page: user turns, assistant text, each tool call
+//! folded into a `<details>` element together with its result, and a footer
+//! with turns, duration and cost taken from the result messages. Attach
+//! [`SessionMetadata`] to title the export and list its tags.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+use crate::error::SdkError;
+use crate::internal::message_parser::parse_message;
+use crate::message::{ContentBlock, Message, ResultMessage, ToolResultBlock, UserMessageContent};
+
+/// Labels an application gives a session; kept by the SDK, not the CLI.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SessionMetadata {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+}
+
+impl SessionMetadata {
+    pub fn new(title: Option<String>, tags: Vec<String>) -> Self {
+        Self { title, tags }
+    }
+}
+
+/// Messages of one session, in order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Conversation {
+    messages: Vec<Message>,
+    metadata: Option<SessionMetadata>,
+}
+
+/// One rendered entry, shared by the Markdown and HTML exports.
+enum Entry<'a> {
+    User(String),
+    Text {
+        text: &'a str,
+        subagent: bool,
+    },
+    Thinking(&'a str),
+    Tool {
+        name: &'a str,
+        input: Value,
+        result: Option<&'a ToolResultBlock>,
+    },
+}
+
+impl Conversation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn from_messages(messages: Vec<Message>) -> Self {
+        Self {
+            messages,
+            metadata: None,
+        }
+    }
+
+    /// Title and tags for the exports.
+    pub fn with_metadata(mut self, metadata: SessionMetadata) -> Self {
+        self.metadata = Some(metadata);
+        self
+    }
+
+    pub fn metadata(&self) -> Option<&SessionMetadata> {
+        self.metadata.as_ref()
+    }
+
+    /// Read a JSONL transcript such as the CLI's `--output-format
+    /// stream-json` output. Lines that are not SDK messages (control
+    /// frames, transcript metadata) are skipped.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let contents = std::fs::read_to_string(path)?;
+        let mut messages = Vec::new();
+        for line in contents.lines().filter(|line| !line.trim().is_empty()) {
+            let raw: Value = serde_json::from_str(line)?;
+            if let Ok(message) = parse_message(&raw) {
+                messages.push(message);
+            }
+        }
+        Ok(Self::from_messages(messages))
+    }
+
+    pub fn push(&mut self, message: Message) {
+        self.messages.push(message);
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
+    /// Session id reported by the first result message.
+    pub fn session_id(&self) -> Option<&str> {
+        self.results()
+            .next()
+            .map(|result| result.session_id.as_str())
+    }
+
+    /// Sum of the reported costs; `None` when no result reported one.
+    pub fn total_cost_usd(&self) -> Option<f64> {
+        self.results()
+            .filter_map(|result| result.total_cost_usd)
+            .fold(None, |total, cost| Some(total.unwrap_or(0.0) + cost))
+    }
+
+    /// The conversation as Markdown.
+    pub fn export_markdown(&self) -> String {
+        let mut out = String::new();
+        let _ = writeln!(out, "# {}", self.title());
+        if let Some(tags) = self.tags() {
+            let tags: Vec<String> = tags.iter().map(|tag| format!("`{tag}`")).collect();
+            let _ = writeln!(out, "\nTags: {}", tags.join(", "));
+        }
+        for entry in self.entries() {
+            out.push('\n');
+            match entry {
+                Entry::User(text) => {
+                    let _ = writeln!(out, "## User\n\n{}", text.trim_end());
+                }
+                Entry::Text { text, subagent } => {
+                    let heading = if subagent {
+                        "Assistant (subagent)"
+                    } else {
+                        "Assistant"
+                    };
+                    let _ = writeln!(out, "## {heading}\n\n{}", text.trim_end());
+                }
+                Entry::Thinking(thinking) => {
+                    let _ = writeln!(
+                        out,
+                        "<details>\n<summary>Thinking</summary>\n\n{}\n\n</details>",
+                        thinking.trim_end()
+                    );
+                }
+                Entry::Tool {
+                    name,
+                    input,
+                    result,
+                } => {
+                    let input = serde_json::to_string_pretty(&input).unwrap_or_default();
+                    let _ = writeln!(
+                        out,
+                        "<details>\n<summary>{}</summary>\n\n**Input**\n\n{}",
+                        tool_summary(name, result),
+                        fenced(&input, "json")
+                    );
+                    if let Some(result) = result {
+                        let _ = writeln!(
+                            out,
+                            "\n**Result**\n\n{}",
+                            fenced(&tool_result_text(result), "")
+                        );
+                    }
+                    out.push_str("\n</details>\n");
+                }
+            }
+        }
+        if let Some(footer) = self.footer() {
+            let _ = writeln!(out, "\n---\n\n_{footer}_");
+        }
+        out
+    }
+
+    /// The conversation as a standalone HTML page.
+    pub fn export_html(&self) -> String {
+        let title = escape_html(&self.title());
+        let mut out = format!(
+            "<!DOCTYPE html>\n<html>\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+             <style>{HTML_STYLE}</style>\n</head>\n<body>\n<h1>{title}</h1>\n"
+        );
+        if let Some(tags) = self.tags() {
+            out.push_str("<p class=\"tags\">");
+            for tag in tags {
+                let _ = write!(out, "<span class=\"tag\">{}</span>", escape_html(tag));
+            }
+            out.push_str("</p>\n");
+        }
+        for entry in self.entries() {
+            match entry {
+                Entry::User(text) => {
+                    let _ = writeln!(
+                        out,
+                        "<section class=\"user\"><h2>User</h2><div class=\"text\">{}</div></section>",
+                        escape_html(text.trim_end())
+                    );
+                }
+                Entry::Text { text, subagent } => {
+                    let heading = if subagent {
+                        "Assistant (subagent)"
+                    } else {
+                        "Assistant"
+                    };
+                    let _ = writeln!(
+                        out,
+                        "<section class=\"assistant\"><h2>{heading}</h2><div class=\"text\">{}</div></section>",
+                        escape_html(text.trim_end())
+                    );
+                }
+                Entry::Thinking(thinking) => {
+                    let _ = writeln!(
+                        out,
+                        "<details class=\"thinking\"><summary>Thinking</summary><div class=\"text\">{}</div></details>",
+                        escape_html(thinking.trim_end())
+                    );
+                }
+                Entry::Tool {
+                    name,
+                    input,
+                    result,
+                } => {
+                    let input = serde_json::to_string_pretty(&input).unwrap_or_default();
+                    let _ = write!(
+                        out,
+                        "<details class=\"tool\"><summary>{}</summary><h3>Input</h3><pre>{}</pre>",
+                        escape_html(&tool_summary(name, result)),
+                        escape_html(&input)
+                    );
+                    if let Some(result) = result {
+                        let _ = write!(
+                            out,
+                            "<h3>Result</h3><pre>{}</pre>",
+                            escape_html(&tool_result_text(result))
+                        );
+                    }
+                    out.push_str("</details>\n");
+                }
+            }
+        }
+        if let Some(footer) = self.footer() {
+            let _ = writeln!(out, "<footer>{}</footer>", escape_html(&footer));
+        }
+        out.push_str("</body>\n</html>\n");
+        out
+    }
+
+    fn results(&self) -> impl Iterator<Item = &ResultMessage> {
+        self.messages.iter().filter_map(|message| match message {
+            Message::Result(result) => Some(result),
+            _ => None,
+        })
+    }
+
+    fn title(&self) -> String {
+        if let Some(title) = self.metadata.as_ref().and_then(|meta| meta.title.clone()) {
+            return title;
+        }
+        match self.session_id() {
+            Some(session_id) => format!("Session {session_id}"),
+            None => "Conversation".to_string(),
+        }
+    }
+
+    fn tags(&self) -> Option<&[String]> {
+        self.metadata
+            .as_ref()
+            .map(|meta| meta.tags.as_slice())
+            .filter(|tags| !tags.is_empty())
+    }
+
+    fn entries(&self) -> Vec<Entry<'_>> {
+        let results: HashMap<&str, &ToolResultBlock> = self
+            .messages
+            .iter()
+            .filter_map(|message| match message {
+                Message::User(user) => match &user.content {
+                    UserMessageContent::Blocks(blocks) => Some(blocks),
+                    UserMessageContent::Text(_) => None,
+                },
+                _ => None,
+            })
+            .flatten()
+            .filter_map(|block| match block {
+                ContentBlock::ToolResult(result) => Some((result.tool_use_id.as_str(), result)),
+                _ => None,
+            })
+            .collect();
+
+        let mut entries = Vec::new();
+        for message in &self.messages {
+            match message {
+                // Tool results are shown with their calls; subagent prompts
+                // are the Task tool's input.
+                Message::User(user) if user.parent_tool_use_id.is_none() => {
+                    let text = match &user.content {
+                        UserMessageContent::Text(text) => text.clone(),
+                        UserMessageContent::Blocks(blocks) => blocks
+                            .iter()
+                            .filter_map(|block| match block {
+                                ContentBlock::Text(text) => Some(text.text.as_str()),
+                                _ => None,
+                            })
+                            .collect::<Vec<_>>()
+                            .join("\n\n"),
+                    };
+                    if !text.trim().is_empty() {
+                        entries.push(Entry::User(text));
+                    }
+                }
+                Message::Assistant(assistant) => {
+                    let subagent = assistant.parent_tool_use_id.is_some();
+                    for block in &assistant.content {
+                        match block {
+                            ContentBlock::Text(text) if !text.text.trim().is_empty() => entries
+                                .push(Entry::Text {
+                                    text: &text.text,
+                                    subagent,
+                                }),
+                            ContentBlock::Thinking(thinking) => {
+                                entries.push(Entry::Thinking(&thinking.thinking))
+                            }
+                            ContentBlock::ToolUse(tool) => entries.push(Entry::Tool {
+                                name: &tool.name,
+                                input: Value::Object(tool.input.clone()),
+                                result: results.get(tool.id.as_str()).copied(),
+                            }),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        entries
+    }
+
+    fn footer(&self) -> Option<String> {
+        let mut results = self.results().peekable();
+        results.peek()?;
+        let (mut turns, mut duration_ms, mut errors) = (0, 0, 0);
+        for result in results {
+            turns += result.num_turns;
+            duration_ms += result.duration_ms;
+            errors += usize::from(result.is_error);
+        }
+
+        let mut footer = format!(
+            "{turns} turn{} · {:.1}s",
+            if turns == 1 { "" } else { "s" },
+            duration_ms as f64 / 1000.0
+        );
+        if let Some(cost) = self.total_cost_usd() {
+            let _ = write!(footer, " · ${cost:.4}");
+        }
+        if errors > 0 {
+            let _ = write!(footer, " · ended with an error");
+        }
+        Some(footer)
+    }
+}
+
+impl FromIterator<Message> for Conversation {
+    fn from_iter<I: IntoIterator<Item = Message>>(iter: I) -> Self {
+        Self::from_messages(iter.into_iter().collect())
+    }
+}
+
+impl Extend<Message> for Conversation {
+    fn extend<I: IntoIterator<Item = Message>>(&mut self, iter: I) {
+        self.messages.extend(iter);
+    }
+}
+
+const HTML_STYLE: &str =
+    "body{font-family:system-ui,sans-serif;max-width:50rem;margin:2rem auto;padding:0 1rem}\
+section{margin:1rem 0}.user h2{color:#1d4ed8}.assistant h2{color:#047857}\
+.text{white-space:pre-wrap}pre{background:#f3f4f6;padding:.5rem;overflow-x:auto}\
+details{margin:.5rem 0;border-left:3px solid #d1d5db;padding-left:.5rem}\
+footer{margin-top:2rem;color:#6b7280}\
+.tag{background:#e5e7eb;border-radius:.25rem;padding:0 .4rem;margin-right:.3rem}";
+
+fn tool_summary(name: &str, result: Option<&ToolResultBlock>) -> String {
+    match result {
+        Some(result) if result.is_error == Some(true) => format!("Tool: {name} (error)"),
+        Some(_) => format!("Tool: {name}"),
+        None => format!("Tool: {name} (no result)"),
+    }
+}
+
+fn tool_result_text(result: &ToolResultBlock) -> String {
+    match &result.content {
+        None | Some(Value::Null) => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .map(|block| match block.get("text").and_then(Value::as_str) {
+                Some(text) => text.to_string(),
+                None => format!(
+                    "[{}]",
+                    block.get("type").and_then(Value::as_str).unwrap_or("block")
+                ),
+            })
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => serde_json::to_string_pretty(other).unwrap_or_default(),
+    }
+}
+
+/// Code fence longer than any backtick run inside `content`.
+fn fenced(content: &str, language: &str) -> String {
+    let longest = content.split(|c| c != '`').map(str::len).max().unwrap_or(0);
+    let fence = "`".repeat(longest.max(2) + 1);
+    format!("{fence}{language}\n{}\n{fence}", content.trim_end())
+}
+
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,259 @@
+//! Credential sources resolved each time the CLI is started.
+//!
+//! A [`CredentialProvider`] returns environment variables for the CLI
+//! process, such as `ANTHROPIC_API_KEY` or the AWS/GCP variables of a
+//! [`Provider`]. Set one on
+//! [`ClaudeAgentOptions::credential_provider`](crate::config::ClaudeAgentOptions::credential_provider)
+//! and it is asked again on every connect, so a rotated key is picked up by
+//! the next session without restarting the service. Its variables take
+//! precedence over the process environment and
+//! [`ClaudeAgentOptions::env`](crate::config::ClaudeAgentOptions::env).
+//!
+//! Built-in sources read the process environment ([`EnvCredentials`]), a
+//! `.env` file ([`DotenvCredentials`], with the `dotenvy` feature) and the OS
+//! keychain
+//! ([`KeychainCredentials`], not on wasm32); closures returning a future work
+//! as callbacks, and [`CredentialChain`] layers several sources.
+
+use std::collections::HashMap;
+use std::future::Future;
+#[cfg(feature = "dotenvy")]
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::Arc;
+
+use crate::env::Provider;
+use crate::error::SdkError;
+
+/// Environment variables supplied by a credential source.
+pub type Credentials = HashMap<String, String>;
+
+/// Boxed future returned by credential providers.
+pub type CredentialFuture = Pin<Box<dyn Future<Output = Result<Credentials, SdkError>> + Send>>;
+
+/// Source of credentials for the CLI process.
+pub trait CredentialProvider: Send + Sync {
+    fn credentials(&self) -> CredentialFuture;
+}
+
+impl<F, Fut> CredentialProvider for F
+where
+    F: Fn() -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Credentials, SdkError>> + Send + 'static,
+{
+    fn credentials(&self) -> CredentialFuture {
+        Box::pin(self())
+    }
+}
+
+/// Convenient handle for storing credential providers.
+pub type CredentialProviderHandle = Arc<dyn CredentialProvider>;
+
+/// Credentials from the process environment: the `ANTHROPIC_*` variables
+/// plus those of the [`Provider`] selected there, read at call time.
+#[derive(Debug, Clone, Default)]
+pub struct EnvCredentials {
+    extra: Vec<String>,
+}
+
+impl EnvCredentials {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Also pass `name` through when it is set.
+    pub fn with_var(mut self, name: impl Into<String>) -> Self {
+        self.extra.push(name.into());
+        self
+    }
+
+    pub fn read(&self) -> Credentials {
+        let names = Provider::Anthropic
+            .env_vars()
+            .iter()
+            .chain(Provider::from_env().env_vars())
+            .copied()
+            .chain(self.extra.iter().map(String::as_str));
+        names
+            .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+            .collect()
+    }
+}
+
+impl CredentialProvider for EnvCredentials {
+    fn credentials(&self) -> CredentialFuture {
+        let credentials = self.read();
+        Box::pin(async move { Ok(credentials) })
+    }
+}
+
+#[cfg(feature = "dotenvy")]
+/// Every variable in a `.env` file, re-read on each call. Unlike
+/// [`load_env`](crate::env::load_env) it leaves the process environment
+/// untouched. A missing file yields no credentials.
+#[derive(Debug, Clone)]
+pub struct DotenvCredentials {
+    path: PathBuf,
+}
+
+#[cfg(feature = "dotenvy")]
+impl DotenvCredentials {
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        Self { path: path.into() }
+    }
+
+    pub fn read(&self) -> Result<Credentials, SdkError> {
+        let entries = match dotenvy::from_path_iter(&self.path) {
+            Ok(entries) => entries,
+            Err(dotenvy::Error::Io(err)) if err.kind() == std::io::ErrorKind::NotFound => {
+                return Ok(Credentials::new())
+            }
+            Err(err) => return Err(dotenv_error(&self.path, err)),
+        };
+        entries
+            .map(|entry| entry.map_err(|err| dotenv_error(&self.path, err)))
+            .collect()
+    }
+}
+
+#[cfg(feature = "dotenvy")]
+fn dotenv_error(path: &std::path::Path, err: dotenvy::Error) -> SdkError {
+    SdkError::Credentials(format!("failed to read {}: {err}", path.display()))
+}
+
+#[cfg(feature = "dotenvy")]
+impl CredentialProvider for DotenvCredentials {
+    fn credentials(&self) -> CredentialFuture {
+        let credentials = self.read();
+        Box::pin(async move { credentials })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+/// A secret stored in the OS keychain, exposed as one variable.
+///
+/// Uses `security` on macOS and `secret-tool` (libsecret) on Linux; other
+/// platforms report [`SdkError::Credentials`].
+#[derive(Debug, Clone)]
+pub struct KeychainCredentials {
+    service: String,
+    account: String,
+    var: String,
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl KeychainCredentials {
+    /// Look up the password stored for `service` and `account` and pass it
+    /// as `ANTHROPIC_API_KEY`.
+    pub fn new(service: impl Into<String>, account: impl Into<String>) -> Self {
+        Self {
+            service: service.into(),
+            account: account.into(),
+            var: "ANTHROPIC_API_KEY".into(),
+        }
+    }
+
+    /// Pass the secret as `var` instead.
+    pub fn with_var(mut self, var: impl Into<String>) -> Self {
+        self.var = var.into();
+        self
+    }
+
+    fn command(&self) -> Option<tokio::process::Command> {
+        let (program, args): (&str, [&str; 5]) = if cfg!(target_os = "macos") {
+            (
+                "security",
+                [
+                    "find-generic-password",
+                    "-s",
+                    &self.service,
+                    "-a",
+                    &self.account,
+                ],
+            )
+        } else if cfg!(target_os = "linux") {
+            (
+                "secret-tool",
+                ["lookup", "service", &self.service, "account", &self.account],
+            )
+        } else {
+            return None;
+        };
+        let mut command = tokio::process::Command::new(program);
+        command.args(args);
+        if cfg!(target_os = "macos") {
+            command.arg("-w");
+        }
+        Some(command)
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+impl CredentialProvider for KeychainCredentials {
+    fn credentials(&self) -> CredentialFuture {
+        let command = self.command();
+        let this = self.clone();
+        Box::pin(async move {
+            let Some(mut command) = command else {
+                return Err(SdkError::Credentials(
+                    "OS keychain lookup is not supported on this platform".into(),
+                ));
+            };
+            let output = command.output().await.map_err(|err| {
+                SdkError::Credentials(format!("failed to run the keychain tool: {err}"))
+            })?;
+            let secret = String::from_utf8_lossy(&output.stdout)
+                .trim_end_matches(['\r', '\n'])
+                .to_string();
+            if !output.status.success() || secret.is_empty() {
+                return Err(SdkError::Credentials(format!(
+                    "no keychain entry for service '{}' and account '{}'",
+                    this.service, this.account
+                )));
+            }
+            Ok(Credentials::from([(this.var, secret)]))
+        })
+    }
+}
+
+/// Several providers merged in order; later ones win on conflicts.
+#[derive(Clone, Default)]
+pub struct CredentialChain {
+    providers: Vec<CredentialProviderHandle>,
+}
+
+impl std::fmt::Debug for CredentialChain {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CredentialChain")
+            .field("providers", &self.providers.len())
+            .finish()
+    }
+}
+
+impl CredentialChain {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, provider: impl CredentialProvider + 'static) -> Self {
+        self.providers.push(Arc::new(provider));
+        self
+    }
+}
+
+impl CredentialProvider for CredentialChain {
+    fn credentials(&self) -> CredentialFuture {
+        let futures: Vec<_> = self
+            .providers
+            .iter()
+            .map(|provider| provider.credentials())
+            .collect();
+        Box::pin(async move {
+            let mut credentials = Credentials::new();
+            for future in futures {
+                credentials.extend(future.await?);
+            }
+            Ok(credentials)
+        })
+    }
+}
@@ -0,0 +1,138 @@
+//! Self-contained snapshot of a session for attaching to bug reports.
+//!
+//! See [`ClaudeSdkClient::capture_debug_bundle`](crate::client::ClaudeSdkClient::capture_debug_bundle).
+//! A bundle is written as a directory:
+//!
+//! | File | Contents |
+//! | --- | --- |
+//! | `manifest.json` | SDK version, capture time, session id, CLI path/version, redacted argv |
+//! | `options.json` | Resolved [`ClaudeAgentOptions`] with environment values redacted |
+//! | `transcript.jsonl` | Recent conversation messages in both directions, redacted |
+//! | `control_frames.jsonl` | Recent control protocol exchanges |
+//! | `stderr.log` | Recent CLI stderr lines |
+
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde_json::{json, Value};
+
+use crate::config::ClaudeAgentOptions;
+use crate::error::{ConnectionDiagnostics, SdkError};
+use crate::frame_log::{ControlFrameRecord, FrameDirection, FrameOutcome};
+
+const REDACTED: &str = "<redacted>";
+const SECRET_MARKERS: &[&str] = &["key", "token", "secret", "password", "credential", "auth"];
+
+/// Everything collected for a debug bundle.
+#[derive(Debug, Clone)]
+pub struct DebugBundle {
+    /// Options as JSON, already redacted.
+    pub options: Value,
+    pub diagnostics: Option<ConnectionDiagnostics>,
+    pub session_id: Option<String>,
+    pub transcript: Vec<Value>,
+    pub control_frames: Vec<ControlFrameRecord>,
+    pub stderr: Vec<String>,
+    pub captured_at: SystemTime,
+}
+
+impl DebugBundle {
+    /// Write the bundle into `dir`, creating it if needed.
+    pub fn write_to(&self, dir: &Path) -> Result<(), SdkError> {
+        fs::create_dir_all(dir)?;
+
+        let diagnostics = self.diagnostics.as_ref();
+        let manifest = json!({
+            "sdk_version": env!("CARGO_PKG_VERSION"),
+            "captured_at_ms": unix_millis(self.captured_at),
+            "session_id": self.session_id,
+            "cli_path": diagnostics.and_then(|d| d.cli_path.as_ref()),
+            "cli_version": diagnostics.and_then(|d| d.cli_version.as_ref()),
+            "cwd": diagnostics.and_then(|d| d.cwd.as_ref()),
+            "argv": diagnostics.map(|d| d.argv.clone()).unwrap_or_default(),
+        });
+        fs::write(
+            dir.join("manifest.json"),
+            serde_json::to_vec_pretty(&manifest)?,
+        )?;
+        fs::write(
+            dir.join("options.json"),
+            serde_json::to_vec_pretty(&self.options)?,
+        )?;
+        write_jsonl(
+            &dir.join("transcript.jsonl"),
+            self.transcript.iter().cloned(),
+        )?;
+        write_jsonl(
+            &dir.join("control_frames.jsonl"),
+            self.control_frames.iter().map(frame_to_json),
+        )?;
+
+        let mut stderr = self.stderr.join("\n");
+        if !stderr.is_empty() {
+            stderr.push('\n');
+        }
+        fs::write(dir.join("stderr.log"), stderr)?;
+        Ok(())
+    }
+}
+
+/// Options as JSON with `env` values, secret-looking `extra_args` values and
+/// anything the [effective redactor](ClaudeAgentOptions::effective_redactor) matches hidden.
+pub fn redact_options(options: &ClaudeAgentOptions) -> Result<Value, SdkError> {
+    let mut value = serde_json::to_value(options)?;
+    if let Some(env) = value.get_mut("env").and_then(Value::as_object_mut) {
+        for entry in env.values_mut() {
+            *entry = Value::String(REDACTED.to_string());
+        }
+    }
+    if let Some(extra_args) = value.get_mut("extra_args").and_then(Value::as_object_mut) {
+        for (flag, entry) in extra_args.iter_mut() {
+            let flag = flag.to_ascii_lowercase();
+            if !entry.is_null() && SECRET_MARKERS.iter().any(|marker| flag.contains(marker)) {
+                *entry = Value::String(REDACTED.to_string());
+            }
+        }
+    }
+    options.effective_redactor().redact_value(&mut value);
+    Ok(value)
+}
+
+fn write_jsonl(path: &Path, values: impl Iterator<Item = Value>) -> Result<(), SdkError> {
+    let mut file = fs::File::create(path)?;
+    for value in values {
+        serde_json::to_writer(&mut file, &value)?;
+        file.write_all(b"\n")?;
+    }
+    Ok(())
+}
+
+fn frame_to_json(record: &ControlFrameRecord) -> Value {
+    let outcome = match &record.outcome {
+        FrameOutcome::Success => json!("success"),
+        FrameOutcome::Error(message) => json!({ "error": message }),
+        FrameOutcome::Timeout => json!("timeout"),
+        FrameOutcome::Cancelled => json!("cancelled"),
+    };
+    json!({
+        "request_id": record.request_id,
+        "subtype": record.subtype,
+        "direction": match record.direction {
+            FrameDirection::Outbound => "outbound",
+            FrameDirection::Inbound => "inbound",
+        },
+        "started_at_ms": unix_millis(record.started_at),
+        "latency_ms": record.latency.as_secs_f64() * 1000.0,
+        "outcome": outcome,
+        "request": record.request,
+        "response": record.response,
+    })
+}
+
+fn unix_millis(time: SystemTime) -> u128 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis())
+        .unwrap_or(0)
+}
@@ -1,8 +1,145 @@
 //! Environment configuration helpers for loading API credentials from .env files.
+//!
+//! These read credentials once, into the process environment. To resolve them
+//! on every connect instead, e.g. to pick up rotated keys, set a
+//! [`CredentialProvider`](crate::credentials::CredentialProvider) on
+//! [`ClaudeAgentOptions::credential_provider`](crate::config::ClaudeAgentOptions::credential_provider).
 
 use std::collections::HashMap;
-use std::path::Path;
+#[cfg(feature = "dotenvy")]
+use std::path::{Path, PathBuf};
 
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::client::DynTransport;
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message};
+
+const USE_BEDROCK: &str = "CLAUDE_CODE_USE_BEDROCK";
+const USE_VERTEX: &str = "CLAUDE_CODE_USE_VERTEX";
+
+/// Where the CLI sends model requests.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    /// The Anthropic API.
+    #[default]
+    Anthropic,
+    /// Amazon Bedrock (`CLAUDE_CODE_USE_BEDROCK`).
+    Bedrock,
+    /// Google Vertex AI (`CLAUDE_CODE_USE_VERTEX`).
+    Vertex,
+}
+
+impl Provider {
+    /// Provider selected by `CLAUDE_CODE_USE_BEDROCK` / `CLAUDE_CODE_USE_VERTEX`
+    /// in the process environment.
+    pub fn from_env() -> Self {
+        let enabled = |name| std::env::var(name).is_ok_and(|value| is_truthy(&value));
+        if enabled(USE_BEDROCK) {
+            Provider::Bedrock
+        } else if enabled(USE_VERTEX) {
+            Provider::Vertex
+        } else {
+            Provider::Anthropic
+        }
+    }
+
+    /// Variables the CLI cannot run this provider without.
+    pub fn required_vars(self) -> &'static [&'static str] {
+        match self {
+            Provider::Anthropic => &[],
+            Provider::Bedrock => &["AWS_REGION"],
+            Provider::Vertex => &["CLOUD_ML_REGION", "ANTHROPIC_VERTEX_PROJECT_ID"],
+        }
+    }
+
+    /// Variables passed through to the CLI for this provider.
+    pub fn env_vars(self) -> &'static [&'static str] {
+        match self {
+            Provider::Anthropic => &["ANTHROPIC_API_KEY", "ANTHROPIC_BASE_URL", "ANTHROPIC_MODEL"],
+            Provider::Bedrock => &[
+                USE_BEDROCK,
+                "AWS_REGION",
+                "AWS_PROFILE",
+                "AWS_ACCESS_KEY_ID",
+                "AWS_SECRET_ACCESS_KEY",
+                "AWS_SESSION_TOKEN",
+                "AWS_BEARER_TOKEN_BEDROCK",
+                "ANTHROPIC_BEDROCK_BASE_URL",
+                "CLAUDE_CODE_SKIP_BEDROCK_AUTH",
+                "ANTHROPIC_MODEL",
+            ],
+            Provider::Vertex => &[
+                USE_VERTEX,
+                "CLOUD_ML_REGION",
+                "ANTHROPIC_VERTEX_PROJECT_ID",
+                "GOOGLE_APPLICATION_CREDENTIALS",
+                "ANTHROPIC_VERTEX_BASE_URL",
+                "CLAUDE_CODE_SKIP_VERTEX_AUTH",
+                "ANTHROPIC_MODEL",
+            ],
+        }
+    }
+
+    /// Check that `env`, or else the process environment, sets every
+    /// [`required_vars`](Provider::required_vars) entry.
+    pub fn validate(self, env: &HashMap<String, String>) -> Result<(), EnvError> {
+        let missing: Vec<String> = self
+            .required_vars()
+            .iter()
+            .filter(|name| {
+                let set = |value: &str| !value.trim().is_empty();
+                !env.get(**name).is_some_and(|value| set(value))
+                    && !std::env::var(name).is_ok_and(|value| set(&value))
+            })
+            .map(|name| name.to_string())
+            .collect();
+        if missing.is_empty() {
+            Ok(())
+        } else {
+            Err(EnvError::MissingVars {
+                provider: self,
+                missing,
+            })
+        }
+    }
+
+    /// Set the provider switch in a CLI environment, clearing the other one.
+    pub fn apply_to_env(self, env: &mut HashMap<String, String>) {
+        env.remove(USE_BEDROCK);
+        env.remove(USE_VERTEX);
+        match self {
+            Provider::Anthropic => {}
+            Provider::Bedrock => {
+                env.insert(USE_BEDROCK.to_string(), "1".to_string());
+            }
+            Provider::Vertex => {
+                env.insert(USE_VERTEX.to_string(), "1".to_string());
+            }
+        }
+    }
+}
+
+impl std::fmt::Display for Provider {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            Provider::Anthropic => "anthropic",
+            Provider::Bedrock => "bedrock",
+            Provider::Vertex => "vertex",
+        })
+    }
+}
+
+fn is_truthy(value: &str) -> bool {
+    matches!(
+        value.trim().to_ascii_lowercase().as_str(),
+        "1" | "true" | "yes" | "on"
+    )
+}
+
+#[cfg(feature = "dotenvy")]
 /// Loads environment variables from a .env file in the specified directory.
 /// Falls back to the current directory if no path is provided.
 ///
@@ -17,15 +154,10 @@ use std::path::Path;
 /// let env_vars = load_env(None).unwrap();
 ///
 /// // Load from specific path
-/// let env_vars = load_env(Some("/path/to/project")).unwrap();
+/// let env_vars = load_env(Some(std::path::Path::new("/path/to/project"))).unwrap();
 /// ```
 pub fn load_env(dir: Option<&Path>) -> Result<HashMap<String, String>, EnvError> {
-    let env_path = match dir {
-        Some(d) => d.join(".env"),
-        None => std::env::current_dir()
-            .map_err(|e| EnvError::Io(e.to_string()))?
-            .join(".env"),
-    };
+    let env_path = env_dir(dir)?.join(".env");
 
     if env_path.exists() {
         dotenvy::from_path(&env_path).map_err(|e| EnvError::Parse(e.to_string()))?;
@@ -34,26 +166,73 @@ pub fn load_env(dir: Option<&Path>) -> Result<HashMap<String, String>, EnvError>
     Ok(get_anthropic_env())
 }
 
-/// Returns a HashMap with ANTHROPIC_* environment variables.
-/// Use this to pass credentials to ClaudeAgentOptions.env.
-pub fn get_anthropic_env() -> HashMap<String, String> {
-    let mut env = HashMap::new();
-
-    if let Ok(key) = std::env::var("ANTHROPIC_API_KEY") {
-        env.insert("ANTHROPIC_API_KEY".to_string(), key);
+#[cfg(feature = "dotenvy")]
+/// Like [`load_env`], but first loads `.env.<profile>` from the same
+/// directory, e.g. `.env.staging` for one Anthropic org and `.env.prod` for
+/// another.
+///
+/// Variables already set in the process win over the profile file, which
+/// wins over the base `.env`. The profile file must exist.
+///
+/// # Example
+/// ```no_run
+/// use sdk_claude_rust::env::load_env_profile;
+///
+/// let env_vars = load_env_profile(None, "staging").unwrap();
+/// ```
+pub fn load_env_profile(
+    dir: Option<&Path>,
+    profile: &str,
+) -> Result<HashMap<String, String>, EnvError> {
+    if profile.is_empty()
+        || !profile
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+        || profile.starts_with('.')
+    {
+        return Err(EnvError::Parse(format!(
+            "invalid env profile name {profile:?}"
+        )));
     }
-
-    if let Ok(url) = std::env::var("ANTHROPIC_BASE_URL") {
-        env.insert("ANTHROPIC_BASE_URL".to_string(), url);
+    let profile_path = env_dir(dir)?.join(format!(".env.{profile}"));
+    if !profile_path.is_file() {
+        return Err(EnvError::Io(format!(
+            "env profile file {} not found",
+            profile_path.display()
+        )));
     }
+    // dotenvy never overrides variables that are already set, so loading the
+    // profile before the base file gives the documented precedence.
+    dotenvy::from_path(&profile_path).map_err(|e| EnvError::Parse(e.to_string()))?;
+    load_env(dir)
+}
 
-    if let Ok(model) = std::env::var("ANTHROPIC_MODEL") {
-        env.insert("ANTHROPIC_MODEL".to_string(), model);
+#[cfg(feature = "dotenvy")]
+fn env_dir(dir: Option<&Path>) -> Result<PathBuf, EnvError> {
+    match dir {
+        Some(d) => Ok(d.to_path_buf()),
+        None => std::env::current_dir().map_err(|e| EnvError::Io(e.to_string())),
     }
+}
 
-    env
+/// Returns a HashMap with ANTHROPIC_* environment variables, plus the
+/// AWS/GCP variables of the provider selected by [`Provider::from_env`].
+/// Use this to pass credentials to ClaudeAgentOptions.env.
+pub fn get_anthropic_env() -> HashMap<String, String> {
+    crate::credentials::EnvCredentials::new().read()
 }
 
+/// Returns a HashMap with the [`Provider::env_vars`] set in the process
+/// environment.
+pub fn get_provider_env(provider: Provider) -> HashMap<String, String> {
+    provider
+        .env_vars()
+        .iter()
+        .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+        .collect()
+}
+
+#[cfg(feature = "dotenvy")]
 /// Creates ClaudeAgentOptions with environment variables loaded from .env.
 /// This is a convenience function that combines load_env with options creation.
 ///
@@ -65,17 +244,238 @@ pub fn get_anthropic_env() -> HashMap<String, String> {
 /// // options.env now contains ANTHROPIC_API_KEY, ANTHROPIC_BASE_URL, etc.
 /// ```
 pub fn options_from_env(dir: Option<&Path>) -> Result<crate::config::ClaudeAgentOptions, EnvError> {
-    let env_vars = load_env(dir)?;
+    Ok(options_with_env(load_env(dir)?))
+}
 
-    let mut options = crate::config::ClaudeAgentOptions::default();
-    options.env = env_vars;
+#[cfg(feature = "dotenvy")]
+/// [`options_from_env`] for a [`load_env_profile`] profile.
+pub fn options_from_env_profile(
+    dir: Option<&Path>,
+    profile: &str,
+) -> Result<crate::config::ClaudeAgentOptions, EnvError> {
+    Ok(options_with_env(load_env_profile(dir, profile)?))
+}
+
+pub(crate) fn options_with_env(
+    env_vars: HashMap<String, String>,
+) -> crate::config::ClaudeAgentOptions {
+    let provider = Provider::from_env();
+    let mut options = crate::config::ClaudeAgentOptions {
+        env: env_vars,
+        provider: (provider != Provider::Anthropic).then_some(provider),
+        ..Default::default()
+    };
 
     // Also set model if provided
     if let Ok(model) = std::env::var("ANTHROPIC_MODEL") {
         options.model = Some(model);
     }
 
-    Ok(options)
+    options
+}
+
+/// Variables that carry an API key or login token.
+const KEY_VARS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "ANTHROPIC_AUTH_TOKEN",
+    "CLAUDE_CODE_OAUTH_TOKEN",
+];
+
+const PREFLIGHT_PROMPT: &str = "Reply with the single word OK.";
+
+/// Outcome of [`validate_credentials`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CredentialStatus {
+    /// The CLI completed an authenticated request.
+    Valid {
+        /// Model that answered, when reported.
+        model: Option<String>,
+    },
+    /// No API key is configured and the CLI is not logged in.
+    MissingKey,
+    /// The key or login was rejected.
+    InvalidKey { message: String },
+    /// The account is authenticated but cannot use the model, e.g. because
+    /// of billing, model access or a disabled organization.
+    InsufficientEntitlements { message: String },
+}
+
+impl CredentialStatus {
+    pub fn is_valid(&self) -> bool {
+        matches!(self, CredentialStatus::Valid { .. })
+    }
+}
+
+/// Check that `options` can authenticate before starting a long run.
+///
+/// Sends a one-turn query asking for a one-word reply, so it costs a few
+/// tokens. The probe keeps only the CLI path, environment, credentials, model
+/// and working directory from `options`, so no callbacks, hooks, tools or MCP
+/// servers run. Failures that are not about credentials, such as a missing CLI,
+/// are returned as errors.
+///
+/// # Example
+/// ```no_run
+/// # async fn example() -> Result<(), sdk_claude_rust::error::SdkError> {
+/// use sdk_claude_rust::env::{options_from_env, validate_credentials};
+///
+/// let options = options_from_env(None).unwrap();
+/// let status = validate_credentials(&options).await?;
+/// if !status.is_valid() {
+///     eprintln!("cannot start: {status:?}");
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub async fn validate_credentials(
+    options: &crate::config::ClaudeAgentOptions,
+) -> Result<CredentialStatus, SdkError> {
+    validate_credentials_with(options, None).await
+}
+
+/// [`validate_credentials`] over an explicit transport.
+pub async fn validate_credentials_with(
+    options: &crate::config::ClaudeAgentOptions,
+    transport: Option<DynTransport>,
+) -> Result<CredentialStatus, SdkError> {
+    let has_key = has_credentials(options).await?;
+    let options = probe_options(options).await?;
+
+    let stream = match crate::query::query(PREFLIGHT_PROMPT, Some(options), transport).await {
+        Ok(stream) => stream,
+        Err(err) => return classify_error(err, has_key),
+    };
+    futures::pin_mut!(stream);
+
+    let mut model = None;
+    let mut failure = None;
+    while let Some(message) = stream.next().await {
+        match message {
+            Ok(Message::Assistant(assistant)) => {
+                // The CLI reports request failures as synthetic replies.
+                if assistant.model == "<synthetic>" {
+                    let text: String = assistant
+                        .content
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text(block) => Some(block.text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    failure = failure.or_else(|| classify_failure(&text, has_key));
+                } else {
+                    model = Some(assistant.model);
+                }
+            }
+            Ok(Message::Result(result)) => {
+                if result.is_error {
+                    let text = result.result.as_deref().unwrap_or_default();
+                    failure = failure.or_else(|| classify_failure(text, has_key));
+                }
+                return Ok(failure.unwrap_or(CredentialStatus::Valid { model }));
+            }
+            Ok(_) => {}
+            Err(err) => return classify_error(err, has_key),
+        }
+    }
+    failure.ok_or_else(|| SdkError::Message("CLI exited without a result".into()))
+}
+
+/// Whether any source configures a key, login token or cloud provider.
+/// One-turn options for the preflight probe.
+///
+/// Only the CLI, environment, model and working directory carry over, with
+/// credential and provider settings resolved into the environment; the
+/// caller's callbacks, hooks, tools, MCP servers and permission settings must
+/// not run for a probe.
+async fn probe_options(
+    options: &crate::config::ClaudeAgentOptions,
+) -> Result<crate::config::ClaudeAgentOptions, SdkError> {
+    let mut env = options.env.clone();
+    if let Some(credentials) = &options.credential_provider {
+        env.extend(credentials.credentials().await?);
+    }
+    if let Some(provider) = options.provider {
+        provider
+            .validate(&env)
+            .map_err(|err| SdkError::InvalidConfig(err.to_string()))?;
+        provider.apply_to_env(&mut env);
+    }
+    Ok(crate::config::ClaudeAgentOptions {
+        cli_path: options.cli_path.clone(),
+        env,
+        model: options.model.clone(),
+        cwd: options.cwd.clone(),
+        max_turns: Some(1),
+        can_use_tool: None,
+        hooks: None,
+        allowed_tools: Vec::new(),
+        disallowed_tools: Vec::new(),
+        sdk_servers: HashMap::new(),
+        mcp_servers: crate::config::McpServers::default(),
+        permission_mode: None,
+        permission_prompt_tool_name: None,
+        ..Default::default()
+    })
+}
+
+async fn has_credentials(options: &crate::config::ClaudeAgentOptions) -> Result<bool, SdkError> {
+    let mut env = options.env.clone();
+    if let Some(credentials) = &options.credential_provider {
+        env.extend(credentials.credentials().await?);
+    }
+    let set = |name: &str| {
+        env.get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+            .is_some_and(|value| !value.trim().is_empty())
+    };
+    let cloud = options.provider.unwrap_or_else(Provider::from_env) != Provider::Anthropic
+        || [USE_BEDROCK, USE_VERTEX]
+            .iter()
+            .any(|name| env.get(*name).is_some_and(|value| is_truthy(value)));
+    Ok(cloud || KEY_VARS.iter().any(|name| set(name)))
+}
+
+fn classify_error(err: SdkError, has_key: bool) -> Result<CredentialStatus, SdkError> {
+    match &err {
+        SdkError::Process(_) | SdkError::CliConnection(_) => {
+            classify_failure(&err.to_string(), has_key).ok_or(err)
+        }
+        _ => Err(err),
+    }
+}
+
+fn classify_failure(text: &str, has_key: bool) -> Option<CredentialStatus> {
+    let lower = text.to_ascii_lowercase();
+    let contains = |needles: &[&str]| needles.iter().any(|needle| lower.contains(needle));
+    let message = text.trim().to_string();
+    if contains(&[
+        "credit balance",
+        "permission_error",
+        "does not have access",
+        "not have permission",
+        "billing",
+        "organization has been disabled",
+        "quota",
+    ]) {
+        Some(CredentialStatus::InsufficientEntitlements { message })
+    } else if contains(&[
+        "invalid api key",
+        "invalid x-api-key",
+        "authentication_error",
+        "/login",
+        "oauth token has expired",
+        "unauthorized",
+    ]) {
+        Some(if has_key {
+            CredentialStatus::InvalidKey { message }
+        } else {
+            CredentialStatus::MissingKey
+        })
+    } else {
+        None
+    }
 }
 
 /// Errors that can occur when loading environment configuration.
@@ -83,6 +483,11 @@ pub fn options_from_env(dir: Option<&Path>) -> Result<crate::config::ClaudeAgent
 pub enum EnvError {
     Io(String),
     Parse(String),
+    /// Variables the chosen provider requires are not set.
+    MissingVars {
+        provider: Provider,
+        missing: Vec<String>,
+    },
 }
 
 impl std::fmt::Display for EnvError {
@@ -90,6 +495,12 @@ impl std::fmt::Display for EnvError {
         match self {
             EnvError::Io(msg) => write!(f, "IO error: {}", msg),
             EnvError::Parse(msg) => write!(f, "Parse error: {}", msg),
+            EnvError::MissingVars { provider, missing } => write!(
+                f,
+                "provider {} requires {} to be set",
+                provider,
+                missing.join(", ")
+            ),
         }
     }
 }
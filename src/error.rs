@@ -1,10 +1,13 @@
 //! Error types exposed by the Rust SDK.
 
 use std::path::PathBuf;
+use std::time::Duration;
 
 use serde_json::Value;
 use thiserror::Error;
 
+use crate::message::ResultMessage;
+
 /// Top-level error type for all SDK operations.
 #[derive(Debug, Error)]
 pub enum SdkError {
@@ -44,9 +47,278 @@ pub enum SdkError {
     #[error(transparent)]
     Json(#[from] serde_json::Error),
 
-    /// Timeout while awaiting a CLI response.
+    /// Raised when an operation did not complete in time.
+    #[error("{operation} timed out after {elapsed:?}")]
+    Timeout {
+        /// What was being waited on.
+        operation: TimeoutOperation,
+        /// How long the SDK waited before giving up.
+        elapsed: Duration,
+    },
+
+    /// Raised when options are invalid or cannot be combined.
+    #[error("{0}")]
+    InvalidConfig(String),
+
+    /// Raised when an option needs a newer CLI than the one installed.
+    #[error("{feature} requires Claude Code {required} or newer (found {cli_version})")]
+    UnsupportedFeature {
+        /// The flag the option maps to.
+        feature: crate::transport::capabilities::CliFeature,
+        /// Version reported by `claude -v`.
+        cli_version: String,
+        /// First version accepting the flag.
+        required: String,
+    },
+
+    /// Raised when a credential provider cannot supply credentials.
+    #[error("credentials unavailable: {0}")]
+    Credentials(String),
+
+    /// Raised when the CLI sends a frame that violates the control protocol.
+    #[error("{0}")]
+    Protocol(String),
+
+    /// Raised when an operation is abandoned because the query was closed.
+    #[error("{0}")]
+    Cancelled(String),
+
+    /// Raised when the CLI answers a control request with an error.
+    #[error(transparent)]
+    Control(#[from] ControlError),
+
+    /// Raised when the CLI stops because `max_budget_usd` was reached.
+    #[error("budget exceeded: spent ${spent:.4}{}", limit.map(|limit| format!(" of ${limit:.4}")).unwrap_or_default())]
+    BudgetExceeded {
+        /// Total cost reported by the CLI, in USD.
+        spent: f64,
+        /// Configured budget, when known.
+        limit: Option<f64>,
+        /// Result message the CLI ended the run with, for its usage,
+        /// session id and cost.
+        result: Box<ResultMessage>,
+    },
+
+    /// Raised when buffered CLI output outgrows a [`BufferLimit`] using
+    /// [`SlowConsumerPolicy::Error`].
+    ///
+    /// [`BufferLimit`]: crate::buffer_limit::BufferLimit
+    /// [`SlowConsumerPolicy::Error`]: crate::buffer_limit::SlowConsumerPolicy::Error
+    #[error("buffered CLI output would reach {buffered} bytes, over the {limit} byte limit")]
+    BufferLimitExceeded {
+        /// Bytes buffered including the message that did not fit.
+        buffered: usize,
+        /// Configured limit in bytes.
+        limit: usize,
+    },
+
+    /// Raised by [`InFlightPolicy::Error`] when a prompt is sent while the
+    /// session's previous response is still streaming, and by
+    /// [`ClaudeSdkClient::compact`] while any earlier response is still
+    /// streaming or unread.
+    ///
+    /// [`InFlightPolicy::Error`]: crate::config::InFlightPolicy::Error
+    /// [`ClaudeSdkClient::compact`]: crate::client::ClaudeSdkClient::compact
+    #[error("session '{session_id}' is still streaming a response; wait for its result before sending another prompt")]
+    QueryInFlight {
+        /// Session id passed to `query` or `compact`.
+        session_id: String,
+    },
+
+    /// Yielded by the message stream when writing a streamed prompt to the
+    /// CLI failed; input after the first `delivered` messages was not sent.
+    #[error("prompt stream failed after {delivered} message(s): {source}")]
+    PromptStream {
+        /// Messages written before the failure.
+        delivered: usize,
+        source: Box<SdkError>,
+    },
+
+    /// Reported when a permission callback, hook or SDK MCP tool panicked
+    /// while answering the CLI; the CLI received an error response instead.
     #[error(transparent)]
-    Timeout(#[from] tokio::time::error::Elapsed),
+    CallbackPanicked(#[from] CallbackPanic),
+
+    /// Raised when the CLI stops because `max_turns` was reached.
+    #[error("maximum turns reached after {turns} turn(s){}", limit.map(|limit| format!(" (limit {limit})")).unwrap_or_default())]
+    MaxTurns {
+        /// Number of turns reported by the CLI.
+        turns: i64,
+        /// Configured turn limit, when known.
+        limit: Option<u32>,
+        /// Result message the CLI ended the run with; `None` when
+        /// [`ClaudeAgentOptions::session_turn_limit`] refused the prompt.
+        ///
+        /// [`ClaudeAgentOptions::session_turn_limit`]: crate::config::ClaudeAgentOptions::session_turn_limit
+        result: Option<Box<ResultMessage>>,
+    },
+
+    /// Raised by [`McpPreflight::Require`] when a remote MCP server does not
+    /// answer its probe.
+    ///
+    /// [`McpPreflight::Require`]: crate::mcp::health::McpPreflight::Require
+    #[error("MCP server '{server}' at {url} is unreachable: {reason}")]
+    McpServerUnreachable {
+        /// Name of the server in `mcp_servers`.
+        server: String,
+        /// Server URL as configured, with `${VAR}` references unexpanded.
+        url: String,
+        /// Why the probe failed.
+        reason: String,
+    },
+}
+
+/// Operation that exceeded its time limit in [`SdkError::Timeout`].
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum TimeoutOperation {
+    /// A control request sent to the CLI, identified by its subtype.
+    ControlRequest { subtype: String },
+    /// Establishing the transport connection.
+    ///
+    /// Not produced by the bundled subprocess transport; available to custom
+    /// [`Transport`](crate::transport::Transport) implementations.
+    Connect,
+    /// Probing the CLI version with `claude -v`.
+    VersionCheck,
+    /// Waiting for the next message from the transport.
+    ///
+    /// Like [`TimeoutOperation::Connect`], this is reserved for custom transports.
+    Read,
+    /// Draining buffered output during a graceful shutdown.
+    ///
+    /// The SDK's own graceful disconnect logs this timeout and closes anyway
+    /// rather than returning it.
+    Drain,
+}
+
+impl std::fmt::Display for TimeoutOperation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeoutOperation::ControlRequest { subtype } => {
+                write!(f, "control request '{subtype}'")
+            }
+            TimeoutOperation::Connect => f.write_str("connect"),
+            TimeoutOperation::VersionCheck => f.write_str("version check"),
+            TimeoutOperation::Read => f.write_str("read"),
+            TimeoutOperation::Drain => f.write_str("shutdown drain"),
+        }
+    }
+}
+
+/// Broad classification of [`SdkError`] values for retry and reporting policies.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ErrorKind {
+    /// The CLI could not be found, spawned, or talked to.
+    Connection,
+    /// The CLI process exited unsuccessfully.
+    ProcessExit,
+    /// The CLI sent frames that violate the control protocol.
+    Protocol,
+    /// CLI output could not be decoded or mapped onto typed messages.
+    Parse,
+    /// An operation did not complete in time.
+    Timeout,
+    /// A spending or turn limit was reached.
+    Budget,
+    /// The operation was abandoned because the session closed.
+    Cancelled,
+    /// A tool or hook handler failed.
+    Tool,
+    /// A permission check rejected the operation.
+    Permission,
+    /// Options were invalid or incompatible.
+    Configuration,
+    /// Anything not covered by a more specific kind.
+    Other,
+}
+
+impl SdkError {
+    /// Classify the error without inspecting its message.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            SdkError::CliConnection(_)
+            | SdkError::CliNotFound(_)
+            | SdkError::Io(_)
+            | SdkError::McpServerUnreachable { .. } => ErrorKind::Connection,
+            SdkError::Process(_) => ErrorKind::ProcessExit,
+            SdkError::Protocol(_) => ErrorKind::Protocol,
+            SdkError::CliJsonDecode(_) | SdkError::MessageParse(_) | SdkError::Json(_) => {
+                ErrorKind::Parse
+            }
+            SdkError::Timeout { .. } => ErrorKind::Timeout,
+            SdkError::PromptStream { source, .. } => source.kind(),
+            SdkError::Cancelled(_) => ErrorKind::Cancelled,
+            SdkError::InvalidConfig(_)
+            | SdkError::Credentials(_)
+            | SdkError::UnsupportedFeature { .. } => ErrorKind::Configuration,
+            SdkError::BudgetExceeded { .. } | SdkError::MaxTurns { .. } => ErrorKind::Budget,
+            SdkError::CallbackPanicked(_) => ErrorKind::Tool,
+            SdkError::NotImplemented
+            | SdkError::Message(_)
+            | SdkError::Control(_)
+            | SdkError::BufferLimitExceeded { .. }
+            | SdkError::QueryInFlight { .. } => ErrorKind::Other,
+        }
+    }
+
+    /// Whether retrying the same operation could plausibly succeed.
+    ///
+    /// Transient transport failures, crashed CLI processes, and timeouts are
+    /// retryable; a missing CLI binary or invalid input is not.
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            SdkError::CliNotFound(_) => false,
+            other => matches!(
+                other.kind(),
+                ErrorKind::Connection | ErrorKind::ProcessExit | ErrorKind::Timeout
+            ),
+        }
+    }
+}
+
+/// Context captured when the CLI cannot be started or exits unexpectedly.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ConnectionDiagnostics {
+    /// Resolved path of the CLI binary.
+    pub cli_path: Option<PathBuf>,
+    /// Output of `claude -v`, when it could be obtained.
+    pub cli_version: Option<String>,
+    /// Arguments passed to the CLI, with prompts and configuration payloads redacted.
+    pub argv: Vec<String>,
+    /// Working directory the CLI was started in.
+    pub cwd: Option<PathBuf>,
+    /// First lines the CLI wrote to stderr; empty unless a `stderr`
+    /// callback or `debug-to-stderr` had the SDK capture them.
+    pub stderr_head: Vec<String>,
+    /// Suggested next step for the user.
+    pub hint: Option<String>,
+}
+
+impl std::fmt::Display for ConnectionDiagnostics {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if let Some(path) = &self.cli_path {
+            write!(f, "\n  cli: {}", path.display())?;
+            if let Some(version) = &self.cli_version {
+                write!(f, " ({version})")?;
+            }
+        }
+        if !self.argv.is_empty() {
+            write!(f, "\n  argv: {}", self.argv.join(" "))?;
+        }
+        if let Some(cwd) = &self.cwd {
+            write!(f, "\n  cwd: {}", cwd.display())?;
+        }
+        if !self.stderr_head.is_empty() {
+            write!(f, "\n  stderr:")?;
+            for line in &self.stderr_head {
+                write!(f, "\n    {line}")?;
+            }
+        }
+        if let Some(hint) = &self.hint {
+            write!(f, "\n  hint: {hint}")?;
+        }
+        Ok(())
+    }
 }
 
 /// Raised when unable to connect to the Claude Code CLI.
@@ -54,18 +326,31 @@ pub enum SdkError {
 #[error("{message}")]
 pub struct CliConnectionError {
     message: String,
+    diagnostics: Option<Box<ConnectionDiagnostics>>,
 }
 
 impl CliConnectionError {
     pub fn new(message: impl Into<String>) -> Self {
         Self {
             message: message.into(),
+            diagnostics: None,
         }
     }
 
+    /// Attach spawn context; it is appended to the error message.
+    pub fn with_diagnostics(mut self, diagnostics: ConnectionDiagnostics) -> Self {
+        self.message = format!("{}{diagnostics}", self.message);
+        self.diagnostics = Some(Box::new(diagnostics));
+        self
+    }
+
     pub fn message(&self) -> &str {
         &self.message
     }
+
+    pub fn diagnostics(&self) -> Option<&ConnectionDiagnostics> {
+        self.diagnostics.as_deref()
+    }
 }
 
 /// Raised when Claude Code is not found or not installed.
@@ -101,6 +386,7 @@ pub struct ProcessError {
     message: String,
     exit_code: Option<i32>,
     stderr: Option<String>,
+    diagnostics: Option<Box<ConnectionDiagnostics>>,
 }
 
 impl ProcessError {
@@ -121,13 +407,25 @@ impl ProcessError {
             message,
             exit_code,
             stderr,
+            diagnostics: None,
         }
     }
 
+    /// Attach spawn context; it is appended to the error message.
+    pub fn with_diagnostics(mut self, diagnostics: ConnectionDiagnostics) -> Self {
+        self.message = format!("{}{diagnostics}", self.message);
+        self.diagnostics = Some(Box::new(diagnostics));
+        self
+    }
+
     pub fn exit_code(&self) -> Option<i32> {
         self.exit_code
     }
 
+    pub fn diagnostics(&self) -> Option<&ConnectionDiagnostics> {
+        self.diagnostics.as_deref()
+    }
+
     pub fn stderr(&self) -> Option<&str> {
         self.stderr.as_deref()
     }
@@ -188,6 +486,198 @@ impl MessageParseError {
     }
 }
 
+/// A callback that panicked while handling a CLI control request.
+#[derive(Debug, Error, Clone, PartialEq, Eq)]
+#[error("{subtype} callback '{callback}' panicked: {message}")]
+pub struct CallbackPanic {
+    /// Control request subtype: `can_use_tool`, `hook_callback` or `mcp_message`.
+    pub subtype: String,
+    /// Tool name, hook callback id or MCP server name the request named.
+    pub callback: String,
+    /// Panic payload, when it was a string.
+    pub message: String,
+}
+
+/// Error returned by the CLI in a `control_response` with subtype `error`.
+#[derive(Debug, Error, Clone)]
+#[error("{message}")]
+pub struct ControlError {
+    code: Option<String>,
+    message: String,
+    data: Option<Value>,
+}
+
+impl ControlError {
+    pub fn new(code: Option<String>, message: impl Into<String>, data: Option<Value>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            data,
+        }
+    }
+
+    /// Build from the `response` object of an error `control_response`.
+    ///
+    /// The CLI either sends `error` as a plain string, optionally alongside
+    /// `code`/`data` siblings, or as an object carrying those fields itself.
+    pub fn from_response(response: &serde_json::Map<String, Value>) -> Self {
+        let (source, message) = match response.get("error") {
+            Some(Value::Object(error)) => (
+                error,
+                error
+                    .get("message")
+                    .and_then(Value::as_str)
+                    .map(str::to_string),
+            ),
+            Some(Value::String(message)) => (response, Some(message.clone())),
+            _ => (response, None),
+        };
+        let code = source
+            .get("code")
+            .or_else(|| source.get("error_code"))
+            .and_then(|code| match code {
+                Value::String(code) => Some(code.clone()),
+                Value::Number(code) => Some(code.to_string()),
+                _ => None,
+            });
+        let data = source.get("data").filter(|data| !data.is_null()).cloned();
+        let message = message
+            .filter(|message| !message.is_empty())
+            .unwrap_or_else(|| "Unknown error".to_string());
+        Self::new(code, message, data)
+    }
+
+    pub fn code(&self) -> Option<&str> {
+        self.code.as_deref()
+    }
+
+    pub fn message(&self) -> &str {
+        &self.message
+    }
+
+    pub fn data(&self) -> Option<&Value> {
+        self.data.as_ref()
+    }
+}
+
+#[cfg(feature = "diagnostics")]
+mod diagnostic {
+    use std::fmt::Display;
+
+    use miette::{Diagnostic, LabeledSpan, SourceCode};
+
+    use super::{CliJsonDecodeError, ErrorKind, SdkError};
+
+    impl ErrorKind {
+        fn code(self) -> &'static str {
+            match self {
+                ErrorKind::Connection => "claude_sdk::connection",
+                ErrorKind::ProcessExit => "claude_sdk::process_exit",
+                ErrorKind::Protocol => "claude_sdk::protocol",
+                ErrorKind::Parse => "claude_sdk::parse",
+                ErrorKind::Timeout => "claude_sdk::timeout",
+                ErrorKind::Budget => "claude_sdk::budget",
+                ErrorKind::Cancelled => "claude_sdk::cancelled",
+                ErrorKind::Tool => "claude_sdk::tool",
+                ErrorKind::Permission => "claude_sdk::permission",
+                ErrorKind::Configuration => "claude_sdk::configuration",
+                ErrorKind::Other => "claude_sdk::other",
+            }
+        }
+    }
+
+    impl Diagnostic for CliJsonDecodeError {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(ErrorKind::Parse.code()))
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            Some(Box::new(
+                "the CLI wrote a line that is not valid JSON; check for stray output on stdout",
+            ))
+        }
+
+        fn source_code(&self) -> Option<&dyn SourceCode> {
+            Some(&self.line)
+        }
+
+        fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+            // serde_json columns are 1-based; 0 means the error has no position.
+            let offset = self
+                .source
+                .column()
+                .saturating_sub(1)
+                .min(self.line.len().saturating_sub(1));
+            let label = LabeledSpan::at_offset(offset, self.source.to_string());
+            Some(Box::new(std::iter::once(label)))
+        }
+    }
+
+    impl Diagnostic for SdkError {
+        fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            match self {
+                SdkError::Control(error) => match error.code() {
+                    Some(code) => Some(Box::new(format!("claude_sdk::control::{code}"))),
+                    None => Some(Box::new("claude_sdk::control")),
+                },
+                other => Some(Box::new(other.kind().code())),
+            }
+        }
+
+        fn help<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+            let connection_hint = |diagnostics: Option<&super::ConnectionDiagnostics>| {
+                diagnostics
+                    .and_then(|diagnostics| diagnostics.hint.clone())
+                    .unwrap_or_else(|| "run `claude doctor` to check the installation".into())
+            };
+            let help: String = match self {
+                SdkError::CliNotFound(_) => "install the CLI with `npm install -g @anthropic-ai/claude-code` or set `ClaudeAgentOptions::cli_path`".into(),
+                SdkError::CliConnection(error) => connection_hint(error.diagnostics()),
+                SdkError::Process(error) => connection_hint(error.diagnostics()),
+                SdkError::CliJsonDecode(error) => return error.help(),
+                SdkError::MessageParse(_) => {
+                    "the CLI may be newer than this SDK; check for an SDK update".into()
+                }
+                SdkError::BudgetExceeded { .. } => {
+                    "raise `max_budget_usd` or split the task into smaller queries".into()
+                }
+                SdkError::MaxTurns { .. } => {
+                    "raise `max_turns` or continue the session with another query".into()
+                }
+                SdkError::UnsupportedFeature { .. } => {
+                    "upgrade the CLI with `claude update` or drop the option".into()
+                }
+                SdkError::BufferLimitExceeded { .. } => {
+                    "read messages faster or raise the buffer limit".into()
+                }
+                SdkError::CallbackPanicked(_) => {
+                    "the CLI was told the request failed and the session continues".into()
+                }
+                SdkError::Cancelled(_) => "the query was closed before the operation finished".into(),
+                SdkError::McpServerUnreachable { .. } => {
+                    "start the server or fix its URL, or set `mcp_preflight` to `Warn` to connect without it".into()
+                }
+                _ => return None,
+            };
+            Some(Box::new(help))
+        }
+
+        fn source_code(&self) -> Option<&dyn SourceCode> {
+            match self {
+                SdkError::CliJsonDecode(error) => error.source_code(),
+                _ => None,
+            }
+        }
+
+        fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+            match self {
+                SdkError::CliJsonDecode(error) => error.labels(),
+                _ => None,
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -218,6 +708,29 @@ mod tests {
         assert!(message.contains("Command not found"));
     }
 
+    #[test]
+    fn process_error_appends_diagnostics() {
+        let diagnostics = ConnectionDiagnostics {
+            cli_path: Some(PathBuf::from("/usr/local/bin/claude")),
+            cli_version: Some("2.0.1 (Claude Code)".into()),
+            argv: vec!["--output-format".into(), "stream-json".into()],
+            cwd: Some(PathBuf::from("/work")),
+            stderr_head: vec!["Invalid API key".into()],
+            hint: Some("run `claude doctor`".into()),
+        };
+        let err = ProcessError::new("Command failed with exit code 1", Some(1), None)
+            .with_diagnostics(diagnostics.clone());
+
+        assert_eq!(err.diagnostics(), Some(&diagnostics));
+        let message = err.to_string();
+        assert!(message.starts_with("Command failed with exit code 1 (exit code: 1)"));
+        assert!(message.contains("cli: /usr/local/bin/claude (2.0.1 (Claude Code))"));
+        assert!(message.contains("argv: --output-format stream-json"));
+        assert!(message.contains("cwd: /work"));
+        assert!(message.contains("    Invalid API key"));
+        assert!(message.contains("hint: run `claude doctor`"));
+    }
+
     #[test]
     fn cli_json_decode_error_exposes_line_and_message() {
         let source = serde_json::from_str::<serde_json::Value>("{invalid json}").unwrap_err();
@@ -226,6 +739,120 @@ mod tests {
         assert!(err.to_string().contains("Failed to decode JSON"));
     }
 
+    #[test]
+    fn sdk_error_kind_classifies_variants() {
+        let process = SdkError::from(ProcessError::new("crashed", Some(1), None));
+        assert_eq!(process.kind(), ErrorKind::ProcessExit);
+        assert!(process.is_retryable());
+
+        let not_found = SdkError::from(CliNotFoundError::new("missing", None));
+        assert_eq!(not_found.kind(), ErrorKind::Connection);
+        assert!(!not_found.is_retryable());
+
+        let parse = SdkError::from(MessageParseError::new("bad", None));
+        assert_eq!(parse.kind(), ErrorKind::Parse);
+        assert!(!parse.is_retryable());
+
+        assert_eq!(
+            SdkError::Cancelled("query closed".into()).kind(),
+            ErrorKind::Cancelled
+        );
+        assert_eq!(
+            SdkError::InvalidConfig("bad option".into()).kind(),
+            ErrorKind::Configuration
+        );
+        assert_eq!(SdkError::Message("other".into()).kind(), ErrorKind::Other);
+    }
+
+    #[test]
+    fn timeout_names_the_operation() {
+        let err = SdkError::Timeout {
+            operation: TimeoutOperation::ControlRequest {
+                subtype: "interrupt".into(),
+            },
+            elapsed: Duration::from_secs(60),
+        };
+        assert_eq!(err.kind(), ErrorKind::Timeout);
+        assert!(err.is_retryable());
+        assert_eq!(
+            err.to_string(),
+            "control request 'interrupt' timed out after 60s"
+        );
+    }
+
+    #[test]
+    fn limit_errors_report_spend_and_turns() {
+        let result: ResultMessage = serde_json::from_value(json!({
+            "subtype": "error_max_budget_usd",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": true,
+            "num_turns": 3,
+            "session_id": "sess-limit",
+            "total_cost_usd": 0.125
+        }))
+        .unwrap();
+        let budget = result.limit_error(None, Some(0.1)).unwrap();
+        assert_eq!(budget.kind(), ErrorKind::Budget);
+        assert!(!budget.is_retryable());
+        assert_eq!(
+            budget.to_string(),
+            "budget exceeded: spent $0.1250 of $0.1000"
+        );
+        let SdkError::BudgetExceeded { result: kept, .. } = &budget else {
+            panic!("expected a budget error, got {budget:?}");
+        };
+        assert_eq!(**kept, result);
+
+        let result = ResultMessage {
+            subtype: "error_max_turns".into(),
+            ..result
+        };
+        let turns = result.limit_error(None, None).unwrap();
+        assert_eq!(turns.to_string(), "maximum turns reached after 3 turn(s)");
+        assert!(
+            matches!(turns, SdkError::MaxTurns { result: Some(kept), .. } if kept.session_id == "sess-limit")
+        );
+    }
+
+    #[test]
+    fn control_error_reads_string_and_structured_payloads() {
+        let flat = json!({
+            "subtype": "error",
+            "error": "Unknown model: nope",
+            "code": "unknown_model",
+        });
+        let err = ControlError::from_response(flat.as_object().unwrap());
+        assert_eq!(err.code(), Some("unknown_model"));
+        assert_eq!(err.to_string(), "Unknown model: nope");
+        assert!(err.data().is_none());
+
+        let nested = json!({
+            "subtype": "error",
+            "error": {"code": -32602, "message": "invalid request", "data": {"field": "mode"}},
+        });
+        let err = ControlError::from_response(nested.as_object().unwrap());
+        assert_eq!(err.code(), Some("-32602"));
+        assert_eq!(err.message(), "invalid request");
+        assert_eq!(err.data(), Some(&json!({"field": "mode"})));
+    }
+
+    #[cfg(feature = "diagnostics")]
+    #[test]
+    fn json_decode_diagnostic_points_at_offending_column() {
+        use miette::Diagnostic;
+
+        let line = r#"{"type": "assistant", oops}"#;
+        let source = serde_json::from_str::<Value>(line).unwrap_err();
+        let err = SdkError::from(CliJsonDecodeError::new(line, source));
+
+        assert_eq!(err.code().unwrap().to_string(), "claude_sdk::parse");
+        assert!(err.help().is_some());
+        assert!(err.source_code().is_some());
+        let label = err.labels().unwrap().next().unwrap();
+        assert_eq!(label.offset(), line.find("oops").unwrap());
+    }
+
     #[test]
     fn message_parse_error_retains_payload() {
         let payload = json!({"type": "unknown"});
@@ -0,0 +1,130 @@
+//! Opt-in recording of control protocol frames exchanged with the CLI.
+
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use serde_json::Value;
+
+/// Which side of the connection initiated a control request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FrameDirection {
+    /// Request sent by the SDK (interrupt, initialize, set_model, ...).
+    Outbound,
+    /// Request sent by the CLI (can_use_tool, hook_callback, mcp_message, ...).
+    Inbound,
+}
+
+/// Final state of a control request/response exchange.
+#[derive(Debug, Clone, PartialEq)]
+pub enum FrameOutcome {
+    Success,
+    Error(String),
+    Timeout,
+    Cancelled,
+}
+
+impl FrameOutcome {
+    pub fn is_success(&self) -> bool {
+        matches!(self, FrameOutcome::Success)
+    }
+}
+
+/// A completed control_request/control_response pair.
+#[derive(Debug, Clone)]
+pub struct ControlFrameRecord {
+    pub request_id: String,
+    pub subtype: String,
+    pub direction: FrameDirection,
+    pub request: Value,
+    pub response: Option<Value>,
+    pub outcome: FrameOutcome,
+    pub started_at: SystemTime,
+    pub latency: Duration,
+}
+
+/// Destination for control frame records.
+pub trait ControlFrameSink: Send + Sync {
+    fn record(&self, record: &ControlFrameRecord);
+}
+
+impl<F> ControlFrameSink for F
+where
+    F: Fn(&ControlFrameRecord) + Send + Sync,
+{
+    fn record(&self, record: &ControlFrameRecord) {
+        self(record)
+    }
+}
+
+/// Convenient handle for storing frame sinks.
+pub type ControlFrameSinkHandle = Arc<dyn ControlFrameSink>;
+
+/// Sink that forwards a one-line summary of every frame to the `log` crate.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LogFrameSink;
+
+impl ControlFrameSink for LogFrameSink {
+    fn record(&self, record: &ControlFrameRecord) {
+        log::debug!(
+            "[control] {:?} {} id={} outcome={:?} latency={:?}",
+            record.direction,
+            record.subtype,
+            record.request_id,
+            record.outcome,
+            record.latency
+        );
+    }
+}
+
+/// Bounded in-memory sink retaining the most recent frames.
+#[derive(Debug)]
+pub struct InMemoryFrameLog {
+    capacity: usize,
+    records: Mutex<VecDeque<ControlFrameRecord>>,
+}
+
+impl InMemoryFrameLog {
+    /// Create a log retaining at most `capacity` records.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            records: Mutex::new(VecDeque::new()),
+        }
+    }
+
+    /// Snapshot of the retained records, oldest first.
+    pub fn records(&self) -> Vec<ControlFrameRecord> {
+        self.records
+            .lock()
+            .map(|records| records.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+
+    /// Remove all retained records.
+    pub fn clear(&self) {
+        if let Ok(mut records) = self.records.lock() {
+            records.clear();
+        }
+    }
+}
+
+impl Default for InMemoryFrameLog {
+    fn default() -> Self {
+        Self::new(1_000)
+    }
+}
+
+impl ControlFrameSink for InMemoryFrameLog {
+    fn record(&self, record: &ControlFrameRecord) {
+        if self.capacity == 0 {
+            return;
+        }
+        if let Ok(mut records) = self.records.lock() {
+            while records.len() >= self.capacity {
+                records.pop_front();
+            }
+            records.push_back(record.clone());
+        }
+    }
+}
@@ -1,5 +1,7 @@
 //! Hook configuration and execution helpers.
 
+use std::collections::HashMap;
+use std::path::PathBuf;
 use std::pin::Pin;
 use std::sync::Arc;
 
@@ -7,6 +9,12 @@ use futures::Future;
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
+use crate::error::{CliJsonDecodeError, SdkError};
+use crate::internal::message_parser;
+use crate::message::Message;
+
+pub mod moderation;
+
 /// Supported hook event names.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum HookEvent {
@@ -42,12 +50,38 @@ impl HookEvent {
 #[serde(rename_all = "camelCase")]
 pub struct BaseHookInput {
     pub session_id: String,
-    pub transcript_path: String,
-    pub cwd: String,
+    pub transcript_path: PathBuf,
+    pub cwd: PathBuf,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub permission_mode: Option<String>,
 }
 
+impl BaseHookInput {
+    /// Read the session transcript at `transcript_path` as typed messages.
+    ///
+    /// Entries that are not conversation messages, such as summaries, are
+    /// skipped.
+    pub fn load_transcript(&self) -> Result<Vec<Message>, SdkError> {
+        let contents = std::fs::read_to_string(&self.transcript_path)?;
+        let mut messages = Vec::new();
+        for line in contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+        {
+            let entry: Value = serde_json::from_str(line)
+                .map_err(|err| SdkError::from(CliJsonDecodeError::new(line, err)))?;
+            if matches!(
+                entry.get("type").and_then(Value::as_str),
+                Some("user" | "assistant" | "system" | "result")
+            ) {
+                messages.push(message_parser::parse_message(&entry)?);
+            }
+        }
+        Ok(messages)
+    }
+}
+
 /// Input payload for the PreToolUse hook.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
@@ -269,3 +303,37 @@ impl std::fmt::Debug for HookMatcher {
             .finish()
     }
 }
+
+/// Hook registrations sent in the `hooks` field of the `initialize` control
+/// request, by event.
+pub type HooksRegistration = HashMap<HookEvent, Vec<HookRegistrationConfig>>;
+
+/// A [`HookMatcher`] as registered with the CLI: its matcher and the ids the
+/// CLI sends back in `hook_callback` requests for each of its callbacks.
+///
+/// ```
+/// # use sdk_claude_rust::hooks::HookRegistrationConfig;
+/// # use serde_json::json;
+/// let config = HookRegistrationConfig::new(Some(json!("Bash")), vec!["hook_0".into()]);
+/// assert_eq!(
+///     serde_json::to_value(&config).unwrap(),
+///     json!({"matcher": "Bash", "hookCallbackIds": ["hook_0"]})
+/// );
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookRegistrationConfig {
+    /// Omitted when the matcher applies to every tool.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<Value>,
+    #[serde(rename = "hookCallbackIds")]
+    pub hook_callback_ids: Vec<String>,
+}
+
+impl HookRegistrationConfig {
+    pub fn new(matcher: Option<Value>, hook_callback_ids: Vec<String>) -> Self {
+        Self {
+            matcher,
+            hook_callback_ids,
+        }
+    }
+}
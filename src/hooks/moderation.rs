@@ -0,0 +1,239 @@
+//! Keyword, pattern and callback checks over prompts and replies.
+//!
+//! A [`Moderation`] registers two hooks. On `UserPromptSubmit` it checks the
+//! prompt; on `Stop` it reads the session transcript and checks the text of
+//! the reply that just finished. Each side either blocks or annotates,
+//! according to its [`ModerationAction`]:
+//!
+//! - a blocked prompt is erased before Claude sees it;
+//! - a blocked reply makes Claude continue with the violation as feedback,
+//!   once per turn;
+//! - an annotated prompt gets a moderation note as additional context, and an
+//!   annotated reply a system message shown to the user.
+
+use std::sync::Arc;
+
+use regex::Regex;
+
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::hooks::{
+    HookEvent, HookInput, HookJsonOutput, HookMatcher, HookSpecificOutput, SyncHookJsonOutput,
+    UserPromptSubmitHookSpecificOutput,
+};
+use crate::internal::trace::sdk_debug;
+use crate::message::{ContentBlock, Message};
+
+/// Caller-supplied check returning why `text` is not allowed.
+pub type ModerationCheck = Arc<dyn Fn(&str) -> Option<String> + Send + Sync>;
+
+/// What a hook does with text that fails a check.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ModerationAction {
+    #[default]
+    Block,
+    Annotate,
+}
+
+/// Why a text failed moderation.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Violation {
+    pub reason: String,
+}
+
+/// Set of checks shared by the prompt and reply hooks.
+#[derive(Clone, Default)]
+pub struct Moderation {
+    patterns: Vec<(String, Regex)>,
+    checks: Vec<ModerationCheck>,
+    prompt_action: ModerationAction,
+    reply_action: ModerationAction,
+}
+
+impl std::fmt::Debug for Moderation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let rules: Vec<&str> = self
+            .patterns
+            .iter()
+            .map(|(rule, _)| rule.as_str())
+            .collect();
+        f.debug_struct("Moderation")
+            .field("rules", &rules)
+            .field("checks_len", &self.checks.len())
+            .field("prompt_action", &self.prompt_action)
+            .field("reply_action", &self.reply_action)
+            .finish()
+    }
+}
+
+impl Moderation {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Flag `keyword` as a whole word, ignoring case.
+    pub fn with_keyword(mut self, keyword: &str) -> Self {
+        let pattern = format!(r"(?i)\b{}\b", regex::escape(keyword));
+        let regex = Regex::new(&pattern).expect("escaped keyword is a valid pattern");
+        self.patterns
+            .push((format!("keyword \"{keyword}\""), regex));
+        self
+    }
+
+    /// Flag text matching the regular expression `pattern`.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, SdkError> {
+        let regex = Regex::new(pattern).map_err(|err| {
+            SdkError::InvalidConfig(format!("invalid moderation pattern {pattern:?}: {err}"))
+        })?;
+        self.patterns.push((format!("pattern /{pattern}/"), regex));
+        Ok(self)
+    }
+
+    /// Flag text for which `check` returns a reason.
+    pub fn with_check<F>(mut self, check: F) -> Self
+    where
+        F: Fn(&str) -> Option<String> + Send + Sync + 'static,
+    {
+        self.checks.push(Arc::new(check));
+        self
+    }
+
+    pub fn with_prompt_action(mut self, action: ModerationAction) -> Self {
+        self.prompt_action = action;
+        self
+    }
+
+    pub fn with_reply_action(mut self, action: ModerationAction) -> Self {
+        self.reply_action = action;
+        self
+    }
+
+    /// First check `text` fails, patterns before callbacks.
+    pub fn check(&self, text: &str) -> Option<Violation> {
+        let matched = self
+            .patterns
+            .iter()
+            .find(|(_, regex)| regex.is_match(text))
+            .map(|(rule, _)| format!("matched {rule}"));
+        matched
+            .or_else(|| self.checks.iter().find_map(|check| check(text)))
+            .map(|reason| Violation { reason })
+    }
+
+    /// Hook checking each submitted prompt.
+    pub fn user_prompt_submit_hook(&self) -> HookMatcher {
+        let moderation = self.clone();
+        let mut matcher = HookMatcher::new(None);
+        matcher.hooks.push(Arc::new(move |input: HookInput, _, _| {
+            let output = match &input {
+                HookInput::UserPromptSubmit(input) => moderation.review_prompt(&input.prompt),
+                _ => SyncHookJsonOutput::default(),
+            };
+            async move { HookJsonOutput::Sync(output) }
+        }));
+        matcher
+    }
+
+    /// Hook checking the reply that ends each turn.
+    pub fn stop_hook(&self) -> HookMatcher {
+        let moderation = self.clone();
+        let mut matcher = HookMatcher::new(None);
+        matcher.hooks.push(Arc::new(move |input: HookInput, _, _| {
+            let output = match &input {
+                HookInput::Stop(input) => match input.base.load_transcript() {
+                    Ok(messages) => moderation.review_reply(&messages, input.stop_hook_active),
+                    Err(err) => {
+                        sdk_debug!("moderation: cannot read transcript: {err}");
+                        SyncHookJsonOutput::default()
+                    }
+                },
+                _ => SyncHookJsonOutput::default(),
+            };
+            async move { HookJsonOutput::Sync(output) }
+        }));
+        matcher
+    }
+
+    /// Register [`Moderation::user_prompt_submit_hook`] and
+    /// [`Moderation::stop_hook`].
+    pub fn apply_to(&self, options: &mut ClaudeAgentOptions) {
+        let hooks = options.hooks.get_or_insert_with(Default::default);
+        hooks
+            .entry(HookEvent::UserPromptSubmit)
+            .or_default()
+            .push(self.user_prompt_submit_hook());
+        hooks
+            .entry(HookEvent::Stop)
+            .or_default()
+            .push(self.stop_hook());
+    }
+
+    fn review_prompt(&self, prompt: &str) -> SyncHookJsonOutput {
+        let Some(violation) = self.check(prompt) else {
+            return SyncHookJsonOutput::default();
+        };
+        match self.prompt_action {
+            ModerationAction::Block => SyncHookJsonOutput {
+                decision: Some("block".into()),
+                reason: Some(format!(
+                    "Prompt blocked by moderation: {}",
+                    violation.reason
+                )),
+                ..Default::default()
+            },
+            ModerationAction::Annotate => SyncHookJsonOutput {
+                hook_specific_output: Some(HookSpecificOutput::UserPromptSubmit(
+                    UserPromptSubmitHookSpecificOutput {
+                        additional_context: Some(format!(
+                            "Moderation note: this prompt {}; answer within policy.",
+                            violation.reason
+                        )),
+                    },
+                )),
+                ..Default::default()
+            },
+        }
+    }
+
+    fn review_reply(&self, messages: &[Message], stop_hook_active: bool) -> SyncHookJsonOutput {
+        let Some(violation) = self.check(&last_reply(messages)) else {
+            return SyncHookJsonOutput::default();
+        };
+        // Blocking again after a forced continuation could loop forever.
+        if self.reply_action == ModerationAction::Block && !stop_hook_active {
+            return SyncHookJsonOutput {
+                decision: Some("block".into()),
+                reason: Some(format!(
+                    "Your reply {}. Rewrite it without that content.",
+                    violation.reason
+                )),
+                ..Default::default()
+            };
+        }
+        SyncHookJsonOutput {
+            system_message: Some(format!("Moderation: the reply {}.", violation.reason)),
+            ..Default::default()
+        }
+    }
+}
+
+/// Text of the assistant messages after the last prompt.
+fn last_reply(messages: &[Message]) -> String {
+    let start = messages
+        .iter()
+        .rposition(Message::is_user_echo)
+        .map_or(0, |index| index + 1);
+    messages[start..]
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant(assistant) => Some(&assistant.content),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.as_str()),
+            _ => None,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
@@ -5,15 +5,19 @@ use std::sync::Arc;
 
 use futures::stream::BoxStream;
 use futures::{stream, Stream, StreamExt};
-use serde_json::{Map, Value};
+use serde_json::{json, Map, Value};
 
+use crate::buffer_limit::BufferLimit;
+use crate::client::surface_limit_error;
 use crate::config::ClaudeAgentOptions;
 use crate::error::SdkError;
 use crate::hooks::{HookEvent, HookMatcher};
 use crate::internal::query::Query;
+use crate::mcp::health;
 use crate::message::Message;
-use crate::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+use crate::middleware;
 use crate::transport::Transport;
+use crate::transport::{default_transport, PromptMode};
 
 /// Prompt input accepted by the internal client.
 pub enum PromptInput {
@@ -63,6 +67,14 @@ impl InternalClient {
     }
 
     /// Process a query through the transport and control protocol, returning a message stream.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.connect",
+            skip_all,
+            fields(streaming = prompt.is_streaming())
+        )
+    )]
     pub async fn process_query(
         &self,
         prompt: PromptInput,
@@ -71,18 +83,32 @@ impl InternalClient {
     ) -> Result<impl Stream<Item = Result<Message, SdkError>>, SdkError> {
         let is_streaming = prompt.is_streaming();
         Self::validate_permission_options(&mut options, is_streaming)?;
+        health::preflight(&options).await?;
 
         let (prompt_mode, stream_source) = match prompt {
-            PromptInput::Text(text) => (PromptMode::Text(text), None),
+            PromptInput::Text(text) => {
+                let text = Self::rewrite_text_prompt(&options, text)?;
+                (PromptMode::Text(text), None)
+            }
             PromptInput::Stream(stream) => (PromptMode::Streaming, Some(stream)),
         };
 
+        let session_permit = match &options.rate_limiter {
+            Some(limiter) => {
+                let permit = limiter.acquire_session().await;
+                limiter
+                    .acquire_query(options.effective_clock().as_ref())
+                    .await;
+                Some(permit)
+            }
+            None => None,
+        };
+
+        options.buffer_limit = options.buffer_limit.as_ref().map(BufferLimit::for_session);
         let transport = if let Some(custom) = transport {
             custom
         } else {
-            let transport_options = options.clone();
-            let subprocess = SubprocessCliTransport::new(prompt_mode, transport_options)?;
-            Arc::new(subprocess) as Arc<dyn Transport>
+            default_transport(prompt_mode, options.clone())?
         };
 
         transport.connect().await?;
@@ -99,44 +125,97 @@ impl InternalClient {
             sdk_servers,
         );
 
+        query
+            .set_frame_sink(options.control_frame_sink.clone())
+            .await;
+        query.set_metrics(options.metrics.clone()).await;
+        query.set_session_permit(session_permit).await;
+        query.set_tool_result_limit(options.tool_result_limit).await;
+        query.set_buffer_limit(options.buffer_limit.clone()).await;
+        query.set_message_spill(options.message_spill.clone()).await;
+        query.set_mcp_fallback(options.mcp_fallback.clone()).await;
+        query
+            .set_prompt_middleware(options.prompt_middleware.clone())
+            .await;
+        query.set_redactor(Some(options.effective_redactor())).await;
+        query.set_clock(options.effective_clock()).await;
+        query.record_permission_mode(options.permission_mode).await;
+        if let Some(config) = options.control_watchdog {
+            query.set_control_watchdog(config).await;
+        }
         query.start().await?;
 
         if is_streaming {
-            query.initialize().await?;
+            let server_info = query.initialize().await?;
+            if let Err(err) = options.check_system_prompt_preset(server_info.as_ref()) {
+                let _ = query.close().await;
+                return Err(err);
+            }
         }
 
         if let Some(stream) = stream_source {
             let query_clone = query.clone();
             tokio::spawn(async move {
                 if let Err(err) = query_clone.stream_input(stream).await {
-                    let _ = query_clone.close().await;
-                    let _ = err;
+                    query_clone.fail_input(err).await;
                 }
             });
         }
 
-        Ok(Self::message_stream(query))
+        let limits = (options.max_turns, options.max_budget_usd);
+        Ok(Self::message_stream(
+            query,
+            limits,
+            options.hide_user_echoes,
+        ))
     }
 
-    fn message_stream<T>(query: Query<T>) -> impl Stream<Item = Result<Message, SdkError>>
+    /// Run the prompt middleware over a prompt passed on the command line.
+    fn rewrite_text_prompt(options: &ClaudeAgentOptions, text: String) -> Result<String, SdkError> {
+        if options.prompt_middleware.is_empty() {
+            return Ok(text);
+        }
+        let mut message = json!({
+            "type": "user",
+            "message": { "role": "user", "content": text },
+        });
+        middleware::apply(&options.prompt_middleware, &mut message)?;
+        match message.pointer_mut("/message/content").map(Value::take) {
+            Some(Value::String(text)) => Ok(text),
+            _ => Err(SdkError::InvalidConfig(
+                "prompt middleware must keep a text prompt as text; pass a streamed prompt to send content blocks".into(),
+            )),
+        }
+    }
+
+    fn message_stream<T>(
+        query: Query<T>,
+        limits: (Option<u32>, Option<f64>),
+        hide_user_echoes: bool,
+    ) -> impl Stream<Item = Result<Message, SdkError>>
     where
         T: Transport + ?Sized + 'static,
     {
-        stream::unfold((query, false), |(query, finished)| async move {
+        stream::unfold((query, false), move |(query, finished)| async move {
             if finished {
                 return None;
             }
 
-            match query.next_message().await {
-                Ok(Some(message)) => Some((Ok(message), (query, false))),
-                Ok(None) => {
-                    let _ = query.close().await;
-                    None
-                }
-                Err(err) => {
-                    let _ = query.close().await;
-                    Some((Err(err), (query, true)))
-                }
+            loop {
+                return match query.next_message().await {
+                    Ok(Some(message)) if hide_user_echoes && message.is_user_echo() => continue,
+                    Ok(Some(message)) => {
+                        Some((surface_limit_error(message, limits), (query, false)))
+                    }
+                    Ok(None) => {
+                        let _ = query.close().await;
+                        None
+                    }
+                    Err(err) => {
+                        let _ = query.close().await;
+                        Some((Err(err), (query, true)))
+                    }
+                };
             }
         })
     }
@@ -147,13 +226,13 @@ impl InternalClient {
     ) -> Result<(), SdkError> {
         if options.can_use_tool.is_some() {
             if !is_streaming {
-                return Err(SdkError::Message(
+                return Err(SdkError::InvalidConfig(
                     "can_use_tool callback requires streaming prompt".into(),
                 ));
             }
 
             if options.permission_prompt_tool_name.is_some() {
-                return Err(SdkError::Message(
+                return Err(SdkError::InvalidConfig(
                     "can_use_tool cannot be combined with permission_prompt_tool_name".into(),
                 ));
             }
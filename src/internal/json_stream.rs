@@ -0,0 +1,255 @@
+//! Incremental decoder for the CLI's newline-delimited JSON on stdout.
+//!
+//! Bytes are scanned once as they arrive: the decoder tracks string and
+//! nesting state, so a message is parsed exactly once, when the line that
+//! closes it ends. A multi-megabyte tool result costs time linear in its
+//! size no matter how many reads it is split across.
+
+use serde_json::Value;
+
+/// Why a chunk of stdout did not decode.
+#[derive(Debug)]
+pub enum DecodeError {
+    /// A message grew past `max_buffer_size`; the rest of its line is dropped.
+    Overflow {
+        text: String,
+        len: usize,
+        limit: usize,
+    },
+    /// A complete object or array that is not valid JSON.
+    Invalid {
+        text: String,
+        error: serde_json::Error,
+    },
+    /// A line that does not start an object, dropped when noise is
+    /// discarded.
+    Noise { text: String },
+}
+
+/// Splits stdout bytes into JSON values.
+#[derive(Debug)]
+pub struct JsonStreamDecoder {
+    pending: Vec<u8>,
+    /// First non-whitespace byte of `pending`.
+    first: Option<u8>,
+    depth: usize,
+    in_string: bool,
+    escaped: bool,
+    /// Dropping the remainder of an overflowing line.
+    discarding: bool,
+    /// Drop lines that do not start with `{` instead of buffering them.
+    discard_noise: bool,
+    max_buffer_size: usize,
+}
+
+impl JsonStreamDecoder {
+    pub fn new(max_buffer_size: usize) -> Self {
+        Self {
+            pending: Vec::new(),
+            first: None,
+            depth: 0,
+            in_string: false,
+            escaped: false,
+            discarding: false,
+            discard_noise: false,
+            max_buffer_size,
+        }
+    }
+
+    /// Report lines that cannot begin a message as [`DecodeError::Noise`]
+    /// and drop them, rather than keeping them as the start of a value.
+    pub fn discarding_noise(mut self) -> Self {
+        self.discard_noise = true;
+        self
+    }
+
+    /// Feed bytes read from stdout, returning every value they complete.
+    pub fn push(&mut self, mut bytes: &[u8]) -> Vec<Result<Value, DecodeError>> {
+        let mut decoded = Vec::new();
+        while !bytes.is_empty() {
+            let (segment, line_end) = match bytes.iter().position(|byte| *byte == b'\n') {
+                Some(index) => {
+                    let segment = &bytes[..index];
+                    bytes = &bytes[index + 1..];
+                    (segment, true)
+                }
+                None => (std::mem::take(&mut bytes), false),
+            };
+
+            if !self.discarding {
+                self.scan(segment);
+                self.pending.extend_from_slice(segment);
+                if self.pending.len() > self.max_buffer_size {
+                    decoded.push(Err(DecodeError::Overflow {
+                        text: String::from_utf8_lossy(&self.pending).into_owned(),
+                        len: self.pending.len(),
+                        limit: self.max_buffer_size,
+                    }));
+                    self.reset();
+                    self.discarding = !line_end;
+                    continue;
+                }
+            }
+            if line_end {
+                if self.discarding {
+                    self.discarding = false;
+                } else {
+                    self.end_line(&mut decoded);
+                }
+            }
+        }
+        decoded
+    }
+
+    /// Decode a last line that was not newline-terminated. An unfinished
+    /// object is dropped.
+    pub fn finish(&mut self) -> Vec<Result<Value, DecodeError>> {
+        let mut decoded = Vec::new();
+        if !self.discarding {
+            self.end_line(&mut decoded);
+        }
+        self.reset();
+        decoded
+    }
+
+    /// Bytes waiting for the rest of their message.
+    pub fn buffered(&self) -> usize {
+        self.pending.len()
+    }
+
+    fn scan(&mut self, segment: &[u8]) {
+        for &byte in segment {
+            if self.first.is_none() {
+                if byte.is_ascii_whitespace() {
+                    continue;
+                }
+                self.first = Some(byte);
+            }
+            if self.in_string {
+                if self.escaped {
+                    self.escaped = false;
+                } else if byte == b'\\' {
+                    self.escaped = true;
+                } else if byte == b'"' {
+                    self.in_string = false;
+                }
+                continue;
+            }
+            match byte {
+                b'"' => self.in_string = true,
+                b'{' | b'[' => self.depth += 1,
+                b'}' | b']' => self.depth = self.depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+
+    fn end_line(&mut self, decoded: &mut Vec<Result<Value, DecodeError>>) {
+        if self.discard_noise && self.first.is_some_and(|first| first != b'{') {
+            decoded.push(Err(DecodeError::Noise {
+                text: String::from_utf8_lossy(&self.pending).trim().to_string(),
+            }));
+            self.reset();
+            return;
+        }
+        match self.first {
+            None => self.pending.clear(),
+            // The object continues on the next line.
+            Some(b'{' | b'[') if self.depth > 0 || self.in_string => {}
+            Some(b'{' | b'[') => {
+                let values = serde_json::Deserializer::from_slice(&self.pending).into_iter();
+                for value in values {
+                    match value {
+                        Ok(value) => decoded.push(Ok(value)),
+                        Err(error) => {
+                            decoded.push(Err(DecodeError::Invalid {
+                                text: String::from_utf8_lossy(&self.pending).into_owned(),
+                                error,
+                            }));
+                            break;
+                        }
+                    }
+                }
+                self.reset();
+            }
+            // Anything else is kept until the lines so far parse as a value.
+            Some(_) => {
+                if let Ok(value) = serde_json::from_slice(&self.pending) {
+                    decoded.push(Ok(value));
+                    self.reset();
+                }
+            }
+        }
+    }
+
+    fn reset(&mut self) {
+        self.pending.clear();
+        self.first = None;
+        self.depth = 0;
+        self.in_string = false;
+        self.escaped = false;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn values(decoded: Vec<Result<Value, DecodeError>>) -> Vec<Value> {
+        decoded.into_iter().map(Result::unwrap).collect()
+    }
+
+    #[test]
+    fn values_split_across_reads_and_lines() {
+        let mut decoder = JsonStreamDecoder::new(1024);
+        assert!(decoder
+            .push(br#"{"type":"assistant","text":"a } \" {""#)
+            .is_empty());
+        assert!(decoder.push(b"\n").is_empty());
+        assert_eq!(
+            values(decoder.push(b"}\n{\"n\":1}{\"n\":2}\n  \n[3]")),
+            [
+                json!({"type": "assistant", "text": "a } \" {"}),
+                json!({"n": 1}),
+                json!({"n": 2}),
+            ]
+        );
+        assert_eq!(decoder.buffered(), 3);
+        assert_eq!(values(decoder.finish()), [json!([3])]);
+    }
+
+    #[test]
+    fn oversized_and_invalid_lines_are_reported() {
+        let mut decoder = JsonStreamDecoder::new(16);
+        let decoded = decoder.push(b"{\"text\":\"0123456789");
+        assert!(matches!(
+            decoded.as_slice(),
+            [Err(DecodeError::Overflow {
+                len: 19,
+                limit: 16,
+                ..
+            })]
+        ));
+        assert!(decoder.push(b"abcdef\"}").is_empty());
+        assert_eq!(values(decoder.push(b"\n{\"ok\":1}\n")), [json!({"ok": 1})]);
+
+        let decoded = decoder.push(b"{\"a\":}\n{\"b\":2}\n");
+        assert!(
+            matches!(&decoded[0], Err(DecodeError::Invalid { text, .. }) if text == "{\"a\":}")
+        );
+        assert_eq!(decoded[1].as_ref().unwrap(), &json!({"b": 2}));
+    }
+
+    #[test]
+    fn noise_lines_are_dropped_when_discarding_noise() {
+        let mut decoder = JsonStreamDecoder::new(64).discarding_noise();
+        let decoded = decoder.push(b"Plugin ready v1.2\n[warn] no config\n{\"ok\":");
+        assert!(
+            matches!(&decoded[..], [Err(DecodeError::Noise { text: first }), Err(DecodeError::Noise { text: second })]
+                if first == "Plugin ready v1.2" && second == "[warn] no config")
+        );
+        assert_eq!(values(decoder.push(b"1}\n")), [json!({"ok": 1})]);
+        assert_eq!(decoder.buffered(), 0);
+    }
+}
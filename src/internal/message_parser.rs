@@ -1,6 +1,7 @@
 //! Parse raw CLI JSON messages into strongly typed structures.
 
-use serde_json::Value;
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
 
 use crate::error::{MessageParseError, SdkError};
 use crate::message::{
@@ -134,6 +135,20 @@ fn parse_system_message(raw: &Value) -> Result<Message, SdkError> {
     Ok(Message::System(SystemMessage { subtype, data }))
 }
 
+/// Result fields parsed into dedicated [`ResultMessage`] fields.
+const RESULT_FIELDS: &[&str] = &[
+    "type",
+    "subtype",
+    "duration_ms",
+    "duration_api_ms",
+    "is_error",
+    "num_turns",
+    "session_id",
+    "total_cost_usd",
+    "usage",
+    "result",
+];
+
 fn parse_result_message(raw: &Value) -> Result<Message, SdkError> {
     let subtype = raw
         .get("subtype")
@@ -160,6 +175,14 @@ fn parse_result_message(raw: &Value) -> Result<Message, SdkError> {
         .and_then(Value::as_str)
         .map(|s| s.to_string());
 
+    // Extension fields are optional; malformed ones stay in `extra` as sent.
+    let mut extra = raw.as_object().cloned().unwrap_or_default();
+    for key in RESULT_FIELDS {
+        extra.remove(*key);
+    }
+    let permission_denials = take_extension(&mut extra, "permission_denials").unwrap_or_default();
+    let model_usage = take_extension(&mut extra, "modelUsage").unwrap_or_default();
+
     Ok(Message::Result(ResultMessage {
         subtype,
         duration_ms,
@@ -170,9 +193,25 @@ fn parse_result_message(raw: &Value) -> Result<Message, SdkError> {
         total_cost_usd,
         usage,
         result,
+        permission_denials,
+        model_usage,
+        extra,
     }))
 }
 
+/// Remove `key` from `extra` and parse it, putting it back when it does not
+/// parse.
+fn take_extension<T: DeserializeOwned>(extra: &mut Map<String, Value>, key: &str) -> Option<T> {
+    let value = extra.remove(key)?;
+    match serde_json::from_value(value.clone()) {
+        Ok(parsed) => Some(parsed),
+        Err(_) => {
+            extra.insert(key.to_string(), value);
+            None
+        }
+    }
+}
+
 fn parse_stream_event(raw: &Value) -> Result<Message, SdkError> {
     let uuid = raw
         .get("uuid")
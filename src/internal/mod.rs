@@ -1,5 +1,41 @@
 //! Internal implementation details mirroring the Python SDK's `_internal` package.
+//!
+//! Nothing here is covered by semver; [`RawSession`](crate::raw::RawSession)
+//! is the stable way to drive the control protocol directly.
 
 pub mod client;
+pub mod json_stream;
 pub mod message_parser;
 pub mod query;
+pub(crate) mod trace;
+
+/// The user's home directory.
+#[cfg(feature = "dirs")]
+pub(crate) fn home_dir() -> Option<std::path::PathBuf> {
+    dirs::home_dir()
+}
+
+/// The user's home directory, from `HOME` (`USERPROFILE` on Windows).
+#[cfg(not(feature = "dirs"))]
+pub(crate) fn home_dir() -> Option<std::path::PathBuf> {
+    let var = if cfg!(windows) { "USERPROFILE" } else { "HOME" };
+    std::env::var_os(var)
+        .filter(|home| !home.is_empty())
+        .map(std::path::PathBuf::from)
+}
+
+/// Process-unique id for control requests and generated tool uses.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn unique_id() -> String {
+    uuid::Uuid::now_v7().simple().to_string()
+}
+
+/// wasm32 has no OS random source to seed UUIDs; ids only need to be unique
+/// within the session, so a counter will do.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn unique_id() -> String {
+    use std::sync::atomic::{AtomicU64, Ordering};
+
+    static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+    format!("{:032x}", NEXT_ID.fetch_add(1, Ordering::Relaxed))
+}
@@ -1,31 +1,144 @@
 //! Core control protocol handling for the SDK.
 
-use std::collections::HashMap;
-use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
-use std::sync::Arc;
-use std::time::{Duration, SystemTime, UNIX_EPOCH};
-
-use futures::{Stream, StreamExt};
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::panic::AssertUnwindSafe;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Weak};
+use std::time::{Duration, SystemTime};
+
+use futures::{FutureExt, Stream, StreamExt};
 use serde_json::{json, Map, Value};
-use tokio::sync::{mpsc, oneshot, Mutex};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, Notify};
 use tokio::task::JoinHandle;
-use tokio::time::timeout;
-
-use crate::error::SdkError;
-use crate::hooks::{HookCallback, HookContext, HookEvent, HookInput, HookMatcher};
+use tokio::time::Instant;
+
+use crate::buffer_limit::{BufferCharge, BufferLimit, Overdraft};
+use crate::clock::{self, ClockHandle};
+use crate::config::{ControlWatchdogConfig, InFlightPolicy};
+use crate::error::{CallbackPanic, ControlError, ErrorKind, SdkError, TimeoutOperation};
+use crate::frame_log::{
+    ControlFrameRecord, ControlFrameSink, ControlFrameSinkHandle, FrameDirection, FrameOutcome,
+    InMemoryFrameLog,
+};
+use crate::hooks::{
+    HookCallback, HookContext, HookEvent, HookInput, HookMatcher, HookRegistrationConfig,
+    HooksRegistration,
+};
 use crate::internal::message_parser;
-use crate::mcp::{McpToolCallResult, McpToolContent, McpToolInfo, SdkMcpServer};
-use crate::message::Message;
+use crate::internal::trace::{sdk_debug, sdk_error, sdk_record, sdk_warn};
+use crate::mcp::{
+    McpCompletion, McpFallbackHandle, McpPromptInfo, McpPromptResult, McpResourceContents,
+    McpResourceInfo, McpResourceTemplateInfo, McpToolCallResult, McpToolContent, McpToolInfo,
+    SdkMcpServer, ToolInvocationContext,
+};
+use crate::message::{ContentBlock, Message, RawMessage, SystemMessage};
+use crate::metrics::SdkMetricsHandle;
+use crate::middleware::{self, PromptMiddlewareHandle};
 use crate::permission::{
     CanUseToolCallback, PermissionMode, PermissionResult, PermissionUpdate, ToolPermissionContext,
 };
+use crate::rate_limit::SessionPermit;
+use crate::redact::RedactorHandle;
+use crate::spill::{MessageSpill, SpillQueue, Spilled};
 use crate::transport::Transport;
+use crate::truncation::ToolResultLimit;
 
 const CONTROL_REQUEST_TIMEOUT: Duration = Duration::from_secs(60);
 const MESSAGE_CHANNEL_CAPACITY: usize = 100;
+/// Messages the reader may queue for delivery before it waits for the
+/// consumer, unless a control response is outstanding.
+const READ_AHEAD_LIMIT: usize = 1_000;
+const SYSTEM_EVENT_CAPACITY: usize = 32;
+const MESSAGE_EVENT_CAPACITY: usize = 256;
+const RECENT_TRANSCRIPT_CAPACITY: usize = 1_000;
+const RECENT_FRAME_CAPACITY: usize = 200;
+/// Subagent tool uses remembered to attribute SDK MCP tool calls.
+const SUBAGENT_TOOL_USE_CAPACITY: usize = 256;
+
+/// Snapshot of session activity observed on the message stream.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct QueryActivity {
+    /// Session id reported by the CLI, once seen.
+    pub session_id: Option<String>,
+    /// Model reported by the init message or the latest assistant message.
+    pub model: Option<String>,
+    /// Number of result messages received so far.
+    pub completed_turns: u64,
+    /// Whether a prompt has been sent without a result message following it.
+    pub in_flight: bool,
+    /// Time of the last message written to or read from the CLI.
+    pub last_activity: Option<SystemTime>,
+}
+
+impl QueryActivity {
+    fn observe(&mut self, message: &Message, now: SystemTime) {
+        self.last_activity = Some(now);
+        match message {
+            Message::System(system) => {
+                if let Some(id) = system.data.get("session_id").and_then(Value::as_str) {
+                    self.session_id = Some(id.to_string());
+                }
+                if let Some(model) = system.data.get("model").and_then(Value::as_str) {
+                    self.model = Some(model.to_string());
+                }
+            }
+            Message::Assistant(assistant) => {
+                self.model = Some(assistant.model.clone());
+            }
+            Message::Result(result) => {
+                self.session_id = Some(result.session_id.clone());
+                self.completed_turns += 1;
+                self.in_flight = false;
+            }
+            Message::StreamEvent(event) => {
+                self.session_id = Some(event.session_id.clone());
+            }
+            Message::User(_) => {}
+        }
+    }
+}
 
 type ControlResponder = oneshot::Sender<Result<Value, SdkError>>;
+
+/// Control request awaiting a response from the CLI.
+struct PendingControl {
+    responder: ControlResponder,
+    subtype: String,
+    started: Instant,
+    warned: bool,
+    /// Lifts the buffer limit until the response arrives.
+    _overdraft: Option<Overdraft>,
+}
 type HookCallbackHandle = Arc<dyn HookCallback>;
+/// A message read from the CLI on its way to [`Query::delivery_loop`], with
+/// the buffer space it holds.
+type Delivery = Result<(Value, Option<BufferCharge>), SdkError>;
+/// An item waiting for [`Query::next_message`].
+// Almost every item is a message, so boxing it would not save space.
+#[allow(clippy::large_enum_variant)]
+enum QueuedMessage {
+    /// A message as read from the CLI and its parsed form. The charge
+    /// releases its buffer space when the item is dropped.
+    Message {
+        raw: Value,
+        parsed: Result<Message, SdkError>,
+        _charge: Option<BufferCharge>,
+    },
+    /// An error ending the stream.
+    Error(SdkError),
+}
+
+impl QueuedMessage {
+    fn is_result(&self) -> bool {
+        match self {
+            QueuedMessage::Message { raw, .. } => {
+                raw.get("type").and_then(Value::as_str) == Some("result")
+            }
+            QueuedMessage::Error(_) => false,
+        }
+    }
+}
 type ToolPermissionCallbackHandle = Arc<dyn CanUseToolCallback>;
 type McpServerHandle = Arc<dyn SdkMcpServer>;
 
@@ -45,19 +158,56 @@ impl<T: Transport + ?Sized> Clone for Query<T> {
 struct QueryInner<T: Transport + ?Sized> {
     transport: Arc<T>,
     is_streaming_mode: bool,
-    can_use_tool: Option<ToolPermissionCallbackHandle>,
+    can_use_tool: Mutex<Option<ToolPermissionCallbackHandle>>,
     hooks: Mutex<Option<HashMap<HookEvent, Vec<HookMatcher>>>>,
     sdk_mcp_servers: HashMap<String, McpServerHandle>,
-    pending_control: Mutex<HashMap<String, ControlResponder>>,
+    pending_control: Mutex<HashMap<String, PendingControl>>,
+    control_settled: Notify,
+    frame_sink: Mutex<Option<ControlFrameSinkHandle>>,
+    metrics: Mutex<Option<SdkMetricsHandle>>,
+    session_permit: Mutex<Option<SessionPermit>>,
+    tool_result_limit: Mutex<Option<ToolResultLimit>>,
+    prompt_middleware: Mutex<Vec<PromptMiddlewareHandle>>,
+    redactor: Mutex<Option<RedactorHandle>>,
+    clock: Mutex<ClockHandle>,
+    recent_frames: InMemoryFrameLog,
+    transcript: Mutex<VecDeque<Value>>,
+    activity: Mutex<QueryActivity>,
+    system_events: Mutex<Option<broadcast::Sender<SystemMessage>>>,
+    message_events: Mutex<Option<broadcast::Sender<Message>>>,
+    callback_panics: Mutex<Option<broadcast::Sender<CallbackPanic>>>,
     hook_callbacks: Mutex<HashMap<String, HookCallbackHandle>>,
-    message_tx: Mutex<Option<mpsc::Sender<Result<Message, SdkError>>>>,
-    message_rx: Mutex<mpsc::Receiver<Result<Message, SdkError>>>,
+    message_tx: Mutex<Option<mpsc::Sender<QueuedMessage>>>,
+    message_rx: Mutex<mpsc::Receiver<QueuedMessage>>,
+    buffer_limit: Mutex<Option<BufferLimit>>,
+    /// Messages past a full message channel, oldest first.
+    spill: Mutex<Option<SpillQueue<QueuedMessage>>>,
+    spill_room: Notify,
+    mcp_fallback: Mutex<Option<McpFallbackHandle>>,
     read_handle: Mutex<Option<JoinHandle<()>>>,
+    delivery_handle: Mutex<Option<JoinHandle<()>>>,
+    queued_deliveries: AtomicUsize,
+    read_room: Notify,
+    watchdog: Mutex<ControlWatchdogConfig>,
+    watchdog_handle: Mutex<Option<JoinHandle<()>>>,
     next_callback_id: AtomicU64,
-    request_counter: AtomicU64,
     initialized: AtomicBool,
     initialization_result: Mutex<Option<Value>>,
+    /// Session ids of prompts awaiting their result, oldest first.
+    in_flight_sessions: Mutex<VecDeque<String>>,
+    /// Session id of the prompt answered by the latest result.
+    last_settled_session: Mutex<Option<String>>,
+    /// Results queued for the consumer that it has not read yet.
+    unread_results: AtomicUsize,
+    /// Prompts sent per session id, for `session_turn_limit`.
+    session_turns: Mutex<HashMap<String, u32>>,
+    /// Recent subagent tool uses and the `Task` tool use they belong to.
+    subagent_tool_uses: Mutex<VecDeque<(String, String)>>,
+    permission_mode: Mutex<Option<PermissionMode>>,
+    prompt_settled: Notify,
     closed: AtomicBool,
+    input_closed: AtomicBool,
+    keep_input_open: AtomicBool,
 }
 
 impl<T> Query<T>
@@ -73,23 +223,57 @@ where
         sdk_mcp_servers: HashMap<String, McpServerHandle>,
     ) -> Self {
         let (message_tx, message_rx) = mpsc::channel(MESSAGE_CHANNEL_CAPACITY);
+        let (system_tx, _) = broadcast::channel(SYSTEM_EVENT_CAPACITY);
+        let (message_events_tx, _) = broadcast::channel(MESSAGE_EVENT_CAPACITY);
+        let (panic_tx, _) = broadcast::channel(SYSTEM_EVENT_CAPACITY);
         Self {
             inner: Arc::new(QueryInner {
                 transport,
                 is_streaming_mode,
-                can_use_tool,
+                can_use_tool: Mutex::new(can_use_tool),
                 hooks: Mutex::new(hooks),
                 sdk_mcp_servers,
                 pending_control: Mutex::new(HashMap::new()),
+                control_settled: Notify::new(),
+                frame_sink: Mutex::new(None),
+                metrics: Mutex::new(None),
+                session_permit: Mutex::new(None),
+                tool_result_limit: Mutex::new(None),
+                prompt_middleware: Mutex::new(Vec::new()),
+                redactor: Mutex::new(None),
+                clock: Mutex::new(clock::default_clock()),
+                recent_frames: InMemoryFrameLog::new(RECENT_FRAME_CAPACITY),
+                transcript: Mutex::new(VecDeque::new()),
+                activity: Mutex::new(QueryActivity::default()),
+                system_events: Mutex::new(Some(system_tx)),
+                message_events: Mutex::new(Some(message_events_tx)),
+                callback_panics: Mutex::new(Some(panic_tx)),
                 hook_callbacks: Mutex::new(HashMap::new()),
                 message_tx: Mutex::new(Some(message_tx)),
                 message_rx: Mutex::new(message_rx),
+                buffer_limit: Mutex::new(None),
+                spill: Mutex::new(None),
+                spill_room: Notify::new(),
+                mcp_fallback: Mutex::new(None),
                 read_handle: Mutex::new(None),
+                delivery_handle: Mutex::new(None),
+                queued_deliveries: AtomicUsize::new(0),
+                read_room: Notify::new(),
+                watchdog: Mutex::new(ControlWatchdogConfig::default()),
+                watchdog_handle: Mutex::new(None),
                 next_callback_id: AtomicU64::new(0),
-                request_counter: AtomicU64::new(0),
                 initialized: AtomicBool::new(false),
                 initialization_result: Mutex::new(None),
+                in_flight_sessions: Mutex::new(VecDeque::new()),
+                last_settled_session: Mutex::new(None),
+                unread_results: AtomicUsize::new(0),
+                session_turns: Mutex::new(HashMap::new()),
+                subagent_tool_uses: Mutex::new(VecDeque::new()),
+                permission_mode: Mutex::new(None),
+                prompt_settled: Notify::new(),
                 closed: AtomicBool::new(false),
+                input_closed: AtomicBool::new(false),
+                keep_input_open: AtomicBool::new(false),
             }),
         }
     }
@@ -104,10 +288,70 @@ where
         self.inner.closed.load(Ordering::SeqCst)
     }
 
+    /// Record every control request/response pair to the given sink.
+    pub async fn set_frame_sink(&self, sink: Option<ControlFrameSinkHandle>) {
+        *self.inner.frame_sink.lock().await = sink;
+    }
+
+    /// Install (or clear) the metrics receiver for this query.
+    pub async fn set_metrics(&self, metrics: Option<SdkMetricsHandle>) {
+        *self.inner.metrics.lock().await = metrics;
+    }
+
+    /// Hold `permit` until the query closes and charge result usage to its
+    /// rate limiter.
+    pub async fn set_session_permit(&self, permit: Option<SessionPermit>) {
+        *self.inner.session_permit.lock().await = permit;
+    }
+
+    /// Truncate oversized SDK MCP tool results and streamed `tool_result`
+    /// blocks before they reach the CLI.
+    pub async fn set_tool_result_limit(&self, limit: Option<ToolResultLimit>) {
+        *self.inner.tool_result_limit.lock().await = limit;
+    }
+
+    /// Count messages waiting for [`Query::next_message`] against `limit`.
+    pub async fn set_buffer_limit(&self, limit: Option<BufferLimit>) {
+        *self.inner.buffer_limit.lock().await = limit;
+    }
+
+    /// Write messages that do not fit the message channel to disk. Set it
+    /// before [`Query::start`].
+    pub async fn set_message_spill(&self, spill: Option<MessageSpill>) {
+        *self.inner.spill.lock().await = spill.map(SpillQueue::new);
+    }
+
+    /// Answer MCP messages for unknown SDK server names with `fallback`.
+    pub async fn set_mcp_fallback(&self, fallback: Option<McpFallbackHandle>) {
+        *self.inner.mcp_fallback.lock().await = fallback;
+    }
+
+    /// Rewrite user messages from [`Query::stream_input`] with `chain`.
+    pub async fn set_prompt_middleware(&self, chain: Vec<PromptMiddlewareHandle>) {
+        *self.inner.prompt_middleware.lock().await = chain;
+    }
+
+    /// Mask secrets in recorded control frames and CLI error messages.
+    pub async fn set_redactor(&self, redactor: Option<RedactorHandle>) {
+        *self.inner.redactor.lock().await = redactor;
+    }
+
+    /// Time source for control timeouts, the watchdog and timestamps. Set it
+    /// before [`Query::start`]; the watchdog keeps the clock it started with.
+    pub async fn set_clock(&self, clock: ClockHandle) {
+        *self.inner.clock.lock().await = clock;
+    }
+
+    /// Replace the permission callback; requests already being answered
+    /// keep the previous one.
+    pub async fn set_can_use_tool(&self, callback: Option<ToolPermissionCallbackHandle>) {
+        *self.inner.can_use_tool.lock().await = callback;
+    }
+
     /// Start the background reader if it has not already been started.
     pub async fn start(&self) -> Result<(), SdkError> {
         if self.inner.closed.load(Ordering::SeqCst) {
-            return Err(SdkError::Message("query is closed".into()));
+            return Err(SdkError::Cancelled("query is closed".into()));
         }
 
         let mut handle_guard = self.inner.read_handle.lock().await;
@@ -115,32 +359,50 @@ where
             return Ok(());
         }
 
+        let (delivery_tx, delivery_rx) = mpsc::unbounded_channel();
+        let inner = Arc::clone(&self.inner);
+        *self.inner.delivery_handle.lock().await = Some(tokio::spawn(async move {
+            Query { inner }.delivery_loop(delivery_rx).await;
+        }));
         let inner = Arc::clone(&self.inner);
         let handle = tokio::spawn(async move {
-            Query { inner }.read_loop().await;
+            Query { inner }.read_loop(delivery_tx).await;
         });
         *handle_guard = Some(handle);
+
+        let config = *self.inner.watchdog.lock().await;
+        let clock = self.clock().await;
+        let weak = Arc::downgrade(&self.inner);
+        *self.inner.watchdog_handle.lock().await =
+            Some(tokio::spawn(control_watchdog_loop(weak, config, clock)));
         Ok(())
     }
 
+    /// Configure the pending control request sweep. Takes effect on [`Query::start`].
+    pub async fn set_control_watchdog(&self, config: ControlWatchdogConfig) {
+        *self.inner.watchdog.lock().await = config;
+    }
+
+    /// Number of control requests sent by the SDK still awaiting a response.
+    pub async fn pending_control_count(&self) -> usize {
+        self.inner.pending_control.lock().await.len()
+    }
+
     /// Initialize the control protocol and register hooks when in streaming mode.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(name = "claude_sdk.initialize", skip_all)
+    )]
     pub async fn initialize(&self) -> Result<Option<Value>, SdkError> {
         if !self.inner.is_streaming_mode {
             return Ok(None);
         }
 
         self.start().await?;
-        let hooks_config = self.prepare_hooks_configuration().await?;
+        let hooks = self.prepare_hooks_configuration().await;
+        let request = json!({"subtype": "initialize", "hooks": hooks});
 
-        let mut request = Map::new();
-        request.insert("subtype".into(), Value::String("initialize".into()));
-        if let Some(config) = hooks_config {
-            request.insert("hooks".into(), config);
-        } else {
-            request.insert("hooks".into(), Value::Null);
-        }
-
-        let response = self.send_control_request(Value::Object(request)).await?;
+        let response = self.send_control_request(request).await?;
         self.inner.initialized.store(true, Ordering::SeqCst);
         {
             let mut guard = self.inner.initialization_result.lock().await;
@@ -150,41 +412,121 @@ where
     }
 
     /// Stream input messages to the transport.
+    ///
+    /// Failures are reported as [`SdkError::PromptStream`].
     pub async fn stream_input<S>(&self, mut input: S) -> Result<(), SdkError>
     where
         S: Stream<Item = Value> + Unpin + Send,
     {
-        log::debug!("[stream_input] Starting stream consumption");
-        let mut wrote_any = false;
-        while let Some(message) = input.next().await {
-            if self.inner.closed.load(Ordering::SeqCst) {
-                log::debug!("[stream_input] Query closed, breaking");
+        sdk_debug!("stream_input: starting stream consumption");
+        let mut delivered = 0;
+        let failed = |delivered, source| SdkError::PromptStream {
+            delivered,
+            source: Box::new(source),
+        };
+        let limit = *self.inner.tool_result_limit.lock().await;
+        let middleware = self.inner.prompt_middleware.lock().await.clone();
+        while let Some(mut message) = input.next().await {
+            if self.inner.closed.load(Ordering::SeqCst)
+                || self.inner.input_closed.load(Ordering::SeqCst)
+            {
+                sdk_debug!("stream_input: query closed, stopping");
                 break;
             }
-            log::debug!("[stream_input] Writing message to transport");
-            self.inner.transport.write(&message).await?;
-            wrote_any = true;
+            middleware::apply(&middleware, &mut message).map_err(|err| failed(delivered, err))?;
+            if limit.is_some_and(|limit| limit.apply_to_message(&mut message)) {
+                sdk_debug!("stream_input: truncated oversized tool result");
+            }
+            sdk_debug!("stream_input: writing message to transport");
+            self.inner
+                .transport
+                .write(&message)
+                .await
+                .map_err(|err| failed(delivered, err))?;
+            self.record_message_sent(&message).await;
+            if message.get("type").and_then(Value::as_str) == Some("user") {
+                self.mark_prompt_sent().await;
+            }
+            delivered += 1;
         }
-        if wrote_any {
-            log::debug!("[stream_input] Wrote messages, calling end_input");
-            self.inner.transport.end_input().await?;
+        if delivered > 0 && !self.inner.keep_input_open.load(Ordering::SeqCst) {
+            sdk_debug!("stream_input: input exhausted, calling end_input");
+            self.end_input()
+                .await
+                .map_err(|err| failed(delivered, err))?;
         } else {
-            log::debug!("[stream_input] No messages written, keeping stdin open");
+            sdk_debug!("stream_input: no messages written, keeping stdin open");
         }
-        log::debug!("[stream_input] Stream input completed");
+        sdk_debug!("stream_input: completed");
         Ok(())
     }
 
+    /// Hand a failure of a spawned [`Query::stream_input`] to the consumer,
+    /// then close the query.
+    pub(crate) async fn fail_input(&self, err: SdkError) {
+        sdk_warn!("prompt stream failed: {err}");
+        let _ = self.enqueue_error(err).await;
+        let _ = self.close().await;
+    }
+
     /// Retrieve the next SDK message, if available.
     pub async fn next_message(&self) -> Result<Option<Message>, SdkError> {
-        let mut receiver = self.inner.message_rx.lock().await;
-        match receiver.recv().await {
-            Some(Ok(message)) => Ok(Some(message)),
-            Some(Err(err)) => Err(err),
+        match self.receive_queued().await {
+            Some(QueuedMessage::Message { parsed, .. }) => parsed.map(Some),
+            Some(QueuedMessage::Error(err)) => Err(err),
             None => Ok(None),
         }
     }
 
+    /// Retrieve the next message as read from the CLI, with its parsed form
+    /// when it parses.
+    pub async fn next_raw_message(&self) -> Result<Option<RawMessage>, SdkError> {
+        match self.receive_queued().await {
+            Some(QueuedMessage::Message { raw, parsed, .. }) => Ok(Some(RawMessage {
+                value: raw,
+                message: parsed.ok(),
+            })),
+            Some(QueuedMessage::Error(err)) => Err(err),
+            None => Ok(None),
+        }
+    }
+
+    /// Next queued message, counting results as read.
+    async fn receive_queued(&self) -> Option<QueuedMessage> {
+        let queued = self.receive_queued_inner().await;
+        if queued.as_ref().is_some_and(QueuedMessage::is_result) {
+            self.inner.unread_results.fetch_sub(1, Ordering::SeqCst);
+            self.inner.prompt_settled.notify_waiters();
+        }
+        queued
+    }
+
+    /// Next queued message: from the channel, then from the spill, which
+    /// only holds messages newer than everything in the channel.
+    async fn receive_queued_inner(&self) -> Option<QueuedMessage> {
+        let mut receiver = self.inner.message_rx.lock().await;
+        if let Some(queue) = self.inner.spill.lock().await.as_mut() {
+            if let Ok(queued) = receiver.try_recv() {
+                return Some(queued);
+            }
+            let popped = queue.pop();
+            self.inner.spill_room.notify_waiters();
+            match popped {
+                Ok(Some(Spilled::Value(raw))) => {
+                    return Some(QueuedMessage::Message {
+                        parsed: message_parser::parse_message(&raw),
+                        raw,
+                        _charge: None,
+                    });
+                }
+                Ok(Some(Spilled::Held(queued))) => return Some(queued),
+                Ok(None) => {}
+                Err(err) => return Some(QueuedMessage::Error(err.into())),
+            }
+        }
+        receiver.recv().await
+    }
+
     /// Interrupt the current run via the control protocol.
     pub async fn interrupt(&self) -> Result<(), SdkError> {
         self.send_control_request(json!({ "subtype": "interrupt" }))
@@ -198,8 +540,14 @@ where
             "subtype": "set_permission_mode",
             "mode": mode.as_str(),
         }))
-        .await
-        .map(|_| ())
+        .await?;
+        self.record_permission_mode(Some(mode)).await;
+        Ok(())
+    }
+
+    /// Remember the session's permission mode for tool invocation contexts.
+    pub async fn record_permission_mode(&self, mode: Option<PermissionMode>) {
+        *self.inner.permission_mode.lock().await = mode;
     }
 
     /// Update the active model via the control protocol.
@@ -215,6 +563,16 @@ where
             .map(|_| ())
     }
 
+    /// Switch the output style via the control protocol.
+    pub async fn set_output_style(&self, style: &str) -> Result<(), SdkError> {
+        self.send_control_request(json!({
+            "subtype": "set_output_style",
+            "output_style": style,
+        }))
+        .await
+        .map(|_| ())
+    }
+
     /// Close the query and underlying transport, cancelling any pending work.
     pub async fn close(&self) -> Result<(), SdkError> {
         if self.inner.closed.swap(true, Ordering::SeqCst) {
@@ -226,13 +584,21 @@ where
             let _ = handle.await;
         }
 
-        {
-            let mut pending = self.inner.pending_control.lock().await;
-            for (_, responder) in pending.drain() {
-                let _ = responder.send(Err(SdkError::Message("query closed".into())));
-            }
+        if let Some(handle) = self.inner.delivery_handle.lock().await.take() {
+            handle.abort();
+            let _ = handle.await;
         }
 
+        if let Some(handle) = self.inner.watchdog_handle.lock().await.take() {
+            handle.abort();
+            let _ = handle.await;
+        }
+
+        self.cancel_pending_control("query closed").await;
+        self.inner.session_permit.lock().await.take();
+        self.inner.prompt_settled.notify_waiters();
+        self.inner.callback_panics.lock().await.take();
+
         {
             let mut tx_guard = self.inner.message_tx.lock().await;
             tx_guard.take();
@@ -241,39 +607,405 @@ where
         self.inner.transport.close().await
     }
 
+    /// Close the query after letting in-flight work settle.
+    ///
+    /// New input is refused and stdin is closed, then the reader keeps delivering
+    /// messages while pending control requests resolve. Once the CLI finishes
+    /// (or `drain_timeout` elapses) the transport is torn down as in
+    /// [`Query::close`].
+    ///
+    /// Delivery waits for room in the message channel, so the drain only
+    /// completes if another task keeps reading [`Query::next_message`]
+    /// meanwhile. Without one, the drain runs into `drain_timeout`: messages
+    /// already in the channel stay readable after this returns, and output
+    /// still queued behind them is dropped.
+    pub async fn close_graceful(&self, drain_timeout: Duration) -> Result<(), SdkError> {
+        if self.inner.closed.load(Ordering::SeqCst) {
+            return Ok(());
+        }
+
+        if !self.inner.input_closed.swap(true, Ordering::SeqCst) && self.inner.is_streaming_mode {
+            if let Err(err) = self.inner.transport.end_input().await {
+                sdk_debug!("close_graceful: end_input failed: {err}");
+            }
+        }
+
+        let clock = self.clock().await;
+        if clock::timeout(clock.as_ref(), drain_timeout, self.wait_for_drain())
+            .await
+            .is_none()
+        {
+            sdk_warn!("close_graceful: drain timed out after {drain_timeout:?}, closing anyway");
+        }
+
+        self.close().await
+    }
+
+    /// Keep stdin open when a [`Query::stream_input`] stream ends.
+    pub fn set_keep_input_open(&self, keep_open: bool) {
+        self.inner
+            .keep_input_open
+            .store(keep_open, Ordering::SeqCst);
+    }
+
+    /// Close the CLI's stdin; later calls do nothing.
+    pub async fn end_input(&self) -> Result<(), SdkError> {
+        if self.inner.input_closed.swap(true, Ordering::SeqCst) {
+            return Ok(());
+        }
+        self.inner.transport.end_input().await
+    }
+
+    /// Returns whether new input is still accepted by this query.
+    pub fn is_input_closed(&self) -> bool {
+        self.inner.input_closed.load(Ordering::SeqCst)
+    }
+
+    async fn wait_for_drain(&self) {
+        loop {
+            let settled = self.inner.control_settled.notified();
+            if self.inner.pending_control.lock().await.is_empty() {
+                break;
+            }
+            settled.await;
+        }
+
+        for handle in [&self.inner.read_handle, &self.inner.delivery_handle] {
+            let mut handle_guard = handle.lock().await;
+            if let Some(handle) = handle_guard.as_mut() {
+                let _ = handle.await;
+                handle_guard.take();
+            }
+        }
+    }
+
+    /// Subscribe to system messages as they are read from the CLI.
+    ///
+    /// Messages are still delivered through [`Query::next_message`]; this is a
+    /// side channel for callers waiting on a particular system event. The
+    /// receiver reports closure once the reader stops.
+    pub async fn subscribe_system_messages(
+        &self,
+    ) -> Result<broadcast::Receiver<SystemMessage>, SdkError> {
+        self.inner
+            .system_events
+            .lock()
+            .await
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+            .ok_or_else(|| SdkError::Cancelled("query is closed".into()))
+    }
+
+    /// Subscribe to every parsed message as it is read from the CLI.
+    ///
+    /// Like [`Query::subscribe_system_messages`], this does not consume
+    /// messages from [`Query::next_message`]. A receiver that falls more than
+    /// a few hundred messages behind skips ahead.
+    pub async fn subscribe_messages(&self) -> Result<broadcast::Receiver<Message>, SdkError> {
+        self.inner
+            .message_events
+            .lock()
+            .await
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+            .ok_or_else(|| SdkError::Cancelled("query is closed".into()))
+    }
+
+    /// Subscribe to panics caught in callbacks answering CLI control
+    /// requests. The receiver reports closure once the query closes.
+    pub async fn subscribe_callback_panics(
+        &self,
+    ) -> Result<broadcast::Receiver<CallbackPanic>, SdkError> {
+        self.inner
+            .callback_panics
+            .lock()
+            .await
+            .as_ref()
+            .map(broadcast::Sender::subscribe)
+            .ok_or_else(|| SdkError::Cancelled("query is closed".into()))
+    }
+
+    /// Snapshot of the session activity observed so far.
+    pub async fn activity(&self) -> QueryActivity {
+        self.inner.activity.lock().await.clone()
+    }
+
+    /// Register a prompt for `session_id`, applying `policy` when the
+    /// session's previous prompt has no result yet.
+    pub(crate) async fn begin_prompt(
+        &self,
+        session_id: &str,
+        policy: InFlightPolicy,
+    ) -> Result<(), SdkError> {
+        loop {
+            let settled = self.inner.prompt_settled.notified();
+            tokio::pin!(settled);
+            settled.as_mut().enable();
+            {
+                let mut sessions = self.inner.in_flight_sessions.lock().await;
+                let busy = sessions.iter().any(|pending| pending == session_id);
+                match policy {
+                    InFlightPolicy::Error if busy => {
+                        return Err(SdkError::QueryInFlight {
+                            session_id: session_id.to_string(),
+                        })
+                    }
+                    InFlightPolicy::Queue if busy => {}
+                    _ => {
+                        sessions.push_back(session_id.to_string());
+                        return Ok(());
+                    }
+                }
+            }
+            if self.is_closed() {
+                return Err(SdkError::Cancelled("query is closed".into()));
+            }
+            settled.await;
+        }
+    }
+
+    /// Register a prompt for `session_id` once no prompt of any session is
+    /// awaiting its result and every earlier result has been read, so the
+    /// next result read is this prompt's.
+    pub(crate) async fn begin_exclusive_prompt(&self, session_id: &str) -> Result<(), SdkError> {
+        loop {
+            let settled = self.inner.prompt_settled.notified();
+            tokio::pin!(settled);
+            settled.as_mut().enable();
+            {
+                let mut sessions = self.inner.in_flight_sessions.lock().await;
+                if sessions.is_empty() && self.inner.unread_results.load(Ordering::SeqCst) == 0 {
+                    sessions.push_back(session_id.to_string());
+                    return Ok(());
+                }
+            }
+            if self.is_closed() {
+                return Err(SdkError::Cancelled("query is closed".into()));
+            }
+            settled.await;
+        }
+    }
+
+    /// Whether a prompt awaits its result or a result awaits the consumer.
+    pub(crate) async fn has_unread_responses(&self) -> bool {
+        !self.inner.in_flight_sessions.lock().await.is_empty()
+            || self.inner.unread_results.load(Ordering::SeqCst) > 0
+    }
+
+    /// Forget a prompt registered with [`Query::begin_prompt`] that was
+    /// never sent.
+    pub(crate) async fn abandon_prompt(&self, session_id: &str) {
+        let mut sessions = self.inner.in_flight_sessions.lock().await;
+        if let Some(index) = sessions.iter().rposition(|pending| pending == session_id) {
+            sessions.remove(index);
+        }
+        drop(sessions);
+        self.inner.prompt_settled.notify_waiters();
+    }
+
+    /// Session id of the prompt the latest result answered.
+    pub(crate) async fn last_settled_session(&self) -> Option<String> {
+        self.inner.last_settled_session.lock().await.clone()
+    }
+
+    /// Prompts counted for `session_id` by [`Query::count_session_turn`].
+    pub(crate) async fn session_turns(&self, session_id: &str) -> u32 {
+        let turns = self.inner.session_turns.lock().await;
+        turns.get(session_id).copied().unwrap_or_default()
+    }
+
+    pub(crate) async fn count_session_turn(&self, session_id: &str) {
+        let mut turns = self.inner.session_turns.lock().await;
+        *turns.entry(session_id.to_string()).or_default() += 1;
+    }
+
+    pub(crate) async fn reset_session_turns(&self, session_id: &str) {
+        self.inner.session_turns.lock().await.remove(session_id);
+    }
+
+    /// Record that a user prompt was written outside of [`Query::stream_input`].
+    pub async fn mark_prompt_sent(&self) {
+        let mut activity = self.inner.activity.lock().await;
+        activity.in_flight = true;
+        activity.last_activity = Some(self.clock().await.wall_time());
+    }
+
+    pub(crate) async fn record_message_sent(&self, message: &Value) {
+        self.append_transcript(message.clone()).await;
+        if let Some(metrics) = self.metrics().await {
+            metrics.message_sent();
+        }
+    }
+
+    /// Most recent conversation messages written or read, oldest first.
+    ///
+    /// Control protocol traffic is excluded; see [`Query::recent_frames`].
+    pub async fn transcript(&self) -> Vec<Value> {
+        self.inner.transcript.lock().await.iter().cloned().collect()
+    }
+
+    /// Most recent control request/response exchanges, oldest first.
+    pub fn recent_frames(&self) -> Vec<ControlFrameRecord> {
+        self.inner.recent_frames.records()
+    }
+
+    async fn append_transcript(&self, message: Value) {
+        let mut transcript = self.inner.transcript.lock().await;
+        if transcript.len() == RECENT_TRANSCRIPT_CAPACITY {
+            transcript.pop_front();
+        }
+        transcript.push_back(message);
+    }
+
+    async fn metrics(&self) -> Option<SdkMetricsHandle> {
+        self.inner.metrics.lock().await.clone()
+    }
+
+    async fn cancel_pending_control(&self, reason: &str) {
+        {
+            let mut pending = self.inner.pending_control.lock().await;
+            for (_, entry) in pending.drain() {
+                let _ = entry
+                    .responder
+                    .send(Err(SdkError::Cancelled(reason.to_string())));
+            }
+        }
+        self.inner.control_settled.notify_waiters();
+    }
+
+    async fn clock(&self) -> ClockHandle {
+        self.inner.clock.lock().await.clone()
+    }
+
     /// Previously returned initialization payload, if initialization has completed.
     pub async fn initialization_result(&self) -> Option<Value> {
         self.inner.initialization_result.lock().await.clone()
     }
 
-    async fn read_loop(self) {
-        loop {
+    /// Read CLI output, answering control frames as they arrive and handing
+    /// everything else to [`Query::delivery_loop`], so a slow consumer never
+    /// holds up a control response the CLI or the SDK is waiting on.
+    async fn read_loop(self, deliveries: mpsc::UnboundedSender<Delivery>) {
+        let reason = loop {
             if self.inner.closed.load(Ordering::SeqCst) {
-                break;
+                break "query closed".to_string();
             }
-
-            match self.inner.transport.read().await {
-                Ok(Some(raw)) => {
-                    if let Err(err) = self.route_incoming_message(raw).await {
-                        let _ = self.enqueue_message(Err(err)).await;
-                        break;
+            self.wait_for_read_room(&deliveries).await;
+
+            match self.inner.transport.read_charged().await {
+                Ok(Some((raw, _charge))) if is_control_frame(&raw) => {
+                    if let Err(err) = self.route_incoming_message(raw, None).await {
+                        let reason = format!("CLI output stopped: {err}");
+                        let _ = self.deliver(&deliveries, Err(err));
+                        break reason;
+                    }
+                }
+                Ok(Some((raw, charge))) => {
+                    // Charged before queueing, so messages count against the
+                    // limit while they wait for the consumer.
+                    let limit = self.inner.buffer_limit.lock().await.clone();
+                    let charge = match (charge, limit) {
+                        (Some(charge), _) => Some(charge),
+                        (None, None) => None,
+                        (None, Some(limit)) => match limit.admit(&raw).await {
+                            Ok(Some(charge)) => Some(charge),
+                            Ok(None) => continue,
+                            Err(err) => {
+                                let reason = format!("CLI output stopped: {err}");
+                                let _ = self.deliver(&deliveries, Err(err));
+                                break reason;
+                            }
+                        },
+                    };
+                    if !self.deliver(&deliveries, Ok((raw, charge))) {
+                        break "message delivery stopped".to_string();
                     }
                 }
-                Ok(None) => break,
+                Ok(None) => break "CLI output ended".to_string(),
                 Err(err) => {
-                    let _ = self.enqueue_message(Err(err)).await;
-                    break;
+                    if err.kind() == ErrorKind::Parse {
+                        if let Some(metrics) = self.metrics().await {
+                            metrics.parse_failure();
+                        }
+                    }
+                    let reason = format!("CLI output stopped: {err}");
+                    let _ = self.deliver(&deliveries, Err(err));
+                    break reason;
                 }
             }
+        };
+
+        // Nothing can answer these any more; fail them now rather than at
+        // their timeout.
+        self.cancel_pending_control(&reason).await;
+    }
+
+    fn deliver(&self, deliveries: &mpsc::UnboundedSender<Delivery>, item: Delivery) -> bool {
+        self.inner.queued_deliveries.fetch_add(1, Ordering::SeqCst);
+        deliveries.send(item).is_ok()
+    }
+
+    /// Wait while [`READ_AHEAD_LIMIT`] messages are queued and no control
+    /// response is expected.
+    async fn wait_for_read_room(&self, deliveries: &mpsc::UnboundedSender<Delivery>) {
+        loop {
+            let room = self.inner.read_room.notified();
+            tokio::pin!(room);
+            room.as_mut().enable();
+            if deliveries.is_closed()
+                || self.inner.queued_deliveries.load(Ordering::SeqCst) < READ_AHEAD_LIMIT
+                || !self.inner.pending_control.lock().await.is_empty()
+            {
+                return;
+            }
+            room.await;
         }
+    }
+
+    async fn delivery_loop(self, mut deliveries: mpsc::UnboundedReceiver<Delivery>) {
+        while let Some(item) = deliveries.recv().await {
+            self.inner.queued_deliveries.fetch_sub(1, Ordering::SeqCst);
+            self.inner.read_room.notify_waiters();
+            let routed = match item {
+                Ok((raw, charge)) => self.route_incoming_message(raw, charge).await,
+                Err(err) => Err(err),
+            };
+            if let Err(err) = routed {
+                let _ = self.enqueue_error(err).await;
+                break;
+            }
+        }
+        deliveries.close();
+        self.inner.read_room.notify_waiters();
 
         {
             let mut tx_guard = self.inner.message_tx.lock().await;
             tx_guard.take();
         }
+        self.inner.system_events.lock().await.take();
+        self.inner.message_events.lock().await.take();
     }
 
-    async fn route_incoming_message(&self, raw: Value) -> Result<(), SdkError> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.route_message",
+            level = "trace",
+            skip_all,
+            fields(
+                message_type = raw
+                    .get("type")
+                    .and_then(serde_json::Value::as_str)
+                    .unwrap_or("unknown"),
+                session_id = tracing::field::Empty,
+            )
+        )
+    )]
+    async fn route_incoming_message(
+        &self,
+        raw: Value,
+        charge: Option<BufferCharge>,
+    ) -> Result<(), SdkError> {
         let message_type = raw.get("type").and_then(Value::as_str);
         match message_type {
             Some("control_response") => self.handle_control_response(raw).await,
@@ -283,8 +1015,59 @@ where
             }
             Some("control_cancel_request") => Ok(()),
             _ => {
+                self.append_transcript(raw.clone()).await;
                 let parsed = message_parser::parse_message(&raw);
-                self.enqueue_message(parsed).await
+                if let Some(metrics) = self.metrics().await {
+                    match &parsed {
+                        Ok(message) => {
+                            metrics.message_received(message_type.unwrap_or("unknown"));
+                            if let Message::Result(result) = message {
+                                metrics.query_completed(result);
+                            }
+                        }
+                        Err(_) => metrics.parse_failure(),
+                    }
+                }
+                if let Ok(Message::Result(result)) = &parsed {
+                    if let Some(permit) = self.inner.session_permit.lock().await.as_ref() {
+                        let clock = self.clock().await;
+                        permit.limiter().record_result(result, clock.as_ref());
+                    }
+                    let settled = self.inner.in_flight_sessions.lock().await.pop_front();
+                    if settled.is_some() {
+                        *self.inner.last_settled_session.lock().await = settled;
+                    }
+                    self.inner.prompt_settled.notify_waiters();
+                }
+                if message_type == Some("result") {
+                    self.inner.unread_results.fetch_add(1, Ordering::SeqCst);
+                }
+                if let Ok(message) = &parsed {
+                    let now = self.clock().await.wall_time();
+                    let mut activity = self.inner.activity.lock().await;
+                    activity.observe(message, now);
+                    if let Some(session_id) = activity.session_id.as_deref() {
+                        sdk_record!("session_id", session_id);
+                    }
+                    drop(activity);
+                    self.record_subagent_tool_uses(message).await;
+                    if let Message::System(system) = message {
+                        if let Some(sender) = self.inner.system_events.lock().await.as_ref() {
+                            let _ = sender.send(system.clone());
+                        }
+                    }
+                    if let Some(sender) = self.inner.message_events.lock().await.as_ref() {
+                        if sender.receiver_count() > 0 {
+                            let _ = sender.send(message.clone());
+                        }
+                    }
+                }
+                self.enqueue(QueuedMessage::Message {
+                    raw,
+                    parsed,
+                    _charge: charge,
+                })
+                .await
             }
         }
     }
@@ -296,20 +1079,60 @@ where
         });
     }
 
-    async fn enqueue_message(&self, payload: Result<Message, SdkError>) -> Result<(), SdkError> {
+    async fn enqueue_error(&self, err: SdkError) -> Result<(), SdkError> {
+        self.enqueue(QueuedMessage::Error(err)).await
+    }
+
+    async fn enqueue(&self, mut queued: QueuedMessage) -> Result<(), SdkError> {
         let sender = {
             let guard = self.inner.message_tx.lock().await;
             guard.as_ref().cloned()
         };
 
-        if let Some(sender) = sender {
-            sender
-                .send(payload)
-                .await
-                .map_err(|err| SdkError::Message(format!("failed to enqueue message: {err}")))
-        } else {
-            Ok(())
+        let Some(sender) = sender else {
+            return Ok(());
+        };
+        loop {
+            let room = self.inner.spill_room.notified();
+            tokio::pin!(room);
+            room.as_mut().enable();
+            {
+                let mut spill = self.inner.spill.lock().await;
+                let Some(queue) = spill.as_mut() else {
+                    break;
+                };
+                if queue.is_empty() {
+                    queued = match sender.try_send(queued) {
+                        Ok(()) => return Ok(()),
+                        Err(mpsc::error::TrySendError::Full(queued)) => queued,
+                        Err(mpsc::error::TrySendError::Closed(_)) => {
+                            return Err(SdkError::Message(
+                                "failed to enqueue message: channel closed".into(),
+                            ));
+                        }
+                    };
+                }
+                match &queued {
+                    // On disk the message no longer holds buffer space; it is
+                    // parsed again when read back.
+                    QueuedMessage::Message { raw, .. } => {
+                        if queue.push_value(raw)? {
+                            return Ok(());
+                        }
+                        sdk_debug!("message spill is full, waiting for the consumer");
+                    }
+                    QueuedMessage::Error(_) => {
+                        queue.push_held(queued);
+                        return Ok(());
+                    }
+                }
+            }
+            room.await;
         }
+        sender
+            .send(queued)
+            .await
+            .map_err(|err| SdkError::Message(format!("failed to enqueue message: {err}")))
     }
 
     async fn handle_control_response(&self, message: Value) -> Result<(), SdkError> {
@@ -317,33 +1140,41 @@ where
             .get("response")
             .and_then(Value::as_object)
             .cloned()
-            .ok_or_else(|| SdkError::Message("control response missing 'response' field".into()))?;
+            .ok_or_else(|| {
+                SdkError::Protocol("control response missing 'response' field".into())
+            })?;
 
         let request_id = response
             .get("request_id")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("control response missing request_id".into()))?
+            .ok_or_else(|| SdkError::Protocol("control response missing request_id".into()))?
             .to_string();
 
-        let subtype = response
-            .get("subtype")
-            .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("control response missing subtype".into()))?;
-
         let responder = {
             let mut guard = self.inner.pending_control.lock().await;
             guard.remove(&request_id)
         };
+        self.inner.control_settled.notify_waiters();
+
+        let Some(subtype) = response.get("subtype").and_then(Value::as_str) else {
+            let message = format!("control response for {request_id} missing subtype");
+            if let Some(PendingControl { responder, .. }) = responder {
+                let _ = responder.send(Err(SdkError::Protocol(message.clone())));
+            }
+            return Err(SdkError::Protocol(message));
+        };
 
-        if let Some(responder) = responder {
+        if let Some(PendingControl { responder, .. }) = responder {
             match subtype {
                 "error" => {
-                    let message = response
-                        .get("error")
-                        .and_then(Value::as_str)
-                        .unwrap_or("Unknown error")
-                        .to_string();
-                    let _ = responder.send(Err(SdkError::Message(message)));
+                    let mut response = response;
+                    if let Some(redactor) = self.inner.redactor.lock().await.as_ref() {
+                        response
+                            .values_mut()
+                            .for_each(|value| redactor.redact_value(value));
+                    }
+                    let error = ControlError::from_response(&response);
+                    let _ = responder.send(Err(SdkError::Control(error)));
                 }
                 _ => {
                     let payload = response.get("response").cloned().unwrap_or(Value::Null);
@@ -355,11 +1186,26 @@ where
         Ok(())
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.control_request",
+            skip_all,
+            fields(
+                direction = "inbound",
+                request_id = tracing::field::Empty,
+                subtype = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                outcome = tracing::field::Empty,
+            )
+        )
+    )]
     async fn process_control_request(&self, request: Value) {
         let request_id = match request.get("request_id").and_then(Value::as_str) {
             Some(id) => id.to_string(),
             None => return,
         };
+        sdk_record!("request_id", request_id.as_str());
 
         let payload = match request.get("request").and_then(Value::as_object).cloned() {
             Some(payload) => payload,
@@ -374,16 +1220,129 @@ where
             }
         };
 
-        match self.dispatch_control_request(&payload).await {
+        if let Some(subtype) = payload.get("subtype").and_then(Value::as_str) {
+            sdk_record!("subtype", subtype);
+        }
+        let started = FrameTimer::start(self.clock().await);
+        let result = match AssertUnwindSafe(self.dispatch_control_request(&payload))
+            .catch_unwind()
+            .await
+        {
+            Ok(result) => result,
+            Err(panic) => Err(self.report_callback_panic(&payload, panic).await),
+        };
+        sdk_record!("duration_ms", started.elapsed().as_millis() as u64);
+        sdk_record!("outcome", if result.is_ok() { "success" } else { "error" });
+        match result {
             Ok(response) => {
-                let _ = self.send_success_response(&request_id, response).await;
+                let _ = self
+                    .send_success_response(&request_id, response.clone())
+                    .await;
+                self.record_frame(
+                    started,
+                    FrameDirection::Inbound,
+                    &request_id,
+                    Value::Object(payload),
+                    Some(response),
+                    FrameOutcome::Success,
+                )
+                .await;
             }
             Err(err) => {
-                let _ = self.send_error_response(&request_id, err.to_string()).await;
+                let message = err.to_string();
+                let _ = self.send_error_response(&request_id, message.clone()).await;
+                self.record_frame(
+                    started,
+                    FrameDirection::Inbound,
+                    &request_id,
+                    Value::Object(payload),
+                    None,
+                    FrameOutcome::Error(message),
+                )
+                .await;
             }
         }
     }
 
+    /// Turn a panic raised while dispatching `payload` into an error for the
+    /// CLI, notifying panic subscribers.
+    async fn report_callback_panic(
+        &self,
+        payload: &Map<String, Value>,
+        panic: Box<dyn std::any::Any + Send>,
+    ) -> SdkError {
+        let field = |key: &str| {
+            payload
+                .get(key)
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+        };
+        let subtype = field("subtype");
+        let callback = match subtype {
+            "can_use_tool" => field("tool_name"),
+            "hook_callback" => field("callback_id"),
+            "mcp_message" => field("server_name"),
+            _ => "unknown",
+        };
+        let message = panic
+            .downcast_ref::<&str>()
+            .map(|message| message.to_string())
+            .or_else(|| panic.downcast_ref::<String>().cloned())
+            .unwrap_or_else(|| "non-string panic payload".into());
+        let panic = CallbackPanic {
+            subtype: subtype.to_string(),
+            callback: callback.to_string(),
+            message,
+        };
+        sdk_error!("{panic}");
+        if let Some(sender) = self.inner.callback_panics.lock().await.as_ref() {
+            let _ = sender.send(panic.clone());
+        }
+        panic.into()
+    }
+
+    async fn record_frame(
+        &self,
+        started: FrameTimer,
+        direction: FrameDirection,
+        request_id: &str,
+        request: Value,
+        response: Option<Value>,
+        outcome: FrameOutcome,
+    ) {
+        let latency = started.elapsed();
+        let subtype = request
+            .get("subtype")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        if let Some(metrics) = self.metrics().await {
+            metrics.control_request(&subtype, direction, latency, outcome.is_success());
+        }
+
+        let (mut request, mut response) = (request, response);
+        if let Some(redactor) = self.inner.redactor.lock().await.as_ref() {
+            redactor.redact_value(&mut request);
+            if let Some(response) = response.as_mut() {
+                redactor.redact_value(response);
+            }
+        }
+        let record = ControlFrameRecord {
+            request_id: request_id.to_string(),
+            subtype,
+            direction,
+            request,
+            response,
+            outcome,
+            started_at: started.wall,
+            latency,
+        };
+        self.inner.recent_frames.record(&record);
+        if let Some(sink) = self.inner.frame_sink.lock().await.clone() {
+            sink.record(&record);
+        }
+    }
+
     async fn dispatch_control_request(
         &self,
         payload: &Map<String, Value>,
@@ -391,18 +1350,26 @@ where
         let subtype = payload
             .get("subtype")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("control request missing subtype".into()))?;
+            .ok_or_else(|| SdkError::Protocol("control request missing subtype".into()))?;
 
         match subtype {
             "can_use_tool" => self.handle_permission_request(payload).await,
             "hook_callback" => self.handle_hook_callback(payload).await,
             "mcp_message" => self.handle_mcp_message(payload).await,
-            other => Err(SdkError::Message(format!(
+            other => Err(SdkError::Protocol(format!(
                 "unsupported control request subtype: {other}",
             ))),
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.can_use_tool",
+            skip_all,
+            fields(tool = tracing::field::Empty, behavior = tracing::field::Empty)
+        )
+    )]
     async fn handle_permission_request(
         &self,
         payload: &Map<String, Value>,
@@ -410,19 +1377,22 @@ where
         let callback = self
             .inner
             .can_use_tool
-            .as_ref()
-            .ok_or_else(|| SdkError::Message("canUseTool callback is not provided".into()))?;
+            .lock()
+            .await
+            .clone()
+            .ok_or_else(|| SdkError::InvalidConfig("canUseTool callback is not provided".into()))?;
 
         let tool_name = payload
             .get("tool_name")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("permission request missing tool_name".into()))?;
+            .ok_or_else(|| SdkError::Protocol("permission request missing tool_name".into()))?;
+        sdk_record!("tool", tool_name);
 
         let input_value = payload
             .get("input")
             .and_then(Value::as_object)
             .cloned()
-            .ok_or_else(|| SdkError::Message("permission request missing input".into()))?;
+            .ok_or_else(|| SdkError::Protocol("permission request missing input".into()))?;
 
         let suggestions_raw = payload
             .get("permission_suggestions")
@@ -444,20 +1414,22 @@ where
                 updated_input,
                 updated_permissions,
             } => {
+                sdk_record!("behavior", "allow");
                 let mut response = Map::new();
                 response.insert("behavior".into(), Value::String("allow".into()));
                 let final_input = updated_input.unwrap_or(input_value);
                 response.insert("updatedInput".into(), Value::Object(final_input));
                 if let Some(updates) = updated_permissions {
                     let serialized = updates
-                        .into_iter()
-                        .map(|update| update.to_control_payload())
-                        .collect();
+                        .iter()
+                        .map(PermissionUpdate::to_control_payload)
+                        .collect::<Result<Vec<_>, _>>()?;
                     response.insert("updatedPermissions".into(), Value::Array(serialized));
                 }
                 Ok(Value::Object(response))
             }
             PermissionResult::Deny { message, interrupt } => {
+                sdk_record!("behavior", "deny");
                 let mut response = Map::new();
                 response.insert("behavior".into(), Value::String("deny".into()));
                 if !message.is_empty() {
@@ -471,12 +1443,36 @@ where
         }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.hook_callback",
+            skip_all,
+            fields(
+                callback_id = tracing::field::Empty,
+                hook_event = tracing::field::Empty,
+                tool_use_id = tracing::field::Empty,
+            )
+        )
+    )]
     async fn handle_hook_callback(&self, payload: &Map<String, Value>) -> Result<Value, SdkError> {
         let callback_id = payload
             .get("callback_id")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("hook callback missing callback_id".into()))?
+            .ok_or_else(|| SdkError::Protocol("hook callback missing callback_id".into()))?
             .to_string();
+        sdk_record!("callback_id", callback_id.as_str());
+        if let Some(event) = payload
+            .get("input")
+            .and_then(|input| {
+                input
+                    .get("hook_event_name")
+                    .or_else(|| input.get("hookEventName"))
+            })
+            .and_then(Value::as_str)
+        {
+            sdk_record!("hook_event", event);
+        }
 
         let callback = {
             let callbacks = self.inner.hook_callbacks.lock().await;
@@ -489,6 +1485,7 @@ where
         let input_value = payload
             .get("input")
             .cloned()
+            .map(normalize_hook_input)
             .unwrap_or_else(|| Value::Object(Map::new()));
         let hook_input: HookInput = serde_json::from_value(input_value)?;
 
@@ -496,6 +1493,9 @@ where
             .get("tool_use_id")
             .and_then(Value::as_str)
             .map(|s| s.to_string());
+        if let Some(tool_use_id) = tool_use_id.as_deref() {
+            sdk_record!("tool_use_id", tool_use_id);
+        }
 
         let output = callback
             .call(hook_input, tool_use_id, HookContext { signal: None })
@@ -505,41 +1505,181 @@ where
         Ok(convert_hook_output_for_cli(output_value))
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.mcp_message",
+            skip_all,
+            fields(server = tracing::field::Empty, method = tracing::field::Empty)
+        )
+    )]
     async fn handle_mcp_message(&self, payload: &Map<String, Value>) -> Result<Value, SdkError> {
         let server_name = payload
             .get("server_name")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("MCP request missing server_name".into()))?;
+            .ok_or_else(|| SdkError::Protocol("MCP request missing server_name".into()))?;
+        sdk_record!("server", server_name);
 
         let message_value = payload
             .get("message")
             .cloned()
-            .ok_or_else(|| SdkError::Message("MCP request missing message payload".into()))?;
+            .ok_or_else(|| SdkError::Protocol("MCP request missing message payload".into()))?;
 
         let message = message_value
             .as_object()
             .cloned()
-            .ok_or_else(|| SdkError::Message("MCP message must be an object".into()))?;
+            .ok_or_else(|| SdkError::Protocol("MCP message must be an object".into()))?;
 
-        let server = self
-            .inner
-            .sdk_mcp_servers
-            .get(server_name)
-            .cloned()
-            .ok_or_else(|| SdkError::Message(format!("Server '{server_name}' not found")))?;
+        let Some(server) = self.inner.sdk_mcp_servers.get(server_name).cloned() else {
+            let fallback = self.inner.mcp_fallback.lock().await.clone();
+            if let Some(fallback) = fallback {
+                return fallback.handle(server_name, message).await;
+            }
+            let mut known: Vec<&str> = self
+                .inner
+                .sdk_mcp_servers
+                .keys()
+                .map(String::as_str)
+                .collect();
+            known.sort_unstable();
+            let known = if known.is_empty() {
+                "no SDK MCP servers are registered".to_string()
+            } else {
+                format!("SDK MCP servers: {}", known.join(", "))
+            };
+            return Err(SdkError::Message(format!(
+                "Server '{server_name}' not found; {known}"
+            )));
+        };
 
         let method = message
             .get("method")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("MCP message missing method".into()))?;
+            .ok_or_else(|| SdkError::Protocol("MCP message missing method".into()))?;
+        sdk_record!("method", method);
+
+        let id_value = message.get("id").cloned().unwrap_or(Value::Null);
+        let params = message
+            .get("params")
+            .and_then(Value::as_object)
+            .cloned()
+            .unwrap_or_default();
 
         match method {
             "initialize" => Ok(build_mcp_initialize_response(&message, &server)),
+            "ping" => Ok(jsonrpc_result(id_value, Value::Object(Map::new()))),
             "tools/list" => self.mcp_list_tools(&message, server).await,
             "tools/call" => self.mcp_call_tool(&message, server).await,
-            "notifications/initialized" => Ok(json!({ "jsonrpc": "2.0", "result": {} })),
+            "resources/list" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let resources = server.list_resources().await?;
+                    Ok(json!({ "resources": convert_mcp_resource_list(resources) }))
+                }
+                .await,
+            )),
+            "resources/templates/list" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let templates = server.list_resource_templates().await?;
+                    Ok(json!({ "resourceTemplates": convert_mcp_resource_templates(templates) }))
+                }
+                .await,
+            )),
+            "resources/read" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let uri = required_str_param(&params, "uri")?;
+                    let contents = server.read_resource(uri).await?;
+                    Ok(json!({ "contents": convert_mcp_resource_contents(contents) }))
+                }
+                .await,
+            )),
+            "resources/subscribe" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let uri = required_str_param(&params, "uri")?;
+                    server.subscribe_resource(uri).await?;
+                    Ok(Value::Object(Map::new()))
+                }
+                .await,
+            )),
+            "resources/unsubscribe" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let uri = required_str_param(&params, "uri")?;
+                    server.unsubscribe_resource(uri).await?;
+                    Ok(Value::Object(Map::new()))
+                }
+                .await,
+            )),
+            "prompts/list" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let prompts = server.list_prompts().await?;
+                    Ok(json!({ "prompts": convert_mcp_prompt_list(prompts) }))
+                }
+                .await,
+            )),
+            "prompts/get" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let name = required_str_param(&params, "name")?;
+                    let arguments = params
+                        .get("arguments")
+                        .and_then(Value::as_object)
+                        .cloned()
+                        .unwrap_or_default();
+                    let prompt = server.get_prompt(name, arguments).await?;
+                    Ok(convert_mcp_prompt_result(prompt))
+                }
+                .await,
+            )),
+            "logging/setLevel" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let level = required_str_param(&params, "level")?;
+                    server.set_logging_level(level).await?;
+                    Ok(Value::Object(Map::new()))
+                }
+                .await,
+            )),
+            "completion/complete" => Ok(mcp_outcome(
+                id_value,
+                method,
+                async {
+                    let reference = params
+                        .get("ref")
+                        .cloned()
+                        .ok_or_else(|| McpRequestError::InvalidParams("ref".into()))?;
+                    let argument = params
+                        .get("argument")
+                        .and_then(Value::as_object)
+                        .cloned()
+                        .unwrap_or_default();
+                    let argument_name = required_str_param(&argument, "name")?;
+                    let argument_value =
+                        argument.get("value").and_then(Value::as_str).unwrap_or("");
+                    let completion = server
+                        .complete(reference, argument_name, argument_value)
+                        .await?;
+                    Ok(convert_mcp_completion(completion))
+                }
+                .await,
+            )),
+            other if other.starts_with("notifications/") => {
+                Ok(json!({ "jsonrpc": "2.0", "result": {} }))
+            }
             other => Ok(jsonrpc_error(
-                message.get("id").cloned().unwrap_or(Value::Null),
+                id_value,
                 -32601,
                 format!("Method '{other}' not found"),
             )),
@@ -583,7 +1723,7 @@ where
         let tool_name = params
             .get("name")
             .and_then(Value::as_str)
-            .ok_or_else(|| SdkError::Message("tools/call missing name parameter".into()))?;
+            .ok_or_else(|| SdkError::Protocol("tools/call missing name parameter".into()))?;
 
         let arguments = params
             .get("arguments")
@@ -591,8 +1731,34 @@ where
             .cloned()
             .unwrap_or_default();
 
-        match server.call_tool(tool_name, arguments).await {
-            Ok(result) => {
+        let tool_use_id = params
+            .get("_meta")
+            .and_then(|meta| meta.get("claudecode/toolUseId"))
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let context = self.tool_invocation_context(tool_use_id).await;
+
+        let clock = self.clock().await;
+        let started = clock.now();
+        let outcome = server
+            .call_tool_with_context(tool_name, arguments, context)
+            .await;
+        if let Some(metrics) = self.metrics().await {
+            let success = outcome.as_ref().is_ok_and(|result| !result.is_error);
+            let latency = clock.now().saturating_duration_since(started);
+            metrics.tool_call(server.name(), tool_name, latency, success);
+        }
+
+        match outcome {
+            Ok(mut result) => {
+                if let Some(limit) = *self.inner.tool_result_limit.lock().await {
+                    if limit.apply_to_mcp_result(&mut result) {
+                        sdk_debug!(
+                            "truncated oversized result of {}/{tool_name}",
+                            server.name()
+                        );
+                    }
+                }
                 let payload = convert_mcp_call_result(result);
                 let mut response = Map::new();
                 response.insert("jsonrpc".into(), Value::String("2.0".into()));
@@ -604,6 +1770,45 @@ where
         }
     }
 
+    async fn record_subagent_tool_uses(&self, message: &Message) {
+        let Message::Assistant(assistant) = message else {
+            return;
+        };
+        let Some(parent) = &assistant.parent_tool_use_id else {
+            return;
+        };
+        let mut tool_uses = self.inner.subagent_tool_uses.lock().await;
+        for block in &assistant.content {
+            if let ContentBlock::ToolUse(tool_use) = block {
+                if tool_uses.len() == SUBAGENT_TOOL_USE_CAPACITY {
+                    tool_uses.pop_front();
+                }
+                tool_uses.push_back((tool_use.id.clone(), parent.clone()));
+            }
+        }
+    }
+
+    async fn tool_invocation_context(&self, tool_use_id: Option<String>) -> ToolInvocationContext {
+        let parent_tool_use_id = match &tool_use_id {
+            Some(id) => self
+                .inner
+                .subagent_tool_uses
+                .lock()
+                .await
+                .iter()
+                .rev()
+                .find(|(tool_use, _)| tool_use == id)
+                .map(|(_, parent)| parent.clone()),
+            None => None,
+        };
+        ToolInvocationContext {
+            session_id: self.inner.activity.lock().await.session_id.clone(),
+            tool_use_id,
+            parent_tool_use_id,
+            permission_mode: *self.inner.permission_mode.lock().await,
+        }
+    }
+
     async fn send_success_response(
         &self,
         request_id: &str,
@@ -632,118 +1837,266 @@ where
         self.inner.transport.write(&envelope).await
     }
 
-    async fn send_control_request(&self, request: Value) -> Result<Value, SdkError> {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.control_request",
+            skip_all,
+            fields(
+                direction = "outbound",
+                request_id = tracing::field::Empty,
+                subtype = tracing::field::Empty,
+                duration_ms = tracing::field::Empty,
+                outcome = tracing::field::Empty,
+            )
+        )
+    )]
+    pub(crate) async fn send_control_request(&self, request: Value) -> Result<Value, SdkError> {
         if !self.inner.is_streaming_mode {
-            return Err(SdkError::Message(
+            return Err(SdkError::InvalidConfig(
                 "control requests require streaming mode".into(),
             ));
         }
 
         self.start().await?;
 
-        let counter = self.inner.request_counter.fetch_add(1, Ordering::SeqCst) + 1;
-        let request_id = format!("req_{}_{}", counter, unique_request_suffix());
+        let request_id = format!("req_{}", crate::internal::unique_id());
+        let subtype = request
+            .get("subtype")
+            .and_then(Value::as_str)
+            .unwrap_or("unknown")
+            .to_string();
+        sdk_record!("request_id", request_id.as_str());
+        sdk_record!("subtype", subtype.as_str());
 
+        let clock = self.clock().await;
+        let overdraft = self
+            .inner
+            .buffer_limit
+            .lock()
+            .await
+            .as_ref()
+            .map(BufferLimit::overdraft);
         let (sender, receiver) = oneshot::channel();
         {
             let mut pending = self.inner.pending_control.lock().await;
-            pending.insert(request_id.clone(), sender);
+            match pending.entry(request_id.clone()) {
+                Entry::Occupied(_) => {
+                    return Err(SdkError::Protocol(format!(
+                        "duplicate control request id: {request_id}"
+                    )));
+                }
+                Entry::Vacant(slot) => {
+                    slot.insert(PendingControl {
+                        responder: sender,
+                        subtype: subtype.clone(),
+                        started: clock.now(),
+                        warned: false,
+                        _overdraft: overdraft,
+                    });
+                    self.inner.read_room.notify_waiters();
+                }
+            }
         }
 
+        let started = FrameTimer::start(clock.clone());
         let envelope = json!({
             "type": "control_request",
             "request_id": request_id.clone(),
-            "request": request,
+            "request": request.clone(),
         });
 
         if let Err(err) = self.inner.transport.write(&envelope).await {
             let mut pending = self.inner.pending_control.lock().await;
             pending.remove(&request_id);
+            self.inner.control_settled.notify_waiters();
+            drop(pending);
+            self.record_frame(
+                started,
+                FrameDirection::Outbound,
+                &request_id,
+                request,
+                None,
+                FrameOutcome::Error(err.to_string()),
+            )
+            .await;
             return Err(err);
         }
 
-        match timeout(CONTROL_REQUEST_TIMEOUT, receiver).await {
-            Ok(Ok(response)) => response,
-            Ok(Err(_)) => Err(SdkError::Message("control response channel closed".into())),
-            Err(err) => {
+        let result = match clock::timeout(clock.as_ref(), CONTROL_REQUEST_TIMEOUT, receiver).await {
+            Some(Ok(response)) => response,
+            Some(Err(_)) => Err(SdkError::Cancelled(
+                "control response channel closed".into(),
+            )),
+            None => {
                 let mut pending = self.inner.pending_control.lock().await;
                 pending.remove(&request_id);
-                Err(SdkError::Timeout(err))
+                self.inner.control_settled.notify_waiters();
+                Err(SdkError::Timeout {
+                    operation: TimeoutOperation::ControlRequest { subtype },
+                    elapsed: CONTROL_REQUEST_TIMEOUT,
+                })
             }
-        }
-    }
-
-    async fn prepare_hooks_configuration(&self) -> Result<Option<Value>, SdkError> {
-        let mut hooks_guard = self.inner.hooks.lock().await;
-        let hooks = hooks_guard.take();
-        drop(hooks_guard);
+        };
 
-        let Some(mut hook_map) = hooks else {
-            return Ok(None);
+        let (response, outcome) = match &result {
+            Ok(value) => (Some(value.clone()), FrameOutcome::Success),
+            Err(SdkError::Timeout { .. }) => (None, FrameOutcome::Timeout),
+            Err(SdkError::Cancelled(_)) => (None, FrameOutcome::Cancelled),
+            Err(err) => (None, FrameOutcome::Error(err.to_string())),
         };
+        sdk_record!("duration_ms", started.elapsed().as_millis() as u64);
+        sdk_record!(
+            "outcome",
+            match &outcome {
+                FrameOutcome::Success => "success",
+                FrameOutcome::Error(_) => "error",
+                FrameOutcome::Timeout => "timeout",
+                FrameOutcome::Cancelled => "cancelled",
+            }
+        );
+        self.record_frame(
+            started,
+            FrameDirection::Outbound,
+            &request_id,
+            request,
+            response,
+            outcome,
+        )
+        .await;
+
+        result
+    }
 
-        if hook_map.is_empty() {
-            return Ok(None);
+    /// Take the configured hooks, store their callbacks under fresh ids and
+    /// describe them for the `initialize` request.
+    async fn prepare_hooks_configuration(&self) -> Option<HooksRegistration> {
+        let hook_map = self.inner.hooks.lock().await.take()?;
+
+        let mut callbacks = self.inner.hook_callbacks.lock().await;
+        let mut config = HooksRegistration::new();
+        for (event, matchers) in hook_map {
+            let entries: Vec<_> = matchers
+                .into_iter()
+                .filter_map(|matcher| self.register_hook_matcher(&mut callbacks, matcher))
+                .collect();
+            if !entries.is_empty() {
+                config.insert(event, entries);
+            }
         }
+        (!config.is_empty()).then_some(config)
+    }
 
-        let mut callbacks_guard = self.inner.hook_callbacks.lock().await;
-        let mut config = Map::new();
-
-        for (event, mut matchers) in hook_map.drain() {
-            if matchers.is_empty() {
-                continue;
-            }
+    /// Store `matcher`'s callbacks in `callbacks` under new ids; `None` when
+    /// it has no callbacks.
+    fn register_hook_matcher(
+        &self,
+        callbacks: &mut HashMap<String, HookCallbackHandle>,
+        matcher: HookMatcher,
+    ) -> Option<HookRegistrationConfig> {
+        if matcher.hooks.is_empty() {
+            return None;
+        }
+        let ids = matcher
+            .hooks
+            .into_iter()
+            .map(|hook| {
+                let id = format!(
+                    "hook_{}",
+                    self.inner.next_callback_id.fetch_add(1, Ordering::SeqCst)
+                );
+                callbacks.insert(id.clone(), hook);
+                id
+            })
+            .collect();
+        Some(HookRegistrationConfig::new(matcher.matcher, ids))
+    }
+}
 
-            let mut matcher_entries = Vec::new();
-            for matcher in matchers.iter_mut() {
-                if matcher.hooks.is_empty() {
-                    continue;
-                }
+/// Periodically inspect pending control requests, warning about (and optionally
+/// failing) those that have waited past the configured thresholds.
+async fn control_watchdog_loop<T: Transport + ?Sized>(
+    inner: Weak<QueryInner<T>>,
+    config: ControlWatchdogConfig,
+    clock: ClockHandle,
+) {
+    // A zero period would spin, so clamp misconfigured intervals.
+    let period = config.interval.max(Duration::from_millis(1));
+    loop {
+        clock.sleep(period).await;
+        let Some(inner) = inner.upgrade() else {
+            break;
+        };
+        if inner.closed.load(Ordering::SeqCst) {
+            break;
+        }
 
-                let mut callback_ids = Vec::new();
-                for hook in matcher.hooks.drain(..) {
-                    let id = format!(
-                        "hook_{}",
-                        self.inner.next_callback_id.fetch_add(1, Ordering::SeqCst)
+        let mut failed = Vec::new();
+        {
+            let mut pending = inner.pending_control.lock().await;
+            let now = clock.now();
+            for (request_id, entry) in pending.iter_mut() {
+                let elapsed = now.saturating_duration_since(entry.started);
+                if config.fail_after.is_some_and(|limit| elapsed >= limit) {
+                    failed.push(request_id.clone());
+                } else if elapsed >= config.warn_after && !entry.warned {
+                    entry.warned = true;
+                    sdk_warn!(
+                        { request_id = %request_id, subtype = %entry.subtype, elapsed_ms = elapsed.as_millis() as u64 },
+                        "control_watchdog: {} request {request_id} pending for {elapsed:?}",
+                        entry.subtype
                     );
-                    callbacks_guard.insert(id.clone(), hook);
-                    callback_ids.push(Value::String(id));
-                }
-
-                if callback_ids.is_empty() {
-                    continue;
-                }
-
-                let mut entry = Map::new();
-                if let Some(matcher_value) = matcher.matcher.clone() {
-                    entry.insert("matcher".into(), matcher_value);
                 }
-                entry.insert("hookCallbackIds".into(), Value::Array(callback_ids));
-                matcher_entries.push(Value::Object(entry));
             }
-
-            if !matcher_entries.is_empty() {
-                config.insert(event.as_str().to_string(), Value::Array(matcher_entries));
+            for request_id in failed.drain(..) {
+                if let Some(entry) = pending.remove(&request_id) {
+                    let elapsed = now.saturating_duration_since(entry.started);
+                    sdk_error!(
+                        { request_id = %request_id, subtype = %entry.subtype, elapsed_ms = elapsed.as_millis() as u64 },
+                        "control_watchdog: failing {} request {request_id} after {elapsed:?}",
+                        entry.subtype
+                    );
+                    let _ = entry.responder.send(Err(SdkError::Timeout {
+                        operation: TimeoutOperation::ControlRequest {
+                            subtype: entry.subtype,
+                        },
+                        elapsed,
+                    }));
+                }
             }
         }
+        inner.control_settled.notify_waiters();
+    }
+}
 
-        drop(callbacks_guard);
+/// Start time of a control exchange, captured for frame logging.
+#[derive(Clone)]
+struct FrameTimer {
+    wall: SystemTime,
+    instant: Instant,
+    clock: ClockHandle,
+}
 
-        if config.is_empty() {
-            Ok(None)
-        } else {
-            Ok(Some(Value::Object(config)))
+impl FrameTimer {
+    fn start(clock: ClockHandle) -> Self {
+        Self {
+            wall: clock.wall_time(),
+            instant: clock.now(),
+            clock,
         }
     }
+
+    fn elapsed(&self) -> Duration {
+        self.clock.now().saturating_duration_since(self.instant)
+    }
 }
 
-fn unique_request_suffix() -> String {
-    let timestamp = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map(|duration| duration.as_nanos())
-        .unwrap_or_default();
-    let pid = std::process::id() as u128;
-    format!("{:x}", timestamp ^ pid)
+/// Frames of the control protocol, handled ahead of queued messages.
+fn is_control_frame(raw: &Value) -> bool {
+    matches!(
+        raw.get("type").and_then(Value::as_str),
+        Some("control_response" | "control_request" | "control_cancel_request")
+    )
 }
 
 fn convert_hook_output_for_cli(value: Value) -> Value {
@@ -773,6 +2126,36 @@ fn convert_hook_output_for_cli(value: Value) -> Value {
     }
 }
 
+/// The CLI sends hook inputs with snake_case keys while [`HookInput`] uses
+/// camelCase; only top-level keys are renamed so `tool_input` and
+/// `tool_response` reach the callback untouched.
+fn normalize_hook_input(input: Value) -> Value {
+    match input {
+        Value::Object(map) => Value::Object(
+            map.into_iter()
+                .map(|(key, value)| (snake_to_camel(&key), value))
+                .collect(),
+        ),
+        other => other,
+    }
+}
+
+fn snake_to_camel(key: &str) -> String {
+    let mut converted = String::with_capacity(key.len());
+    let mut upper = false;
+    for ch in key.chars() {
+        if ch == '_' {
+            upper = true;
+        } else if upper {
+            converted.extend(ch.to_uppercase());
+            upper = false;
+        } else {
+            converted.push(ch);
+        }
+    }
+    converted
+}
+
 fn deserialize_permission_suggestions(entries: &[Value]) -> Vec<PermissionUpdate> {
     entries
         .iter()
@@ -781,8 +2164,23 @@ fn deserialize_permission_suggestions(entries: &[Value]) -> Vec<PermissionUpdate
 }
 
 fn build_mcp_initialize_response(message: &Map<String, Value>, server: &McpServerHandle) -> Value {
+    let advertised = server.capabilities();
     let mut capabilities = Map::new();
-    capabilities.insert("tools".into(), Value::Object(Map::new()));
+    if advertised.tools {
+        capabilities.insert("tools".into(), Value::Object(Map::new()));
+    }
+    if advertised.resources {
+        capabilities.insert("resources".into(), Value::Object(Map::new()));
+    }
+    if advertised.prompts {
+        capabilities.insert("prompts".into(), Value::Object(Map::new()));
+    }
+    if advertised.logging {
+        capabilities.insert("logging".into(), Value::Object(Map::new()));
+    }
+    if advertised.completions {
+        capabilities.insert("completions".into(), Value::Object(Map::new()));
+    }
 
     let mut server_info = Map::new();
     server_info.insert("name".into(), Value::String(server.name().to_string()));
@@ -806,6 +2204,166 @@ fn build_mcp_initialize_response(message: &Map<String, Value>, server: &McpServe
     Value::Object(response)
 }
 
+/// Failure raised while serving an MCP request, mapped onto a JSON-RPC error code.
+enum McpRequestError {
+    InvalidParams(String),
+    Sdk(SdkError),
+}
+
+impl From<SdkError> for McpRequestError {
+    fn from(value: SdkError) -> Self {
+        McpRequestError::Sdk(value)
+    }
+}
+
+fn required_str_param<'a>(
+    params: &'a Map<String, Value>,
+    key: &str,
+) -> Result<&'a str, McpRequestError> {
+    params
+        .get(key)
+        .and_then(Value::as_str)
+        .ok_or_else(|| McpRequestError::InvalidParams(key.to_string()))
+}
+
+fn mcp_outcome(id: Value, method: &str, outcome: Result<Value, McpRequestError>) -> Value {
+    match outcome {
+        Ok(result) => jsonrpc_result(id, result),
+        Err(McpRequestError::InvalidParams(param)) => jsonrpc_error(
+            id,
+            -32602,
+            format!("Missing required parameter '{param}' for '{method}'"),
+        ),
+        Err(McpRequestError::Sdk(SdkError::NotImplemented)) => {
+            jsonrpc_error(id, -32601, format!("Method '{method}' not found"))
+        }
+        Err(McpRequestError::Sdk(err)) => jsonrpc_error(id, -32603, err.to_string()),
+    }
+}
+
+fn convert_mcp_resource_list(resources: Vec<McpResourceInfo>) -> Vec<Value> {
+    resources
+        .into_iter()
+        .map(|resource| {
+            let mut obj = Map::new();
+            obj.insert("uri".into(), Value::String(resource.uri));
+            obj.insert("name".into(), Value::String(resource.name));
+            if let Some(description) = resource.description {
+                obj.insert("description".into(), Value::String(description));
+            }
+            if let Some(mime_type) = resource.mime_type {
+                obj.insert("mimeType".into(), Value::String(mime_type));
+            }
+            Value::Object(obj)
+        })
+        .collect()
+}
+
+fn convert_mcp_resource_templates(templates: Vec<McpResourceTemplateInfo>) -> Vec<Value> {
+    templates
+        .into_iter()
+        .map(|template| {
+            let mut obj = Map::new();
+            obj.insert("uriTemplate".into(), Value::String(template.uri_template));
+            obj.insert("name".into(), Value::String(template.name));
+            if let Some(description) = template.description {
+                obj.insert("description".into(), Value::String(description));
+            }
+            if let Some(mime_type) = template.mime_type {
+                obj.insert("mimeType".into(), Value::String(mime_type));
+            }
+            Value::Object(obj)
+        })
+        .collect()
+}
+
+fn convert_mcp_resource_contents(contents: Vec<McpResourceContents>) -> Vec<Value> {
+    contents
+        .into_iter()
+        .map(|item| {
+            let (uri, mime_type, key, body) = match item {
+                McpResourceContents::Text {
+                    uri,
+                    mime_type,
+                    text,
+                } => (uri, mime_type, "text", text),
+                McpResourceContents::Blob {
+                    uri,
+                    mime_type,
+                    blob,
+                } => (uri, mime_type, "blob", blob),
+            };
+            let mut obj = Map::new();
+            obj.insert("uri".into(), Value::String(uri));
+            if let Some(mime_type) = mime_type {
+                obj.insert("mimeType".into(), Value::String(mime_type));
+            }
+            obj.insert(key.into(), Value::String(body));
+            Value::Object(obj)
+        })
+        .collect()
+}
+
+fn convert_mcp_prompt_list(prompts: Vec<McpPromptInfo>) -> Vec<Value> {
+    prompts
+        .into_iter()
+        .map(|prompt| {
+            let mut obj = Map::new();
+            obj.insert("name".into(), Value::String(prompt.name));
+            if let Some(description) = prompt.description {
+                obj.insert("description".into(), Value::String(description));
+            }
+            let arguments = prompt
+                .arguments
+                .into_iter()
+                .map(|argument| {
+                    let mut arg = Map::new();
+                    arg.insert("name".into(), Value::String(argument.name));
+                    if let Some(description) = argument.description {
+                        arg.insert("description".into(), Value::String(description));
+                    }
+                    arg.insert("required".into(), Value::Bool(argument.required));
+                    Value::Object(arg)
+                })
+                .collect();
+            obj.insert("arguments".into(), Value::Array(arguments));
+            Value::Object(obj)
+        })
+        .collect()
+}
+
+fn convert_mcp_prompt_result(prompt: McpPromptResult) -> Value {
+    let mut result = Map::new();
+    if let Some(description) = prompt.description {
+        result.insert("description".into(), Value::String(description));
+    }
+    let messages = prompt
+        .messages
+        .into_iter()
+        .map(|message| {
+            json!({
+                "role": message.role,
+                "content": convert_mcp_content(message.content),
+            })
+        })
+        .collect();
+    result.insert("messages".into(), Value::Array(messages));
+    Value::Object(result)
+}
+
+fn convert_mcp_completion(completion: McpCompletion) -> Value {
+    let mut inner = Map::new();
+    inner.insert(
+        "values".into(),
+        Value::Array(completion.values.into_iter().map(Value::String).collect()),
+    );
+    if let Some(total) = completion.total {
+        inner.insert("total".into(), Value::Number(total.into()));
+    }
+    inner.insert("hasMore".into(), Value::Bool(completion.has_more));
+    json!({ "completion": inner })
+}
+
 fn convert_mcp_tool_list(tools: Vec<McpToolInfo>) -> Vec<Value> {
     tools
         .into_iter()
@@ -830,13 +2388,7 @@ fn convert_mcp_call_result(result: McpToolCallResult) -> Value {
     let content = result
         .content
         .into_iter()
-        .map(|item| match item {
-            McpToolContent::Text { text } => json!({ "type": "text", "text": text }),
-            McpToolContent::Image { data, mime_type } => {
-                json!({ "type": "image", "data": data, "mimeType": mime_type })
-            }
-            McpToolContent::Json { value } => json!({ "type": "json", "value": value }),
-        })
+        .map(convert_mcp_content)
         .collect();
     result_map.insert("content".into(), Value::Array(content));
     if result.is_error {
@@ -845,6 +2397,24 @@ fn convert_mcp_call_result(result: McpToolCallResult) -> Value {
     Value::Object(result_map)
 }
 
+fn convert_mcp_content(item: McpToolContent) -> Value {
+    match item {
+        McpToolContent::Text { text } => json!({ "type": "text", "text": text }),
+        McpToolContent::Image { data, mime_type } => {
+            json!({ "type": "image", "data": data, "mimeType": mime_type })
+        }
+        McpToolContent::Json { value } => json!({ "type": "json", "value": value }),
+    }
+}
+
+fn jsonrpc_result(id: Value, result: Value) -> Value {
+    let mut response = Map::new();
+    response.insert("jsonrpc".into(), Value::String("2.0".into()));
+    response.insert("id".into(), id);
+    response.insert("result".into(), result);
+    Value::Object(response)
+}
+
 fn jsonrpc_error(id: Value, code: i64, message: String) -> Value {
     let mut error = Map::new();
     error.insert("code".into(), Value::Number(code.into()));
@@ -0,0 +1,44 @@
+//! Logging shims that emit `tracing` events when the `tracing` feature is
+//! enabled and fall back to the `log` crate otherwise.
+//!
+//! Structured fields (`{ request_id = %id, ... }`) are only attached in the
+//! `tracing` build; the `log` build keeps just the formatted message.
+
+macro_rules! sdk_event {
+    ($tracing_level:ident, $log_level:ident, { $($fields:tt)+ }, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$tracing_level, $($fields)+, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::log!(log::Level::$log_level, $($arg)+);
+    }};
+    ($tracing_level:ident, $log_level:ident, $($arg:tt)+) => {{
+        #[cfg(feature = "tracing")]
+        tracing::event!(tracing::Level::$tracing_level, $($arg)+);
+        #[cfg(not(feature = "tracing"))]
+        log::log!(log::Level::$log_level, $($arg)+);
+    }};
+}
+
+macro_rules! sdk_debug {
+    ($($arg:tt)+) => { $crate::internal::trace::sdk_event!(DEBUG, Debug, $($arg)+) };
+}
+
+macro_rules! sdk_warn {
+    ($($arg:tt)+) => { $crate::internal::trace::sdk_event!(WARN, Warn, $($arg)+) };
+}
+
+macro_rules! sdk_error {
+    ($($arg:tt)+) => { $crate::internal::trace::sdk_event!(ERROR, Error, $($arg)+) };
+}
+
+/// Record a field on the current span; a no-op without the `tracing` feature.
+macro_rules! sdk_record {
+    ($field:literal, $value:expr) => {{
+        #[cfg(feature = "tracing")]
+        tracing::Span::current().record($field, $value);
+        #[cfg(not(feature = "tracing"))]
+        let _ = $value;
+    }};
+}
+
+pub(crate) use {sdk_debug, sdk_error, sdk_event, sdk_record, sdk_warn};
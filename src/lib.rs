@@ -1,11 +1,56 @@
+pub mod attachments;
+pub mod budget;
+pub mod buffer_limit;
+pub mod cache;
 pub mod client;
+pub mod clock;
 pub mod config;
+pub mod context_window;
+pub mod conversation;
+pub mod credentials;
+pub mod debug_bundle;
 pub mod env;
 pub mod error;
+pub mod frame_log;
 pub mod hooks;
 pub mod internal;
 pub mod mcp;
+pub mod memory;
 pub mod message;
+pub mod metrics;
+pub mod middleware;
+pub mod models;
+pub mod options_document;
+pub mod orchestrator;
+#[cfg(feature = "otel")]
+pub mod otel;
+pub mod parallel;
+pub mod partial;
 pub mod permission;
+pub mod pool;
+pub mod progress;
+pub mod prompts;
 pub mod query;
+pub mod rate_limit;
+pub mod raw;
+pub mod recovery;
+pub mod redact;
+#[cfg(feature = "tower")]
+pub mod service;
+pub mod settings;
+pub mod sinks;
+pub mod spill;
+pub mod stream_ext;
+pub mod subagent;
+#[cfg(feature = "testing")]
+pub mod testing;
+pub mod tokens;
+pub mod tool_events;
 pub mod transport;
+pub mod truncation;
+#[cfg(feature = "web")]
+pub mod web;
+pub mod workspace;
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use query::ask;
@@ -0,0 +1,244 @@
+//! Reachability checks for `sse` and `http` MCP servers before connecting.
+//!
+//! With [`ClaudeAgentOptions::mcp_preflight`] set, every remote server in
+//! [`ClaudeAgentOptions::mcp_servers`] is probed before the CLI starts, so an
+//! unreachable one is reported up front instead of the model finding its
+//! tools missing mid-conversation. [`McpPreflight::Warn`] logs each
+//! [`McpHealthIssue`] and keeps it for
+//! [`ClaudeSdkClient::mcp_health_issues`](crate::client::ClaudeSdkClient::mcp_health_issues);
+//! [`McpPreflight::Require`] fails the connect with
+//! [`SdkError::McpServerUnreachable`].
+//!
+//! The built-in [`TcpProbe`] opens a connection to the server and, for
+//! plain `http://` URLs, sends a `HEAD` request; any HTTP response counts as
+//! reachable. Set [`ClaudeAgentOptions::mcp_probe`] to probe differently,
+//! for example with an MCP `initialize` over TLS.
+
+use std::fmt;
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::future::join_all;
+use serde::{Deserialize, Serialize};
+
+use crate::config::{ClaudeAgentOptions, McpServerConfig, McpServers};
+use crate::error::SdkError;
+use crate::internal::trace::sdk_warn;
+
+/// Time allowed for each probe.
+pub const PROBE_TIMEOUT: Duration = Duration::from_secs(3);
+
+/// What to do with unreachable MCP servers on connect.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum McpPreflight {
+    /// Do not probe.
+    #[default]
+    Off,
+    /// Log unreachable servers and connect anyway.
+    Warn,
+    /// Fail the connect on the first unreachable server.
+    Require,
+}
+
+/// A remote MCP server that did not answer its probe.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct McpHealthIssue {
+    /// Name of the server in `mcp_servers`.
+    pub server: String,
+    /// Server URL as configured, with `${VAR}` references unexpanded and
+    /// secrets masked by [`ClaudeAgentOptions::effective_redactor`].
+    pub url: String,
+    /// Why the probe failed.
+    pub reason: String,
+}
+
+impl fmt::Display for McpHealthIssue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "MCP server '{}' at {} is unreachable: {}",
+            self.server, self.url, self.reason
+        )
+    }
+}
+
+impl From<McpHealthIssue> for SdkError {
+    fn from(issue: McpHealthIssue) -> Self {
+        SdkError::McpServerUnreachable {
+            server: issue.server,
+            url: issue.url,
+            reason: issue.reason,
+        }
+    }
+}
+
+/// Boxed future returned by MCP probes; `Err` carries the failure reason.
+pub type McpProbeFuture = Pin<Box<dyn Future<Output = Result<(), String>> + Send>>;
+
+/// Checks that a remote MCP server answers.
+pub trait McpProbe: Send + Sync {
+    /// Probe `server`, an `sse` or `http` config with its environment
+    /// references already expanded.
+    fn probe(&self, name: &str, server: &McpServerConfig) -> McpProbeFuture;
+}
+
+impl<F, Fut> McpProbe for F
+where
+    F: Fn(&str, &McpServerConfig) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<(), String>> + Send + 'static,
+{
+    fn probe(&self, name: &str, server: &McpServerConfig) -> McpProbeFuture {
+        Box::pin(self(name, server))
+    }
+}
+
+/// Convenient handle for storing MCP probes.
+pub type McpProbeHandle = Arc<dyn McpProbe>;
+
+/// Probe that connects to the server's host and port, sending a `HEAD`
+/// request for `http://` URLs. TLS is not negotiated for `https://`.
+#[cfg(not(target_arch = "wasm32"))]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct TcpProbe;
+
+#[cfg(not(target_arch = "wasm32"))]
+impl McpProbe for TcpProbe {
+    fn probe(&self, _name: &str, server: &McpServerConfig) -> McpProbeFuture {
+        let url = remote_url(server).unwrap_or_default().to_string();
+        Box::pin(async move { probe_url(&url).await })
+    }
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+async fn probe_url(url: &str) -> Result<(), String> {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let (scheme, rest) = url
+        .split_once("://")
+        .ok_or_else(|| format!("'{url}' is not an absolute URL"))?;
+    let (authority, path) = match rest.find('/') {
+        Some(slash) => (&rest[..slash], &rest[slash..]),
+        None => (rest, "/"),
+    };
+    let host = authority.rsplit('@').next().unwrap_or(authority);
+    let address = match (host.rsplit_once(':'), scheme) {
+        (Some((_, port)), _) if port.parse::<u16>().is_ok() => host.to_string(),
+        (_, "https") => format!("{host}:443"),
+        (_, "http") => format!("{host}:80"),
+        _ => return Err(format!("unsupported URL scheme '{scheme}'")),
+    };
+
+    let mut stream = tokio::net::TcpStream::connect(&address)
+        .await
+        .map_err(|err| format!("cannot connect to {address}: {err}"))?;
+    if scheme != "http" {
+        return Ok(());
+    }
+    let request = format!("HEAD {path} HTTP/1.1\r\nHost: {host}\r\nConnection: close\r\n\r\n");
+    stream
+        .write_all(request.as_bytes())
+        .await
+        .map_err(|err| format!("cannot send HEAD request: {err}"))?;
+    let mut status = [0u8; 5];
+    stream
+        .read_exact(&mut status)
+        .await
+        .map_err(|err| format!("no HTTP response: {err}"))?;
+    if &status != b"HTTP/" {
+        return Err("the server did not answer with HTTP".into());
+    }
+    Ok(())
+}
+
+fn remote_url(server: &McpServerConfig) -> Option<&str> {
+    match server {
+        McpServerConfig::Sse(config) => Some(&config.url),
+        McpServerConfig::Http(config) => Some(&config.url),
+        McpServerConfig::Stdio(_) | McpServerConfig::Sdk(_) => None,
+    }
+}
+
+/// Probe every `sse` and `http` server in `options`, returning the ones that
+/// failed, ordered by server name.
+///
+/// Uses [`ClaudeAgentOptions::mcp_probe`], or [`TcpProbe`] when unset.
+pub async fn check_mcp_servers(options: &ClaudeAgentOptions) -> Vec<McpHealthIssue> {
+    let McpServers::Map(servers) = &options.mcp_servers else {
+        return Vec::new();
+    };
+    let probe = match &options.mcp_probe {
+        Some(probe) => Arc::clone(probe),
+        None => default_probe(),
+    };
+    let redactor = options.effective_redactor();
+    let lookup = |name: &str| {
+        options
+            .env
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+    };
+
+    let mut names: Vec<&String> = servers.keys().collect();
+    names.sort();
+    let probes = names.into_iter().filter_map(|name| {
+        let server = &servers[name];
+        let url = remote_url(server)?.to_string();
+        let probe = Arc::clone(&probe);
+        let redactor = Arc::clone(&redactor);
+        let server = server.interpolate_env(lookup);
+        Some(async move {
+            let issue = |reason: String| McpHealthIssue {
+                server: name.to_string(),
+                url: redactor.redact(&url).into_owned(),
+                reason: redactor.redact(&reason).into_owned(),
+            };
+            let server = match server {
+                Ok(server) => server,
+                Err(err) => return Some(issue(err.to_string())),
+            };
+            // Expanded values may be credentials, so reasons quoting the
+            // expanded URL show the configured one instead.
+            let expanded = remote_url(&server).unwrap_or_default();
+            match tokio::time::timeout(PROBE_TIMEOUT, probe.probe(name, &server)).await {
+                Ok(Ok(())) => None,
+                Ok(Err(reason)) => Some(issue(reason.replace(expanded, &url))),
+                Err(_) => Some(issue(format!("no answer within {PROBE_TIMEOUT:?}"))),
+            }
+        })
+    });
+    join_all(probes).await.into_iter().flatten().collect()
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn default_probe() -> McpProbeHandle {
+    Arc::new(TcpProbe)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn default_probe() -> McpProbeHandle {
+    Arc::new(|_: &str, _: &McpServerConfig| async { Ok::<(), String>(()) })
+}
+
+/// Run the preflight configured in `options`, returning the issues to keep.
+pub(crate) async fn preflight(
+    options: &ClaudeAgentOptions,
+) -> Result<Vec<McpHealthIssue>, SdkError> {
+    if options.mcp_preflight == McpPreflight::Off {
+        return Ok(Vec::new());
+    }
+    let issues = check_mcp_servers(options).await;
+    if options.mcp_preflight == McpPreflight::Require {
+        if let Some(issue) = issues.into_iter().next() {
+            return Err(issue.into());
+        }
+        return Ok(Vec::new());
+    }
+    for issue in &issues {
+        sdk_warn!("{issue}");
+    }
+    Ok(issues)
+}
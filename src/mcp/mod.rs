@@ -5,9 +5,13 @@ use std::sync::Arc;
 
 use async_trait::async_trait;
 use futures::Future;
+use serde::Serialize;
 use serde_json::{json, Map, Value};
 
 use crate::error::SdkError;
+use crate::permission::PermissionMode;
+
+pub mod health;
 
 /// Metadata describing an MCP tool exposed by an SDK server.
 #[derive(Debug, Clone, PartialEq)]
@@ -75,18 +79,265 @@ impl McpToolCallResult {
         self.is_error = is_error;
         self
     }
+
+    /// Failed call reporting `message` to the model.
+    pub fn error(message: impl Into<String>) -> Self {
+        Self::new(vec![McpToolContent::text(message)]).with_error(true)
+    }
+
+    /// `value` as pretty-printed JSON text; a value that cannot be
+    /// serialized becomes an [`error`](Self::error) result.
+    pub fn from_serialize<T: Serialize + ?Sized>(value: &T) -> Self {
+        match serde_json::to_string_pretty(value) {
+            Ok(text) => Self::new(vec![McpToolContent::text(text)]),
+            Err(err) => Self::error(format!("failed to serialize tool result: {err}")),
+        }
+    }
+
+    /// Markdown text, passed through as is.
+    pub fn from_markdown(markdown: impl Into<String>) -> Self {
+        Self::new(vec![McpToolContent::text(markdown)])
+    }
+
+    /// Rows rendered as a Markdown table, the first row being the header.
+    /// Short rows are padded; `|` and line breaks in cells are escaped.
+    pub fn from_table(rows: Vec<Vec<String>>) -> Self {
+        let width = rows.iter().map(Vec::len).max().unwrap_or_default();
+        let mut table = String::new();
+        for (index, row) in rows.iter().enumerate() {
+            let cells = (0..width).map(|column| {
+                row.get(column)
+                    .map(|cell| cell.replace('|', "\\|").replace(['\r', '\n'], " "))
+                    .unwrap_or_default()
+            });
+            table.push_str(&format!("| {} |\n", cells.collect::<Vec<_>>().join(" | ")));
+            if index == 0 {
+                table.push_str(&format!("|{}\n", " --- |".repeat(width)));
+            }
+        }
+        Self::from_markdown(table)
+    }
+}
+
+/// Metadata describing a resource exposed by an SDK server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpResourceInfo {
+    pub uri: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+impl McpResourceInfo {
+    pub fn new(uri: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            uri: uri.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+        }
+    }
+
+    pub fn with_description(mut self, description: impl Into<String>) -> Self {
+        self.description = Some(description.into());
+        self
+    }
+
+    pub fn with_mime_type(mut self, mime_type: impl Into<String>) -> Self {
+        self.mime_type = Some(mime_type.into());
+        self
+    }
+}
+
+/// Parameterised resource template exposed by an SDK server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpResourceTemplateInfo {
+    pub uri_template: String,
+    pub name: String,
+    pub description: Option<String>,
+    pub mime_type: Option<String>,
+}
+
+impl McpResourceTemplateInfo {
+    pub fn new(uri_template: impl Into<String>, name: impl Into<String>) -> Self {
+        Self {
+            uri_template: uri_template.into(),
+            name: name.into(),
+            description: None,
+            mime_type: None,
+        }
+    }
+}
+
+/// Contents returned when reading a resource.
+#[derive(Debug, Clone, PartialEq)]
+pub enum McpResourceContents {
+    Text {
+        uri: String,
+        mime_type: Option<String>,
+        text: String,
+    },
+    Blob {
+        uri: String,
+        mime_type: Option<String>,
+        blob: String,
+    },
+}
+
+impl McpResourceContents {
+    pub fn text(uri: impl Into<String>, text: impl Into<String>) -> Self {
+        Self::Text {
+            uri: uri.into(),
+            mime_type: None,
+            text: text.into(),
+        }
+    }
+
+    pub fn blob(
+        uri: impl Into<String>,
+        blob: impl Into<String>,
+        mime_type: Option<String>,
+    ) -> Self {
+        Self::Blob {
+            uri: uri.into(),
+            mime_type,
+            blob: blob.into(),
+        }
+    }
+}
+
+/// Argument accepted by an MCP prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpPromptArgument {
+    pub name: String,
+    pub description: Option<String>,
+    pub required: bool,
+}
+
+/// Metadata describing a prompt exposed by an SDK server.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpPromptInfo {
+    pub name: String,
+    pub description: Option<String>,
+    pub arguments: Vec<McpPromptArgument>,
+}
+
+impl McpPromptInfo {
+    pub fn new(name: impl Into<String>, description: Option<String>) -> Self {
+        Self {
+            name: name.into(),
+            description,
+            arguments: Vec::new(),
+        }
+    }
+
+    pub fn with_argument(
+        mut self,
+        name: impl Into<String>,
+        description: Option<String>,
+        required: bool,
+    ) -> Self {
+        self.arguments.push(McpPromptArgument {
+            name: name.into(),
+            description,
+            required,
+        });
+        self
+    }
+}
+
+/// Single message produced when rendering a prompt.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpPromptMessage {
+    pub role: String,
+    pub content: McpToolContent,
+}
+
+impl McpPromptMessage {
+    pub fn user(content: McpToolContent) -> Self {
+        Self {
+            role: "user".into(),
+            content,
+        }
+    }
+
+    pub fn assistant(content: McpToolContent) -> Self {
+        Self {
+            role: "assistant".into(),
+            content,
+        }
+    }
+}
+
+/// Result of rendering a prompt via `prompts/get`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct McpPromptResult {
+    pub description: Option<String>,
+    pub messages: Vec<McpPromptMessage>,
+}
+
+/// Completion candidates returned from `completion/complete`.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct McpCompletion {
+    pub values: Vec<String>,
+    pub total: Option<u64>,
+    pub has_more: bool,
+}
+
+/// Capabilities advertised by an SDK server during `initialize`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct McpServerCapabilities {
+    pub tools: bool,
+    pub resources: bool,
+    pub prompts: bool,
+    pub logging: bool,
+    pub completions: bool,
+}
+
+impl Default for McpServerCapabilities {
+    fn default() -> Self {
+        Self {
+            tools: true,
+            resources: false,
+            prompts: false,
+            logging: false,
+            completions: false,
+        }
+    }
 }
 
 /// Future type returned by SDK MCP tool handlers.
 pub type ToolFuture = Pin<Box<dyn Future<Output = Result<McpToolCallResult, SdkError>> + Send>>;
 
+/// Where a tool call comes from, for servers shared between sessions.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ToolInvocationContext {
+    /// Session reported by the CLI, once known.
+    pub session_id: Option<String>,
+    /// Id of the `tool_use` block being answered, when the CLI sends it.
+    pub tool_use_id: Option<String>,
+    /// `Task` tool use of the subagent making the call; `None` for the main
+    /// agent.
+    pub parent_tool_use_id: Option<String>,
+    /// Permission mode the session was started with or last switched to.
+    pub permission_mode: Option<PermissionMode>,
+}
+
+/// Handler of an [`SdkMcpTool`].
+pub type ToolHandler = Arc<dyn Fn(Map<String, Value>) -> ToolFuture + Send + Sync>;
+
+tokio::task_local! {
+    /// Context of the call whose [`ToolHandler`] is being invoked.
+    static INVOCATION_CONTEXT: ToolInvocationContext;
+}
+
 /// Definition of an SDK MCP tool that can be registered with a server.
 #[derive(Clone)]
 pub struct SdkMcpTool {
     pub name: String,
     pub description: String,
     pub input_schema: Value,
-    pub handler: Arc<dyn Fn(Map<String, Value>) -> ToolFuture + Send + Sync>,
+    pub handler: ToolHandler,
 }
 
 impl SdkMcpTool {
@@ -107,6 +358,35 @@ impl SdkMcpTool {
             handler: Arc::new(move |args| Box::pin(handler(args))),
         }
     }
+
+    /// Like [`SdkMcpTool::new`], with a handler that also receives the
+    /// [`ToolInvocationContext`] of each call.
+    ///
+    /// The stored [`ToolHandler`] keeps the plain signature and picks the
+    /// context up when the SDK invokes it; called directly, it passes
+    /// [`ToolInvocationContext::default`].
+    pub fn new_with_context<F, Fut>(
+        name: impl Into<String>,
+        description: impl Into<String>,
+        input_schema: Value,
+        handler: F,
+    ) -> Self
+    where
+        F: Fn(Map<String, Value>, ToolInvocationContext) -> Fut + Send + Sync + 'static,
+        Fut: Future<Output = Result<McpToolCallResult, SdkError>> + Send + 'static,
+    {
+        Self {
+            name: name.into(),
+            description: description.into(),
+            input_schema,
+            handler: Arc::new(move |args| {
+                let context = INVOCATION_CONTEXT
+                    .try_with(Clone::clone)
+                    .unwrap_or_default();
+                Box::pin(handler(args, context))
+            }),
+        }
+    }
 }
 
 /// Convenience factory emulating the Python `@tool` decorator.
@@ -123,6 +403,45 @@ where
     SdkMcpTool::new(name, description, input_schema, handler)
 }
 
+/// [`tool`] with a handler receiving the [`ToolInvocationContext`].
+pub fn tool_with_context<F, Fut>(
+    name: impl Into<String>,
+    description: impl Into<String>,
+    input_schema: Value,
+    handler: F,
+) -> SdkMcpTool
+where
+    F: Fn(Map<String, Value>, ToolInvocationContext) -> Fut + Send + Sync + 'static,
+    Fut: Future<Output = Result<McpToolCallResult, SdkError>> + Send + 'static,
+{
+    SdkMcpTool::new_with_context(name, description, input_schema, handler)
+}
+
+/// Future returned by [`McpFallbackHandler`]s: the JSON-RPC response.
+pub type McpFallbackFuture = Pin<Box<dyn Future<Output = Result<Value, SdkError>> + Send>>;
+
+/// Handles MCP messages the CLI routes to a server name with no SDK server,
+/// for example by proxying them to an external MCP client.
+///
+/// Return the JSON-RPC response for `message`; an error is reported to the
+/// CLI as a failed request.
+pub trait McpFallbackHandler: Send + Sync {
+    fn handle(&self, server_name: &str, message: Map<String, Value>) -> McpFallbackFuture;
+}
+
+impl<F, Fut> McpFallbackHandler for F
+where
+    F: Fn(&str, Map<String, Value>) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<Value, SdkError>> + Send + 'static,
+{
+    fn handle(&self, server_name: &str, message: Map<String, Value>) -> McpFallbackFuture {
+        Box::pin(self(server_name, message))
+    }
+}
+
+/// Shared handle for a fallback handler.
+pub type McpFallbackHandle = Arc<dyn McpFallbackHandler>;
+
 /// Trait implemented by MCP servers hosted inside the SDK process.
 #[async_trait]
 pub trait SdkMcpServer: Send + Sync {
@@ -143,6 +462,77 @@ pub trait SdkMcpServer: Send + Sync {
         name: &str,
         arguments: Map<String, Value>,
     ) -> Result<McpToolCallResult, SdkError>;
+
+    /// Invoke a tool with the context of the call; the SDK calls this, and
+    /// by default it ignores `context` and calls [`SdkMcpServer::call_tool`].
+    async fn call_tool_with_context(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+        context: ToolInvocationContext,
+    ) -> Result<McpToolCallResult, SdkError> {
+        let _ = context;
+        self.call_tool(name, arguments).await
+    }
+
+    /// Capabilities advertised to the CLI in the `initialize` response.
+    fn capabilities(&self) -> McpServerCapabilities {
+        McpServerCapabilities::default()
+    }
+
+    /// List the resources made available by this server.
+    async fn list_resources(&self) -> Result<Vec<McpResourceInfo>, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// List the resource templates made available by this server.
+    async fn list_resource_templates(&self) -> Result<Vec<McpResourceTemplateInfo>, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Read the contents of a resource.
+    async fn read_resource(&self, _uri: &str) -> Result<Vec<McpResourceContents>, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Subscribe to change notifications for a resource.
+    async fn subscribe_resource(&self, _uri: &str) -> Result<(), SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Cancel a previous resource subscription.
+    async fn unsubscribe_resource(&self, _uri: &str) -> Result<(), SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// List the prompts made available by this server.
+    async fn list_prompts(&self) -> Result<Vec<McpPromptInfo>, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Render a prompt with the supplied arguments.
+    async fn get_prompt(
+        &self,
+        _name: &str,
+        _arguments: Map<String, Value>,
+    ) -> Result<McpPromptResult, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Update the minimum log level the server should emit.
+    async fn set_logging_level(&self, _level: &str) -> Result<(), SdkError> {
+        Err(SdkError::NotImplemented)
+    }
+
+    /// Produce completion candidates for a prompt or resource template argument.
+    async fn complete(
+        &self,
+        _reference: Value,
+        _argument_name: &str,
+        _argument_value: &str,
+    ) -> Result<McpCompletion, SdkError> {
+        Err(SdkError::NotImplemented)
+    }
 }
 
 /// In-process MCP server implementation.
@@ -180,13 +570,25 @@ impl SdkMcpServer for InProcessMcpServer {
         &self,
         name: &str,
         arguments: Map<String, Value>,
+    ) -> Result<McpToolCallResult, SdkError> {
+        self.call_tool_with_context(name, arguments, ToolInvocationContext::default())
+            .await
+    }
+
+    async fn call_tool_with_context(
+        &self,
+        name: &str,
+        arguments: Map<String, Value>,
+        context: ToolInvocationContext,
     ) -> Result<McpToolCallResult, SdkError> {
         let tool = self
             .tools
             .iter()
             .find(|tool| tool.name == name)
             .ok_or_else(|| SdkError::Message(format!("Tool '{name}' not found")))?;
-        (tool.handler)(arguments).await
+        INVOCATION_CONTEXT
+            .sync_scope(context, || (tool.handler)(arguments))
+            .await
     }
 }
 
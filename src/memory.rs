@@ -0,0 +1,307 @@
+//! Facts and decisions carried across sessions without `resume`.
+//!
+//! A [`ConversationMemory`] collects short entries while a session runs:
+//!
+//! * assistant text lines starting with `Decision:`, `Fact:`, `Note:` or
+//!   `Remember:`, fed through [`ConversationMemory::observe`];
+//! * files edited and commands run, fed by the PostToolUse hook from
+//!   [`ConversationMemory::post_tool_use_hook`];
+//! * anything passed to [`ConversationMemory::remember`].
+//!
+//! Entries are kept verbatim; nothing is summarized by a model.
+//! [`ConversationMemory::prompt_section`] lists the newest entries that fit
+//! in a character budget, grouped by kind, and
+//! [`ConversationMemory::apply_to`] appends that list to the system prompt of
+//! the next session. Save the memory with [`ConversationMemory::save`] to keep
+//! it, and its entry limit, across process restarts.
+
+use std::fmt;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::SystemTime;
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::hooks::{HookEvent, HookInput, HookJsonOutput, HookMatcher, SyncHookJsonOutput};
+use crate::message::{ContentBlock, Message};
+
+/// Entries kept when no limit is configured.
+const DEFAULT_MAX_ENTRIES: usize = 200;
+/// Longest command recorded for a Bash call.
+const MAX_COMMAND_CHARS: usize = 120;
+
+/// Assistant line prefixes picked up by [`ConversationMemory::observe`].
+const MARKERS: &[(&str, MemoryKind)] = &[
+    ("decision:", MemoryKind::Decision),
+    ("fact:", MemoryKind::Fact),
+    ("note:", MemoryKind::Fact),
+    ("remember:", MemoryKind::Fact),
+];
+
+/// What a memory entry records.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MemoryKind {
+    Decision,
+    Fact,
+    /// A file edited or a command run.
+    Change,
+}
+
+impl MemoryKind {
+    fn heading(self) -> &'static str {
+        match self {
+            MemoryKind::Decision => "Decisions",
+            MemoryKind::Fact => "Facts",
+            MemoryKind::Change => "Changes",
+        }
+    }
+}
+
+/// A single remembered item.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub kind: MemoryKind,
+    pub text: String,
+    pub recorded_at: SystemTime,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct MemoryState {
+    entries: Vec<MemoryEntry>,
+    #[serde(default = "default_max_entries")]
+    max_entries: usize,
+}
+
+fn default_max_entries() -> usize {
+    DEFAULT_MAX_ENTRIES
+}
+
+/// Shared, bounded list of remembered entries. Clones share the same list.
+#[derive(Clone)]
+pub struct ConversationMemory {
+    state: Arc<Mutex<MemoryState>>,
+}
+
+impl Default for ConversationMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for ConversationMemory {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let state = self.state.lock().expect("memory lock poisoned");
+        f.debug_struct("ConversationMemory")
+            .field("entries", &state.entries.len())
+            .field("max_entries", &state.max_entries)
+            .finish()
+    }
+}
+
+impl ConversationMemory {
+    pub fn new() -> Self {
+        Self {
+            state: Arc::new(Mutex::new(MemoryState {
+                entries: Vec::new(),
+                max_entries: DEFAULT_MAX_ENTRIES,
+            })),
+        }
+    }
+
+    /// Keep at most `max_entries`, dropping the oldest first.
+    pub fn with_max_entries(self, max_entries: usize) -> Self {
+        {
+            let mut state = self.state.lock().expect("memory lock poisoned");
+            state.max_entries = max_entries.max(1);
+            Self::trim(&mut state);
+        }
+        self
+    }
+
+    /// Memory saved by [`ConversationMemory::save`], trimmed to its saved
+    /// entry limit; empty if `path` does not exist yet.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let bytes = match std::fs::read(path.as_ref()) {
+            Ok(bytes) => bytes,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Self::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut state: MemoryState = serde_json::from_slice(&bytes)?;
+        state.max_entries = state.max_entries.max(1);
+        Self::trim(&mut state);
+        Ok(Self {
+            state: Arc::new(Mutex::new(state)),
+        })
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SdkError> {
+        let json = {
+            let state = self.state.lock().expect("memory lock poisoned");
+            serde_json::to_vec_pretty(&*state)?
+        };
+        std::fs::write(path, json)?;
+        Ok(())
+    }
+
+    /// Entries from oldest to newest.
+    pub fn entries(&self) -> Vec<MemoryEntry> {
+        self.state
+            .lock()
+            .expect("memory lock poisoned")
+            .entries
+            .clone()
+    }
+
+    pub fn clear(&self) {
+        self.state
+            .lock()
+            .expect("memory lock poisoned")
+            .entries
+            .clear();
+    }
+
+    /// Record an entry. Repeating an existing entry moves it to the newest
+    /// position instead of adding a duplicate.
+    pub fn remember(&self, kind: MemoryKind, text: impl Into<String>) {
+        let text = text.into().trim().to_string();
+        if text.is_empty() {
+            return;
+        }
+        let mut state = self.state.lock().expect("memory lock poisoned");
+        state
+            .entries
+            .retain(|entry| entry.kind != kind || entry.text != text);
+        state.entries.push(MemoryEntry {
+            kind,
+            text,
+            recorded_at: SystemTime::now(),
+        });
+        Self::trim(&mut state);
+    }
+
+    /// Record assistant text lines starting with one of the markers, minus
+    /// the marker.
+    pub fn observe(&self, message: &Message) {
+        let Message::Assistant(assistant) = message else {
+            return;
+        };
+        for block in &assistant.content {
+            let ContentBlock::Text(text) = block else {
+                continue;
+            };
+            for line in text.text.lines() {
+                let line = line.trim().trim_start_matches(['-', '*', ' ']);
+                let lower = line.to_ascii_lowercase();
+                if let Some((marker, kind)) =
+                    MARKERS.iter().find(|(marker, _)| lower.starts_with(marker))
+                {
+                    self.remember(*kind, &line[marker.len()..]);
+                }
+            }
+        }
+    }
+
+    /// Record the effect of a completed tool call: edited files and Bash
+    /// commands. Other tools are ignored.
+    pub fn record_tool_use(&self, tool_name: &str, input: &Map<String, Value>) {
+        let field = |name: &str| input.get(name).and_then(Value::as_str);
+        match tool_name {
+            "Write" | "Edit" | "MultiEdit" => {
+                if let Some(path) = field("file_path") {
+                    self.remember(MemoryKind::Change, format!("Modified {path}"));
+                }
+            }
+            "NotebookEdit" => {
+                if let Some(path) = field("notebook_path") {
+                    self.remember(MemoryKind::Change, format!("Modified {path}"));
+                }
+            }
+            "Bash" => {
+                if let Some(command) = field("command") {
+                    let shown: String = command.chars().take(MAX_COMMAND_CHARS).collect();
+                    let ellipsis = if shown.len() < command.len() {
+                        "…"
+                    } else {
+                        ""
+                    };
+                    self.remember(MemoryKind::Change, format!("Ran `{shown}{ellipsis}`"));
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// PostToolUse hook feeding [`ConversationMemory::record_tool_use`].
+    pub fn post_tool_use_hook(&self) -> HookMatcher {
+        let memory = self.clone();
+        let mut matcher = HookMatcher::new(None);
+        matcher.hooks.push(Arc::new(move |input: HookInput, _, _| {
+            if let HookInput::PostToolUse(input) = &input {
+                memory.record_tool_use(&input.tool_name, &input.tool_input);
+            }
+            async { HookJsonOutput::Sync(SyncHookJsonOutput::default()) }
+        }));
+        matcher
+    }
+
+    /// System prompt section listing the newest entries that fit in
+    /// `max_chars`, grouped by kind, or `None` when nothing is remembered.
+    pub fn prompt_section(&self, max_chars: usize) -> Option<String> {
+        const HEADER: &str = "Context remembered from earlier sessions:";
+        let state = self.state.lock().expect("memory lock poisoned");
+        let mut used = HEADER.len();
+        let mut kept: Vec<&MemoryEntry> = Vec::new();
+        for entry in state.entries.iter().rev() {
+            // "- text\n" plus a possible group heading.
+            let cost = entry.text.len() + 3 + entry.kind.heading().len() + 2;
+            if used + cost > max_chars {
+                break;
+            }
+            used += cost;
+            kept.push(entry);
+        }
+        if kept.is_empty() {
+            return None;
+        }
+        kept.reverse();
+
+        let mut section = String::from(HEADER);
+        for kind in [MemoryKind::Decision, MemoryKind::Fact, MemoryKind::Change] {
+            let mut group = kept.iter().filter(|entry| entry.kind == kind).peekable();
+            if group.peek().is_none() {
+                continue;
+            }
+            section.push_str("\n\n");
+            section.push_str(kind.heading());
+            section.push(':');
+            for entry in group {
+                section.push_str("\n- ");
+                section.push_str(&entry.text);
+            }
+        }
+        Some(section)
+    }
+
+    /// Append [`ConversationMemory::prompt_section`] to the system prompt and
+    /// register [`ConversationMemory::post_tool_use_hook`].
+    pub fn apply_to(&self, options: &mut ClaudeAgentOptions, max_chars: usize) {
+        if let Some(section) = self.prompt_section(max_chars) {
+            options.append_system_prompt(&section);
+        }
+        options
+            .hooks
+            .get_or_insert_with(Default::default)
+            .entry(HookEvent::PostToolUse)
+            .or_default()
+            .push(self.post_tool_use_hook());
+    }
+
+    fn trim(state: &mut MemoryState) {
+        let excess = state.entries.len().saturating_sub(state.max_entries);
+        state.entries.drain(..excess);
+    }
+}
@@ -1,5 +1,7 @@
 //! Typed messages exchanged with the Claude Code CLI.
 
+use std::collections::BTreeMap;
+
 use serde::{Deserialize, Serialize};
 use serde_json::{Map, Value};
 
@@ -95,6 +97,103 @@ pub struct ResultMessage {
     pub usage: Option<Map<String, Value>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub result: Option<String>,
+    /// Tool calls denied during the run.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub permission_denials: Vec<PermissionDenial>,
+    /// Usage and cost per model, including subagents and fallbacks.
+    #[serde(
+        default,
+        rename = "modelUsage",
+        skip_serializing_if = "BTreeMap::is_empty"
+    )]
+    pub model_usage: BTreeMap<String, ModelUsage>,
+    /// Fields of the result not mapped above, as the CLI sent them.
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
+}
+
+/// A tool call refused by a permission rule, callback or the user.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct PermissionDenial {
+    pub tool_name: String,
+    pub tool_use_id: String,
+    #[serde(default)]
+    pub tool_input: Map<String, Value>,
+}
+
+/// Tokens and cost one model accounted for in a run.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+#[serde(default, rename_all = "camelCase")]
+pub struct ModelUsage {
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_read_input_tokens: u64,
+    pub cache_creation_input_tokens: u64,
+    pub web_search_requests: u64,
+    #[serde(rename = "costUSD")]
+    pub cost_usd: f64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub context_window: Option<u64>,
+}
+
+impl ResultMessage {
+    /// Typed error for results where the CLI stopped on a configured limit.
+    ///
+    /// `max_turns` and `max_budget_usd` are the limits the session was started
+    /// with; they are echoed back in the error because the CLI does not report them.
+    /// The error carries a copy of this result.
+    pub fn limit_error(
+        &self,
+        max_turns: Option<u32>,
+        max_budget_usd: Option<f64>,
+    ) -> Option<crate::error::SdkError> {
+        match self.subtype.as_str() {
+            "error_max_turns" => Some(crate::error::SdkError::MaxTurns {
+                turns: self.num_turns,
+                limit: max_turns,
+                result: Some(Box::new(self.clone())),
+            }),
+            "error_max_budget_usd" => Some(crate::error::SdkError::BudgetExceeded {
+                spent: self.total_cost_usd.unwrap_or_default(),
+                limit: max_budget_usd,
+                result: Box::new(self.clone()),
+            }),
+            _ => None,
+        }
+    }
+
+    /// Why this result calls for retrying on another model, if it does.
+    ///
+    /// The CLI reports API failures as error results carrying the API error
+    /// text, so this matches on that text: HTTP 529 and `overloaded_error`
+    /// mean overloaded, `not_found_error` or an invalid or unavailable model
+    /// mean the model cannot serve the request.
+    pub fn fallback_reason(&self) -> Option<ModelFallbackReason> {
+        if !self.is_error {
+            return None;
+        }
+        let text = self.result.as_deref()?.to_ascii_lowercase();
+        if text.contains("overloaded") || text.contains("error: 529") {
+            return Some(ModelFallbackReason::Overloaded);
+        }
+        let model_problem = ["not_found_error", "invalid model", "model not found"]
+            .iter()
+            .any(|needle| text.contains(needle))
+            || (text.contains("model")
+                && ["not available", "unavailable", "does not exist"]
+                    .iter()
+                    .any(|needle| text.contains(needle)));
+        model_problem.then_some(ModelFallbackReason::ModelUnavailable)
+    }
+}
+
+/// Result failures that [`ResultMessage::fallback_reason`] treats as model
+/// specific.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ModelFallbackReason {
+    Overloaded,
+    /// The model name is invalid or not available to this account.
+    ModelUnavailable,
 }
 
 /// Stream event for partial updates during streaming completions.
@@ -116,3 +215,26 @@ pub enum Message {
     Result(ResultMessage),
     StreamEvent(StreamEvent),
 }
+
+impl Message {
+    /// A user message that carries no tool results, such as a prompt the CLI
+    /// replays with `--replay-user-messages`.
+    pub fn is_user_echo(&self) -> bool {
+        match self {
+            Message::User(user) => {
+                user.parent_tool_use_id.is_none()
+                    && !matches!(&user.content, UserMessageContent::Blocks(blocks)
+                        if blocks.iter().any(|block| matches!(block, ContentBlock::ToolResult(_))))
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A message exactly as the CLI wrote it, with its typed form.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RawMessage {
+    pub value: Value,
+    /// `None` when the payload does not parse as a [`Message`].
+    pub message: Option<Message>,
+}
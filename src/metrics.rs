@@ -0,0 +1,215 @@
+//! Pluggable counters and latency histograms for SDK activity.
+//!
+//! Implement [`SdkMetrics`] to forward measurements to Prometheus, StatsD or
+//! any other backend; every method defaults to a no-op so implementors only
+//! override what they export.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::frame_log::FrameDirection;
+use crate::message::ResultMessage;
+
+/// Receiver for SDK measurements.
+pub trait SdkMetrics: Send + Sync {
+    /// A message was read from the CLI; `kind` is its `type` field.
+    fn message_received(&self, _kind: &str) {}
+
+    /// A message was written to the CLI.
+    fn message_sent(&self) {}
+
+    /// A control request/response exchange finished.
+    fn control_request(
+        &self,
+        _subtype: &str,
+        _direction: FrameDirection,
+        _latency: Duration,
+        _success: bool,
+    ) {
+    }
+
+    /// An in-process MCP tool call finished.
+    fn tool_call(&self, _server: &str, _tool: &str, _latency: Duration, _success: bool) {}
+
+    /// A line from the CLI could not be decoded or parsed into a message.
+    fn parse_failure(&self) {}
+
+    /// The CLI process was started again for an existing client.
+    fn process_restart(&self) {}
+
+    /// A query finished with the given result, carrying its usage and cost.
+    fn query_completed(&self, _result: &ResultMessage) {}
+}
+
+/// Convenient handle for storing metrics implementations.
+pub type SdkMetricsHandle = Arc<dyn SdkMetrics>;
+
+/// Metrics implementation that discards everything.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopMetrics;
+
+impl SdkMetrics for NoopMetrics {}
+
+/// Upper bounds of the latency buckets, in milliseconds.
+pub const LATENCY_BUCKETS_MS: [u64; 11] =
+    [5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// Cumulative latency distribution with fixed buckets.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct LatencyHistogram {
+    /// Number of observations.
+    pub count: u64,
+    /// Sum of all observations.
+    pub sum: Duration,
+    /// Smallest observation.
+    pub min: Option<Duration>,
+    /// Largest observation.
+    pub max: Option<Duration>,
+    /// Observation counts per [`LATENCY_BUCKETS_MS`] bound; the last slot counts
+    /// observations above the largest bound.
+    pub buckets: [u64; LATENCY_BUCKETS_MS.len() + 1],
+}
+
+impl LatencyHistogram {
+    /// Record one observation.
+    pub fn observe(&mut self, latency: Duration) {
+        self.count += 1;
+        self.sum += latency;
+        self.min = Some(self.min.map_or(latency, |min| min.min(latency)));
+        self.max = Some(self.max.map_or(latency, |max| max.max(latency)));
+        let millis = latency.as_millis();
+        let slot = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| millis <= u128::from(*bound))
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[slot] += 1;
+    }
+
+    /// Average latency, if anything was observed.
+    pub fn mean(&self) -> Option<Duration> {
+        u32::try_from(self.count)
+            .ok()
+            .filter(|count| *count > 0)
+            .map(|count| self.sum / count)
+    }
+}
+
+/// Point-in-time copy of the values held by [`InMemoryMetrics`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MetricsSnapshot {
+    /// Received messages keyed by message type.
+    pub messages_received: HashMap<String, u64>,
+    pub messages_sent: u64,
+    /// Control request latency keyed by subtype.
+    pub control_latency: HashMap<String, LatencyHistogram>,
+    pub control_failures: u64,
+    /// Tool call latency keyed by `server/tool`.
+    pub tool_latency: HashMap<String, LatencyHistogram>,
+    pub tool_failures: u64,
+    pub parse_failures: u64,
+    pub process_restarts: u64,
+    pub queries_completed: u64,
+    /// Sum of `total_cost_usd` over completed queries.
+    pub total_cost_usd: f64,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+}
+
+/// Metrics implementation aggregating everything in memory.
+#[derive(Debug, Default)]
+pub struct InMemoryMetrics {
+    state: Mutex<MetricsSnapshot>,
+}
+
+impl InMemoryMetrics {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copy of the current values.
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        self.state
+            .lock()
+            .map(|state| state.clone())
+            .unwrap_or_default()
+    }
+
+    /// Reset every counter and histogram.
+    pub fn reset(&self) {
+        self.update(|state| *state = MetricsSnapshot::default());
+    }
+
+    fn update(&self, apply: impl FnOnce(&mut MetricsSnapshot)) {
+        if let Ok(mut state) = self.state.lock() {
+            apply(&mut state);
+        }
+    }
+}
+
+impl SdkMetrics for InMemoryMetrics {
+    fn message_received(&self, kind: &str) {
+        self.update(|state| *state.messages_received.entry(kind.to_string()).or_default() += 1);
+    }
+
+    fn message_sent(&self) {
+        self.update(|state| state.messages_sent += 1);
+    }
+
+    fn control_request(
+        &self,
+        subtype: &str,
+        _direction: FrameDirection,
+        latency: Duration,
+        success: bool,
+    ) {
+        self.update(|state| {
+            state
+                .control_latency
+                .entry(subtype.to_string())
+                .or_default()
+                .observe(latency);
+            if !success {
+                state.control_failures += 1;
+            }
+        });
+    }
+
+    fn tool_call(&self, server: &str, tool: &str, latency: Duration, success: bool) {
+        self.update(|state| {
+            state
+                .tool_latency
+                .entry(format!("{server}/{tool}"))
+                .or_default()
+                .observe(latency);
+            if !success {
+                state.tool_failures += 1;
+            }
+        });
+    }
+
+    fn parse_failure(&self) {
+        self.update(|state| state.parse_failures += 1);
+    }
+
+    fn process_restart(&self) {
+        self.update(|state| state.process_restarts += 1);
+    }
+
+    fn query_completed(&self, result: &ResultMessage) {
+        let tokens = |key: &str| {
+            result
+                .usage
+                .as_ref()
+                .and_then(|usage| usage.get(key))
+                .and_then(serde_json::Value::as_u64)
+                .unwrap_or(0)
+        };
+        self.update(|state| {
+            state.queries_completed += 1;
+            state.total_cost_usd += result.total_cost_usd.unwrap_or(0.0);
+            state.input_tokens += tokens("input_tokens");
+            state.output_tokens += tokens("output_tokens");
+        });
+    }
+}
@@ -0,0 +1,94 @@
+//! Rewriting of outgoing user messages.
+//!
+//! Each [`PromptMiddleware`] in [`ClaudeAgentOptions::prompt_middleware`]
+//! sees every user message before it is written to the CLI, in order, and
+//! may edit it in place or reject it with an error. Messages are the raw
+//! stream-json payloads:
+//!
+//! ```json
+//! {"type": "user", "message": {"role": "user", "content": "..."}, "session_id": "..."}
+//! ```
+//!
+//! where `content` is a string or an array of content blocks.
+//!
+//! The chain runs in [`ClaudeSdkClient::query`](crate::client::ClaudeSdkClient::query),
+//! on streamed prompts, and on one-shot text prompts. A one-shot text prompt
+//! is passed on the command line, so its content has to remain a string.
+//!
+//! [`ClaudeAgentOptions::prompt_middleware`]: crate::config::ClaudeAgentOptions::prompt_middleware
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use crate::error::SdkError;
+use crate::redact::Redactor;
+
+/// Hook rewriting an outgoing user message.
+pub trait PromptMiddleware: Send + Sync {
+    fn process(&self, message: &mut Value) -> Result<(), SdkError>;
+}
+
+impl<F> PromptMiddleware for F
+where
+    F: Fn(&mut Value) -> Result<(), SdkError> + Send + Sync,
+{
+    fn process(&self, message: &mut Value) -> Result<(), SdkError> {
+        self(message)
+    }
+}
+
+/// Convenient handle for storing prompt middleware.
+pub type PromptMiddlewareHandle = Arc<dyn PromptMiddleware>;
+
+/// Run `chain` over `message` in order. Messages other than user messages
+/// pass through untouched.
+pub fn apply(chain: &[PromptMiddlewareHandle], message: &mut Value) -> Result<(), SdkError> {
+    if message.get("type").and_then(Value::as_str) != Some("user") {
+        return Ok(());
+    }
+    chain
+        .iter()
+        .try_for_each(|middleware| middleware.process(message))
+}
+
+/// Appends fixed text, such as project context or guardrails, to every
+/// user message. Text content gets it after a blank line; block content gets
+/// an extra text block.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppendText {
+    text: String,
+}
+
+impl AppendText {
+    pub fn new(text: impl Into<String>) -> Self {
+        Self { text: text.into() }
+    }
+}
+
+impl PromptMiddleware for AppendText {
+    fn process(&self, message: &mut Value) -> Result<(), SdkError> {
+        match message.pointer_mut("/message/content") {
+            Some(Value::String(content)) => {
+                content.push_str("\n\n");
+                content.push_str(&self.text);
+            }
+            Some(Value::Array(blocks)) => {
+                blocks.push(json!({"type": "text", "text": self.text}));
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+}
+
+/// Masks secrets and pattern matches in the message content, e.g. to strip
+/// PII before it leaves the process.
+impl PromptMiddleware for Redactor {
+    fn process(&self, message: &mut Value) -> Result<(), SdkError> {
+        if let Some(content) = message.pointer_mut("/message/content") {
+            self.redact_value(content);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,159 @@
+//! Model names the CLI accepts.
+//!
+//! [`Model::new`] checks a name before it reaches the CLI: aliases such as
+//! `sonnet` and anything naming a Claude model (`claude-sonnet-4-5`, Bedrock
+//! and Vertex ids) pass, while typos like `sonet` fail with a suggestion.
+//! Other names, such as Bedrock inference profile ARNs or a gateway's own
+//! model names, pass with a warning. Deprecated ids are accepted with a
+//! warning naming their replacement.
+
+use std::fmt;
+use std::str::FromStr;
+
+use crate::config::edit_distance;
+use crate::error::SdkError;
+use crate::internal::trace::sdk_warn;
+
+/// Short names the CLI resolves to its current models.
+pub const ALIASES: &[&str] = &[
+    "default",
+    "sonnet",
+    "opus",
+    "haiku",
+    "opusplan",
+    "sonnet[1m]",
+    "opus[1m]",
+    "haiku[1m]",
+];
+
+/// A model id this SDK knows about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KnownModel {
+    pub id: &'static str,
+    /// Id to move to, when this one is deprecated.
+    pub replaced_by: Option<&'static str>,
+}
+
+const fn current(id: &'static str) -> KnownModel {
+    KnownModel {
+        id,
+        replaced_by: None,
+    }
+}
+
+const fn deprecated(id: &'static str, replaced_by: &'static str) -> KnownModel {
+    KnownModel {
+        id,
+        replaced_by: Some(replaced_by),
+    }
+}
+
+/// Dated and undated model ids, newest first.
+pub const KNOWN_MODELS: &[KnownModel] = &[
+    current("claude-sonnet-4-5"),
+    current("claude-sonnet-4-5-20250929"),
+    current("claude-haiku-4-5"),
+    current("claude-haiku-4-5-20251001"),
+    current("claude-opus-4-1"),
+    current("claude-opus-4-1-20250805"),
+    current("claude-opus-4-0"),
+    current("claude-opus-4-20250514"),
+    current("claude-sonnet-4-0"),
+    current("claude-sonnet-4-20250514"),
+    current("claude-3-7-sonnet-latest"),
+    current("claude-3-7-sonnet-20250219"),
+    current("claude-3-5-haiku-latest"),
+    current("claude-3-5-haiku-20241022"),
+    current("claude-3-haiku-20240307"),
+    deprecated("claude-3-5-sonnet-latest", "claude-sonnet-4-5"),
+    deprecated("claude-3-5-sonnet-20241022", "claude-sonnet-4-5"),
+    deprecated("claude-3-5-sonnet-20240620", "claude-sonnet-4-5"),
+    deprecated("claude-3-opus-latest", "claude-opus-4-1"),
+    deprecated("claude-3-opus-20240229", "claude-opus-4-1"),
+    deprecated("claude-3-sonnet-20240229", "claude-sonnet-4-5"),
+];
+
+/// Registry entry for `id`, if it is a known model id.
+pub fn lookup(id: &str) -> Option<&'static KnownModel> {
+    KNOWN_MODELS.iter().find(|known| known.id == id)
+}
+
+/// A validated model name.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub struct Model(String);
+
+impl Model {
+    /// Validate `name`, rejecting near-misses of an alias or known id and
+    /// warning when it is deprecated or unrecognised.
+    pub fn new(name: impl Into<String>) -> Result<Self, SdkError> {
+        let name = name.into();
+        if !ALIASES.contains(&name.as_str()) && !name.to_ascii_lowercase().contains("claude") {
+            if let Some(known) = closest_model(&name) {
+                return Err(SdkError::InvalidConfig(format!(
+                    "unknown model \"{name}\"; did you mean {known}?"
+                )));
+            }
+            sdk_warn!("model {name} is not a known alias or id; passing it to the CLI as is");
+        }
+        if let Some(replacement) = lookup(&name).and_then(|known| known.replaced_by) {
+            sdk_warn!("model {name} is deprecated; use {replacement}");
+        }
+        Ok(Self(name))
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.0
+    }
+
+    /// Whether this is one of [`ALIASES`] rather than a concrete id.
+    pub fn is_alias(&self) -> bool {
+        ALIASES.contains(&self.0.as_str())
+    }
+
+    /// Replacement for a deprecated model.
+    pub fn replaced_by(&self) -> Option<&'static str> {
+        lookup(&self.0).and_then(|known| known.replaced_by)
+    }
+
+    pub fn into_string(self) -> String {
+        self.0
+    }
+}
+
+impl fmt::Display for Model {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl AsRef<str> for Model {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for Model {
+    type Err = SdkError;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        Self::new(name)
+    }
+}
+
+impl From<Model> for String {
+    fn from(model: Model) -> Self {
+        model.0
+    }
+}
+
+/// Alias or known id within a few edits of `name`.
+fn closest_model(name: &str) -> Option<&'static str> {
+    ALIASES
+        .iter()
+        .copied()
+        .chain(KNOWN_MODELS.iter().map(|known| known.id))
+        .map(|known| (edit_distance(name, known), known))
+        .filter(|(distance, known)| *distance <= (known.len() / 3).max(2))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, known)| known)
+}
@@ -0,0 +1,628 @@
+//! Serializable options for configuration files.
+//!
+//! [`ClaudeAgentOptions`] mixes plain settings with runtime handles such as
+//! callbacks, hooks and in-process MCP servers, which are skipped when it is
+//! serialized. An [`OptionsDocument`] holds exactly the serializable part,
+//! rejects unknown fields so typos in a config file fail loudly, and
+//! [`OptionsDocument::json_schema`] describes it for editor autocomplete:
+//!
+//! ```no_run
+//! # use sdk_claude_rust::config::ClaudeAgentOptions;
+//! # use sdk_claude_rust::options_document::OptionsDocument;
+//! # fn main() -> Result<(), sdk_claude_rust::error::SdkError> {
+//! let document = OptionsDocument::load("claude.json")?;
+//! let mut options = ClaudeAgentOptions::from_document(document);
+//! options.stderr = Some(std::sync::Arc::new(|line: &str| eprintln!("{line}")));
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! A file may name its schema with a `$schema` key, which is kept but does
+//! not affect the options.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map, Value};
+
+use crate::config::{
+    AgentDefinition, ClaudeAgentOptions, FlagMode, InFlightPolicy, McpServers, SdkPluginConfig,
+    SessionTurnLimit, SettingSource, StdoutMode, SystemPrompt, ThinkingConfig, MIN_THINKING_BUDGET,
+};
+use crate::env::Provider;
+use crate::error::SdkError;
+use crate::mcp::health::McpPreflight;
+use crate::permission::PermissionMode;
+
+/// The serializable fields of [`ClaudeAgentOptions`], serialized the same
+/// way.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct OptionsDocument {
+    /// Schema reference for editors; ignored otherwise.
+    #[serde(rename = "$schema", skip_serializing_if = "Option::is_none")]
+    pub schema: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub allowed_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_prompt: Option<SystemPrompt>,
+    pub mcp_servers: McpServers,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<PermissionMode>,
+    pub continue_conversation: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resume: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_turns: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_budget_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub disallowed_tools: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub model_fallbacks: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub permission_prompt_tool_name: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cli_path: Option<PathBuf>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub settings: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub output_style: Option<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub add_dirs: Vec<PathBuf>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub env: HashMap<String, String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provider: Option<Provider>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entrypoint: Option<String>,
+    #[serde(skip_serializing_if = "HashMap::is_empty")]
+    pub extra_args: HashMap<String, Option<String>>,
+    pub flag_mode: FlagMode,
+    pub in_flight_policy: InFlightPolicy,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_turn_limit: Option<SessionTurnLimit>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_buffer_size: Option<usize>,
+    pub stdout_mode: StdoutMode,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub arg_file_threshold: Option<usize>,
+    pub mcp_preflight: McpPreflight,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user: Option<String>,
+    pub include_partial_messages: bool,
+    pub replay_user_messages: bool,
+    pub hide_user_echoes: bool,
+    pub keep_input_open: bool,
+    pub fork_session: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub agents: Option<HashMap<String, AgentDefinition>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub setting_sources: Option<Vec<SettingSource>>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub plugins: Vec<SdkPluginConfig>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_thinking_tokens: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub thinking: Option<ThinkingConfig>,
+}
+
+impl OptionsDocument {
+    /// Parse a document, failing with [`SdkError::InvalidConfig`] on unknown
+    /// fields and malformed values.
+    pub fn from_json(json: &str) -> Result<Self, SdkError> {
+        serde_json::from_str(json)
+            .map_err(|err| SdkError::InvalidConfig(format!("invalid options document: {err}")))
+    }
+
+    /// Read and parse a JSON document from `path`.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)?;
+        Self::from_json(&json).map_err(|err| match err {
+            SdkError::InvalidConfig(message) => {
+                SdkError::InvalidConfig(format!("{}: {message}", path.display()))
+            }
+            err => err,
+        })
+    }
+
+    pub fn to_json_pretty(&self) -> Result<String, SdkError> {
+        Ok(serde_json::to_string_pretty(self)?)
+    }
+
+    /// JSON Schema (draft-07) of the document, for editors to validate and
+    /// complete config files.
+    pub fn json_schema() -> Value {
+        let string = || json!({"type": "string"});
+        let strings = || json!({"type": "array", "items": {"type": "string"}});
+        let count = || json!({"type": "integer", "minimum": 0});
+        let flag = |description: &str| json!({"type": "boolean", "description": description});
+        let described = |mut schema: Value, description: &str| {
+            schema["description"] = json!(description);
+            schema
+        };
+        let properties: Map<String, Value> = [
+            (
+                "$schema",
+                described(string(), "Schema of this file; ignored by the SDK."),
+            ),
+            (
+                "allowed_tools",
+                described(
+                    strings(),
+                    "Tools allowed without a permission prompt, e.g. `Read` or `Bash(git:*)`.",
+                ),
+            ),
+            (
+                "system_prompt",
+                json!({"$ref": "#/definitions/system_prompt"}),
+            ),
+            ("mcp_servers", json!({"$ref": "#/definitions/mcp_servers"})),
+            (
+                "permission_mode",
+                json!({
+                    "description": "Permission mode requested from the CLI.",
+                    "enum": ["default", "acceptEdits", "plan", "bypassPermissions"]
+                }),
+            ),
+            (
+                "continue_conversation",
+                flag("Continue the most recent conversation."),
+            ),
+            ("resume", described(string(), "Session id to resume.")),
+            (
+                "max_turns",
+                described(count(), "Agent turns the CLI allows per query."),
+            ),
+            (
+                "max_budget_usd",
+                json!({
+                    "type": "number",
+                    "minimum": 0,
+                    "description": "Spending cap for the session, in US dollars."
+                }),
+            ),
+            (
+                "disallowed_tools",
+                described(strings(), "Tools the model may not use."),
+            ),
+            (
+                "model",
+                described(string(), "Model id or alias, e.g. `sonnet`."),
+            ),
+            (
+                "model_fallbacks",
+                described(
+                    strings(),
+                    "Models tried in order when the current one is overloaded or unavailable.",
+                ),
+            ),
+            (
+                "permission_prompt_tool_name",
+                described(string(), "MCP tool answering permission prompts."),
+            ),
+            ("cwd", described(string(), "Working directory of the CLI.")),
+            (
+                "cli_path",
+                described(string(), "Path to the `claude` executable."),
+            ),
+            (
+                "settings",
+                described(string(), "Settings file path or inline settings JSON."),
+            ),
+            (
+                "output_style",
+                described(
+                    string(),
+                    "Output style to start the session with, e.g. `Explanatory`.",
+                ),
+            ),
+            (
+                "add_dirs",
+                described(strings(), "Extra directories the CLI may access."),
+            ),
+            (
+                "env",
+                json!({
+                    "type": "object",
+                    "additionalProperties": {"type": "string"},
+                    "description": "Environment variables for the CLI process."
+                }),
+            ),
+            (
+                "provider",
+                json!({
+                    "description": "Where the CLI sends model requests.",
+                    "enum": ["anthropic", "bedrock", "vertex"]
+                }),
+            ),
+            (
+                "entrypoint",
+                described(
+                    string(),
+                    "`CLAUDE_CODE_ENTRYPOINT` reported by the CLI process.",
+                ),
+            ),
+            (
+                "extra_args",
+                json!({
+                    "type": "object",
+                    "additionalProperties": {"type": ["string", "null"]},
+                    "description": "Extra CLI flags without the leading `--`; `null` for flags \
+                                    without a value."
+                }),
+            ),
+            (
+                "flag_mode",
+                json!({
+                    "description": "Whether unknown `extra_args` flags are rejected.",
+                    "enum": ["permissive", "strict"]
+                }),
+            ),
+            (
+                "in_flight_policy",
+                json!({
+                    "description": "Handling of prompts sent while the previous response is \
+                                    still streaming.",
+                    "enum": ["allow", "error", "queue"]
+                }),
+            ),
+            (
+                "session_turn_limit",
+                json!({
+                    "type": "object",
+                    "description": "Ceiling on prompts per session, enforced by the client.",
+                    "properties": {
+                        "max_turns": count(),
+                        "on_limit": {"enum": ["refuse", "compact"]}
+                    },
+                    "required": ["max_turns"],
+                    "additionalProperties": false
+                }),
+            ),
+            (
+                "max_buffer_size",
+                described(count(), "Largest JSON message read from the CLI, in bytes."),
+            ),
+            (
+                "stdout_mode",
+                json!({
+                    "description": "Whether stdout lines that are not JSON are discarded.",
+                    "enum": ["strict", "tolerant"]
+                }),
+            ),
+            (
+                "arg_file_threshold",
+                described(
+                    count(),
+                    "Command-line length past which large arguments are passed through files.",
+                ),
+            ),
+            (
+                "mcp_preflight",
+                json!({
+                    "description": "Probing of remote MCP servers before connect.",
+                    "enum": ["off", "warn", "require"]
+                }),
+            ),
+            ("user", described(string(), "User to run the CLI as.")),
+            (
+                "include_partial_messages",
+                flag("Stream partial messages as `stream_event`s."),
+            ),
+            (
+                "replay_user_messages",
+                flag("Have the CLI echo each streamed user message back."),
+            ),
+            (
+                "hide_user_echoes",
+                flag("Drop user messages without tool results from message streams."),
+            ),
+            (
+                "keep_input_open",
+                flag("Leave the CLI's stdin open when the prompt stream ends."),
+            ),
+            (
+                "fork_session",
+                flag("Fork resumed sessions into a new session id."),
+            ),
+            (
+                "agents",
+                json!({
+                    "type": "object",
+                    "additionalProperties": {"$ref": "#/definitions/agent"},
+                    "description": "Subagents by name."
+                }),
+            ),
+            (
+                "setting_sources",
+                json!({
+                    "type": "array",
+                    "items": {"enum": ["user", "project", "local"]},
+                    "description": "Settings files the CLI loads."
+                }),
+            ),
+            (
+                "plugins",
+                json!({
+                    "type": "array",
+                    "items": {
+                        "type": "object",
+                        "properties": {
+                            "type": {"const": "local"},
+                            "path": string()
+                        },
+                        "required": ["type", "path"],
+                        "additionalProperties": false
+                    },
+                    "description": "Local plugins to load."
+                }),
+            ),
+            (
+                "max_thinking_tokens",
+                described(
+                    count(),
+                    "Extended thinking budget; superseded by `thinking`.",
+                ),
+            ),
+            (
+                "thinking",
+                json!({
+                    "type": "object",
+                    "description": "Extended thinking settings.",
+                    "properties": {
+                        "enabled": {"type": "boolean"},
+                        "budget_tokens": {"type": "integer", "minimum": MIN_THINKING_BUDGET}
+                    },
+                    "required": ["enabled"],
+                    "additionalProperties": false
+                }),
+            ),
+        ]
+        .into_iter()
+        .map(|(name, schema)| (name.to_string(), schema))
+        .collect();
+        let headers = || json!({"type": "object", "additionalProperties": {"type": "string"}});
+        let remote = |kind: &str| {
+            json!({
+                "type": "object",
+                "properties": {"type": {"const": kind}, "url": string(), "headers": headers()},
+                "required": ["type", "url"],
+                "additionalProperties": false
+            })
+        };
+        json!({
+            "$schema": "http://json-schema.org/draft-07/schema#",
+            "title": "Claude agent options",
+            "type": "object",
+            "properties": properties,
+            "additionalProperties": false,
+            "definitions": {
+                "system_prompt": {
+                    "description": "Custom system prompt, or a preset with optional text appended.",
+                    "oneOf": [
+                        string(),
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": {"const": "preset"},
+                                "preset": {"anyOf": [{"enum": ["claude_code"]}, string()]},
+                                "append": string()
+                            },
+                            "required": ["type", "preset"],
+                            "additionalProperties": false
+                        }
+                    ]
+                },
+                "mcp_servers": {
+                    "description": "MCP servers by name, or the path or inline JSON of an \
+                                    MCP config.",
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "additionalProperties": {"$ref": "#/definitions/mcp_server"}
+                        },
+                        string()
+                    ]
+                },
+                "mcp_server": {
+                    "oneOf": [
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": {"const": "stdio"},
+                                "command": string(),
+                                "args": strings(),
+                                "env": headers()
+                            },
+                            "required": ["type", "command"],
+                            "additionalProperties": false
+                        },
+                        remote("sse"),
+                        remote("http"),
+                        {
+                            "type": "object",
+                            "properties": {
+                                "type": {"const": "sdk"},
+                                "name": string(),
+                                "instance": {}
+                            },
+                            "required": ["type", "name"],
+                            "additionalProperties": false
+                        }
+                    ]
+                },
+                "agent": {
+                    "type": "object",
+                    "properties": {
+                        "description": string(),
+                        "prompt": string(),
+                        "tools": strings(),
+                        "model": string()
+                    },
+                    "required": ["description", "prompt"],
+                    "additionalProperties": false
+                }
+            }
+        })
+    }
+}
+
+impl ClaudeAgentOptions {
+    /// The serializable options, for writing a config file.
+    pub fn to_document(&self) -> OptionsDocument {
+        OptionsDocument {
+            schema: None,
+            allowed_tools: self.allowed_tools.clone(),
+            system_prompt: self.system_prompt.clone(),
+            mcp_servers: self.mcp_servers.clone(),
+            permission_mode: self.permission_mode,
+            continue_conversation: self.continue_conversation,
+            resume: self.resume.clone(),
+            max_turns: self.max_turns,
+            max_budget_usd: self.max_budget_usd,
+            disallowed_tools: self.disallowed_tools.clone(),
+            model: self.model.clone(),
+            model_fallbacks: self.model_fallbacks.clone(),
+            permission_prompt_tool_name: self.permission_prompt_tool_name.clone(),
+            cwd: self.cwd.clone(),
+            cli_path: self.cli_path.clone(),
+            settings: self.settings.clone(),
+            output_style: self.output_style.clone(),
+            add_dirs: self.add_dirs.clone(),
+            env: self.env.clone(),
+            provider: self.provider,
+            entrypoint: self.entrypoint.clone(),
+            extra_args: self.extra_args.clone(),
+            flag_mode: self.flag_mode,
+            in_flight_policy: self.in_flight_policy,
+            session_turn_limit: self.session_turn_limit,
+            max_buffer_size: self.max_buffer_size,
+            stdout_mode: self.stdout_mode,
+            arg_file_threshold: self.arg_file_threshold,
+            mcp_preflight: self.mcp_preflight,
+            user: self.user.clone(),
+            include_partial_messages: self.include_partial_messages,
+            replay_user_messages: self.replay_user_messages,
+            hide_user_echoes: self.hide_user_echoes,
+            keep_input_open: self.keep_input_open,
+            fork_session: self.fork_session,
+            agents: self.agents.clone(),
+            setting_sources: self.setting_sources.clone(),
+            plugins: self.plugins.clone(),
+            max_thinking_tokens: self.max_thinking_tokens,
+            thinking: self.thinking,
+        }
+    }
+
+    /// Options with the settings of `document` and no runtime handles.
+    pub fn from_document(document: OptionsDocument) -> Self {
+        let mut options = Self::default();
+        options.apply_document(document);
+        options
+    }
+
+    /// Replace the serializable options with those of `document`, keeping
+    /// callbacks, hooks, SDK servers and other runtime handles.
+    pub fn apply_document(&mut self, document: OptionsDocument) {
+        let OptionsDocument {
+            schema: _,
+            allowed_tools,
+            system_prompt,
+            mcp_servers,
+            permission_mode,
+            continue_conversation,
+            resume,
+            max_turns,
+            max_budget_usd,
+            disallowed_tools,
+            model,
+            model_fallbacks,
+            permission_prompt_tool_name,
+            cwd,
+            cli_path,
+            settings,
+            output_style,
+            add_dirs,
+            env,
+            provider,
+            entrypoint,
+            extra_args,
+            flag_mode,
+            in_flight_policy,
+            session_turn_limit,
+            max_buffer_size,
+            stdout_mode,
+            arg_file_threshold,
+            mcp_preflight,
+            user,
+            include_partial_messages,
+            replay_user_messages,
+            hide_user_echoes,
+            keep_input_open,
+            fork_session,
+            agents,
+            setting_sources,
+            plugins,
+            max_thinking_tokens,
+            thinking,
+        } = document;
+        self.allowed_tools = allowed_tools;
+        self.system_prompt = system_prompt;
+        self.mcp_servers = mcp_servers;
+        self.permission_mode = permission_mode;
+        self.continue_conversation = continue_conversation;
+        self.resume = resume;
+        self.max_turns = max_turns;
+        self.max_budget_usd = max_budget_usd;
+        self.disallowed_tools = disallowed_tools;
+        self.model = model;
+        self.model_fallbacks = model_fallbacks;
+        self.permission_prompt_tool_name = permission_prompt_tool_name;
+        self.cwd = cwd;
+        self.cli_path = cli_path;
+        self.settings = settings;
+        self.output_style = output_style;
+        self.add_dirs = add_dirs;
+        self.env = env;
+        self.provider = provider;
+        self.entrypoint = entrypoint;
+        self.extra_args = extra_args;
+        self.flag_mode = flag_mode;
+        self.in_flight_policy = in_flight_policy;
+        self.session_turn_limit = session_turn_limit;
+        self.max_buffer_size = max_buffer_size;
+        self.stdout_mode = stdout_mode;
+        self.arg_file_threshold = arg_file_threshold;
+        self.mcp_preflight = mcp_preflight;
+        self.user = user;
+        self.include_partial_messages = include_partial_messages;
+        self.replay_user_messages = replay_user_messages;
+        self.hide_user_echoes = hide_user_echoes;
+        self.keep_input_open = keep_input_open;
+        self.fork_session = fork_session;
+        self.agents = agents;
+        self.setting_sources = setting_sources;
+        self.plugins = plugins;
+        self.max_thinking_tokens = max_thinking_tokens;
+        self.thinking = thinking;
+    }
+}
+
+impl From<OptionsDocument> for ClaudeAgentOptions {
+    fn from(document: OptionsDocument) -> Self {
+        Self::from_document(document)
+    }
+}
+
+impl From<&ClaudeAgentOptions> for OptionsDocument {
+    fn from(options: &ClaudeAgentOptions) -> Self {
+        options.to_document()
+    }
+}
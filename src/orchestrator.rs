@@ -0,0 +1,429 @@
+//! Composing several agents into pipelines.
+//!
+//! A [`Stage`] turns an input into an output asynchronously. [`Agent`] is the
+//! stage backed by a one-shot Claude session: its input is the prompt and its
+//! output the answer text. Stages combine through [`StageExt`]:
+//!
+//! * [`then`](StageExt::then) hands one stage's output to the next;
+//! * [`map`](StageExt::map) and [`parse_json`](StageExt::parse_json) convert
+//!   outputs into typed values, and [`stage_fn`] wraps any async function,
+//!   e.g. to turn a typed input into a prompt;
+//! * [`fan_out`] runs stages concurrently on the same input and collects
+//!   their outputs for a following fan-in stage;
+//! * [`critic_loop`] alternates an author and a critic until the critic
+//!   accepts or the round limit is reached.
+//!
+//! ```no_run
+//! # use sdk_claude_rust::config::AgentDefinition;
+//! # use sdk_claude_rust::orchestrator::{critic_loop, Agent, Stage, StageExt, Verdict};
+//! # async fn example() -> Result<(), sdk_claude_rust::error::SdkError> {
+//! let writer = Agent::new("writer");
+//! let critic = Agent::new("critic").map(|review: String| {
+//!     if review.starts_with("LGTM") {
+//!         Verdict::Accept
+//!     } else {
+//!         Verdict::Revise(review)
+//!     }
+//! });
+//! let reviewed = critic_loop(writer, critic, 3)
+//!     .run("Write a haiku about borrow checking.".to_string())
+//!     .await?;
+//! println!("{} (after {} rounds)", reviewed.draft, reviewed.rounds);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+use std::future::Future;
+use std::marker::PhantomData;
+use std::sync::Arc;
+
+use futures::future::{try_join_all, BoxFuture};
+use serde::de::DeserializeOwned;
+
+use crate::client::DynTransport;
+use crate::config::{AgentDefinition, ClaudeAgentOptions, SystemPrompt};
+use crate::error::SdkError;
+use crate::query::ask_with;
+
+/// One step of a pipeline.
+pub trait Stage<I, O>: Send + Sync {
+    fn run(&self, input: I) -> BoxFuture<'_, Result<O, SdkError>>;
+}
+
+impl<I, O, S> Stage<I, O> for Arc<S>
+where
+    S: Stage<I, O> + ?Sized,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<O, SdkError>> {
+        (**self).run(input)
+    }
+}
+
+/// Shared, type-erased stage.
+pub type StageHandle<I, O> = Arc<dyn Stage<I, O>>;
+
+/// Creates the transport for each session an [`Agent`] starts, given the
+/// session's prompt.
+pub type TransportFactory = Arc<dyn Fn(&str) -> DynTransport + Send + Sync>;
+
+/// A stage that asks Claude: the input is the prompt, the output the answer.
+///
+/// Each run is a separate one-shot session started from the agent's
+/// options, so stages share no conversation history; pass whatever context
+/// the next agent needs through the prompt.
+#[derive(Clone)]
+pub struct Agent {
+    name: String,
+    options: ClaudeAgentOptions,
+    transport: Option<TransportFactory>,
+}
+
+impl Agent {
+    /// Agent using default options.
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            name: name.into(),
+            options: ClaudeAgentOptions::default(),
+            transport: None,
+        }
+    }
+
+    /// Agent running with the prompt, tools and model of an
+    /// [`AgentDefinition`], as configured for `ClaudeAgentOptions::agents`.
+    pub fn from_definition(name: impl Into<String>, definition: &AgentDefinition) -> Self {
+        let mut agent = Self::new(name);
+        agent.options.system_prompt = Some(SystemPrompt::Text(definition.prompt.clone()));
+        if let Some(tools) = &definition.tools {
+            agent.options.allowed_tools = tools.clone();
+        }
+        agent.options.model = definition.model.clone();
+        agent
+    }
+
+    /// Replace the options every session starts with.
+    pub fn with_options(mut self, options: ClaudeAgentOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// Run sessions over transports from `factory` instead of spawning the CLI.
+    ///
+    /// A custom transport never sees a one-shot prompt, which the CLI takes
+    /// as an argument, so the factory is handed the prompt instead.
+    pub fn with_transport_factory(
+        mut self,
+        factory: impl Fn(&str) -> DynTransport + Send + Sync + 'static,
+    ) -> Self {
+        self.transport = Some(Arc::new(factory));
+        self
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn options(&self) -> &ClaudeAgentOptions {
+        &self.options
+    }
+}
+
+impl fmt::Debug for Agent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Agent")
+            .field("name", &self.name)
+            .field("options", &self.options)
+            .field("has_transport_factory", &self.transport.is_some())
+            .finish()
+    }
+}
+
+impl Stage<String, String> for Agent {
+    fn run(&self, input: String) -> BoxFuture<'_, Result<String, SdkError>> {
+        let transport = self.transport.as_ref().map(|factory| factory(&input));
+        Box::pin(ask_with(input, Some(self.options.clone()), transport))
+    }
+}
+
+/// Stage running an async function.
+pub struct FnStage<F, I> {
+    function: F,
+    _input: PhantomData<fn(I)>,
+}
+
+/// Wrap an async function as a stage.
+pub fn stage_fn<F, Fut, I, O>(function: F) -> FnStage<F, I>
+where
+    F: Fn(I) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<O, SdkError>> + Send + 'static,
+{
+    FnStage {
+        function,
+        _input: PhantomData,
+    }
+}
+
+impl<F, Fut, I, O> Stage<I, O> for FnStage<F, I>
+where
+    F: Fn(I) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<O, SdkError>> + Send + 'static,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<O, SdkError>> {
+        Box::pin((self.function)(input))
+    }
+}
+
+/// Two stages run one after the other; see [`StageExt::then`].
+pub struct Then<A, B, M> {
+    first: A,
+    second: B,
+    _middle: PhantomData<fn(M)>,
+}
+
+impl<I, M, O, A, B> Stage<I, O> for Then<A, B, M>
+where
+    I: Send + 'static,
+    M: Send + 'static,
+    A: Stage<I, M>,
+    B: Stage<M, O>,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<O, SdkError>> {
+        Box::pin(async move {
+            let middle = self.first.run(input).await?;
+            self.second.run(middle).await
+        })
+    }
+}
+
+/// A stage with its output converted; see [`StageExt::map`].
+pub struct Map<S, F, M> {
+    stage: S,
+    function: F,
+    _middle: PhantomData<fn(M)>,
+}
+
+impl<I, M, O, S, F> Stage<I, O> for Map<S, F, M>
+where
+    I: Send + 'static,
+    M: Send + 'static,
+    S: Stage<I, M>,
+    F: Fn(M) -> O + Send + Sync,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<O, SdkError>> {
+        Box::pin(async move { self.stage.run(input).await.map(&self.function) })
+    }
+}
+
+/// Combinators available on every stage.
+pub trait StageExt<I, O>: Stage<I, O> + Sized {
+    /// Feed this stage's output into `next`.
+    fn then<P, N>(self, next: N) -> Then<Self, N, O>
+    where
+        N: Stage<O, P>,
+    {
+        Then {
+            first: self,
+            second: next,
+            _middle: PhantomData,
+        }
+    }
+
+    /// Convert the output with `function`.
+    fn map<P, F>(self, function: F) -> Map<Self, F, O>
+    where
+        F: Fn(O) -> P + Send + Sync,
+    {
+        Map {
+            stage: self,
+            function,
+            _middle: PhantomData,
+        }
+    }
+
+    /// Deserialize a text output as JSON into `T`.
+    ///
+    /// The first fenced code block is used when the text has one, so answers
+    /// like "Here you go: ```json {...} ```" parse as well.
+    fn parse_json<T>(self) -> ParseJson<Self, O, T>
+    where
+        O: AsRef<str>,
+        T: DeserializeOwned,
+    {
+        ParseJson {
+            stage: self,
+            _types: PhantomData,
+        }
+    }
+
+    /// Share this stage behind an [`Arc`].
+    fn boxed(self) -> StageHandle<I, O>
+    where
+        Self: 'static,
+    {
+        Arc::new(self)
+    }
+}
+
+impl<I, O, S: Stage<I, O>> StageExt<I, O> for S {}
+
+/// A stage whose text output is deserialized; see [`StageExt::parse_json`].
+pub struct ParseJson<S, M, T> {
+    stage: S,
+    _types: PhantomData<fn(M) -> T>,
+}
+
+impl<I, M, T, S> Stage<I, T> for ParseJson<S, M, T>
+where
+    I: Send + 'static,
+    M: AsRef<str> + Send + 'static,
+    T: DeserializeOwned + Send + 'static,
+    S: Stage<I, M>,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<T, SdkError>> {
+        Box::pin(async move {
+            let output = self.stage.run(input).await?;
+            let text = output.as_ref();
+            let body = fenced_block(text).unwrap_or(text).trim();
+            serde_json::from_str(body).map_err(|err| {
+                SdkError::Message(format!("stage output is not the expected JSON: {err}"))
+            })
+        })
+    }
+}
+
+fn fenced_block(text: &str) -> Option<&str> {
+    let start = text.find("```")? + 3;
+    let rest = &text[start..];
+    // Skip the info string, e.g. "json".
+    let body_start = rest.find('\n')? + 1;
+    let end = rest[body_start..].find("```")?;
+    Some(&rest[body_start..body_start + end])
+}
+
+/// Stages run concurrently on one input; see [`fan_out`].
+pub struct FanOut<I, O> {
+    stages: Vec<StageHandle<I, O>>,
+}
+
+/// Run every stage on a clone of the input at the same time; the output
+/// lists their results in order. The first failure fails the whole stage.
+pub fn fan_out<I, O>(stages: Vec<StageHandle<I, O>>) -> FanOut<I, O> {
+    FanOut { stages }
+}
+
+impl<I, O> Stage<I, Vec<O>> for FanOut<I, O>
+where
+    I: Clone + Send + 'static,
+    O: Send + 'static,
+{
+    fn run(&self, input: I) -> BoxFuture<'_, Result<Vec<O>, SdkError>> {
+        let runs: Vec<_> = self
+            .stages
+            .iter()
+            .map(|stage| stage.run(input.clone()))
+            .collect();
+        Box::pin(try_join_all(runs))
+    }
+}
+
+/// A critic's judgement of a draft.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    /// Revise the draft; the text is the feedback for the author.
+    Revise(String),
+}
+
+/// Outcome of a [`critic_loop`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reviewed {
+    pub draft: String,
+    /// Drafts written, including the first.
+    pub rounds: usize,
+    /// `false` when the round limit was reached without the critic accepting.
+    pub accepted: bool,
+    /// Feedback from every rejected round, oldest first.
+    pub feedback: Vec<String>,
+}
+
+/// Prompt asking the author to revise `draft` given the original `task` and
+/// the critic's `feedback`.
+pub type RevisionPrompt = Arc<dyn Fn(&str, &str, &str) -> String + Send + Sync>;
+
+/// Author/critic loop; see [`critic_loop`].
+pub struct CriticLoop<A, C> {
+    author: A,
+    critic: C,
+    max_rounds: usize,
+    revision_prompt: RevisionPrompt,
+}
+
+/// Have `author` draft an answer to the input and `critic` judge it,
+/// revising until the critic accepts or `max_rounds` drafts were written.
+pub fn critic_loop<A, C>(author: A, critic: C, max_rounds: usize) -> CriticLoop<A, C>
+where
+    A: Stage<String, String>,
+    C: Stage<String, Verdict>,
+{
+    CriticLoop {
+        author,
+        critic,
+        max_rounds: max_rounds.max(1),
+        revision_prompt: Arc::new(default_revision_prompt),
+    }
+}
+
+impl<A, C> CriticLoop<A, C> {
+    /// Replace the prompt used for revision rounds.
+    pub fn with_revision_prompt(
+        mut self,
+        prompt: impl Fn(&str, &str, &str) -> String + Send + Sync + 'static,
+    ) -> Self {
+        self.revision_prompt = Arc::new(prompt);
+        self
+    }
+}
+
+/// Revision prompt [`critic_loop`] uses unless one is configured.
+pub fn default_revision_prompt(task: &str, draft: &str, feedback: &str) -> String {
+    format!(
+        "{task}\n\nYour previous answer was:\n\n{draft}\n\nA reviewer asked for these changes:\n\n{feedback}\n\nReply with the revised answer only."
+    )
+}
+
+impl<A, C> Stage<String, Reviewed> for CriticLoop<A, C>
+where
+    A: Stage<String, String>,
+    C: Stage<String, Verdict>,
+{
+    fn run(&self, task: String) -> BoxFuture<'_, Result<Reviewed, SdkError>> {
+        Box::pin(async move {
+            let mut draft = self.author.run(task.clone()).await?;
+            let mut feedback = Vec::new();
+            for round in 1..=self.max_rounds {
+                match self.critic.run(draft.clone()).await? {
+                    Verdict::Accept => {
+                        return Ok(Reviewed {
+                            draft,
+                            rounds: round,
+                            accepted: true,
+                            feedback,
+                        })
+                    }
+                    Verdict::Revise(notes) if round < self.max_rounds => {
+                        let prompt = (self.revision_prompt)(&task, &draft, &notes);
+                        feedback.push(notes);
+                        draft = self.author.run(prompt).await?;
+                    }
+                    Verdict::Revise(notes) => feedback.push(notes),
+                }
+            }
+            Ok(Reviewed {
+                draft,
+                rounds: self.max_rounds,
+                accepted: false,
+                feedback,
+            })
+        })
+    }
+}
@@ -0,0 +1,271 @@
+//! OpenTelemetry export of SDK activity (requires the `otel` feature).
+//!
+//! [`OtelMetrics`] is an [`SdkMetrics`] implementation that records through the
+//! global OpenTelemetry tracer and meter providers, using the GenAI semantic
+//! conventions where they apply. The SDK only depends on the OpenTelemetry API:
+//! the application installs the providers and exporters, which pick up the
+//! standard `OTEL_*` environment variables (`OTEL_EXPORTER_OTLP_ENDPOINT`,
+//! `OTEL_SERVICE_NAME`, ...).
+//!
+//! ```no_run
+//! # use sdk_claude_rust::config::ClaudeAgentOptions;
+//! # use sdk_claude_rust::otel::OtelMetrics;
+//! let options = ClaudeAgentOptions {
+//!     metrics: OtelMetrics::from_env(),
+//!     ..Default::default()
+//! };
+//! ```
+
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use opentelemetry::global::{self, BoxedTracer};
+use opentelemetry::metrics::{Counter, Histogram};
+use opentelemetry::trace::{Span, SpanKind, Status, Tracer};
+use opentelemetry::KeyValue;
+use serde_json::Value;
+
+use crate::frame_log::FrameDirection;
+use crate::message::ResultMessage;
+use crate::metrics::{SdkMetrics, SdkMetricsHandle};
+
+/// Instrumentation scope name used for the tracer and meter.
+pub const INSTRUMENTATION_SCOPE: &str = "sdk-claude-rust";
+
+const PROVIDER_NAME: &str = "anthropic";
+
+/// Bucket boundaries recommended by the GenAI conventions for
+/// `gen_ai.client.operation.duration`, in seconds.
+const DURATION_BOUNDARIES: [f64; 14] = [
+    0.01, 0.02, 0.04, 0.08, 0.16, 0.32, 0.64, 1.28, 2.56, 5.12, 10.24, 20.48, 40.96, 81.92,
+];
+
+/// Bucket boundaries recommended for `gen_ai.client.token.usage`.
+const TOKEN_BOUNDARIES: [f64; 14] = [
+    1.0, 4.0, 16.0, 64.0, 256.0, 1024.0, 4096.0, 16384.0, 65536.0, 262144.0, 1048576.0, 4194304.0,
+    16777216.0, 67108864.0,
+];
+
+/// [`SdkMetrics`] implementation exporting spans and metrics via OpenTelemetry.
+///
+/// Completed queries become `invoke_agent` spans and in-process MCP tool calls
+/// become `execute_tool` spans; both are back-dated to their measured start.
+pub struct OtelMetrics {
+    tracer: BoxedTracer,
+    operation_duration: Histogram<f64>,
+    token_usage: Histogram<u64>,
+    cost: Counter<f64>,
+    control_duration: Histogram<f64>,
+    messages: Counter<u64>,
+    parse_failures: Counter<u64>,
+    process_restarts: Counter<u64>,
+}
+
+impl OtelMetrics {
+    /// Create instruments on the global tracer and meter providers.
+    pub fn new() -> Self {
+        let meter = global::meter(INSTRUMENTATION_SCOPE);
+        Self {
+            tracer: global::tracer(INSTRUMENTATION_SCOPE),
+            operation_duration: meter
+                .f64_histogram("gen_ai.client.operation.duration")
+                .with_unit("s")
+                .with_description("GenAI operation duration")
+                .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                .build(),
+            token_usage: meter
+                .u64_histogram("gen_ai.client.token.usage")
+                .with_unit("{token}")
+                .with_description("Number of input and output tokens used")
+                .with_boundaries(TOKEN_BOUNDARIES.to_vec())
+                .build(),
+            cost: meter
+                .f64_counter("claude_sdk.cost")
+                .with_unit("USD")
+                .with_description("Cost reported by the CLI for completed queries")
+                .build(),
+            control_duration: meter
+                .f64_histogram("claude_sdk.control_request.duration")
+                .with_unit("s")
+                .with_description("Control protocol round-trip duration")
+                .with_boundaries(DURATION_BOUNDARIES.to_vec())
+                .build(),
+            messages: meter
+                .u64_counter("claude_sdk.messages")
+                .with_description("Messages exchanged with the CLI")
+                .build(),
+            parse_failures: meter
+                .u64_counter("claude_sdk.parse_failures")
+                .with_description("CLI output lines that could not be parsed")
+                .build(),
+            process_restarts: meter
+                .u64_counter("claude_sdk.process_restarts")
+                .with_description("CLI processes started again for an existing client")
+                .build(),
+        }
+    }
+
+    /// Handle for [`ClaudeAgentOptions::metrics`](crate::config::ClaudeAgentOptions::metrics),
+    /// or `None` when `OTEL_SDK_DISABLED` is set to `true`.
+    pub fn from_env() -> Option<SdkMetricsHandle> {
+        let disabled = std::env::var("OTEL_SDK_DISABLED")
+            .map(|value| value.trim().eq_ignore_ascii_case("true"))
+            .unwrap_or(false);
+        (!disabled).then(|| Arc::new(Self::new()) as SdkMetricsHandle)
+    }
+
+    fn record_span(
+        &self,
+        name: String,
+        latency: Duration,
+        attributes: Vec<KeyValue>,
+        error: Option<String>,
+    ) {
+        let end = SystemTime::now();
+        let mut span = self
+            .tracer
+            .span_builder(name)
+            .with_kind(SpanKind::Client)
+            .with_start_time(end.checked_sub(latency).unwrap_or(end))
+            .with_attributes(attributes)
+            .start(&self.tracer);
+        if let Some(description) = error {
+            span.set_status(Status::error(description));
+        }
+        span.end_with_timestamp(end);
+    }
+}
+
+impl Default for OtelMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl fmt::Debug for OtelMetrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("OtelMetrics").finish_non_exhaustive()
+    }
+}
+
+impl SdkMetrics for OtelMetrics {
+    fn message_received(&self, kind: &str) {
+        self.messages.add(
+            1,
+            &[
+                KeyValue::new("claude_sdk.direction", "inbound"),
+                KeyValue::new("claude_sdk.message.type", kind.to_string()),
+            ],
+        );
+    }
+
+    fn message_sent(&self) {
+        self.messages
+            .add(1, &[KeyValue::new("claude_sdk.direction", "outbound")]);
+    }
+
+    fn control_request(
+        &self,
+        subtype: &str,
+        direction: FrameDirection,
+        latency: Duration,
+        success: bool,
+    ) {
+        let direction = match direction {
+            FrameDirection::Inbound => "inbound",
+            FrameDirection::Outbound => "outbound",
+        };
+        self.control_duration.record(
+            latency.as_secs_f64(),
+            &[
+                KeyValue::new("claude_sdk.control.subtype", subtype.to_string()),
+                KeyValue::new("claude_sdk.direction", direction),
+                KeyValue::new("claude_sdk.success", success),
+            ],
+        );
+    }
+
+    fn tool_call(&self, server: &str, tool: &str, latency: Duration, success: bool) {
+        let mut attributes = vec![
+            KeyValue::new("gen_ai.operation.name", "execute_tool"),
+            KeyValue::new("gen_ai.provider.name", PROVIDER_NAME),
+            KeyValue::new("gen_ai.tool.name", tool.to_string()),
+            KeyValue::new("claude_sdk.mcp.server", server.to_string()),
+        ];
+        if !success {
+            attributes.push(KeyValue::new("error.type", "tool_error"));
+        }
+        self.operation_duration
+            .record(latency.as_secs_f64(), &attributes);
+        self.record_span(
+            format!("execute_tool {tool}"),
+            latency,
+            attributes,
+            (!success).then(|| format!("tool {server}/{tool} failed")),
+        );
+    }
+
+    fn parse_failure(&self) {
+        self.parse_failures.add(1, &[]);
+    }
+
+    fn process_restart(&self) {
+        self.process_restarts.add(1, &[]);
+    }
+
+    fn query_completed(&self, result: &ResultMessage) {
+        let mut attributes = vec![
+            KeyValue::new("gen_ai.operation.name", "invoke_agent"),
+            KeyValue::new("gen_ai.provider.name", PROVIDER_NAME),
+        ];
+        if result.is_error {
+            attributes.push(KeyValue::new("error.type", result.subtype.clone()));
+        }
+        let latency = Duration::from_millis(u64::try_from(result.duration_ms).unwrap_or(0));
+        self.operation_duration
+            .record(latency.as_secs_f64(), &attributes);
+
+        let tokens = |key: &str| {
+            result
+                .usage
+                .as_ref()
+                .and_then(|usage| usage.get(key))
+                .and_then(Value::as_u64)
+        };
+        let input_tokens = tokens("input_tokens");
+        let output_tokens = tokens("output_tokens");
+        for (token_type, count) in [("input", input_tokens), ("output", output_tokens)] {
+            if let Some(count) = count {
+                let mut token_attributes = attributes.clone();
+                token_attributes.push(KeyValue::new("gen_ai.token.type", token_type));
+                self.token_usage.record(count, &token_attributes);
+            }
+        }
+        if let Some(cost) = result.total_cost_usd {
+            self.cost.add(cost, &attributes);
+        }
+
+        let mut span_attributes = attributes;
+        span_attributes.push(KeyValue::new(
+            "gen_ai.conversation.id",
+            result.session_id.clone(),
+        ));
+        span_attributes.push(KeyValue::new("claude_sdk.num_turns", result.num_turns));
+        if let Some(count) = input_tokens {
+            span_attributes.push(KeyValue::new("gen_ai.usage.input_tokens", count as i64));
+        }
+        if let Some(count) = output_tokens {
+            span_attributes.push(KeyValue::new("gen_ai.usage.output_tokens", count as i64));
+        }
+        if let Some(cost) = result.total_cost_usd {
+            span_attributes.push(KeyValue::new("claude_sdk.cost_usd", cost));
+        }
+        self.record_span(
+            "invoke_agent claude-code".to_string(),
+            latency,
+            span_attributes,
+            result.is_error.then(|| result.subtype.clone()),
+        );
+    }
+}
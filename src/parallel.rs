@@ -0,0 +1,183 @@
+//! Running several prompts in separate sessions at once.
+//!
+//! [`ClaudeSdkClient::parallel`] starts one one-shot session per
+//! [`ParallelTask`] and [`QueryPool::parallel`] dispatches the prompts to the
+//! pool's workers, so at most the pool size run at a time. Both wait for every
+//! task and return one [`TaskOutcome`] per task, in the order given; a failed
+//! task does not cancel the others.
+//!
+//! [`ClaudeSdkClient::parallel`]: crate::client::ClaudeSdkClient::parallel
+//! [`QueryPool::parallel`]: crate::pool::QueryPool::parallel
+
+use futures::StreamExt;
+
+use crate::client::DynTransport;
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, ResultMessage};
+use crate::query::query;
+
+/// One prompt for a parallel run.
+#[derive(Clone)]
+pub struct ParallelTask {
+    pub prompt: String,
+    /// Options replacing the client's for this task.
+    pub options: Option<ClaudeAgentOptions>,
+    transport: Option<DynTransport>,
+}
+
+impl std::fmt::Debug for ParallelTask {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ParallelTask")
+            .field("prompt", &self.prompt)
+            .field("options", &self.options)
+            .field("has_transport", &self.transport.is_some())
+            .finish()
+    }
+}
+
+impl ParallelTask {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            options: None,
+            transport: None,
+        }
+    }
+
+    pub fn with_options(mut self, options: ClaudeAgentOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+
+    /// Run this task over `transport` instead of spawning the CLI.
+    pub fn with_transport(mut self, transport: DynTransport) -> Self {
+        self.transport = Some(transport);
+        self
+    }
+}
+
+impl From<&str> for ParallelTask {
+    fn from(prompt: &str) -> Self {
+        Self::new(prompt)
+    }
+}
+
+impl From<String> for ParallelTask {
+    fn from(prompt: String) -> Self {
+        Self::new(prompt)
+    }
+}
+
+/// How one task of a parallel run ended.
+#[derive(Debug)]
+pub enum TaskOutcome {
+    /// The session ran to its result message.
+    Completed(Vec<Message>),
+    /// The session failed; `messages` holds what arrived before the error.
+    Failed {
+        messages: Vec<Message>,
+        error: SdkError,
+    },
+}
+
+impl TaskOutcome {
+    pub(crate) fn from_run(messages: Vec<Message>, error: Option<SdkError>) -> Self {
+        match error {
+            Some(error) => Self::Failed { messages, error },
+            None => Self::Completed(messages),
+        }
+    }
+
+    pub fn is_completed(&self) -> bool {
+        matches!(self, Self::Completed(_))
+    }
+
+    pub fn messages(&self) -> &[Message] {
+        match self {
+            Self::Completed(messages) | Self::Failed { messages, .. } => messages,
+        }
+    }
+
+    pub fn error(&self) -> Option<&SdkError> {
+        match self {
+            Self::Completed(_) => None,
+            Self::Failed { error, .. } => Some(error),
+        }
+    }
+
+    pub fn result(&self) -> Option<&ResultMessage> {
+        self.messages()
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Result(result) => Some(result),
+                _ => None,
+            })
+    }
+
+    /// Text of the result, or of the last assistant message without one.
+    pub fn text(&self) -> String {
+        if let Some(text) = self.result().and_then(|result| result.result.clone()) {
+            return text;
+        }
+        self.messages()
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Assistant(assistant) => {
+                    let text: String = assistant
+                        .content
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text(text) => Some(text.text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    (!text.is_empty()).then_some(text)
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    /// `Ok` with the messages of a completed task.
+    pub fn into_result(self) -> Result<Vec<Message>, SdkError> {
+        match self {
+            Self::Completed(messages) => Ok(messages),
+            Self::Failed { error, .. } => Err(error),
+        }
+    }
+}
+
+/// Run every task in its own one-shot session, `options` unless overridden.
+pub(crate) async fn run_tasks(
+    options: &ClaudeAgentOptions,
+    tasks: Vec<ParallelTask>,
+) -> Vec<TaskOutcome> {
+    let runs = tasks.into_iter().map(|task| {
+        let options = task.options.unwrap_or_else(|| options.clone());
+        run_task(task.prompt, options, task.transport)
+    });
+    futures::future::join_all(runs).await
+}
+
+async fn run_task(
+    prompt: String,
+    options: ClaudeAgentOptions,
+    transport: Option<DynTransport>,
+) -> TaskOutcome {
+    let stream = match query(prompt, Some(options), transport).await {
+        Ok(stream) => stream,
+        Err(error) => return TaskOutcome::from_run(Vec::new(), Some(error)),
+    };
+    futures::pin_mut!(stream);
+    let mut messages = Vec::new();
+    while let Some(message) = stream.next().await {
+        match message {
+            Ok(message) => messages.push(message),
+            Err(error) => return TaskOutcome::from_run(messages, Some(error)),
+        }
+    }
+    TaskOutcome::from_run(messages, None)
+}
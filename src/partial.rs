@@ -0,0 +1,185 @@
+//! Assembling partial messages from `stream_event`s.
+//!
+//! With `include_partial_messages` the CLI streams each API response as
+//! `message_start`, `content_block_start`/`_delta`/`_stop` and `message_stop`
+//! events. A [`PartialAssembler`] rebuilds the text of every content block
+//! from them and checks the sequence: an event whose `uuid` was already
+//! seen, as happens when a reconnecting consumer replays part of the stream,
+//! is reported as [`StreamDiagnostic::Duplicate`] and ignored, and an event
+//! for a block whose start, or an earlier block's, never arrived is
+//! reported as [`StreamDiagnostic::Gap`]. Events of subagents, which carry a
+//! `parent_tool_use_id`, are assembled apart from the main agent's.
+//!
+//! [`MessageStreamExt::check_stream_events`](crate::stream_ext::MessageStreamExt::check_stream_events)
+//! runs an assembler over a message stream.
+
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+
+use serde_json::Value;
+
+use crate::message::{Message, StreamEvent};
+
+/// Event uuids remembered to detect duplicates.
+pub const SEEN_UUID_CAPACITY: usize = 4096;
+
+/// Problem found in the sequence of stream events.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StreamDiagnostic {
+    /// The event repeats one already assembled and was ignored.
+    Duplicate {
+        uuid: String,
+        parent_tool_use_id: Option<String>,
+    },
+    /// Blocks from `expected` up to `index` never started, so their content
+    /// is missing; `index` itself is included unless the event starts it.
+    Gap {
+        uuid: String,
+        parent_tool_use_id: Option<String>,
+        expected: u64,
+        index: u64,
+    },
+}
+
+/// Content block rebuilt from deltas.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PartialBlock {
+    /// `text`, `thinking`, `tool_use`, ...; inferred from the deltas when
+    /// the block's start was missed, empty if that is not possible.
+    pub kind: String,
+    /// Concatenated text, thinking or partial JSON of the deltas.
+    pub content: String,
+    pub complete: bool,
+}
+
+#[derive(Debug, Default)]
+struct PartialMessage {
+    blocks: BTreeMap<u64, PartialBlock>,
+    next_index: u64,
+}
+
+/// Rebuilds partial messages, reporting duplicated and missing events.
+#[derive(Debug, Default)]
+pub struct PartialAssembler {
+    seen: HashSet<String>,
+    seen_order: VecDeque<String>,
+    messages: HashMap<Option<String>, PartialMessage>,
+}
+
+impl PartialAssembler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Assemble `message` if it is a stream event.
+    pub fn observe(&mut self, message: &Message) -> Option<StreamDiagnostic> {
+        match message {
+            Message::StreamEvent(event) => self.push(event),
+            _ => None,
+        }
+    }
+
+    /// Assemble `event`, returning what was wrong with it, if anything.
+    pub fn push(&mut self, event: &StreamEvent) -> Option<StreamDiagnostic> {
+        let parent = event.parent_tool_use_id.clone();
+        if !self.remember(&event.uuid) {
+            return Some(StreamDiagnostic::Duplicate {
+                uuid: event.uuid.clone(),
+                parent_tool_use_id: parent,
+            });
+        }
+
+        let kind = event.event.get("type").and_then(Value::as_str);
+        if kind == Some("message_start") {
+            self.messages.insert(parent, PartialMessage::default());
+            return None;
+        }
+        let index = event.event.get("index").and_then(Value::as_u64)?;
+        let starts = kind == Some("content_block_start");
+        let message = self.messages.entry(parent.clone()).or_default();
+        if starts && index < message.next_index {
+            // A new message whose `message_start` was missed.
+            *message = PartialMessage::default();
+        }
+
+        let missing = if starts {
+            index > message.next_index
+        } else {
+            index >= message.next_index
+        };
+        let diagnostic = missing.then(|| StreamDiagnostic::Gap {
+            uuid: event.uuid.clone(),
+            parent_tool_use_id: parent,
+            expected: message.next_index,
+            index,
+        });
+        message.next_index = message.next_index.max(index + 1);
+        let block = message.blocks.entry(index).or_default();
+        match kind {
+            Some("content_block_start") => {
+                block.kind = event
+                    .event
+                    .pointer("/content_block/type")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string();
+            }
+            Some("content_block_delta") => {
+                if let Some((block_kind, text)) = event.event.get("delta").and_then(delta_text) {
+                    if block.kind.is_empty() {
+                        block.kind = block_kind.to_string();
+                    }
+                    block.content.push_str(text);
+                }
+            }
+            Some("content_block_stop") => block.complete = true,
+            _ => {}
+        }
+        diagnostic
+    }
+
+    /// Blocks assembled so far for the main agent (`None`) or a subagent,
+    /// by index.
+    pub fn blocks(&self, parent_tool_use_id: Option<&str>) -> Option<&BTreeMap<u64, PartialBlock>> {
+        self.messages
+            .get(&parent_tool_use_id.map(str::to_string))
+            .map(|message| &message.blocks)
+    }
+
+    /// Text of the current message's `text` blocks, in order.
+    pub fn text(&self, parent_tool_use_id: Option<&str>) -> String {
+        self.blocks(parent_tool_use_id)
+            .into_iter()
+            .flat_map(BTreeMap::values)
+            .filter(|block| block.kind == "text")
+            .map(|block| block.content.as_str())
+            .collect()
+    }
+
+    /// Whether `uuid` is new, remembering it.
+    fn remember(&mut self, uuid: &str) -> bool {
+        if uuid.is_empty() {
+            return true;
+        }
+        if !self.seen.insert(uuid.to_string()) {
+            return false;
+        }
+        self.seen_order.push_back(uuid.to_string());
+        if self.seen_order.len() > SEEN_UUID_CAPACITY {
+            if let Some(oldest) = self.seen_order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        true
+    }
+}
+
+/// Kind of block a delta belongs to, and its content.
+fn delta_text(delta: &Value) -> Option<(&'static str, &str)> {
+    let (kind, field) = match delta.get("type").and_then(Value::as_str)? {
+        "text_delta" => ("text", "text"),
+        "thinking_delta" => ("thinking", "thinking"),
+        "input_json_delta" => ("tool_use", "partial_json"),
+        _ => return None,
+    };
+    Some((kind, delta.get(field)?.as_str()?))
+}
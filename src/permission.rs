@@ -5,7 +5,10 @@ use std::sync::Arc;
 
 use futures::Future;
 use serde::{Deserialize, Serialize};
-use serde_json::{Map, Value};
+use serde_json::{json, Map, Value};
+
+use crate::error::SdkError;
+use crate::mcp::{create_sdk_mcp_server, tool, McpToolCallResult, McpToolContent, SdkMcpServer};
 
 /// Permission mode requested from the CLI.
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
@@ -167,8 +170,8 @@ impl PermissionUpdate {
     }
 
     /// Convert the update into the control protocol JSON payload expected by the CLI.
-    pub fn to_control_payload(&self) -> Value {
-        serde_json::to_value(self).expect("PermissionUpdate should always serialize")
+    pub fn to_control_payload(&self) -> Result<Value, SdkError> {
+        Ok(serde_json::to_value(self)?)
     }
 }
 
@@ -286,3 +289,67 @@ where
 
 /// Convenient handle for storing permission callbacks.
 pub type CanUseToolHandle = Arc<dyn CanUseToolCallback>;
+
+/// Name of the SDK MCP server registered by
+/// [`ClaudeAgentOptions::set_permission_prompt_tool`](crate::config::ClaudeAgentOptions::set_permission_prompt_tool).
+pub const PERMISSION_PROMPT_SERVER: &str = "sdk_permissions";
+/// Name of its approval tool.
+pub const PERMISSION_PROMPT_TOOL: &str = "approve";
+
+/// SDK MCP server implementing the `--permission-prompt-tool` contract with
+/// `callback`.
+///
+/// The CLI calls the tool with `tool_name` and `input`; the callback's
+/// decision is returned as the JSON text the CLI expects. An allow without
+/// `updatedInput` echoes the original input.
+pub fn permission_prompt_server(callback: CanUseToolHandle) -> Arc<dyn SdkMcpServer> {
+    let schema = json!({
+        "type": "object",
+        "properties": {
+            "tool_name": {"type": "string"},
+            "input": {"type": "object"},
+            "tool_use_id": {"type": "string"}
+        },
+        "required": ["tool_name", "input"]
+    });
+    let approve = tool(
+        PERMISSION_PROMPT_TOOL,
+        "Decide whether Claude may run a tool",
+        schema,
+        move |arguments: Map<String, Value>| {
+            let callback = Arc::clone(&callback);
+            async move {
+                let tool_name = arguments
+                    .get("tool_name")
+                    .and_then(Value::as_str)
+                    .ok_or_else(|| SdkError::Message("permission prompt without tool_name".into()))?
+                    .to_string();
+                let input = match arguments.get("input") {
+                    Some(Value::Object(input)) => input.clone(),
+                    _ => Map::new(),
+                };
+                let decision = callback
+                    .call(&tool_name, input.clone(), ToolPermissionContext::default())
+                    .await;
+                let decision = match decision {
+                    PermissionResult::Allow {
+                        updated_input,
+                        updated_permissions,
+                    } => PermissionResult::Allow {
+                        updated_input: Some(updated_input.unwrap_or(input)),
+                        updated_permissions,
+                    },
+                    deny => deny,
+                };
+                Ok(McpToolCallResult::new(vec![McpToolContent::text(
+                    serde_json::to_string(&decision)?,
+                )]))
+            }
+        },
+    );
+    create_sdk_mcp_server(
+        PERMISSION_PROMPT_SERVER,
+        env!("CARGO_PKG_VERSION"),
+        vec![approve],
+    )
+}
@@ -0,0 +1,233 @@
+//! Warmed CLI sessions for one-shot prompts.
+//!
+//! Starting the CLI dominates the latency of a short [`query`]. A
+//! [`QueryPool`] keeps up to `size` connected [`ClaudeSdkClient`]s idle and
+//! hands each prompt to one of them, starting a replacement in the background
+//! once a worker is retired.
+//!
+//! A worker is retired after [`QueryPool::with_max_queries_per_worker`]
+//! prompts (one by default, so every prompt starts from an empty
+//! conversation), after a failed prompt, or when it fails its health check
+//! ([`ClaudeSdkClient::is_healthy`]) while idle. Workers serving more than one
+//! prompt carry the earlier prompts in their context.
+//!
+//! [`query`]: crate::query::query
+
+use std::sync::Arc;
+
+use futures::StreamExt;
+use tokio::sync::{Mutex, OwnedSemaphorePermit, Semaphore};
+
+use crate::client::{ClaudeSdkClient, DynTransport};
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::internal::trace::sdk_debug;
+use crate::message::Message;
+use crate::parallel::TaskOutcome;
+
+type TransportFactory = Arc<dyn Fn() -> DynTransport + Send + Sync>;
+
+struct Worker {
+    client: ClaudeSdkClient,
+    served: u32,
+}
+
+struct PoolState {
+    idle: Mutex<Vec<Worker>>,
+    /// One permit per worker serving a prompt or being replaced.
+    busy: Arc<Semaphore>,
+}
+
+/// Pool of connected CLI sessions serving one-shot prompts.
+///
+/// Clones share the same workers.
+#[derive(Clone)]
+pub struct QueryPool {
+    options: ClaudeAgentOptions,
+    transport: Option<TransportFactory>,
+    size: usize,
+    max_queries: u32,
+    state: Arc<PoolState>,
+}
+
+impl std::fmt::Debug for QueryPool {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryPool")
+            .field("options", &self.options)
+            .field("size", &self.size)
+            .field("max_queries", &self.max_queries)
+            .field("has_transport", &self.transport.is_some())
+            .finish()
+    }
+}
+
+impl QueryPool {
+    /// Pool of at most `size` workers using `options`.
+    pub fn new(options: ClaudeAgentOptions, size: usize) -> Self {
+        let size = size.max(1);
+        Self {
+            options,
+            transport: None,
+            size,
+            max_queries: 1,
+            state: Arc::new(PoolState {
+                idle: Mutex::new(Vec::new()),
+                busy: Arc::new(Semaphore::new(size)),
+            }),
+        }
+    }
+
+    /// Prompts a worker serves before it is replaced; at least one.
+    pub fn with_max_queries_per_worker(mut self, max_queries: u32) -> Self {
+        self.max_queries = max_queries.max(1);
+        self
+    }
+
+    /// Build a fresh transport for every worker instead of spawning the CLI.
+    pub fn with_transport<F>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> DynTransport + Send + Sync + 'static,
+    {
+        self.transport = Some(Arc::new(factory));
+        self
+    }
+
+    /// Connect workers until the pool holds `size` of them.
+    pub async fn warm(&self) -> Result<(), SdkError> {
+        loop {
+            let busy = self.size - self.state.busy.available_permits();
+            if self.idle_count().await + busy >= self.size {
+                return Ok(());
+            }
+            let worker = self.connect().await?;
+            self.park(worker).await;
+        }
+    }
+
+    /// Workers connected and waiting for a prompt.
+    pub async fn idle_count(&self) -> usize {
+        self.state.idle.lock().await.len()
+    }
+
+    /// Run `prompt` on an idle worker, waiting for one when all are busy,
+    /// and collect its response up to the result message.
+    pub async fn query(&self, prompt: impl Into<String>) -> Result<Vec<Message>, SdkError> {
+        self.dispatch(prompt.into()).await.into_result()
+    }
+
+    /// Run `prompts` on the pool's workers concurrently, at most `size` at a
+    /// time, returning their outcomes in order.
+    pub async fn parallel<T>(&self, prompts: Vec<T>) -> Vec<TaskOutcome>
+    where
+        T: Into<String>,
+    {
+        let runs = prompts
+            .into_iter()
+            .map(|prompt| self.dispatch(prompt.into()));
+        futures::future::join_all(runs).await
+    }
+
+    /// Stop taking prompts and disconnect the idle workers.
+    pub async fn close(&self) {
+        self.state.busy.close();
+        let workers: Vec<Worker> = self.state.idle.lock().await.drain(..).collect();
+        for mut worker in workers {
+            let _ = worker.client.disconnect().await;
+        }
+    }
+
+    async fn connect(&self) -> Result<Worker, SdkError> {
+        let transport = self.transport.as_ref().map(|factory| factory());
+        let mut client = ClaudeSdkClient::new(Some(self.options.clone()), transport);
+        client.connect(None).await?;
+        Ok(Worker { client, served: 0 })
+    }
+
+    async fn dispatch(&self, prompt: String) -> TaskOutcome {
+        let permit = match Arc::clone(&self.state.busy).acquire_owned().await {
+            Ok(permit) => permit,
+            Err(_) => {
+                let error = SdkError::InvalidConfig("query pool is closed".into());
+                return TaskOutcome::from_run(Vec::new(), Some(error));
+            }
+        };
+        let mut worker = match self.take_idle().await {
+            Some(worker) => worker,
+            None => match self.connect().await {
+                Ok(worker) => worker,
+                Err(error) => return TaskOutcome::from_run(Vec::new(), Some(error)),
+            },
+        };
+
+        let outcome = run(&worker.client, prompt).await;
+        worker.served += 1;
+        if outcome.is_completed() && worker.served < self.max_queries {
+            self.park(worker).await;
+        } else {
+            self.replace(worker, permit);
+        }
+        outcome
+    }
+
+    /// Keep `worker` idle, or disconnect it if the pool closed meanwhile.
+    async fn park(&self, mut worker: Worker) {
+        {
+            // `close` marks the pool closed before draining the idle list, so
+            // a worker pushed under this lock is drained with the others.
+            let mut idle = self.state.idle.lock().await;
+            if !self.state.busy.is_closed() {
+                idle.push(worker);
+                return;
+            }
+        }
+        sdk_debug!("query pool: closed while a worker was busy, disconnecting it");
+        let _ = worker.client.disconnect().await;
+    }
+
+    /// Pop a healthy idle worker, disconnecting any that went bad while idle.
+    async fn take_idle(&self) -> Option<Worker> {
+        let mut idle = self.state.idle.lock().await;
+        while let Some(mut worker) = idle.pop() {
+            if worker.client.is_healthy() {
+                return Some(worker);
+            }
+            sdk_debug!("query pool: dropping unhealthy idle worker");
+            let _ = worker.client.disconnect().await;
+        }
+        None
+    }
+
+    /// Retire `worker` and warm its successor, holding the slot meanwhile.
+    fn replace(&self, mut worker: Worker, permit: OwnedSemaphorePermit) {
+        let pool = self.clone();
+        tokio::spawn(async move {
+            let _ = worker.client.disconnect().await;
+            if !pool.state.busy.is_closed() {
+                match pool.connect().await {
+                    Ok(worker) => pool.park(worker).await,
+                    Err(err) => sdk_debug!("query pool: cannot warm a replacement: {err}"),
+                }
+            }
+            drop(permit);
+        });
+    }
+}
+
+async fn run(client: &ClaudeSdkClient, prompt: String) -> TaskOutcome {
+    if let Err(error) = client.query(prompt, "default").await {
+        return TaskOutcome::from_run(Vec::new(), Some(error));
+    }
+    let stream = match client.receive_response() {
+        Ok(stream) => stream,
+        Err(error) => return TaskOutcome::from_run(Vec::new(), Some(error)),
+    };
+    futures::pin_mut!(stream);
+    let mut messages = Vec::new();
+    while let Some(message) = stream.next().await {
+        match message {
+            Ok(message) => messages.push(message),
+            Err(error) => return TaskOutcome::from_run(messages, Some(error)),
+        }
+    }
+    TaskOutcome::from_run(messages, None)
+}
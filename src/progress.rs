@@ -0,0 +1,198 @@
+//! Coarse progress events for long-running agent sessions.
+//!
+//! [`ProgressTracker`] folds the message stream into events such as
+//! [`ProgressEvent::TurnStarted`] or [`ProgressEvent::ToolRunning`] that map
+//! directly onto a progress bar or status line.
+//! [`ClaudeSdkClient::progress_events`](crate::client::ClaudeSdkClient::progress_events)
+//! runs a tracker over a connected client; one-shot queries can feed their
+//! messages to [`ProgressTracker::observe`] directly.
+
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::message::{ContentBlock, Message, UserMessageContent};
+
+/// Progress update derived from the message stream.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProgressEvent {
+    /// The CLI reported its session through the `init` system message.
+    SessionStarted {
+        session_id: Option<String>,
+        model: Option<String>,
+    },
+    /// Claude began its `n`th response of the session (1-based).
+    TurnStarted(u32),
+    /// Claude requested a tool call.
+    ToolStarted { tool_use_id: String, name: String },
+    /// A tool call is still running; emitted on every tracker tick.
+    ToolRunning {
+        tool_use_id: String,
+        name: String,
+        elapsed: Duration,
+    },
+    /// A tool result arrived.
+    ToolFinished {
+        tool_use_id: String,
+        name: String,
+        elapsed: Duration,
+        is_error: bool,
+    },
+    /// The CLI started summarising the conversation.
+    Compacting,
+    /// Compaction finished.
+    Compacted,
+    /// Any other status reported by the CLI.
+    Status(String),
+    /// The query finished.
+    Completed {
+        turns: i64,
+        duration: Duration,
+        total_cost_usd: Option<f64>,
+        is_error: bool,
+    },
+}
+
+#[derive(Debug, Clone)]
+struct RunningTool {
+    tool_use_id: String,
+    name: String,
+    started: Instant,
+}
+
+/// Folds messages into [`ProgressEvent`]s.
+#[derive(Debug, Clone)]
+pub struct ProgressTracker {
+    turn: u32,
+    awaiting_turn: bool,
+    running: Vec<RunningTool>,
+}
+
+impl Default for ProgressTracker {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProgressTracker {
+    pub fn new() -> Self {
+        Self {
+            turn: 0,
+            awaiting_turn: true,
+            running: Vec::new(),
+        }
+    }
+
+    /// Number of turns started so far.
+    pub fn turn(&self) -> u32 {
+        self.turn
+    }
+
+    /// Names of the tools currently running, oldest first.
+    pub fn running_tools(&self) -> impl Iterator<Item = &str> {
+        self.running.iter().map(|tool| tool.name.as_str())
+    }
+
+    /// Events caused by `message`, in order.
+    pub fn observe(&mut self, message: &Message) -> Vec<ProgressEvent> {
+        let mut events = Vec::new();
+        match message {
+            Message::System(system) => {
+                let text = |key: &str| {
+                    system
+                        .data
+                        .get(key)
+                        .and_then(Value::as_str)
+                        .map(str::to_string)
+                };
+                match system.subtype.as_str() {
+                    "init" => events.push(ProgressEvent::SessionStarted {
+                        session_id: text("session_id"),
+                        model: text("model"),
+                    }),
+                    "compact_boundary" => events.push(ProgressEvent::Compacted),
+                    "status" => match text("status") {
+                        Some(status) if status == "compacting" => {
+                            events.push(ProgressEvent::Compacting)
+                        }
+                        Some(status) => events.push(ProgressEvent::Status(status)),
+                        None => {}
+                    },
+                    _ => {}
+                }
+            }
+            Message::Assistant(assistant) => {
+                if assistant.parent_tool_use_id.is_none() && self.awaiting_turn {
+                    self.awaiting_turn = false;
+                    self.turn += 1;
+                    events.push(ProgressEvent::TurnStarted(self.turn));
+                }
+                for block in &assistant.content {
+                    if let ContentBlock::ToolUse(tool) = block {
+                        self.running.push(RunningTool {
+                            tool_use_id: tool.id.clone(),
+                            name: tool.name.clone(),
+                            started: Instant::now(),
+                        });
+                        events.push(ProgressEvent::ToolStarted {
+                            tool_use_id: tool.id.clone(),
+                            name: tool.name.clone(),
+                        });
+                    }
+                }
+            }
+            Message::User(user) => {
+                if user.parent_tool_use_id.is_none() {
+                    self.awaiting_turn = true;
+                }
+                let UserMessageContent::Blocks(blocks) = &user.content else {
+                    return events;
+                };
+                for block in blocks {
+                    let ContentBlock::ToolResult(result) = block else {
+                        continue;
+                    };
+                    let Some(index) = self
+                        .running
+                        .iter()
+                        .position(|tool| tool.tool_use_id == result.tool_use_id)
+                    else {
+                        continue;
+                    };
+                    let tool = self.running.remove(index);
+                    events.push(ProgressEvent::ToolFinished {
+                        tool_use_id: tool.tool_use_id,
+                        name: tool.name,
+                        elapsed: tool.started.elapsed(),
+                        is_error: result.is_error.unwrap_or(false),
+                    });
+                }
+            }
+            Message::Result(result) => {
+                self.awaiting_turn = true;
+                self.running.clear();
+                events.push(ProgressEvent::Completed {
+                    turns: result.num_turns,
+                    duration: Duration::from_millis(u64::try_from(result.duration_ms).unwrap_or(0)),
+                    total_cost_usd: result.total_cost_usd,
+                    is_error: result.is_error,
+                });
+            }
+            Message::StreamEvent(_) => {}
+        }
+        events
+    }
+
+    /// A [`ProgressEvent::ToolRunning`] for every tool still running.
+    pub fn tick(&self) -> Vec<ProgressEvent> {
+        self.running
+            .iter()
+            .map(|tool| ProgressEvent::ToolRunning {
+                tool_use_id: tool.tool_use_id.clone(),
+                name: tool.name.clone(),
+                elapsed: tool.started.elapsed(),
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,344 @@
+//! Named prompt templates with variables and partials.
+//!
+//! Templates use a small mustache-like syntax:
+//!
+//! * `{{name}}` inserts a variable with `&`, `<` and `>` escaped, so user
+//!   input placed inside `<document>...</document>`-style fences cannot close
+//!   the fence or open new tags;
+//! * `{{{name}}}` inserts a variable verbatim, for trusted text only;
+//! * `{{> partial}}` inserts another template from the same [`PromptLibrary`];
+//! * `\{{` produces a literal `{{`.
+//!
+//! Variable values are never themselves expanded, and rendering fails on
+//! unknown variables or partials instead of leaving placeholders behind.
+//!
+//! ```
+//! # use sdk_claude_rust::prompts::{PromptLibrary, PromptVars};
+//! # fn example() -> Result<(), sdk_claude_rust::error::SdkError> {
+//! let mut library = PromptLibrary::new();
+//! library.add("rules", "Answer in {{language}}.")?;
+//! library.add("review", "{{> rules}}\n<diff>\n{{diff}}\n</diff>")?;
+//!
+//! let prompt = library.render(
+//!     "review",
+//!     &PromptVars::new()
+//!         .with("language", "English")
+//!         .with("diff", "-a </diff> +b"),
+//! )?;
+//! assert_eq!(prompt, "Answer in English.\n<diff>\n-a &lt;/diff&gt; +b\n</diff>");
+//! # Ok(())
+//! # }
+//! # example().unwrap();
+//! ```
+
+use std::collections::{BTreeMap, HashMap};
+use std::path::Path;
+
+use crate::error::SdkError;
+
+/// File extensions picked up by [`PromptLibrary::load_dir`].
+pub const TEMPLATE_EXTENSIONS: &[&str] = &["prompt", "md", "txt"];
+
+/// Partials nested deeper than this are treated as a cycle.
+const MAX_PARTIAL_DEPTH: usize = 16;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Variable { name: String, escape: bool },
+    Partial(String),
+}
+
+/// A parsed template.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PromptTemplate {
+    name: String,
+    segments: Vec<Segment>,
+}
+
+impl PromptTemplate {
+    /// Parse `source`; fails on unterminated or empty tags.
+    pub fn parse(name: impl Into<String>, source: &str) -> Result<Self, SdkError> {
+        let name = name.into();
+        let segments = parse_segments(&name, source)?;
+        Ok(Self { name, segments })
+    }
+
+    /// Read and parse a template file, named after the file stem.
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let path = path.as_ref();
+        let name = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .ok_or_else(|| {
+                SdkError::InvalidConfig(format!(
+                    "prompt template path has no usable name: {}",
+                    path.display()
+                ))
+            })?;
+        let source = std::fs::read_to_string(path)?;
+        Self::parse(name, &source)
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Variables referenced directly by this template, in order of first use.
+    pub fn variables(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = Vec::new();
+        for segment in &self.segments {
+            if let Segment::Variable { name, .. } = segment {
+                if !names.contains(&name.as_str()) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+
+    /// Partials referenced directly by this template.
+    pub fn partials(&self) -> Vec<&str> {
+        self.segments
+            .iter()
+            .filter_map(|segment| match segment {
+                Segment::Partial(name) => Some(name.as_str()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Render a template that uses no partials.
+    pub fn render(&self, vars: &PromptVars) -> Result<String, SdkError> {
+        let mut output = String::new();
+        self.render_into(&mut output, vars, None, 0)?;
+        Ok(output)
+    }
+
+    /// [`tokens::estimate`](crate::tokens::estimate) of the rendered prompt,
+    /// to check it against a context budget before sending it.
+    pub fn estimated_tokens(&self, vars: &PromptVars) -> Result<usize, SdkError> {
+        Ok(crate::tokens::estimate(&self.render(vars)?))
+    }
+
+    fn render_into(
+        &self,
+        output: &mut String,
+        vars: &PromptVars,
+        library: Option<&PromptLibrary>,
+        depth: usize,
+    ) -> Result<(), SdkError> {
+        if depth > MAX_PARTIAL_DEPTH {
+            return Err(SdkError::InvalidConfig(format!(
+                "prompt template `{}` nests partials more than {MAX_PARTIAL_DEPTH} levels deep; check for a cycle",
+                self.name
+            )));
+        }
+        for segment in &self.segments {
+            match segment {
+                Segment::Text(text) => output.push_str(text),
+                Segment::Variable { name, escape } => {
+                    let value = vars.get(name).ok_or_else(|| {
+                        SdkError::InvalidConfig(format!(
+                            "prompt template `{}` needs variable `{name}`",
+                            self.name
+                        ))
+                    })?;
+                    if *escape {
+                        output.push_str(&escape_value(value));
+                    } else {
+                        output.push_str(value);
+                    }
+                }
+                Segment::Partial(name) => {
+                    let partial =
+                        library
+                            .and_then(|library| library.get(name))
+                            .ok_or_else(|| {
+                                SdkError::InvalidConfig(format!(
+                                    "prompt template `{}` includes unknown partial `{name}`",
+                                    self.name
+                                ))
+                            })?;
+                    partial.render_into(output, vars, library, depth + 1)?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Variable values for rendering.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PromptVars {
+    values: BTreeMap<String, String>,
+}
+
+impl PromptVars {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    pub fn insert(&mut self, name: impl Into<String>, value: impl Into<String>) {
+        self.values.insert(name.into(), value.into());
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.values.get(name).map(String::as_str)
+    }
+}
+
+impl<K: Into<String>, V: Into<String>> FromIterator<(K, V)> for PromptVars {
+    fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+        Self {
+            values: iter
+                .into_iter()
+                .map(|(name, value)| (name.into(), value.into()))
+                .collect(),
+        }
+    }
+}
+
+/// A set of named templates that can include each other as partials.
+#[derive(Debug, Clone, Default)]
+pub struct PromptLibrary {
+    templates: HashMap<String, PromptTemplate>,
+}
+
+impl PromptLibrary {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Add a parsed template, replacing any template with the same name.
+    pub fn insert(&mut self, template: PromptTemplate) {
+        self.templates.insert(template.name.clone(), template);
+    }
+
+    /// Parse `source` and add it under `name`.
+    pub fn add(&mut self, name: impl Into<String>, source: &str) -> Result<(), SdkError> {
+        self.insert(PromptTemplate::parse(name, source)?);
+        Ok(())
+    }
+
+    /// Add every file in `dir` with one of the [`TEMPLATE_EXTENSIONS`],
+    /// named after its file stem; returns how many were loaded.
+    pub fn load_dir(&mut self, dir: impl AsRef<Path>) -> Result<usize, SdkError> {
+        let mut loaded = 0;
+        for entry in std::fs::read_dir(dir)? {
+            let path = entry?.path();
+            let is_template = path.is_file()
+                && path
+                    .extension()
+                    .and_then(|extension| extension.to_str())
+                    .is_some_and(|extension| TEMPLATE_EXTENSIONS.contains(&extension));
+            if is_template {
+                self.insert(PromptTemplate::from_file(&path)?);
+                loaded += 1;
+            }
+        }
+        Ok(loaded)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&PromptTemplate> {
+        self.templates.get(name)
+    }
+
+    /// Template names, sorted.
+    pub fn names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.templates.keys().map(String::as_str).collect();
+        names.sort_unstable();
+        names
+    }
+
+    /// Render template `name`, resolving partials from this library.
+    pub fn render(&self, name: &str, vars: &PromptVars) -> Result<String, SdkError> {
+        let template = self
+            .get(name)
+            .ok_or_else(|| SdkError::InvalidConfig(format!("unknown prompt template `{name}`")))?;
+        let mut output = String::new();
+        template.render_into(&mut output, vars, Some(self), 0)?;
+        Ok(output)
+    }
+}
+
+fn escape_value(value: &str) -> String {
+    let mut escaped = String::with_capacity(value.len());
+    for ch in value.chars() {
+        match ch {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            other => escaped.push(other),
+        }
+    }
+    escaped
+}
+
+fn parse_segments(name: &str, source: &str) -> Result<Vec<Segment>, SdkError> {
+    let mut segments = Vec::new();
+    let mut text = String::new();
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        if rest[..start].ends_with('\\') {
+            text.push_str(&rest[..start - 1]);
+            text.push_str("{{");
+            rest = &rest[start + 2..];
+            continue;
+        }
+        text.push_str(&rest[..start]);
+        let tag = &rest[start..];
+        let (raw, open, close) = if tag.starts_with("{{{") {
+            (true, 3, "}}}")
+        } else {
+            (false, 2, "}}")
+        };
+        let end = tag[open..].find(close).ok_or_else(|| {
+            SdkError::InvalidConfig(format!(
+                "prompt template `{name}` has an unterminated tag at byte {start}"
+            ))
+        })?;
+        let body = tag[open..open + end].trim();
+        if !text.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut text)));
+        }
+        let (target, segment) = match body.strip_prefix('>') {
+            Some(partial) if !raw => {
+                let partial = partial.trim();
+                (partial, Segment::Partial(partial.to_string()))
+            }
+            _ => (
+                body,
+                Segment::Variable {
+                    name: body.to_string(),
+                    escape: !raw,
+                },
+            ),
+        };
+        if !valid_identifier(target) {
+            return Err(SdkError::InvalidConfig(format!(
+                "prompt template `{name}` has an invalid tag `{body}`"
+            )));
+        }
+        segments.push(segment);
+        rest = &tag[open + end + close.len()..];
+    }
+    text.push_str(rest);
+    if !text.is_empty() {
+        segments.push(Segment::Text(text));
+    }
+    Ok(segments)
+}
+
+fn valid_identifier(name: &str) -> bool {
+    !name.is_empty()
+        && name
+            .chars()
+            .all(|ch| ch.is_alphanumeric() || matches!(ch, '_' | '-' | '.'))
+}
@@ -1,12 +1,12 @@
 //! One-shot query helper mirroring the Python `query` coroutine.
 
-use futures::Stream;
+use futures::{Stream, StreamExt};
 
 use crate::client::DynTransport;
 use crate::config::ClaudeAgentOptions;
 use crate::error::SdkError;
 use crate::internal::client::{InternalClient, PromptInput};
-use crate::message::Message;
+use crate::message::{ContentBlock, Message};
 
 /// Execute a one-off query against Claude Code, yielding streamed messages.
 pub async fn query<P>(
@@ -17,11 +17,87 @@ pub async fn query<P>(
 where
     P: Into<PromptInput>,
 {
-    std::env::set_var("CLAUDE_CODE_ENTRYPOINT", "sdk-rs");
-
     let internal = InternalClient::new();
     let prompt = prompt.into();
     let options = options.unwrap_or_default();
 
     internal.process_query(prompt, options, transport).await
 }
+
+/// Ask a single question and return the answer text.
+///
+/// Credentials and the model are read from `.env` in the current directory
+/// and the process environment, as [`options_from_env`] does; without the
+/// `dotenvy` feature only the process environment is used. Use [`ask_with`]
+/// to pass options or a transport explicitly.
+///
+/// ```no_run
+/// # async fn example() -> Result<(), sdk_claude_rust::error::SdkError> {
+/// let answer = sdk_claude_rust::ask("What is the capital of France?").await?;
+/// println!("{answer}");
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`options_from_env`]: crate::env::options_from_env
+#[cfg(not(target_arch = "wasm32"))]
+pub async fn ask(question: impl Into<String>) -> Result<String, SdkError> {
+    #[cfg(feature = "dotenvy")]
+    let options = crate::env::options_from_env(None)
+        .map_err(|err| SdkError::InvalidConfig(format!("failed to load .env: {err}")))?;
+    #[cfg(not(feature = "dotenvy"))]
+    let options = crate::env::options_with_env(crate::env::get_anthropic_env());
+    ask_with(question, Some(options), None).await
+}
+
+/// [`ask`] with explicit options and transport.
+///
+/// Returns the `result` text of the final result message, falling back to
+/// the text of the last assistant message when the CLI reports none. Error
+/// results become errors: limit results as [`SdkError::MaxTurns`] or
+/// [`SdkError::BudgetExceeded`], anything else as [`SdkError::Message`].
+pub async fn ask_with(
+    question: impl Into<String>,
+    options: Option<ClaudeAgentOptions>,
+    transport: Option<DynTransport>,
+) -> Result<String, SdkError> {
+    let stream = query(question.into(), options, transport).await?;
+    futures::pin_mut!(stream);
+
+    let mut last_text = None;
+    while let Some(message) = stream.next().await {
+        match message? {
+            Message::Assistant(assistant) => {
+                let text: String = assistant
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::Text(block) => Some(block.text.as_str()),
+                        _ => None,
+                    })
+                    .collect();
+                if !text.is_empty() {
+                    last_text = Some(text);
+                }
+            }
+            Message::Result(result) if result.is_error => {
+                return Err(SdkError::Message(
+                    result
+                        .result
+                        .unwrap_or_else(|| format!("query ended with {}", result.subtype)),
+                ));
+            }
+            Message::Result(result) => {
+                return Ok(result
+                    .result
+                    .filter(|text| !text.is_empty())
+                    .or(last_text)
+                    .unwrap_or_default());
+            }
+            _ => {}
+        }
+    }
+    Err(SdkError::Protocol(
+        "CLI output ended before a result message".into(),
+    ))
+}
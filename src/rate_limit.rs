@@ -0,0 +1,289 @@
+//! Client-side rate limiting shared by every session of a process.
+//!
+//! A [`RateLimiter`] enforces up to three [`RateLimits`]: queries started
+//! per minute, concurrently connected sessions and tokens used per hour.
+//! Set it as [`ClaudeAgentOptions::rate_limiter`] on every client and
+//! one-shot query that draws from the same budget (options clone the handle,
+//! so all copies share one limiter):
+//!
+//! * [`ClaudeSdkClient::connect`] and [`query`](crate::query::query) wait for
+//!   a session slot, released when the session closes;
+//! * [`ClaudeSdkClient::query`] and [`query`](crate::query::query) wait until
+//!   starting a query stays within the per-minute and per-hour limits;
+//! * the usage of every result message is charged to the hourly budget.
+//!
+//! Waiters are served in arrival order, so a burst from one caller cannot
+//! starve the others. The token limit is checked before a query starts,
+//! so a single large query can overshoot it; the excess delays later queries.
+//!
+//! Windows are measured on the [`Clock`] passed to each call; the SDK passes
+//! [`ClaudeAgentOptions::clock`](crate::config::ClaudeAgentOptions::clock).
+//!
+//! [`ClaudeAgentOptions::rate_limiter`]: crate::config::ClaudeAgentOptions::rate_limiter
+//! [`ClaudeSdkClient::connect`]: crate::client::ClaudeSdkClient::connect
+//! [`ClaudeSdkClient::query`]: crate::client::ClaudeSdkClient::query
+
+use std::collections::VecDeque;
+use std::fmt;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio::time::Instant;
+
+use crate::clock::Clock;
+use crate::error::SdkError;
+use crate::message::ResultMessage;
+
+const MINUTE: Duration = Duration::from_secs(60);
+const HOUR: Duration = Duration::from_secs(3600);
+
+/// Usage fields charged to [`RateLimits::tokens_per_hour`]. Cache reads are
+/// left out, as the API does not count them towards input limits.
+const CHARGED_USAGE: &[&str] = &[
+    "input_tokens",
+    "output_tokens",
+    "cache_creation_input_tokens",
+];
+
+/// Limits enforced by a [`RateLimiter`]; `None` leaves a dimension unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub queries_per_minute: Option<u32>,
+    pub max_concurrent_sessions: Option<usize>,
+    pub tokens_per_hour: Option<u64>,
+}
+
+impl RateLimits {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_queries_per_minute(mut self, queries: u32) -> Self {
+        self.queries_per_minute = Some(queries);
+        self
+    }
+
+    pub fn with_max_concurrent_sessions(mut self, sessions: usize) -> Self {
+        self.max_concurrent_sessions = Some(sessions);
+        self
+    }
+
+    pub fn with_tokens_per_hour(mut self, tokens: u64) -> Self {
+        self.tokens_per_hour = Some(tokens);
+        self
+    }
+}
+
+/// Shared handle enforcing [`RateLimits`]; clones share their state.
+#[derive(Clone)]
+pub struct RateLimiter {
+    inner: Arc<LimiterInner>,
+}
+
+struct LimiterInner {
+    limits: RateLimits,
+    sessions: Option<Arc<Semaphore>>,
+    /// Held while a caller waits for a query slot, so slots go out in
+    /// arrival order (tokio's mutex is fair).
+    queue: tokio::sync::Mutex<()>,
+    windows: Mutex<Windows>,
+}
+
+#[derive(Default)]
+struct Windows {
+    query_starts: VecDeque<Instant>,
+    token_charges: VecDeque<(Instant, u64)>,
+    tokens_in_window: u64,
+}
+
+impl Windows {
+    fn prune(&mut self, now: Instant) {
+        while let Some(start) = self.query_starts.front() {
+            if now.duration_since(*start) < MINUTE {
+                break;
+            }
+            self.query_starts.pop_front();
+        }
+        while let Some((charged_at, tokens)) = self.token_charges.front() {
+            if now.duration_since(*charged_at) < HOUR {
+                break;
+            }
+            self.tokens_in_window -= tokens;
+            self.token_charges.pop_front();
+        }
+    }
+}
+
+impl fmt::Debug for RateLimiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RateLimiter")
+            .field("limits", &self.inner.limits)
+            .field("active_sessions", &self.active_sessions())
+            .field("tokens_charged", &self.windows().tokens_in_window)
+            .finish()
+    }
+}
+
+impl RateLimiter {
+    /// Create a limiter, failing with [`SdkError::InvalidConfig`] when a
+    /// limit is zero, which would never admit anything.
+    pub fn new(limits: RateLimits) -> Result<Self, SdkError> {
+        let zero = [
+            ("queries_per_minute", limits.queries_per_minute == Some(0)),
+            (
+                "max_concurrent_sessions",
+                limits.max_concurrent_sessions == Some(0),
+            ),
+            ("tokens_per_hour", limits.tokens_per_hour == Some(0)),
+        ];
+        if let Some((name, _)) = zero.iter().find(|(_, is_zero)| *is_zero) {
+            return Err(SdkError::InvalidConfig(format!(
+                "rate limit {name} must be at least 1; leave it unset for no limit"
+            )));
+        }
+        Ok(Self {
+            inner: Arc::new(LimiterInner {
+                limits,
+                sessions: limits
+                    .max_concurrent_sessions
+                    .map(|sessions| Arc::new(Semaphore::new(sessions))),
+                queue: tokio::sync::Mutex::new(()),
+                windows: Mutex::new(Windows::default()),
+            }),
+        })
+    }
+
+    pub fn limits(&self) -> RateLimits {
+        self.inner.limits
+    }
+
+    /// Sessions currently holding a slot.
+    pub fn active_sessions(&self) -> usize {
+        match (
+            &self.inner.sessions,
+            self.inner.limits.max_concurrent_sessions,
+        ) {
+            (Some(sessions), Some(max)) => max - sessions.available_permits(),
+            _ => 0,
+        }
+    }
+
+    /// Tokens charged during the last hour on `clock`.
+    pub fn tokens_last_hour(&self, clock: &dyn Clock) -> u64 {
+        let mut windows = self.windows();
+        windows.prune(clock.now());
+        windows.tokens_in_window
+    }
+
+    /// Wait for a session slot; it is released when the permit is dropped.
+    pub async fn acquire_session(&self) -> SessionPermit {
+        let permit = match &self.inner.sessions {
+            Some(sessions) => Some(
+                Arc::clone(sessions)
+                    .acquire_owned()
+                    .await
+                    .expect("rate limiter semaphore is never closed"),
+            ),
+            None => None,
+        };
+        SessionPermit {
+            limiter: self.clone(),
+            _permit: permit,
+        }
+    }
+
+    /// Wait on `clock` until a query may start, then count it.
+    pub async fn acquire_query(&self, clock: &dyn Clock) {
+        let _turn = self.inner.queue.lock().await;
+        loop {
+            let wait = {
+                let now = clock.now();
+                let mut windows = self.windows();
+                windows.prune(now);
+                match self.next_slot(&windows, now) {
+                    Some(at) => at.saturating_duration_since(now),
+                    None => {
+                        windows.query_starts.push_back(now);
+                        return;
+                    }
+                }
+            };
+            clock.sleep(wait).await;
+        }
+    }
+
+    /// Charge `tokens` to the hourly budget at the current time on `clock`.
+    pub fn record_tokens(&self, tokens: u64, clock: &dyn Clock) {
+        if tokens == 0 {
+            return;
+        }
+        let mut windows = self.windows();
+        windows.token_charges.push_back((clock.now(), tokens));
+        windows.tokens_in_window += tokens;
+    }
+
+    /// Charge the usage reported by `result`.
+    pub fn record_result(&self, result: &ResultMessage, clock: &dyn Clock) {
+        let Some(usage) = &result.usage else {
+            return;
+        };
+        let tokens = CHARGED_USAGE
+            .iter()
+            .filter_map(|field| usage.get(*field).and_then(Value::as_u64))
+            .sum();
+        self.record_tokens(tokens, clock);
+    }
+
+    /// The windows stay consistent between statements, so a panic while
+    /// holding the lock leaves nothing to recover.
+    fn windows(&self) -> MutexGuard<'_, Windows> {
+        self.inner
+            .windows
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// When the next query may start, or `None` if it may start now.
+    fn next_slot(&self, windows: &Windows, now: Instant) -> Option<Instant> {
+        let limits = &self.inner.limits;
+        if let Some(max) = limits.queries_per_minute {
+            if windows.query_starts.len() >= max as usize {
+                let oldest = windows.query_starts.front().copied().unwrap_or(now);
+                return Some(oldest + MINUTE);
+            }
+        }
+        if let Some(max) = limits.tokens_per_hour {
+            if windows.tokens_in_window >= max {
+                // Wait until enough charges expire to get back under the limit.
+                let mut remaining = windows.tokens_in_window;
+                for (charged_at, tokens) in &windows.token_charges {
+                    remaining -= tokens;
+                    if remaining < max {
+                        return Some(*charged_at + HOUR);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+/// A session slot from [`RateLimiter::acquire_session`].
+pub struct SessionPermit {
+    limiter: RateLimiter,
+    _permit: Option<OwnedSemaphorePermit>,
+}
+
+impl SessionPermit {
+    pub fn limiter(&self) -> &RateLimiter {
+        &self.limiter
+    }
+}
+
+impl fmt::Debug for SessionPermit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionPermit").finish_non_exhaustive()
+    }
+}
@@ -0,0 +1,175 @@
+//! Low-level access to the control protocol.
+//!
+//! [`ClaudeSdkClient`](crate::client::ClaudeSdkClient) hides the wire format
+//! behind prompts, typed options and response streams. A [`RawSession`]
+//! sits one level down: it runs the control protocol over a transport you
+//! provide and lets you write arbitrary frames and send control requests of
+//! any subtype, while still answering the CLI's permission, hook and SDK MCP
+//! requests.
+//!
+//! `RawSession` is the supported entry point to the engine in
+//! [`internal::query`](crate::internal::query), which may change in any
+//! release. Its API follows semver on its own: a breaking change to it is a
+//! major release even when the high-level client is unaffected, and the
+//! reverse does not hold.
+
+use futures::Stream;
+use serde_json::Value;
+
+use crate::buffer_limit::BufferLimit;
+use crate::client::DynTransport;
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::internal::query::Query;
+use crate::message::{Message, RawMessage};
+use crate::permission::PermissionMode;
+use crate::transport::Transport;
+
+/// A connected control protocol session over a caller-supplied transport.
+pub struct RawSession {
+    transport: DynTransport,
+    query: Query<dyn Transport>,
+    server_info: Option<Value>,
+}
+
+impl std::fmt::Debug for RawSession {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("RawSession")
+            .field("closed", &self.query.is_closed())
+            .finish()
+    }
+}
+
+impl RawSession {
+    /// Connect `transport` and initialize the control protocol.
+    ///
+    /// Of `options`, only what the SDK side of the protocol handles is used:
+    /// `can_use_tool`, `hooks`, `sdk_servers`, `mcp_fallback`, the buffer
+    /// limit, watchdog, clock, redactor, metrics and control frame sink. CLI
+    /// flags are the transport's business. Frames are written as given, so
+    /// prompt middleware and tool result limits do not apply.
+    pub async fn open(
+        transport: DynTransport,
+        options: &ClaudeAgentOptions,
+    ) -> Result<Self, SdkError> {
+        transport.connect().await?;
+        let query: Query<dyn Transport> = Query::new(
+            transport.clone(),
+            true,
+            options.can_use_tool.clone(),
+            options.hooks.clone(),
+            options.sdk_servers.clone(),
+        );
+        query
+            .set_frame_sink(options.control_frame_sink.clone())
+            .await;
+        query.set_metrics(options.metrics.clone()).await;
+        query
+            .set_buffer_limit(options.buffer_limit.as_ref().map(BufferLimit::for_session))
+            .await;
+        query.set_message_spill(options.message_spill.clone()).await;
+        query.set_mcp_fallback(options.mcp_fallback.clone()).await;
+        query.set_redactor(Some(options.effective_redactor())).await;
+        query.set_clock(options.effective_clock()).await;
+        if let Some(config) = options.control_watchdog {
+            query.set_control_watchdog(config).await;
+        }
+        query.start().await?;
+        let initialized = query.initialize().await.and_then(|info| {
+            options.check_system_prompt_preset(info.as_ref())?;
+            Ok(info)
+        });
+        let server_info = match initialized {
+            Ok(info) => info,
+            Err(err) => {
+                let _ = query.close().await;
+                return Err(err);
+            }
+        };
+        Ok(Self {
+            transport,
+            query,
+            server_info,
+        })
+    }
+
+    /// Response to the `initialize` request.
+    pub fn server_info(&self) -> Option<&Value> {
+        self.server_info.as_ref()
+    }
+
+    /// Write `frame` to the CLI unchanged.
+    pub async fn send(&self, frame: &Value) -> Result<(), SdkError> {
+        self.transport.write(frame).await?;
+        self.query.record_message_sent(frame).await;
+        if frame.get("type").and_then(Value::as_str) == Some("user") {
+            self.query.mark_prompt_sent().await;
+        }
+        Ok(())
+    }
+
+    /// Send a control request and wait for its response payload.
+    ///
+    /// `request` is the inner request object, e.g.
+    /// `{"subtype": "set_model", "model": "opus"}`; the envelope and request
+    /// id are added here. An error response becomes an `Err`.
+    pub async fn control_request(&self, request: Value) -> Result<Value, SdkError> {
+        self.query.send_control_request(request).await
+    }
+
+    pub async fn interrupt(&self) -> Result<(), SdkError> {
+        self.query.interrupt().await
+    }
+
+    pub async fn set_permission_mode(&self, mode: PermissionMode) -> Result<(), SdkError> {
+        self.query.set_permission_mode(mode).await
+    }
+
+    pub async fn set_model(&self, model: Option<String>) -> Result<(), SdkError> {
+        self.query.set_model(model).await
+    }
+
+    /// Next message from the CLI; `None` once the transport is exhausted.
+    ///
+    /// Control frames are consumed by the session and never returned.
+    pub async fn next_message(&self) -> Result<Option<Message>, SdkError> {
+        self.query.next_message().await
+    }
+
+    /// Like [`RawSession::next_message`], keeping the wire form.
+    pub async fn next_raw_message(&self) -> Result<Option<RawMessage>, SdkError> {
+        self.query.next_raw_message().await
+    }
+
+    /// Every remaining message, as a stream.
+    pub fn messages(&self) -> impl Stream<Item = Result<Message, SdkError>> + 'static {
+        futures::stream::unfold(Some(self.query.clone()), |query| async move {
+            let query = query?;
+            match query.next_message().await {
+                Ok(Some(message)) => Some((Ok(message), Some(query))),
+                Ok(None) => None,
+                Err(err) => Some((Err(err), None)),
+            }
+        })
+    }
+
+    /// Number of control requests sent here still awaiting a response.
+    pub async fn pending_control_count(&self) -> usize {
+        self.query.pending_control_count().await
+    }
+
+    /// Close the CLI's stdin, signalling that no more frames follow.
+    pub async fn end_input(&self) -> Result<(), SdkError> {
+        self.query.end_input().await
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.query.is_closed()
+    }
+
+    /// Close the session and its transport, cancelling pending control
+    /// requests.
+    pub async fn close(&self) -> Result<(), SdkError> {
+        self.query.close().await
+    }
+}
@@ -0,0 +1,234 @@
+//! Respawning the CLI when its process dies mid-turn.
+//!
+//! A [`RecoveringTransport`] wraps transports made by a factory. When a read
+//! fails with [`SdkError::Process`] while a turn is in flight, it starts a new
+//! transport resuming the last session id it saw, replays the `initialize`
+//! request and the user messages not yet answered by a result, and yields a
+//! `system` message with subtype [`RECOVERED_SUBTYPE`] before the new
+//! process's output.
+//!
+//! Set [`ClaudeAgentOptions::crash_recovery`] to wrap the default subprocess
+//! transport; wrap a custom transport with [`RecoveringTransport::new`].
+//!
+//! [`ClaudeAgentOptions::crash_recovery`]: crate::config::ClaudeAgentOptions::crash_recovery
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::{broadcast, Mutex, RwLock};
+
+use crate::buffer_limit::BufferCharge;
+use crate::client::DynTransport;
+use crate::error::{ConnectionDiagnostics, SdkError};
+use crate::internal::trace::sdk_warn;
+use crate::transport::stderr::StderrEvent;
+use crate::transport::{ProcessInfo, Transport};
+
+/// Subtype of the system message emitted after a restart.
+pub const RECOVERED_SUBTYPE: &str = "recovered";
+
+/// How often a crashed CLI is restarted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CrashRecovery {
+    /// Restarts allowed over the transport's lifetime.
+    pub max_restarts: u32,
+}
+
+impl CrashRecovery {
+    pub fn new(max_restarts: u32) -> Self {
+        Self { max_restarts }
+    }
+}
+
+impl Default for CrashRecovery {
+    fn default() -> Self {
+        Self::new(3)
+    }
+}
+
+/// Builds a transport, resuming the given session id when one is passed.
+pub type TransportFactory =
+    Arc<dyn Fn(Option<String>) -> Result<DynTransport, SdkError> + Send + Sync>;
+
+#[derive(Default)]
+struct RecoveryState {
+    session_id: Option<String>,
+    initialize: Option<Value>,
+    /// User messages written since the last result.
+    pending: Vec<Value>,
+    awaiting_result: bool,
+    restarts: u32,
+    closed: bool,
+}
+
+/// Transport that restarts its inner transport after a process crash.
+///
+/// Stderr subscriptions follow the transport current when they were made.
+pub struct RecoveringTransport {
+    factory: TransportFactory,
+    recovery: CrashRecovery,
+    current: RwLock<DynTransport>,
+    state: Mutex<RecoveryState>,
+}
+
+impl RecoveringTransport {
+    /// Wrap a first transport built by `factory(None)`.
+    pub fn new(factory: TransportFactory, recovery: CrashRecovery) -> Result<Self, SdkError> {
+        let first = factory(None)?;
+        Ok(Self {
+            factory,
+            recovery,
+            current: RwLock::new(first),
+            state: Mutex::new(RecoveryState {
+                awaiting_result: true,
+                ..Default::default()
+            }),
+        })
+    }
+
+    /// Restarts so far.
+    pub async fn restarts(&self) -> u32 {
+        self.state.lock().await.restarts
+    }
+
+    async fn current(&self) -> DynTransport {
+        Arc::clone(&*self.current.read().await)
+    }
+
+    async fn observe_read(&self, message: &Value) {
+        let mut state = self.state.lock().await;
+        if let Some(session_id) = message.get("session_id").and_then(Value::as_str) {
+            state.session_id = Some(session_id.to_string());
+        }
+        if message.get("type").and_then(Value::as_str) == Some("result") {
+            state.pending.clear();
+            state.awaiting_result = false;
+        }
+    }
+
+    /// Start a new transport in place of the crashed one, returning the
+    /// recovered event, or `None` when no restart is due.
+    async fn recover(
+        &self,
+        crashed: &DynTransport,
+        error: &SdkError,
+    ) -> Result<Option<Value>, SdkError> {
+        let mut current = self.current.write().await;
+        let (session_id, replay, attempt) = {
+            let mut state = self.state.lock().await;
+            if state.closed
+                || !state.awaiting_result
+                || state.restarts >= self.recovery.max_restarts
+            {
+                return Ok(None);
+            }
+            state.restarts += 1;
+            let replay: Vec<Value> = state
+                .initialize
+                .iter()
+                .chain(&state.pending)
+                .cloned()
+                .collect();
+            (state.session_id.clone(), replay, state.restarts)
+        };
+        sdk_warn!(
+            "CLI process died ({error}); restarting (attempt {attempt}/{}), resuming {session_id:?}",
+            self.recovery.max_restarts
+        );
+
+        let _ = crashed.close().await;
+        let transport = (self.factory)(session_id.clone())?;
+        transport.connect().await?;
+        for message in &replay {
+            transport.write(message).await?;
+        }
+        *current = transport;
+
+        Ok(Some(json!({
+            "type": "system",
+            "subtype": RECOVERED_SUBTYPE,
+            "session_id": session_id,
+            "attempt": attempt,
+            "error": error.to_string(),
+        })))
+    }
+}
+
+#[async_trait]
+impl Transport for RecoveringTransport {
+    async fn connect(&self) -> Result<(), SdkError> {
+        self.current().await.connect().await
+    }
+
+    async fn write(&self, payload: &Value) -> Result<(), SdkError> {
+        {
+            let mut state = self.state.lock().await;
+            match payload.get("type").and_then(Value::as_str) {
+                Some("user") => {
+                    state.pending.push(payload.clone());
+                    state.awaiting_result = true;
+                }
+                Some("control_request")
+                    if payload.pointer("/request/subtype").and_then(Value::as_str)
+                        == Some("initialize") =>
+                {
+                    state.initialize = Some(payload.clone());
+                }
+                _ => {}
+            }
+        }
+        self.current().await.write(payload).await
+    }
+
+    async fn read(&self) -> Result<Option<Value>, SdkError> {
+        Ok(self.read_charged().await?.map(|(message, _charge)| message))
+    }
+
+    async fn read_charged(&self) -> Result<Option<(Value, Option<BufferCharge>)>, SdkError> {
+        let transport = self.current().await;
+        match transport.read_charged().await {
+            Ok(Some((message, charge))) => {
+                self.observe_read(&message).await;
+                Ok(Some((message, charge)))
+            }
+            Err(error @ SdkError::Process(_)) => match self.recover(&transport, &error).await? {
+                Some(recovered) => Ok(Some((recovered, None))),
+                None => Err(error),
+            },
+            other => other,
+        }
+    }
+
+    async fn end_input(&self) -> Result<(), SdkError> {
+        self.current().await.end_input().await
+    }
+
+    async fn close(&self) -> Result<(), SdkError> {
+        self.state.lock().await.closed = true;
+        self.current().await.close().await
+    }
+
+    fn is_ready(&self) -> bool {
+        match self.current.try_read() {
+            Ok(current) => current.is_ready(),
+            Err(_) => false,
+        }
+    }
+
+    fn subscribe_stderr(&self) -> Option<broadcast::Receiver<StderrEvent>> {
+        self.current.try_read().ok()?.subscribe_stderr()
+    }
+
+    async fn diagnostics(&self) -> Option<ConnectionDiagnostics> {
+        self.current().await.diagnostics().await
+    }
+
+    async fn recent_stderr(&self) -> Vec<String> {
+        self.current().await.recent_stderr().await
+    }
+
+    async fn process_info(&self) -> Option<ProcessInfo> {
+        self.current().await.process_info().await
+    }
+}
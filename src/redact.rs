@@ -0,0 +1,163 @@
+//! Masking of credentials in logs, Debug output and error messages.
+//!
+//! A [`Redactor`] replaces known secret values and anything matching
+//! user-supplied regular expressions with [`MASK`]. The SDK applies the
+//! redactor from [`ClaudeAgentOptions::effective_redactor`] to CLI stderr,
+//! control frame records, JSON decode errors, debug bundles and the `Debug`
+//! output of the options themselves.
+//!
+//! [`ClaudeAgentOptions::effective_redactor`]: crate::config::ClaudeAgentOptions::effective_redactor
+
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Arc;
+
+use regex::Regex;
+use serde_json::Value;
+
+use crate::error::SdkError;
+
+/// Replacement text for redacted values.
+pub const MASK: &str = "[REDACTED]";
+
+/// Environment variables whose values are always treated as secrets.
+pub const SECRET_ENV_VARS: &[&str] = &[
+    "ANTHROPIC_API_KEY",
+    "ANTHROPIC_AUTH_TOKEN",
+    "CLAUDE_CODE_OAUTH_TOKEN",
+    "AWS_SECRET_ACCESS_KEY",
+    "AWS_SESSION_TOKEN",
+    "AWS_BEARER_TOKEN_BEDROCK",
+];
+
+/// Anthropic API keys and OAuth tokens share the `sk-ant-` prefix.
+const ANTHROPIC_KEY_PATTERN: &str = r"sk-ant-[A-Za-z0-9_\-]{8,}";
+
+/// Secrets shorter than this are ignored; masking them would mangle ordinary text.
+const MIN_SECRET_LEN: usize = 4;
+
+/// Masks secrets and pattern matches in text and JSON values.
+#[derive(Clone, Default)]
+pub struct Redactor {
+    secrets: Vec<String>,
+    patterns: Vec<Regex>,
+}
+
+/// Convenient handle for sharing a redactor.
+pub type RedactorHandle = Arc<Redactor>;
+
+impl Redactor {
+    /// Create a redactor that masks nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Redactor masking the values of [`SECRET_ENV_VARS`] found in the process
+    /// environment or in `env`, plus anything that looks like an Anthropic key.
+    pub fn with_defaults(env: &HashMap<String, String>) -> Self {
+        let mut redactor = Self::new();
+        redactor.extend_defaults(env);
+        redactor
+    }
+
+    /// Mask every occurrence of `secret`.
+    pub fn with_secret(mut self, secret: impl Into<String>) -> Self {
+        self.add_secret(secret.into());
+        self
+    }
+
+    /// Mask every match of the regular expression `pattern`.
+    pub fn with_pattern(mut self, pattern: &str) -> Result<Self, SdkError> {
+        let regex = Regex::new(pattern).map_err(|err| {
+            SdkError::InvalidConfig(format!("invalid redaction pattern {pattern:?}: {err}"))
+        })?;
+        self.patterns.push(regex);
+        Ok(self)
+    }
+
+    /// Whether this redactor has nothing to mask.
+    pub fn is_empty(&self) -> bool {
+        self.secrets.is_empty() && self.patterns.is_empty()
+    }
+
+    /// Copy of `text` with secrets masked; borrows when nothing matched.
+    pub fn redact<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        let mut output = Cow::Borrowed(text);
+        for secret in &self.secrets {
+            if output.contains(secret.as_str()) {
+                output = Cow::Owned(output.replace(secret.as_str(), MASK));
+            }
+        }
+        for pattern in &self.patterns {
+            if let Cow::Owned(replaced) = pattern.replace_all(&output, MASK) {
+                output = Cow::Owned(replaced);
+            }
+        }
+        output
+    }
+
+    /// Mask secrets in every string (and object key) of `value`, in place.
+    pub fn redact_value(&self, value: &mut Value) {
+        if self.is_empty() {
+            return;
+        }
+        match value {
+            Value::String(text) => {
+                if let Cow::Owned(redacted) = self.redact(text) {
+                    *text = redacted;
+                }
+            }
+            Value::Array(items) => items.iter_mut().for_each(|item| self.redact_value(item)),
+            Value::Object(map) => {
+                let entries = std::mem::take(map);
+                for (key, mut item) in entries {
+                    self.redact_value(&mut item);
+                    map.insert(self.redact(&key).into_owned(), item);
+                }
+            }
+            _ => {}
+        }
+    }
+
+    pub(crate) fn extend_defaults(&mut self, env: &HashMap<String, String>) {
+        for name in SECRET_ENV_VARS {
+            if let Ok(value) = std::env::var(name) {
+                self.add_secret(value);
+            }
+            if let Some(value) = env.get(*name) {
+                self.add_secret(value.clone());
+            }
+        }
+        if !self
+            .patterns
+            .iter()
+            .any(|pattern| pattern.as_str() == ANTHROPIC_KEY_PATTERN)
+        {
+            self.patterns
+                .push(Regex::new(ANTHROPIC_KEY_PATTERN).expect("built-in pattern is valid"));
+        }
+    }
+
+    fn add_secret(&mut self, secret: String) {
+        let secret = secret.trim().to_string();
+        if secret.len() >= MIN_SECRET_LEN && !self.secrets.contains(&secret) {
+            self.secrets.push(secret);
+            // Longest first so a secret containing another is masked whole.
+            self.secrets
+                .sort_by_key(|secret| std::cmp::Reverse(secret.len()));
+        }
+    }
+}
+
+impl fmt::Debug for Redactor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Redactor")
+            .field("secrets", &self.secrets.len())
+            .field(
+                "patterns",
+                &self.patterns.iter().map(Regex::as_str).collect::<Vec<_>>(),
+            )
+            .finish()
+    }
+}
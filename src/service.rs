@@ -0,0 +1,178 @@
+//! [`tower::Service`](tower_service::Service) over the one-shot query path
+//! (`tower` feature).
+//!
+//! [`QueryService`] runs each [`QueryRequest`] through [`query`] and
+//! collects the messages into a [`QueryResponse`], so timeouts, retries,
+//! rate limits and load shedding from the tower ecosystem can wrap agent
+//! calls:
+//!
+//! ```ignore
+//! use std::time::Duration;
+//! use tower::{ServiceBuilder, ServiceExt};
+//! use sdk_claude_rust::service::{QueryRequest, QueryService};
+//!
+//! let service = ServiceBuilder::new()
+//!     .concurrency_limit(4)
+//!     .timeout(Duration::from_secs(120))
+//!     .service(QueryService::new(options));
+//! let response = service.oneshot(QueryRequest::new("Summarize README.md")).await?;
+//! println!("{}", response.text());
+//! ```
+//!
+//! The service is always ready; each call starts its own CLI process.
+//! Limit results fail the call as [`SdkError::MaxTurns`] or
+//! [`SdkError::BudgetExceeded`], like [`query`]; other error results are
+//! returned as responses with [`QueryResponse::is_error`] set.
+
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+
+use futures::StreamExt;
+
+use crate::client::DynTransport;
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, ResultMessage};
+use crate::query::query;
+
+/// One prompt for [`QueryService`].
+#[derive(Debug, Clone)]
+pub struct QueryRequest {
+    pub prompt: String,
+    /// Options for this call instead of the service defaults.
+    pub options: Option<ClaudeAgentOptions>,
+}
+
+impl QueryRequest {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            options: None,
+        }
+    }
+
+    pub fn with_options(mut self, options: ClaudeAgentOptions) -> Self {
+        self.options = Some(options);
+        self
+    }
+}
+
+/// Every message of a finished query.
+#[derive(Debug, Clone)]
+pub struct QueryResponse {
+    pub messages: Vec<Message>,
+}
+
+impl QueryResponse {
+    /// The final result message, if the CLI sent one.
+    pub fn result(&self) -> Option<&ResultMessage> {
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Result(result) => Some(result),
+                _ => None,
+            })
+    }
+
+    pub fn is_error(&self) -> bool {
+        self.result().is_some_and(|result| result.is_error)
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.result().map(|result| result.session_id.as_str())
+    }
+
+    /// The result text, or the text of the last assistant message when the
+    /// result carries none.
+    pub fn text(&self) -> String {
+        if let Some(text) = self
+            .result()
+            .and_then(|result| result.result.as_deref())
+            .filter(|text| !text.is_empty())
+        {
+            return text.to_string();
+        }
+        self.messages
+            .iter()
+            .rev()
+            .find_map(|message| match message {
+                Message::Assistant(assistant) => {
+                    let text: String = assistant
+                        .content
+                        .iter()
+                        .filter_map(|block| match block {
+                            ContentBlock::Text(block) => Some(block.text.as_str()),
+                            _ => None,
+                        })
+                        .collect();
+                    (!text.is_empty()).then_some(text)
+                }
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+}
+
+type TransportFactory = Arc<dyn Fn() -> DynTransport + Send + Sync>;
+
+/// Runs one-shot queries as a tower service.
+#[derive(Clone)]
+pub struct QueryService {
+    options: ClaudeAgentOptions,
+    transport: Option<TransportFactory>,
+}
+
+impl std::fmt::Debug for QueryService {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("QueryService")
+            .field("options", &self.options)
+            .field("has_transport", &self.transport.is_some())
+            .finish()
+    }
+}
+
+impl QueryService {
+    /// Service using `options` for requests that carry none.
+    pub fn new(options: ClaudeAgentOptions) -> Self {
+        Self {
+            options,
+            transport: None,
+        }
+    }
+
+    /// Build a fresh transport for every call instead of spawning the CLI.
+    pub fn with_transport<F>(mut self, factory: F) -> Self
+    where
+        F: Fn() -> DynTransport + Send + Sync + 'static,
+    {
+        self.transport = Some(Arc::new(factory));
+        self
+    }
+}
+
+impl tower_service::Service<QueryRequest> for QueryService {
+    type Response = QueryResponse;
+    type Error = SdkError;
+    type Future = Pin<Box<dyn Future<Output = Result<QueryResponse, SdkError>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), SdkError>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: QueryRequest) -> Self::Future {
+        let options = request.options.unwrap_or_else(|| self.options.clone());
+        let transport = self.transport.as_ref().map(|factory| factory());
+        Box::pin(async move {
+            let stream = query(request.prompt, Some(options), transport).await?;
+            futures::pin_mut!(stream);
+            let mut messages = Vec::new();
+            while let Some(message) = stream.next().await {
+                messages.push(message?);
+            }
+            Ok(QueryResponse { messages })
+        })
+    }
+}
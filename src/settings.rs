@@ -0,0 +1,264 @@
+//! Reading, merging and writing Claude Code `settings.json` documents.
+//!
+//! [`Settings`] types the sections SDK tools usually provision —
+//! `permissions`, `hooks` and `env` — and keeps every other key as JSON, so
+//! a document round-trips without losing settings this crate does not know
+//! about. Build one in code, [`Settings::merge`] it over an existing file
+//! and [`Settings::save`] it, or pass it to a session directly with
+//! [`Settings::apply_to`].
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::hooks::HookEvent;
+
+/// A `settings.json` document.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Settings {
+    #[serde(default, skip_serializing_if = "PermissionSettings::is_empty")]
+    pub permissions: PermissionSettings,
+    /// Matchers per hook event name (`PreToolUse`, `Stop`, ...).
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub hooks: BTreeMap<String, Vec<HookCommandMatcher>>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub env: BTreeMap<String, String>,
+    /// Every other setting (`model`, `statusLine`, ...), untouched.
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+/// The `permissions` section.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PermissionSettings {
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub allow: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub deny: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ask: Vec<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub additional_directories: Vec<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_mode: Option<String>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl PermissionSettings {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+}
+
+/// Commands run for tool names matching `matcher`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HookCommandMatcher {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub matcher: Option<String>,
+    pub hooks: Vec<HookCommand>,
+}
+
+/// A shell command hook.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HookCommand {
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub command: String,
+    /// Seconds before the command is cancelled.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timeout: Option<u64>,
+    #[serde(flatten)]
+    pub other: Map<String, Value>,
+}
+
+impl HookCommand {
+    pub fn new(command: impl Into<String>) -> Self {
+        Self {
+            kind: "command".into(),
+            command: command.into(),
+            timeout: None,
+            other: Map::new(),
+        }
+    }
+
+    pub fn with_timeout(mut self, seconds: u64) -> Self {
+        self.timeout = Some(seconds);
+        self
+    }
+}
+
+impl Settings {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// `<root>/.claude/settings.json`, the shared project settings.
+    pub fn project_path(root: impl AsRef<Path>) -> PathBuf {
+        root.as_ref().join(".claude").join("settings.json")
+    }
+
+    /// `<root>/.claude/settings.local.json`, the uncommitted project settings.
+    pub fn local_path(root: impl AsRef<Path>) -> PathBuf {
+        root.as_ref().join(".claude").join("settings.local.json")
+    }
+
+    /// `~/.claude/settings.json`, when the home directory is known.
+    pub fn user_path() -> Option<PathBuf> {
+        crate::internal::home_dir().map(|home| home.join(".claude").join("settings.json"))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self, SdkError> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// [`Settings::load`], or empty settings if `path` does not exist.
+    pub fn load_or_default(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        match std::fs::read_to_string(path) {
+            Ok(json) => Self::from_json(&json),
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    /// Pretty-printed JSON with a trailing newline.
+    pub fn to_json(&self) -> Result<String, SdkError> {
+        let mut json = serde_json::to_string_pretty(self)?;
+        json.push('\n');
+        Ok(json)
+    }
+
+    /// Write the document, creating parent directories.
+    pub fn save(&self, path: impl AsRef<Path>) -> Result<(), SdkError> {
+        let path = path.as_ref();
+        if let Some(parent) = path
+            .parent()
+            .filter(|parent| !parent.as_os_str().is_empty())
+        {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    /// Pass these settings to a session as inline JSON through
+    /// [`ClaudeAgentOptions::settings`].
+    pub fn apply_to(&self, options: &mut ClaudeAgentOptions) -> Result<(), SdkError> {
+        options.settings = Some(serde_json::to_string(self)?);
+        Ok(())
+    }
+
+    pub fn allow(mut self, rule: impl Into<String>) -> Self {
+        push_unique(&mut self.permissions.allow, rule.into());
+        self
+    }
+
+    pub fn deny(mut self, rule: impl Into<String>) -> Self {
+        push_unique(&mut self.permissions.deny, rule.into());
+        self
+    }
+
+    pub fn ask(mut self, rule: impl Into<String>) -> Self {
+        push_unique(&mut self.permissions.ask, rule.into());
+        self
+    }
+
+    pub fn with_env(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        self.env.insert(key.into(), value.into());
+        self
+    }
+
+    /// Run `command` on `event` for tools matching `matcher`.
+    pub fn with_hook(
+        mut self,
+        event: HookEvent,
+        matcher: Option<&str>,
+        command: HookCommand,
+    ) -> Self {
+        let incoming = HookCommandMatcher {
+            matcher: matcher.map(str::to_string),
+            hooks: vec![command],
+        };
+        merge_matchers(
+            self.hooks.entry(event.as_str().to_string()).or_default(),
+            vec![incoming],
+        );
+        self
+    }
+
+    /// Layer `other` over these settings: permission rules and hook
+    /// commands are added without duplicates, `env` entries and scalar
+    /// settings from `other` win, and nested objects merge key by key.
+    pub fn merge(&mut self, other: Settings) {
+        let permissions = other.permissions;
+        for (target, rules) in [
+            (&mut self.permissions.allow, permissions.allow),
+            (&mut self.permissions.deny, permissions.deny),
+            (&mut self.permissions.ask, permissions.ask),
+            (
+                &mut self.permissions.additional_directories,
+                permissions.additional_directories,
+            ),
+        ] {
+            rules.into_iter().for_each(|rule| push_unique(target, rule));
+        }
+        if permissions.default_mode.is_some() {
+            self.permissions.default_mode = permissions.default_mode;
+        }
+        merge_objects(&mut self.permissions.other, permissions.other);
+
+        for (event, matchers) in other.hooks {
+            merge_matchers(self.hooks.entry(event).or_default(), matchers);
+        }
+        self.env.extend(other.env);
+        merge_objects(&mut self.other, other.other);
+    }
+
+    /// [`Settings::merge`] returning the result.
+    pub fn merged(mut self, other: Settings) -> Self {
+        self.merge(other);
+        self
+    }
+}
+
+fn push_unique<T: PartialEq>(list: &mut Vec<T>, item: T) {
+    if !list.contains(&item) {
+        list.push(item);
+    }
+}
+
+/// Matchers with the same pattern share one entry.
+fn merge_matchers(target: &mut Vec<HookCommandMatcher>, incoming: Vec<HookCommandMatcher>) {
+    for matcher in incoming {
+        match target
+            .iter_mut()
+            .find(|existing| existing.matcher == matcher.matcher)
+        {
+            Some(existing) => matcher
+                .hooks
+                .into_iter()
+                .for_each(|hook| push_unique(&mut existing.hooks, hook)),
+            None => target.push(matcher),
+        }
+    }
+}
+
+fn merge_objects(target: &mut Map<String, Value>, incoming: Map<String, Value>) {
+    for (key, value) in incoming {
+        match (target.get_mut(&key), value) {
+            (Some(Value::Object(existing)), Value::Object(value)) => merge_objects(existing, value),
+            (_, value) => {
+                target.insert(key, value);
+            }
+        }
+    }
+}
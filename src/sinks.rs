@@ -0,0 +1,424 @@
+//! Machine-readable run records for log pipelines.
+//!
+//! A [`JsonlSink`] turns each message of a run into one flat [`RunRecord`]
+//! and writes it as a JSON line. Records keep what dashboards and alerts
+//! query — event type, timestamp, session, tool names, turn counts and cost —
+//! rather than the CLI's wire format, which
+//! [`ClaudeSdkClient::receive_raw`](crate::client::ClaudeSdkClient::receive_raw)
+//! exposes instead:
+//!
+//! ```text
+//! {"event":"assistant","ts_ms":1700000000000,"session_id":"abc","model":"claude-sonnet-4-5","tools":["Read"]}
+//! {"event":"result","ts_ms":1700000004000,"session_id":"abc","subtype":"success","num_turns":2,"duration_ms":4000,"total_cost_usd":0.012,"cost_delta_usd":0.012}
+//! ```
+//!
+//! A [`TextSink`] writes the answer text itself into any [`AsyncWrite`] —
+//! a socket, file or pipe — flushing after every piece, optionally with
+//! ANSI styling for terminals.
+
+use std::collections::HashMap;
+use std::io::Write;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use futures::{Stream, StreamExt};
+use serde::Serialize;
+use serde_json::Value;
+use tokio::io::{AsyncWrite, AsyncWriteExt};
+
+use crate::clock::{default_clock, ClockHandle};
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, UserMessageContent};
+use crate::stream_ext::{MessageStreamExt, ThinkingPart};
+
+/// One normalized line written by a [`JsonlSink`].
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct RunRecord {
+    /// `user`, `assistant`, `system`, `result`, `stream_event` or `error`.
+    pub event: String,
+    /// Milliseconds since the Unix epoch.
+    pub ts_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    /// Subtype of system and result messages.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subtype: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    /// Tools called by an assistant message.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub tools: Vec<String>,
+    /// Tool results in a user message that report an error.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool_errors: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub parent_tool_use_id: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_error: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub num_turns: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub duration_ms: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total_cost_usd: Option<f64>,
+    /// Cost added since the session's previous result.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cost_delta_usd: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Writes a [`RunRecord`] per message as JSON lines.
+pub struct JsonlSink<W> {
+    writer: W,
+    clock: ClockHandle,
+    session_id: Option<String>,
+    /// Last total cost reported per session.
+    costs: HashMap<String, f64>,
+}
+
+impl<W> std::fmt::Debug for JsonlSink<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JsonlSink")
+            .field("session_id", &self.session_id)
+            .field("sessions", &self.costs.len())
+            .finish()
+    }
+}
+
+impl<W: Write> JsonlSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            clock: default_clock(),
+            session_id: None,
+            costs: HashMap::new(),
+        }
+    }
+
+    /// Timestamp records with `clock` instead of the system clock.
+    pub fn with_clock(mut self, clock: ClockHandle) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Build the record for `message` without writing it.
+    ///
+    /// Messages that carry no session id get the last one seen.
+    pub fn record_for(&mut self, message: &Message) -> RunRecord {
+        let mut record = RunRecord {
+            ts_ms: unix_millis(self.clock.wall_time()),
+            ..Default::default()
+        };
+        match message {
+            Message::User(user) => {
+                record.event = "user".into();
+                record.parent_tool_use_id = user.parent_tool_use_id.clone();
+                if let UserMessageContent::Blocks(blocks) = &user.content {
+                    let errors = blocks
+                        .iter()
+                        .filter(|block| {
+                            matches!(block, ContentBlock::ToolResult(result)
+                                if result.is_error == Some(true))
+                        })
+                        .count();
+                    record.tool_errors = (errors > 0).then_some(errors);
+                }
+            }
+            Message::Assistant(assistant) => {
+                record.event = "assistant".into();
+                record.model = Some(assistant.model.clone());
+                record.parent_tool_use_id = assistant.parent_tool_use_id.clone();
+                record.tools = assistant
+                    .content
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::ToolUse(tool) => Some(tool.name.clone()),
+                        _ => None,
+                    })
+                    .collect();
+            }
+            Message::System(system) => {
+                record.event = "system".into();
+                record.subtype = Some(system.subtype.clone());
+                record.session_id = system
+                    .data
+                    .get("session_id")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+                record.model = system
+                    .data
+                    .get("model")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+            }
+            Message::Result(result) => {
+                record.event = "result".into();
+                record.session_id = Some(result.session_id.clone());
+                record.subtype = Some(result.subtype.clone());
+                record.is_error = Some(result.is_error);
+                record.num_turns = Some(result.num_turns);
+                record.duration_ms = Some(result.duration_ms);
+                record.total_cost_usd = result.total_cost_usd;
+                if let Some(total) = result.total_cost_usd {
+                    let previous = self.costs.insert(result.session_id.clone(), total);
+                    record.cost_delta_usd = Some(total - previous.unwrap_or(0.0));
+                }
+            }
+            Message::StreamEvent(event) => {
+                record.event = "stream_event".into();
+                record.session_id = Some(event.session_id.clone());
+                record.parent_tool_use_id = event.parent_tool_use_id.clone();
+                record.subtype = event
+                    .event
+                    .get("type")
+                    .and_then(Value::as_str)
+                    .map(str::to_string);
+            }
+        }
+        match &record.session_id {
+            Some(session_id) => self.session_id = Some(session_id.clone()),
+            None => record.session_id = self.session_id.clone(),
+        }
+        record
+    }
+
+    /// Write the record for `message`.
+    pub fn write_message(&mut self, message: &Message) -> Result<(), SdkError> {
+        let record = self.record_for(message);
+        self.write_record(&record)
+    }
+
+    /// Write an `error` record for `error`.
+    pub fn write_error(&mut self, error: &SdkError) -> Result<(), SdkError> {
+        let record = RunRecord {
+            event: "error".into(),
+            ts_ms: unix_millis(self.clock.wall_time()),
+            session_id: self.session_id.clone(),
+            error: Some(error.to_string()),
+            ..Default::default()
+        };
+        self.write_record(&record)
+    }
+
+    pub fn write_record(&mut self, record: &RunRecord) -> Result<(), SdkError> {
+        serde_json::to_writer(&mut self.writer, record)?;
+        self.writer.write_all(b"\n")?;
+        Ok(())
+    }
+
+    /// Write a record for every message of `messages`, returning how many
+    /// were written.
+    ///
+    /// A stream error is written as an `error` record and then returned.
+    pub async fn consume<S>(&mut self, messages: S) -> Result<usize, SdkError>
+    where
+        S: Stream<Item = Result<Message, SdkError>>,
+    {
+        futures::pin_mut!(messages);
+        let mut written = 0;
+        while let Some(message) = messages.next().await {
+            match message {
+                Ok(message) => self.write_message(&message)?,
+                Err(error) => {
+                    self.write_error(&error)?;
+                    self.writer.flush()?;
+                    return Err(error);
+                }
+            }
+            written += 1;
+        }
+        self.writer.flush()?;
+        Ok(written)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+const ANSI_THINKING: &str = "\x1b[2;3m";
+const ANSI_TOOL: &str = "\x1b[36m";
+const ANSI_RESET: &str = "\x1b[0m";
+
+/// How a [`TextSink`] renders a response.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub enum TextRendering {
+    /// Answer text only.
+    #[default]
+    Plain,
+    /// Answer text plus dimmed thinking and a line per tool call, styled
+    /// with ANSI escapes.
+    Ansi,
+}
+
+/// Kind of text a [`TextSink`] wrote last.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Written {
+    Nothing,
+    Text,
+    Thinking,
+}
+
+/// Writes the text of a response into an [`AsyncWrite`], flushing after
+/// each piece.
+pub struct TextSink<W> {
+    writer: W,
+    rendering: TextRendering,
+    last: Written,
+    /// Whether an assistant message ended since the last text.
+    boundary: bool,
+    wrote_text: bool,
+    at_line_start: bool,
+}
+
+impl<W> std::fmt::Debug for TextSink<W> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("TextSink")
+            .field("rendering", &self.rendering)
+            .finish()
+    }
+}
+
+impl<W: AsyncWrite + Unpin> TextSink<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            rendering: TextRendering::Plain,
+            last: Written::Nothing,
+            boundary: false,
+            wrote_text: false,
+            at_line_start: true,
+        }
+    }
+
+    pub fn with_rendering(mut self, rendering: TextRendering) -> Self {
+        self.rendering = rendering;
+        self
+    }
+
+    /// Write `part`; text from separate assistant messages goes on separate
+    /// lines.
+    pub async fn write_part(&mut self, part: &ThinkingPart) -> Result<(), SdkError> {
+        let ansi = self.rendering == TextRendering::Ansi;
+        match part {
+            ThinkingPart::Text(text) => {
+                if self.last == Written::Thinking {
+                    self.write(ANSI_RESET).await?;
+                    self.start_line().await?;
+                } else if self.boundary {
+                    self.start_line().await?;
+                }
+                self.write(text).await?;
+                self.last = Written::Text;
+            }
+            ThinkingPart::Thinking(thinking) if ansi => {
+                if self.last != Written::Thinking {
+                    self.start_line().await?;
+                    self.write(ANSI_THINKING).await?;
+                }
+                self.write(thinking).await?;
+                self.last = Written::Thinking;
+            }
+            ThinkingPart::Thinking(_) => return Ok(()),
+            ThinkingPart::Message(Message::Assistant(assistant)) => {
+                self.end_thinking().await?;
+                self.boundary = true;
+                if ansi {
+                    for block in &assistant.content {
+                        if let ContentBlock::ToolUse(tool) = block {
+                            self.start_line().await?;
+                            self.write(ANSI_TOOL).await?;
+                            self.write(&format!("→ {}", tool.name)).await?;
+                            self.write(ANSI_RESET).await?;
+                            self.write("\n").await?;
+                        }
+                    }
+                }
+                return self.flush().await;
+            }
+            ThinkingPart::Message(_) => return Ok(()),
+        }
+        self.boundary = false;
+        self.flush().await
+    }
+
+    /// Write the text of every message of `messages`, up to and including
+    /// the first result, and return the messages.
+    ///
+    /// Output ends with a newline. A stream error is returned after what was
+    /// written so far is flushed.
+    pub async fn consume<S>(&mut self, messages: S) -> Result<Vec<Message>, SdkError>
+    where
+        S: Stream<Item = Result<Message, SdkError>>,
+    {
+        let mut collected = Vec::new();
+        let mut outcome = Ok(());
+        {
+            let parts = messages
+                .until_result()
+                .inspect(|message| {
+                    if let Ok(message) = message {
+                        collected.push(message.clone());
+                    }
+                })
+                .split_thinking();
+            futures::pin_mut!(parts);
+            while let Some(part) = parts.next().await {
+                match part {
+                    Ok(part) => self.write_part(&part).await?,
+                    Err(err) => {
+                        outcome = Err(err);
+                        break;
+                    }
+                }
+            }
+        }
+        self.end_thinking().await?;
+        self.start_line().await?;
+        self.flush().await?;
+        outcome.map(|()| collected)
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    async fn end_thinking(&mut self) -> Result<(), SdkError> {
+        if self.last == Written::Thinking {
+            self.write(ANSI_RESET).await?;
+            self.last = Written::Nothing;
+        }
+        Ok(())
+    }
+
+    /// Move to a new line unless nothing was written or a line just ended.
+    async fn start_line(&mut self) -> Result<(), SdkError> {
+        if self.wrote_text && !self.at_line_start {
+            self.write("\n").await?;
+        }
+        Ok(())
+    }
+
+    async fn write(&mut self, text: &str) -> Result<(), SdkError> {
+        if text.is_empty() {
+            return Ok(());
+        }
+        self.writer.write_all(text.as_bytes()).await?;
+        if !text.starts_with('\x1b') {
+            self.wrote_text = true;
+            self.at_line_start = text.ends_with('\n');
+        }
+        Ok(())
+    }
+
+    async fn flush(&mut self) -> Result<(), SdkError> {
+        self.writer.flush().await?;
+        Ok(())
+    }
+}
+
+fn unix_millis(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_millis() as u64)
+        .unwrap_or(0)
+}
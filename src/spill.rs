@@ -0,0 +1,183 @@
+//! Disk overflow for messages a slow consumer has not read yet.
+//!
+//! The query hands CLI output to the consumer through a bounded channel.
+//! When it fills, the reader waits, and once enough output piles up it
+//! stops reading from the CLI, which then blocks on its stdout pipe. With a
+//! [`MessageSpill`] set as [`ClaudeAgentOptions::message_spill`], messages
+//! that do not fit are appended to a file in the spill directory instead
+//! and read back, in order, once the consumer has drained the channel.
+//! Spilled messages no longer count against a
+//! [`BufferLimit`](crate::buffer_limit::BufferLimit).
+//!
+//! The file grows up to [`MessageSpill::max_bytes`]; beyond that the reader
+//! waits for the consumer as it would without a spill. The file is emptied
+//! whenever the consumer catches up and removed when the query is dropped.
+//!
+//! [`ClaudeAgentOptions::message_spill`]: crate::config::ClaudeAgentOptions::message_spill
+
+use std::collections::VecDeque;
+use std::fs::{File, OpenOptions};
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+
+use serde_json::Value;
+
+/// Spill files grow up to 1 GiB unless configured otherwise.
+pub const DEFAULT_SPILL_MAX_BYTES: u64 = 1 << 30;
+
+/// Where and how much queued CLI output may be written to disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MessageSpill {
+    dir: PathBuf,
+    max_bytes: u64,
+}
+
+impl MessageSpill {
+    /// Spill into `dir`, which is created when first needed.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_bytes: DEFAULT_SPILL_MAX_BYTES,
+        }
+    }
+
+    /// Spill into the system temporary directory.
+    pub fn temp() -> Self {
+        Self::new(std::env::temp_dir())
+    }
+
+    pub fn with_max_bytes(mut self, max_bytes: u64) -> Self {
+        self.max_bytes = max_bytes;
+        self
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    pub fn max_bytes(&self) -> u64 {
+        self.max_bytes
+    }
+}
+
+/// Item read back from a [`SpillQueue`].
+pub(crate) enum Spilled<T> {
+    /// A message that was written to disk.
+    Value(Value),
+    /// An item kept in memory to preserve its place in the queue.
+    Held(T),
+}
+
+enum Entry<T> {
+    Disk(u64),
+    Held(T),
+}
+
+/// Spill file, removed on drop.
+struct SpillFile {
+    path: PathBuf,
+    file: File,
+}
+
+impl Drop for SpillFile {
+    fn drop(&mut self) {
+        let _ = std::fs::remove_file(&self.path);
+    }
+}
+
+/// FIFO of messages on disk, interleaved with items that cannot be
+/// written there.
+pub(crate) struct SpillQueue<T> {
+    config: MessageSpill,
+    file: Option<SpillFile>,
+    entries: VecDeque<Entry<T>>,
+    read_pos: u64,
+    write_pos: u64,
+}
+
+impl<T> SpillQueue<T> {
+    pub(crate) fn new(config: MessageSpill) -> Self {
+        Self {
+            config,
+            file: None,
+            entries: VecDeque::new(),
+            read_pos: 0,
+            write_pos: 0,
+        }
+    }
+
+    pub(crate) fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Append `value` to the spill file. Returns `false`, writing nothing,
+    /// when the file would outgrow its cap; an empty file takes any message.
+    pub(crate) fn push_value(&mut self, value: &Value) -> io::Result<bool> {
+        let bytes = serde_json::to_vec(value)?;
+        let len = bytes.len() as u64;
+        if self.write_pos > 0 && self.write_pos + len > self.config.max_bytes {
+            return Ok(false);
+        }
+        let write_pos = self.write_pos;
+        let file = self.open()?;
+        file.seek(SeekFrom::Start(write_pos))?;
+        file.write_all(&bytes)?;
+        self.write_pos += len;
+        self.entries.push_back(Entry::Disk(len));
+        Ok(true)
+    }
+
+    /// Queue `item` in memory behind the spilled messages.
+    pub(crate) fn push_held(&mut self, item: T) {
+        self.entries.push_back(Entry::Held(item));
+    }
+
+    pub(crate) fn pop(&mut self) -> io::Result<Option<Spilled<T>>> {
+        let popped = match self.entries.pop_front() {
+            None => return Ok(None),
+            Some(Entry::Held(item)) => Spilled::Held(item),
+            Some(Entry::Disk(len)) => {
+                let read_pos = self.read_pos;
+                let file = self.open()?;
+                file.seek(SeekFrom::Start(read_pos))?;
+                let mut bytes = vec![0; len as usize];
+                file.read_exact(&mut bytes)?;
+                self.read_pos += len;
+                Spilled::Value(serde_json::from_slice(&bytes)?)
+            }
+        };
+        if self.read_pos == self.write_pos {
+            self.reset()?;
+        }
+        Ok(Some(popped))
+    }
+
+    /// Empty the file once every spilled message has been read.
+    fn reset(&mut self) -> io::Result<()> {
+        if self.write_pos > 0 {
+            if let Some(spill) = &self.file {
+                spill.file.set_len(0)?;
+            }
+        }
+        self.read_pos = 0;
+        self.write_pos = 0;
+        Ok(())
+    }
+
+    fn open(&mut self) -> io::Result<&mut File> {
+        if self.file.is_none() {
+            std::fs::create_dir_all(&self.config.dir)?;
+            let path = self.config.dir.join(format!(
+                "claude-sdk-spill-{}.jsonl",
+                crate::internal::unique_id()
+            ));
+            let file = OpenOptions::new()
+                .read(true)
+                .write(true)
+                .create_new(true)
+                .open(&path)?;
+            self.file = Some(SpillFile { path, file });
+        }
+        Ok(&mut self.file.as_mut().expect("spill file was just opened").file)
+    }
+}
@@ -0,0 +1,359 @@
+//! Combinators for streams of [`Message`]s.
+//!
+//! [`ClaudeSdkClient::receive_messages`](crate::client::ClaudeSdkClient::receive_messages)
+//! yields every message of the session, across queries. Each query ends with
+//! a [`ResultMessage`](crate::message::ResultMessage), so
+//! [`MessageStreamExt::split_responses`] cuts the stream there and yields one
+//! sub-stream per query:
+//!
+//! ```no_run
+//! # use futures::StreamExt;
+//! # use sdk_claude_rust::client::ClaudeSdkClient;
+//! # use sdk_claude_rust::stream_ext::MessageStreamExt;
+//! # async fn run(client: &mut ClaudeSdkClient) -> Result<(), sdk_claude_rust::error::SdkError> {
+//! client.query("Plan the change", "default").await?;
+//! client.query("Now implement it", "default").await?;
+//! let mut responses = client.receive_messages()?.split_responses();
+//! while let Some(mut response) = responses.next().await {
+//!     while let Some(message) = response.next().await {
+//!         println!("{:?}", message?);
+//!     }
+//!     println!("--- query finished ---");
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`MessageStreamExt::split_thinking`] separates extended thinking from the
+//! answer text, so reasoning can be rendered on its own.
+//!
+//! [`MessageStreamExt::check_stream_events`] drops replayed stream events
+//! and reports missing ones, see [`crate::partial`].
+
+use std::collections::VecDeque;
+use std::pin::Pin;
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+
+use futures::Stream;
+use serde_json::Value;
+
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message};
+use crate::partial::{PartialAssembler, StreamDiagnostic};
+
+/// Combinators available on every `Result<Message, SdkError>` stream.
+pub trait MessageStreamExt: Stream<Item = Result<Message, SdkError>> + Sized {
+    /// Yield messages up to and including the first [`Message::Result`].
+    fn until_result(self) -> UntilResult<Self> {
+        UntilResult {
+            inner: Box::pin(self),
+            done: false,
+        }
+    }
+
+    /// Yield one sub-stream per response, each ending with its
+    /// [`Message::Result`].
+    ///
+    /// Sub-streams read from this stream, so consume them in order: asking
+    /// for the next response discards whatever the previous one did not read.
+    fn split_responses(self) -> SplitResponses<Self> {
+        SplitResponses {
+            shared: Arc::new(Mutex::new(Shared {
+                inner: Box::pin(self),
+                current: 0,
+                peeked: None,
+                finished: false,
+            })),
+            next_index: 0,
+        }
+    }
+
+    /// Yield thinking and answer text as separate [`ThinkingPart`]s.
+    ///
+    /// With `include_partial_messages`, text comes from the streamed deltas
+    /// and the assistant message that completes them adds no parts again.
+    fn split_thinking(self) -> SplitThinking<Self> {
+        SplitThinking {
+            inner: Box::pin(self),
+            pending: VecDeque::new(),
+            streamed_text: false,
+            streamed_thinking: false,
+        }
+    }
+
+    /// Yield messages as [`CheckedMessage`]s, replacing duplicated stream
+    /// events with a diagnostic and reporting gaps before the event that
+    /// reveals them.
+    fn check_stream_events(self) -> CheckStreamEvents<Self> {
+        CheckStreamEvents {
+            inner: Box::pin(self),
+            assembler: PartialAssembler::new(),
+            pending: None,
+        }
+    }
+}
+
+impl<S> MessageStreamExt for S where S: Stream<Item = Result<Message, SdkError>> {}
+
+/// Stream returned by [`MessageStreamExt::until_result`].
+pub struct UntilResult<S> {
+    inner: Pin<Box<S>>,
+    done: bool,
+}
+
+impl<S> Stream for UntilResult<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    type Item = Result<Message, SdkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if self.done {
+            return Poll::Ready(None);
+        }
+        let next = futures::ready!(self.inner.as_mut().poll_next(cx));
+        match &next {
+            Some(Ok(Message::Result(_))) | None => self.done = true,
+            _ => {}
+        }
+        Poll::Ready(next)
+    }
+}
+
+struct Shared<S> {
+    inner: Pin<Box<S>>,
+    /// Index of the response the next message belongs to.
+    current: usize,
+    /// First message of `current`, read to find out whether it exists.
+    peeked: Option<Result<Message, SdkError>>,
+    finished: bool,
+}
+
+impl<S> Shared<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    /// Next message, moving on to the next response after a result.
+    fn poll_message(&mut self, cx: &mut Context<'_>) -> Poll<Option<Result<Message, SdkError>>> {
+        let next = match self.peeked.take() {
+            Some(next) => next,
+            None if self.finished => return Poll::Ready(None),
+            None => match futures::ready!(self.inner.as_mut().poll_next(cx)) {
+                Some(next) => next,
+                None => {
+                    self.finished = true;
+                    return Poll::Ready(None);
+                }
+            },
+        };
+        if matches!(next, Ok(Message::Result(_))) {
+            self.current += 1;
+        }
+        Poll::Ready(Some(next))
+    }
+}
+
+/// Stream returned by [`MessageStreamExt::split_responses`].
+pub struct SplitResponses<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+    next_index: usize,
+}
+
+impl<S> Stream for SplitResponses<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    type Item = ResponseStream<S>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let next_index = self.next_index;
+        {
+            let mut shared = self.shared.lock().expect("response stream lock poisoned");
+            // Skip what the previous response left unread.
+            while shared.current < next_index {
+                if futures::ready!(shared.poll_message(cx)).is_none() {
+                    return Poll::Ready(None);
+                }
+            }
+            if shared.peeked.is_none() {
+                if shared.finished {
+                    return Poll::Ready(None);
+                }
+                match futures::ready!(shared.inner.as_mut().poll_next(cx)) {
+                    Some(message) => shared.peeked = Some(message),
+                    None => {
+                        shared.finished = true;
+                        return Poll::Ready(None);
+                    }
+                }
+            }
+        }
+        self.next_index += 1;
+        Poll::Ready(Some(ResponseStream {
+            shared: self.shared.clone(),
+            index: next_index,
+        }))
+    }
+}
+
+/// The messages of one response, yielded by [`SplitResponses`].
+pub struct ResponseStream<S> {
+    shared: Arc<Mutex<Shared<S>>>,
+    index: usize,
+}
+
+impl<S> Stream for ResponseStream<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    type Item = Result<Message, SdkError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let mut shared = self.shared.lock().expect("response stream lock poisoned");
+        if shared.current != self.index {
+            return Poll::Ready(None);
+        }
+        shared.poll_message(cx)
+    }
+}
+
+/// A piece of a response, yielded by [`MessageStreamExt::split_thinking`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum ThinkingPart {
+    /// Reasoning, from a thinking delta or block.
+    Thinking(String),
+    /// Answer text, from a text delta or block.
+    Text(String),
+    /// Any other message, and assistant messages after their parts.
+    Message(Message),
+}
+
+/// Stream returned by [`MessageStreamExt::split_thinking`].
+pub struct SplitThinking<S> {
+    inner: Pin<Box<S>>,
+    pending: VecDeque<ThinkingPart>,
+    streamed_text: bool,
+    streamed_thinking: bool,
+}
+
+impl<S> SplitThinking<S> {
+    fn split(&mut self, message: Message) {
+        match &message {
+            Message::StreamEvent(event) => {
+                if let Some(part) = self.split_delta(&event.event) {
+                    self.pending.push_back(part);
+                    return;
+                }
+            }
+            Message::Assistant(assistant) => {
+                for block in &assistant.content {
+                    match block {
+                        ContentBlock::Thinking(thinking) if !self.streamed_thinking => self
+                            .pending
+                            .push_back(ThinkingPart::Thinking(thinking.thinking.clone())),
+                        ContentBlock::Text(text) if !self.streamed_text => self
+                            .pending
+                            .push_back(ThinkingPart::Text(text.text.clone())),
+                        _ => {}
+                    }
+                }
+                self.streamed_text = false;
+                self.streamed_thinking = false;
+            }
+            _ => {}
+        }
+        self.pending.push_back(ThinkingPart::Message(message));
+    }
+
+    fn split_delta(&mut self, event: &Value) -> Option<ThinkingPart> {
+        if event.get("type").and_then(Value::as_str) != Some("content_block_delta") {
+            return None;
+        }
+        let delta = event.get("delta")?;
+        match delta.get("type").and_then(Value::as_str)? {
+            "text_delta" => {
+                self.streamed_text = true;
+                Some(ThinkingPart::Text(delta.get("text")?.as_str()?.to_string()))
+            }
+            "thinking_delta" => {
+                self.streamed_thinking = true;
+                Some(ThinkingPart::Thinking(
+                    delta.get("thinking")?.as_str()?.to_string(),
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+impl<S> Stream for SplitThinking<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    type Item = Result<ThinkingPart, SdkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        loop {
+            if let Some(part) = self.pending.pop_front() {
+                return Poll::Ready(Some(Ok(part)));
+            }
+            match futures::ready!(self.inner.as_mut().poll_next(cx)) {
+                Some(Ok(message)) => self.split(message),
+                Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+                None => return Poll::Ready(None),
+            }
+        }
+    }
+}
+
+/// Item of [`MessageStreamExt::check_stream_events`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum CheckedMessage {
+    Message(Message),
+    Diagnostic(StreamDiagnostic),
+}
+
+/// Stream returned by [`MessageStreamExt::check_stream_events`].
+pub struct CheckStreamEvents<S> {
+    inner: Pin<Box<S>>,
+    assembler: PartialAssembler,
+    /// Message that follows the gap just reported.
+    pending: Option<Message>,
+}
+
+impl<S> CheckStreamEvents<S> {
+    /// The assembler, holding the partial text received so far.
+    pub fn assembler(&self) -> &PartialAssembler {
+        &self.assembler
+    }
+}
+
+impl<S> Stream for CheckStreamEvents<S>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    type Item = Result<CheckedMessage, SdkError>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        if let Some(message) = self.pending.take() {
+            return Poll::Ready(Some(Ok(CheckedMessage::Message(message))));
+        }
+        let message = match futures::ready!(self.inner.as_mut().poll_next(cx)) {
+            Some(Ok(message)) => message,
+            Some(Err(err)) => return Poll::Ready(Some(Err(err))),
+            None => return Poll::Ready(None),
+        };
+        let item = match self.assembler.observe(&message) {
+            Some(duplicate @ StreamDiagnostic::Duplicate { .. }) => {
+                CheckedMessage::Diagnostic(duplicate)
+            }
+            Some(gap) => {
+                self.pending = Some(message);
+                CheckedMessage::Diagnostic(gap)
+            }
+            None => CheckedMessage::Message(message),
+        };
+        Poll::Ready(Some(Ok(item)))
+    }
+}
@@ -0,0 +1,214 @@
+//! Running configured agents as subtasks of a client session.
+//!
+//! [`ClaudeSdkClient::spawn_subagent`](crate::client::ClaudeSdkClient::spawn_subagent)
+//! asks Claude to delegate a task to one of the
+//! [`AgentDefinition`](crate::config::AgentDefinition)s in
+//! [`ClaudeAgentOptions::agents`](crate::config::ClaudeAgentOptions::agents)
+//! through the Task tool, then follows the `Task` call by its tool use id:
+//! messages whose `parent_tool_use_id` points at it form the subagent's
+//! transcript, and the matching `tool_result` is its answer.
+//!
+//! [`ClaudeSdkClient::spawn_subagents`](crate::client::ClaudeSdkClient::spawn_subagents)
+//! starts several tasks from one prompt so they run in parallel, at most
+//! [`ClaudeAgentOptions::max_concurrent_subagents`](crate::config::ClaudeAgentOptions::max_concurrent_subagents)
+//! at a time.
+
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::time::Duration;
+
+use serde_json::Value;
+use tokio::time::Instant;
+
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, UserMessageContent};
+
+/// Subagents started per prompt when no limit is configured.
+pub const DEFAULT_MAX_CONCURRENT_SUBAGENTS: usize = 4;
+
+/// Name of the CLI tool that launches subagents.
+const TASK_TOOL: &str = "Task";
+
+/// A task for a named agent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SubagentTask {
+    pub agent: String,
+    pub task: String,
+}
+
+impl SubagentTask {
+    pub fn new(agent: impl Into<String>, task: impl Into<String>) -> Self {
+        Self {
+            agent: agent.into(),
+            task: task.into(),
+        }
+    }
+}
+
+/// Outcome of a subagent run.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SubagentResult {
+    pub agent: String,
+    /// Id of the `Task` tool call that ran the agent.
+    pub tool_use_id: String,
+    /// Text of the agent's final answer.
+    pub output: String,
+    pub is_error: bool,
+    /// Messages the agent produced, tagged with `tool_use_id` as parent.
+    pub messages: Vec<Message>,
+    pub duration: Duration,
+}
+
+/// Prompt asking Claude to launch `tasks` with the Task tool.
+pub(crate) fn prompt(tasks: &[SubagentTask]) -> String {
+    let mut prompt = String::from(
+        "Launch the following tasks with the Task tool, all at once, using the given \
+         subagent_type and passing the task text verbatim as the prompt. Do not do the \
+         work yourself; when the tasks finish, reply with a one-line acknowledgement.\n",
+    );
+    for (index, task) in tasks.iter().enumerate() {
+        let _ = write!(
+            prompt,
+            "\n{}. subagent_type: {}\n<task>\n{}\n</task>\n",
+            index + 1,
+            task.agent,
+            task.task
+        );
+    }
+    prompt
+}
+
+struct Run {
+    tool_use_id: String,
+    started: Instant,
+    messages: Vec<Message>,
+    output: Option<(String, bool, Duration)>,
+}
+
+/// Follows the Task calls made for one prompt.
+pub(crate) struct SubagentCollector {
+    tasks: Vec<SubagentTask>,
+    runs: Vec<Option<Run>>,
+    by_tool_use_id: HashMap<String, usize>,
+}
+
+impl SubagentCollector {
+    pub(crate) fn new(tasks: Vec<SubagentTask>) -> Self {
+        let runs = tasks.iter().map(|_| None).collect();
+        Self {
+            tasks,
+            runs,
+            by_tool_use_id: HashMap::new(),
+        }
+    }
+
+    pub(crate) fn observe(&mut self, message: &Message) {
+        let (parent, blocks): (_, &[ContentBlock]) = match message {
+            Message::Assistant(assistant) => (&assistant.parent_tool_use_id, &assistant.content),
+            Message::User(user) => match &user.content {
+                UserMessageContent::Blocks(blocks) => (&user.parent_tool_use_id, blocks),
+                UserMessageContent::Text(_) => (&user.parent_tool_use_id, &[]),
+            },
+            _ => return,
+        };
+        if let Some(index) = parent
+            .as_ref()
+            .and_then(|parent| self.by_tool_use_id.get(parent))
+        {
+            if let Some(run) = &mut self.runs[*index] {
+                run.messages.push(message.clone());
+            }
+            return;
+        }
+        if parent.is_some() {
+            // Task calls made inside other subagents are not ours.
+            return;
+        }
+        for block in blocks {
+            match block {
+                ContentBlock::ToolUse(tool) if tool.name == TASK_TOOL => {
+                    let agent = tool.input.get("subagent_type").and_then(Value::as_str);
+                    let task = tool.input.get("prompt").and_then(Value::as_str);
+                    if let Some(index) = self.claim(agent, task) {
+                        self.by_tool_use_id.insert(tool.id.clone(), index);
+                        self.runs[index] = Some(Run {
+                            tool_use_id: tool.id.clone(),
+                            started: Instant::now(),
+                            messages: Vec::new(),
+                            output: None,
+                        });
+                    }
+                }
+                ContentBlock::ToolResult(result) => {
+                    let Some(index) = self.by_tool_use_id.get(&result.tool_use_id) else {
+                        continue;
+                    };
+                    if let Some(run) = &mut self.runs[*index] {
+                        run.output = Some((
+                            result_text(result.content.as_ref()),
+                            result.is_error.unwrap_or(false),
+                            run.started.elapsed(),
+                        ));
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// Unstarted task for a Task call: the one with the same agent and task
+    /// text, else the first one for the agent.
+    fn claim(&self, agent: Option<&str>, task: Option<&str>) -> Option<usize> {
+        let agent = agent?;
+        let open = |index: &usize| self.runs[*index].is_none() && self.tasks[*index].agent == agent;
+        let task = task.map(str::trim);
+        (0..self.tasks.len())
+            .filter(open)
+            .find(|index| Some(self.tasks[*index].task.trim()) == task)
+            .or_else(|| (0..self.tasks.len()).find(open))
+    }
+
+    /// Results in task order. Tasks that were never started or did not
+    /// report back are errors.
+    pub(crate) fn finish(self) -> Vec<Result<SubagentResult, SdkError>> {
+        self.tasks
+            .into_iter()
+            .zip(self.runs)
+            .map(|(task, run)| {
+                let Some(run) = run else {
+                    return Err(SdkError::Message(format!(
+                        "Claude did not start agent '{}' for the task",
+                        task.agent
+                    )));
+                };
+                let Some((output, is_error, duration)) = run.output else {
+                    return Err(SdkError::Message(format!(
+                        "agent '{}' ({}) did not return a result",
+                        task.agent, run.tool_use_id
+                    )));
+                };
+                Ok(SubagentResult {
+                    agent: task.agent,
+                    tool_use_id: run.tool_use_id,
+                    output,
+                    is_error,
+                    messages: run.messages,
+                    duration,
+                })
+            })
+            .collect()
+    }
+}
+
+fn result_text(content: Option<&Value>) -> String {
+    match content {
+        None => String::new(),
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(items)) => items
+            .iter()
+            .filter_map(|item| item.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join("\n"),
+        Some(other) => other.to_string(),
+    }
+}
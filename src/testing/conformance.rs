@@ -0,0 +1,316 @@
+//! Control protocol conformance with the Python SDK.
+//!
+//! A case is a JSON file describing one control protocol exchange as the
+//! Python SDK performs it: the callbacks it was configured with and every
+//! frame, in order, that the SDK wrote (`sdk`) or the CLI sent (`cli`).
+//!
+//! ```json
+//! {
+//!   "description": "allow with rewritten input",
+//!   "setup": {
+//!     "can_use_tool": {"behavior": "allow", "updatedInput": {"command": "ls -la"}},
+//!     "hooks": {"PreToolUse": [{"matcher": "Bash", "output": {"decision": "block"}}]}
+//!   },
+//!   "exchange": [
+//!     {"sdk": {"type": "control_request", "request_id": "$init", "request": {"subtype": "initialize", "hooks": null}}},
+//!     {"cli": {"type": "control_response", "response": {"subtype": "success", "request_id": "$init", "response": {}}}}
+//!   ]
+//! }
+//! ```
+//!
+//! [`ConformanceCase::run`] configures a [`ClaudeSdkClient`] the same way,
+//! feeds it the `cli` frames and requires each `sdk` frame to match what the
+//! client writes next, byte for byte once keys are sorted. Strings starting
+//! with `$` stand for values the SDK generates, such as request ids: the
+//! first `sdk` frame that holds one binds it, later frames must repeat it,
+//! and `cli` frames get the bound value substituted.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde::Deserialize;
+use serde_json::{Map, Value};
+
+use super::MockTransport;
+use crate::client::{ClaudeSdkClient, DynTransport};
+use crate::config::ClaudeAgentOptions;
+use crate::error::SdkError;
+use crate::hooks::{HookContext, HookEvent, HookInput, HookJsonOutput, HookMatcher};
+use crate::permission::{PermissionResult, ToolPermissionContext};
+
+/// Time allowed for the SDK to write each expected frame.
+const FRAME_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// One recorded exchange.
+#[derive(Debug, Clone, Deserialize)]
+pub struct ConformanceCase {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub setup: ConformanceSetup,
+    pub exchange: Vec<ConformanceStep>,
+}
+
+/// Callbacks the recording SDK was configured with.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConformanceSetup {
+    /// Result every permission request gets.
+    #[serde(default)]
+    pub can_use_tool: Option<PermissionResult>,
+    /// Hook matchers per event, each answering with a fixed output.
+    #[serde(default)]
+    pub hooks: HashMap<HookEvent, Vec<HookFixture>>,
+}
+
+/// A hook matcher whose callback returns `output`.
+#[derive(Debug, Clone, Deserialize)]
+pub struct HookFixture {
+    #[serde(default)]
+    pub matcher: Option<Value>,
+    pub output: HookJsonOutput,
+}
+
+/// A frame written by one side of the exchange.
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConformanceStep {
+    Sdk(Value),
+    Cli(Value),
+}
+
+impl ConformanceCase {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    /// Options carrying the case's callbacks.
+    pub fn options(&self) -> ClaudeAgentOptions {
+        let mut options = ClaudeAgentOptions::default();
+        if let Some(result) = self.setup.can_use_tool.clone() {
+            options.can_use_tool = Some(Arc::new(
+                move |_: &str, _: Map<String, Value>, _: ToolPermissionContext| {
+                    let result = result.clone();
+                    async move { result }
+                },
+            ));
+        }
+        if !self.setup.hooks.is_empty() {
+            let hooks = self
+                .setup
+                .hooks
+                .iter()
+                .map(|(event, fixtures)| {
+                    let matchers = fixtures.iter().map(HookFixture::matcher).collect();
+                    (*event, matchers)
+                })
+                .collect();
+            options.hooks = Some(hooks);
+        }
+        options
+    }
+
+    /// Replay the exchange against a client, describing the first mismatch.
+    pub async fn run(&self) -> Result<(), String> {
+        let transport = MockTransport::new();
+        transport.set_keep_open(true);
+        transport.set_withhold_control_responses(true);
+        let mut client = ClaudeSdkClient::new(
+            Some(self.options()),
+            Some(transport.clone() as DynTransport),
+        );
+
+        let mut bindings = Map::new();
+        let outcome = {
+            let connect = client.connect(None);
+            let replay = self.replay(&transport, &mut bindings);
+            tokio::pin!(connect, replay);
+            let mut connected = None;
+            let replayed = loop {
+                tokio::select! {
+                    result = &mut connect, if connected.is_none() => connected = Some(result),
+                    replayed = &mut replay => break replayed,
+                }
+            };
+            match (replayed, connected) {
+                (Err(message), _) => Err(message),
+                (Ok(()), Some(result)) => result.map_err(|err| format!("connect failed: {err}")),
+                (Ok(()), None) => match tokio::time::timeout(FRAME_TIMEOUT, connect).await {
+                    Ok(result) => result.map_err(|err| format!("connect failed: {err}")),
+                    Err(_) => Err("connect did not finish after the exchange".into()),
+                },
+            }
+        };
+        let written = transport.writes().await.len();
+        let _ = client.disconnect().await;
+        outcome?;
+
+        let expected = self.sdk_frames();
+        if written > expected {
+            let extra = &transport.writes().await[expected..written];
+            return Err(format!(
+                "the SDK wrote {} unexpected frame(s), first: {}",
+                extra.len(),
+                canonical(&extra[0])
+            ));
+        }
+        Ok(())
+    }
+
+    fn sdk_frames(&self) -> usize {
+        self.exchange
+            .iter()
+            .filter(|step| matches!(step, ConformanceStep::Sdk(_)))
+            .count()
+    }
+
+    async fn replay(
+        &self,
+        transport: &MockTransport,
+        bindings: &mut Map<String, Value>,
+    ) -> Result<(), String> {
+        let mut cursor = 0;
+        for (index, step) in self.exchange.iter().enumerate() {
+            let step_number = index + 1;
+            match step {
+                ConformanceStep::Cli(frame) => {
+                    let frame = substitute(frame, bindings)
+                        .map_err(|name| format!("step {step_number}: {name} is not bound yet"))?;
+                    transport.enqueue_read(Ok(Some(frame))).await;
+                }
+                ConformanceStep::Sdk(expected) => {
+                    let actual = next_write(transport, cursor).await.ok_or_else(|| {
+                        format!(
+                            "step {step_number}: the SDK wrote nothing, expected {}",
+                            canonical(expected)
+                        )
+                    })?;
+                    cursor += 1;
+                    let expected = bind(expected, &actual, bindings);
+                    let (expected, actual) = (canonical(&expected), canonical(&actual));
+                    if expected != actual {
+                        return Err(format!(
+                            "step {step_number}:\n  expected {expected}\n  actual   {actual}"
+                        ));
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl HookFixture {
+    fn matcher(&self) -> HookMatcher {
+        let mut matcher = HookMatcher::new(self.matcher.clone());
+        let output = self.output.clone();
+        matcher.hooks.push(Arc::new(
+            move |_: HookInput, _: Option<String>, _: HookContext| {
+                let output = output.clone();
+                async move { output }
+            },
+        ));
+        matcher
+    }
+}
+
+/// Load and run the case at `path`, prefixing failures with its file name.
+pub async fn check_conformance(path: impl AsRef<Path>) -> Result<(), String> {
+    let path = path.as_ref();
+    let case = ConformanceCase::load(path).map_err(|err| format!("{}: {err}", path.display()))?;
+    case.run()
+        .await
+        .map_err(|message| format!("{} ({}): {message}", path.display(), case.description))
+}
+
+async fn next_write(transport: &MockTransport, cursor: usize) -> Option<Value> {
+    let wait = async {
+        loop {
+            if let Some(frame) = transport.writes().await.get(cursor) {
+                return frame.clone();
+            }
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    };
+    tokio::time::timeout(FRAME_TIMEOUT, wait).await.ok()
+}
+
+fn placeholder(value: &Value) -> Option<&str> {
+    value.as_str().filter(|text| text.starts_with('$'))
+}
+
+/// `expected` with its placeholders replaced by their bound values, binding
+/// new ones to what `actual` holds at the same position.
+fn bind(expected: &Value, actual: &Value, bindings: &mut Map<String, Value>) -> Value {
+    if let Some(name) = placeholder(expected) {
+        return bindings
+            .entry(name.to_string())
+            .or_insert_with(|| actual.clone())
+            .clone();
+    }
+    match (expected, actual) {
+        (Value::Object(expected), Value::Object(actual)) => Value::Object(
+            expected
+                .iter()
+                .map(|(key, value)| {
+                    let bound = bind(value, actual.get(key).unwrap_or(&Value::Null), bindings);
+                    (key.clone(), bound)
+                })
+                .collect(),
+        ),
+        (Value::Array(expected), Value::Array(actual)) => Value::Array(
+            expected
+                .iter()
+                .enumerate()
+                .map(|(index, value)| {
+                    bind(value, actual.get(index).unwrap_or(&Value::Null), bindings)
+                })
+                .collect(),
+        ),
+        _ => expected.clone(),
+    }
+}
+
+/// `frame` with bound placeholders substituted; an unbound one is an error.
+fn substitute(frame: &Value, bindings: &Map<String, Value>) -> Result<Value, String> {
+    if let Some(name) = placeholder(frame) {
+        return bindings.get(name).cloned().ok_or_else(|| name.to_string());
+    }
+    Ok(match frame {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| Ok((key.clone(), substitute(value, bindings)?)))
+                .collect::<Result<_, String>>()?,
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| substitute(item, bindings))
+                .collect::<Result<_, String>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Compact JSON with object keys sorted at every level.
+fn canonical(value: &Value) -> String {
+    fn sorted(value: &Value) -> Value {
+        match value {
+            Value::Object(map) => {
+                let mut entries: Vec<_> = map.iter().collect();
+                entries.sort_by_key(|(key, _)| *key);
+                Value::Object(
+                    entries
+                        .into_iter()
+                        .map(|(key, value)| (key.clone(), sorted(value)))
+                        .collect(),
+                )
+            }
+            Value::Array(items) => Value::Array(items.iter().map(sorted).collect()),
+            other => other.clone(),
+        }
+    }
+    sorted(value).to_string()
+}
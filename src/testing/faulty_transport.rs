@@ -0,0 +1,188 @@
+//! Transport wrapper injecting failures on a deterministic schedule.
+
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::Value;
+use tokio::sync::broadcast;
+
+use crate::error::{
+    CliConnectionError, CliJsonDecodeError, ConnectionDiagnostics, ProcessError, SdkError,
+};
+use crate::transport::stderr::StderrEvent;
+use crate::transport::{ProcessInfo, Transport};
+
+/// Wraps a transport and injects faults at fixed points in its traffic.
+///
+/// Schedules count messages, control responses included: "after 2 reads"
+/// means the first two reads are delivered untouched and the third is affected. Faults that end the stream
+/// (EOF, process exit) stay in effect for every later read, as they would with
+/// a real CLI process.
+pub struct FaultyTransport<T: Transport + ?Sized> {
+    inner: Arc<T>,
+    read_delay: Option<Duration>,
+    truncated_reads: Vec<usize>,
+    eof_after: Option<usize>,
+    exit_after: Option<(usize, i32)>,
+    write_failure_after: Option<usize>,
+    reads: AtomicUsize,
+    writes: AtomicUsize,
+    ended: AtomicBool,
+}
+
+impl<T: Transport + ?Sized> FaultyTransport<T> {
+    /// Wrap `inner` with no faults scheduled.
+    pub fn new(inner: Arc<T>) -> Self {
+        Self {
+            inner,
+            read_delay: None,
+            truncated_reads: Vec::new(),
+            eof_after: None,
+            exit_after: None,
+            write_failure_after: None,
+            reads: AtomicUsize::new(0),
+            writes: AtomicUsize::new(0),
+            ended: AtomicBool::new(false),
+        }
+    }
+
+    /// Sleep before every read.
+    pub fn with_read_delay(mut self, delay: Duration) -> Self {
+        self.read_delay = Some(delay);
+        self
+    }
+
+    /// Deliver read number `index` (0-based) as a truncated JSON line, the way
+    /// a CLI killed mid-write would produce it.
+    pub fn with_truncated_read(mut self, index: usize) -> Self {
+        self.truncated_reads.push(index);
+        self
+    }
+
+    /// Report end of stream after `reads` messages.
+    pub fn with_eof_after(mut self, reads: usize) -> Self {
+        self.eof_after = Some(reads);
+        self
+    }
+
+    /// Report the CLI exiting with `exit_code` after `reads` messages.
+    pub fn with_exit_after(mut self, reads: usize, exit_code: i32) -> Self {
+        self.exit_after = Some((reads, exit_code));
+        self
+    }
+
+    /// Fail every write after the first `writes` succeed.
+    pub fn with_write_failure_after(mut self, writes: usize) -> Self {
+        self.write_failure_after = Some(writes);
+        self
+    }
+
+    /// Number of reads attempted so far.
+    pub fn reads(&self) -> usize {
+        self.reads.load(Ordering::SeqCst)
+    }
+
+    /// Number of writes attempted so far.
+    pub fn writes(&self) -> usize {
+        self.writes.load(Ordering::SeqCst)
+    }
+
+    /// The wrapped transport.
+    pub fn inner(&self) -> &Arc<T> {
+        &self.inner
+    }
+}
+
+#[async_trait]
+impl<T: Transport + ?Sized> Transport for FaultyTransport<T> {
+    async fn connect(&self) -> Result<(), SdkError> {
+        self.inner.connect().await
+    }
+
+    async fn write(&self, payload: &Value) -> Result<(), SdkError> {
+        let index = self.writes.fetch_add(1, Ordering::SeqCst);
+        if self.write_failure_after.is_some_and(|limit| index >= limit) {
+            return Err(CliConnectionError::new(format!(
+                "injected write failure on write {}",
+                index + 1
+            ))
+            .into());
+        }
+        self.inner.write(payload).await
+    }
+
+    async fn read(&self) -> Result<Option<Value>, SdkError> {
+        if let Some(delay) = self.read_delay {
+            tokio::time::sleep(delay).await;
+        }
+        let index = self.reads.fetch_add(1, Ordering::SeqCst);
+        if let Some((limit, exit_code)) = self.exit_after {
+            if index >= limit {
+                self.ended.store(true, Ordering::SeqCst);
+                return Err(ProcessError::new(
+                    "Command failed",
+                    Some(exit_code),
+                    Some("injected process exit".to_string()),
+                )
+                .into());
+            }
+        }
+        if self.ended.load(Ordering::SeqCst) || self.eof_after.is_some_and(|limit| index >= limit) {
+            self.ended.store(true, Ordering::SeqCst);
+            return Ok(None);
+        }
+
+        let value = self.inner.read().await?;
+        match value {
+            Some(value) if self.truncated_reads.contains(&index) => {
+                let line = value.to_string();
+                let cut = line.len() / 2;
+                let cut = (0..=cut)
+                    .rev()
+                    .find(|cut| line.is_char_boundary(*cut))
+                    .unwrap_or(0);
+                let truncated = &line[..cut];
+                let source = serde_json::from_str::<Value>(truncated)
+                    .err()
+                    .unwrap_or_else(|| {
+                        serde_json::Error::io(std::io::Error::new(
+                            std::io::ErrorKind::InvalidData,
+                            "truncated line",
+                        ))
+                    });
+                Err(CliJsonDecodeError::new(truncated, source).into())
+            }
+            other => Ok(other),
+        }
+    }
+
+    async fn end_input(&self) -> Result<(), SdkError> {
+        self.inner.end_input().await
+    }
+
+    async fn close(&self) -> Result<(), SdkError> {
+        self.inner.close().await
+    }
+
+    fn is_ready(&self) -> bool {
+        self.inner.is_ready()
+    }
+
+    fn subscribe_stderr(&self) -> Option<broadcast::Receiver<StderrEvent>> {
+        self.inner.subscribe_stderr()
+    }
+
+    async fn diagnostics(&self) -> Option<ConnectionDiagnostics> {
+        self.inner.diagnostics().await
+    }
+
+    async fn recent_stderr(&self) -> Vec<String> {
+        self.inner.recent_stderr().await
+    }
+
+    async fn process_info(&self) -> Option<ProcessInfo> {
+        self.inner.process_info().await
+    }
+}
@@ -0,0 +1,229 @@
+//! Snapshot tests over recorded CLI transcripts.
+//!
+//! A transcript is the CLI's stdout saved as JSONL, one message per line.
+//! [`GoldenTranscript::render`] turns it into a stable text snapshot of what
+//! the SDK makes of it:
+//!
+//! * every line's top-level keys and the typed output of
+//!   [`parse_message`](crate::internal::message_parser::parse_message), so a
+//!   new field or message type shows up as a diff;
+//! * the stream a one-shot [`query`](crate::query::query) yields when the
+//!   transcript is replayed through the `Query` router, so a line that would
+//!   end a real session early shows up as an error followed by the end of the
+//!   stream.
+//!
+//! [`assert_golden`] compares the rendering with the `.snap` file next to the
+//! transcript. Run with `SDK_UPDATE_GOLDEN=1` to write or refresh snapshots
+//! after reviewing the change.
+
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::Value;
+
+use super::MockTransport;
+use crate::error::{CliJsonDecodeError, SdkError};
+use crate::internal::message_parser::parse_message;
+use crate::message::Message;
+use crate::transport::Transport;
+
+/// Set to `1` to write snapshots instead of comparing against them.
+pub const UPDATE_ENV: &str = "SDK_UPDATE_GOLDEN";
+
+/// Extension of the snapshot stored next to each transcript.
+pub const SNAPSHOT_EXTENSION: &str = "snap";
+
+const CONTROL_TYPES: &[&str] = &[
+    "control_request",
+    "control_response",
+    "control_cancel_request",
+];
+
+/// A recorded CLI transcript.
+#[derive(Debug, Clone)]
+pub struct GoldenTranscript {
+    lines: Vec<Value>,
+}
+
+impl GoldenTranscript {
+    /// Transcript made of already-decoded lines.
+    pub fn new(lines: Vec<Value>) -> Self {
+        Self { lines }
+    }
+
+    /// Read a JSONL transcript; blank lines are skipped.
+    pub fn load(path: impl AsRef<Path>) -> Result<Self, SdkError> {
+        let contents = std::fs::read_to_string(path)?;
+        let lines = contents
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|err| CliJsonDecodeError::new(line, err).into())
+            })
+            .collect::<Result<_, SdkError>>()?;
+        Ok(Self { lines })
+    }
+
+    /// The raw transcript lines.
+    pub fn lines(&self) -> &[Value] {
+        &self.lines
+    }
+
+    /// Text snapshot of the parsed and routed transcript.
+    pub async fn render(&self) -> String {
+        let mut output = String::from("## parsed\n");
+        for (index, line) in self.lines.iter().enumerate() {
+            let _ = writeln!(output, "\n[{}] {}", index + 1, describe(line));
+            let _ = writeln!(output, "keys: {}", keys(line));
+            if let Some(message) = line.get("message").filter(|message| message.is_object()) {
+                let _ = writeln!(output, "message keys: {}", keys(message));
+            }
+            if is_control(line) {
+                output.push_str("control protocol message\n");
+                continue;
+            }
+            match parse_message(line) {
+                Ok(message) => {
+                    let _ = writeln!(output, "{message:#?}");
+                }
+                Err(err) => {
+                    let _ = writeln!(output, "parse error: {err}");
+                }
+            }
+        }
+
+        output.push_str("\n## routed\n");
+        for item in self.replay().await {
+            match item {
+                Ok(message) => {
+                    let _ = writeln!(output, "{}", summarize(&message));
+                }
+                Err(err) => {
+                    let _ = writeln!(output, "error ({:?}): {err}", err.kind());
+                }
+            }
+        }
+        output.push_str("end of stream\n");
+        output
+    }
+
+    /// Items a one-shot query yields when this transcript is the CLI output.
+    pub async fn replay(&self) -> Vec<Result<Message, SdkError>> {
+        let reads = self
+            .lines
+            .iter()
+            .cloned()
+            .map(|line| Ok(Some(line)))
+            .chain(std::iter::once(Ok(None)));
+        let transport: Arc<dyn Transport> = MockTransport::with_reads(reads);
+        match crate::query::query("golden transcript replay", None, Some(transport)).await {
+            Ok(stream) => stream.collect().await,
+            Err(err) => vec![Err(err)],
+        }
+    }
+}
+
+/// Path of the snapshot belonging to `transcript`.
+pub fn snapshot_path(transcript: &Path) -> PathBuf {
+    transcript.with_extension(SNAPSHOT_EXTENSION)
+}
+
+/// Compare the rendering of `transcript` with its snapshot.
+///
+/// With [`UPDATE_ENV`] set the snapshot is (re)written instead. The error
+/// describes the first differing line.
+pub async fn check_golden(transcript: impl AsRef<Path>) -> Result<(), String> {
+    let transcript = transcript.as_ref();
+    let rendered = GoldenTranscript::load(transcript)
+        .map_err(|err| format!("{}: {err}", transcript.display()))?
+        .render()
+        .await;
+    let snapshot = snapshot_path(transcript);
+
+    if std::env::var(UPDATE_ENV).is_ok_and(|value| value == "1") {
+        return std::fs::write(&snapshot, rendered)
+            .map_err(|err| format!("{}: {err}", snapshot.display()));
+    }
+
+    let expected = std::fs::read_to_string(&snapshot).map_err(|err| {
+        format!(
+            "{}: {err}; run with {UPDATE_ENV}=1 to create it",
+            snapshot.display()
+        )
+    })?;
+    if expected == rendered {
+        return Ok(());
+    }
+    let (line, expected_line, actual_line) = expected
+        .lines()
+        .map(Some)
+        .chain(std::iter::repeat(None))
+        .zip(rendered.lines().map(Some).chain(std::iter::repeat(None)))
+        .enumerate()
+        .find(|(_, (expected, actual))| expected != actual)
+        .map(|(index, (expected, actual))| (index + 1, expected, actual))
+        .unwrap_or((0, None, None));
+    Err(format!(
+        "{} differs from {} at line {line}:\n  expected: {}\n  actual:   {}\nrun with {UPDATE_ENV}=1 to accept the new output",
+        transcript.display(),
+        snapshot.display(),
+        expected_line.unwrap_or("<end of snapshot>"),
+        actual_line.unwrap_or("<end of output>"),
+    ))
+}
+
+/// [`check_golden`], panicking with the mismatch.
+pub async fn assert_golden(transcript: impl AsRef<Path>) {
+    if let Err(message) = check_golden(transcript).await {
+        panic!("golden transcript mismatch: {message}");
+    }
+}
+
+fn is_control(line: &Value) -> bool {
+    line.get("type")
+        .and_then(Value::as_str)
+        .is_some_and(|kind| CONTROL_TYPES.contains(&kind))
+}
+
+fn describe(line: &Value) -> String {
+    let kind = line
+        .get("type")
+        .and_then(Value::as_str)
+        .unwrap_or("<no type>");
+    match line.get("subtype").and_then(Value::as_str) {
+        Some(subtype) => format!("{kind}/{subtype}"),
+        None => kind.to_string(),
+    }
+}
+
+fn keys(value: &Value) -> String {
+    match value.as_object() {
+        // serde_json maps are ordered by key unless `preserve_order` is on.
+        Some(map) => {
+            let mut keys: Vec<&str> = map.keys().map(String::as_str).collect();
+            keys.sort_unstable();
+            keys.join(", ")
+        }
+        None => "<not an object>".to_string(),
+    }
+}
+
+fn summarize(message: &Message) -> String {
+    match message {
+        Message::User(_) => "user".to_string(),
+        Message::Assistant(assistant) => format!("assistant ({} blocks)", assistant.content.len()),
+        Message::System(system) => format!("system/{}", system.subtype),
+        Message::Result(result) => format!("result/{}", result.subtype),
+        Message::StreamEvent(event) => format!(
+            "stream_event/{}",
+            event
+                .event
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+        ),
+    }
+}
@@ -0,0 +1,395 @@
+//! Assertions over the messages a session yields.
+//!
+//! [`assert_stream_yields!`](crate::assert_stream_yields) drains a message
+//! stream and checks it against a list of patterns in one line:
+//!
+//! ```no_run
+//! # use sdk_claude_rust::assert_stream_yields;
+//! # async fn example(client: sdk_claude_rust::client::ClaudeSdkClient) {
+//! let stream = client.receive_response().unwrap();
+//! assert_stream_yields!(stream, [
+//!     System(init),
+//!     Assistant(tool_use "Bash"),
+//!     User(tool_result "toolu_1"),
+//!     Assistant(text ~ "hello"),
+//!     Result(success),
+//! ]);
+//! # }
+//! ```
+//!
+//! Patterns that the shorthand does not cover can be written as a
+//! [`MessageMatcher`] expression in braces, e.g. `{ matchers::any() }`.
+
+use std::fmt;
+use std::time::Duration;
+
+use futures::{pin_mut, Stream, StreamExt};
+use serde_json::Value;
+
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, UserMessageContent};
+
+/// How long [`assert_stream_yields`] waits for each message.
+pub const STREAM_ITEM_TIMEOUT: Duration = Duration::from_secs(5);
+
+type Predicate = Box<dyn Fn(&Message) -> bool + Send + Sync>;
+
+/// Predicate over a single message, with a description for failure output.
+pub struct MessageMatcher {
+    description: String,
+    predicate: Predicate,
+}
+
+impl MessageMatcher {
+    pub fn new(
+        description: impl Into<String>,
+        predicate: impl Fn(&Message) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            description: description.into(),
+            predicate: Box::new(predicate),
+        }
+    }
+
+    /// Whether `message` satisfies this matcher.
+    pub fn matches(&self, message: &Message) -> bool {
+        (self.predicate)(message)
+    }
+
+    pub fn description(&self) -> &str {
+        &self.description
+    }
+
+    /// Matcher requiring both `self` and `other`.
+    pub fn and(self, other: MessageMatcher) -> Self {
+        Self {
+            description: format!("{} and {}", self.description, other.description),
+            predicate: Box::new(move |message| self.matches(message) && other.matches(message)),
+        }
+    }
+}
+
+impl fmt::Debug for MessageMatcher {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_tuple("MessageMatcher")
+            .field(&self.description)
+            .finish()
+    }
+}
+
+/// Any message.
+pub fn any() -> MessageMatcher {
+    MessageMatcher::new("any message", |_| true)
+}
+
+/// Any assistant message.
+pub fn assistant() -> MessageMatcher {
+    MessageMatcher::new("Assistant", |message| {
+        matches!(message, Message::Assistant(_))
+    })
+}
+
+/// Assistant message with a text block containing `needle`.
+pub fn assistant_text(needle: impl Into<String>) -> MessageMatcher {
+    let needle = needle.into();
+    MessageMatcher::new(format!("Assistant(text ~ {needle:?})"), move |message| {
+        assistant_blocks(message).iter().any(|block| {
+            matches!(block, ContentBlock::Text(text) if text.text.contains(needle.as_str()))
+        })
+    })
+}
+
+/// Assistant message requesting the tool `name`.
+pub fn tool_use(name: impl Into<String>) -> MessageMatcher {
+    let name = name.into();
+    MessageMatcher::new(format!("Assistant(tool_use {name:?})"), move |message| {
+        assistant_blocks(message)
+            .iter()
+            .any(|block| matches!(block, ContentBlock::ToolUse(tool) if tool.name == name))
+    })
+}
+
+/// Assistant message requesting the tool `name` with an input containing
+/// every field of `input`.
+pub fn tool_use_with(name: impl Into<String>, input: Value) -> MessageMatcher {
+    let name = name.into();
+    MessageMatcher::new(
+        format!("Assistant(tool_use {name:?} with {input})"),
+        move |message| {
+            assistant_blocks(message).iter().any(|block| match block {
+                ContentBlock::ToolUse(tool) if tool.name == name => {
+                    json_contains(&Value::Object(tool.input.clone()), &input)
+                }
+                _ => false,
+            })
+        },
+    )
+}
+
+/// Any user message.
+pub fn user() -> MessageMatcher {
+    MessageMatcher::new("User", |message| matches!(message, Message::User(_)))
+}
+
+/// User message carrying the result of the tool call `tool_use_id`.
+pub fn tool_result(tool_use_id: impl Into<String>) -> MessageMatcher {
+    let tool_use_id = tool_use_id.into();
+    MessageMatcher::new(
+        format!("User(tool_result {tool_use_id:?})"),
+        move |message| {
+            let Message::User(user) = message else {
+                return false;
+            };
+            let UserMessageContent::Blocks(blocks) = &user.content else {
+                return false;
+            };
+            blocks.iter().any(|block| match block {
+                ContentBlock::ToolResult(result) => result.tool_use_id == tool_use_id,
+                _ => false,
+            })
+        },
+    )
+}
+
+/// Any system message.
+pub fn system() -> MessageMatcher {
+    MessageMatcher::new("System", |message| matches!(message, Message::System(_)))
+}
+
+/// System message with the given subtype.
+pub fn system_subtype(subtype: impl Into<String>) -> MessageMatcher {
+    let subtype = subtype.into();
+    MessageMatcher::new(
+        format!("System({subtype})"),
+        move |message| matches!(message, Message::System(system) if system.subtype == subtype),
+    )
+}
+
+/// Any result message.
+pub fn result() -> MessageMatcher {
+    MessageMatcher::new("Result", |message| matches!(message, Message::Result(_)))
+}
+
+/// Result message with the given subtype.
+pub fn result_subtype(subtype: impl Into<String>) -> MessageMatcher {
+    let subtype = subtype.into();
+    MessageMatcher::new(
+        format!("Result({subtype})"),
+        move |message| matches!(message, Message::Result(result) if result.subtype == subtype),
+    )
+}
+
+/// Result message reporting a failure, whatever its subtype.
+pub fn result_error() -> MessageMatcher {
+    MessageMatcher::new(
+        "Result(error)",
+        |message| matches!(message, Message::Result(result) if result.is_error),
+    )
+}
+
+/// Any partial stream event.
+pub fn stream_event() -> MessageMatcher {
+    MessageMatcher::new("StreamEvent", |message| {
+        matches!(message, Message::StreamEvent(_))
+    })
+}
+
+/// Check that `messages` match `matchers` one to one, in order.
+pub fn check_messages(messages: &[Message], matchers: &[MessageMatcher]) -> Result<(), String> {
+    let mismatch = messages.len() != matchers.len()
+        || messages
+            .iter()
+            .zip(matchers)
+            .any(|(message, matcher)| !matcher.matches(message));
+    if !mismatch {
+        return Ok(());
+    }
+
+    let mut report = format!(
+        "expected {} messages, got {}:",
+        matchers.len(),
+        messages.len()
+    );
+    for index in 0..messages.len().max(matchers.len()) {
+        let message = messages.get(index);
+        let matcher = matchers.get(index);
+        let marker = match (message, matcher) {
+            (Some(message), Some(matcher)) if matcher.matches(message) => "  ",
+            _ => "✗ ",
+        };
+        report.push_str(&format!(
+            "\n{marker}[{index}] expected {}, got {}",
+            matcher.map_or("nothing", MessageMatcher::description),
+            message.map_or_else(|| "nothing".to_string(), describe),
+        ));
+    }
+    Err(report)
+}
+
+/// [`check_messages`], panicking with the report.
+pub fn assert_messages(messages: &[Message], matchers: &[MessageMatcher]) {
+    if let Err(report) = check_messages(messages, matchers) {
+        panic!("{report}");
+    }
+}
+
+/// Drain `stream` and assert its messages match `matchers`; returns the
+/// messages for further checks.
+///
+/// # Panics
+///
+/// Panics if the stream yields an error, stalls for longer than
+/// [`STREAM_ITEM_TIMEOUT`], or its messages do not match.
+pub async fn assert_stream_yields<S>(stream: S, matchers: Vec<MessageMatcher>) -> Vec<Message>
+where
+    S: Stream<Item = Result<Message, SdkError>>,
+{
+    pin_mut!(stream);
+    let mut messages = Vec::new();
+    loop {
+        match tokio::time::timeout(STREAM_ITEM_TIMEOUT, stream.next()).await {
+            Ok(Some(Ok(message))) => messages.push(message),
+            Ok(Some(Err(err))) => panic!(
+                "stream yielded an error after {} messages: {err}",
+                messages.len()
+            ),
+            Ok(None) => break,
+            Err(_) => panic!(
+                "stream stalled after {} messages: {:?}",
+                messages.len(),
+                messages.iter().map(describe).collect::<Vec<_>>()
+            ),
+        }
+    }
+    assert_messages(&messages, &matchers);
+    messages
+}
+
+/// Assert that a message stream yields exactly the listed messages.
+///
+/// Drains the stream (so it must be used in an async context) and returns the
+/// collected messages. Each pattern is one of:
+///
+/// * `Assistant`, `Assistant(text ~ "needle")`, `Assistant(tool_use "Bash")`,
+///   `Assistant(tool_use "Bash", json!({...}))`
+/// * `User`, `User(tool_result "toolu_1")`
+/// * `System`, `System(init)`
+/// * `Result`, `Result(success)`, `Result(error)`, `Result(error_max_turns)`
+/// * `StreamEvent`, `_`
+/// * `{ matcher }` for any [`MessageMatcher`] expression
+///
+/// See the [`matchers`](crate::testing::matchers) module for an example.
+#[macro_export]
+macro_rules! assert_stream_yields {
+    ($stream:expr, [$($kind:tt $(($($args:tt)*))?),* $(,)?]) => {
+        $crate::testing::matchers::assert_stream_yields(
+            $stream,
+            vec![$($crate::__message_matcher!($kind $(($($args)*))?)),*],
+        )
+        .await
+    };
+}
+
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __message_matcher {
+    (_) => {
+        $crate::testing::matchers::any()
+    };
+    ({ $matcher:expr }) => {
+        $matcher
+    };
+    (Assistant) => {
+        $crate::testing::matchers::assistant()
+    };
+    (Assistant(text ~ $needle:expr)) => {
+        $crate::testing::matchers::assistant_text($needle)
+    };
+    (Assistant(tool_use $name:literal, $input:expr)) => {
+        $crate::testing::matchers::tool_use_with($name, $input)
+    };
+    (Assistant(tool_use $name:expr)) => {
+        $crate::testing::matchers::tool_use($name)
+    };
+    (User) => {
+        $crate::testing::matchers::user()
+    };
+    (User(tool_result $id:expr)) => {
+        $crate::testing::matchers::tool_result($id)
+    };
+    (System) => {
+        $crate::testing::matchers::system()
+    };
+    (System($subtype:ident)) => {
+        $crate::testing::matchers::system_subtype(stringify!($subtype))
+    };
+    (Result) => {
+        $crate::testing::matchers::result()
+    };
+    (Result(error)) => {
+        $crate::testing::matchers::result_error()
+    };
+    (Result($subtype:ident)) => {
+        $crate::testing::matchers::result_subtype(stringify!($subtype))
+    };
+    (StreamEvent) => {
+        $crate::testing::matchers::stream_event()
+    };
+}
+
+/// Whether `actual` has every field of `expected`, recursively.
+pub(crate) fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => expected.iter().all(|(key, value)| {
+            actual
+                .get(key)
+                .is_some_and(|actual| json_contains(actual, value))
+        }),
+        _ => actual == expected,
+    }
+}
+
+fn assistant_blocks(message: &Message) -> &[ContentBlock] {
+    match message {
+        Message::Assistant(assistant) => &assistant.content,
+        _ => &[],
+    }
+}
+
+fn describe(message: &Message) -> String {
+    let blocks = |blocks: &[ContentBlock]| {
+        blocks
+            .iter()
+            .map(|block| match block {
+                ContentBlock::Text(text) => format!("text {:?}", text.text),
+                ContentBlock::Thinking(_) => "thinking".to_string(),
+                ContentBlock::ToolUse(tool) => format!("tool_use {:?}", tool.name),
+                ContentBlock::ToolResult(result) => {
+                    format!("tool_result {:?}", result.tool_use_id)
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(", ")
+    };
+    match message {
+        Message::Assistant(assistant) => format!("Assistant[{}]", blocks(&assistant.content)),
+        Message::User(user) => match &user.content {
+            UserMessageContent::Text(text) => format!("User[{text:?}]"),
+            UserMessageContent::Blocks(content) => format!("User[{}]", blocks(content)),
+        },
+        Message::System(system) => format!("System({})", system.subtype),
+        Message::Result(result) => format!(
+            "Result({}{})",
+            result.subtype,
+            if result.is_error { ", is_error" } else { "" }
+        ),
+        Message::StreamEvent(event) => format!(
+            "StreamEvent({})",
+            event
+                .event
+                .get("type")
+                .and_then(Value::as_str)
+                .unwrap_or("unknown")
+        ),
+    }
+}
@@ -0,0 +1,198 @@
+//! In-memory [`Transport`] with scripted reads and recorded writes.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use serde_json::{json, Value};
+use tokio::sync::{Mutex, Notify};
+
+use crate::error::SdkError;
+use crate::transport::Transport;
+
+#[derive(Default)]
+struct MockTransportState {
+    reads: VecDeque<Result<Option<Value>, SdkError>>,
+    user_replies: VecDeque<Vec<Value>>,
+    writes: Vec<Value>,
+    connect_calls: usize,
+    end_input_calls: usize,
+    close_calls: usize,
+}
+
+/// Transport stub with queued reads and recorded writes.
+///
+/// Control requests written by the SDK are answered with an empty success
+/// response unless [`MockTransport::set_withhold_control_responses`] is set.
+#[derive(Default)]
+pub struct MockTransport {
+    state: Mutex<MockTransportState>,
+    ready: AtomicBool,
+    keep_open: AtomicBool,
+    withhold_control_responses: AtomicBool,
+    readable: Notify,
+}
+
+impl MockTransport {
+    /// Transport with nothing queued that reports end of stream when drained.
+    pub fn new() -> Arc<Self> {
+        Arc::new(Self {
+            state: Mutex::new(MockTransportState::default()),
+            ready: AtomicBool::new(true),
+            keep_open: AtomicBool::new(false),
+            withhold_control_responses: AtomicBool::new(false),
+            readable: Notify::new(),
+        })
+    }
+
+    /// Transport that yields `reads` in order.
+    pub fn with_reads<T>(reads: T) -> Arc<Self>
+    where
+        T: IntoIterator<Item = Result<Option<Value>, SdkError>>,
+    {
+        let state = MockTransportState {
+            reads: reads.into_iter().collect(),
+            ..Default::default()
+        };
+        Arc::new(Self {
+            state: Mutex::new(state),
+            ready: AtomicBool::new(true),
+            keep_open: AtomicBool::new(false),
+            withhold_control_responses: AtomicBool::new(false),
+            readable: Notify::new(),
+        })
+    }
+
+    /// Queue one more read.
+    pub async fn enqueue_read(&self, value: Result<Option<Value>, SdkError>) {
+        let mut state = self.state.lock().await;
+        state.reads.push_back(value);
+        self.readable.notify_waiters();
+    }
+
+    /// Queue messages to be read once the next `user` payload is written.
+    pub async fn reply_to_next_user<T>(&self, replies: T)
+    where
+        T: IntoIterator<Item = Value>,
+    {
+        let mut state = self.state.lock().await;
+        state.user_replies.push_back(replies.into_iter().collect());
+    }
+
+    /// Stop answering control requests automatically, simulating a stalled CLI.
+    pub fn set_withhold_control_responses(&self, withhold: bool) {
+        self.withhold_control_responses
+            .store(withhold, Ordering::SeqCst);
+    }
+
+    /// Block reads on an empty queue until more data arrives or the transport closes,
+    /// instead of reporting end of stream.
+    pub fn set_keep_open(&self, keep_open: bool) {
+        self.keep_open.store(keep_open, Ordering::SeqCst);
+    }
+
+    /// Every payload written so far, in order.
+    pub async fn writes(&self) -> Vec<Value> {
+        let state = self.state.lock().await;
+        state.writes.clone()
+    }
+
+    pub async fn connect_calls(&self) -> usize {
+        let state = self.state.lock().await;
+        state.connect_calls
+    }
+
+    pub async fn end_input_calls(&self) -> usize {
+        let state = self.state.lock().await;
+        state.end_input_calls
+    }
+
+    pub async fn close_calls(&self) -> usize {
+        let state = self.state.lock().await;
+        state.close_calls
+    }
+
+    pub fn set_ready(&self, ready: bool) {
+        self.ready.store(ready, Ordering::SeqCst);
+    }
+}
+
+#[async_trait]
+impl Transport for MockTransport {
+    async fn connect(&self) -> Result<(), SdkError> {
+        let mut state = self.state.lock().await;
+        state.connect_calls += 1;
+        Ok(())
+    }
+
+    async fn write(&self, payload: &Value) -> Result<(), SdkError> {
+        let mut state = self.state.lock().await;
+        state.writes.push(payload.clone());
+
+        if payload
+            .get("type")
+            .and_then(Value::as_str)
+            .map(|value| value == "control_request")
+            .unwrap_or(false)
+        {
+            if self.withhold_control_responses.load(Ordering::SeqCst) {
+                self.readable.notify_waiters();
+                return Ok(());
+            }
+            if let Some(request_id) = payload.get("request_id").and_then(Value::as_str) {
+                let response = json!({
+                    "type": "control_response",
+                    "response": {
+                        "subtype": "success",
+                        "request_id": request_id,
+                        "response": serde_json::Value::Null,
+                    }
+                });
+                state.reads.push_front(Ok(Some(response)));
+            }
+        } else if payload.get("type").and_then(Value::as_str) == Some("user") {
+            if let Some(replies) = state.user_replies.pop_front() {
+                state
+                    .reads
+                    .extend(replies.into_iter().map(|reply| Ok(Some(reply))));
+            }
+        }
+
+        self.readable.notify_waiters();
+        Ok(())
+    }
+
+    async fn read(&self) -> Result<Option<Value>, SdkError> {
+        loop {
+            let readable = self.readable.notified();
+            {
+                let mut state = self.state.lock().await;
+                if let Some(next) = state.reads.pop_front() {
+                    return next;
+                }
+                if !self.keep_open.load(Ordering::SeqCst) || state.close_calls > 0 {
+                    return Ok(None);
+                }
+            }
+            readable.await;
+        }
+    }
+
+    async fn end_input(&self) -> Result<(), SdkError> {
+        let mut state = self.state.lock().await;
+        state.end_input_calls += 1;
+        Ok(())
+    }
+
+    async fn close(&self) -> Result<(), SdkError> {
+        let mut state = self.state.lock().await;
+        state.close_calls += 1;
+        self.readable.notify_waiters();
+        Ok(())
+    }
+
+    fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::SeqCst)
+    }
+}
@@ -0,0 +1,28 @@
+//! Test doubles for code built on the SDK (requires the `testing` feature).
+//!
+//! [`MockTransport`] replaces the CLI subprocess with queued reads and
+//! recorded writes; [`Scenario`] scripts whole conversations on top of it.
+//! [`FaultyTransport`] wraps any transport to inject failures, and [`golden`]
+//! snapshots how recorded CLI transcripts are parsed and routed, while
+//! [`conformance`] replays control protocol exchanges recorded from the
+//! Python SDK. [`matchers`] backs
+//! [`assert_stream_yields!`](crate::assert_stream_yields) for checking the
+//! messages a session yields. [`permission_flow`] builds realistic
+//! permission prompts and hook invocations and captures the SDK's replies.
+//! With the `proptest` feature, [`strategies`] generates well-formed and
+//! malformed CLI output for property tests.
+
+pub mod conformance;
+mod faulty_transport;
+pub mod golden;
+pub mod matchers;
+mod mock_transport;
+pub mod permission_flow;
+mod scenario;
+#[cfg(feature = "proptest")]
+pub mod strategies;
+
+pub use faulty_transport::FaultyTransport;
+pub use mock_transport::MockTransport;
+pub use permission_flow::{ControlReply, HookRequest, PermissionRequest};
+pub use scenario::Scenario;
@@ -0,0 +1,376 @@
+//! Realistic `can_use_tool` and `hook_callback` control requests.
+//!
+//! [`PermissionRequest`] and [`HookRequest`] build the request bodies the CLI
+//! sends when it asks for permission to run a tool or invokes a registered
+//! hook: permission suggestions, `tool_use_id`s and the snake_case hook input
+//! included. [`MockTransport::request_permission`] and
+//! [`MockTransport::invoke_hook`] queue them as CLI output, and
+//! [`MockTransport::wait_for_control_reply`] returns what the SDK answered,
+//! so permission callbacks and hooks can be tested against the wire format.
+//!
+//! ```no_run
+//! # use std::sync::Arc;
+//! # use std::time::Duration;
+//! # use sdk_claude_rust::client::ClaudeSdkClient;
+//! # use sdk_claude_rust::config::ClaudeAgentOptions;
+//! # use sdk_claude_rust::testing::{MockTransport, PermissionRequest};
+//! # use serde_json::json;
+//! # async fn example(options: ClaudeAgentOptions) {
+//! let transport = MockTransport::new();
+//! transport.set_keep_open(true);
+//! let mut client = ClaudeSdkClient::new(Some(options), Some(transport.clone()));
+//! client.connect(None).await.unwrap();
+//!
+//! let request_id = transport
+//!     .request_permission(PermissionRequest::new("Bash", json!({"command": "ls"})))
+//!     .await;
+//! let reply = transport
+//!     .wait_for_control_reply(&request_id, Duration::from_secs(1))
+//!     .await
+//!     .expect("the SDK should answer");
+//! assert_eq!(reply.behavior(), Some("allow"));
+//! # }
+//! ```
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use super::MockTransport;
+use crate::hooks::HookEvent;
+use crate::permission::PermissionUpdate;
+
+const REPLY_POLL: Duration = Duration::from_millis(5);
+
+static NEXT_REQUEST: AtomicU64 = AtomicU64::new(1);
+
+fn generated_tool_use_id() -> String {
+    format!("toolu_{}", crate::internal::unique_id())
+}
+
+/// A `can_use_tool` request as the CLI sends it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionRequest {
+    tool_name: String,
+    input: Value,
+    tool_use_id: String,
+    suggestions: Vec<PermissionUpdate>,
+    blocked_path: Option<String>,
+}
+
+impl PermissionRequest {
+    /// Request to run `tool_name` with `input`, under a generated
+    /// `toolu_...` id.
+    pub fn new(tool_name: impl Into<String>, input: Value) -> Self {
+        Self {
+            tool_name: tool_name.into(),
+            input,
+            tool_use_id: generated_tool_use_id(),
+            suggestions: Vec::new(),
+            blocked_path: None,
+        }
+    }
+
+    /// Use this tool use id instead of a generated one.
+    pub fn with_tool_use_id(mut self, tool_use_id: impl Into<String>) -> Self {
+        self.tool_use_id = tool_use_id.into();
+        self
+    }
+
+    /// Offer a permission update, such as "always allow", alongside the prompt.
+    pub fn with_suggestion(mut self, suggestion: PermissionUpdate) -> Self {
+        self.suggestions.push(suggestion);
+        self
+    }
+
+    /// Path outside the allowed directories that triggered the prompt.
+    pub fn with_blocked_path(mut self, path: impl Into<String>) -> Self {
+        self.blocked_path = Some(path.into());
+        self
+    }
+
+    pub fn tool_use_id(&self) -> &str {
+        &self.tool_use_id
+    }
+
+    /// The `request` body of the control request.
+    pub fn to_request(&self) -> Value {
+        let suggestions: Vec<Value> = self
+            .suggestions
+            .iter()
+            .map(|update| serde_json::to_value(update).unwrap_or(Value::Null))
+            .collect();
+        let mut request = json!({
+            "subtype": "can_use_tool",
+            "tool_name": self.tool_name,
+            "input": self.input,
+            "permission_suggestions": suggestions,
+            "tool_use_id": self.tool_use_id,
+        });
+        if let (Some(path), Some(map)) = (&self.blocked_path, request.as_object_mut()) {
+            map.insert("blocked_path".into(), Value::String(path.clone()));
+        }
+        request
+    }
+}
+
+/// A `hook_callback` request as the CLI sends it.
+///
+/// Callback ids are `hook_0`, `hook_1`, ... in the order hooks were
+/// registered through `ClaudeAgentOptions::hooks`. The input carries the
+/// common fields (`session_id`, `transcript_path`, `cwd`) with placeholder
+/// values that can be overridden.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HookRequest {
+    callback_id: String,
+    event: HookEvent,
+    input: Map<String, Value>,
+    tool_use_id: Option<String>,
+}
+
+impl HookRequest {
+    fn new(callback_id: impl Into<String>, event: HookEvent, fields: Value) -> Self {
+        let mut input = Map::new();
+        input.insert("hook_event_name".into(), json!(event.as_str()));
+        input.insert("session_id".into(), json!("sim-session"));
+        input.insert("transcript_path".into(), json!("/tmp/sim-transcript.jsonl"));
+        input.insert("cwd".into(), json!("/tmp"));
+        if let Value::Object(fields) = fields {
+            input.extend(fields);
+        }
+        Self {
+            callback_id: callback_id.into(),
+            event,
+            input,
+            tool_use_id: None,
+        }
+    }
+
+    /// `PreToolUse` for `tool_name`, under a generated tool use id.
+    pub fn pre_tool_use(
+        callback_id: impl Into<String>,
+        tool_name: &str,
+        tool_input: Value,
+    ) -> Self {
+        let mut request = Self::new(
+            callback_id,
+            HookEvent::PreToolUse,
+            json!({"tool_name": tool_name, "tool_input": tool_input}),
+        );
+        request.tool_use_id = Some(generated_tool_use_id());
+        request
+    }
+
+    /// `PostToolUse` for `tool_name`, under a generated tool use id.
+    pub fn post_tool_use(
+        callback_id: impl Into<String>,
+        tool_name: &str,
+        tool_input: Value,
+        tool_response: Value,
+    ) -> Self {
+        let mut request = Self::new(
+            callback_id,
+            HookEvent::PostToolUse,
+            json!({
+                "tool_name": tool_name,
+                "tool_input": tool_input,
+                "tool_response": tool_response,
+            }),
+        );
+        request.tool_use_id = Some(generated_tool_use_id());
+        request
+    }
+
+    pub fn user_prompt_submit(callback_id: impl Into<String>, prompt: &str) -> Self {
+        Self::new(
+            callback_id,
+            HookEvent::UserPromptSubmit,
+            json!({"prompt": prompt}),
+        )
+    }
+
+    pub fn stop(callback_id: impl Into<String>, stop_hook_active: bool) -> Self {
+        Self::new(
+            callback_id,
+            HookEvent::Stop,
+            json!({"stop_hook_active": stop_hook_active}),
+        )
+    }
+
+    pub fn subagent_stop(callback_id: impl Into<String>, stop_hook_active: bool) -> Self {
+        Self::new(
+            callback_id,
+            HookEvent::SubagentStop,
+            json!({"stop_hook_active": stop_hook_active}),
+        )
+    }
+
+    /// `PreCompact` with `trigger` set to `"manual"` or `"auto"`.
+    pub fn pre_compact(callback_id: impl Into<String>, trigger: &str) -> Self {
+        Self::new(
+            callback_id,
+            HookEvent::PreCompact,
+            json!({"trigger": trigger}),
+        )
+    }
+
+    /// Set the tool use id, or clear it with `None`.
+    pub fn with_tool_use_id(mut self, tool_use_id: Option<String>) -> Self {
+        self.tool_use_id = tool_use_id;
+        self
+    }
+
+    pub fn with_session_id(self, session_id: impl Into<String>) -> Self {
+        self.with_field("session_id", Value::String(session_id.into()))
+    }
+
+    pub fn with_transcript_path(self, path: impl Into<String>) -> Self {
+        self.with_field("transcript_path", Value::String(path.into()))
+    }
+
+    pub fn with_cwd(self, cwd: impl Into<String>) -> Self {
+        self.with_field("cwd", Value::String(cwd.into()))
+    }
+
+    pub fn with_permission_mode(self, mode: impl Into<String>) -> Self {
+        self.with_field("permission_mode", Value::String(mode.into()))
+    }
+
+    /// Set any other input field, e.g. `custom_instructions`.
+    pub fn with_field(mut self, key: &str, value: Value) -> Self {
+        self.input.insert(key.to_string(), value);
+        self
+    }
+
+    pub fn event(&self) -> HookEvent {
+        self.event
+    }
+
+    pub fn tool_use_id(&self) -> Option<&str> {
+        self.tool_use_id.as_deref()
+    }
+
+    /// The `request` body of the control request.
+    pub fn to_request(&self) -> Value {
+        json!({
+            "subtype": "hook_callback",
+            "callback_id": self.callback_id,
+            "input": self.input,
+            "tool_use_id": self.tool_use_id,
+        })
+    }
+}
+
+/// The SDK's answer to a control request sent by the CLI.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ControlReply {
+    Success(Value),
+    Error(String),
+}
+
+impl ControlReply {
+    /// Decode the `response` object of a `control_response` frame.
+    pub fn from_response(response: &Value) -> Option<Self> {
+        match response.get("subtype").and_then(Value::as_str)? {
+            "success" => Some(Self::Success(
+                response.get("response").cloned().unwrap_or(Value::Null),
+            )),
+            "error" => Some(Self::Error(
+                response
+                    .get("error")
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string(),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Success payload, if the request succeeded.
+    pub fn payload(&self) -> Option<&Value> {
+        match self {
+            Self::Success(payload) => Some(payload),
+            Self::Error(_) => None,
+        }
+    }
+
+    /// Error message, if the request failed.
+    pub fn error(&self) -> Option<&str> {
+        match self {
+            Self::Success(_) => None,
+            Self::Error(message) => Some(message),
+        }
+    }
+
+    /// `"allow"` or `"deny"` for permission replies.
+    pub fn behavior(&self) -> Option<&str> {
+        self.payload()?.get("behavior")?.as_str()
+    }
+
+    /// `updatedInput` of an allowed permission request.
+    pub fn updated_input(&self) -> Option<&Value> {
+        self.payload()?.get("updatedInput")
+    }
+
+    /// `updatedPermissions` of an allowed permission request.
+    pub fn updated_permissions(&self) -> Option<&Value> {
+        self.payload()?.get("updatedPermissions")
+    }
+}
+
+impl MockTransport {
+    /// Queue a control request from the CLI side and return its request id.
+    pub async fn send_control_request(&self, request: Value) -> String {
+        let request_id = format!("sim-{}", NEXT_REQUEST.fetch_add(1, Ordering::Relaxed));
+        self.enqueue_read(Ok(Some(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": request,
+        }))))
+        .await;
+        request_id
+    }
+
+    /// Ask the SDK's `can_use_tool` callback for permission.
+    pub async fn request_permission(&self, request: PermissionRequest) -> String {
+        self.send_control_request(request.to_request()).await
+    }
+
+    /// Invoke a registered hook.
+    pub async fn invoke_hook(&self, request: HookRequest) -> String {
+        self.send_control_request(request.to_request()).await
+    }
+
+    /// The SDK's reply to `request_id`, if it has been written.
+    pub async fn control_reply(&self, request_id: &str) -> Option<ControlReply> {
+        self.writes().await.iter().find_map(|write| {
+            if write.get("type").and_then(Value::as_str) != Some("control_response") {
+                return None;
+            }
+            let response = write.get("response")?;
+            if response.get("request_id").and_then(Value::as_str) != Some(request_id) {
+                return None;
+            }
+            ControlReply::from_response(response)
+        })
+    }
+
+    /// Wait up to `timeout` for the SDK's reply to `request_id`.
+    pub async fn wait_for_control_reply(
+        &self,
+        request_id: &str,
+        timeout: Duration,
+    ) -> Option<ControlReply> {
+        let deadline = tokio::time::Instant::now() + timeout;
+        loop {
+            if let Some(reply) = self.control_reply(request_id).await {
+                return Some(reply);
+            }
+            if tokio::time::Instant::now() >= deadline {
+                return None;
+            }
+            tokio::time::sleep(REPLY_POLL).await;
+        }
+    }
+}
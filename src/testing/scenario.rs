@@ -0,0 +1,341 @@
+//! Scripted multi-turn conversations on top of [`MockTransport`].
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use super::matchers::json_contains;
+use super::permission_flow::{HookRequest, PermissionRequest};
+use super::MockTransport;
+
+const SESSION_ID: &str = "scenario-session";
+const MODEL: &str = "claude-scenario";
+const VERIFY_TIMEOUT: Duration = Duration::from_secs(2);
+const VERIFY_POLL: Duration = Duration::from_millis(10);
+
+#[derive(Debug, Clone)]
+struct Turn {
+    expected_user: Option<String>,
+    replies: Vec<Value>,
+}
+
+#[derive(Debug, Clone)]
+struct ControlExpectation {
+    request_id: String,
+    expected: Option<Value>,
+}
+
+/// Builder for a scripted conversation.
+///
+/// Messages added with the `respond_*` methods are read by the SDK once the
+/// preceding [`Scenario::expect_user`] prompt has been written; messages added
+/// before the first `expect_user` are readable immediately. Control requests
+/// from the "CLI" (permission prompts, hook callbacks) are queued the same
+/// way, and [`Scenario::verify`] checks the prompts and control responses the
+/// SDK wrote.
+///
+/// ```no_run
+/// # use sdk_claude_rust::testing::Scenario;
+/// # use serde_json::json;
+/// # async fn example() {
+/// let scenario = Scenario::new()
+///     .expect_user("list the files")
+///     .request_permission("Bash", json!({"command": "ls"}))
+///     .expect_allow()
+///     .respond_assistant("Done.")
+///     .respond_result("Done.");
+/// let transport = scenario.transport().await;
+/// // ... drive a client over `transport`, then:
+/// scenario.assert_satisfied(&transport).await;
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Scenario {
+    turns: Vec<Turn>,
+    controls: Vec<ControlExpectation>,
+}
+
+impl Default for Scenario {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scenario {
+    pub fn new() -> Self {
+        Self {
+            turns: vec![Turn {
+                expected_user: None,
+                replies: Vec::new(),
+            }],
+            controls: Vec::new(),
+        }
+    }
+
+    /// Wait for the SDK to send a user message with exactly this text.
+    pub fn expect_user(mut self, text: impl Into<String>) -> Self {
+        self.turns.push(Turn {
+            expected_user: Some(text.into()),
+            replies: Vec::new(),
+        });
+        self
+    }
+
+    /// Respond with a raw CLI message.
+    pub fn respond(mut self, message: Value) -> Self {
+        self.turns
+            .last_mut()
+            .expect("scenario always has a turn")
+            .replies
+            .push(message);
+        self
+    }
+
+    /// Respond with a system message.
+    pub fn respond_system(self, subtype: &str, data: Value) -> Self {
+        let mut message = json!({"type": "system", "subtype": subtype});
+        if let (Some(target), Value::Object(data)) = (message.as_object_mut(), data) {
+            target.extend(data);
+        }
+        self.respond(message)
+    }
+
+    /// Respond with an assistant text message.
+    pub fn respond_assistant(self, text: &str) -> Self {
+        self.respond(json!({
+            "type": "assistant",
+            "message": {
+                "model": MODEL,
+                "content": [{"type": "text", "text": text}]
+            }
+        }))
+    }
+
+    /// Respond with an assistant message requesting a tool call.
+    pub fn respond_tool_use(self, id: &str, name: &str, input: Value) -> Self {
+        self.respond(json!({
+            "type": "assistant",
+            "message": {
+                "model": MODEL,
+                "content": [{"type": "tool_use", "id": id, "name": name, "input": input}]
+            }
+        }))
+    }
+
+    /// Respond with the user message carrying a tool's output.
+    pub fn respond_tool_result(self, tool_use_id: &str, content: Value) -> Self {
+        self.respond(json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": tool_use_id, "content": content}]
+            }
+        }))
+    }
+
+    /// Respond with a successful result message.
+    pub fn respond_result(self, text: &str) -> Self {
+        let turns = self
+            .turns
+            .iter()
+            .filter(|turn| turn.expected_user.is_some())
+            .count()
+            .max(1);
+        self.respond(json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": turns,
+            "session_id": SESSION_ID,
+            "result": text
+        }))
+    }
+
+    /// Ask the SDK's `can_use_tool` callback for permission to run a tool.
+    pub fn request_permission(self, tool_name: &str, input: Value) -> Self {
+        self.control_request(json!({
+            "subtype": "can_use_tool",
+            "tool_name": tool_name,
+            "input": input,
+            "permission_suggestions": []
+        }))
+    }
+
+    /// Invoke a registered hook; callback ids are `hook_0`, `hook_1`, ... in
+    /// registration order.
+    pub fn invoke_hook(self, callback_id: &str, input: Value) -> Self {
+        self.control_request(json!({
+            "subtype": "hook_callback",
+            "callback_id": callback_id,
+            "input": input,
+            "tool_use_id": Value::Null
+        }))
+    }
+
+    /// Send a fully specified permission request, with suggestions and a
+    /// tool use id.
+    pub fn permission(self, request: PermissionRequest) -> Self {
+        self.control_request(request.to_request())
+    }
+
+    /// Invoke a hook with the input shape the CLI sends.
+    pub fn hook(self, request: HookRequest) -> Self {
+        self.control_request(request.to_request())
+    }
+
+    /// Send an arbitrary control request from the CLI side.
+    pub fn control_request(mut self, request: Value) -> Self {
+        let request_id = format!("scenario-{}", self.controls.len() + 1);
+        self.controls.push(ControlExpectation {
+            request_id: request_id.clone(),
+            expected: None,
+        });
+        self.respond(json!({
+            "type": "control_request",
+            "request_id": request_id,
+            "request": request
+        }))
+    }
+
+    /// Require the response to the latest control request to succeed with a
+    /// payload containing every field of `subset`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if no control request has been added yet.
+    pub fn expect_response(mut self, subset: Value) -> Self {
+        self.controls
+            .last_mut()
+            .expect("expect_response must follow a control request")
+            .expected = Some(subset);
+        self
+    }
+
+    /// Require the latest permission request to be allowed.
+    pub fn expect_allow(self) -> Self {
+        self.expect_response(json!({"behavior": "allow"}))
+    }
+
+    /// Require the latest permission request to be denied.
+    pub fn expect_deny(self) -> Self {
+        self.expect_response(json!({"behavior": "deny"}))
+    }
+
+    /// Transport that plays this scenario; it stays open until closed.
+    pub async fn transport(&self) -> Arc<MockTransport> {
+        let mut turns = self.turns.iter();
+        let initial = turns
+            .next()
+            .map(|turn| turn.replies.clone())
+            .unwrap_or_default();
+        let transport = MockTransport::with_reads(initial.into_iter().map(|reply| Ok(Some(reply))));
+        transport.set_keep_open(true);
+        for turn in turns {
+            transport.reply_to_next_user(turn.replies.clone()).await;
+        }
+        transport
+    }
+
+    /// Check the writes recorded by `transport` against the expectations,
+    /// waiting briefly for writes that are still in flight.
+    pub async fn verify(&self, transport: &MockTransport) -> Result<(), String> {
+        let deadline = tokio::time::Instant::now() + VERIFY_TIMEOUT;
+        loop {
+            match self.check(&transport.writes().await) {
+                Ok(()) => return Ok(()),
+                Err(Mismatch::Wrong(message)) => return Err(message),
+                Err(Mismatch::Missing(message)) if tokio::time::Instant::now() >= deadline => {
+                    return Err(message)
+                }
+                Err(Mismatch::Missing(_)) => tokio::time::sleep(VERIFY_POLL).await,
+            }
+        }
+    }
+
+    /// [`Scenario::verify`], panicking with the mismatch.
+    pub async fn assert_satisfied(&self, transport: &MockTransport) {
+        if let Err(message) = self.verify(transport).await {
+            panic!("scenario not satisfied: {message}");
+        }
+    }
+
+    fn check(&self, writes: &[Value]) -> Result<(), Mismatch> {
+        let prompts: Vec<String> = writes
+            .iter()
+            .filter(|write| write.get("type").and_then(Value::as_str) == Some("user"))
+            .map(user_text)
+            .collect();
+        let expected = self
+            .turns
+            .iter()
+            .filter_map(|turn| turn.expected_user.as_ref());
+        for (index, expected) in expected.enumerate() {
+            match prompts.get(index) {
+                Some(actual) if actual == expected => {}
+                Some(actual) => {
+                    return Err(Mismatch::Wrong(format!(
+                        "user message {} was {actual:?}, expected {expected:?}",
+                        index + 1
+                    )))
+                }
+                None => {
+                    return Err(Mismatch::Missing(format!(
+                        "user message {} ({expected:?}) was never sent",
+                        index + 1
+                    )))
+                }
+            }
+        }
+
+        for control in &self.controls {
+            let Some(expected) = &control.expected else {
+                continue;
+            };
+            let response = writes.iter().find_map(|write| {
+                let response = write.get("response")?;
+                (write.get("type").and_then(Value::as_str) == Some("control_response")
+                    && response.get("request_id").and_then(Value::as_str)
+                        == Some(control.request_id.as_str()))
+                .then_some(response)
+            });
+            let Some(response) = response else {
+                return Err(Mismatch::Missing(format!(
+                    "no control response for {}",
+                    control.request_id
+                )));
+            };
+            let payload = response.get("response").unwrap_or(&Value::Null);
+            if response.get("subtype").and_then(Value::as_str) != Some("success")
+                || !json_contains(payload, expected)
+            {
+                return Err(Mismatch::Wrong(format!(
+                    "control response for {} was {response}, expected a success containing {expected}",
+                    control.request_id
+                )));
+            }
+        }
+        Ok(())
+    }
+}
+
+enum Mismatch {
+    /// Not written yet; may still arrive.
+    Missing(String),
+    /// Written with the wrong content.
+    Wrong(String),
+}
+
+fn user_text(write: &Value) -> String {
+    match write.pointer("/message/content") {
+        Some(Value::String(text)) => text.clone(),
+        Some(Value::Array(blocks)) => blocks
+            .iter()
+            .filter_map(|block| block.get("text").and_then(Value::as_str))
+            .collect::<Vec<_>>()
+            .join(""),
+        _ => String::new(),
+    }
+}
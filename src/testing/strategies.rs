@@ -0,0 +1,396 @@
+//! `proptest` strategies for raw CLI output (requires the `proptest` feature).
+//!
+//! The well-formed strategies ([`message`], [`control_response`],
+//! [`control_request`]) produce JSON the CLI could legitimately emit, with
+//! extra unknown fields mixed in the way newer CLI versions add them.
+//! The `adversarial_*` strategies start from well-formed output and break it:
+//! required fields removed or retyped, objects replaced by scalars, oversized
+//! and non-ASCII strings. The SDK must reject those cleanly, never panic or
+//! hang.
+
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+
+/// Arbitrary JSON, nested up to four levels.
+pub fn json_value() -> impl Strategy<Value = Value> {
+    let leaf = prop_oneof![
+        Just(Value::Null),
+        any::<bool>().prop_map(Value::Bool),
+        any::<i64>().prop_map(Value::from),
+        any::<f64>().prop_map(Value::from),
+        ".{0,16}".prop_map(Value::String),
+    ];
+    leaf.prop_recursive(4, 48, 6, |inner| {
+        prop_oneof![
+            prop::collection::vec(inner.clone(), 0..6).prop_map(Value::Array),
+            prop::collection::hash_map("[a-z_]{1,10}", inner, 0..6)
+                .prop_map(|map| Value::Object(map.into_iter().collect())),
+        ]
+    })
+}
+
+fn text() -> impl Strategy<Value = String> {
+    prop_oneof![
+        4 => "[ -~]{0,32}",
+        1 => "\\PC{0,16}",
+    ]
+}
+
+fn identifier(prefix: &'static str) -> impl Strategy<Value = String> {
+    "[A-Za-z0-9]{4,12}".prop_map(move |suffix| format!("{prefix}{suffix}"))
+}
+
+/// Unknown fields the parser must ignore.
+fn extra_fields() -> impl Strategy<Value = Map<String, Value>> {
+    prop::collection::hash_map("x_[a-z_]{1,8}", json_value(), 0..3)
+        .prop_map(|fields| fields.into_iter().collect())
+}
+
+fn with_extra_fields(message: impl Strategy<Value = Value>) -> impl Strategy<Value = Value> {
+    (message, extra_fields()).prop_map(|(mut message, extra)| {
+        if let Some(object) = message.as_object_mut() {
+            for (key, value) in extra {
+                object.entry(key).or_insert(value);
+            }
+        }
+        message
+    })
+}
+
+fn json_object() -> impl Strategy<Value = Map<String, Value>> {
+    prop::collection::hash_map("[a-z_]{1,8}", json_value(), 0..4)
+        .prop_map(|fields| fields.into_iter().collect())
+}
+
+/// A well-formed content block of any kind.
+pub fn content_block() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        text().prop_map(|text| json!({"type": "text", "text": text})),
+        (text(), "[A-Za-z0-9]{0,16}").prop_map(|(thinking, signature)| {
+            json!({"type": "thinking", "thinking": thinking, "signature": signature})
+        }),
+        (identifier("toolu_"), "[A-Z][a-zA-Z_]{0,12}", json_object()).prop_map(
+            |(id, name, input)| json!({"type": "tool_use", "id": id, "name": name, "input": input})
+        ),
+        (
+            identifier("toolu_"),
+            prop::option::of(json_value()),
+            prop::option::of(any::<bool>())
+        )
+            .prop_map(|(id, content, is_error)| {
+                let mut block = json!({"type": "tool_result", "tool_use_id": id});
+                if let Some(content) = content {
+                    block["content"] = content;
+                }
+                if let Some(is_error) = is_error {
+                    block["is_error"] = Value::Bool(is_error);
+                }
+                block
+            }),
+    ]
+}
+
+fn parent_tool_use_id() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        Just(Value::Null),
+        identifier("toolu_").prop_map(Value::String)
+    ]
+}
+
+/// A well-formed `user` message.
+pub fn user_message() -> impl Strategy<Value = Value> {
+    let content = prop_oneof![
+        text().prop_map(Value::String),
+        prop::collection::vec(content_block(), 0..4).prop_map(Value::Array),
+    ];
+    with_extra_fields(
+        (content, parent_tool_use_id()).prop_map(|(content, parent)| {
+            json!({
+                "type": "user",
+                "message": {"role": "user", "content": content},
+                "parent_tool_use_id": parent
+            })
+        }),
+    )
+}
+
+/// A well-formed `assistant` message.
+pub fn assistant_message() -> impl Strategy<Value = Value> {
+    with_extra_fields(
+        (
+            prop::collection::vec(content_block(), 0..4),
+            "claude-[a-z0-9\\-]{1,16}",
+            parent_tool_use_id(),
+        )
+            .prop_map(|(content, model, parent)| {
+                json!({
+                    "type": "assistant",
+                    "message": {"role": "assistant", "model": model, "content": content},
+                    "parent_tool_use_id": parent
+                })
+            }),
+    )
+}
+
+/// A well-formed `system` message.
+pub fn system_message() -> impl Strategy<Value = Value> {
+    let subtype = prop_oneof![
+        Just("init".to_string()),
+        Just("compact_boundary".to_string()),
+        Just("status".to_string()),
+        "[a-z_]{1,12}",
+    ];
+    with_extra_fields(subtype.prop_map(|subtype| json!({"type": "system", "subtype": subtype})))
+}
+
+/// A well-formed `result` message.
+pub fn result_message() -> impl Strategy<Value = Value> {
+    let subtype = prop_oneof![
+        Just("success"),
+        Just("error_max_turns"),
+        Just("error_max_budget_usd"),
+        Just("error_during_execution"),
+    ];
+    with_extra_fields(
+        (
+            subtype,
+            0..100_000i64,
+            0..100_000i64,
+            0..50i64,
+            identifier(""),
+            prop::option::of(0.0..10.0f64),
+            prop::option::of(text()),
+        )
+            .prop_map(
+                |(subtype, duration, api_duration, turns, session, cost, result)| {
+                    let mut message = json!({
+                        "type": "result",
+                        "subtype": subtype,
+                        "duration_ms": duration,
+                        "duration_api_ms": api_duration,
+                        "is_error": subtype != "success",
+                        "num_turns": turns,
+                        "session_id": session,
+                        "usage": {"input_tokens": 1, "output_tokens": 1}
+                    });
+                    if let Some(cost) = cost {
+                        message["total_cost_usd"] = json!(cost);
+                    }
+                    if let Some(result) = result {
+                        message["result"] = Value::String(result);
+                    }
+                    message
+                },
+            ),
+    )
+}
+
+/// A well-formed `stream_event` message.
+pub fn stream_event() -> impl Strategy<Value = Value> {
+    with_extra_fields(
+        (
+            identifier(""),
+            identifier(""),
+            json_value(),
+            parent_tool_use_id(),
+        )
+            .prop_map(|(uuid, session, event, parent)| {
+                json!({
+                    "type": "stream_event",
+                    "uuid": uuid,
+                    "session_id": session,
+                    "event": event,
+                    "parent_tool_use_id": parent
+                })
+            }),
+    )
+}
+
+/// Any well-formed message; `parse_message` must accept all of them.
+pub fn message() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        user_message(),
+        assistant_message(),
+        system_message(),
+        result_message(),
+        stream_event(),
+    ]
+}
+
+/// Ways of breaking a JSON object.
+#[derive(Debug, Clone)]
+enum Mutation {
+    Remove(prop::sample::Index),
+    Retype(prop::sample::Index, Value),
+    Nest(prop::sample::Index),
+    Oversize(prop::sample::Index, usize),
+    Replace(Value),
+}
+
+fn mutation() -> impl Strategy<Value = Mutation> {
+    prop_oneof![
+        3 => any::<prop::sample::Index>().prop_map(Mutation::Remove),
+        3 => (any::<prop::sample::Index>(), json_value())
+            .prop_map(|(index, value)| Mutation::Retype(index, value)),
+        1 => any::<prop::sample::Index>().prop_map(Mutation::Nest),
+        1 => (any::<prop::sample::Index>(), 1_000..100_000usize)
+            .prop_map(|(index, len)| Mutation::Oversize(index, len)),
+        1 => json_value().prop_map(Mutation::Replace),
+    ]
+}
+
+fn apply(mut value: Value, mutation: &Mutation) -> Value {
+    let Some(object) = value.as_object_mut() else {
+        return value;
+    };
+    let pick = |index: &prop::sample::Index, object: &Map<String, Value>| {
+        (!object.is_empty()).then(|| {
+            object
+                .keys()
+                .nth(index.index(object.len()))
+                .cloned()
+                .expect("index is in range")
+        })
+    };
+    match mutation {
+        Mutation::Remove(index) => {
+            if let Some(key) = pick(index, object) {
+                object.remove(&key);
+            }
+        }
+        Mutation::Retype(index, replacement) => {
+            if let Some(key) = pick(index, object) {
+                object.insert(key, replacement.clone());
+            }
+        }
+        Mutation::Nest(index) => {
+            if let Some(key) = pick(index, object) {
+                let mut nested = object.remove(&key).unwrap_or(Value::Null);
+                for _ in 0..64 {
+                    nested = json!([nested]);
+                }
+                object.insert(key, nested);
+            }
+        }
+        Mutation::Oversize(index, len) => {
+            if let Some(key) = pick(index, object) {
+                object.insert(key, Value::String("é".repeat(*len)));
+            }
+        }
+        Mutation::Replace(replacement) => return replacement.clone(),
+    }
+    value
+}
+
+/// Break `strategy`'s output with one to three mutations, applied to the
+/// top level or to the nested `message` object.
+pub fn adversarial(strategy: impl Strategy<Value = Value>) -> impl Strategy<Value = Value> {
+    (
+        strategy,
+        prop::collection::vec((any::<bool>(), mutation()), 1..4),
+    )
+        .prop_map(|(mut value, mutations)| {
+            for (nested, mutation) in &mutations {
+                match value.get_mut("message") {
+                    Some(inner) if *nested => *inner = apply(inner.take(), mutation),
+                    _ => value = apply(value, mutation),
+                }
+            }
+            value
+        })
+}
+
+/// A malformed or truncated message.
+pub fn adversarial_message() -> impl Strategy<Value = Value> {
+    adversarial(message())
+}
+
+/// A well-formed `control_response` for `request_id`.
+pub fn control_response(request_id: String) -> impl Strategy<Value = Value> {
+    prop_oneof![
+        prop::option::of(json_object()).prop_map({
+            let request_id = request_id.clone();
+            move |payload| {
+                let mut response = json!({"subtype": "success", "request_id": request_id});
+                if let Some(payload) = payload {
+                    response["response"] = Value::Object(payload);
+                }
+                json!({"type": "control_response", "response": response})
+            }
+        }),
+        (text(), prop::option::of(json_value())).prop_map(move |(error, data)| {
+            let mut response =
+                json!({"subtype": "error", "request_id": request_id, "error": error});
+            if let Some(data) = data {
+                response["data"] = data;
+            }
+            json!({"type": "control_response", "response": response})
+        }),
+    ]
+}
+
+/// A malformed `control_response` that still names `request_id`, unless the
+/// mutation removed or retyped it.
+pub fn adversarial_control_response(request_id: String) -> impl Strategy<Value = Value> {
+    (
+        control_response(request_id),
+        prop::collection::vec(mutation(), 1..3),
+    )
+        .prop_map(|(mut frame, mutations)| {
+            for mutation in &mutations {
+                match frame.get_mut("response") {
+                    Some(response) if !matches!(mutation, Mutation::Replace(_)) => {
+                        *response = apply(response.take(), mutation)
+                    }
+                    _ => frame = apply(frame, mutation),
+                }
+            }
+            if let Some(object) = frame.as_object_mut() {
+                object.insert("type".into(), json!("control_response"));
+            }
+            frame
+        })
+}
+
+/// A `control_request` from the CLI, of a known or unknown subtype.
+pub fn control_request() -> impl Strategy<Value = Value> {
+    let request = prop_oneof![
+        ("[A-Z][a-zA-Z]{0,10}", json_object()).prop_map(|(tool, input)| json!({
+            "subtype": "can_use_tool",
+            "tool_name": tool,
+            "input": input,
+            "permission_suggestions": []
+        })),
+        ("hook_[0-9]{1,2}", json_value()).prop_map(|(callback_id, input)| json!({
+            "subtype": "hook_callback",
+            "callback_id": callback_id,
+            "input": input
+        })),
+        ("[a-z]{1,8}", json_value()).prop_map(|(server, message)| json!({
+            "subtype": "mcp_message",
+            "server_name": server,
+            "message": message
+        })),
+        ("[a-z_]{1,12}", json_object()).prop_map(|(subtype, fields)| {
+            let mut request = Value::Object(fields);
+            request["subtype"] = Value::String(subtype);
+            request
+        }),
+    ];
+    (identifier("cli_"), request).prop_map(|(request_id, request)| {
+        json!({"type": "control_request", "request_id": request_id, "request": request})
+    })
+}
+
+/// Any line the CLI might print: messages and control traffic, well-formed
+/// or not.
+pub fn cli_line() -> impl Strategy<Value = Value> {
+    prop_oneof![
+        4 => message(),
+        2 => adversarial_message(),
+        1 => control_request(),
+        1 => adversarial(control_request()),
+        1 => identifier("req_").prop_flat_map(adversarial_control_response),
+        1 => json_value(),
+    ]
+}
@@ -0,0 +1,164 @@
+//! Tool lifecycle events correlated from the message stream.
+//!
+//! Claude requests a tool in a `tool_use` block of an assistant message and
+//! the result comes back in a `tool_result` block of a later user message.
+//! [`ToolEventTracker`] pairs the two by tool use id and reports a
+//! [`ToolEvent::Started`] and a [`ToolEvent::Finished`] with the tool's
+//! input, duration and error status, which is what a UI showing
+//! "running Bash…" needs.
+//! [`ClaudeSdkClient::tool_events`](crate::client::ClaudeSdkClient::tool_events)
+//! runs a tracker over a connected client.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use serde_json::{Map, Value};
+use tokio::time::Instant;
+
+use crate::message::{ContentBlock, Message, UserMessageContent};
+
+/// Lifecycle event of a single tool call.
+#[derive(Debug, Clone, PartialEq)]
+pub enum ToolEvent {
+    /// Claude requested a tool call.
+    Started {
+        tool_use_id: String,
+        name: String,
+        input: Map<String, Value>,
+        /// Task tool call that issued this one, for subagent tools.
+        parent_tool_use_id: Option<String>,
+    },
+    /// The tool's result arrived.
+    Finished {
+        tool_use_id: String,
+        name: String,
+        input: Map<String, Value>,
+        parent_tool_use_id: Option<String>,
+        duration: Duration,
+        is_error: bool,
+        /// Content of the `tool_result` block.
+        content: Option<Value>,
+    },
+    /// The query ended before the tool reported a result, e.g. after an
+    /// interrupt.
+    Abandoned {
+        tool_use_id: String,
+        name: String,
+        parent_tool_use_id: Option<String>,
+        duration: Duration,
+    },
+}
+
+impl ToolEvent {
+    pub fn tool_use_id(&self) -> &str {
+        match self {
+            ToolEvent::Started { tool_use_id, .. }
+            | ToolEvent::Finished { tool_use_id, .. }
+            | ToolEvent::Abandoned { tool_use_id, .. } => tool_use_id,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        match self {
+            ToolEvent::Started { name, .. }
+            | ToolEvent::Finished { name, .. }
+            | ToolEvent::Abandoned { name, .. } => name,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct PendingTool {
+    name: String,
+    input: Map<String, Value>,
+    parent_tool_use_id: Option<String>,
+    started: Instant,
+    /// Position in the start order, so abandoned tools are reported in it.
+    sequence: u64,
+}
+
+/// Folds messages into [`ToolEvent`]s.
+#[derive(Debug, Clone, Default)]
+pub struct ToolEventTracker {
+    pending: HashMap<String, PendingTool>,
+    started: u64,
+}
+
+impl ToolEventTracker {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Tool calls still waiting for a result.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Events caused by `message`, in order.
+    pub fn observe(&mut self, message: &Message) -> Vec<ToolEvent> {
+        let mut events = Vec::new();
+        match message {
+            Message::Assistant(assistant) => {
+                for block in &assistant.content {
+                    let ContentBlock::ToolUse(tool) = block else {
+                        continue;
+                    };
+                    self.started += 1;
+                    self.pending.insert(
+                        tool.id.clone(),
+                        PendingTool {
+                            name: tool.name.clone(),
+                            input: tool.input.clone(),
+                            parent_tool_use_id: assistant.parent_tool_use_id.clone(),
+                            started: Instant::now(),
+                            sequence: self.started,
+                        },
+                    );
+                    events.push(ToolEvent::Started {
+                        tool_use_id: tool.id.clone(),
+                        name: tool.name.clone(),
+                        input: tool.input.clone(),
+                        parent_tool_use_id: assistant.parent_tool_use_id.clone(),
+                    });
+                }
+            }
+            Message::User(user) => {
+                let UserMessageContent::Blocks(blocks) = &user.content else {
+                    return events;
+                };
+                for block in blocks {
+                    let ContentBlock::ToolResult(result) = block else {
+                        continue;
+                    };
+                    // Results for calls made before tracking started are skipped.
+                    let Some(tool) = self.pending.remove(&result.tool_use_id) else {
+                        continue;
+                    };
+                    events.push(ToolEvent::Finished {
+                        tool_use_id: result.tool_use_id.clone(),
+                        name: tool.name,
+                        input: tool.input,
+                        parent_tool_use_id: tool.parent_tool_use_id,
+                        duration: tool.started.elapsed(),
+                        is_error: result.is_error.unwrap_or(false),
+                        content: result.content.clone(),
+                    });
+                }
+            }
+            Message::Result(_) => {
+                let mut abandoned: Vec<_> = self.pending.drain().collect();
+                abandoned.sort_by_key(|(_, tool)| tool.sequence);
+                events.extend(abandoned.into_iter().map(|(tool_use_id, tool)| {
+                    ToolEvent::Abandoned {
+                        tool_use_id,
+                        name: tool.name,
+                        parent_tool_use_id: tool.parent_tool_use_id,
+                        duration: tool.started.elapsed(),
+                    }
+                }));
+            }
+            Message::System(_) | Message::StreamEvent(_) => {}
+        }
+        events
+    }
+}
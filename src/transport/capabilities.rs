@@ -0,0 +1,157 @@
+//! Which CLI flags each Claude Code version understands.
+//!
+//! The subprocess transport reads the version from `claude -v` and consults
+//! [`CliCapabilities`] before passing a flag that older CLIs reject. A flag
+//! the installed CLI lacks is either translated to an environment variable,
+//! dropped when losing it only degrades output, or reported as
+//! [`SdkError::UnsupportedFeature`] when dropping it would change what the
+//! session does. When the version is unknown every flag is passed.
+
+use std::fmt;
+
+use crate::error::SdkError;
+
+/// A CLI flag that only newer Claude Code versions accept.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CliFeature {
+    IncludePartialMessages,
+    ForkSession,
+    Agents,
+    SettingSources,
+    PluginDir,
+    MaxThinkingTokens,
+    MaxBudgetUsd,
+}
+
+/// What to do when the installed CLI lacks a flag.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Fallback {
+    /// Fail with [`SdkError::UnsupportedFeature`].
+    Reject,
+    /// Leave the flag out.
+    Omit,
+    /// Pass the value through this environment variable instead.
+    Env(&'static str),
+}
+
+impl CliFeature {
+    pub const ALL: [CliFeature; 7] = [
+        CliFeature::IncludePartialMessages,
+        CliFeature::ForkSession,
+        CliFeature::Agents,
+        CliFeature::SettingSources,
+        CliFeature::PluginDir,
+        CliFeature::MaxThinkingTokens,
+        CliFeature::MaxBudgetUsd,
+    ];
+
+    pub fn flag(self) -> &'static str {
+        match self {
+            CliFeature::IncludePartialMessages => "--include-partial-messages",
+            CliFeature::ForkSession => "--fork-session",
+            CliFeature::Agents => "--agents",
+            CliFeature::SettingSources => "--setting-sources",
+            CliFeature::PluginDir => "--plugin-dir",
+            CliFeature::MaxThinkingTokens => "--max-thinking-tokens",
+            CliFeature::MaxBudgetUsd => "--max-budget-usd",
+        }
+    }
+
+    /// First CLI version accepting the flag.
+    pub fn min_version(self) -> [u32; 3] {
+        match self {
+            CliFeature::IncludePartialMessages => [1, 0, 86],
+            CliFeature::ForkSession => [1, 0, 94],
+            CliFeature::Agents | CliFeature::SettingSources => [2, 0, 0],
+            CliFeature::PluginDir => [2, 0, 12],
+            CliFeature::MaxThinkingTokens => [2, 0, 22],
+            CliFeature::MaxBudgetUsd => [2, 0, 28],
+        }
+    }
+
+    pub fn fallback(self) -> Fallback {
+        match self {
+            CliFeature::IncludePartialMessages => Fallback::Omit,
+            CliFeature::MaxThinkingTokens => Fallback::Env("MAX_THINKING_TOKENS"),
+            _ => Fallback::Reject,
+        }
+    }
+}
+
+impl fmt::Display for CliFeature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.flag())
+    }
+}
+
+/// Flags supported by one CLI version.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct CliCapabilities {
+    version: Option<[u32; 3]>,
+}
+
+impl CliCapabilities {
+    /// Capabilities for the output of `claude -v`, such as
+    /// `2.0.5 (Claude Code)`. Unparseable output counts as unknown.
+    pub fn detect(version_output: &str) -> Self {
+        Self {
+            version: parse_version(version_output),
+        }
+    }
+
+    /// Capabilities of an unknown version, which supports every flag.
+    pub fn unknown() -> Self {
+        Self::default()
+    }
+
+    pub fn version(&self) -> Option<[u32; 3]> {
+        self.version
+    }
+
+    pub fn supports(&self, feature: CliFeature) -> bool {
+        match self.version {
+            Some(version) => version >= feature.min_version(),
+            None => true,
+        }
+    }
+
+    /// Flags this version lacks.
+    pub fn unsupported(&self) -> Vec<CliFeature> {
+        CliFeature::ALL
+            .into_iter()
+            .filter(|feature| !self.supports(*feature))
+            .collect()
+    }
+
+    /// How to pass `feature`: `Ok(None)` to use the flag, otherwise its
+    /// non-rejecting [`Fallback`].
+    pub fn fallback_for(&self, feature: CliFeature) -> Result<Option<Fallback>, SdkError> {
+        if self.supports(feature) {
+            return Ok(None);
+        }
+        match feature.fallback() {
+            Fallback::Reject => Err(SdkError::UnsupportedFeature {
+                feature,
+                cli_version: self.version.map(format_version).unwrap_or_default(),
+                required: format_version(feature.min_version()),
+            }),
+            fallback => Ok(Some(fallback)),
+        }
+    }
+}
+
+pub(crate) fn parse_version(input: &str) -> Option<[u32; 3]> {
+    let token = input
+        .split_whitespace()
+        .find(|segment| segment.chars().all(|ch| ch.is_ascii_digit() || ch == '.'))?;
+
+    let mut parts = token.split('.');
+    let major = parts.next()?.parse().ok()?;
+    let minor = parts.next().unwrap_or("0").parse().ok()?;
+    let patch = parts.next().unwrap_or("0").parse().ok()?;
+    Some([major, minor, patch])
+}
+
+fn format_version([major, minor, patch]: [u32; 3]) -> String {
+    format!("{major}.{minor}.{patch}")
+}
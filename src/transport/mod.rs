@@ -12,6 +12,22 @@ pub trait Transport: Send + Sync {
     /// Read the next JSON message produced by the CLI.
     async fn read(&self) -> Result<Option<serde_json::Value>, crate::error::SdkError>;
 
+    /// [`Transport::read`], with the charge the message holds against
+    /// [`ClaudeAgentOptions::buffer_limit`] if the transport buffered it.
+    ///
+    /// The query keeps the charge until the consumer takes the message;
+    /// uncharged messages are charged by the query itself.
+    ///
+    /// [`ClaudeAgentOptions::buffer_limit`]: crate::config::ClaudeAgentOptions::buffer_limit
+    async fn read_charged(
+        &self,
+    ) -> Result<
+        Option<(serde_json::Value, Option<crate::buffer_limit::BufferCharge>)>,
+        crate::error::SdkError,
+    > {
+        Ok(self.read().await?.map(|value| (value, None)))
+    }
+
     /// Finish sending input to the CLI.
     async fn end_input(&self) -> Result<(), crate::error::SdkError>;
 
@@ -20,6 +36,89 @@ pub trait Transport: Send + Sync {
 
     /// Whether the transport is ready for IO.
     fn is_ready(&self) -> bool;
+
+    /// Subscribe to parsed stderr lines, if the transport has a stderr stream.
+    ///
+    /// Only lines written after subscribing are delivered.
+    fn subscribe_stderr(&self) -> Option<tokio::sync::broadcast::Receiver<stderr::StderrEvent>> {
+        None
+    }
+
+    /// Details about the underlying process, for bug reports.
+    async fn diagnostics(&self) -> Option<crate::error::ConnectionDiagnostics> {
+        None
+    }
+
+    /// Most recent stderr lines, oldest first.
+    async fn recent_stderr(&self) -> Vec<String> {
+        Vec::new()
+    }
+
+    /// The running CLI process, if the transport has one.
+    async fn process_info(&self) -> Option<ProcessInfo> {
+        None
+    }
+}
+
+/// Operating-system view of a running CLI process.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    /// When the SDK spawned the process.
+    pub started_at: std::time::SystemTime,
+    /// Resident set size in bytes, where the platform reports it.
+    pub rss_bytes: Option<u64>,
+}
+
+/// Mode describing how the prompt should be handled when starting the CLI.
+#[derive(Debug, Clone)]
+pub enum PromptMode {
+    Text(String),
+    Streaming,
+}
+
+/// Transport used when the caller supplies none: the CLI subprocess, wrapped
+/// in a [`RecoveringTransport`](crate::recovery::RecoveringTransport) when
+/// `crash_recovery` is set.
+#[cfg(not(target_arch = "wasm32"))]
+pub(crate) fn default_transport(
+    prompt: PromptMode,
+    options: crate::config::ClaudeAgentOptions,
+) -> Result<std::sync::Arc<dyn Transport>, crate::error::SdkError> {
+    let Some(recovery) = options.crash_recovery else {
+        let subprocess = subprocess_cli::SubprocessCliTransport::new(prompt, options)?;
+        return Ok(std::sync::Arc::new(subprocess));
+    };
+    let factory = move |resume: Option<String>| {
+        let mut options = options.clone();
+        if resume.is_some() {
+            if let Some(metrics) = &options.metrics {
+                metrics.process_restart();
+            }
+            options.resume = resume;
+            options.continue_conversation = false;
+            options.fork_session = false;
+        }
+        let subprocess = subprocess_cli::SubprocessCliTransport::new(prompt.clone(), options)?;
+        Ok(std::sync::Arc::new(subprocess) as std::sync::Arc<dyn Transport>)
+    };
+    let recovering =
+        crate::recovery::RecoveringTransport::new(std::sync::Arc::new(factory), recovery)?;
+    Ok(std::sync::Arc::new(recovering))
+}
+
+/// Processes cannot be spawned on wasm32, so a transport must be supplied.
+#[cfg(target_arch = "wasm32")]
+pub(crate) fn default_transport(
+    _prompt: PromptMode,
+    _options: crate::config::ClaudeAgentOptions,
+) -> Result<std::sync::Arc<dyn Transport>, crate::error::SdkError> {
+    Err(crate::error::SdkError::InvalidConfig(
+        "the CLI subprocess is unavailable on wasm32; pass a transport explicitly".into(),
+    ))
 }
 
+pub mod capabilities;
+pub mod stderr;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod subprocess_cli;
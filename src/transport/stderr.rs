@@ -0,0 +1,174 @@
+//! Structured view of the CLI's stderr output.
+//!
+//! With `--debug-to-stderr` the CLI writes lines such as
+//! `2025-06-01T10:00:00.000Z [ERROR] MCP server "files" Connection failed: ...`.
+//! [`StderrEvent::parse`] splits those into level, subsystem and message and
+//! recognises the handful of states worth showing to a user. Lines that do not
+//! follow the format still produce an event with the whole line as message.
+
+use std::fmt;
+
+/// Severity of a stderr line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum StderrLevel {
+    Debug,
+    Info,
+    Warn,
+    Error,
+}
+
+impl StderrLevel {
+    fn from_tag(tag: &str) -> Option<Self> {
+        match tag.to_ascii_uppercase().as_str() {
+            "DEBUG" | "TRACE" | "VERBOSE" => Some(Self::Debug),
+            "INFO" | "LOG" => Some(Self::Info),
+            "WARN" | "WARNING" => Some(Self::Warn),
+            "ERROR" | "FATAL" => Some(Self::Error),
+            _ => None,
+        }
+    }
+}
+
+/// Notable state change recognised in a stderr line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum StderrProgress {
+    /// The CLI started summarising the conversation.
+    CompactingContext,
+    /// An MCP server is being started or connected.
+    McpServerStarting { server: String },
+    /// An MCP server connected successfully.
+    McpServerConnected { server: String },
+    /// An MCP server could not be started or lost its connection.
+    McpServerFailed { server: String },
+}
+
+impl fmt::Display for StderrProgress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::CompactingContext => f.write_str("compacting context"),
+            Self::McpServerStarting { server } => write!(f, "starting MCP server {server}"),
+            Self::McpServerConnected { server } => write!(f, "MCP server {server} connected"),
+            Self::McpServerFailed { server } => write!(f, "MCP server {server} failed to start"),
+        }
+    }
+}
+
+/// One parsed line of CLI stderr.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StderrEvent {
+    pub level: StderrLevel,
+    /// Component the line came from, e.g. `mcp` or a bracketed tag.
+    pub subsystem: Option<String>,
+    /// Line text without timestamp, level and subsystem tags.
+    pub message: String,
+    pub progress: Option<StderrProgress>,
+    /// The line as written by the CLI.
+    pub raw: String,
+}
+
+impl StderrEvent {
+    /// Parse one line of stderr output.
+    pub fn parse(line: &str) -> Self {
+        let raw = line.trim_end().to_string();
+        let mut rest = strip_timestamp(raw.trim_start());
+
+        let mut level = None;
+        let mut subsystem = None;
+        while let Some((tag, after)) = bracket_tag(rest) {
+            match StderrLevel::from_tag(tag) {
+                Some(parsed) if level.is_none() => level = Some(parsed),
+                _ if subsystem.is_none() => subsystem = Some(tag.to_ascii_lowercase()),
+                _ => break,
+            }
+            rest = after;
+        }
+        let message = rest.trim().to_string();
+
+        let mcp_server = mcp_server_name(&message);
+        if subsystem.is_none() && mcp_server.is_some() {
+            subsystem = Some("mcp".to_string());
+        }
+        let level = level.unwrap_or_else(|| {
+            if message.starts_with("Error") || message.starts_with("error:") {
+                StderrLevel::Error
+            } else {
+                StderrLevel::Info
+            }
+        });
+        let progress = detect_progress(&message, mcp_server, level);
+
+        Self {
+            level,
+            subsystem,
+            message,
+            progress,
+            raw,
+        }
+    }
+}
+
+/// Drop a leading RFC 3339 timestamp such as `2025-06-01T10:00:00.000Z`.
+fn strip_timestamp(line: &str) -> &str {
+    let Some((first, rest)) = line.split_once(char::is_whitespace) else {
+        return line;
+    };
+    let looks_like_timestamp = first.len() >= 19
+        && first.as_bytes()[..4].iter().all(u8::is_ascii_digit)
+        && first.as_bytes()[4] == b'-'
+        && first.contains('T');
+    if looks_like_timestamp {
+        rest.trim_start()
+    } else {
+        line
+    }
+}
+
+/// Split `[tag] rest` into the tag and the remainder.
+fn bracket_tag(text: &str) -> Option<(&str, &str)> {
+    let inner = text.strip_prefix('[')?;
+    let end = inner.find(']')?;
+    let tag = &inner[..end];
+    if tag.is_empty() || tag.contains(char::is_whitespace) {
+        return None;
+    }
+    Some((tag, inner[end + 1..].trim_start()))
+}
+
+/// Server name from messages like `MCP server "files": ...`.
+fn mcp_server_name(message: &str) -> Option<String> {
+    let start = message.find("MCP server ")? + "MCP server ".len();
+    let rest = &message[start..];
+    let name = match rest.strip_prefix('"') {
+        Some(quoted) => &quoted[..quoted.find('"')?],
+        None => rest
+            .split(|c: char| c.is_whitespace() || c == ':')
+            .next()
+            .unwrap_or_default(),
+    };
+    (!name.is_empty()).then(|| name.to_string())
+}
+
+fn detect_progress(
+    message: &str,
+    mcp_server: Option<String>,
+    level: StderrLevel,
+) -> Option<StderrProgress> {
+    let lower = message.to_ascii_lowercase();
+    if let Some(server) = mcp_server {
+        return if level == StderrLevel::Error
+            || ["failed", "error", "disconnected", "timed out"]
+                .iter()
+                .any(|word| lower.contains(word))
+        {
+            Some(StderrProgress::McpServerFailed { server })
+        } else if lower.contains("connected") || lower.contains("successfully") {
+            Some(StderrProgress::McpServerConnected { server })
+        } else if lower.contains("starting") || lower.contains("connecting") {
+            Some(StderrProgress::McpServerStarting { server })
+        } else {
+            None
+        };
+    }
+    (lower.contains("compacting") || lower.contains("auto-compact"))
+        .then_some(StderrProgress::CompactingContext)
+}
@@ -1,45 +1,64 @@
 //! Subprocess-based transport implementation replicating the Python SDK behaviour.
 
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
 use std::ffi::OsString;
-use std::io::{ErrorKind, Write};
+use std::io::ErrorKind;
 use std::path::{Path, PathBuf};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
 use std::sync::Arc;
+use std::time::SystemTime;
 
 use serde_json::{json, Map, Value};
+#[cfg(feature = "tempfile")]
 use tempfile::{NamedTempFile, TempPath};
-use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader};
 use tokio::process::{Child, ChildStderr, ChildStdin, ChildStdout, Command};
-use tokio::sync::{mpsc, Mutex};
+use tokio::sync::{broadcast, mpsc, Mutex, Notify};
 use tokio::task::JoinHandle;
 use tokio::time::{timeout, Duration};
 
-#[cfg(unix)]
+#[cfg(all(unix, feature = "users"))]
 use users::get_user_by_name;
 
+use crate::buffer_limit::BufferCharge;
 use crate::config::{
     AgentDefinition, ClaudeAgentOptions, McpServerConfig, McpServers, SdkPluginKind, SettingSource,
-    SystemPrompt,
+    StdoutMode, SystemPrompt,
 };
 use crate::error::{
-    CliConnectionError, CliJsonDecodeError, CliNotFoundError, ProcessError, SdkError,
+    CliConnectionError, CliJsonDecodeError, CliNotFoundError, ConnectionDiagnostics, ProcessError,
+    SdkError, TimeoutOperation,
 };
-use crate::transport::Transport;
+use crate::internal::json_stream::{DecodeError, JsonStreamDecoder};
+use crate::internal::trace::{sdk_debug, sdk_error, sdk_warn};
+use crate::redact::RedactorHandle;
+use crate::transport::capabilities::{parse_version, CliCapabilities, CliFeature, Fallback};
+use crate::transport::stderr::StderrEvent;
+use crate::transport::{ProcessInfo, Transport};
 
 const DEFAULT_MAX_BUFFER_SIZE: usize = 1024 * 1024;
+const DEFAULT_ENTRYPOINT: &str = "sdk-rs";
+/// Bytes requested from stdout per read.
+const STDOUT_READ_CHUNK: usize = 64 * 1024;
 const MINIMUM_CLAUDE_CODE_VERSION: &str = "2.0.0";
+const VERSION_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+const STDERR_HEAD_LINES: usize = 20;
+const STDERR_TAIL_LINES: usize = 200;
+const STDERR_DRAIN_TIMEOUT: Duration = Duration::from_millis(200);
+const STDERR_EVENT_CAPACITY: usize = 256;
+const REDACTED_VALUE_FLAGS: &[&str] = &[
+    "--system-prompt",
+    "--append-system-prompt",
+    "--mcp-config",
+    "--settings",
+    "--agents",
+];
 #[cfg(windows)]
 const CMD_LENGTH_LIMIT: usize = 8_000;
 #[cfg(not(windows))]
 const CMD_LENGTH_LIMIT: usize = 100_000;
 
-/// Mode describing how the prompt should be handled when starting the CLI.
-#[derive(Debug, Clone)]
-pub enum PromptMode {
-    Text(String),
-    Streaming,
-}
+pub use super::PromptMode;
 
 /// Transport implementation backed by the Claude CLI subprocess.
 #[derive(Debug, Clone)]
@@ -55,10 +74,20 @@ struct Inner {
     cwd: Option<PathBuf>,
     max_buffer_size: usize,
     ready: AtomicBool,
-    temp_files: Mutex<Vec<tempfile::TempPath>>,
+    /// Id of the running CLI process; zero when there is none.
+    pid: AtomicU32,
+    started_at: Mutex<Option<SystemTime>>,
+    temp_files: Mutex<Vec<TempPath>>,
     child: Mutex<Option<ProcessHandles>>,
-    stdout_rx: Mutex<Option<mpsc::Receiver<Result<Value, SdkError>>>>,
+    stdout_rx: Mutex<Option<mpsc::Receiver<Result<StdoutMessage, SdkError>>>>,
     exit_error: Mutex<Option<SdkError>>,
+    cli_version: Mutex<Option<String>>,
+    argv: Mutex<Vec<String>>,
+    stderr_head: Mutex<Vec<String>>,
+    stderr_tail: Mutex<VecDeque<String>>,
+    stderr_eof: Notify,
+    stderr_events: broadcast::Sender<StderrEvent>,
+    redactor: RedactorHandle,
 }
 
 #[derive(Debug)]
@@ -72,6 +101,7 @@ struct ProcessHandles {
 impl SubprocessCliTransport {
     /// Create a new transport using the provided prompt and options.
     pub fn new(prompt: PromptMode, options: ClaudeAgentOptions) -> Result<Self, SdkError> {
+        options.validate()?;
         let cli_path = match &options.cli_path {
             Some(path) => path.clone(),
             None => find_cli()?,
@@ -79,6 +109,7 @@ impl SubprocessCliTransport {
 
         let cwd = options.cwd.clone();
         let max_buffer_size = options.max_buffer_size.unwrap_or(DEFAULT_MAX_BUFFER_SIZE);
+        let redactor = options.effective_redactor();
 
         Ok(Self {
             inner: Arc::new(Inner {
@@ -88,17 +119,43 @@ impl SubprocessCliTransport {
                 cwd,
                 max_buffer_size,
                 ready: AtomicBool::new(false),
+                pid: AtomicU32::new(0),
+                started_at: Mutex::new(None),
                 temp_files: Mutex::new(Vec::new()),
                 child: Mutex::new(None),
                 stdout_rx: Mutex::new(None),
                 exit_error: Mutex::new(None),
+                cli_version: Mutex::new(None),
+                argv: Mutex::new(Vec::new()),
+                stderr_head: Mutex::new(Vec::new()),
+                stderr_tail: Mutex::new(VecDeque::new()),
+                stderr_eof: Notify::new(),
+                stderr_events: broadcast::channel(STDERR_EVENT_CAPACITY).0,
+                redactor,
             }),
         })
     }
+
+    /// Id of the CLI process while connected, e.g. to attach a debugger or
+    /// apply external resource limits.
+    pub fn pid(&self) -> Option<u32> {
+        match self.inner.pid.load(Ordering::SeqCst) {
+            0 => None,
+            pid => Some(pid),
+        }
+    }
 }
 
 #[async_trait::async_trait]
 impl Transport for SubprocessCliTransport {
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "claude_sdk.transport.spawn",
+            skip_all,
+            fields(cli_path = %self.inner.cli_path.display())
+        )
+    )]
     async fn connect(&self) -> Result<(), SdkError> {
         {
             let child_guard = self.inner.child.lock().await;
@@ -107,15 +164,28 @@ impl Transport for SubprocessCliTransport {
             }
         }
 
+        let mut options_env = self.inner.options.env.clone();
+        if let Some(credentials) = &self.inner.options.credential_provider {
+            options_env.extend(credentials.credentials().await?);
+        }
+        if let Some(provider) = self.inner.options.provider {
+            provider
+                .validate(&options_env)
+                .map_err(|err| SdkError::InvalidConfig(err.to_string()))?;
+        }
+
         if std::env::var("CLAUDE_AGENT_SDK_SKIP_VERSION_CHECK").is_err() {
             self.inner.check_version().await?;
         }
 
-        let mut build = self.inner.build_command()?;
-        {
-            let mut temp_guard = self.inner.temp_files.lock().await;
-            temp_guard.extend(build.temp_files.drain(..));
-        }
+        let capabilities = match self.inner.cli_version.lock().await.as_deref() {
+            Some(version) => CliCapabilities::detect(version),
+            None => CliCapabilities::unknown(),
+        };
+        let mut build = self.inner.build_command(&capabilities, &options_env)?;
+
+        *self.inner.argv.lock().await = redact_argv(&build.args);
+        self.inner.stderr_head.lock().await.clear();
 
         let mut command = Command::new(&self.inner.cli_path);
         command.args(&build.args);
@@ -125,8 +195,16 @@ impl Transport for SubprocessCliTransport {
         }
 
         let mut env: HashMap<String, String> = std::env::vars().collect();
-        env.extend(self.inner.options.env.clone());
-        env.insert("CLAUDE_CODE_ENTRYPOINT".to_string(), "sdk-rs".to_string());
+        env.extend(build.env.drain(..));
+        env.extend(options_env);
+        if let Some(provider) = self.inner.options.provider {
+            provider.apply_to_env(&mut env);
+        }
+        let entrypoint = self.inner.options.entrypoint.as_deref();
+        env.insert(
+            "CLAUDE_CODE_ENTRYPOINT".to_string(),
+            entrypoint.unwrap_or(DEFAULT_ENTRYPOINT).to_string(),
+        );
         env.insert(
             "CLAUDE_AGENT_SDK_VERSION".to_string(),
             env!("CARGO_PKG_VERSION").to_string(),
@@ -138,48 +216,82 @@ impl Transport for SubprocessCliTransport {
             command.env(key, value);
         }
 
-        let should_pipe_stderr = should_pipe_stderr(&self.inner.options);
-        if should_pipe_stderr {
+        // Without a callback the CLI writes to our stderr directly, so the SDK
+        // never prints on the host's behalf; stderr diagnostics and events
+        // then stay empty.
+        if should_pipe_stderr(&self.inner.options) {
             command.stderr(std::process::Stdio::piped());
+        } else {
+            command.stderr(std::process::Stdio::inherit());
         }
 
         command.stdin(std::process::Stdio::piped());
         command.stdout(std::process::Stdio::piped());
 
-        #[cfg(unix)]
+        #[cfg(all(unix, feature = "users"))]
         if let Some(user) = &self.inner.options.user {
             if let Some(info) = get_user_by_name(user) {
                 command.uid(info.uid());
                 command.gid(info.primary_group_id());
             }
         }
+        #[cfg(all(unix, not(feature = "users")))]
+        if self.inner.options.user.is_some() {
+            return Err(SdkError::InvalidConfig(
+                "running the CLI as another user requires the `users` feature".into(),
+            ));
+        }
 
-        let mut child = command
-            .spawn()
-            .map_err(|err| CliConnectionError::new(format!("Failed to start Claude CLI: {err}")))?;
+        let mut child = match command.spawn() {
+            Ok(child) => child,
+            Err(err) => {
+                let hint = match err.kind() {
+                    ErrorKind::NotFound => {
+                        "check that the CLI exists at this path or set `cli_path` explicitly"
+                    }
+                    ErrorKind::PermissionDenied => "make sure the CLI binary is executable",
+                    _ => "run `claude doctor` to check the installation",
+                };
+                let diagnostics = self.inner.diagnostics(hint).await;
+                return Err(
+                    CliConnectionError::new(format!("Failed to start Claude CLI: {err}"))
+                        .with_diagnostics(diagnostics)
+                        .into(),
+                );
+            }
+        };
+
+        // Argument files live until `close`; on any earlier failure they are
+        // dropped, and deleted, with `build`.
+        self.inner
+            .temp_files
+            .lock()
+            .await
+            .extend(build.temp_files.drain(..));
 
         let stdout = child
             .stdout
             .take()
             .ok_or_else(|| CliConnectionError::new("Missing stdout handle from CLI process"))?;
         let stdin = child.stdin.take();
-        let stderr = if should_pipe_stderr {
-            child.stderr.take()
-        } else {
-            None
-        };
+        let stderr = child.stderr.take();
+        sdk_debug!({ pid = child.id() }, "transport: spawned CLI process");
+        *self.inner.started_at.lock().await = Some(SystemTime::now());
+        self.inner
+            .pid
+            .store(child.id().unwrap_or_default(), Ordering::SeqCst);
 
         let child_arc = Arc::new(Mutex::new(child));
         let stdin_arc = Arc::new(Mutex::new(stdin));
 
         if matches!(self.inner.prompt, PromptMode::Text(_)) {
-            log::debug!("[transport::connect] Text prompt mode - closing stdin immediately");
+            sdk_debug!("transport: text prompt mode, closing stdin immediately");
             let mut guard = stdin_arc.lock().await;
             if let Some(mut stdin) = guard.take() {
                 let _ = stdin.shutdown().await;
             }
         } else {
-            log::debug!("[transport::connect] Streaming mode - keeping stdin open for stream_input");
+            sdk_debug!("transport: streaming mode, keeping stdin open for stream_input");
         }
 
         let (tx, rx) = mpsc::channel(64);
@@ -227,16 +339,20 @@ impl Transport for SubprocessCliTransport {
         {
             let mut stdin_guard = handles.0.lock().await;
             if let Some(stdin) = stdin_guard.as_mut() {
-                log::debug!("[transport::write] stdin available, writing {} bytes", line.len());
+                sdk_debug!(
+                    { bytes = line.len() },
+                    "transport: writing {} bytes to stdin",
+                    line.len()
+                );
                 stdin.write_all(line.as_bytes()).await.map_err(|err| {
                     CliConnectionError::new(format!("Failed to write to process stdin: {err}"))
                 })?;
                 stdin.flush().await.map_err(|err| {
                     CliConnectionError::new(format!("Failed to flush process stdin: {err}"))
                 })?;
-                log::debug!("[transport::write] write successful");
+                sdk_debug!("transport: write successful");
             } else {
-                log::error!("[transport::write] stdin is None - was already closed!");
+                sdk_error!("transport: write after stdin was closed");
                 return Err(SdkError::from(CliConnectionError::new(
                     "Process stdin is not available",
                 )));
@@ -252,11 +368,10 @@ impl Transport for SubprocessCliTransport {
                     Some(code) => format!("Command failed with exit code {code}"),
                     None => "Command failed with unknown exit status".to_string(),
                 };
-                return Err(SdkError::from(ProcessError::new(
-                    message,
-                    status.code(),
-                    None,
-                )));
+                let diagnostics = self.inner.exit_diagnostics().await;
+                return Err(SdkError::from(
+                    ProcessError::new(message, status.code(), None).with_diagnostics(diagnostics),
+                ));
             }
         }
 
@@ -264,13 +379,17 @@ impl Transport for SubprocessCliTransport {
     }
 
     async fn read(&self) -> Result<Option<serde_json::Value>, SdkError> {
+        Ok(self.read_charged().await?.map(|(value, _charge)| value))
+    }
+
+    async fn read_charged(&self) -> Result<Option<StdoutMessage>, SdkError> {
         let mut rx_guard = self.inner.stdout_rx.lock().await;
         let rx = rx_guard
             .as_mut()
             .ok_or_else(|| CliConnectionError::new("Not connected"))?;
 
         match rx.recv().await {
-            Some(Ok(value)) => Ok(Some(value)),
+            Some(Ok(message)) => Ok(Some(message)),
             Some(Err(err)) => Err(err),
             None => {
                 let mut exit_error = self.inner.exit_error.lock().await;
@@ -284,7 +403,7 @@ impl Transport for SubprocessCliTransport {
     }
 
     async fn end_input(&self) -> Result<(), SdkError> {
-        log::debug!("[transport::end_input] Called - will close stdin");
+        sdk_debug!("transport: end_input called, closing stdin");
         let handles = {
             let child_guard = self.inner.child.lock().await;
             child_guard
@@ -295,14 +414,14 @@ impl Transport for SubprocessCliTransport {
 
         let mut stdin_guard = handles.lock().await;
         if let Some(mut stdin) = stdin_guard.take() {
-            log::debug!("[transport::end_input] Shutting down stdin now");
+            sdk_debug!("transport: shutting down stdin");
             stdin
                 .shutdown()
                 .await
                 .map_err(|err| CliConnectionError::new(format!("Failed to close stdin: {err}")))?;
-            log::debug!("[transport::end_input] stdin closed successfully");
+            sdk_debug!("transport: stdin closed");
         } else {
-            log::warn!("[transport::end_input] stdin was already None");
+            sdk_warn!("transport: end_input called but stdin was already closed");
         }
 
         Ok(())
@@ -310,6 +429,7 @@ impl Transport for SubprocessCliTransport {
 
     async fn close(&self) -> Result<(), SdkError> {
         self.inner.ready.store(false, Ordering::SeqCst);
+        self.inner.pid.store(0, Ordering::SeqCst);
 
         {
             let mut temp_guard = self.inner.temp_files.lock().await;
@@ -361,11 +481,67 @@ impl Transport for SubprocessCliTransport {
     fn is_ready(&self) -> bool {
         self.inner.ready.load(Ordering::SeqCst)
     }
+
+    fn subscribe_stderr(&self) -> Option<broadcast::Receiver<StderrEvent>> {
+        should_pipe_stderr(&self.inner.options).then(|| self.inner.stderr_events.subscribe())
+    }
+
+    async fn diagnostics(&self) -> Option<ConnectionDiagnostics> {
+        let mut diagnostics = self.inner.diagnostics("").await;
+        diagnostics.hint = None;
+        Some(diagnostics)
+    }
+
+    async fn recent_stderr(&self) -> Vec<String> {
+        self.inner
+            .stderr_tail
+            .lock()
+            .await
+            .iter()
+            .cloned()
+            .collect()
+    }
+
+    async fn process_info(&self) -> Option<ProcessInfo> {
+        let pid = self.pid()?;
+        Some(ProcessInfo {
+            pid,
+            started_at: (*self.inner.started_at.lock().await)?,
+            rss_bytes: resident_set_size(pid),
+        })
+    }
+}
+
+/// Resident set size of process `pid`, from `/proc/<pid>/status`.
+#[cfg(target_os = "linux")]
+fn resident_set_size(pid: u32) -> Option<u64> {
+    let status = std::fs::read_to_string(format!("/proc/{pid}/status")).ok()?;
+    let kib = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?
+        .trim()
+        .strip_suffix("kB")?
+        .trim()
+        .parse::<u64>()
+        .ok()?;
+    Some(kib * 1024)
+}
+
+#[cfg(not(target_os = "linux"))]
+fn resident_set_size(_pid: u32) -> Option<u64> {
+    None
 }
 
 impl Inner {
-    fn build_command(&self) -> Result<CommandBuild, SdkError> {
+    /// `options_env` is the environment given to the CLI on top of ours; MCP
+    /// server configs are interpolated against it.
+    fn build_command(
+        &self,
+        capabilities: &CliCapabilities,
+        options_env: &HashMap<String, String>,
+    ) -> Result<CommandBuild, SdkError> {
         let mut args: Vec<OsString> = Vec::new();
+        let mut env: Vec<(String, String)> = Vec::new();
         args.push(OsString::from("--output-format"));
         args.push(OsString::from("stream-json"));
         args.push(OsString::from("--verbose"));
@@ -398,8 +574,13 @@ impl Inner {
         }
 
         if let Some(max_budget) = self.options.max_budget_usd {
-            args.push(OsString::from("--max-budget-usd"));
-            args.push(max_budget.to_string().into());
+            push_flag(
+                &mut args,
+                &mut env,
+                capabilities,
+                CliFeature::MaxBudgetUsd,
+                Some(max_budget.to_string()),
+            )?;
         }
 
         if !self.options.disallowed_tools.is_empty() {
@@ -431,9 +612,9 @@ impl Inner {
             args.push(resume.clone().into());
         }
 
-        if let Some(settings) = &self.options.settings {
+        if let Some(settings) = self.options.effective_settings()? {
             args.push(OsString::from("--settings"));
-            args.push(settings.clone().into());
+            args.push(settings.into());
         }
 
         for directory in &self.options.add_dirs {
@@ -447,25 +628,53 @@ impl Inner {
             McpServers::Path(_) => true,
         };
 
+        // Flags whose value must not appear in the process list.
+        let mut secret_flags = Vec::new();
         if has_mcp_servers {
-            let mcp_arg = build_mcp_argument(&self.options.mcp_servers)?;
+            let lookup = |name: &str| {
+                options_env
+                    .get(name)
+                    .cloned()
+                    .or_else(|| std::env::var(name).ok())
+            };
+            let (mcp_arg, expanded) = build_mcp_argument(&self.options.mcp_servers, lookup)?;
+            if expanded {
+                secret_flags.push("--mcp-config");
+            }
             args.push(OsString::from("--mcp-config"));
             args.push(mcp_arg.into());
         }
 
         if self.options.include_partial_messages {
-            args.push(OsString::from("--include-partial-messages"));
+            push_flag(
+                &mut args,
+                &mut env,
+                capabilities,
+                CliFeature::IncludePartialMessages,
+                None,
+            )?;
         }
 
         if self.options.fork_session {
-            args.push(OsString::from("--fork-session"));
+            push_flag(
+                &mut args,
+                &mut env,
+                capabilities,
+                CliFeature::ForkSession,
+                None,
+            )?;
         }
 
         if let Some(agents) = &self.options.agents {
             if !agents.is_empty() {
                 let agents_json = build_agents_json(agents)?;
-                args.push(OsString::from("--agents"));
-                args.push(agents_json.into());
+                push_flag(
+                    &mut args,
+                    &mut env,
+                    capabilities,
+                    CliFeature::Agents,
+                    Some(agents_json),
+                )?;
             }
         }
 
@@ -475,15 +684,25 @@ impl Inner {
                 .map(SettingSource::as_str)
                 .collect::<Vec<_>>()
                 .join(",");
-            args.push(OsString::from("--setting-sources"));
-            args.push(sources_value.into());
+            push_flag(
+                &mut args,
+                &mut env,
+                capabilities,
+                CliFeature::SettingSources,
+                Some(sources_value),
+            )?;
         }
 
         for plugin in &self.options.plugins {
             match plugin.kind {
                 SdkPluginKind::Local => {
-                    args.push(OsString::from("--plugin-dir"));
-                    args.push(plugin.path.display().to_string().into());
+                    push_flag(
+                        &mut args,
+                        &mut env,
+                        capabilities,
+                        CliFeature::PluginDir,
+                        Some(plugin.path.display().to_string()),
+                    )?;
                 }
             }
         }
@@ -500,6 +719,9 @@ impl Inner {
             PromptMode::Streaming => {
                 args.push(OsString::from("--input-format"));
                 args.push(OsString::from("stream-json"));
+                if self.options.replay_user_messages {
+                    args.push(OsString::from("--replay-user-messages"));
+                }
             }
             PromptMode::Text(prompt) => {
                 args.push(OsString::from("--print"));
@@ -508,45 +730,56 @@ impl Inner {
             }
         }
 
-        if let Some(max_thinking) = self.options.max_thinking_tokens {
-            args.push(OsString::from("--max-thinking-tokens"));
-            args.push(max_thinking.to_string().into());
+        if let Some(max_thinking) = self.options.effective_max_thinking_tokens() {
+            push_flag(
+                &mut args,
+                &mut env,
+                capabilities,
+                CliFeature::MaxThinkingTokens,
+                Some(max_thinking.to_string()),
+            )?;
         }
 
-        let mut temp_files: Vec<TempPath> = Vec::new();
-        let cmd_len = command_length(&self.cli_path, &args);
-        if cmd_len > CMD_LENGTH_LIMIT {
-            if let Some(position) = args.iter().position(|arg| arg == "--agents") {
-                if position + 1 < args.len() {
-                    let agents_json = args[position + 1].to_string_lossy().to_string();
-                    let mut temp_file = NamedTempFile::new()?;
-                    temp_file.write_all(agents_json.as_bytes())?;
-                    let temp_path = temp_file.into_temp_path();
-                    let replacement = format!("@{}", temp_path.display());
-                    args[position + 1] = replacement.into();
-                    temp_files.push(temp_path);
-                }
-            }
-        }
-
-        Ok(CommandBuild { args, temp_files })
+        let threshold = self.options.arg_file_threshold.unwrap_or(CMD_LENGTH_LIMIT);
+        let temp_files = spill_long_arguments(&self.cli_path, &mut args, threshold, &secret_flags)?;
+        Ok(CommandBuild {
+            args,
+            env,
+            temp_files,
+        })
     }
 
     async fn check_version(&self) -> Result<(), SdkError> {
         let output = match timeout(
-            Duration::from_secs(2),
+            VERSION_CHECK_TIMEOUT,
             Command::new(&self.cli_path).arg("-v").output(),
         )
         .await
         {
             Ok(Ok(output)) => output,
-            _ => return Ok(()),
+            Ok(Err(err)) => {
+                sdk_debug!("transport: version check skipped: {err}");
+                return Ok(());
+            }
+            Err(_) => {
+                // A slow `claude -v` must not block startup; report and move on.
+                let err = SdkError::Timeout {
+                    operation: TimeoutOperation::VersionCheck,
+                    elapsed: VERSION_CHECK_TIMEOUT,
+                };
+                sdk_debug!("transport: version check skipped: {err}");
+                return Ok(());
+            }
         };
 
         let stdout = String::from_utf8_lossy(&output.stdout);
+        let version = stdout.trim();
+        if !version.is_empty() {
+            *self.cli_version.lock().await = Some(version.to_string());
+        }
         if let (Some(current), Some(minimum)) = (
-            parse_version_components(&stdout),
-            parse_version_components(MINIMUM_CLAUDE_CODE_VERSION),
+            parse_version(&stdout),
+            parse_version(MINIMUM_CLAUDE_CODE_VERSION),
         ) {
             if current < minimum {
                 eprintln!(
@@ -558,19 +791,171 @@ impl Inner {
 
         Ok(())
     }
+
+    async fn diagnostics(&self, hint: &str) -> ConnectionDiagnostics {
+        ConnectionDiagnostics {
+            cli_path: Some(self.cli_path.clone()),
+            cli_version: self.cli_version.lock().await.clone(),
+            argv: self
+                .argv
+                .lock()
+                .await
+                .iter()
+                .map(|arg| self.redactor.redact(arg).into_owned())
+                .collect(),
+            cwd: self.cwd.clone().or_else(|| std::env::current_dir().ok()),
+            stderr_head: self.stderr_head.lock().await.clone(),
+            hint: Some(hint.to_string()),
+        }
+    }
+
+    /// Diagnostics for a CLI that exited unsuccessfully, once stderr has drained.
+    async fn exit_diagnostics(&self) -> ConnectionDiagnostics {
+        let _ = timeout(STDERR_DRAIN_TIMEOUT, self.stderr_eof.notified()).await;
+        let stderr_head = self.stderr_head.lock().await;
+        let mentions_auth = stderr_head.iter().any(|line| {
+            let line = line.to_ascii_lowercase();
+            line.contains("login") || line.contains("api key") || line.contains("auth")
+        });
+        drop(stderr_head);
+
+        let hint = if mentions_auth {
+            "run `claude login` or set ANTHROPIC_API_KEY"
+        } else {
+            "run `claude doctor` to check the installation"
+        };
+        self.diagnostics(hint).await
+    }
 }
 
+/// A decoded stdout value and the buffer space it holds.
+type StdoutMessage = (Value, Option<BufferCharge>);
+
 struct CommandBuild {
     args: Vec<OsString>,
+    /// Variables standing in for flags the CLI is too old to accept.
+    env: Vec<(String, String)>,
     temp_files: Vec<TempPath>,
 }
 
+/// Pass `feature`'s flag, or its fallback when the CLI is too old for it.
+fn push_flag(
+    args: &mut Vec<OsString>,
+    env: &mut Vec<(String, String)>,
+    capabilities: &CliCapabilities,
+    feature: CliFeature,
+    value: Option<String>,
+) -> Result<(), SdkError> {
+    match capabilities.fallback_for(feature)? {
+        None => {
+            args.push(OsString::from(feature.flag()));
+            args.extend(value.map(OsString::from));
+        }
+        Some(Fallback::Env(var)) => {
+            sdk_debug!("transport: passing {feature} as {var} for an older CLI");
+            env.push((var.to_string(), value.unwrap_or_default()));
+        }
+        Some(_) => {
+            sdk_warn!("transport: the installed CLI does not support {feature}; leaving it out");
+        }
+    }
+    Ok(())
+}
+
+/// Flags whose value may move into a temporary file, and whether the CLI
+/// takes that file as `@path` or as a plain path.
+#[cfg(feature = "tempfile")]
+const SPILLABLE_FLAGS: &[(&str, bool)] = &[
+    ("--agents", true),
+    ("--system-prompt", true),
+    ("--append-system-prompt", true),
+    ("--mcp-config", false),
+    ("--settings", false),
+];
+
+/// Move the values of `secret_flags`, then the largest spillable values,
+/// into temporary files until the command line fits in `threshold` bytes.
+///
+/// The returned paths delete their files when dropped, so they must outlive
+/// the CLI's startup.
+#[cfg(feature = "tempfile")]
+fn spill_long_arguments(
+    cli_path: &Path,
+    args: &mut [OsString],
+    threshold: usize,
+    secret_flags: &[&str],
+) -> Result<Vec<TempPath>, SdkError> {
+    use std::io::Write as _;
+
+    let mut candidates: Vec<(usize, bool, bool)> = args
+        .windows(2)
+        .enumerate()
+        .filter_map(|(position, pair)| {
+            let (_, at_file) = SPILLABLE_FLAGS.iter().find(|(flag, _)| pair[0] == *flag)?;
+            // Paths already name a file; only inline values are moved.
+            let value = pair[1].to_string_lossy();
+            let inline = if *at_file {
+                !value.is_empty() && !value.starts_with('@')
+            } else {
+                value.trim_start().starts_with('{')
+            };
+            let secret = secret_flags.iter().any(|flag| pair[0] == *flag);
+            inline.then_some((position + 1, *at_file, secret))
+        })
+        .collect();
+    candidates
+        .sort_by_key(|(position, _, secret)| (!secret, std::cmp::Reverse(args[*position].len())));
+
+    let mut temp_files = Vec::new();
+    for (position, at_file, secret) in candidates {
+        if !secret && command_length(cli_path, args) <= threshold {
+            break;
+        }
+        let mut temp_file = NamedTempFile::new()?;
+        temp_file.write_all(args[position].to_string_lossy().as_bytes())?;
+        let temp_path = temp_file.into_temp_path();
+        sdk_debug!(
+            "transport: passing {} through {}",
+            args[position - 1].to_string_lossy(),
+            temp_path.display()
+        );
+        args[position] = if at_file {
+            format!("@{}", temp_path.display()).into()
+        } else {
+            temp_path.as_os_str().to_owned()
+        };
+        temp_files.push(temp_path);
+    }
+    Ok(temp_files)
+}
+
+/// Without `tempfile` no argument files are written.
+#[cfg(not(feature = "tempfile"))]
+type TempPath = std::convert::Infallible;
+
+#[cfg(not(feature = "tempfile"))]
+fn spill_long_arguments(
+    cli_path: &Path,
+    args: &mut [OsString],
+    threshold: usize,
+    secret_flags: &[&str],
+) -> Result<Vec<TempPath>, SdkError> {
+    for flag in secret_flags {
+        sdk_warn!("transport: {flag} holds expanded environment variables and is visible in the process list; enable the `tempfile` feature to pass it through a file");
+    }
+    if command_length(cli_path, args) > threshold {
+        sdk_warn!("transport: command line exceeds {threshold} bytes; enable the `tempfile` feature to pass large arguments through files");
+    }
+    Ok(Vec::new())
+}
+
 fn find_cli() -> Result<PathBuf, SdkError> {
+    #[cfg(feature = "which")]
     if let Ok(path) = which::which("claude") {
         return Ok(path);
     }
 
-    let home = dirs::home_dir();
+    let home = crate::internal::home_dir();
     let mut locations: Vec<PathBuf> = Vec::new();
 
     if let Some(ref home_dir) = home {
@@ -595,12 +980,18 @@ fn find_cli() -> Result<PathBuf, SdkError> {
     )))
 }
 
-fn build_mcp_argument(servers: &McpServers) -> Result<String, SdkError> {
+/// `--mcp-config` value, and whether it holds values expanded from
+/// `${VAR}` references.
+fn build_mcp_argument(
+    servers: &McpServers,
+    lookup: impl Fn(&str) -> Option<String>,
+) -> Result<(String, bool), SdkError> {
     match servers {
-        McpServers::Inline(inline) => Ok(inline.clone()),
-        McpServers::Path(path) => Ok(path.display().to_string()),
+        McpServers::Inline(inline) => Ok((inline.clone(), false)),
+        McpServers::Path(path) => Ok((path.display().to_string(), false)),
         McpServers::Map(map) => {
             let mut mcp_servers = Map::new();
+            let mut expanded = false;
             for (name, config) in map {
                 let value = match config {
                     McpServerConfig::Sdk(sdk) => {
@@ -610,13 +1001,16 @@ fn build_mcp_argument(servers: &McpServers) -> Result<String, SdkError> {
                         }
                         sdk_value
                     }
-                    _ => serde_json::to_value(config)?,
+                    _ => {
+                        let interpolated = config.interpolate_env(&lookup)?;
+                        expanded |= interpolated != *config;
+                        serde_json::to_value(interpolated)?
+                    }
                 };
                 mcp_servers.insert(name.clone(), value);
             }
-            Ok(serde_json::to_string(
-                &json!({ "mcpServers": mcp_servers }),
-            )?)
+            let json = serde_json::to_string(&json!({ "mcpServers": mcp_servers }))?;
+            Ok((json, expanded))
         }
     }
 }
@@ -634,8 +1028,29 @@ fn build_agents_json(agents: &HashMap<String, AgentDefinition>) -> Result<String
     Ok(serde_json::to_string(&Value::Object(root))?)
 }
 
-fn should_pipe_stderr(options: &ClaudeAgentOptions) -> bool {
-    options.stderr.is_some() || options.extra_args.contains_key("debug-to-stderr")
+/// Render argv for diagnostics without leaking prompts or inline configuration.
+fn redact_argv(args: &[OsString]) -> Vec<String> {
+    let mut redacted = Vec::with_capacity(args.len());
+    let mut redact_next = false;
+    let mut after_separator = false;
+    for arg in args {
+        let text = arg.to_string_lossy();
+        if after_separator {
+            redacted.push("<prompt>".to_string());
+        } else if redact_next {
+            redact_next = false;
+            if text.starts_with('@') || text.is_empty() {
+                redacted.push(text.into_owned());
+            } else {
+                redacted.push(format!("<redacted {} bytes>", text.len()));
+            }
+        } else {
+            after_separator = text == "--";
+            redact_next = REDACTED_VALUE_FLAGS.contains(&text.as_ref());
+            redacted.push(text.into_owned());
+        }
+    }
+    redacted
 }
 
 fn command_length(cli_path: &Path, args: &[OsString]) -> usize {
@@ -645,80 +1060,24 @@ fn command_length(cli_path: &Path, args: &[OsString]) -> usize {
     parts.join(" ").len()
 }
 
-fn parse_version_components(input: &str) -> Option<[u32; 3]> {
-    let token = input
-        .split_whitespace()
-        .find(|segment| segment.chars().all(|ch| ch.is_ascii_digit() || ch == '.'))?;
-
-    let mut parts = token.split('.');
-    let major = parts.next()?.parse().ok()?;
-    let minor = parts.next().unwrap_or("0").parse().ok()?;
-    let patch = parts.next().unwrap_or("0").parse().ok()?;
-    Some([major, minor, patch])
-}
-
 fn spawn_stdout_task(
     inner: Arc<Inner>,
     child: Arc<Mutex<Child>>,
     stdout: ChildStdout,
-    sender: mpsc::Sender<Result<Value, SdkError>>,
+    sender: mpsc::Sender<Result<StdoutMessage, SdkError>>,
 ) -> JoinHandle<()> {
     tokio::spawn(async move {
-        let mut reader = BufReader::new(stdout);
-        let mut buffer = String::new();
-        let mut json_buffer = String::new();
+        let mut stdout = stdout;
+        let mut decoder = JsonStreamDecoder::new(inner.max_buffer_size);
+        if inner.options.stdout_mode == StdoutMode::Tolerant {
+            decoder = decoder.discarding_noise();
+        }
+        let mut chunk = vec![0u8; STDOUT_READ_CHUNK];
 
         loop {
-            buffer.clear();
-            match reader.read_line(&mut buffer).await {
+            let decoded = match stdout.read(&mut chunk).await {
                 Ok(0) => break,
-                Ok(_) => {
-                    for fragment in buffer.split('\n') {
-                        let fragment = fragment.trim();
-                        if fragment.is_empty() {
-                            continue;
-                        }
-                        json_buffer.push_str(fragment);
-                        if json_buffer.len() > inner.max_buffer_size {
-                            let err_message = format!(
-                                "Buffer size {} exceeds limit {}",
-                                json_buffer.len(),
-                                inner.max_buffer_size
-                            );
-                            let snapshot = json_buffer.clone();
-
-                            let send_error = CliJsonDecodeError::new(
-                                snapshot.clone(),
-                                serde_json::Error::io(std::io::Error::new(
-                                    ErrorKind::InvalidData,
-                                    err_message.clone(),
-                                )),
-                            );
-                            let _ = sender.send(Err(SdkError::from(send_error))).await;
-
-                            let stored_error = CliJsonDecodeError::new(
-                                snapshot,
-                                serde_json::Error::io(std::io::Error::new(
-                                    ErrorKind::InvalidData,
-                                    err_message,
-                                )),
-                            );
-                            *inner.exit_error.lock().await = Some(SdkError::from(stored_error));
-
-                            json_buffer.clear();
-                            continue;
-                        }
-                        match serde_json::from_str::<Value>(&json_buffer) {
-                            Ok(value) => {
-                                json_buffer.clear();
-                                if sender.send(Ok(value)).await.is_err() {
-                                    return;
-                                }
-                            }
-                            Err(_) => continue,
-                        }
-                    }
-                }
+                Ok(read) => decoder.push(&chunk[..read]),
                 Err(err) => {
                     let _ = sender
                         .send(Err(SdkError::from(CliConnectionError::new(format!(
@@ -727,8 +1086,14 @@ fn spawn_stdout_task(
                         .await;
                     return;
                 }
+            };
+            if !forward_decoded(&inner, &sender, decoded).await {
+                return;
             }
         }
+        if !forward_decoded(&inner, &sender, decoder.finish()).await {
+            return;
+        }
 
         let status = {
             let mut child_guard = child.lock().await;
@@ -738,6 +1103,7 @@ fn spawn_stdout_task(
         match status {
             Ok(status) => {
                 if !status.success() {
+                    let diagnostics = inner.exit_diagnostics().await;
                     let error = ProcessError::new(
                         match status.code() {
                             Some(code) => format!("Command failed with exit code {code}"),
@@ -745,7 +1111,8 @@ fn spawn_stdout_task(
                         },
                         status.code(),
                         None,
-                    );
+                    )
+                    .with_diagnostics(diagnostics);
                     *inner.exit_error.lock().await = Some(SdkError::from(error.clone()));
                     let _ = sender.send(Err(SdkError::from(error))).await;
                 }
@@ -761,6 +1128,61 @@ fn spawn_stdout_task(
     })
 }
 
+/// Send decoded stdout values on; `false` once the receiver is gone.
+async fn forward_decoded(
+    inner: &Inner,
+    sender: &mpsc::Sender<Result<StdoutMessage, SdkError>>,
+    decoded: Vec<Result<Value, DecodeError>>,
+) -> bool {
+    for item in decoded {
+        let error = match item {
+            Ok(value) => {
+                let charge = match &inner.options.buffer_limit {
+                    Some(limit) => match limit.admit(&value).await {
+                        Ok(Some(charge)) => Some(charge),
+                        Ok(None) => continue,
+                        Err(err) => {
+                            let _ = sender.send(Err(err)).await;
+                            return false;
+                        }
+                    },
+                    None => None,
+                };
+                if sender.send(Ok((value, charge))).await.is_err() {
+                    return false;
+                }
+                continue;
+            }
+            Err(DecodeError::Overflow { text, len, limit }) => {
+                let message = format!("Buffer size {len} exceeds limit {limit}");
+                let snapshot = inner.redactor.redact(&text).into_owned();
+                let overflow = |message: &str| {
+                    CliJsonDecodeError::new(
+                        snapshot.clone(),
+                        serde_json::Error::io(std::io::Error::new(
+                            ErrorKind::InvalidData,
+                            message.to_string(),
+                        )),
+                    )
+                };
+                *inner.exit_error.lock().await = Some(SdkError::from(overflow(&message)));
+                overflow(&message)
+            }
+            Err(DecodeError::Invalid { text, error }) => {
+                CliJsonDecodeError::new(inner.redactor.redact(&text).into_owned(), error)
+            }
+            Err(DecodeError::Noise { text }) => {
+                let text = inner.redactor.redact(&text).into_owned();
+                sdk_debug!("transport: discarded non-JSON stdout line: {text}");
+                forward_stderr(inner, &text);
+                continue;
+            }
+        };
+        let _ = sender.send(Err(SdkError::from(error))).await;
+    }
+    true
+}
+
 fn spawn_stderr_task(inner: Arc<Inner>, stderr: ChildStderr) -> JoinHandle<()> {
     tokio::spawn(async move {
         let mut reader = BufReader::new(stderr);
@@ -772,22 +1194,47 @@ fn spawn_stderr_task(inner: Arc<Inner>, stderr: ChildStderr) -> JoinHandle<()> {
             .filter(|len| *len > 0)
             .is_some()
         {
-            let text = line.trim_end().to_string();
+            let text = inner.redactor.redact(line.trim_end()).into_owned();
             line.clear();
             if text.is_empty() {
                 continue;
             }
-            if let Some(callback) = inner.options.stderr.as_ref() {
-                callback(&text);
-            } else if inner.options.extra_args.contains_key("debug-to-stderr") {
-                if let Some(callback) = inner.options.debug_stderr.as_ref() {
-                    callback(&text);
+            {
+                let mut head = inner.stderr_head.lock().await;
+                if head.len() < STDERR_HEAD_LINES {
+                    head.push(text.clone());
                 }
+                drop(head);
+                let mut tail = inner.stderr_tail.lock().await;
+                if tail.len() == STDERR_TAIL_LINES {
+                    tail.pop_front();
+                }
+                tail.push_back(text.clone());
+            }
+            if inner.stderr_events.receiver_count() > 0 {
+                let _ = inner.stderr_events.send(StderrEvent::parse(&text));
             }
+            forward_stderr(&inner, &text);
         }
+        inner.stderr_eof.notify_one();
     })
 }
 
+fn should_pipe_stderr(options: &ClaudeAgentOptions) -> bool {
+    options.stderr.is_some() || options.extra_args.contains_key("debug-to-stderr")
+}
+
+/// Hand `text` to the `stderr` callback, if one is set.
+fn forward_stderr(inner: &Inner, text: &str) {
+    if let Some(callback) = inner.options.stderr.as_ref() {
+        callback(text);
+    } else if inner.options.extra_args.contains_key("debug-to-stderr") {
+        if let Some(callback) = inner.options.debug_stderr.as_ref() {
+            callback(text);
+        }
+    }
+}
+
 impl SettingSource {
     fn as_str(&self) -> &'static str {
         match self {
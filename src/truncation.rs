@@ -0,0 +1,173 @@
+//! Size limits for tool results sent back to the CLI.
+//!
+//! A tool that returns a whole log file or a large query result fills the
+//! model's context in one turn. [`ToolResultLimit`] caps each text item of a
+//! tool result and replaces the cut with a marker naming how many bytes were
+//! dropped, so the model knows the output is partial.
+//!
+//! Set it as [`ClaudeAgentOptions::tool_result_limit`](crate::config::ClaudeAgentOptions::tool_result_limit)
+//! and it applies to results returned by in-process SDK MCP tools and to
+//! `tool_result` blocks in user messages streamed to the CLI. Images are left
+//! untouched.
+
+use std::borrow::Cow;
+
+use serde_json::Value;
+
+use crate::mcp::{McpToolCallResult, McpToolContent};
+
+/// Which part of an oversized result is kept.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum TruncationStrategy {
+    /// Keep the beginning.
+    #[default]
+    Head,
+    /// Keep the beginning and the end, which preserves trailing errors and
+    /// summaries in command output.
+    HeadAndTail,
+}
+
+/// Per-item byte limit for tool result text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolResultLimit {
+    /// Largest text item passed through unchanged, in bytes.
+    pub max_bytes: usize,
+    pub strategy: TruncationStrategy,
+}
+
+impl Default for ToolResultLimit {
+    fn default() -> Self {
+        Self::new(Self::DEFAULT_MAX_BYTES)
+    }
+}
+
+impl ToolResultLimit {
+    pub const DEFAULT_MAX_BYTES: usize = 100 * 1024;
+
+    pub fn new(max_bytes: usize) -> Self {
+        Self {
+            max_bytes,
+            strategy: TruncationStrategy::default(),
+        }
+    }
+
+    pub fn with_strategy(mut self, strategy: TruncationStrategy) -> Self {
+        self.strategy = strategy;
+        self
+    }
+
+    /// `text` cut to about `max_bytes` with a truncation marker, or borrowed
+    /// unchanged when it fits. Cuts fall on character boundaries.
+    pub fn truncate<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        if text.len() <= self.max_bytes {
+            return Cow::Borrowed(text);
+        }
+        match self.strategy {
+            TruncationStrategy::Head => {
+                let head = floor_boundary(text, self.max_bytes);
+                let omitted = text.len() - head;
+                Cow::Owned(format!(
+                    "{}\n[... truncated {omitted} bytes ...]",
+                    &text[..head]
+                ))
+            }
+            TruncationStrategy::HeadAndTail => {
+                let head = floor_boundary(text, self.max_bytes / 2);
+                let tail = ceil_boundary(text, text.len() - (self.max_bytes - self.max_bytes / 2));
+                let omitted = tail - head;
+                Cow::Owned(format!(
+                    "{}\n[... truncated {omitted} bytes ...]\n{}",
+                    &text[..head],
+                    &text[tail..]
+                ))
+            }
+        }
+    }
+
+    /// Truncate the text items of an SDK MCP tool result. JSON items whose
+    /// rendering is over the limit become truncated text. Returns whether
+    /// anything was cut.
+    pub fn apply_to_mcp_result(&self, result: &mut McpToolCallResult) -> bool {
+        let mut truncated = false;
+        for item in &mut result.content {
+            let replacement = match item {
+                McpToolContent::Text { text } => match self.truncate(text) {
+                    Cow::Owned(cut) => cut,
+                    Cow::Borrowed(_) => continue,
+                },
+                McpToolContent::Json { value } => {
+                    let rendered = value.to_string();
+                    match self.truncate(&rendered) {
+                        Cow::Owned(cut) => cut,
+                        Cow::Borrowed(_) => continue,
+                    }
+                }
+                McpToolContent::Image { .. } => continue,
+            };
+            *item = McpToolContent::Text { text: replacement };
+            truncated = true;
+        }
+        truncated
+    }
+
+    /// Truncate `tool_result` blocks in a raw user message. Returns whether
+    /// anything was cut.
+    pub fn apply_to_message(&self, message: &mut Value) -> bool {
+        if message.get("type").and_then(Value::as_str) != Some("user") {
+            return false;
+        }
+        let Some(blocks) = message
+            .pointer_mut("/message/content")
+            .and_then(Value::as_array_mut)
+        else {
+            return false;
+        };
+        let mut truncated = false;
+        for block in blocks {
+            if block.get("type").and_then(Value::as_str) != Some("tool_result") {
+                continue;
+            }
+            match block.get_mut("content") {
+                Some(content @ Value::String(_)) => truncated |= self.truncate_value(content),
+                Some(Value::Array(items)) => {
+                    for item in items {
+                        if item.get("type").and_then(Value::as_str) == Some("text") {
+                            if let Some(text) = item.get_mut("text") {
+                                truncated |= self.truncate_value(text);
+                            }
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        truncated
+    }
+
+    fn truncate_value(&self, value: &mut Value) -> bool {
+        let Some(text) = value.as_str() else {
+            return false;
+        };
+        match self.truncate(text) {
+            Cow::Owned(cut) => {
+                *value = Value::String(cut);
+                true
+            }
+            Cow::Borrowed(_) => false,
+        }
+    }
+}
+
+fn floor_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
+}
+
+fn ceil_boundary(text: &str, mut index: usize) -> usize {
+    while !text.is_char_boundary(index) {
+        index += 1;
+    }
+    index
+}
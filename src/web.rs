@@ -0,0 +1,238 @@
+//! Server-Sent Events for web chat backends (`web` feature).
+//!
+//! [`SseFramer`] turns the messages of a response into a small set of named
+//! events a browser can render without knowing the CLI's message model:
+//!
+//! | event | data |
+//! | --- | --- |
+//! | `text` | `{"text": "..."}`, a chunk of assistant text |
+//! | `thinking` | `{"thinking": "..."}` |
+//! | `tool_use` | `{"id", "name", "input"}` |
+//! | `tool_result` | `{"tool_use_id", "content", "is_error"}` |
+//! | `result` | `{"subtype", "is_error", "result", "session_id", "num_turns", "duration_ms", "total_cost_usd"}` |
+//! | `error` | `{"message": "..."}`, after which the stream ends |
+//!
+//! With `include_partial_messages` text arrives delta by delta; otherwise
+//! each assistant text block is sent as one `text` event. Events for
+//! subagent messages carry their `parent_tool_use_id`.
+//!
+//! [`sse_bytes`] encodes a stream such as
+//! [`ClaudeSdkClient::receive_response`](crate::client::ClaudeSdkClient::receive_response)
+//! as `text/event-stream` chunks for any HTTP framework, and [`sse_response`]
+//! wraps it in an axum [`Sse`] response:
+//!
+//! ```no_run
+//! use axum::response::IntoResponse;
+//! use axum::{routing::post, Router};
+//! use sdk_claude_rust::query::query;
+//! use sdk_claude_rust::web::sse_response;
+//!
+//! async fn chat(prompt: String) -> axum::response::Response {
+//!     match query(prompt, None, None).await {
+//!         Ok(messages) => sse_response(messages).into_response(),
+//!         Err(err) => (axum::http::StatusCode::BAD_GATEWAY, err.to_string()).into_response(),
+//!     }
+//! }
+//!
+//! let app: Router = Router::new().route("/chat", post(chat));
+//! ```
+
+use std::convert::Infallible;
+
+use axum::response::sse::{Event, KeepAlive, Sse};
+use bytes::Bytes;
+use futures::stream::{self, Stream, StreamExt};
+use serde_json::{json, Value};
+
+use crate::error::SdkError;
+use crate::message::{ContentBlock, Message, UserMessageContent};
+
+/// One named Server-Sent Event with a JSON payload.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SseEvent {
+    pub event: &'static str,
+    pub data: Value,
+}
+
+impl SseEvent {
+    fn new(event: &'static str, data: Value) -> Self {
+        Self { event, data }
+    }
+
+    /// `error` event for a failed response.
+    pub fn error(error: &SdkError) -> Self {
+        Self::new("error", json!({ "message": error.to_string() }))
+    }
+
+    /// Wire form: `event: <name>\ndata: <json>\n\n`.
+    pub fn to_frame(&self) -> String {
+        format!("event: {}\ndata: {}\n\n", self.event, self.data)
+    }
+
+    /// The same event for axum's [`Sse`] response.
+    pub fn to_axum_event(&self) -> Event {
+        Event::default()
+            .event(self.event)
+            .data(self.data.to_string())
+    }
+}
+
+/// Maps response messages to [`SseEvent`]s.
+#[derive(Debug, Clone, Default)]
+pub struct SseFramer {
+    /// Text or thinking deltas were sent since the last assistant message,
+    /// so its complete blocks would repeat them.
+    streamed_text: bool,
+    streamed_thinking: bool,
+}
+
+impl SseFramer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Events for `message`; messages without anything to show (system
+    /// messages, other stream events) produce none.
+    pub fn frame(&mut self, message: &Message) -> Vec<SseEvent> {
+        match message {
+            Message::StreamEvent(event) => self
+                .frame_delta(&event.event)
+                .map(|sse| with_parent(sse, event.parent_tool_use_id.as_deref()))
+                .into_iter()
+                .collect(),
+            Message::Assistant(assistant) => {
+                let parent = assistant.parent_tool_use_id.as_deref();
+                let mut events = Vec::new();
+                for block in &assistant.content {
+                    let sse = match block {
+                        ContentBlock::Text(text) if !self.streamed_text => {
+                            SseEvent::new("text", json!({ "text": text.text }))
+                        }
+                        ContentBlock::Thinking(thinking) if !self.streamed_thinking => {
+                            SseEvent::new("thinking", json!({ "thinking": thinking.thinking }))
+                        }
+                        ContentBlock::ToolUse(tool) => SseEvent::new(
+                            "tool_use",
+                            json!({ "id": tool.id, "name": tool.name, "input": tool.input }),
+                        ),
+                        ContentBlock::ToolResult(result) => tool_result(
+                            &result.tool_use_id,
+                            result.content.as_ref(),
+                            result.is_error,
+                        ),
+                        _ => continue,
+                    };
+                    events.push(with_parent(sse, parent));
+                }
+                self.streamed_text = false;
+                self.streamed_thinking = false;
+                events
+            }
+            Message::User(user) => match &user.content {
+                UserMessageContent::Blocks(blocks) => blocks
+                    .iter()
+                    .filter_map(|block| match block {
+                        ContentBlock::ToolResult(result) => Some(tool_result(
+                            &result.tool_use_id,
+                            result.content.as_ref(),
+                            result.is_error,
+                        )),
+                        _ => None,
+                    })
+                    .map(|sse| with_parent(sse, user.parent_tool_use_id.as_deref()))
+                    .collect(),
+                UserMessageContent::Text(_) => Vec::new(),
+            },
+            Message::Result(result) => vec![SseEvent::new(
+                "result",
+                json!({
+                    "subtype": result.subtype,
+                    "is_error": result.is_error,
+                    "result": result.result,
+                    "session_id": result.session_id,
+                    "num_turns": result.num_turns,
+                    "duration_ms": result.duration_ms,
+                    "total_cost_usd": result.total_cost_usd,
+                }),
+            )],
+            Message::System(_) => Vec::new(),
+        }
+    }
+
+    fn frame_delta(&mut self, event: &Value) -> Option<SseEvent> {
+        if event.get("type").and_then(Value::as_str) != Some("content_block_delta") {
+            return None;
+        }
+        let delta = event.get("delta")?;
+        match delta.get("type").and_then(Value::as_str)? {
+            "text_delta" => {
+                self.streamed_text = true;
+                let text = delta.get("text")?.as_str()?;
+                Some(SseEvent::new("text", json!({ "text": text })))
+            }
+            "thinking_delta" => {
+                self.streamed_thinking = true;
+                let thinking = delta.get("thinking")?.as_str()?;
+                Some(SseEvent::new("thinking", json!({ "thinking": thinking })))
+            }
+            _ => None,
+        }
+    }
+}
+
+fn tool_result(tool_use_id: &str, content: Option<&Value>, is_error: Option<bool>) -> SseEvent {
+    SseEvent::new(
+        "tool_result",
+        json!({
+            "tool_use_id": tool_use_id,
+            "content": content,
+            "is_error": is_error.unwrap_or(false),
+        }),
+    )
+}
+
+fn with_parent(mut sse: SseEvent, parent_tool_use_id: Option<&str>) -> SseEvent {
+    if let (Some(parent), Value::Object(data)) = (parent_tool_use_id, &mut sse.data) {
+        data.insert("parent_tool_use_id".into(), Value::String(parent.into()));
+    }
+    sse
+}
+
+/// Frames `messages` with an [`SseFramer`]; an error becomes a final
+/// `error` event.
+pub fn sse_events<S>(messages: S) -> impl Stream<Item = SseEvent>
+where
+    S: Stream<Item = Result<Message, SdkError>> + Send + 'static,
+{
+    let messages = messages.boxed();
+    stream::unfold(Some((messages, SseFramer::new())), |state| async move {
+        let (mut messages, mut framer) = state?;
+        match messages.next().await? {
+            Ok(message) => {
+                let events = framer.frame(&message);
+                Some((events, Some((messages, framer))))
+            }
+            Err(error) => Some((vec![SseEvent::error(&error)], None)),
+        }
+    })
+    .flat_map(stream::iter)
+}
+
+/// `text/event-stream` body chunks for `messages`, e.g. for
+/// `axum::body::Body::from_stream` or any framework taking a byte stream.
+pub fn sse_bytes<S>(messages: S) -> impl Stream<Item = Result<Bytes, Infallible>>
+where
+    S: Stream<Item = Result<Message, SdkError>> + Send + 'static,
+{
+    sse_events(messages).map(|sse| Ok(Bytes::from(sse.to_frame())))
+}
+
+/// axum SSE response for `messages`, with keep-alive comments while Claude
+/// is busy with tools.
+pub fn sse_response<S>(messages: S) -> Sse<impl Stream<Item = Result<Event, Infallible>> + Send>
+where
+    S: Stream<Item = Result<Message, SdkError>> + Send + 'static,
+{
+    Sse::new(sse_events(messages).map(|sse| Ok(sse.to_axum_event())))
+        .keep_alive(KeepAlive::default())
+}
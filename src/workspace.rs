@@ -0,0 +1,177 @@
+//! Working directory, extra directories and generated context for a session.
+//!
+//! A [`Workspace`] bundles what code-automation callers otherwise wire up by
+//! hand: the `cwd` and `add_dirs` options, CLAUDE.md-style context sections
+//! appended to the system prompt, and the list of files Claude changed.
+//! Changes are taken from successful Edit, MultiEdit, Write and NotebookEdit
+//! calls seen by [`Workspace::observe`]:
+//!
+//! ```no_run
+//! # use futures::StreamExt;
+//! # use sdk_claude_rust::client::ClaudeSdkClient;
+//! # use sdk_claude_rust::config::ClaudeAgentOptions;
+//! # use sdk_claude_rust::workspace::Workspace;
+//! # async fn run() -> Result<(), sdk_claude_rust::error::SdkError> {
+//! let workspace = Workspace::new("/srv/repo")
+//!     .with_add_dir("/srv/shared-protos")
+//!     .with_context("Conventions", "Run `make check` before finishing.");
+//! let mut options = ClaudeAgentOptions::default();
+//! workspace.apply_to(&mut options);
+//!
+//! let mut client = ClaudeSdkClient::new(Some(options), None);
+//! client.connect(None).await?;
+//! client.query("Fix the failing test", "default").await?;
+//! let mut response = std::pin::pin!(client.receive_response()?);
+//! while let Some(message) = response.next().await {
+//!     workspace.observe(&message?);
+//! }
+//! println!("changed: {:?}", workspace.changed_files());
+//! # Ok(())
+//! # }
+//! ```
+
+use std::collections::BTreeSet;
+use std::fmt::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use serde_json::Value;
+
+use crate::config::ClaudeAgentOptions;
+use crate::message::Message;
+use crate::tool_events::{ToolEvent, ToolEventTracker};
+
+/// Tools whose successful calls change a file, with the input field naming it.
+const FILE_TOOLS: &[(&str, &str)] = &[
+    ("Edit", "file_path"),
+    ("MultiEdit", "file_path"),
+    ("Write", "file_path"),
+    ("NotebookEdit", "notebook_path"),
+];
+
+#[derive(Default)]
+struct Changes {
+    tracker: ToolEventTracker,
+    files: BTreeSet<PathBuf>,
+}
+
+/// Session workspace; clones share the recorded changes.
+#[derive(Clone)]
+pub struct Workspace {
+    root: PathBuf,
+    add_dirs: Vec<PathBuf>,
+    context: Vec<(String, String)>,
+    changes: Arc<Mutex<Changes>>,
+}
+
+impl std::fmt::Debug for Workspace {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Workspace")
+            .field("root", &self.root)
+            .field("add_dirs", &self.add_dirs)
+            .field("context_sections", &self.context.len())
+            .field("changed_files", &self.changed_files().len())
+            .finish()
+    }
+}
+
+impl Workspace {
+    /// Workspace rooted at `root`, which becomes the session's `cwd`.
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self {
+            root: root.into(),
+            add_dirs: Vec::new(),
+            context: Vec::new(),
+            changes: Arc::default(),
+        }
+    }
+
+    /// Give Claude access to another directory.
+    pub fn with_add_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.add_dirs.push(dir.into());
+        self
+    }
+
+    /// Add a context section, rendered as `## title` followed by `body`.
+    pub fn with_context(mut self, title: impl Into<String>, body: impl Into<String>) -> Self {
+        self.context.push((title.into(), body.into()));
+        self
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    pub fn add_dirs(&self) -> &[PathBuf] {
+        &self.add_dirs
+    }
+
+    /// Context sections as a CLAUDE.md-style Markdown document, or `None`
+    /// without sections.
+    pub fn context_markdown(&self) -> Option<String> {
+        if self.context.is_empty() {
+            return None;
+        }
+        let mut markdown = String::from("# Workspace context\n");
+        for (title, body) in &self.context {
+            let _ = write!(markdown, "\n## {title}\n\n{}\n", body.trim_end());
+        }
+        Some(markdown)
+    }
+
+    /// Set `cwd`, add the extra directories and append the context to the
+    /// system prompt.
+    pub fn apply_to(&self, options: &mut ClaudeAgentOptions) {
+        options.cwd = Some(self.root.clone());
+        for dir in &self.add_dirs {
+            if !options.add_dirs.contains(dir) {
+                options.add_dirs.push(dir.clone());
+            }
+        }
+        if let Some(context) = self.context_markdown() {
+            options.append_system_prompt(&context);
+        }
+    }
+
+    /// Record files changed by tool calls in `message`. Feed it every message
+    /// of the session, in order.
+    pub fn observe(&self, message: &Message) {
+        let mut changes = self.changes.lock().expect("workspace lock poisoned");
+        for event in changes.tracker.observe(message) {
+            let ToolEvent::Finished {
+                name,
+                input,
+                is_error: false,
+                ..
+            } = event
+            else {
+                continue;
+            };
+            let Some((_, field)) = FILE_TOOLS.iter().find(|(tool, _)| *tool == name) else {
+                continue;
+            };
+            if let Some(path) = input.get(*field).and_then(Value::as_str) {
+                let path = self.relative(Path::new(path));
+                changes.files.insert(path);
+            }
+        }
+    }
+
+    /// Files changed so far, sorted. Paths under the root are relative to it.
+    pub fn changed_files(&self) -> Vec<PathBuf> {
+        let changes = self.changes.lock().expect("workspace lock poisoned");
+        changes.files.iter().cloned().collect()
+    }
+
+    /// Forget recorded changes, e.g. between runs.
+    pub fn clear_changes(&self) {
+        let mut changes = self.changes.lock().expect("workspace lock poisoned");
+        *changes = Changes::default();
+    }
+
+    fn relative(&self, path: &Path) -> PathBuf {
+        path.strip_prefix(&self.root)
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|_| path.to_path_buf())
+    }
+}
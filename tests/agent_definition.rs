@@ -0,0 +1,57 @@
+use sdk_claude_rust::config::AgentDefinition;
+use sdk_claude_rust::error::SdkError;
+
+fn config_error(result: Result<AgentDefinition, SdkError>) -> String {
+    match result {
+        Err(SdkError::InvalidConfig(message)) => message,
+        other => panic!("expected a config error, got {other:?}"),
+    }
+}
+
+#[test]
+fn builder_accepts_builtin_and_registered_mcp_tools() {
+    let agent = AgentDefinition::builder("Reviews diffs", "You review code.")
+        .with_mcp_tools("github", ["list_prs"])
+        .with_tools(["Read", "Bash(git diff:*)", "mcp__github__list_prs"])
+        .with_model("sonnet")
+        .build()
+        .unwrap();
+    assert_eq!(agent.model.as_deref(), Some("sonnet"));
+    assert_eq!(agent.tools.as_ref().map(Vec::len), Some(3));
+
+    let inherited = AgentDefinition::builder("Helper", "Help.")
+        .with_model("inherit")
+        .build()
+        .unwrap();
+    assert_eq!(inherited.tools, None);
+}
+
+#[test]
+fn builder_rejects_typos_and_empty_prompts() {
+    let message = config_error(
+        AgentDefinition::builder("Reviews diffs", "You review code.")
+            .with_tools(["Raed"])
+            .build(),
+    );
+    assert_eq!(
+        message,
+        "unknown tool \"Raed\" for agent; did you mean Read?"
+    );
+
+    let message = config_error(
+        AgentDefinition::builder("Reviews diffs", "You review code.")
+            .with_tools(["mcp__github__list_prs"])
+            .build(),
+    );
+    assert!(message.starts_with("unknown tool"), "{message}");
+
+    let message = config_error(AgentDefinition::builder("Reviews diffs", "  ").build());
+    assert_eq!(message, "agent prompt must not be empty");
+
+    let message = config_error(
+        AgentDefinition::builder("Reviews diffs", "You review code.")
+            .with_model("sonet")
+            .build(),
+    );
+    assert!(message.contains("did you mean sonnet?"), "{message}");
+}
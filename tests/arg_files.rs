@@ -0,0 +1,118 @@
+//! Large CLI arguments passed through temporary files, checked against the
+//! bundled `fake-claude` binary.
+#![cfg(feature = "tempfile")]
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use sdk_claude_rust::config::{
+    AgentDefinition, ClaudeAgentOptions, McpHttpServerConfig, McpServerConfig, McpServerKind,
+    McpServers, SystemPrompt, SystemPromptPreset, SystemPromptPresetName, SystemPromptPresetType,
+};
+use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+use sdk_claude_rust::transport::Transport;
+
+fn options(threshold: Option<usize>) -> ClaudeAgentOptions {
+    let agent = AgentDefinition::builder("Reviews diffs", "You review code.")
+        .build()
+        .unwrap();
+    ClaudeAgentOptions {
+        cli_path: Some(PathBuf::from(env!("CARGO_BIN_EXE_fake-claude"))),
+        system_prompt: Some(SystemPrompt::Preset(SystemPromptPreset {
+            kind: SystemPromptPresetType::Preset,
+            preset: SystemPromptPresetName::ClaudeCode,
+            append: Some("Answer tersely. ".repeat(64)),
+        })),
+        agents: Some(HashMap::from([("reviewer".to_string(), agent)])),
+        settings: Some(r#"{"model": "sonnet"}"#.into()),
+        arg_file_threshold: threshold,
+        ..Default::default()
+    }
+}
+
+async fn argv(transport: &SubprocessCliTransport) -> Vec<String> {
+    transport.diagnostics().await.unwrap().argv
+}
+
+fn value_of<'a>(argv: &'a [String], flag: &str) -> &'a str {
+    let position = argv.iter().position(|arg| arg == flag).unwrap();
+    &argv[position + 1]
+}
+
+#[tokio::test]
+async fn zero_threshold_moves_every_large_argument_into_a_file() {
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options(Some(0))).unwrap();
+    transport.connect().await.unwrap();
+    let argv = argv(&transport).await;
+
+    let append = value_of(&argv, "--append-system-prompt");
+    let append_path = PathBuf::from(append.strip_prefix('@').expect("an @file argument"));
+    assert_eq!(
+        std::fs::read_to_string(&append_path).unwrap(),
+        "Answer tersely. ".repeat(64)
+    );
+    let agents = value_of(&argv, "--agents");
+    let agents_path = PathBuf::from(agents.strip_prefix('@').expect("an @file argument"));
+    assert!(std::fs::read_to_string(&agents_path)
+        .unwrap()
+        .contains("You review code."));
+
+    transport.close().await.unwrap();
+    assert!(!append_path.exists());
+    assert!(!agents_path.exists());
+}
+
+#[tokio::test]
+async fn short_command_lines_stay_inline() {
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options(None)).unwrap();
+    transport.connect().await.unwrap();
+    let argv = argv(&transport).await;
+    assert!(!value_of(&argv, "--agents").starts_with('@'));
+    assert!(!value_of(&argv, "--append-system-prompt").starts_with('@'));
+    transport.close().await.unwrap();
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn mcp_configs_with_expanded_variables_always_use_a_file() {
+    let server = McpServerConfig::Http(McpHttpServerConfig {
+        kind: McpServerKind::Http,
+        url: "https://mcp.example.com".into(),
+        headers: Some(HashMap::from([(
+            "Authorization".to_string(),
+            "Bearer ${MCP_TOKEN}".to_string(),
+        )])),
+    });
+    let options = ClaudeAgentOptions {
+        mcp_servers: McpServers::Map(HashMap::from([("remote".to_string(), server)])),
+        env: HashMap::from([("MCP_TOKEN".to_string(), "tok-1234567890".to_string())]),
+        ..options(None)
+    };
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options).unwrap();
+    transport.connect().await.unwrap();
+
+    // What `ps` shows for the running CLI, once it has replaced the forked
+    // test process.
+    let cmdline = format!("/proc/{}/cmdline", transport.pid().unwrap());
+    let argv = || {
+        String::from_utf8(std::fs::read(&cmdline).unwrap())
+            .unwrap()
+            .split('\0')
+            .map(str::to_string)
+            .collect::<Vec<_>>()
+    };
+    for _ in 0..200 {
+        if argv().iter().any(|arg| arg == "--mcp-config") {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    let argv = argv();
+    assert!(argv.iter().all(|arg| !arg.contains("tok-1234567890")));
+    let config_path = PathBuf::from(value_of(&argv, "--mcp-config"));
+    assert!(std::fs::read_to_string(&config_path)
+        .unwrap()
+        .contains("Bearer tok-1234567890"));
+    assert!(!value_of(&argv, "--agents").starts_with('@'));
+    transport.close().await.unwrap();
+}
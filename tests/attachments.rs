@@ -0,0 +1,115 @@
+use std::fs;
+
+use serde_json::json;
+
+use sdk_claude_rust::attachments::{load_file, user_message, Attachment, AttachmentLoader};
+use sdk_claude_rust::error::SdkError;
+
+const PNG_HEADER: &[u8] = b"\x89PNG\r\n\x1a\n\0\0\0\rIHDR";
+
+#[test]
+fn files_become_typed_content_blocks() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let text_path = dir.path().join("notes.md");
+    fs::write(&text_path, "# Notes\nfine").unwrap();
+    // Named like text, detected as PNG from its contents.
+    let image_path = dir.path().join("capture.bin");
+    fs::write(&image_path, PNG_HEADER).unwrap();
+    let pdf_path = dir.path().join("spec.pdf");
+    fs::write(&pdf_path, b"%PDF-1.7\n").unwrap();
+
+    let text = load_file(&text_path).unwrap();
+    assert_eq!(
+        text.to_content_block(),
+        json!({
+            "type": "text",
+            "text": format!("<file path=\"{}\">\n# Notes\nfine\n</file>", text_path.display())
+        })
+    );
+
+    let image = load_file(&image_path).unwrap();
+    assert_eq!(image.media_type(), "image/png");
+    let block = image.to_content_block();
+    assert_eq!(block["type"], "image");
+    assert_eq!(block["source"]["type"], "base64");
+    assert_eq!(block["source"]["data"], "iVBORw0KGgoAAAANSUhEUg==");
+
+    let pdf = load_file(&pdf_path).unwrap();
+    assert!(matches!(pdf, Attachment::Pdf { .. }));
+    assert_eq!(pdf.to_content_block()["source"]["data"], "JVBERi0xLjcK");
+}
+
+#[test]
+fn size_limits_and_binary_files_are_rejected() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let big = dir.path().join("big.txt");
+    fs::write(&big, "x".repeat(2048)).unwrap();
+    let binary = dir.path().join("blob.dat");
+    fs::write(&binary, [0u8, 159, 146, 150]).unwrap();
+
+    let loader = AttachmentLoader::new().with_max_text_bytes(1024);
+    let err = loader.load(&big).unwrap_err();
+    assert!(
+        matches!(&err, SdkError::InvalidConfig(message) if message.contains("exceeds the 1.0 KiB")),
+        "{err}"
+    );
+    assert!(matches!(
+        loader.load(&binary),
+        Err(SdkError::InvalidConfig(_))
+    ));
+    assert!(matches!(
+        loader.load(dir.path().join("missing.txt")),
+        Err(SdkError::Io(_))
+    ));
+}
+
+#[test]
+fn directory_summary_lists_tree_and_inlines_small_files() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    fs::create_dir_all(dir.path().join("src/nested")).unwrap();
+    fs::create_dir_all(dir.path().join("target/debug")).unwrap();
+    fs::write(dir.path().join("Cargo.toml"), "[package]").unwrap();
+    fs::write(dir.path().join("src/lib.rs"), "pub fn a() {}").unwrap();
+    fs::write(dir.path().join("src/nested/big.rs"), "x".repeat(4096)).unwrap();
+    fs::write(dir.path().join(".env"), "SECRET=1").unwrap();
+
+    let summary = AttachmentLoader::new()
+        .with_max_inline_bytes(100)
+        .summarize_dir(dir.path())
+        .unwrap();
+    let Attachment::Text { text, .. } = summary else {
+        panic!("directory summaries are text");
+    };
+    assert_eq!(
+        text,
+        "Cargo.toml (9 B)\nsrc/\n  lib.rs (13 B)\n  nested/\n    big.rs (4.0 KiB)\n\n\
+         <file path=\"Cargo.toml\">\n[package]\n</file>\n\n\
+         <file path=\"src/lib.rs\">\npub fn a() {}\n</file>"
+    );
+
+    let Attachment::Text { text, .. } = AttachmentLoader::new()
+        .with_max_dir_entries(2)
+        .summarize_dir(dir.path())
+        .unwrap()
+    else {
+        panic!("directory summaries are text");
+    };
+    assert_eq!(
+        text,
+        "Cargo.toml (9 B)\nsrc/\n... listing truncated after 2 entries"
+    );
+}
+
+#[test]
+fn user_message_puts_attachments_before_the_prompt() {
+    let attachment = Attachment::Text {
+        name: "a.txt".into(),
+        text: "hi".into(),
+    };
+    let message = user_message("Summarize", &[attachment]);
+
+    assert_eq!(message["type"], "user");
+    let content = message["message"]["content"].as_array().unwrap();
+    assert_eq!(content.len(), 2);
+    assert_eq!(content[1], json!({"type": "text", "text": "Summarize"}));
+}
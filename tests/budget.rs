@@ -0,0 +1,161 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::budget::{BudgetGuard, BudgetedMessage, TokenPricing};
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn stream_event(event: Value) -> Value {
+    json!({
+        "type": "stream_event",
+        "uuid": "evt",
+        "session_id": "sess-budget",
+        "event": event
+    })
+}
+
+fn message_start(input_tokens: u64) -> Value {
+    stream_event(json!({
+        "type": "message_start",
+        "message": {"usage": {"input_tokens": input_tokens, "output_tokens": 1}}
+    }))
+}
+
+fn message_delta(output_tokens: u64) -> Value {
+    stream_event(json!({
+        "type": "message_delta",
+        "delta": {"stop_reason": null},
+        "usage": {"output_tokens": output_tokens}
+    }))
+}
+
+fn result(cost: Option<f64>) -> Value {
+    let mut result = json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-budget"
+    });
+    if let Some(cost) = cost {
+        result["total_cost_usd"] = json!(cost);
+    }
+    result
+}
+
+fn parsed(raw: Value) -> Message {
+    parse_message(&raw).expect("fixture should parse")
+}
+
+#[test]
+fn projection_follows_stream_usage_and_settles_on_results() {
+    let mut guard = BudgetGuard::new(10.0).with_pricing(TokenPricing::SONNET);
+
+    // 1M input tokens at $3 plus one output token.
+    assert!(guard.observe(&parsed(message_start(1_000_000))).is_none());
+    assert!((guard.projected_cost_usd() - 3.000015).abs() < 1e-9);
+    // Cumulative output count: 100k tokens at $15/M.
+    guard.observe(&parsed(message_delta(100_000)));
+    assert!((guard.projected_cost_usd() - 4.5).abs() < 1e-9);
+    // A second API call in the same query keeps the first one's cost.
+    guard.observe(&parsed(message_start(0)));
+    assert!((guard.projected_cost_usd() - 4.500015).abs() < 1e-9);
+
+    // The reported cost replaces the estimate.
+    guard.observe(&parsed(result(Some(4.0))));
+    assert_eq!(guard.spent_usd(), 4.0);
+    assert_eq!(guard.projected_cost_usd(), 4.0);
+
+    // Without a reported cost the estimate is kept.
+    guard.observe(&parsed(message_start(1_000_000)));
+    guard.observe(&parsed(result(None)));
+    assert!((guard.spent_usd() - 7.000015).abs() < 1e-9);
+}
+
+#[test]
+fn warning_fires_once_per_query() {
+    let mut guard = BudgetGuard::new(1.0).with_spent(0.5);
+
+    let warning = guard
+        .observe(&parsed(message_start(200_000)))
+        .expect("$0.5 + $0.6 should cross the limit");
+    assert_eq!(warning.soft_limit_usd, 1.0);
+    assert_eq!(warning.spent_usd, 0.5);
+    assert!(warning.projected_cost_usd > 1.0);
+    assert!(!warning.interrupted);
+    assert!(guard.observe(&parsed(message_delta(10_000))).is_none());
+    assert!(guard.observe(&parsed(result(Some(0.7)))).is_none());
+
+    // Already over the limit: the next query warns on its first message.
+    assert!(guard.observe(&parsed(message_start(10))).is_some());
+}
+
+#[tokio::test]
+async fn client_interrupts_and_reports_warning_when_limit_is_projected() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![
+            message_start(100),
+            message_delta(400_000),
+            message_delta(500_000),
+            result(Some(7.5)),
+        ])
+        .await;
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("write a novel", "default")
+        .await
+        .expect("query should be written");
+
+    let mut guard = BudgetGuard::new(5.0);
+    let items: Vec<_> = client
+        .receive_response_with_budget(&mut guard)
+        .expect("stream should be available")
+        .collect()
+        .await;
+    let items: Vec<_> = items
+        .into_iter()
+        .map(|item| item.expect("no errors expected"))
+        .collect();
+
+    assert_eq!(items.len(), 5);
+    assert!(matches!(
+        &items[1],
+        BudgetedMessage::Message(Message::StreamEvent(_))
+    ));
+    match &items[2] {
+        BudgetedMessage::Warning(warning) => {
+            assert!(warning.interrupted);
+            assert!(warning.projected_cost_usd >= 5.0);
+        }
+        other => panic!("expected a warning after the crossing event, got {other:?}"),
+    }
+    assert!(matches!(
+        &items[4],
+        BudgetedMessage::Message(Message::Result(_))
+    ));
+    assert_eq!(guard.spent_usd(), 7.5);
+
+    let interrupts = transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|write| write.pointer("/request/subtype") == Some(&json!("interrupt")))
+        .count();
+    assert_eq!(interrupts, 1);
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
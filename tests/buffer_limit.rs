@@ -0,0 +1,176 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::buffer_limit::{BufferLimit, SlowConsumerPolicy};
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::testing::{FaultyTransport, MockTransport};
+use sdk_claude_rust::transport::Transport;
+
+fn delta(text: &str) -> Value {
+    json!({
+        "type": "stream_event",
+        "uuid": "evt",
+        "session_id": "sess-buffer",
+        "event": {"type": "content_block_delta", "delta": {"type": "text_delta", "text": text}}
+    })
+}
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-buffer"
+    })
+}
+
+/// Run a query whose consumer only starts reading once the CLI output has
+/// piled up against `limit`.
+async fn stalled_consumer(
+    limit: &BufferLimit,
+    reads: Vec<Value>,
+) -> Vec<Result<Message, SdkError>> {
+    let transport = MockTransport::with_reads(reads.into_iter().map(|read| Ok(Some(read))));
+    let options = ClaudeAgentOptions {
+        buffer_limit: Some(limit.clone()),
+        ..Default::default()
+    };
+    let stream = query("Hi", Some(options), Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("query should start");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    stream.collect().await
+}
+
+#[tokio::test]
+async fn backpressure_delivers_everything_within_the_limit() {
+    let limit = BufferLimit::new(1);
+    let messages = stalled_consumer(&limit, vec![says("one"), says("two"), result()]).await;
+    assert_eq!(messages.len(), 3);
+    assert!(messages.iter().all(Result::is_ok));
+    assert_eq!(limit.buffered(), 0);
+}
+
+#[tokio::test]
+async fn slow_consumers_lose_deltas_or_fail_by_policy() {
+    let dropping = BufferLimit::new(1).with_policy(SlowConsumerPolicy::DropStreamEvents);
+    let messages = stalled_consumer(
+        &dropping,
+        vec![delta("a"), delta("b"), delta("c"), says("abc"), result()],
+    )
+    .await;
+    let kinds: Vec<_> = messages
+        .into_iter()
+        .map(|message| match message.unwrap() {
+            Message::StreamEvent(_) => "delta",
+            Message::Assistant(_) => "assistant",
+            Message::Result(_) => "result",
+            _ => "other",
+        })
+        .collect();
+    assert_eq!(kinds, ["delta", "assistant", "result"]);
+
+    let failing = BufferLimit::new(1).with_policy(SlowConsumerPolicy::Error);
+    let messages = stalled_consumer(&failing, vec![says("one"), says("two"), result()]).await;
+    assert!(messages[0].is_ok());
+    assert!(matches!(
+        messages[1],
+        Err(SdkError::BufferLimitExceeded { limit: 1, .. })
+    ));
+    assert_eq!(failing.buffered(), 0);
+}
+
+#[tokio::test]
+async fn messages_read_ahead_count_against_the_limit() {
+    let limit = BufferLimit::new(4 * 1024);
+    let mut reads: Vec<Value> = (0..500).map(|n| says(&format!("part {n}"))).collect();
+    reads.push(result());
+    let transport = Arc::new(FaultyTransport::new(MockTransport::with_reads(
+        reads.into_iter().map(|read| Ok(Some(read))),
+    )));
+    let options = ClaudeAgentOptions {
+        buffer_limit: Some(limit.clone()),
+        ..Default::default()
+    };
+    let stream = query(
+        "Hi",
+        Some(options),
+        Some(transport.clone() as Arc<dyn Transport>),
+    )
+    .await
+    .expect("query should start");
+
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(limit.buffered() <= limit.max_bytes());
+    }
+    // Reading stops once the unread messages fill the limit, well short of
+    // the read-ahead queue's length.
+    assert!(
+        transport.reads() < 100,
+        "read {} messages",
+        transport.reads()
+    );
+
+    let messages: Vec<_> = stream.collect().await;
+    assert_eq!(messages.len(), 501);
+    assert_eq!(limit.buffered(), 0);
+}
+
+#[tokio::test]
+async fn a_pending_control_request_only_lifts_its_own_sessions_limit() {
+    let limit = BufferLimit::new(4 * 1024);
+    let busy = MockTransport::new();
+    busy.set_keep_open(true);
+    let mut client = ClaudeSdkClient::new(
+        Some(ClaudeAgentOptions {
+            buffer_limit: Some(limit.clone()),
+            ..Default::default()
+        }),
+        Some(busy.clone() as Arc<dyn Transport>),
+    );
+    client.connect(None).await.expect("connect should succeed");
+    busy.set_withhold_control_responses(true);
+    let client = Arc::new(client);
+    let interrupt = tokio::spawn({
+        let client = Arc::clone(&client);
+        async move { client.interrupt().await }
+    });
+    tokio::time::sleep(Duration::from_millis(20)).await;
+
+    let mut reads: Vec<Value> = (0..500).map(|n| says(&format!("part {n}"))).collect();
+    reads.push(result());
+    let transport = MockTransport::with_reads(reads.into_iter().map(|read| Ok(Some(read))));
+    let options = ClaudeAgentOptions {
+        buffer_limit: Some(limit.clone()),
+        ..Default::default()
+    };
+    let stream = query("Hi", Some(options), Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("query should start");
+    for _ in 0..10 {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+        assert!(limit.buffered() <= limit.max_bytes());
+    }
+
+    let messages: Vec<_> = stream.collect().await;
+    assert_eq!(messages.len(), 501);
+    interrupt.abort();
+}
@@ -0,0 +1,130 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::cache::QueryCache;
+use sdk_claude_rust::client::DynTransport;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn answer(text: &str, subtype: &str) -> DynTransport {
+    let lines = vec![
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+        }),
+        json!({
+            "type": "result",
+            "subtype": subtype,
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": subtype != "success",
+            "num_turns": 1,
+            "session_id": "sess-cache",
+            "result": text
+        }),
+    ];
+    MockTransport::with_reads(lines.into_iter().map(|line: Value| Ok(Some(line))))
+        as Arc<dyn Transport>
+}
+
+/// Transport that fails the query if it is ever used.
+fn unused() -> DynTransport {
+    MockTransport::with_reads(vec![Ok(None)]) as Arc<dyn Transport>
+}
+
+async fn collect(
+    cache: &QueryCache,
+    prompt: &str,
+    transport: DynTransport,
+) -> Result<Vec<Message>, SdkError> {
+    cache
+        .query(prompt, None, Some(transport))
+        .await?
+        .collect::<Vec<_>>()
+        .await
+        .into_iter()
+        .collect()
+}
+
+#[tokio::test]
+async fn hits_replay_stored_messages_without_the_cli() {
+    let cache = QueryCache::in_memory();
+    assert!(!cache.contains("2+2?", None).await.unwrap());
+
+    let first = collect(&cache, "2+2?", answer("4", "success"))
+        .await
+        .unwrap();
+    assert!(cache.contains("2+2?", None).await.unwrap());
+    let second = collect(&cache, "2+2?", unused()).await.unwrap();
+    assert_eq!(first, second);
+
+    cache.invalidate("2+2?", None).await.unwrap();
+    assert!(!cache.contains("2+2?", None).await.unwrap());
+}
+
+#[tokio::test]
+async fn error_results_are_not_stored() {
+    let cache = QueryCache::in_memory();
+    let messages = collect(&cache, "loop forever", answer("", "error_during_execution"))
+        .await
+        .unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(!cache.contains("loop forever", None).await.unwrap());
+}
+
+#[tokio::test]
+async fn directory_entries_survive_a_new_cache() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let first = collect(
+        &QueryCache::directory(dir.path()),
+        "name a colour",
+        answer("teal", "success"),
+    )
+    .await
+    .unwrap();
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+
+    let reopened = QueryCache::directory(dir.path());
+    let second = collect(&reopened, "name a colour", unused()).await.unwrap();
+    assert_eq!(first, second);
+}
+
+#[test]
+fn keys_cover_prompt_and_answer_shaping_options() {
+    let base = ClaudeAgentOptions::default();
+    let key = QueryCache::key("hi", &base).unwrap();
+    assert_eq!(key.len(), 64);
+    assert_ne!(key, QueryCache::key("hello", &base).unwrap());
+
+    let other_model = ClaudeAgentOptions {
+        model: Some("opus".into()),
+        ..Default::default()
+    };
+    assert_ne!(key, QueryCache::key("hi", &other_model).unwrap());
+
+    let other_cli = ClaudeAgentOptions {
+        cli_path: Some(PathBuf::from("/opt/claude")),
+        max_budget_usd: Some(1.0),
+        ..Default::default()
+    };
+    assert_eq!(key, QueryCache::key("hi", &other_cli).unwrap());
+
+    let mut env_a = ClaudeAgentOptions::default();
+    let mut env_b = ClaudeAgentOptions::default();
+    for (name, value) in [("A", "1"), ("B", "2"), ("C", "3"), ("D", "4")] {
+        env_a.env.insert(name.into(), value.into());
+    }
+    for (name, value) in [("D", "4"), ("C", "3"), ("B", "2"), ("A", "1")] {
+        env_b.env.insert(name.into(), value.into());
+    }
+    assert_eq!(
+        QueryCache::key("hi", &env_a).unwrap(),
+        QueryCache::key("hi", &env_b).unwrap()
+    );
+}
@@ -0,0 +1,95 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::{CallbackPanic, ErrorKind, SdkError};
+use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn permission_request(id: &str, tool: &str) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": id,
+        "request": {"subtype": "can_use_tool", "tool_name": tool, "input": {}}
+    })
+}
+
+async fn response_for(transport: &MockTransport, id: &str) -> Value {
+    loop {
+        let response = transport
+            .writes()
+            .await
+            .into_iter()
+            .find(|write| write.pointer("/response/request_id") == Some(&json!(id)));
+        if let Some(response) = response {
+            return response["response"].clone();
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+#[tokio::test]
+async fn panicking_permission_callback_answers_the_cli_and_notifies_the_app() {
+    let can_use_tool = Arc::new(
+        |tool: &str, _input: Map<String, Value>, _context: ToolPermissionContext| {
+            if tool == "Bash" {
+                panic!("permission store unavailable");
+            }
+            Box::pin(async {
+                PermissionResult::Allow {
+                    updated_input: None,
+                    updated_permissions: None,
+                }
+            })
+        },
+    );
+    let options = ClaudeAgentOptions {
+        can_use_tool: Some(can_use_tool),
+        ..Default::default()
+    };
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    let panics = client.callback_panics().await.unwrap();
+    futures::pin_mut!(panics);
+
+    transport
+        .enqueue_read(Ok(Some(permission_request("cli-1", "Bash"))))
+        .await;
+    let response = tokio::time::timeout(Duration::from_secs(2), response_for(&transport, "cli-1"))
+        .await
+        .expect("the CLI gets a response");
+    assert_eq!(response["subtype"], "error");
+    assert!(response["error"]
+        .as_str()
+        .unwrap()
+        .contains("permission store unavailable"));
+
+    let err = panics.next().await.unwrap();
+    assert_eq!(err.kind(), ErrorKind::Tool);
+    match err {
+        SdkError::CallbackPanicked(panic) => assert_eq!(
+            panic,
+            CallbackPanic {
+                subtype: "can_use_tool".into(),
+                callback: "Bash".into(),
+                message: "permission store unavailable".into(),
+            }
+        ),
+        other => panic!("expected a callback panic, got {other:?}"),
+    }
+
+    transport
+        .enqueue_read(Ok(Some(permission_request("cli-2", "Read"))))
+        .await;
+    let response = response_for(&transport, "cli-2").await;
+    assert_eq!(response["subtype"], "success");
+    client.disconnect().await.unwrap();
+}
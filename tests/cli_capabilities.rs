@@ -0,0 +1,91 @@
+use sdk_claude_rust::transport::capabilities::{CliCapabilities, CliFeature, Fallback};
+
+#[test]
+fn capabilities_follow_the_detected_version() {
+    let old = CliCapabilities::detect("2.0.5 (Claude Code)");
+    assert_eq!(old.version(), Some([2, 0, 5]));
+    assert!(old.supports(CliFeature::Agents));
+    assert!(old.supports(CliFeature::ForkSession));
+    assert_eq!(
+        old.unsupported(),
+        [
+            CliFeature::PluginDir,
+            CliFeature::MaxThinkingTokens,
+            CliFeature::MaxBudgetUsd
+        ]
+    );
+    assert_eq!(
+        old.fallback_for(CliFeature::MaxThinkingTokens).unwrap(),
+        Some(Fallback::Env("MAX_THINKING_TOKENS"))
+    );
+
+    assert!(CliCapabilities::detect("2.1.0").unsupported().is_empty());
+    assert!(CliCapabilities::detect("dev build").supports(CliFeature::MaxBudgetUsd));
+}
+
+#[cfg(unix)]
+mod subprocess {
+    use std::os::unix::fs::PermissionsExt;
+
+    use sdk_claude_rust::config::ClaudeAgentOptions;
+    use sdk_claude_rust::error::{ErrorKind, SdkError};
+    use sdk_claude_rust::transport::capabilities::CliFeature;
+    use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+    use sdk_claude_rust::transport::Transport;
+
+    /// A 2.0.5 CLI that echoes its arguments and `MAX_THINKING_TOKENS`.
+    fn old_cli(dir: &tempfile::TempDir) -> std::path::PathBuf {
+        let cli = dir.path().join("claude");
+        std::fs::write(
+            &cli,
+            "#!/bin/sh\n\
+             if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+             printf '{\"args\":\"%s\",\"thinking\":\"%s\"}\\n' \"$*\" \"$MAX_THINKING_TOKENS\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+        cli
+    }
+
+    #[tokio::test]
+    async fn old_cli_gets_translated_flags_or_a_typed_error() {
+        let dir = tempfile::tempdir().expect("temp dir");
+        let options = ClaudeAgentOptions {
+            cli_path: Some(old_cli(&dir)),
+            max_thinking_tokens: Some(4096),
+            ..Default::default()
+        };
+
+        let transport = SubprocessCliTransport::new(PromptMode::Text("hi".into()), options.clone())
+            .expect("transport should build");
+        transport.connect().await.expect("spawn should succeed");
+        let printed = transport.read().await.unwrap().expect("args line");
+        transport.close().await.unwrap();
+        assert!(!printed["args"]
+            .as_str()
+            .unwrap()
+            .contains("--max-thinking-tokens"));
+        assert_eq!(printed["thinking"], "4096");
+
+        let budgeted = ClaudeAgentOptions {
+            max_budget_usd: Some(1.0),
+            ..options
+        };
+        let transport = SubprocessCliTransport::new(PromptMode::Text("hi".into()), budgeted)
+            .expect("transport should build");
+        let err = transport.connect().await.expect_err("flag is unsupported");
+        assert!(matches!(
+            &err,
+            SdkError::UnsupportedFeature {
+                feature: CliFeature::MaxBudgetUsd,
+                cli_version,
+                required,
+            } if cli_version == "2.0.5" && required == "2.0.28"
+        ));
+        assert_eq!(err.kind(), ErrorKind::Configuration);
+        assert_eq!(
+            err.to_string(),
+            "--max-budget-usd requires Claude Code 2.0.28 or newer (found 2.0.5)"
+        );
+    }
+}
@@ -2,14 +2,17 @@ mod common;
 
 use std::sync::Arc;
 
-use futures::{stream, StreamExt};
+use futures::{stream, StreamExt, TryStreamExt};
 use serde_json::json;
 
 use sdk_claude_rust::client::{ClaudeSdkClient, ClientPrompt};
 use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::{ErrorKind, SdkError};
 use sdk_claude_rust::internal::client::PromptInput;
 use sdk_claude_rust::message::{ContentBlock, Message};
+use sdk_claude_rust::metrics::InMemoryMetrics;
 use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::progress::ProgressEvent;
 
 use common::MockTransport;
 
@@ -142,6 +145,356 @@ async fn client_rejects_permission_callback_without_streaming_prompt() {
         .await
         .expect_err("connect should fail due to invalid configuration");
 
+    assert_eq!(err.kind(), ErrorKind::Configuration);
     let message = err.to_string();
     assert!(message.contains("can_use_tool callback requires streaming mode"));
 }
+
+#[tokio::test]
+async fn client_status_reflects_observed_session() {
+    let transport = MockTransport::with_reads(vec![
+        Ok(Some(json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "sess-abc",
+            "model": "claude-opus-test"
+        }))),
+        Ok(Some(assistant_message("hello"))),
+        Ok(Some(result_message())),
+        Ok(None),
+    ]);
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    let idle = client.status().await;
+    assert!(!idle.connected);
+    assert_eq!(idle.turn_count, 0);
+
+    client
+        .connect(Some(PromptInput::from("Hello")))
+        .await
+        .expect("connect should succeed");
+    let _ = client
+        .receive_response()
+        .expect("stream should be available")
+        .collect::<Vec<_>>()
+        .await;
+
+    let status = client.status().await;
+    assert!(status.connected);
+    assert_eq!(status.session_id.as_deref(), Some("sess-abc"));
+    assert_eq!(status.model.as_deref(), Some("claude-opus-test"));
+    assert_eq!(status.turn_count, 1);
+    assert!(!status.query_in_flight);
+    assert!(status.last_activity.is_some());
+
+    client
+        .query("Hello again", "sess-abc")
+        .await
+        .expect("query should succeed");
+    assert!(client.status().await.query_in_flight);
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn client_compact_resolves_on_compact_boundary() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![
+            json!({
+                "type": "system",
+                "subtype": "compact_boundary",
+                "session_id": "sess-abc",
+                "compact_metadata": {"trigger": "manual", "pre_tokens": 1234}
+            }),
+            result_message(),
+        ])
+        .await;
+    transport
+        .reply_to_next_user(vec![
+            assistant_message("after compaction"),
+            result_message(),
+        ])
+        .await;
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+
+    let boundary = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        client.compact(Some("keep the API decisions".into()), "sess-abc"),
+    )
+    .await
+    .expect("compact should not hang")
+    .expect("compact should succeed");
+    assert_eq!(boundary.subtype, "compact_boundary");
+    assert_eq!(boundary.data["compact_metadata"]["pre_tokens"], json!(1234));
+
+    let writes = transport.writes().await;
+    let command = writes
+        .iter()
+        .find(|payload| payload.get("type").and_then(|v| v.as_str()) == Some("user"))
+        .expect("compact command should be written");
+    assert_eq!(
+        command["message"]["content"],
+        json!("/compact keep the API decisions")
+    );
+    assert_eq!(command["session_id"], json!("sess-abc"));
+
+    // The compaction turn was read through its result, so the next response
+    // starts with the next prompt.
+    client
+        .query("Continue", "sess-abc")
+        .await
+        .expect("query should succeed");
+    let messages: Vec<Message> = client
+        .receive_response()
+        .expect("receive_response")
+        .try_collect()
+        .await
+        .expect("response should succeed");
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], Message::Assistant(_)));
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn client_compact_refuses_while_a_response_is_unread() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![assistant_message("unread"), result_message()])
+        .await;
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("Hi", "sess-abc")
+        .await
+        .expect("query should succeed");
+
+    let err = client
+        .compact(None, "sess-abc")
+        .await
+        .expect_err("compact should refuse");
+    assert!(matches!(err, SdkError::QueryInFlight { .. }));
+
+    // The earlier response is left for its reader.
+    let messages: Vec<Message> = client
+        .receive_response()
+        .expect("receive_response")
+        .try_collect()
+        .await
+        .expect("response should succeed");
+    assert!(matches!(&messages[0], Message::Assistant(assistant)
+        if matches!(&assistant.content[0], ContentBlock::Text(text) if text.text == "unread")));
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn client_reports_metrics() {
+    let transport = MockTransport::with_reads(vec![Ok(Some(assistant_message("hello")))]);
+    transport
+        .reply_to_next_user(vec![result_message(), json!({"type": "mystery"})])
+        .await;
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let metrics = Arc::new(InMemoryMetrics::new());
+    let options = ClaudeAgentOptions {
+        metrics: Some(metrics.clone()),
+        ..Default::default()
+    };
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("Hi", "session-metrics")
+        .await
+        .expect("query should be written");
+
+    let messages = client
+        .receive_response()
+        .expect("stream should be available")
+        .collect::<Vec<_>>()
+        .await;
+    assert!(matches!(messages.last(), Some(Ok(Message::Result(_)))));
+    for _ in 0..100 {
+        if metrics.snapshot().parse_failures == 1 {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(5)).await;
+    }
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+    client
+        .connect(None)
+        .await
+        .expect("reconnect should succeed");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    let snapshot = metrics.snapshot();
+    assert_eq!(snapshot.messages_sent, 1);
+    assert_eq!(snapshot.messages_received.get("assistant"), Some(&1));
+    assert_eq!(snapshot.messages_received.get("result"), Some(&1));
+    assert_eq!(snapshot.parse_failures, 1);
+    assert_eq!(snapshot.control_latency["initialize"].count, 2);
+    assert_eq!(snapshot.control_failures, 0);
+    assert_eq!(snapshot.process_restarts, 1);
+}
+
+#[tokio::test]
+async fn client_captures_debug_bundle() {
+    let transport = MockTransport::with_reads(vec![Ok(Some(assistant_message("hello")))]);
+    transport.reply_to_next_user(vec![result_message()]).await;
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let options = ClaudeAgentOptions {
+        env: [("ANTHROPIC_API_KEY".to_string(), "sk-secret".to_string())].into(),
+        model: Some("claude-opus-test".into()),
+        ..Default::default()
+    };
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("Hi", "session-bundle")
+        .await
+        .expect("query should be written");
+    let _ = client
+        .receive_response()
+        .expect("stream should be available")
+        .collect::<Vec<_>>()
+        .await;
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let bundle = dir.path().join("bundle");
+    client
+        .capture_debug_bundle(&bundle)
+        .await
+        .expect("bundle should be written");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    let options = std::fs::read_to_string(bundle.join("options.json")).unwrap();
+    assert!(options.contains("claude-opus-test"));
+    assert!(!options.contains("sk-secret"));
+    let transcript = std::fs::read_to_string(bundle.join("transcript.jsonl")).unwrap();
+    let mut types: Vec<String> = transcript
+        .lines()
+        .map(|line| serde_json::from_str::<serde_json::Value>(line).unwrap()["type"].to_string())
+        .collect();
+    types.sort();
+    assert_eq!(types, ["\"assistant\"", "\"result\"", "\"user\""]);
+    let frames = std::fs::read_to_string(bundle.join("control_frames.jsonl")).unwrap();
+    assert!(frames.contains("\"initialize\""));
+    let manifest: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(bundle.join("manifest.json")).unwrap()).unwrap();
+    assert_eq!(manifest["sdk_version"], env!("CARGO_PKG_VERSION"));
+    assert!(bundle.join("stderr.log").exists());
+}
+
+#[tokio::test]
+async fn client_reports_progress_events() {
+    let transport = MockTransport::new();
+    transport
+        .reply_to_next_user(vec![
+            json!({"type": "system", "subtype": "init", "session_id": "s-1", "model": "claude-opus-test"}),
+            json!({
+                "type": "assistant",
+                "message": {
+                    "model": "claude-opus-test",
+                    "content": [{"type": "tool_use", "id": "tool-1", "name": "Bash", "input": {"command": "ls"}}]
+                }
+            }),
+            json!({
+                "type": "user",
+                "message": {
+                    "role": "user",
+                    "content": [{"type": "tool_result", "tool_use_id": "tool-1", "content": "ok"}]
+                }
+            }),
+            assistant_message("done"),
+            result_message(),
+        ])
+        .await;
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    let progress = client
+        .progress_events(std::time::Duration::from_secs(60))
+        .await
+        .expect("progress stream should be available");
+    client
+        .query("List files", "s-1")
+        .await
+        .expect("query should be written");
+
+    let events: Vec<ProgressEvent> = tokio::time::timeout(
+        std::time::Duration::from_secs(5),
+        progress
+            .filter(|event| {
+                futures::future::ready(!matches!(event, ProgressEvent::ToolRunning { .. }))
+            })
+            .take(6)
+            .collect(),
+    )
+    .await
+    .expect("progress events should arrive");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    assert_eq!(
+        events[0],
+        ProgressEvent::SessionStarted {
+            session_id: Some("s-1".into()),
+            model: Some("claude-opus-test".into()),
+        }
+    );
+    assert_eq!(events[1], ProgressEvent::TurnStarted(1));
+    assert_eq!(
+        events[2],
+        ProgressEvent::ToolStarted {
+            tool_use_id: "tool-1".into(),
+            name: "Bash".into(),
+        }
+    );
+    assert!(matches!(
+        &events[3],
+        ProgressEvent::ToolFinished { name, is_error: false, .. } if name == "Bash"
+    ));
+    assert_eq!(events[4], ProgressEvent::TurnStarted(2));
+    assert!(matches!(
+        events[5],
+        ProgressEvent::Completed {
+            is_error: false,
+            ..
+        }
+    ));
+}
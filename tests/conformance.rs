@@ -0,0 +1,57 @@
+//! Replays the control protocol exchanges in `tests/fixtures/conformance`,
+//! transcribed from the Python SDK, and requires the same frames from this
+//! SDK.
+//!
+//! A failure means the SDKs drifted apart on the wire. Fix the SDK, or when
+//! the Python SDK changed, update the case to its new output.
+
+use std::path::Path;
+
+use serde_json::json;
+
+use sdk_claude_rust::testing::conformance::{check_conformance, ConformanceCase};
+
+#[tokio::test]
+async fn recorded_exchanges_match_the_python_sdk() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/conformance");
+    let mut cases: Vec<_> = std::fs::read_dir(&dir)
+        .expect("fixture directory")
+        .map(|entry| entry.expect("fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "json"))
+        .collect();
+    cases.sort();
+    assert!(!cases.is_empty(), "no cases in {}", dir.display());
+
+    let mut failures = Vec::new();
+    for case in &cases {
+        if let Err(message) = check_conformance(case).await {
+            failures.push(message);
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[tokio::test]
+async fn drift_in_key_casing_is_reported() {
+    let case: ConformanceCase = serde_json::from_value(json!({
+        "setup": {"can_use_tool": {"behavior": "allow"}},
+        "exchange": [
+            {"sdk": {"type": "control_request", "request_id": "$init",
+                     "request": {"subtype": "initialize", "hooks": null}}},
+            {"cli": {"type": "control_response", "response": {
+                "subtype": "success", "request_id": "$init", "response": {}}}},
+            {"cli": {"type": "control_request", "request_id": "cli-1", "request": {
+                "subtype": "can_use_tool", "tool_name": "Read", "input": {"file_path": "a"}}}},
+            {"sdk": {"type": "control_response", "response": {
+                "subtype": "success", "request_id": "cli-1",
+                "response": {"behavior": "allow", "updated_input": {"file_path": "a"}}}}}
+        ]
+    }))
+    .unwrap();
+    let message = case.run().await.unwrap_err();
+    assert!(message.starts_with("step 4:"), "{message}");
+    assert!(
+        message.contains(r#""updatedInput":{"file_path":"a"}"#),
+        "{message}"
+    );
+}
@@ -0,0 +1,96 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::context_window::{ContextEvent, ContextWindowTracker};
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn event(event: Value) -> Value {
+    json!({"type": "stream_event", "uuid": "evt", "session_id": "sess-ctx", "event": event})
+}
+
+fn result(usage: Value) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-ctx",
+        "usage": usage
+    })
+}
+
+#[test]
+fn warns_once_per_compaction_when_usage_crosses_the_threshold() {
+    let mut tracker = ContextWindowTracker::new(200_000).with_threshold(0.8);
+    let mut observe = |raw: Value| tracker.observe(&parse_message(&raw).unwrap());
+
+    let start = event(json!({"type": "message_start", "message": {"usage": {
+        "input_tokens": 10, "cache_read_input_tokens": 150_000, "output_tokens": 1
+    }}}));
+    assert!(observe(start).is_none());
+    let warning = observe(event(
+        json!({"type": "message_delta", "usage": {"output_tokens": 12_000}}),
+    ))
+    .expect("usage reached 80%");
+    assert_eq!(warning.used_tokens, 162_010);
+    assert!(!warning.will_compact);
+    assert!(observe(result(json!({"input_tokens": 999_999}))).is_none());
+
+    let boundary = json!({"type": "system", "subtype": "compact_boundary",
+                          "compact_metadata": {"trigger": "manual", "pre_tokens": 162_010}});
+    assert!(observe(boundary).is_none());
+    assert!(observe(result(json!({"input_tokens": 170_000}))).is_some());
+}
+
+#[tokio::test]
+async fn auto_compaction_runs_after_the_response() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![result(
+            json!({"input_tokens": 190_000, "output_tokens": 500}),
+        )])
+        .await;
+    transport
+        .reply_to_next_user(vec![
+            json!({"type": "system", "subtype": "compact_boundary", "session_id": "sess-ctx",
+                   "compact_metadata": {"trigger": "manual", "pre_tokens": 190_500}}),
+            result(json!({"input_tokens": 12_000})),
+        ])
+        .await;
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    client.query("Summarize the repo", "default").await.unwrap();
+
+    let mut tracker = ContextWindowTracker::for_model("sonnet").with_auto_compact(true);
+    let events: Vec<_> = client
+        .receive_response_with_context(&mut tracker)
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(events.len(), 3);
+    assert!(matches!(&events[0], ContextEvent::Message(_)));
+    assert!(matches!(&events[1], ContextEvent::Warning(warning) if warning.will_compact));
+    assert!(
+        matches!(&events[2], ContextEvent::Compacted(boundary) if boundary.subtype == "compact_boundary")
+    );
+    assert_eq!(tracker.used_tokens(), 12_000);
+
+    let prompts: Vec<_> = transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|write| write["type"] == "user")
+        .map(|write| write["message"]["content"].clone())
+        .collect();
+    assert_eq!(prompts, [json!("Summarize the repo"), json!("/compact")]);
+    client.disconnect().await.unwrap();
+}
@@ -0,0 +1,59 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::json;
+
+use sdk_claude_rust::buffer_limit::BufferLimit;
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+/// Interrupt while 500 messages wait unread, then read them all.
+async fn interrupt_behind_backlog(options: ClaudeAgentOptions) {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+
+    for index in 0..500 {
+        let says = json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [{"type": "text", "text": format!("{index}")}]}
+        });
+        transport.enqueue_read(Ok(Some(says))).await;
+    }
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    tokio::time::timeout(Duration::from_secs(5), client.interrupt())
+        .await
+        .expect("interrupt should not wait for the consumer")
+        .expect("interrupt should succeed");
+
+    let backlog = client
+        .receive_messages()
+        .unwrap()
+        .take(500)
+        .collect::<Vec<_>>()
+        .await;
+    assert!(backlog.iter().all(Result::is_ok));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn control_responses_overtake_an_unread_message_backlog() {
+    interrupt_behind_backlog(ClaudeAgentOptions::default()).await;
+}
+
+#[tokio::test]
+async fn control_responses_overtake_a_full_buffer_limit() {
+    let limit = BufferLimit::new(4 * 1024);
+    interrupt_behind_backlog(ClaudeAgentOptions {
+        buffer_limit: Some(limit.clone()),
+        ..Default::default()
+    })
+    .await;
+    assert_eq!(limit.buffered(), 0);
+}
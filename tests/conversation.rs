@@ -0,0 +1,103 @@
+use serde_json::{json, Value};
+
+use sdk_claude_rust::conversation::Conversation;
+use sdk_claude_rust::internal::message_parser::parse_message;
+
+fn transcript() -> Vec<Value> {
+    vec![
+        json!({"type": "system", "subtype": "init", "session_id": "sess-42"}),
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": "List the <src> files"}
+        }),
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [
+                {"type": "text", "text": "Checking."},
+                {"type": "tool_use", "id": "toolu_1", "name": "Bash", "input": {"command": "ls src"}}
+            ]}
+        }),
+        json!({
+            "type": "user",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "lib.rs\n```main.rs"}
+            ]}
+        }),
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [
+                {"type": "text", "text": "Two files: lib.rs & main.rs."}
+            ]}
+        }),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 2500,
+            "duration_api_ms": 2000,
+            "is_error": false,
+            "num_turns": 2,
+            "session_id": "sess-42",
+            "total_cost_usd": 0.0125
+        }),
+    ]
+}
+
+fn conversation() -> Conversation {
+    transcript()
+        .iter()
+        .map(|raw| parse_message(raw).expect("fixture should parse"))
+        .collect()
+}
+
+#[test]
+fn markdown_folds_tool_calls_with_their_results() {
+    let conversation = conversation();
+    assert_eq!(conversation.session_id(), Some("sess-42"));
+    assert_eq!(conversation.total_cost_usd(), Some(0.0125));
+
+    assert_eq!(
+        conversation.export_markdown(),
+        "# Session sess-42\n\
+         \n## User\n\nList the <src> files\n\
+         \n## Assistant\n\nChecking.\n\
+         \n<details>\n<summary>Tool: Bash</summary>\n\n**Input**\n\n\
+         ```json\n{\n  \"command\": \"ls src\"\n}\n```\n\
+         \n**Result**\n\n````\nlib.rs\n```main.rs\n````\n\
+         \n</details>\n\
+         \n## Assistant\n\nTwo files: lib.rs & main.rs.\n\
+         \n---\n\n_2 turns · 2.5s · $0.0125_\n"
+    );
+}
+
+#[test]
+fn html_escapes_content_and_keeps_the_same_structure() {
+    let html = conversation().export_html();
+
+    assert!(html.starts_with("<!DOCTYPE html>"));
+    assert!(html.contains("<title>Session sess-42</title>"));
+    assert!(html.contains("List the &lt;src&gt; files"));
+    assert!(html.contains("Two files: lib.rs &amp; main.rs."));
+    assert!(html.contains(
+        "<details class=\"tool\"><summary>Tool: Bash</summary><h3>Input</h3><pre>{\n  &quot;command&quot;: &quot;ls src&quot;\n}</pre><h3>Result</h3><pre>lib.rs\n```main.rs</pre></details>"
+    ));
+    assert!(html.contains("<footer>2 turns · 2.5s · $0.0125</footer>"));
+    assert!(html.ends_with("</html>\n"));
+}
+
+#[test]
+fn load_skips_lines_that_are_not_messages() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("session.jsonl");
+    let mut lines: Vec<String> = transcript().iter().map(Value::to_string).collect();
+    lines.insert(
+        1,
+        json!({"type": "control_request", "request_id": "r1", "request": {"subtype": "interrupt"}})
+            .to_string(),
+    );
+    lines.push(String::new());
+    std::fs::write(&path, lines.join("\n")).unwrap();
+
+    let loaded = Conversation::load(&path).expect("transcript should load");
+    assert_eq!(loaded, conversation());
+    assert_eq!(Conversation::new().export_markdown(), "# Conversation\n");
+}
@@ -0,0 +1,123 @@
+use std::sync::{Arc, Mutex};
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::{ClaudeSdkClient, DynTransport};
+use sdk_claude_rust::error::{ProcessError, SdkError};
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::recovery::{
+    CrashRecovery, RecoveringTransport, TransportFactory, RECOVERED_SUBTYPE,
+};
+use sdk_claude_rust::testing::MockTransport;
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "session_id": "sess-crash",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-crash"
+    })
+}
+
+fn crash() -> Result<Option<Value>, SdkError> {
+    Err(ProcessError::new("CLI exited", Some(137), None).into())
+}
+
+/// Factory handing out `transports` in order, recording the resume ids.
+fn factory(
+    transports: Vec<Arc<MockTransport>>,
+) -> (TransportFactory, Arc<Mutex<Vec<Option<String>>>>) {
+    let resumes = Arc::new(Mutex::new(Vec::new()));
+    let transports = Mutex::new(transports.into_iter());
+    let seen = Arc::clone(&resumes);
+    let factory = move |resume: Option<String>| {
+        seen.lock().unwrap().push(resume);
+        let next = transports
+            .lock()
+            .unwrap()
+            .next()
+            .expect("no transport left");
+        Ok(next as DynTransport)
+    };
+    (Arc::new(factory), resumes)
+}
+
+fn is_recovered(message: &Message) -> bool {
+    matches!(message, Message::System(system) if system.subtype == RECOVERED_SUBTYPE)
+}
+
+#[tokio::test]
+async fn crashed_cli_is_resumed_and_the_turn_completes() {
+    let first = MockTransport::with_reads([Ok(Some(says("partial"))), crash()]);
+    let second = MockTransport::with_reads([Ok(Some(says("done"))), Ok(Some(result()))]);
+    let (factory, resumes) = factory(vec![first, second]);
+    let transport = RecoveringTransport::new(factory, CrashRecovery::new(1)).unwrap();
+
+    let messages: Vec<Message> = query("Hi", None, Some(Arc::new(transport)))
+        .await
+        .expect("query should start")
+        .map(Result::unwrap)
+        .collect()
+        .await;
+
+    assert_eq!(messages.len(), 4);
+    assert!(is_recovered(&messages[1]));
+    assert!(matches!(&messages[3], Message::Result(_)));
+    assert_eq!(
+        *resumes.lock().unwrap(),
+        [None, Some("sess-crash".to_string())]
+    );
+}
+
+#[tokio::test]
+async fn pending_prompt_and_initialize_are_replayed() {
+    let first = MockTransport::new();
+    first.set_keep_open(true);
+    let second = MockTransport::new();
+    second.set_keep_open(true);
+    second.reply_to_next_user([says("done"), result()]).await;
+    let (factory, _) = factory(vec![first.clone(), second.clone()]);
+    let transport = RecoveringTransport::new(factory, CrashRecovery::default()).unwrap();
+
+    let mut client = ClaudeSdkClient::new(None, Some(Arc::new(transport)));
+    client.connect(None).await.expect("connect should succeed");
+    client.query("Keep going", "default").await.unwrap();
+    first.enqueue_read(crash()).await;
+
+    let messages: Vec<Message> = client
+        .receive_response()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    client.disconnect().await.unwrap();
+
+    assert!(is_recovered(&messages[0]));
+    assert!(matches!(&messages[2], Message::Result(_)));
+    let replayed: Vec<_> = second
+        .writes()
+        .await
+        .into_iter()
+        .map(|write| {
+            write
+                .pointer("/request/subtype")
+                .or_else(|| write.pointer("/message/content"))
+                .cloned()
+                .unwrap_or_default()
+        })
+        .collect();
+    assert_eq!(replayed[..2], [json!("initialize"), json!("Keep going")]);
+}
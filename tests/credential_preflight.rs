@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::env::{validate_credentials_with, CredentialStatus, Provider};
+use sdk_claude_rust::permission::{PermissionMode, PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn reply(model: &str, text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": model, "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result(is_error: bool, text: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": is_error,
+        "num_turns": 1,
+        "session_id": "sess-preflight",
+        "result": text
+    })
+}
+
+/// Options whose credentials do not depend on the test's environment.
+fn options(api_key: &str) -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        provider: Some(Provider::Anthropic),
+        env: HashMap::from([
+            ("ANTHROPIC_API_KEY".to_string(), api_key.to_string()),
+            ("ANTHROPIC_AUTH_TOKEN".to_string(), String::new()),
+            ("CLAUDE_CODE_OAUTH_TOKEN".to_string(), String::new()),
+        ]),
+        ..Default::default()
+    }
+}
+
+async fn check(options: &ClaudeAgentOptions, reads: Vec<Value>) -> CredentialStatus {
+    let transport = MockTransport::with_reads(reads.into_iter().map(|read| Ok(Some(read))));
+    validate_credentials_with(options, Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("preflight should complete")
+}
+
+#[tokio::test]
+async fn answered_preflight_is_valid() {
+    let status = check(
+        &options("sk-ant-good"),
+        vec![reply("claude-test", "OK"), result(false, "OK")],
+    )
+    .await;
+    assert_eq!(
+        status,
+        CredentialStatus::Valid {
+            model: Some("claude-test".into())
+        }
+    );
+    assert!(status.is_valid());
+}
+
+#[tokio::test]
+async fn rejected_key_is_missing_or_invalid() {
+    let rejected = || {
+        let text = "Invalid API key · Please run /login";
+        vec![reply("<synthetic>", text), result(true, text)]
+    };
+    assert_eq!(
+        check(&options("sk-ant-revoked"), rejected()).await,
+        CredentialStatus::InvalidKey {
+            message: "Invalid API key · Please run /login".into()
+        }
+    );
+    assert_eq!(
+        check(&options(""), rejected()).await,
+        CredentialStatus::MissingKey
+    );
+}
+
+#[tokio::test]
+async fn billing_failures_are_entitlement_problems() {
+    let text = "Credit balance is too low";
+    let status = check(
+        &options("sk-ant-good"),
+        vec![reply("<synthetic>", text), result(true, text)],
+    )
+    .await;
+    assert_eq!(
+        status,
+        CredentialStatus::InsufficientEntitlements {
+            message: text.into()
+        }
+    );
+}
+
+#[tokio::test]
+async fn preflight_leaves_out_callbacks_tools_and_permissions() {
+    let allow = Arc::new(|_: &str, _: Map<String, Value>, _: ToolPermissionContext| {
+        Box::pin(async {
+            PermissionResult::Allow {
+                updated_input: None,
+                updated_permissions: None,
+            }
+        })
+    });
+    // A permission callback needs a streaming prompt, so a probe that kept
+    // it would fail before reaching the CLI.
+    let options = ClaudeAgentOptions {
+        model: Some("sonnet".into()),
+        can_use_tool: Some(allow),
+        allowed_tools: vec!["Bash".into()],
+        permission_mode: Some(PermissionMode::BypassPermissions),
+        ..options("sk-ant-good")
+    };
+    let status = check(
+        &options,
+        vec![reply("claude-test", "OK"), result(false, "OK")],
+    )
+    .await;
+    assert!(status.is_valid());
+}
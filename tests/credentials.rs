@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Arc;
+
+use sdk_claude_rust::credentials::{
+    CredentialChain, CredentialProvider, Credentials, DotenvCredentials,
+};
+use sdk_claude_rust::error::SdkError;
+
+#[tokio::test]
+async fn dotenv_file_is_reread_on_every_call() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let path = dir.path().join(".env");
+    let provider = DotenvCredentials::new(&path);
+    assert!(provider.credentials().await.unwrap().is_empty());
+
+    std::fs::write(
+        &path,
+        "ANTHROPIC_API_KEY=sk-ant-old\nANTHROPIC_MODEL=claude-test\n",
+    )
+    .unwrap();
+    assert_eq!(
+        provider.credentials().await.unwrap()["ANTHROPIC_API_KEY"],
+        "sk-ant-old"
+    );
+    std::fs::write(&path, "ANTHROPIC_API_KEY=sk-ant-new\n").unwrap();
+    assert_eq!(
+        provider.credentials().await.unwrap(),
+        HashMap::from([("ANTHROPIC_API_KEY".to_string(), "sk-ant-new".to_string())])
+    );
+    assert!(std::env::var("ANTHROPIC_API_KEY").map_or(true, |key| key != "sk-ant-new"));
+}
+
+#[tokio::test]
+async fn chain_merges_in_order_and_propagates_failures() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let path = dir.path().join(".env");
+    std::fs::write(
+        &path,
+        "ANTHROPIC_API_KEY=sk-ant-file\nAWS_REGION=us-east-1\n",
+    )
+    .unwrap();
+    let chain = CredentialChain::new()
+        .with(DotenvCredentials::new(&path))
+        .with(|| async {
+            Ok(Credentials::from([(
+                "ANTHROPIC_API_KEY".to_string(),
+                "sk-ant-vault".to_string(),
+            )]))
+        });
+    let credentials = chain.credentials().await.unwrap();
+    assert_eq!(credentials["ANTHROPIC_API_KEY"], "sk-ant-vault");
+    assert_eq!(credentials["AWS_REGION"], "us-east-1");
+
+    let failing = CredentialChain::new()
+        .with(DotenvCredentials::new(&path))
+        .with(|| async { Err(SdkError::Credentials("vault sealed".into())) });
+    let err = failing.credentials().await.expect_err("chain should fail");
+    assert_eq!(err.to_string(), "credentials unavailable: vault sealed");
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn credentials_are_resolved_on_each_connect() {
+    use std::os::unix::fs::PermissionsExt;
+
+    use sdk_claude_rust::config::ClaudeAgentOptions;
+    use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+    use sdk_claude_rust::transport::Transport;
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let cli = dir.path().join("claude");
+    std::fs::write(
+        &cli,
+        "#!/bin/sh\n\
+         if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+         printf '{\"key\":\"%s\"}\\n' \"$ANTHROPIC_API_KEY\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let rotations = Arc::new(AtomicUsize::new(0));
+    let counter = rotations.clone();
+    let options = ClaudeAgentOptions {
+        cli_path: Some(cli),
+        env: HashMap::from([("ANTHROPIC_API_KEY".to_string(), "sk-ant-stale".to_string())]),
+        credential_provider: Some(Arc::new(move || {
+            let rotation = counter.fetch_add(1, Ordering::SeqCst) + 1;
+            async move {
+                Ok(Credentials::from([(
+                    "ANTHROPIC_API_KEY".to_string(),
+                    format!("sk-ant-{rotation}"),
+                )]))
+            }
+        })),
+        ..Default::default()
+    };
+
+    let mut keys = Vec::new();
+    for _ in 0..2 {
+        let transport = SubprocessCliTransport::new(PromptMode::Text("hi".into()), options.clone())
+            .expect("transport should build");
+        transport.connect().await.expect("spawn should succeed");
+        let printed = transport.read().await.unwrap().expect("key line");
+        transport.close().await.unwrap();
+        keys.push(printed["key"].as_str().unwrap().to_string());
+    }
+    assert_eq!(keys, ["sk-ant-1", "sk-ant-2"]);
+    assert_eq!(rotations.load(Ordering::SeqCst), 2);
+}
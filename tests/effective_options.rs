@@ -0,0 +1,67 @@
+use std::sync::Arc;
+
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test]
+async fn connect_records_the_options_the_session_runs_with() {
+    let allow = Arc::new(|_: &str, _: Map<String, Value>, _: ToolPermissionContext| {
+        Box::pin(async {
+            PermissionResult::Allow {
+                updated_input: None,
+                updated_permissions: None,
+            }
+        })
+    });
+    let options = ClaudeAgentOptions {
+        model: Some("sonnet".into()),
+        can_use_tool: Some(allow),
+        ..Default::default()
+    };
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport as Arc<dyn Transport>));
+    assert!(client.effective_options().is_none());
+
+    client.connect(None).await.unwrap();
+    let effective = client.effective_options().expect("snapshot after connect");
+    assert_eq!(
+        effective.permission_prompt_tool_name.as_deref(),
+        Some("stdio")
+    );
+
+    let changed: Vec<_> = effective
+        .diff_from_defaults()
+        .into_iter()
+        .map(|change| (change.field, change.before, change.after))
+        .collect();
+    assert_eq!(
+        changed,
+        [
+            ("model".to_string(), Value::Null, json!("sonnet")),
+            (
+                "permission_prompt_tool_name".to_string(),
+                Value::Null,
+                json!("stdio")
+            ),
+        ]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn diffs_mask_secrets() {
+    let options = ClaudeAgentOptions {
+        env: [("ANTHROPIC_API_KEY".to_string(), "sk-ant-secret".to_string())].into(),
+        ..Default::default()
+    };
+    let changes = options.diff_from_defaults();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].field, "env");
+    assert!(!changes[0].after.to_string().contains("sk-ant-secret"));
+}
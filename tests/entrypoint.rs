@@ -0,0 +1,45 @@
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+use sdk_claude_rust::transport::Transport;
+
+async fn reported_entrypoint(entrypoint: Option<&str>) -> String {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let cli = dir.path().join("claude");
+    std::fs::write(
+        &cli,
+        "#!/bin/sh\n\
+         if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+         printf '{\"entrypoint\":\"%s\"}\\n' \"$CLAUDE_CODE_ENTRYPOINT\"\n",
+    )
+    .expect("script should be written");
+    std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755))
+        .expect("script should be executable");
+
+    let options = ClaudeAgentOptions {
+        cli_path: Some(cli),
+        entrypoint: entrypoint.map(str::to_string),
+        ..Default::default()
+    };
+    let transport = SubprocessCliTransport::new(PromptMode::Text("hi".into()), options)
+        .expect("transport should build");
+    transport.connect().await.expect("spawn should succeed");
+    let printed = transport.read().await.unwrap().expect("entrypoint line");
+    transport.close().await.unwrap();
+    printed["entrypoint"].as_str().unwrap().to_string()
+}
+
+#[tokio::test]
+async fn entrypoint_is_set_in_the_cli_environment_only() {
+    std::env::remove_var("CLAUDE_CODE_ENTRYPOINT");
+
+    assert_eq!(reported_entrypoint(None).await, "sdk-rs");
+    assert_eq!(
+        reported_entrypoint(Some("billing-bot")).await,
+        "billing-bot"
+    );
+    assert!(std::env::var("CLAUDE_CODE_ENTRYPOINT").is_err());
+}
@@ -0,0 +1,37 @@
+//! Mutates the process environment, so it keeps to a single test.
+
+use sdk_claude_rust::env::{load_env_profile, EnvError};
+
+#[test]
+fn profile_precedence_and_errors() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    std::fs::write(
+        dir.path().join(".env"),
+        "ANTHROPIC_API_KEY=sk-ant-base\n\
+         ANTHROPIC_BASE_URL=https://base.example.com\n\
+         ANTHROPIC_MODEL=claude-base\n",
+    )
+    .unwrap();
+    std::fs::write(
+        dir.path().join(".env.staging"),
+        "ANTHROPIC_API_KEY=sk-ant-staging\nANTHROPIC_MODEL=claude-staging\n",
+    )
+    .unwrap();
+    std::env::remove_var("ANTHROPIC_API_KEY");
+    std::env::remove_var("ANTHROPIC_BASE_URL");
+    std::env::set_var("ANTHROPIC_MODEL", "claude-process");
+
+    let env = load_env_profile(Some(dir.path()), "staging").expect("profile should load");
+    assert_eq!(env["ANTHROPIC_API_KEY"], "sk-ant-staging");
+    assert_eq!(env["ANTHROPIC_BASE_URL"], "https://base.example.com");
+    assert_eq!(env["ANTHROPIC_MODEL"], "claude-process");
+
+    assert!(matches!(
+        load_env_profile(Some(dir.path()), "prod"),
+        Err(EnvError::Io(message)) if message.contains(".env.prod")
+    ));
+    assert!(matches!(
+        load_env_profile(Some(dir.path()), "../staging"),
+        Err(EnvError::Parse(_))
+    ));
+}
@@ -0,0 +1,205 @@
+//! End-to-end tests driving `SubprocessCliTransport` against the bundled
+//! `fake-claude` binary instead of the real CLI.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::{pin_mut, StreamExt};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{ClaudeAgentOptions, StdoutMode};
+use sdk_claude_rust::message::{ContentBlock, Message};
+use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::query::query;
+
+fn fake_cli_options() -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        cli_path: Some(PathBuf::from(env!("CARGO_BIN_EXE_fake-claude"))),
+        ..Default::default()
+    }
+}
+
+fn assistant_text(messages: &[Message]) -> Vec<String> {
+    messages
+        .iter()
+        .filter_map(|message| match message {
+            Message::Assistant(assistant) => Some(&assistant.content),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text.clone()),
+            _ => None,
+        })
+        .collect()
+}
+
+async fn collect_response(client: &ClaudeSdkClient) -> Vec<Message> {
+    let stream = client.receive_response().expect("response stream");
+    pin_mut!(stream);
+    let mut messages = Vec::new();
+    while let Some(message) = tokio::time::timeout(Duration::from_secs(10), stream.next())
+        .await
+        .expect("fake CLI should answer")
+    {
+        messages.push(message.expect("message should parse"));
+    }
+    messages
+}
+
+#[tokio::test]
+async fn fake_cli_answers_streaming_turns() {
+    let mut client = ClaudeSdkClient::new(Some(fake_cli_options()), None);
+    client.connect(None).await.expect("connect to fake CLI");
+
+    for prompt in ["first", "second"] {
+        client.query(prompt, "default").await.expect("send prompt");
+        let messages = collect_response(&client).await;
+        assert_eq!(
+            assistant_text(&messages),
+            vec![format!("fake reply to: {prompt}")]
+        );
+        let Some(Message::Result(result)) = messages.last() else {
+            panic!("expected a result message, got {messages:?}");
+        };
+        assert!(!result.is_error);
+        assert_eq!(result.session_id, "fake-session");
+    }
+
+    client.disconnect().await.expect("disconnect");
+}
+
+#[tokio::test]
+async fn fake_cli_honours_interrupt() {
+    let mut client = ClaudeSdkClient::new(Some(fake_cli_options()), None);
+    client.connect(None).await.expect("connect to fake CLI");
+    client
+        .query("slow task", "default")
+        .await
+        .expect("send prompt");
+
+    let stream = client.receive_response().expect("response stream");
+    pin_mut!(stream);
+    let mut interrupted = false;
+    let mut result = None;
+    while let Some(message) = tokio::time::timeout(Duration::from_secs(10), stream.next())
+        .await
+        .expect("fake CLI should answer")
+    {
+        match message.expect("message should parse") {
+            Message::Assistant(_) if !interrupted => {
+                client.interrupt().await.expect("interrupt");
+                interrupted = true;
+            }
+            Message::Result(message) => result = Some(message),
+            _ => {}
+        }
+    }
+
+    let result = result.expect("result after interrupt");
+    assert!(result.is_error);
+    assert_eq!(result.subtype, "error_during_execution");
+    client.disconnect().await.expect("disconnect");
+}
+
+#[tokio::test]
+async fn fake_cli_asks_for_tool_permission() {
+    let callback = Arc::new(
+        |tool: &str,
+         _input: serde_json::Map<String, serde_json::Value>,
+         _ctx: ToolPermissionContext| {
+            let tool = tool.to_string();
+            Box::pin(async move {
+                if tool == "Bash" {
+                    PermissionResult::Allow {
+                        updated_input: None,
+                        updated_permissions: None,
+                    }
+                } else {
+                    PermissionResult::Deny {
+                        message: "unexpected tool".to_string(),
+                        interrupt: false,
+                    }
+                }
+            })
+        },
+    );
+    let options = ClaudeAgentOptions {
+        can_use_tool: Some(callback),
+        ..fake_cli_options()
+    };
+
+    let mut client = ClaudeSdkClient::new(Some(options), None);
+    client.connect(None).await.expect("connect to fake CLI");
+    client
+        .query("tool please", "default")
+        .await
+        .expect("send prompt");
+    let messages = collect_response(&client).await;
+
+    assert_eq!(
+        assistant_text(&messages),
+        vec!["permission allow", "fake reply to: tool please"]
+    );
+    client.disconnect().await.expect("disconnect");
+}
+
+#[tokio::test]
+async fn fake_cli_serves_one_shot_queries() {
+    let stream = query("ping", Some(fake_cli_options()), None)
+        .await
+        .expect("start query");
+    let messages: Vec<Message> = stream
+        .map(|message| message.expect("message should parse"))
+        .collect()
+        .await;
+
+    assert_eq!(assistant_text(&messages), vec!["fake reply to: ping"]);
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+}
+
+#[tokio::test]
+async fn tolerant_stdout_mode_discards_banners() {
+    let stderr = Arc::new(Mutex::new(Vec::new()));
+    let captured = Arc::clone(&stderr);
+    let mut options = fake_cli_options();
+    options.stdout_mode = StdoutMode::Tolerant;
+    options
+        .env
+        .insert("FAKE_CLAUDE_BANNER".into(), "Plugin loaded: lint v2".into());
+    options.stderr = Some(Arc::new(move |line: &str| {
+        captured.lock().unwrap().push(line.to_string());
+    }));
+
+    let stream = query("ping", Some(options), None)
+        .await
+        .expect("start query");
+    let messages: Vec<Message> = stream
+        .map(|message| message.expect("message should parse"))
+        .collect()
+        .await;
+
+    assert_eq!(assistant_text(&messages), vec!["fake reply to: ping"]);
+    assert!(stderr
+        .lock()
+        .unwrap()
+        .contains(&"Plugin loaded: lint v2".to_string()));
+}
+
+#[tokio::test]
+async fn fake_cli_process_info_describes_the_child() {
+    let mut client = ClaudeSdkClient::new(Some(fake_cli_options()), None);
+    assert_eq!(client.process_info().await, None);
+    client.connect(None).await.expect("connect to fake CLI");
+
+    let info = client.process_info().await.expect("process info");
+    assert_ne!(info.pid, std::process::id());
+    assert!(info.started_at <= std::time::SystemTime::now());
+    if cfg!(target_os = "linux") {
+        assert!(info.rss_bytes.is_some_and(|rss| rss > 0));
+    }
+
+    client.disconnect().await.expect("disconnect");
+    assert_eq!(client.process_info().await, None);
+}
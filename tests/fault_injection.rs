@@ -0,0 +1,116 @@
+mod common;
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use futures::StreamExt;
+use serde_json::json;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::error::{ErrorKind, SdkError};
+use sdk_claude_rust::testing::FaultyTransport;
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+fn assistant_message(text: &str) -> serde_json::Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-opus-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+#[tokio::test]
+async fn faulty_transport_truncates_delays_and_ends_reads() {
+    let inner = MockTransport::with_reads(
+        ["one", "two", "three", "four"].map(|text| Ok(Some(assistant_message(text)))),
+    );
+    let transport = FaultyTransport::new(inner)
+        .with_read_delay(Duration::from_millis(20))
+        .with_truncated_read(1)
+        .with_eof_after(3);
+
+    let started = Instant::now();
+    assert!(matches!(transport.read().await, Ok(Some(_))));
+    assert!(started.elapsed() >= Duration::from_millis(20));
+
+    let err = transport
+        .read()
+        .await
+        .expect_err("second read is truncated");
+    let SdkError::CliJsonDecode(decode) = &err else {
+        panic!("expected decode error, got {err:?}");
+    };
+    assert!(decode.line().starts_with("{\""));
+    assert_eq!(err.kind(), ErrorKind::Parse);
+
+    assert!(matches!(transport.read().await, Ok(Some(_))));
+    assert!(matches!(transport.read().await, Ok(None)));
+    assert!(matches!(transport.read().await, Ok(None)));
+    assert_eq!(transport.reads(), 5);
+}
+
+#[tokio::test]
+async fn faulty_transport_surfaces_process_exit_to_client() {
+    let inner = MockTransport::new();
+    inner
+        .reply_to_next_user(vec![
+            assistant_message("partial"),
+            assistant_message("never delivered"),
+        ])
+        .await;
+    inner.set_keep_open(true);
+    // Read 0 is the initialize response, read 1 the first assistant message.
+    let transport: Arc<dyn Transport> =
+        Arc::new(FaultyTransport::new(inner).with_exit_after(2, 137));
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("Hi", "session-faults")
+        .await
+        .expect("query should be written");
+    let messages: Vec<_> = client
+        .receive_messages()
+        .expect("stream should be available")
+        .collect()
+        .await;
+
+    assert!(matches!(messages.first(), Some(Ok(_))));
+    let err = messages
+        .iter()
+        .find_map(|message| message.as_ref().err())
+        .expect("process exit should be reported");
+    assert_eq!(err.kind(), ErrorKind::ProcessExit);
+    assert!(err.is_retryable());
+    assert!(err.to_string().contains("137"));
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn faulty_transport_fails_writes_after_limit() {
+    let inner = MockTransport::new();
+    inner.set_keep_open(true);
+    // Write 0 is the initialize request.
+    let transport: Arc<dyn Transport> =
+        Arc::new(FaultyTransport::new(inner).with_write_failure_after(2));
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("first", "session-faults")
+        .await
+        .expect("first prompt fits under the limit");
+    let err = client
+        .query("second", "session-faults")
+        .await
+        .expect_err("second prompt should fail");
+    assert_eq!(err.kind(), ErrorKind::Connection);
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
@@ -0,0 +1,54 @@
+use std::collections::HashMap;
+
+use sdk_claude_rust::config::{ClaudeAgentOptions, FlagMode};
+use sdk_claude_rust::error::SdkError;
+
+fn options(flag: &str, flag_mode: FlagMode) -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        extra_args: HashMap::from([
+            ("debug-to-stderr".to_string(), None),
+            (flag.to_string(), Some("3".to_string())),
+        ]),
+        flag_mode,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn strict_mode_rejects_typos_with_a_suggestion() {
+    assert!(options("max-trns", FlagMode::Permissive).validate().is_ok());
+    assert!(options("max-turns", FlagMode::Strict).validate().is_ok());
+
+    let err = options("max-trns", FlagMode::Strict)
+        .validate()
+        .expect_err("typo should be rejected");
+    assert!(matches!(&err, SdkError::InvalidConfig(_)));
+    assert_eq!(
+        err.to_string(),
+        "unknown CLI flag --max-trns in extra_args; did you mean --max-turns?"
+    );
+    assert_eq!(
+        options("frobnicate", FlagMode::Strict)
+            .validate()
+            .unwrap_err()
+            .to_string(),
+        "unknown CLI flag --frobnicate in extra_args"
+    );
+}
+
+#[cfg(unix)]
+#[test]
+fn strict_mode_fails_before_spawning() {
+    use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+
+    let options = ClaudeAgentOptions {
+        cli_path: Some("/nonexistent/claude".into()),
+        ..options("--max-turns", FlagMode::Strict)
+    };
+    let err = SubprocessCliTransport::new(PromptMode::Streaming, options)
+        .expect_err("options should be rejected");
+    assert_eq!(
+        err.to_string(),
+        "unknown CLI flag ----max-turns in extra_args; did you mean --max-turns?"
+    );
+}
@@ -0,0 +1,66 @@
+//! Replays the recorded CLI transcripts in `tests/fixtures/golden` and
+//! compares the SDK's view of them with the checked-in snapshots.
+//!
+//! After an intentional parser change, refresh the snapshots with
+//! `SDK_UPDATE_GOLDEN=1 cargo test --test golden` and review the diff.
+
+use std::path::Path;
+
+use serde_json::json;
+
+use sdk_claude_rust::testing::golden::{check_golden, GoldenTranscript};
+
+#[tokio::test]
+async fn recorded_transcripts_match_snapshots() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/golden");
+    let mut transcripts: Vec<_> = std::fs::read_dir(&dir)
+        .expect("fixture directory")
+        .map(|entry| entry.expect("fixture entry").path())
+        .filter(|path| path.extension().is_some_and(|ext| ext == "jsonl"))
+        .collect();
+    transcripts.sort();
+    assert!(
+        !transcripts.is_empty(),
+        "no transcripts in {}",
+        dir.display()
+    );
+
+    let mut failures = Vec::new();
+    for transcript in &transcripts {
+        if let Err(message) = check_golden(transcript).await {
+            failures.push(message);
+        }
+    }
+    assert!(failures.is_empty(), "{}", failures.join("\n\n"));
+}
+
+#[tokio::test]
+async fn unknown_message_type_is_visible_in_rendering() {
+    let transcript = GoldenTranscript::new(vec![
+        json!({"type": "system", "subtype": "init", "session_id": "s"}),
+        json!({"type": "tool_progress", "tool_use_id": "t", "elapsed_ms": 10}),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "s"
+        }),
+    ]);
+
+    let rendered = transcript.render().await;
+    assert!(
+        rendered.contains("[2] tool_progress\nkeys: elapsed_ms, tool_use_id, type\nparse error: ")
+    );
+    let routed = rendered
+        .split("## routed\n")
+        .nth(1)
+        .expect("routed section");
+    assert!(
+        routed.starts_with("system/init\nerror (Parse): "),
+        "unexpected routing: {routed}"
+    );
+    assert!(routed.ends_with("end of stream\n"));
+}
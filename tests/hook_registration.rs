@@ -0,0 +1,65 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::json;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::hooks::{
+    HookContext, HookEvent, HookInput, HookJsonOutput, HookMatcher, HookRegistrationConfig,
+    HooksRegistration, SyncHookJsonOutput,
+};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn matcher(pattern: Option<&str>, callbacks: usize) -> HookMatcher {
+    let mut matcher = HookMatcher::new(pattern.map(|pattern| json!(pattern)));
+    for _ in 0..callbacks {
+        matcher.hooks.push(Arc::new(
+            |_: HookInput, _: Option<String>, _: HookContext| async {
+                HookJsonOutput::Sync(SyncHookJsonOutput::default())
+            },
+        ));
+    }
+    matcher
+}
+
+#[tokio::test]
+async fn initialize_registers_hooks_in_the_typed_shape() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let options = ClaudeAgentOptions {
+        hooks: Some(HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![matcher(Some("Bash"), 2), matcher(None, 0)],
+        )])),
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+
+    let writes = transport.writes().await;
+    let hooks: HooksRegistration =
+        serde_json::from_value(writes[0]["request"]["hooks"].clone()).unwrap();
+    assert_eq!(
+        hooks,
+        HashMap::from([(
+            HookEvent::PreToolUse,
+            vec![HookRegistrationConfig::new(
+                Some(json!("Bash")),
+                vec!["hook_0".into(), "hook_1".into()]
+            )]
+        )])
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[test]
+fn matchers_for_every_tool_omit_the_matcher_key() {
+    let config = HookRegistrationConfig::new(None, vec!["hook_3".into()]);
+    assert_eq!(
+        serde_json::to_value(&config).unwrap(),
+        json!({"hookCallbackIds": ["hook_3"]})
+    );
+}
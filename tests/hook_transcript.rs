@@ -0,0 +1,59 @@
+use std::path::Path;
+
+use serde_json::json;
+
+use sdk_claude_rust::hooks::HookInput;
+use sdk_claude_rust::message::Message;
+
+#[test]
+fn hook_paths_round_trip_as_paths() {
+    let raw = json!({
+        "hookEventName": "Stop",
+        "stopHookActive": false,
+        "sessionId": "sess-1",
+        "transcriptPath": "C:\\Users\\dev\\my project\\sess-1.jsonl",
+        "cwd": "/srv/repo \"quoted\""
+    });
+    let HookInput::Stop(input) = serde_json::from_value(raw.clone()).unwrap() else {
+        panic!("expected a Stop input");
+    };
+    assert_eq!(
+        input.base.transcript_path,
+        Path::new("C:\\Users\\dev\\my project\\sess-1.jsonl")
+    );
+    assert_eq!(input.base.cwd, Path::new("/srv/repo \"quoted\""));
+    assert_eq!(serde_json::to_value(HookInput::Stop(input)).unwrap(), raw);
+}
+
+#[test]
+fn transcript_loads_as_typed_messages() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("sess-1.jsonl");
+    let lines = [
+        json!({"type": "summary", "summary": "Listing files", "leafUuid": "u2"}),
+        json!({"type": "user", "uuid": "u1", "message": {"role": "user", "content": "List files"}}),
+        json!({
+            "type": "assistant",
+            "uuid": "u2",
+            "message": {"model": "claude-test", "content": [{"type": "text", "text": "README.md"}]}
+        }),
+    ];
+    let contents: Vec<String> = lines.iter().map(|line| line.to_string()).collect();
+    std::fs::write(&path, contents.join("\n") + "\n").unwrap();
+
+    let input: HookInput = serde_json::from_value(json!({
+        "hookEventName": "UserPromptSubmit",
+        "prompt": "next",
+        "sessionId": "sess-1",
+        "transcriptPath": path,
+        "cwd": dir.path()
+    }))
+    .unwrap();
+    let HookInput::UserPromptSubmit(input) = input else {
+        panic!("expected a UserPromptSubmit input");
+    };
+    let messages = input.base.load_transcript().unwrap();
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(&messages[0], Message::User(_)));
+    assert!(matches!(&messages[1], Message::Assistant(_)));
+}
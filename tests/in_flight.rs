@@ -0,0 +1,86 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::TryStreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{ClaudeAgentOptions, InFlightPolicy};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "default"
+    })
+}
+
+async fn connect(policy: InFlightPolicy) -> (ClaudeSdkClient, Arc<MockTransport>) {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let options = ClaudeAgentOptions {
+        in_flight_policy: policy,
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    (client, transport)
+}
+
+async fn prompts(transport: &MockTransport) -> Vec<Value> {
+    transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|write| write["type"] == "user")
+        .map(|write| write["message"]["content"].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn error_policy_rejects_overlapping_prompts() {
+    let (client, transport) = connect(InFlightPolicy::Error).await;
+    client.query("first", "default").await.unwrap();
+    let err = client.query("second", "default").await.unwrap_err();
+    assert!(matches!(err, SdkError::QueryInFlight { session_id } if session_id == "default"));
+    client.query("elsewhere", "other").await.unwrap();
+
+    transport.enqueue_read(Ok(Some(result()))).await;
+    let messages: Vec<Message> = client
+        .receive_response()
+        .unwrap()
+        .try_collect()
+        .await
+        .unwrap();
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+    client.query("second", "default").await.unwrap();
+    assert_eq!(
+        prompts(&transport).await,
+        [json!("first"), json!("elsewhere"), json!("second")]
+    );
+}
+
+#[tokio::test]
+async fn queue_policy_holds_prompts_until_the_result() {
+    let (client, transport) = connect(InFlightPolicy::Queue).await;
+    client.query("first", "default").await.unwrap();
+    let second = client.query("second", "default");
+    tokio::pin!(second);
+    assert!(tokio::time::timeout(Duration::from_millis(50), &mut second)
+        .await
+        .is_err());
+    assert_eq!(prompts(&transport).await, [json!("first")]);
+
+    transport.enqueue_read(Ok(Some(result()))).await;
+    second.await.unwrap();
+    assert_eq!(prompts(&transport).await, [json!("first"), json!("second")]);
+}
@@ -0,0 +1,85 @@
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::clock::VirtualClock;
+use sdk_claude_rust::error::{ProcessError, SdkError};
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::sinks::JsonlSink;
+
+fn result(cost: f64) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1200,
+        "duration_api_ms": 900,
+        "is_error": false,
+        "num_turns": 2,
+        "session_id": "sess-sink",
+        "total_cost_usd": cost
+    })
+}
+
+fn lines(output: Vec<u8>) -> Vec<Value> {
+    String::from_utf8(output)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test(start_paused = true)]
+async fn writes_one_normalized_record_per_message() {
+    let raw = [
+        json!({"type": "system", "subtype": "init", "session_id": "sess-sink", "model": "claude-test"}),
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [
+                {"type": "text", "text": "Reading"},
+                {"type": "tool_use", "id": "t1", "name": "Read", "input": {"file_path": "a"}}
+            ]}
+        }),
+        result(0.25),
+        result(0.75),
+    ];
+    let clock = VirtualClock::new(UNIX_EPOCH + Duration::from_secs(1_700_000_000));
+    let mut sink = JsonlSink::new(Vec::new()).with_clock(Arc::new(clock));
+    let written = sink
+        .consume(stream::iter(raw.iter().map(parse_message)))
+        .await
+        .unwrap();
+    assert_eq!(written, 4);
+
+    let records = lines(sink.into_inner());
+    assert_eq!(
+        records[0],
+        json!({"event": "system", "ts_ms": 1_700_000_000_000u64, "session_id": "sess-sink",
+               "subtype": "init", "model": "claude-test"})
+    );
+    assert_eq!(records[1]["event"], "assistant");
+    assert_eq!(records[1]["session_id"], "sess-sink");
+    assert_eq!(records[1]["tools"], json!(["Read"]));
+    assert_eq!(records[2]["cost_delta_usd"], 0.25);
+    assert_eq!(records[3]["total_cost_usd"], 0.75);
+    assert_eq!(records[3]["cost_delta_usd"], 0.5);
+    assert_eq!(records[3]["num_turns"], 2);
+}
+
+#[tokio::test]
+async fn stream_errors_are_recorded_then_returned() {
+    let messages = stream::iter([
+        parse_message(&result(0.1)),
+        Err(ProcessError::new("CLI exited", Some(1), None).into()),
+    ]);
+    let mut sink = JsonlSink::new(Vec::new());
+    let err = sink.consume(messages).await.unwrap_err();
+    assert!(matches!(err, SdkError::Process(_)));
+
+    let records = lines(sink.into_inner());
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[1]["event"], "error");
+    assert_eq!(records[1]["session_id"], "sess-sink");
+    assert!(records[1]["error"].as_str().unwrap().contains("CLI exited"));
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::internal::client::PromptInput;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+/// Connect a client whose prompt stream holds one message, and wait until
+/// that message was written.
+async fn connect(keep_input_open: bool) -> (ClaudeSdkClient, Arc<MockTransport>) {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let options = ClaudeAgentOptions {
+        keep_input_open,
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    let prompt = json!({
+        "type": "user",
+        "message": {"role": "user", "content": "first"},
+        "parent_tool_use_id": null,
+        "session_id": "default"
+    });
+    client
+        .connect(Some(PromptInput::from_stream(stream::iter(vec![prompt]))))
+        .await
+        .unwrap();
+    while user_prompts(&transport).await.is_empty() {
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    (client, transport)
+}
+
+async fn user_prompts(transport: &MockTransport) -> Vec<Value> {
+    transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|write| write["type"] == "user")
+        .map(|write| write["message"]["content"].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn finite_prompt_streams_close_stdin_by_default() {
+    let (client, transport) = connect(false).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(transport.end_input_calls().await, 1);
+    client.end_input().await.unwrap();
+    assert_eq!(transport.end_input_calls().await, 1);
+}
+
+#[tokio::test]
+async fn keep_input_open_leaves_stdin_to_the_caller() {
+    let (client, transport) = connect(true).await;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    assert_eq!(transport.end_input_calls().await, 0);
+
+    client.query("follow-up", "default").await.unwrap();
+    assert_eq!(
+        user_prompts(&transport).await,
+        [json!("first"), json!("follow-up")]
+    );
+    client.end_input().await.unwrap();
+    client.end_input().await.unwrap();
+    assert_eq!(transport.end_input_calls().await, 1);
+}
@@ -0,0 +1,84 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use sdk_claude_rust::assert_stream_yields;
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::matchers::{self, check_messages};
+use sdk_claude_rust::testing::Scenario;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test]
+async fn assert_stream_yields_matches_a_tool_session() {
+    let scenario = Scenario::new()
+        .respond_system("init", json!({"session_id": "s-1"}))
+        .respond_tool_use("toolu_1", "Bash", json!({"command": "ls", "timeout": 5}))
+        .respond_tool_result("toolu_1", json!("Cargo.toml"))
+        .respond_assistant("hello from the listing")
+        .respond_result("done");
+    let transport = scenario.transport().await;
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+
+    let stream = client
+        .receive_response()
+        .expect("stream should be available");
+    let messages = assert_stream_yields!(stream, [
+        System(init),
+        Assistant(tool_use "Bash", json!({"command": "ls"})),
+        User(tool_result "toolu_1"),
+        { matchers::assistant_text("hello").and(matchers::assistant_text("listing")) },
+        Result(success),
+    ]);
+    assert_eq!(messages.len(), 5);
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[test]
+fn check_messages_reports_each_position() {
+    let messages: Vec<Message> = [
+        json!({"type": "assistant", "message": {"model": "m", "content": [{"type": "text", "text": "hi"}]}}),
+        json!({
+            "type": "result",
+            "subtype": "error_max_turns",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": true,
+            "num_turns": 3,
+            "session_id": "s"
+        }),
+    ]
+    .iter()
+    .map(|raw| parse_message(raw).expect("fixture parses"))
+    .collect();
+
+    assert!(check_messages(
+        &messages,
+        &[matchers::assistant(), matchers::result_error()]
+    )
+    .is_ok());
+
+    let report = check_messages(
+        &messages,
+        &[
+            matchers::assistant_text("bye"),
+            matchers::result_subtype("success"),
+            matchers::any(),
+        ],
+    )
+    .expect_err("mismatch should be reported");
+    assert!(report.starts_with("expected 3 messages, got 2:"));
+    assert!(report.contains("✗ [0] expected Assistant(text ~ \"bye\"), got Assistant[text \"hi\"]"));
+    assert!(
+        report.contains("✗ [1] expected Result(success), got Result(error_max_turns, is_error)")
+    );
+    assert!(report.contains("✗ [2] expected any message, got nothing"));
+}
@@ -0,0 +1,226 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::mcp::{
+    create_sdk_mcp_server, McpFallbackHandle, McpResourceContents, McpResourceInfo,
+    McpServerCapabilities, McpToolCallResult, McpToolInfo, SdkMcpServer,
+};
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+struct DocsServer;
+
+#[async_trait]
+impl SdkMcpServer for DocsServer {
+    fn name(&self) -> &str {
+        "docs"
+    }
+
+    fn capabilities(&self) -> McpServerCapabilities {
+        McpServerCapabilities {
+            resources: true,
+            ..Default::default()
+        }
+    }
+
+    async fn list_tools(&self) -> Result<Vec<McpToolInfo>, SdkError> {
+        Ok(Vec::new())
+    }
+
+    async fn call_tool(
+        &self,
+        name: &str,
+        _arguments: Map<String, Value>,
+    ) -> Result<McpToolCallResult, SdkError> {
+        Err(SdkError::Message(format!("Tool '{name}' not found")))
+    }
+
+    async fn list_resources(&self) -> Result<Vec<McpResourceInfo>, SdkError> {
+        Ok(vec![
+            McpResourceInfo::new("docs://readme", "README").with_mime_type("text/markdown")
+        ])
+    }
+
+    async fn read_resource(&self, uri: &str) -> Result<Vec<McpResourceContents>, SdkError> {
+        Ok(vec![McpResourceContents::text(uri, "# Hello")])
+    }
+}
+
+fn mcp_request(request_id: &str, server: &str, message: Value) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {
+            "subtype": "mcp_message",
+            "server_name": server,
+            "message": message,
+        }
+    })
+}
+
+async fn run_requests(requests: Vec<Value>) -> HashMap<String, Value> {
+    run_requests_with_fallback(requests, None).await
+}
+
+async fn run_requests_with_fallback(
+    requests: Vec<Value>,
+    fallback: Option<McpFallbackHandle>,
+) -> HashMap<String, Value> {
+    let expected = requests.len();
+    let transport = MockTransport::with_reads(requests.into_iter().map(|r| Ok(Some(r))));
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let mut servers: HashMap<String, Arc<dyn SdkMcpServer>> = HashMap::new();
+    servers.insert("docs".into(), Arc::new(DocsServer));
+    servers.insert(
+        "tools-only".into(),
+        create_sdk_mcp_server("tools-only", "1.0.0", Vec::new()),
+    );
+
+    let query = Query::new(transport_arc, true, None, None, servers);
+    query.set_mcp_fallback(fallback).await;
+    query.start().await.expect("query should start");
+
+    let mut responses = HashMap::new();
+    for _ in 0..100 {
+        responses = transport
+            .writes()
+            .await
+            .into_iter()
+            .filter_map(|payload| {
+                let response = payload.get("response")?;
+                let id = response.get("request_id")?.as_str()?.to_string();
+                let body = response.get("response").or_else(|| response.get("error"))?;
+                Some((id, body.clone()))
+            })
+            .collect();
+        if responses.len() == expected {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    query.close().await.expect("close should succeed");
+    responses
+}
+
+#[tokio::test]
+async fn mcp_bridge_routes_extended_methods() {
+    let responses = run_requests(vec![
+        mcp_request(
+            "ping",
+            "docs",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "ping"}),
+        ),
+        mcp_request(
+            "init",
+            "docs",
+            json!({"jsonrpc": "2.0", "id": 2, "method": "initialize"}),
+        ),
+        mcp_request(
+            "list",
+            "docs",
+            json!({"jsonrpc": "2.0", "id": 3, "method": "resources/list"}),
+        ),
+        mcp_request(
+            "read",
+            "docs",
+            json!({
+                "jsonrpc": "2.0",
+                "id": 4,
+                "method": "resources/read",
+                "params": {"uri": "docs://readme"}
+            }),
+        ),
+        mcp_request(
+            "cancelled",
+            "docs",
+            json!({"jsonrpc": "2.0", "method": "notifications/cancelled"}),
+        ),
+    ])
+    .await;
+
+    assert_eq!(responses["ping"]["id"], json!(1));
+    assert_eq!(responses["ping"]["result"], json!({}));
+
+    let capabilities = &responses["init"]["result"]["capabilities"];
+    assert!(capabilities.get("resources").is_some());
+    assert!(capabilities.get("tools").is_some());
+    assert!(capabilities.get("prompts").is_none());
+
+    assert_eq!(
+        responses["list"]["result"]["resources"][0]["mimeType"],
+        json!("text/markdown")
+    );
+    assert_eq!(
+        responses["read"]["result"]["contents"][0]["text"],
+        json!("# Hello")
+    );
+    assert!(responses["cancelled"].get("error").is_none());
+}
+
+#[tokio::test]
+async fn mcp_bridge_reports_unsupported_and_invalid_requests() {
+    let responses = run_requests(vec![
+        mcp_request(
+            "prompts",
+            "tools-only",
+            json!({"jsonrpc": "2.0", "id": 1, "method": "prompts/list"}),
+        ),
+        mcp_request(
+            "unknown",
+            "docs",
+            json!({"jsonrpc": "2.0", "id": 2, "method": "sampling/createMessage"}),
+        ),
+        mcp_request(
+            "missing-uri",
+            "docs",
+            json!({"jsonrpc": "2.0", "id": 3, "method": "resources/read", "params": {}}),
+        ),
+    ])
+    .await;
+
+    assert_eq!(responses["prompts"]["error"]["code"], json!(-32601));
+    assert_eq!(responses["unknown"]["error"]["code"], json!(-32601));
+    assert_eq!(responses["missing-uri"]["error"]["code"], json!(-32602));
+}
+
+#[tokio::test]
+async fn unknown_servers_go_to_the_fallback_or_list_known_servers() {
+    let unknown = || {
+        vec![mcp_request(
+            "github",
+            "github",
+            json!({"jsonrpc": "2.0", "id": 7, "method": "tools/list"}),
+        )]
+    };
+
+    let responses = run_requests(unknown()).await;
+    assert_eq!(
+        responses["github"],
+        json!("Server 'github' not found; SDK MCP servers: docs, tools-only")
+    );
+
+    let fallback: McpFallbackHandle = Arc::new(|server: &str, message: Map<String, Value>| {
+        let server = server.to_string();
+        async move {
+            Ok(json!({
+                "jsonrpc": "2.0",
+                "id": message["id"],
+                "result": {"tools": [], "proxiedTo": server}
+            }))
+        }
+    });
+    let responses = run_requests_with_fallback(unknown(), Some(fallback)).await;
+    assert_eq!(responses["github"]["id"], json!(7));
+    assert_eq!(responses["github"]["result"]["proxiedTo"], json!("github"));
+}
@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use sdk_claude_rust::config::{
+    McpHttpServerConfig, McpServerConfig, McpServerKind, McpStdioServerConfig,
+};
+use sdk_claude_rust::error::SdkError;
+
+fn lookup(name: &str) -> Option<String> {
+    match name {
+        "GITHUB_TOKEN" => Some("ghp_secret".into()),
+        "TOOLS_DIR" => Some("/opt/tools".into()),
+        _ => None,
+    }
+}
+
+#[test]
+fn variables_and_defaults_are_expanded() {
+    let stdio = McpServerConfig::Stdio(McpStdioServerConfig {
+        r#type: None,
+        command: "${TOOLS_DIR}/github-mcp".into(),
+        args: Some(vec!["--log=${LOG_LEVEL:-info}".into()]),
+        env: Some(HashMap::from([(
+            "TOKEN".to_string(),
+            "${GITHUB_TOKEN}".to_string(),
+        )])),
+    });
+    let McpServerConfig::Stdio(stdio) = stdio.interpolate_env(lookup).unwrap() else {
+        panic!("kind changed");
+    };
+    assert_eq!(stdio.command, "/opt/tools/github-mcp");
+    assert_eq!(stdio.args, Some(vec!["--log=info".to_string()]));
+    assert_eq!(stdio.env.unwrap()["TOKEN"], "ghp_secret");
+
+    let http = McpServerConfig::Http(McpHttpServerConfig {
+        kind: McpServerKind::Http,
+        url: "https://${HOST:-api.example.com}/mcp".into(),
+        headers: Some(HashMap::from([(
+            "Authorization".to_string(),
+            "Bearer ${GITHUB_TOKEN}".to_string(),
+        )])),
+    });
+    let McpServerConfig::Http(http) = http.interpolate_env(lookup).unwrap() else {
+        panic!("kind changed");
+    };
+    assert_eq!(http.url, "https://api.example.com/mcp");
+    assert_eq!(http.headers.unwrap()["Authorization"], "Bearer ghp_secret");
+}
+
+#[test]
+fn unset_variables_without_default_are_config_errors() {
+    let config = McpServerConfig::Http(McpHttpServerConfig {
+        kind: McpServerKind::Http,
+        url: "https://${MCP_HOST}/mcp".into(),
+        headers: None,
+    });
+    let err = config.interpolate_env(lookup).unwrap_err();
+    assert!(
+        matches!(&err, SdkError::InvalidConfig(message) if message.contains("MCP_HOST")),
+        "{err}"
+    );
+}
@@ -0,0 +1,116 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{
+    ClaudeAgentOptions, McpHttpServerConfig, McpServerConfig, McpServerKind, McpServers,
+};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::mcp::health::{check_mcp_servers, McpPreflight};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn http(url: String) -> McpServerConfig {
+    McpServerConfig::Http(McpHttpServerConfig {
+        kind: McpServerKind::Http,
+        url,
+        headers: None,
+    })
+}
+
+/// An HTTP server answering every request with 405, and a port with nothing
+/// listening on it.
+async fn endpoints() -> (String, String) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let up = format!("http://{}/mcp", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = [0u8; 512];
+            let _ = stream.read(&mut request).await;
+            let _ = stream
+                .write_all(b"HTTP/1.1 405 Method Not Allowed\r\ncontent-length: 0\r\n\r\n")
+                .await;
+        }
+    });
+    let closed = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let down = format!("http://{}/mcp", closed.local_addr().unwrap());
+    drop(closed);
+    (up, down)
+}
+
+fn options(up: String, down: String, preflight: McpPreflight) -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        mcp_servers: McpServers::Map(HashMap::from([
+            ("docs".to_string(), http(up)),
+            ("tickets".to_string(), http(down)),
+        ])),
+        mcp_preflight: preflight,
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn unreachable_servers_are_reported() {
+    let (up, down) = endpoints().await;
+    let issues = check_mcp_servers(&options(up, down.clone(), McpPreflight::Warn)).await;
+    assert_eq!(issues.len(), 1, "{issues:?}");
+    assert_eq!(issues[0].server, "tickets");
+    assert_eq!(issues[0].url, down);
+}
+
+#[tokio::test]
+async fn preflight_policy_decides_whether_connect_fails() {
+    let (up, down) = endpoints().await;
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client = ClaudeSdkClient::new(
+        Some(options(up.clone(), down.clone(), McpPreflight::Require)),
+        Some(transport.clone() as Arc<dyn Transport>),
+    );
+    let err = client.connect(None).await.unwrap_err();
+    assert!(
+        matches!(&err, SdkError::McpServerUnreachable { server, .. } if server == "tickets"),
+        "{err}"
+    );
+    assert_eq!(transport.connect_calls().await, 0);
+
+    let mut client = ClaudeSdkClient::new(
+        Some(options(up, down, McpPreflight::Warn)),
+        Some(transport.clone() as Arc<dyn Transport>),
+    );
+    client.connect(None).await.unwrap();
+    let servers: Vec<_> = client
+        .mcp_health_issues()
+        .iter()
+        .map(|issue| issue.server.as_str())
+        .collect();
+    assert_eq!(servers, ["tickets"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn issues_do_not_expose_expanded_credentials() {
+    let (_, down) = endpoints().await;
+    let configured = format!("{down}?token=${{MCP_TOKEN}}");
+    let mut options = options(down.clone(), configured.clone(), McpPreflight::Warn);
+    options
+        .env
+        .insert("MCP_TOKEN".to_string(), "tok-1234567890".to_string());
+    options.mcp_probe = Some(Arc::new(|_: &str, server: &McpServerConfig| {
+        let url = match server {
+            McpServerConfig::Http(config) => config.url.clone(),
+            _ => String::new(),
+        };
+        async move { Err::<(), String>(format!("cannot reach {url}")) }
+    }));
+
+    let issues = check_mcp_servers(&options).await;
+    assert_eq!(issues.len(), 2, "{issues:?}");
+    let tickets = &issues[1];
+    assert_eq!(tickets.url, configured);
+    assert_eq!(tickets.reason, format!("cannot reach {configured}"));
+    assert!(!tickets.to_string().contains("tok-1234567890"));
+}
@@ -0,0 +1,142 @@
+use serde_json::json;
+
+use sdk_claude_rust::config::{ClaudeAgentOptions, SystemPrompt};
+use sdk_claude_rust::hooks::{HookContext, HookEvent, HookInput};
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::memory::{ConversationMemory, MemoryKind};
+
+fn assistant(text: &str) -> sdk_claude_rust::message::Message {
+    parse_message(&json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    }))
+    .unwrap()
+}
+
+#[test]
+fn marked_lines_and_tool_calls_become_a_grouped_prompt_section() {
+    let memory = ConversationMemory::new();
+    memory.observe(&assistant(
+        "Looking at the schema.\nDecision: store amounts in cents\n- Fact: the API is rate limited to 10 rps\nnote: tests need Postgres",
+    ));
+    memory.record_tool_use(
+        "Edit",
+        json!({"file_path": "src/money.rs"}).as_object().unwrap(),
+    );
+    memory.record_tool_use(
+        "Bash",
+        json!({"command": "cargo test"}).as_object().unwrap(),
+    );
+    memory.record_tool_use(
+        "Read",
+        json!({"file_path": "README.md"}).as_object().unwrap(),
+    );
+    // Repeats refresh an entry instead of duplicating it.
+    memory.remember(MemoryKind::Decision, "store amounts in cents");
+
+    let kinds: Vec<MemoryKind> = memory.entries().iter().map(|entry| entry.kind).collect();
+    assert_eq!(
+        kinds,
+        vec![
+            MemoryKind::Fact,
+            MemoryKind::Fact,
+            MemoryKind::Change,
+            MemoryKind::Change,
+            MemoryKind::Decision
+        ]
+    );
+    assert_eq!(
+        memory.prompt_section(1_000).unwrap(),
+        "Context remembered from earlier sessions:\n\n\
+         Decisions:\n- store amounts in cents\n\n\
+         Facts:\n- the API is rate limited to 10 rps\n- tests need Postgres\n\n\
+         Changes:\n- Modified src/money.rs\n- Ran `cargo test`"
+    );
+
+    // A tight budget keeps the newest entries.
+    assert_eq!(
+        memory.prompt_section(110).unwrap(),
+        "Context remembered from earlier sessions:\n\n\
+         Decisions:\n- store amounts in cents\n\n\
+         Changes:\n- Ran `cargo test`"
+    );
+    assert_eq!(memory.prompt_section(10), None);
+}
+
+#[test]
+fn memory_survives_a_restart_and_stays_bounded() {
+    let dir = tempfile::tempdir().expect("tempdir");
+    let path = dir.path().join("memory.json");
+    assert!(ConversationMemory::load(&path)
+        .unwrap()
+        .entries()
+        .is_empty());
+
+    let memory = ConversationMemory::new().with_max_entries(2);
+    memory.remember(MemoryKind::Fact, "one");
+    memory.remember(MemoryKind::Fact, "two");
+    memory.remember(MemoryKind::Fact, "three");
+    memory.save(&path).unwrap();
+
+    let texts = |memory: &ConversationMemory| -> Vec<String> {
+        memory
+            .entries()
+            .into_iter()
+            .map(|entry| entry.text)
+            .collect()
+    };
+    let restored = ConversationMemory::load(&path).unwrap();
+    assert_eq!(texts(&restored), vec!["two", "three"]);
+    // The limit is saved with the entries.
+    restored.remember(MemoryKind::Fact, "four");
+    assert_eq!(texts(&restored), vec!["three", "four"]);
+
+    // A file holding more entries than its limit is trimmed on load.
+    let mut saved: serde_json::Value =
+        serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+    saved["max_entries"] = json!(1);
+    std::fs::write(&path, saved.to_string()).unwrap();
+    assert_eq!(
+        texts(&ConversationMemory::load(&path).unwrap()),
+        vec!["three"]
+    );
+}
+
+#[tokio::test]
+async fn apply_to_appends_the_prompt_section_and_installs_the_hook() {
+    let memory = ConversationMemory::new();
+    memory.remember(MemoryKind::Decision, "use sqlx");
+
+    let mut options = ClaudeAgentOptions {
+        system_prompt: Some(SystemPrompt::Text("You are a reviewer.".into())),
+        ..Default::default()
+    };
+    memory.apply_to(&mut options, 1_000);
+    assert_eq!(
+        options.system_prompt,
+        Some(SystemPrompt::Text(
+            "You are a reviewer.\n\nContext remembered from earlier sessions:\n\nDecisions:\n- use sqlx"
+                .into()
+        ))
+    );
+
+    let hooks = &options.hooks.as_ref().unwrap()[&HookEvent::PostToolUse];
+    let input: HookInput = serde_json::from_value(json!({
+        "hookEventName": "PostToolUse",
+        "toolName": "Write",
+        "toolInput": {"file_path": "src/db.rs"},
+        "toolResponse": {},
+        "sessionId": "s",
+        "transcriptPath": "/tmp/t",
+        "cwd": "/tmp"
+    }))
+    .unwrap();
+    hooks[0].hooks[0]
+        .call(input, Some("tool-1".into()), HookContext::default())
+        .await;
+    assert_eq!(memory.entries().last().unwrap().text, "Modified src/db.rs");
+
+    let mut fresh = ClaudeAgentOptions::default();
+    ConversationMemory::new().apply_to(&mut fresh, 1_000);
+    assert_eq!(fresh.system_prompt, None);
+}
@@ -0,0 +1,103 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::buffer_limit::BufferLimit;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::message::{ContentBlock, Message};
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::spill::MessageSpill;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-spill"
+    })
+}
+
+fn spill_files(dir: &std::path::Path) -> Vec<std::fs::Metadata> {
+    std::fs::read_dir(dir)
+        .map(|entries| {
+            entries
+                .map(|entry| entry.unwrap().metadata().unwrap())
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+#[tokio::test]
+async fn stalled_consumers_read_spilled_messages_in_order() {
+    let dir = tempfile::tempdir().unwrap();
+    let limit = BufferLimit::new(64 * 1024);
+    let mut reads: Vec<Value> = (0..500).map(|n| says(&format!("part {n}"))).collect();
+    reads.push(result());
+    let transport = MockTransport::with_reads(reads.into_iter().map(|read| Ok(Some(read))));
+    let options = ClaudeAgentOptions {
+        buffer_limit: Some(limit.clone()),
+        message_spill: Some(MessageSpill::new(dir.path().join("spill"))),
+        ..Default::default()
+    };
+    let stream = query("Hi", Some(options), Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("query should start");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    // Everything past the message channel waits on disk.
+    let files = spill_files(&dir.path().join("spill"));
+    assert_eq!(files.len(), 1);
+    assert!(files[0].len() > 0);
+    assert!(limit.buffered() < 64 * 1024);
+
+    let messages: Vec<Message> = stream.map(Result::unwrap).collect().await;
+    assert_eq!(messages.len(), 501);
+    for (n, message) in messages[..500].iter().enumerate() {
+        let Message::Assistant(assistant) = message else {
+            panic!("expected an assistant message, got {message:?}");
+        };
+        assert!(
+            matches!(&assistant.content[0], ContentBlock::Text(block) if block.text == format!("part {n}"))
+        );
+    }
+    assert!(matches!(messages[500], Message::Result(_)));
+    assert_eq!(limit.buffered(), 0);
+    assert!(spill_files(&dir.path().join("spill")).is_empty());
+}
+
+#[tokio::test]
+async fn a_full_spill_falls_back_to_backpressure() {
+    let dir = tempfile::tempdir().unwrap();
+    let mut reads: Vec<Value> = (0..300).map(|n| says(&format!("part {n}"))).collect();
+    reads.push(result());
+    let transport = MockTransport::with_reads(reads.into_iter().map(|read| Ok(Some(read))));
+    let options = ClaudeAgentOptions {
+        message_spill: Some(MessageSpill::new(dir.path()).with_max_bytes(1_000)),
+        ..Default::default()
+    };
+    let stream = query("Hi", Some(options), Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("query should start");
+    tokio::time::sleep(Duration::from_millis(50)).await;
+
+    let files = spill_files(dir.path());
+    assert_eq!(files.len(), 1);
+    assert!(files[0].len() <= 1_000);
+
+    let messages: Vec<Message> = stream.map(Result::unwrap).collect().await;
+    assert_eq!(messages.len(), 301);
+}
@@ -0,0 +1,116 @@
+use std::sync::Arc;
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::{ClaudeSdkClient, ClientPrompt};
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::middleware::{AppendText, PromptMiddlewareHandle};
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::redact::Redactor;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn user_writes(writes: Vec<Value>) -> Vec<Value> {
+    writes
+        .into_iter()
+        .filter(|payload| payload["type"] == "user")
+        .map(|payload| payload["message"]["content"].clone())
+        .collect()
+}
+
+#[tokio::test]
+async fn client_queries_pass_through_the_chain_in_order() {
+    let upper: PromptMiddlewareHandle = Arc::new(|message: &mut Value| {
+        if let Some(Value::String(text)) = message.pointer_mut("/message/content") {
+            *text = text.to_uppercase();
+        }
+        Ok(())
+    });
+    let options = ClaudeAgentOptions {
+        prompt_middleware: vec![
+            Arc::new(Redactor::new().with_secret("alice@example.com")),
+            upper,
+            Arc::new(AppendText::new("Project: billing")),
+        ],
+        ..Default::default()
+    };
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+
+    client
+        .query("email alice@example.com", "default")
+        .await
+        .expect("text query should be written");
+    client
+        .query(
+            ClientPrompt::from_stream(stream::iter(vec![json!({
+                "type": "user",
+                "message": {"role": "user", "content": [{"type": "text", "text": "hi"}]}
+            })])),
+            "default",
+        )
+        .await
+        .expect("streamed query should be written");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    assert_eq!(
+        user_writes(transport.writes().await),
+        vec![
+            json!("EMAIL [REDACTED]\n\nProject: billing"),
+            json!([{"type": "text", "text": "hi"}, {"type": "text", "text": "Project: billing"}]),
+        ]
+    );
+}
+
+#[tokio::test]
+async fn rejected_messages_are_not_sent() {
+    let guard: PromptMiddlewareHandle = Arc::new(|message: &mut Value| {
+        let text = message["message"]["content"].as_str().unwrap_or_default();
+        if text.contains("rm -rf") {
+            return Err(SdkError::InvalidConfig("prompt blocked".into()));
+        }
+        Ok(())
+    });
+    let options = ClaudeAgentOptions {
+        prompt_middleware: vec![guard],
+        ..Default::default()
+    };
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+
+    let err = client
+        .query("please rm -rf /", "default")
+        .await
+        .expect_err("blocked prompt should fail");
+    assert!(matches!(err, SdkError::InvalidConfig(message) if message == "prompt blocked"));
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+    assert!(user_writes(transport.writes().await).is_empty());
+}
+
+#[tokio::test]
+async fn one_shot_text_prompts_must_stay_text() {
+    let options = ClaudeAgentOptions {
+        prompt_middleware: vec![Arc::new(|message: &mut Value| {
+            message["message"]["content"] = json!([{"type": "text", "text": "blocks"}]);
+            Ok(())
+        })],
+        ..Default::default()
+    };
+    let transport = MockTransport::with_reads(Vec::new());
+    let result = query("hi", Some(options), Some(transport as Arc<dyn Transport>)).await;
+    assert!(matches!(result, Err(SdkError::InvalidConfig(_))));
+}
@@ -0,0 +1,136 @@
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::{ClaudeSdkClient, FailedAttempt};
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::{Message, ModelFallbackReason};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn result(is_error: bool, text: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": is_error,
+        "num_turns": 1,
+        "session_id": "sess-fallback",
+        "result": text
+    })
+}
+
+fn fallback_reason(raw: Value) -> Option<ModelFallbackReason> {
+    match parse_message(&raw).unwrap() {
+        Message::Result(result) => result.fallback_reason(),
+        other => panic!("expected result, got {other:?}"),
+    }
+}
+
+#[test]
+fn overload_and_unknown_model_errors_are_recognised() {
+    assert_eq!(
+        fallback_reason(result(
+            true,
+            r#"API Error: 529 {"type":"error","error":{"type":"overloaded_error","message":"Overloaded"}}"#
+        )),
+        Some(ModelFallbackReason::Overloaded)
+    );
+    assert_eq!(
+        fallback_reason(result(
+            true,
+            r#"API Error: 404 {"type":"error","error":{"type":"not_found_error","message":"model: claude-nope"}}"#
+        )),
+        Some(ModelFallbackReason::ModelUnavailable)
+    );
+    assert_eq!(
+        fallback_reason(result(true, "API Error: 401 invalid x-api-key")),
+        None
+    );
+    assert_eq!(
+        fallback_reason(result(false, "The model is overloaded")),
+        None
+    );
+}
+
+#[tokio::test]
+async fn queries_move_down_the_fallback_chain() {
+    let transport = MockTransport::new();
+    transport
+        .reply_to_next_user(vec![result(true, "API Error: 529 Overloaded")])
+        .await;
+    transport
+        .reply_to_next_user(vec![result(true, "Invalid model: claude-retired")])
+        .await;
+    transport
+        .reply_to_next_user(vec![result(false, "hello")])
+        .await;
+    transport.set_keep_open(true);
+
+    let options = ClaudeAgentOptions {
+        model: Some("claude-opus".into()),
+        model_fallbacks: vec![
+            "claude-opus".into(),
+            "claude-retired".into(),
+            "claude-sonnet".into(),
+            "claude-haiku".into(),
+        ],
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+
+    let response = client
+        .query_with_fallbacks("hi", "default")
+        .await
+        .expect("query should succeed");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    assert_eq!(response.model.as_deref(), Some("claude-sonnet"));
+    assert_eq!(
+        response.failed,
+        vec![
+            FailedAttempt {
+                model: Some("claude-opus".into()),
+                reason: ModelFallbackReason::Overloaded,
+            },
+            FailedAttempt {
+                model: Some("claude-retired".into()),
+                reason: ModelFallbackReason::ModelUnavailable,
+            },
+        ]
+    );
+    assert!(matches!(
+        response.messages.last(),
+        Some(Message::Result(result)) if !result.is_error
+    ));
+
+    let traffic: Vec<Value> = transport
+        .writes()
+        .await
+        .into_iter()
+        .filter_map(|payload| match payload["type"].as_str() {
+            Some("user") => Some(json!("user")),
+            Some("control_request") if payload["request"]["subtype"] == "set_model" => {
+                Some(payload["request"]["model"].clone())
+            }
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        traffic,
+        vec![
+            json!("user"),
+            json!("claude-retired"),
+            json!("user"),
+            json!("claude-sonnet"),
+            json!("user")
+        ]
+    );
+}
@@ -0,0 +1,68 @@
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::models::{self, Model};
+
+#[test]
+fn aliases_and_claude_ids_pass_while_typos_fail() {
+    for name in [
+        "sonnet",
+        "opusplan",
+        "opus[1m]",
+        "claude-sonnet-4-5",
+        "us.anthropic.claude-opus-4-1-v1:0",
+    ] {
+        assert_eq!(Model::new(name).unwrap().as_str(), name);
+    }
+    assert!(Model::new("haiku").unwrap().is_alias());
+
+    let err = Model::new("sonet").unwrap_err();
+    assert!(matches!(err, SdkError::InvalidConfig(_)));
+    assert_eq!(
+        err.to_string(),
+        "unknown model \"sonet\"; did you mean sonnet?"
+    );
+
+    let legacy = Model::new("claude-3-opus-20240229").unwrap();
+    assert_eq!(legacy.replaced_by(), Some("claude-opus-4-1"));
+    assert!(models::lookup("claude-haiku-4-5")
+        .unwrap()
+        .replaced_by
+        .is_none());
+}
+
+#[test]
+fn unrecognised_names_pass_through() {
+    for name in [
+        "arn:aws:bedrock:us-east-1:123456789012:application-inference-profile/a1b2c3d4e5f6",
+        "gateway/large-reasoning-v2",
+        "gpt-4",
+    ] {
+        let model = name.parse::<Model>().unwrap();
+        assert_eq!(model.as_str(), name);
+        assert!(!model.is_alias());
+        assert_eq!(model.replaced_by(), None);
+    }
+
+    let options = ClaudeAgentOptions {
+        model: Some("gateway/large-reasoning-v2".into()),
+        model_fallbacks: vec![
+            "arn:aws:bedrock:us-east-1:123456789012:application-inference-profile/a1b2c3d4e5f6"
+                .into(),
+        ],
+        ..Default::default()
+    };
+    options.validate().unwrap();
+}
+
+#[test]
+fn options_validate_model_and_fallbacks() {
+    let options = ClaudeAgentOptions {
+        model: Some("opus".into()),
+        model_fallbacks: vec!["claude-sonnet-4-5".into(), "hauku".into()],
+        ..Default::default()
+    };
+    assert_eq!(
+        options.validate().unwrap_err().to_string(),
+        "unknown model \"hauku\"; did you mean haiku?"
+    );
+}
@@ -0,0 +1,94 @@
+use serde_json::{json, Value};
+
+use sdk_claude_rust::hooks::moderation::{Moderation, ModerationAction};
+use sdk_claude_rust::hooks::{HookContext, HookInput, HookJsonOutput, HookMatcher};
+
+async fn run(matcher: &HookMatcher, input: Value) -> Value {
+    let input: HookInput = serde_json::from_value(input).unwrap();
+    let HookJsonOutput::Sync(output) = matcher.hooks[0]
+        .call(input, None, HookContext::default())
+        .await
+    else {
+        panic!("expected a synchronous output");
+    };
+    serde_json::to_value(output).unwrap()
+}
+
+fn prompt(text: &str) -> Value {
+    json!({
+        "hookEventName": "UserPromptSubmit",
+        "prompt": text,
+        "sessionId": "sess-mod",
+        "transcriptPath": "/nonexistent.jsonl",
+        "cwd": "/"
+    })
+}
+
+#[tokio::test]
+async fn prompts_are_blocked_or_annotated() {
+    let moderation = Moderation::new()
+        .with_keyword("Project Falcon")
+        .with_pattern(r"\b\d{3}-\d{2}-\d{4}\b")
+        .unwrap()
+        .with_check(|text| {
+            text.contains("rm -rf /")
+                .then(|| "asks to wipe a disk".to_string())
+        });
+    assert!(moderation.check("project falcons").is_none());
+    assert!(Moderation::new().with_pattern("(").is_err());
+
+    let hook = moderation.user_prompt_submit_hook();
+    assert_eq!(run(&hook, prompt("Summarise the roadmap")).await, json!({}));
+    assert_eq!(
+        run(&hook, prompt("What is PROJECT FALCON?")).await,
+        json!({
+            "decision": "block",
+            "reason": "Prompt blocked by moderation: matched keyword \"Project Falcon\""
+        })
+    );
+
+    let hook = moderation
+        .with_prompt_action(ModerationAction::Annotate)
+        .user_prompt_submit_hook();
+    assert_eq!(
+        run(&hook, prompt("please rm -rf / now")).await,
+        json!({"hookSpecificOutput": {
+            "hookEventName": "UserPromptSubmit",
+            "additionalContext": "Moderation note: this prompt asks to wipe a disk; answer within policy."
+        }})
+    );
+}
+
+#[tokio::test]
+async fn replies_are_checked_from_the_transcript() {
+    let dir = tempfile::tempdir().unwrap();
+    let transcript = dir.path().join("sess-mod.jsonl");
+    let lines = [
+        json!({"type": "user", "message": {"role": "user", "content": "Any SSNs on file?"}}),
+        json!({"type": "assistant", "message": {"model": "claude-test", "content": [
+            {"type": "text", "text": "One: 123-45-6789."}
+        ]}}),
+    ];
+    let contents: Vec<String> = lines.iter().map(Value::to_string).collect();
+    std::fs::write(&transcript, contents.join("\n")).unwrap();
+    let stop = |active: bool| {
+        json!({
+            "hookEventName": "Stop",
+            "stopHookActive": active,
+            "sessionId": "sess-mod",
+            "transcriptPath": transcript,
+            "cwd": dir.path()
+        })
+    };
+
+    let hook = Moderation::new()
+        .with_pattern(r"\d{3}-\d{2}-\d{4}")
+        .unwrap()
+        .stop_hook();
+    let blocked = run(&hook, stop(false)).await;
+    assert_eq!(blocked["decision"], "block");
+    assert_eq!(
+        run(&hook, stop(true)).await,
+        json!({"systemMessage": "Moderation: the reply matched pattern /\\d{3}-\\d{2}-\\d{4}/."})
+    );
+}
@@ -0,0 +1,145 @@
+use std::collections::{BTreeSet, HashMap};
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::config::{
+    AgentDefinition, ClaudeAgentOptions, FlagMode, InFlightPolicy, McpServerConfig, McpServers,
+    McpStdioServerConfig, SdkPluginConfig, SdkPluginKind, SessionTurnLimit, SettingSource,
+    StdoutMode, SystemPrompt, ThinkingConfig, TurnLimitAction,
+};
+use sdk_claude_rust::env::Provider;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::mcp::health::McpPreflight;
+use sdk_claude_rust::options_document::OptionsDocument;
+use sdk_claude_rust::permission::PermissionMode;
+
+/// Options with every serializable field set away from its default.
+fn populated() -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        allowed_tools: vec!["Read".into()],
+        system_prompt: Some(SystemPrompt::Text("Be brief.".into())),
+        mcp_servers: McpServers::Map(HashMap::from([(
+            "files".to_string(),
+            McpServerConfig::Stdio(McpStdioServerConfig {
+                r#type: None,
+                command: "files-mcp".into(),
+                args: Some(vec!["--root".into(), ".".into()]),
+                env: None,
+            }),
+        )])),
+        permission_mode: Some(PermissionMode::AcceptEdits),
+        continue_conversation: true,
+        resume: Some("sess-1".into()),
+        max_turns: Some(3),
+        max_budget_usd: Some(1.5),
+        disallowed_tools: vec!["Bash".into()],
+        model: Some("sonnet".into()),
+        model_fallbacks: vec!["haiku".into()],
+        permission_prompt_tool_name: Some("mcp__approve__ask".into()),
+        cwd: Some("/work".into()),
+        cli_path: Some("/usr/bin/claude".into()),
+        settings: Some("{}".into()),
+        output_style: Some("Explanatory".into()),
+        add_dirs: vec!["/data".into()],
+        env: HashMap::from([("LANG".to_string(), "C".to_string())]),
+        provider: Some(Provider::Bedrock),
+        entrypoint: Some("my-app".into()),
+        extra_args: HashMap::from([("verbose".to_string(), None)]),
+        flag_mode: FlagMode::Strict,
+        in_flight_policy: InFlightPolicy::Queue,
+        session_turn_limit: Some(SessionTurnLimit {
+            max_turns: 10,
+            on_limit: TurnLimitAction::Compact,
+        }),
+        max_buffer_size: Some(1 << 20),
+        stdout_mode: StdoutMode::Tolerant,
+        arg_file_threshold: Some(0),
+        mcp_preflight: McpPreflight::Warn,
+        user: Some("claude".into()),
+        include_partial_messages: true,
+        replay_user_messages: true,
+        hide_user_echoes: true,
+        keep_input_open: true,
+        fork_session: true,
+        agents: Some(HashMap::from([(
+            "reviewer".to_string(),
+            AgentDefinition {
+                description: "Reviews code".into(),
+                prompt: "Review the diff.".into(),
+                tools: Some(vec!["Read".into()]),
+                model: None,
+            },
+        )])),
+        setting_sources: Some(vec![SettingSource::Project]),
+        plugins: vec![SdkPluginConfig {
+            kind: SdkPluginKind::Local,
+            path: "/plugins/lint".into(),
+        }],
+        max_thinking_tokens: Some(2_048),
+        thinking: Some(ThinkingConfig::with_budget(4_096)),
+        ..Default::default()
+    }
+}
+
+#[test]
+fn documents_mirror_every_serialized_option() {
+    let options = populated();
+    let document = options.to_document();
+    assert_eq!(
+        serde_json::to_value(&document).unwrap(),
+        serde_json::to_value(&options).unwrap()
+    );
+
+    let json = document.to_json_pretty().unwrap();
+    let restored = ClaudeAgentOptions::from_document(OptionsDocument::from_json(&json).unwrap());
+    assert_eq!(restored.to_document(), document);
+}
+
+#[test]
+fn schema_describes_exactly_the_document_fields() {
+    let mut document = populated().to_document();
+    document.schema = Some("./claude-options.schema.json".into());
+    let Value::Object(fields) = serde_json::to_value(&document).unwrap() else {
+        panic!("documents serialize as objects");
+    };
+    let schema = OptionsDocument::json_schema();
+    let properties = schema["properties"].as_object().unwrap();
+
+    assert_eq!(
+        fields.keys().collect::<BTreeSet<_>>(),
+        properties.keys().collect::<BTreeSet<_>>()
+    );
+    assert_eq!(schema["additionalProperties"], json!(false));
+    assert_eq!(
+        properties["permission_mode"]["enum"],
+        json!(["default", "acceptEdits", "plan", "bypassPermissions"])
+    );
+}
+
+#[test]
+fn unknown_fields_are_rejected() {
+    let err = OptionsDocument::from_json(r#"{"max_trns": 3}"#).unwrap_err();
+    assert!(matches!(&err, SdkError::InvalidConfig(message) if message.contains("max_trns")));
+
+    let document =
+        OptionsDocument::from_json(r#"{"$schema": "./schema.json", "max_turns": 3}"#).unwrap();
+    assert_eq!(document.max_turns, Some(3));
+}
+
+#[test]
+fn applying_a_document_keeps_runtime_handles() {
+    let mut options = ClaudeAgentOptions {
+        stderr: Some(Arc::new(|_: &str| {})),
+        model: Some("opus".into()),
+        ..Default::default()
+    };
+    options.apply_document(OptionsDocument {
+        max_turns: Some(2),
+        ..Default::default()
+    });
+
+    assert!(options.stderr.is_some());
+    assert_eq!(options.max_turns, Some(2));
+    assert_eq!(options.model, None);
+}
@@ -0,0 +1,177 @@
+use std::sync::{Arc, Mutex};
+
+use serde::Deserialize;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::config::{AgentDefinition, SystemPrompt};
+use sdk_claude_rust::orchestrator::{
+    critic_loop, default_revision_prompt, fan_out, stage_fn, Agent, Stage, StageExt, Verdict,
+};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn answer(text: &str) -> Vec<Value> {
+    vec![
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+        }),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "sess-orchestrator",
+            "result": text
+        }),
+    ]
+}
+
+/// Agent answering `text` to every prompt; the prompts it received are kept.
+fn scripted_agent(name: &str, text: &str) -> (Agent, Arc<Mutex<Vec<String>>>) {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let reads = answer(text);
+    let agent = Agent::new(name).with_transport_factory({
+        let prompts = prompts.clone();
+        move |prompt: &str| {
+            prompts.lock().unwrap().push(prompt.to_string());
+            MockTransport::with_reads(reads.clone().into_iter().map(|line| Ok(Some(line))))
+                as Arc<dyn Transport>
+        }
+    });
+    (agent, prompts)
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Plan {
+    steps: Vec<String>,
+}
+
+#[tokio::test]
+async fn sequential_stages_hand_off_typed_values() {
+    let (planner, used) = scripted_agent(
+        "planner",
+        "Here is the plan:\n```json\n{\"steps\": [\"parse\", \"emit\"]}\n```",
+    );
+    let pipeline = stage_fn(|topic: &'static str| async move { Ok(format!("Plan a {topic}")) })
+        .then(planner)
+        .parse_json::<Plan>()
+        .map(|plan: Plan| plan.steps.len());
+
+    assert_eq!(pipeline.run("compiler").await.unwrap(), 2);
+    assert_eq!(*used.lock().unwrap(), vec!["Plan a compiler"]);
+}
+
+#[tokio::test]
+async fn fan_out_collects_outputs_in_order_for_fan_in() {
+    let (optimist, _) = scripted_agent("optimist", "ship it");
+    let (pessimist, _) = scripted_agent("pessimist", "wait a week");
+    let pipeline = fan_out(vec![optimist.boxed(), pessimist.boxed()]).then(stage_fn(
+        |opinions: Vec<String>| async move { Ok(opinions.join(" / ")) },
+    ));
+
+    assert_eq!(
+        pipeline.run("Release today?".to_string()).await.unwrap(),
+        "ship it / wait a week"
+    );
+}
+
+#[tokio::test]
+async fn fan_out_fails_when_any_stage_fails() {
+    let failing = Agent::new("broken").with_transport_factory(|_: &str| {
+        MockTransport::with_reads(vec![Ok(None)]) as Arc<dyn Transport>
+    });
+    let (healthy, _) = scripted_agent("healthy", "fine");
+
+    let result = fan_out(vec![healthy.boxed(), failing.boxed()])
+        .run("status?".to_string())
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn critic_loop_revises_until_accepted() {
+    let prompts = Arc::new(Mutex::new(Vec::new()));
+    let author = stage_fn({
+        let prompts = prompts.clone();
+        move |prompt: String| {
+            let mut prompts = prompts.lock().unwrap();
+            prompts.push(prompt);
+            let draft = format!("draft {}", prompts.len());
+            async move { Ok(draft) }
+        }
+    });
+    let critic = stage_fn(|draft: String| async move {
+        Ok(if draft == "draft 3" {
+            Verdict::Accept
+        } else {
+            Verdict::Revise(format!("improve {draft}"))
+        })
+    });
+
+    let reviewed = critic_loop(author, critic, 5)
+        .with_revision_prompt(|task, draft, feedback| format!("{task} | {draft} | {feedback}"))
+        .run("write".to_string())
+        .await
+        .unwrap();
+
+    assert_eq!(reviewed.draft, "draft 3");
+    assert_eq!(reviewed.rounds, 3);
+    assert!(reviewed.accepted);
+    assert_eq!(
+        reviewed.feedback,
+        vec!["improve draft 1", "improve draft 2"]
+    );
+    assert_eq!(
+        *prompts.lock().unwrap(),
+        vec![
+            "write",
+            "write | draft 1 | improve draft 1",
+            "write | draft 2 | improve draft 2"
+        ]
+    );
+}
+
+#[tokio::test]
+async fn critic_loop_stops_at_round_limit() {
+    let (author, used) = scripted_agent("author", "meh");
+    let critic =
+        stage_fn(|_draft: String| async move { Ok(Verdict::Revise("still meh".to_string())) });
+
+    let reviewed = critic_loop(author, critic, 2)
+        .run("write".to_string())
+        .await
+        .unwrap();
+
+    assert!(!reviewed.accepted);
+    assert_eq!(reviewed.rounds, 2);
+    assert_eq!(reviewed.feedback.len(), 2);
+    assert_eq!(
+        *used.lock().unwrap(),
+        vec![
+            "write".to_string(),
+            default_revision_prompt("write", "meh", "still meh")
+        ]
+    );
+}
+
+#[test]
+fn agent_from_definition_uses_its_prompt_tools_and_model() {
+    let definition = AgentDefinition {
+        description: "Reviews code".into(),
+        prompt: "You review Rust code.".into(),
+        tools: Some(vec!["Read".into(), "Grep".into()]),
+        model: Some("sonnet".into()),
+    };
+    let agent = Agent::from_definition("reviewer", &definition);
+
+    assert_eq!(agent.name(), "reviewer");
+    assert!(matches!(
+        &agent.options().system_prompt,
+        Some(SystemPrompt::Text(prompt)) if prompt == "You review Rust code."
+    ));
+    assert_eq!(agent.options().allowed_tools, vec!["Read", "Grep"]);
+    assert_eq!(agent.options().model.as_deref(), Some("sonnet"));
+}
@@ -0,0 +1,97 @@
+#![cfg(feature = "otel")]
+
+use std::time::Duration;
+
+use opentelemetry::trace::Status;
+use opentelemetry::{global, Value};
+use opentelemetry_sdk::metrics::{InMemoryMetricExporter, PeriodicReader, SdkMeterProvider};
+use opentelemetry_sdk::trace::{InMemorySpanExporter, SdkTracerProvider};
+use serde_json::json;
+
+use sdk_claude_rust::message::ResultMessage;
+use sdk_claude_rust::metrics::SdkMetrics;
+use sdk_claude_rust::otel::OtelMetrics;
+
+#[test]
+fn otel_metrics_export_query_and_tool_spans() {
+    let span_exporter = InMemorySpanExporter::default();
+    let tracer_provider = SdkTracerProvider::builder()
+        .with_simple_exporter(span_exporter.clone())
+        .build();
+    global::set_tracer_provider(tracer_provider.clone());
+    let metric_exporter = InMemoryMetricExporter::default();
+    let meter_provider = SdkMeterProvider::builder()
+        .with_reader(PeriodicReader::builder(metric_exporter.clone()).build())
+        .build();
+    global::set_meter_provider(meter_provider.clone());
+
+    let metrics = OtelMetrics::new();
+    metrics.tool_call("calc", "add", Duration::from_millis(12), false);
+    let result: ResultMessage = serde_json::from_value(json!({
+        "subtype": "success",
+        "duration_ms": 1500,
+        "duration_api_ms": 1200,
+        "is_error": false,
+        "num_turns": 2,
+        "session_id": "session-1",
+        "total_cost_usd": 0.25,
+        "usage": {"input_tokens": 120, "output_tokens": 40}
+    }))
+    .unwrap();
+    metrics.query_completed(&result);
+    metrics.message_sent();
+
+    let spans = span_exporter.get_finished_spans().unwrap();
+    let tool = spans
+        .iter()
+        .find(|span| span.name == "execute_tool add")
+        .expect("tool span");
+    assert!(matches!(tool.status, Status::Error { .. }));
+    let query = spans
+        .iter()
+        .find(|span| span.name == "invoke_agent claude-code")
+        .expect("query span");
+    let attribute = |key: &str| {
+        query
+            .attributes
+            .iter()
+            .find(|kv| kv.key.as_str() == key)
+            .map(|kv| kv.value.clone())
+    };
+    assert_eq!(
+        attribute("gen_ai.conversation.id"),
+        Some(Value::from("session-1"))
+    );
+    assert_eq!(
+        attribute("gen_ai.usage.input_tokens"),
+        Some(Value::I64(120))
+    );
+    assert_eq!(
+        attribute("gen_ai.usage.output_tokens"),
+        Some(Value::I64(40))
+    );
+    assert_eq!(
+        query.end_time.duration_since(query.start_time).unwrap(),
+        Duration::from_millis(1500)
+    );
+
+    meter_provider.force_flush().unwrap();
+    let exported = metric_exporter.get_finished_metrics().unwrap();
+    let names: Vec<String> = exported
+        .iter()
+        .flat_map(|resource| resource.scope_metrics())
+        .flat_map(|scope| scope.metrics())
+        .map(|metric| metric.name().to_string())
+        .collect();
+    for expected in [
+        "gen_ai.client.operation.duration",
+        "gen_ai.client.token.usage",
+        "claude_sdk.cost",
+        "claude_sdk.messages",
+    ] {
+        assert!(
+            names.iter().any(|name| name == expected),
+            "{expected} missing"
+        );
+    }
+}
@@ -0,0 +1,93 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+/// Answer the `initialize` request written to `transport` with `response`.
+async fn answer_initialize(transport: &MockTransport, response: Value) {
+    loop {
+        let request_id = transport.writes().await.iter().find_map(|write| {
+            (write.pointer("/request/subtype") == Some(&json!("initialize")))
+                .then(|| write["request_id"].clone())
+        });
+        if let Some(request_id) = request_id {
+            let reply = json!({
+                "type": "control_response",
+                "response": {"subtype": "success", "request_id": request_id, "response": response}
+            });
+            transport.enqueue_read(Ok(Some(reply))).await;
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+#[test]
+fn output_style_is_merged_into_settings() {
+    let options = ClaudeAgentOptions {
+        settings: Some(r#"{"model": "sonnet"}"#.into()),
+        output_style: Some("Explanatory".into()),
+        ..Default::default()
+    };
+    let settings: Value =
+        serde_json::from_str(&options.effective_settings().unwrap().unwrap()).unwrap();
+    assert_eq!(
+        settings,
+        json!({"model": "sonnet", "outputStyle": "Explanatory"})
+    );
+
+    let plain = ClaudeAgentOptions {
+        settings: Some("/etc/claude/settings.json".into()),
+        ..Default::default()
+    };
+    assert_eq!(
+        plain.effective_settings().unwrap().as_deref(),
+        Some("/etc/claude/settings.json")
+    );
+}
+
+#[tokio::test]
+async fn output_style_is_discovered_and_switched_at_runtime() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    let initialize = json!({
+        "output_style": "default",
+        "available_output_styles": ["default", "Explanatory", "Learning"]
+    });
+    let (connected, ()) = tokio::join!(
+        client.connect(None),
+        answer_initialize(&transport, initialize)
+    );
+    connected.unwrap();
+    transport.set_withhold_control_responses(false);
+
+    assert_eq!(client.output_style().as_deref(), Some("default"));
+    assert_eq!(
+        client.available_output_styles(),
+        ["default", "Explanatory", "Learning"]
+    );
+
+    let err = client.set_output_style("Pirate").await.unwrap_err();
+    assert!(matches!(err, SdkError::InvalidConfig(message) if message.contains("Learning")));
+
+    client.set_output_style("Explanatory").await.unwrap();
+    assert_eq!(client.output_style().as_deref(), Some("Explanatory"));
+    let sent = transport.writes().await;
+    let request = sent
+        .iter()
+        .rev()
+        .find(|write| write["type"] == "control_request")
+        .unwrap();
+    assert_eq!(request["request"]["subtype"], "set_output_style");
+    assert_eq!(request["request"]["output_style"], "Explanatory");
+
+    client.disconnect().await.unwrap();
+}
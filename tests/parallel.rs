@@ -0,0 +1,90 @@
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::{ClaudeSdkClient, DynTransport};
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::{ProcessError, SdkError};
+use sdk_claude_rust::parallel::{ParallelTask, TaskOutcome};
+use sdk_claude_rust::pool::QueryPool;
+use sdk_claude_rust::testing::MockTransport;
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result(text: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-parallel",
+        "result": text
+    })
+}
+
+fn answering(text: &str) -> DynTransport {
+    MockTransport::with_reads([Ok(Some(says(text))), Ok(Some(result(text)))])
+}
+
+#[tokio::test]
+async fn outcomes_come_back_in_task_order_with_partial_failures() {
+    let crashing = MockTransport::with_reads([
+        Ok(Some(says("halfway"))),
+        Err(ProcessError::new("CLI exited", Some(1), None).into()),
+    ]);
+    let client = ClaudeSdkClient::new(None, None);
+    let outcomes = client
+        .parallel(vec![
+            ParallelTask::new("first").with_transport(answering("one")),
+            ParallelTask::new("second").with_transport(crashing),
+            ParallelTask::new("third").with_transport(answering("three")),
+        ])
+        .await;
+
+    assert_eq!(outcomes.len(), 3);
+    assert!(outcomes[0].is_completed());
+    assert_eq!(outcomes[0].text(), "one");
+    assert_eq!(outcomes[2].text(), "three");
+    match &outcomes[1] {
+        TaskOutcome::Failed { messages, error } => {
+            assert_eq!(messages.len(), 1);
+            assert!(matches!(error, SdkError::Process(_)));
+        }
+        other => panic!("expected a failure, got {other:?}"),
+    }
+    assert_eq!(outcomes[1].text(), "halfway");
+}
+
+#[tokio::test]
+async fn pool_parallel_runs_prompts_on_its_workers() {
+    let mut transports = VecDeque::new();
+    for text in ["a", "b", "c"] {
+        let transport = MockTransport::new();
+        transport.set_keep_open(true);
+        transport
+            .reply_to_next_user(vec![says(text), result(text)])
+            .await;
+        transports.push_back(transport);
+    }
+    let transports = Mutex::new(transports);
+    let pool = QueryPool::new(ClaudeAgentOptions::default(), 1).with_transport(move || {
+        transports
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no transport left") as DynTransport
+    });
+
+    let outcomes = pool.parallel(vec!["x", "y"]).await;
+    let texts: Vec<String> = outcomes.iter().map(TaskOutcome::text).collect();
+    assert_eq!(texts, ["a", "b"]);
+    pool.close().await;
+}
@@ -0,0 +1,323 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::hooks::{
+    HookContext, HookEvent, HookInput, HookJsonOutput, HookMatcher, HookSpecificOutput,
+    PreToolUseHookSpecificOutput, SyncHookJsonOutput,
+};
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::permission::{
+    PermissionBehavior, PermissionResult, PermissionRuleValue, PermissionUpdate,
+    PermissionUpdateDestination, PermissionUpdateKind, ToolPermissionContext,
+};
+use sdk_claude_rust::testing::{
+    ControlReply, HookRequest, MockTransport, PermissionRequest, Scenario,
+};
+use sdk_claude_rust::transport::Transport;
+
+const REPLY_TIMEOUT: Duration = Duration::from_secs(2);
+
+type Seen<T> = Arc<Mutex<Vec<T>>>;
+
+fn always_allow_bash() -> PermissionUpdate {
+    PermissionUpdate::new(PermissionUpdateKind::AddRules)
+        .with_rules(vec![PermissionRuleValue::new("Bash", Some("ls:*".into()))])
+        .with_behavior(PermissionBehavior::Allow)
+        .with_destination(PermissionUpdateDestination::Session)
+}
+
+/// Allows `ls` (accepting every suggestion) and denies everything else.
+fn permission_options(seen: Seen<(String, ToolPermissionContext)>) -> ClaudeAgentOptions {
+    let can_use_tool = Arc::new(
+        move |tool: &str, input: Map<String, Value>, context: ToolPermissionContext| {
+            seen.lock()
+                .unwrap()
+                .push((tool.to_string(), context.clone()));
+            let allowed = input.get("command") == Some(&json!("ls"));
+            Box::pin(async move {
+                if allowed {
+                    let mut updated = input;
+                    updated.insert("command".into(), json!("ls -la"));
+                    PermissionResult::Allow {
+                        updated_input: Some(updated),
+                        updated_permissions: Some(context.suggestions),
+                    }
+                } else {
+                    PermissionResult::Deny {
+                        message: "only ls is allowed".into(),
+                        interrupt: true,
+                    }
+                }
+            })
+        },
+    );
+    ClaudeAgentOptions {
+        can_use_tool: Some(can_use_tool),
+        ..Default::default()
+    }
+}
+
+/// Registers one PreToolUse hook (`hook_0`) that blocks `rm` commands.
+fn hook_options(seen: Seen<(HookInput, Option<String>)>) -> ClaudeAgentOptions {
+    let mut matcher = HookMatcher::new(None);
+    matcher.hooks.push(Arc::new(
+        move |input: HookInput, tool_use_id: Option<String>, _ctx: HookContext| {
+            let blocked = matches!(
+                &input,
+                HookInput::PreToolUse(pre)
+                    if pre.tool_input.get("command").and_then(Value::as_str)
+                        .is_some_and(|command| command.starts_with("rm"))
+            );
+            seen.lock().unwrap().push((input, tool_use_id));
+            async move {
+                HookJsonOutput::Sync(SyncHookJsonOutput {
+                    hook_specific_output: Some(HookSpecificOutput::PreToolUse(
+                        PreToolUseHookSpecificOutput {
+                            permission_decision: Some(
+                                if blocked { "deny" } else { "allow" }.into(),
+                            ),
+                            permission_decision_reason: blocked
+                                .then(|| "destructive command".to_string()),
+                            updated_input: None,
+                        },
+                    )),
+                    ..Default::default()
+                })
+            }
+        },
+    ));
+    ClaudeAgentOptions {
+        hooks: Some(HashMap::from([(HookEvent::PreToolUse, vec![matcher])])),
+        ..Default::default()
+    }
+}
+
+async fn connect(options: ClaudeAgentOptions) -> (Arc<MockTransport>, ClaudeSdkClient) {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    (transport, client)
+}
+
+#[test]
+fn permission_request_matches_cli_wire_shape() {
+    let request = PermissionRequest::new("Bash", json!({"command": "ls"}))
+        .with_tool_use_id("toolu_01")
+        .with_suggestion(always_allow_bash())
+        .with_blocked_path("/etc");
+
+    assert_eq!(
+        request.to_request(),
+        json!({
+            "subtype": "can_use_tool",
+            "tool_name": "Bash",
+            "input": {"command": "ls"},
+            "permission_suggestions": [{
+                "type": "addRules",
+                "rules": [{"toolName": "Bash", "ruleContent": "ls:*"}],
+                "behavior": "allow",
+                "destination": "session"
+            }],
+            "tool_use_id": "toolu_01",
+            "blocked_path": "/etc"
+        })
+    );
+    assert!(PermissionRequest::new("Read", json!({}))
+        .tool_use_id()
+        .starts_with("toolu_"));
+}
+
+#[test]
+fn hook_request_uses_snake_case_input() {
+    let request = HookRequest::pre_tool_use("hook_0", "Bash", json!({"command": "ls"}))
+        .with_tool_use_id(Some("toolu_02".into()))
+        .with_session_id("sess-1")
+        .with_permission_mode("default");
+
+    assert_eq!(request.event(), HookEvent::PreToolUse);
+    assert_eq!(
+        request.to_request(),
+        json!({
+            "subtype": "hook_callback",
+            "callback_id": "hook_0",
+            "input": {
+                "hook_event_name": "PreToolUse",
+                "session_id": "sess-1",
+                "transcript_path": "/tmp/sim-transcript.jsonl",
+                "cwd": "/tmp",
+                "permission_mode": "default",
+                "tool_name": "Bash",
+                "tool_input": {"command": "ls"}
+            },
+            "tool_use_id": "toolu_02"
+        })
+    );
+}
+
+#[tokio::test]
+async fn permission_callback_receives_suggestions_and_its_reply_is_captured() {
+    let seen = Seen::default();
+    let (transport, mut client) = connect(permission_options(seen.clone())).await;
+
+    let request_id = transport
+        .request_permission(
+            PermissionRequest::new("Bash", json!({"command": "ls"}))
+                .with_suggestion(always_allow_bash()),
+        )
+        .await;
+    let reply = transport
+        .wait_for_control_reply(&request_id, REPLY_TIMEOUT)
+        .await
+        .expect("the SDK should answer the permission request");
+
+    assert_eq!(reply.behavior(), Some("allow"));
+    assert_eq!(reply.updated_input(), Some(&json!({"command": "ls -la"})));
+    assert_eq!(
+        reply.updated_permissions(),
+        Some(&json!([always_allow_bash().to_control_payload().unwrap()]))
+    );
+    {
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1);
+        assert_eq!(seen[0].0, "Bash");
+        assert_eq!(seen[0].1.suggestions, vec![always_allow_bash()]);
+    }
+
+    let request_id = transport
+        .request_permission(PermissionRequest::new(
+            "Bash",
+            json!({"command": "rm -rf /"}),
+        ))
+        .await;
+    let reply = transport
+        .wait_for_control_reply(&request_id, REPLY_TIMEOUT)
+        .await
+        .expect("the SDK should answer the permission request");
+    assert_eq!(
+        reply,
+        ControlReply::Success(json!({
+            "behavior": "deny",
+            "message": "only ls is allowed",
+            "interrupt": true
+        }))
+    );
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn hook_receives_cli_shaped_input_and_tool_use_id() {
+    let seen = Seen::default();
+    let (transport, mut client) = connect(hook_options(seen.clone())).await;
+
+    let request = HookRequest::pre_tool_use("hook_0", "Bash", json!({"command": "rm -rf build"}))
+        .with_session_id("sess-9");
+    let tool_use_id = request.tool_use_id().map(str::to_string);
+    let request_id = transport.invoke_hook(request).await;
+    let reply = transport
+        .wait_for_control_reply(&request_id, REPLY_TIMEOUT)
+        .await
+        .expect("the SDK should answer the hook callback");
+
+    let payload = reply.payload().expect("hook should succeed");
+    assert_eq!(
+        payload["hookSpecificOutput"],
+        json!({
+            "hookEventName": "PreToolUse",
+            "permissionDecision": "deny",
+            "permissionDecisionReason": "destructive command"
+        })
+    );
+    let seen = seen.lock().unwrap().clone();
+    assert_eq!(seen.len(), 1);
+    assert_eq!(seen[0].1, tool_use_id);
+    match &seen[0].0 {
+        HookInput::PreToolUse(pre) => {
+            assert_eq!(pre.tool_name, "Bash");
+            assert_eq!(pre.tool_input.get("command"), Some(&json!("rm -rf build")));
+            assert_eq!(pre.base.session_id, "sess-9");
+        }
+        other => panic!("unexpected hook input: {other:?}"),
+    }
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn unknown_hook_callback_is_reported_as_error() {
+    let (transport, mut client) = connect(hook_options(Seen::default())).await;
+
+    let request_id = transport
+        .invoke_hook(HookRequest::stop("hook_7", false))
+        .await;
+    let reply = transport
+        .wait_for_control_reply(&request_id, REPLY_TIMEOUT)
+        .await
+        .expect("the SDK should answer the hook callback");
+
+    assert!(reply
+        .error()
+        .is_some_and(|message| message.contains("hook_7")));
+
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn scenario_accepts_permission_and_hook_requests() {
+    let scenario = Scenario::new()
+        .expect_user("list files")
+        .permission(
+            PermissionRequest::new("Bash", json!({"command": "ls"}))
+                .with_suggestion(always_allow_bash()),
+        )
+        .expect_response(json!({"behavior": "allow", "updatedInput": {"command": "ls -la"}}))
+        .hook(HookRequest::pre_tool_use(
+            "hook_0",
+            "Bash",
+            json!({"command": "ls"}),
+        ))
+        .expect_response(json!({"hookSpecificOutput": {"permissionDecision": "allow"}}))
+        .respond_result("done");
+    let transport = scenario.transport().await;
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let options = ClaudeAgentOptions {
+        can_use_tool: permission_options(Seen::default()).can_use_tool,
+        ..hook_options(Seen::default())
+    };
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("list files", "default")
+        .await
+        .expect("query should be written");
+    let messages: Vec<_> = client
+        .receive_response()
+        .expect("stream should be available")
+        .collect()
+        .await;
+    assert!(matches!(messages.last(), Some(Ok(Message::Result(_)))));
+
+    scenario.assert_satisfied(&transport).await;
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
@@ -0,0 +1,58 @@
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::mcp::McpToolContent;
+use sdk_claude_rust::permission::{
+    PermissionResult, ToolPermissionContext, PERMISSION_PROMPT_SERVER, PERMISSION_PROMPT_TOOL,
+};
+
+async fn decide(options: &ClaudeAgentOptions, arguments: Value) -> Value {
+    let Value::Object(arguments) = arguments else {
+        unreachable!()
+    };
+    let result = options.sdk_servers[PERMISSION_PROMPT_SERVER]
+        .call_tool(PERMISSION_PROMPT_TOOL, arguments)
+        .await
+        .expect("tool call should succeed");
+    match &result.content[..] {
+        [McpToolContent::Text { text }] => serde_json::from_str(text).unwrap(),
+        other => panic!("unexpected content {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn approval_tool_answers_with_the_cli_contract() {
+    let mut options = ClaudeAgentOptions::default();
+    options.set_permission_prompt_tool(
+        |tool_name: &str, _input: Map<String, Value>, _context: ToolPermissionContext| {
+            let decision = if tool_name == "Bash" {
+                PermissionResult::Deny {
+                    message: "no shell access".into(),
+                    interrupt: false,
+                }
+            } else {
+                PermissionResult::Allow {
+                    updated_input: None,
+                    updated_permissions: None,
+                }
+            };
+            async move { decision }
+        },
+    );
+    assert_eq!(
+        options.permission_prompt_tool_name.as_deref(),
+        Some("mcp__sdk_permissions__approve")
+    );
+
+    let read =
+        json!({"tool_name": "Read", "input": {"file_path": "README.md"}, "tool_use_id": "t1"});
+    assert_eq!(
+        decide(&options, read).await,
+        json!({"behavior": "allow", "updatedInput": {"file_path": "README.md"}})
+    );
+    let bash = json!({"tool_name": "Bash", "input": {"command": "ls"}});
+    assert_eq!(
+        decide(&options, bash).await,
+        json!({"behavior": "deny", "message": "no shell access", "interrupt": false})
+    );
+}
@@ -0,0 +1,88 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::client::PromptInput;
+use sdk_claude_rust::middleware::PromptMiddlewareHandle;
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn prompt(text: &str) -> Value {
+    json!({
+        "type": "user",
+        "message": {"role": "user", "content": text},
+        "parent_tool_use_id": null,
+        "session_id": "default"
+    })
+}
+
+fn rejecting_options() -> ClaudeAgentOptions {
+    let reject: PromptMiddlewareHandle = Arc::new(|message: &mut Value| {
+        if message.pointer("/message/content") == Some(&json!("drop tables")) {
+            return Err(SdkError::InvalidConfig("prompt rejected".into()));
+        }
+        Ok(())
+    });
+    ClaudeAgentOptions {
+        prompt_middleware: vec![reject],
+        ..Default::default()
+    }
+}
+
+fn assert_prompt_stream_error(err: &SdkError) {
+    match err {
+        SdkError::PromptStream { delivered, source } => {
+            assert_eq!(*delivered, 1);
+            assert!(matches!(**source, SdkError::InvalidConfig(_)));
+        }
+        other => panic!("expected a prompt stream error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn client_prompt_stream_failures_reach_the_message_stream() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client = ClaudeSdkClient::new(
+        Some(rejecting_options()),
+        Some(transport.clone() as Arc<dyn Transport>),
+    );
+    let input = stream::iter(vec![prompt("list tables"), prompt("drop tables")]);
+    client
+        .connect(Some(PromptInput::from_stream(input)))
+        .await
+        .unwrap();
+
+    let items: Vec<_> = client.receive_messages().unwrap().collect().await;
+    assert_eq!(items.len(), 1);
+    assert_prompt_stream_error(items[0].as_ref().unwrap_err());
+    let prompts: Vec<_> = transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|write| write["type"] == "user")
+        .collect();
+    assert_eq!(prompts, [prompt("list tables")]);
+}
+
+#[tokio::test]
+async fn one_shot_prompt_stream_failures_reach_the_message_stream() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let input = stream::iter(vec![prompt("list tables"), prompt("drop tables")]);
+    let messages = query(
+        PromptInput::from_stream(input),
+        Some(rejecting_options()),
+        Some(transport as Arc<dyn Transport>),
+    )
+    .await
+    .unwrap();
+    let items: Vec<_> = messages.collect().await;
+    assert_eq!(items.len(), 1);
+    assert_prompt_stream_error(items[0].as_ref().unwrap_err());
+}
@@ -0,0 +1,99 @@
+#![cfg(unix)]
+
+use std::collections::HashMap;
+use std::os::unix::fs::PermissionsExt;
+
+use serde_json::json;
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::env::{EnvError, Provider};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+use sdk_claude_rust::transport::Transport;
+
+/// CLI that prints the provider variables it was started with.
+fn env_echo_cli(dir: &std::path::Path) -> std::path::PathBuf {
+    let path = dir.join("claude");
+    std::fs::write(
+        &path,
+        "#!/bin/sh\n\
+         if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+         printf '{\"bedrock\":\"%s\",\"vertex\":\"%s\",\"region\":\"%s\"}\\n' \
+         \"$CLAUDE_CODE_USE_BEDROCK\" \"$CLAUDE_CODE_USE_VERTEX\" \"$AWS_REGION\"\n",
+    )
+    .expect("script should be written");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("script should be executable");
+    path
+}
+
+fn options(dir: &std::path::Path, provider: Provider, env: &[(&str, &str)]) -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        cli_path: Some(env_echo_cli(dir)),
+        provider: Some(provider),
+        env: env
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn provider_switch_is_passed_to_the_cli() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let options = options(
+        dir.path(),
+        Provider::Bedrock,
+        &[("AWS_REGION", "eu-west-1"), ("CLAUDE_CODE_USE_VERTEX", "1")],
+    );
+    let transport = SubprocessCliTransport::new(PromptMode::Text("hi".into()), options)
+        .expect("transport should build");
+    transport.connect().await.expect("spawn should succeed");
+    let printed = transport.read().await.expect("read should succeed");
+    transport.close().await.expect("close should succeed");
+
+    assert_eq!(
+        printed,
+        Some(json!({"bedrock": "1", "vertex": "", "region": "eu-west-1"}))
+    );
+}
+
+#[tokio::test]
+async fn missing_provider_variables_fail_before_spawning() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let options = options(
+        dir.path(),
+        Provider::Vertex,
+        &[("CLOUD_ML_REGION", "us-east5")],
+    );
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options)
+        .expect("transport should build");
+
+    let err = transport.connect().await.expect_err("connect should fail");
+    let SdkError::InvalidConfig(message) = err else {
+        panic!("expected a configuration error, got {err:?}");
+    };
+    assert_eq!(
+        message,
+        "provider vertex requires ANTHROPIC_VERTEX_PROJECT_ID to be set"
+    );
+}
+
+#[test]
+fn validate_reports_every_missing_variable() {
+    let env = HashMap::from([("AWS_REGION".to_string(), "us-west-2".to_string())]);
+    assert!(Provider::Bedrock.validate(&env).is_ok());
+    assert!(Provider::Anthropic.validate(&HashMap::new()).is_ok());
+
+    let blank = HashMap::from([("CLOUD_ML_REGION".to_string(), " ".to_string())]);
+    let Err(EnvError::MissingVars { provider, missing }) = Provider::Vertex.validate(&blank) else {
+        panic!("vertex variables should be missing");
+    };
+    assert_eq!(provider, Provider::Vertex);
+    assert_eq!(missing, ["CLOUD_ML_REGION", "ANTHROPIC_VERTEX_PROJECT_ID"]);
+
+    let options: ClaudeAgentOptions =
+        serde_json::from_value(json!({"provider": "bedrock"})).unwrap();
+    assert_eq!(options.provider, Some(Provider::Bedrock));
+}
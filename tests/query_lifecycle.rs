@@ -0,0 +1,316 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::{Duration, UNIX_EPOCH};
+
+use serde_json::json;
+
+use sdk_claude_rust::clock::VirtualClock;
+use sdk_claude_rust::config::ControlWatchdogConfig;
+use sdk_claude_rust::error::{SdkError, TimeoutOperation};
+use sdk_claude_rust::frame_log::{FrameDirection, FrameOutcome, InMemoryFrameLog};
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+fn assistant_message(text: &str) -> serde_json::Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "model": "claude-opus-test",
+            "content": [
+                {"type": "text", "text": text}
+            ]
+        }
+    })
+}
+
+fn result_message() -> serde_json::Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 12,
+        "duration_api_ms": 10,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-lifecycle"
+    })
+}
+
+#[tokio::test]
+async fn close_graceful_delivers_buffered_messages() {
+    let transport = MockTransport::with_reads(vec![
+        Ok(Some(assistant_message("first"))),
+        Ok(Some(assistant_message("second"))),
+        Ok(Some(result_message())),
+    ]);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query.start().await.expect("query should start");
+    query
+        .close_graceful(Duration::from_secs(1))
+        .await
+        .expect("graceful close should succeed");
+
+    assert!(query.is_closed());
+    assert!(query.is_input_closed());
+    assert_eq!(transport.end_input_calls().await, 1);
+    assert_eq!(transport.close_calls().await, 1);
+
+    let mut delivered = Vec::new();
+    while let Some(message) = query.next_message().await.expect("message should parse") {
+        delivered.push(message);
+    }
+    assert_eq!(delivered.len(), 3);
+    assert!(matches!(delivered[2], Message::Result(_)));
+}
+
+#[tokio::test]
+async fn frame_sink_records_control_exchanges() {
+    let transport = MockTransport::with_reads(vec![Ok(Some(json!({
+        "type": "control_request",
+        "request_id": "cli-1",
+        "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {}}
+    })))]);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+    let frames = Arc::new(InMemoryFrameLog::default());
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query.set_frame_sink(Some(frames.clone())).await;
+    query.interrupt().await.expect("interrupt should succeed");
+
+    for _ in 0..100 {
+        if frames.records().len() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    query.close().await.expect("close should succeed");
+
+    let records = frames.records();
+    let outbound = records
+        .iter()
+        .find(|record| record.direction == FrameDirection::Outbound)
+        .expect("outbound frame should be recorded");
+    assert_eq!(outbound.subtype, "interrupt");
+    assert!(outbound.request_id.starts_with("req_"));
+    assert_eq!(outbound.outcome, FrameOutcome::Success);
+
+    let inbound = records
+        .iter()
+        .find(|record| record.direction == FrameDirection::Inbound)
+        .expect("inbound frame should be recorded");
+    assert_eq!(inbound.request_id, "cli-1");
+    assert_eq!(inbound.subtype, "can_use_tool");
+    assert!(matches!(inbound.outcome, FrameOutcome::Error(_)));
+}
+
+#[tokio::test]
+async fn control_request_ids_are_unique_uuid_v7() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query
+        .interrupt()
+        .await
+        .expect("first interrupt should succeed");
+    query
+        .interrupt()
+        .await
+        .expect("second interrupt should succeed");
+    query.close().await.expect("close should succeed");
+
+    let ids: Vec<String> = transport
+        .writes()
+        .await
+        .iter()
+        .filter_map(|payload| payload.get("request_id")?.as_str().map(str::to_string))
+        .collect();
+    assert_eq!(ids.len(), 2);
+    assert_ne!(ids[0], ids[1]);
+    for id in &ids {
+        let raw = id
+            .strip_prefix("req_")
+            .expect("id should keep the req_ prefix");
+        let uuid = uuid::Uuid::parse_str(raw).expect("id should embed a uuid");
+        assert_eq!(uuid.get_version_num(), 7);
+    }
+}
+
+#[tokio::test]
+async fn control_watchdog_fails_stalled_requests() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query
+        .set_control_watchdog(ControlWatchdogConfig {
+            interval: Duration::from_millis(10),
+            warn_after: Duration::from_millis(10),
+            fail_after: Some(Duration::from_millis(50)),
+        })
+        .await;
+    query.start().await.expect("query should start");
+
+    let pending = {
+        let query = query.clone();
+        tokio::spawn(async move { query.interrupt().await })
+    };
+    for _ in 0..100 {
+        if query.pending_control_count().await == 1 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    assert_eq!(query.pending_control_count().await, 1);
+
+    let err = tokio::time::timeout(Duration::from_secs(5), pending)
+        .await
+        .expect("watchdog should fail the request")
+        .expect("task should not panic")
+        .expect_err("stalled interrupt should fail");
+    match &err {
+        SdkError::Timeout {
+            operation: TimeoutOperation::ControlRequest { subtype },
+            elapsed,
+        } => {
+            assert_eq!(subtype, "interrupt");
+            assert!(*elapsed >= Duration::from_millis(50));
+        }
+        other => panic!("expected control request timeout, got {other:?}"),
+    }
+    assert!(err.to_string().contains("interrupt"));
+    assert_eq!(query.pending_control_count().await, 0);
+
+    query.close().await.expect("close should succeed");
+}
+
+#[tokio::test(start_paused = true)]
+async fn control_request_timeout_elapses_on_paused_clock() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let epoch = UNIX_EPOCH + Duration::from_secs(1_700_000_000);
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query.set_clock(Arc::new(VirtualClock::new(epoch))).await;
+    query.start().await.expect("query should start");
+
+    let err = query
+        .interrupt()
+        .await
+        .expect_err("unanswered interrupt should time out");
+    match &err {
+        SdkError::Timeout {
+            operation: TimeoutOperation::ControlRequest { subtype },
+            elapsed,
+        } => {
+            assert_eq!(subtype, "interrupt");
+            assert_eq!(*elapsed, Duration::from_secs(60));
+        }
+        other => panic!("expected control request timeout, got {other:?}"),
+    }
+
+    let frames = query.recent_frames();
+    assert_eq!(frames.len(), 1);
+    assert_eq!(frames[0].outcome, FrameOutcome::Timeout);
+    assert_eq!(frames[0].started_at, epoch);
+    assert_eq!(frames[0].latency, Duration::from_secs(60));
+
+    query.close().await.expect("close should succeed");
+}
+
+#[tokio::test(start_paused = true)]
+async fn control_watchdog_runs_on_paused_clock() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query
+        .set_control_watchdog(ControlWatchdogConfig {
+            interval: Duration::from_secs(1),
+            warn_after: Duration::from_secs(5),
+            fail_after: Some(Duration::from_secs(30)),
+        })
+        .await;
+    query.start().await.expect("query should start");
+
+    let started = tokio::time::Instant::now();
+    let err = query
+        .interrupt()
+        .await
+        .expect_err("watchdog should fail the request");
+    let SdkError::Timeout { elapsed, .. } = err else {
+        panic!("expected control request timeout, got {err:?}");
+    };
+    assert!(elapsed >= Duration::from_secs(30) && elapsed <= Duration::from_secs(31));
+    assert!(started.elapsed() < Duration::from_secs(60));
+
+    query.close().await.expect("close should succeed");
+}
+
+#[tokio::test]
+async fn control_error_responses_keep_structured_fields() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query.start().await.expect("query should start");
+
+    let pending = {
+        let query = query.clone();
+        tokio::spawn(async move { query.set_model(Some("nope".into())).await })
+    };
+    let mut request_id = None;
+    for _ in 0..100 {
+        request_id = transport
+            .writes()
+            .await
+            .iter()
+            .find_map(|payload| payload.get("request_id")?.as_str().map(str::to_string));
+        if request_id.is_some() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "error",
+                "request_id": request_id.expect("request should be written"),
+                "error": "Unknown model: nope",
+                "code": "unknown_model",
+                "data": {"model": "nope"}
+            }
+        }))))
+        .await;
+
+    let err = pending
+        .await
+        .expect("task should not panic")
+        .expect_err("set_model should fail");
+    match err {
+        SdkError::Control(error) => {
+            assert_eq!(error.code(), Some("unknown_model"));
+            assert_eq!(error.message(), "Unknown model: nope");
+            assert_eq!(error.data(), Some(&json!({"model": "nope"})));
+        }
+        other => panic!("expected control error, got {other:?}"),
+    }
+
+    query.close().await.expect("close should succeed");
+}
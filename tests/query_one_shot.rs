@@ -5,8 +5,10 @@ use std::sync::Arc;
 use futures::StreamExt;
 use serde_json::json;
 
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
 use sdk_claude_rust::message::{ContentBlock, Message};
-use sdk_claude_rust::query::query;
+use sdk_claude_rust::query::{ask_with, query};
 
 use common::MockTransport;
 
@@ -74,7 +76,51 @@ async fn query_streams_single_prompt() {
 }
 
 #[tokio::test]
-async fn query_sets_entrypoint_env() {
+async fn query_surfaces_limit_results_as_typed_errors() {
+    let transport = MockTransport::with_reads(vec![
+        Ok(Some(assistant_message("partial"))),
+        Ok(Some(json!({
+            "type": "result",
+            "subtype": "error_max_budget_usd",
+            "duration_ms": 10,
+            "duration_api_ms": 8,
+            "is_error": true,
+            "num_turns": 2,
+            "session_id": "sess-123",
+            "total_cost_usd": 0.12
+        }))),
+        Ok(None),
+    ]);
+    let options = ClaudeAgentOptions {
+        max_budget_usd: Some(0.1),
+        ..Default::default()
+    };
+
+    let transport_arc: Arc<dyn sdk_claude_rust::transport::Transport> = transport.clone();
+    let stream = query("Spend it all", Some(options), Some(transport_arc))
+        .await
+        .expect("query should start");
+
+    let messages = stream.collect::<Vec<_>>().await;
+    assert_eq!(messages.len(), 2);
+    assert!(matches!(messages[0], Ok(Message::Assistant(_))));
+    match &messages[1] {
+        Err(SdkError::BudgetExceeded {
+            spent,
+            limit,
+            result,
+        }) => {
+            assert_eq!(*spent, 0.12);
+            assert_eq!(*limit, Some(0.1));
+            assert_eq!(result.subtype, "error_max_budget_usd");
+            assert_eq!(result.total_cost_usd, Some(0.12));
+        }
+        other => panic!("expected budget error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn query_leaves_process_env_untouched() {
     std::env::remove_var("CLAUDE_CODE_ENTRYPOINT");
 
     let transport = MockTransport::with_reads(vec![Ok(None)]);
@@ -86,8 +132,8 @@ async fn query_sets_entrypoint_env() {
 
     let _messages = stream.collect::<Vec<_>>().await;
 
-    let entrypoint = std::env::var("CLAUDE_CODE_ENTRYPOINT").expect("env var should be set");
-    assert_eq!(entrypoint, "sdk-rs");
+    // The entrypoint is set only in the CLI's own environment.
+    assert!(std::env::var("CLAUDE_CODE_ENTRYPOINT").is_err());
 }
 
 #[tokio::test]
@@ -124,3 +170,49 @@ async fn query_propagates_streaming_payloads() {
         "no additional user payloads should be written"
     );
 }
+
+#[tokio::test]
+async fn ask_with_returns_result_text_or_last_assistant_text() {
+    let mut with_result = result_message();
+    with_result["result"] = json!("Paris.");
+    let transport: Arc<dyn sdk_claude_rust::transport::Transport> =
+        MockTransport::with_reads(vec![
+            Ok(Some(assistant_message("Thinking about it"))),
+            Ok(Some(with_result)),
+        ]);
+    let answer = ask_with("Capital of France?", None, Some(transport))
+        .await
+        .expect("ask should succeed");
+    assert_eq!(answer, "Paris.");
+
+    let transport: Arc<dyn sdk_claude_rust::transport::Transport> =
+        MockTransport::with_reads(vec![
+            Ok(Some(assistant_message("first"))),
+            Ok(Some(assistant_message("Lyon is second."))),
+            Ok(Some(result_message())),
+        ]);
+    let answer = ask_with("Second city?", None, Some(transport))
+        .await
+        .expect("ask should succeed");
+    assert_eq!(answer, "Lyon is second.");
+}
+
+#[tokio::test]
+async fn ask_with_reports_error_results_and_missing_results() {
+    let mut failed = result_message();
+    failed["subtype"] = json!("error_during_execution");
+    failed["is_error"] = json!(true);
+    let transport: Arc<dyn sdk_claude_rust::transport::Transport> =
+        MockTransport::with_reads(vec![Ok(Some(failed))]);
+    let err = ask_with("anything", None, Some(transport))
+        .await
+        .expect_err("error result should fail");
+    assert!(err.to_string().contains("error_during_execution"));
+
+    let transport: Arc<dyn sdk_claude_rust::transport::Transport> =
+        MockTransport::with_reads(vec![Ok(Some(assistant_message("cut off"))), Ok(None)]);
+    let err = ask_with("anything", None, Some(transport))
+        .await
+        .expect_err("missing result should fail");
+    assert!(matches!(err, SdkError::Protocol(_)));
+}
@@ -0,0 +1,152 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::DynTransport;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::pool::QueryPool;
+use sdk_claude_rust::testing::MockTransport;
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-pool"
+    })
+}
+
+/// `count` transports, each answering three prompts with their index.
+async fn workers(count: usize) -> Vec<Arc<MockTransport>> {
+    let mut transports = Vec::new();
+    for index in 0..count {
+        let transport = MockTransport::new();
+        transport.set_keep_open(true);
+        for _ in 0..3 {
+            transport
+                .reply_to_next_user(vec![says(&format!("worker {index}")), result()])
+                .await;
+        }
+        transports.push(transport);
+    }
+    transports
+}
+
+/// Pool over `transports`, handed out in order.
+fn pool(transports: &[Arc<MockTransport>], size: usize) -> QueryPool {
+    let remaining = Mutex::new(transports.iter().cloned().collect::<VecDeque<_>>());
+    QueryPool::new(ClaudeAgentOptions::default(), size).with_transport(move || {
+        remaining
+            .lock()
+            .unwrap()
+            .pop_front()
+            .expect("no transport left") as DynTransport
+    })
+}
+
+fn reply(messages: &[Message]) -> String {
+    messages
+        .iter()
+        .find_map(|message| match message {
+            Message::Assistant(assistant) => Some(format!("{:?}", assistant.content)),
+            _ => None,
+        })
+        .expect("assistant reply")
+}
+
+async fn connected(transports: &[Arc<MockTransport>]) -> usize {
+    let mut count = 0;
+    for transport in transports {
+        count += transport.connect_calls().await;
+    }
+    count
+}
+
+#[tokio::test]
+async fn workers_are_reused_until_their_query_budget_runs_out() {
+    let transports = workers(3).await;
+    let pool = pool(&transports, 1).with_max_queries_per_worker(2);
+    pool.warm().await.unwrap();
+    assert_eq!(pool.idle_count().await, 1);
+
+    let first = pool.query("one").await.unwrap();
+    let second = pool.query("two").await.unwrap();
+    assert!(reply(&first).contains("worker 0"));
+    assert!(reply(&second).contains("worker 0"));
+    assert!(matches!(second.last(), Some(Message::Result(_))));
+
+    let third = pool.query("three").await.unwrap();
+    assert!(reply(&third).contains("worker 1"));
+    assert_eq!(transports[0].close_calls().await, 1);
+    pool.close().await;
+}
+
+#[tokio::test]
+async fn unhealthy_idle_workers_are_replaced() {
+    let transports = workers(3).await;
+    let pool = pool(&transports, 2).with_max_queries_per_worker(5);
+    pool.warm().await.unwrap();
+    assert_eq!(connected(&transports).await, 2);
+
+    transports[1].set_ready(false);
+    let messages = pool.query("hi").await.unwrap();
+    assert!(reply(&messages).contains("worker 0"));
+    assert_eq!(pool.idle_count().await, 1);
+
+    pool.close().await;
+    assert_eq!(pool.idle_count().await, 0);
+    assert!(pool.query("late").await.is_err());
+}
+
+#[tokio::test]
+async fn workers_finishing_after_close_are_disconnected() {
+    let transports = workers(2).await;
+    transports[1].set_withhold_control_responses(true);
+    let pool = pool(&transports, 1);
+    pool.query("one").await.unwrap();
+
+    // The replacement for worker 0 is stuck initializing when the pool closes.
+    let initialize = loop {
+        let writes = transports[1].writes().await;
+        if let Some(request) = writes
+            .iter()
+            .find(|write| write.pointer("/request/subtype") == Some(&json!("initialize")))
+        {
+            break request.clone();
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    };
+    pool.close().await;
+    transports[1]
+        .enqueue_read(Ok(Some(json!({
+            "type": "control_response",
+            "response": {
+                "subtype": "success",
+                "request_id": initialize["request_id"],
+                "response": {}
+            }
+        }))))
+        .await;
+
+    for _ in 0..100 {
+        if transports[1].close_calls().await > 0 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(transports[1].close_calls().await, 1);
+    assert_eq!(pool.idle_count().await, 0);
+}
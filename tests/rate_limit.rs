@@ -0,0 +1,190 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime};
+
+use futures::future::BoxFuture;
+use futures::StreamExt;
+use serde_json::json;
+use tokio::time::Instant;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::clock::{Clock, TokioClock};
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::rate_limit::{RateLimiter, RateLimits};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test(start_paused = true)]
+async fn queries_per_minute_use_a_sliding_window() {
+    let limiter = RateLimiter::new(RateLimits::new().with_queries_per_minute(2)).unwrap();
+    let start = Instant::now();
+
+    limiter.acquire_query(&TokioClock).await;
+    tokio::time::sleep(Duration::from_secs(10)).await;
+    limiter.acquire_query(&TokioClock).await;
+    limiter.acquire_query(&TokioClock).await;
+    assert_eq!(start.elapsed(), Duration::from_secs(60));
+    limiter.acquire_query(&TokioClock).await;
+    assert_eq!(start.elapsed(), Duration::from_secs(70));
+}
+
+/// Clock whose sleeps return at once after moving its time forward.
+struct SteppedClock {
+    origin: Instant,
+    elapsed: Mutex<Duration>,
+}
+
+impl Clock for SteppedClock {
+    fn now(&self) -> Instant {
+        self.origin + *self.elapsed.lock().unwrap()
+    }
+
+    fn wall_time(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + *self.elapsed.lock().unwrap()
+    }
+
+    fn sleep(&self, duration: Duration) -> BoxFuture<'static, ()> {
+        *self.elapsed.lock().unwrap() += duration;
+        Box::pin(async {})
+    }
+}
+
+#[tokio::test]
+async fn windows_follow_the_given_clock() {
+    let clock = SteppedClock {
+        origin: Instant::now(),
+        elapsed: Mutex::new(Duration::ZERO),
+    };
+    let limiter = RateLimiter::new(
+        RateLimits::new()
+            .with_queries_per_minute(1)
+            .with_tokens_per_hour(1_000),
+    )
+    .unwrap();
+
+    limiter.acquire_query(&clock).await;
+    limiter.record_tokens(1_000, &clock);
+    limiter.acquire_query(&clock).await;
+    assert_eq!(*clock.elapsed.lock().unwrap(), Duration::from_secs(3600));
+    assert_eq!(limiter.tokens_last_hour(&clock), 0);
+}
+
+#[test]
+fn zero_limits_are_rejected() {
+    for limits in [
+        RateLimits::new().with_queries_per_minute(0),
+        RateLimits::new().with_max_concurrent_sessions(0),
+        RateLimits::new().with_tokens_per_hour(0),
+    ] {
+        let err = RateLimiter::new(limits).unwrap_err();
+        assert!(matches!(err, SdkError::InvalidConfig(_)), "{err}");
+    }
+}
+
+#[tokio::test(start_paused = true)]
+async fn waiting_queries_are_served_in_arrival_order() {
+    let limiter = RateLimiter::new(RateLimits::new().with_queries_per_minute(1)).unwrap();
+    limiter.acquire_query(&TokioClock).await;
+
+    let order = Arc::new(Mutex::new(Vec::new()));
+    let mut tasks = Vec::new();
+    for caller in 0..3 {
+        let (limiter, order) = (limiter.clone(), order.clone());
+        tasks.push(tokio::spawn(async move {
+            limiter.acquire_query(&TokioClock).await;
+            order.lock().unwrap().push(caller);
+        }));
+        // Let the task queue up before spawning the next one.
+        tokio::task::yield_now().await;
+    }
+    for task in tasks {
+        task.await.unwrap();
+    }
+    assert_eq!(*order.lock().unwrap(), vec![0, 1, 2]);
+}
+
+#[tokio::test(start_paused = true)]
+async fn token_budget_delays_queries_until_usage_expires() {
+    let limiter = RateLimiter::new(RateLimits::new().with_tokens_per_hour(1_000)).unwrap();
+    let start = Instant::now();
+
+    limiter.acquire_query(&TokioClock).await;
+    limiter.record_tokens(600, &TokioClock);
+    tokio::time::sleep(Duration::from_secs(600)).await;
+    limiter.record_tokens(500, &TokioClock);
+    assert_eq!(limiter.tokens_last_hour(&TokioClock), 1_100);
+
+    // Under the limit again once the first charge is an hour old.
+    limiter.acquire_query(&TokioClock).await;
+    assert_eq!(start.elapsed(), Duration::from_secs(3600));
+    assert_eq!(limiter.tokens_last_hour(&TokioClock), 500);
+}
+
+#[tokio::test]
+async fn one_shot_queries_charge_result_usage_and_release_their_session() {
+    let limiter = RateLimiter::new(RateLimits::new().with_max_concurrent_sessions(1)).unwrap();
+    let transport = MockTransport::with_reads(vec![Ok(Some(json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-rate",
+        "usage": {
+            "input_tokens": 100,
+            "output_tokens": 20,
+            "cache_creation_input_tokens": 5,
+            "cache_read_input_tokens": 1_000
+        }
+    })))]);
+    let options = ClaudeAgentOptions {
+        rate_limiter: Some(limiter.clone()),
+        ..Default::default()
+    };
+
+    let messages: Vec<_> = query("hi", Some(options), Some(transport as Arc<dyn Transport>))
+        .await
+        .expect("query should start")
+        .collect()
+        .await;
+    assert_eq!(messages.len(), 1);
+    assert_eq!(limiter.tokens_last_hour(&TokioClock), 125);
+    assert_eq!(limiter.active_sessions(), 0);
+}
+
+#[tokio::test]
+async fn clients_wait_for_a_free_session_slot() {
+    let limiter = RateLimiter::new(RateLimits::new().with_max_concurrent_sessions(1)).unwrap();
+    let client = |limiter: &RateLimiter| {
+        let transport = MockTransport::new();
+        transport.set_keep_open(true);
+        let options = ClaudeAgentOptions {
+            rate_limiter: Some(limiter.clone()),
+            ..Default::default()
+        };
+        ClaudeSdkClient::new(Some(options), Some(transport as Arc<dyn Transport>))
+    };
+
+    let mut first = client(&limiter);
+    first.connect(None).await.expect("first connect");
+    assert_eq!(limiter.active_sessions(), 1);
+
+    let mut second = client(&limiter);
+    let waiting = tokio::spawn(async move {
+        second.connect(None).await.expect("second connect");
+        second
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert!(!waiting.is_finished());
+
+    first.disconnect().await.expect("disconnect");
+    let mut second = tokio::time::timeout(Duration::from_secs(5), waiting)
+        .await
+        .expect("second client should connect once the slot is free")
+        .unwrap();
+    assert_eq!(limiter.active_sessions(), 1);
+    second.disconnect().await.expect("disconnect");
+    assert_eq!(limiter.active_sessions(), 0);
+}
@@ -0,0 +1,56 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::json;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test]
+async fn raw_tap_keeps_unmodelled_fields_and_unknown_messages() {
+    let assistant = json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": "hi"}]},
+        "future_field": {"nested": true}
+    });
+    let result = json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-raw"
+    });
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![
+            assistant.clone(),
+            json!({"type": "mystery", "payload": 7}),
+            result.clone(),
+        ])
+        .await;
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    client.query("Hi", "default").await.unwrap();
+
+    let raw: Vec<_> = client
+        .receive_raw()
+        .unwrap()
+        .take(3)
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    assert_eq!(raw[0].value, assistant);
+    assert!(matches!(raw[0].message, Some(Message::Assistant(_))));
+    assert_eq!(raw[1].value["payload"], 7);
+    assert!(raw[1].message.is_none());
+    assert_eq!(raw[2].value, result);
+    assert!(matches!(raw[2].message, Some(Message::Result(_))));
+
+    client.disconnect().await.unwrap();
+}
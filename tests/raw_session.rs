@@ -0,0 +1,78 @@
+use std::sync::Arc;
+
+use futures::TryStreamExt;
+use serde_json::json;
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::raw::RawSession;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test]
+async fn raw_session_sends_frames_and_custom_control_requests() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let session = RawSession::open(
+        transport.clone() as Arc<dyn Transport>,
+        &ClaudeAgentOptions::default(),
+    )
+    .await
+    .unwrap();
+    assert_eq!(transport.connect_calls().await, 1);
+
+    session
+        .control_request(json!({"subtype": "get_context_usage", "verbose": true}))
+        .await
+        .unwrap();
+    let frame = json!({
+        "type": "user",
+        "message": {"role": "user", "content": "hi"},
+        "parent_tool_use_id": null,
+        "session_id": "raw",
+        "priority": "high"
+    });
+    session.send(&frame).await.unwrap();
+
+    let writes = transport.writes().await;
+    let subtypes: Vec<_> = writes
+        .iter()
+        .filter_map(|write| write.pointer("/request/subtype"))
+        .collect();
+    assert_eq!(
+        subtypes,
+        [&json!("initialize"), &json!("get_context_usage")]
+    );
+    assert_eq!(writes.last(), Some(&frame));
+
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "control_request",
+            "request_id": "cli-1",
+            "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {}}
+        }))))
+        .await;
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 1,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "raw"
+        }))))
+        .await;
+    transport.enqueue_read(Ok(None)).await;
+    let messages: Vec<Message> = session.messages().try_collect().await.unwrap();
+    assert_eq!(messages.len(), 1);
+    assert!(matches!(&messages[0], Message::Result(result) if result.session_id == "raw"));
+    assert!(transport
+        .writes()
+        .await
+        .iter()
+        .any(|write| write.pointer("/response/request_id") == Some(&json!("cli-1"))));
+
+    session.close().await.unwrap();
+    assert!(session.is_closed());
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::json;
+
+use sdk_claude_rust::config::{ClaudeAgentOptions, SystemPrompt};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::frame_log::{FrameDirection, InMemoryFrameLog};
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::redact::{Redactor, MASK};
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+#[test]
+fn redactor_masks_secrets_and_patterns() {
+    let redactor = Redactor::new()
+        .with_secret("hunter22")
+        .with_pattern(r"ticket-\d+")
+        .expect("pattern should compile");
+
+    assert_eq!(
+        redactor.redact("password hunter22 for ticket-481"),
+        format!("password {MASK} for {MASK}")
+    );
+    assert_eq!(redactor.redact("nothing to hide"), "nothing to hide");
+
+    let mut value = json!({"args": ["--token", "hunter22"], "hunter22": 1, "count": 3});
+    redactor.redact_value(&mut value);
+    assert_eq!(
+        value,
+        json!({"args": ["--token", MASK], MASK: 1, "count": 3})
+    );
+
+    assert!(!format!("{redactor:?}").contains("hunter22"));
+    assert!(matches!(
+        Redactor::new().with_pattern("("),
+        Err(SdkError::InvalidConfig(_))
+    ));
+}
+
+#[test]
+fn options_debug_hides_configured_secrets() {
+    let options = ClaudeAgentOptions {
+        env: [(
+            "ANTHROPIC_API_KEY".to_string(),
+            "configured-api-key".to_string(),
+        )]
+        .into(),
+        system_prompt: Some(SystemPrompt::Text(
+            "use sk-ant-api03-abcdefghijkl and internal-42".into(),
+        )),
+        redactor: Some(Arc::new(
+            Redactor::new()
+                .with_pattern(r"internal-\d+")
+                .expect("pattern should compile"),
+        )),
+        ..Default::default()
+    };
+
+    for rendered in [format!("{options:?}"), format!("{options:#?}")] {
+        assert!(rendered.contains("ANTHROPIC_API_KEY"));
+        assert!(!rendered.contains("configured-api-key"));
+        assert!(!rendered.contains("sk-ant-api03"));
+        assert!(!rendered.contains("internal-42"));
+        assert!(rendered.contains(MASK));
+    }
+}
+
+#[tokio::test]
+async fn frame_records_are_redacted() {
+    let transport = MockTransport::with_reads(vec![Ok(Some(json!({
+        "type": "control_request",
+        "request_id": "cli-1",
+        "request": {
+            "subtype": "can_use_tool",
+            "tool_name": "Bash",
+            "input": {"command": "curl -H 'x-api-key: sk-ant-api03-abcdefghijkl'"}
+        }
+    })))]);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+    let frames = Arc::new(InMemoryFrameLog::default());
+
+    let query = Query::new(transport_arc, true, None, None, HashMap::new());
+    query.set_frame_sink(Some(frames.clone())).await;
+    query
+        .set_redactor(Some(ClaudeAgentOptions::default().effective_redactor()))
+        .await;
+    query.start().await.expect("start should succeed");
+    for _ in 0..100 {
+        if !frames.records().is_empty() {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    query.close().await.expect("close should succeed");
+
+    let records = frames.records();
+    let inbound = records
+        .iter()
+        .find(|record| record.direction == FrameDirection::Inbound)
+        .expect("inbound frame should be recorded");
+    let request = inbound.request.to_string();
+    assert!(!request.contains("sk-ant-api03"));
+    assert!(request.contains(MASK));
+    assert!(!query
+        .recent_frames()
+        .iter()
+        .any(|record| record.request.to_string().contains("sk-ant-api03")));
+}
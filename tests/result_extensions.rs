@@ -0,0 +1,65 @@
+use serde_json::json;
+
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+
+fn result(extra: serde_json::Value) -> serde_json::Value {
+    let mut raw = json!({"type": "result", "subtype": "success", "duration_ms": 1,
+                         "duration_api_ms": 1, "is_error": false, "num_turns": 2,
+                         "session_id": "s", "total_cost_usd": 0.02});
+    raw.as_object_mut()
+        .unwrap()
+        .extend(extra.as_object().unwrap().clone());
+    raw
+}
+
+#[test]
+fn denials_and_per_model_usage_are_typed() {
+    let raw = result(json!({
+        "permission_denials": [
+            {"tool_name": "Bash", "tool_use_id": "t1", "tool_input": {"command": "rm -rf /"}}
+        ],
+        "modelUsage": {
+            "claude-sonnet-4-5": {"inputTokens": 10, "outputTokens": 20,
+                                  "cacheReadInputTokens": 5, "cacheCreationInputTokens": 0,
+                                  "webSearchRequests": 0, "costUSD": 0.015,
+                                  "contextWindow": 200000},
+            "claude-haiku-4-5": {"inputTokens": 3, "outputTokens": 4, "costUSD": 0.005}
+        },
+        "uuid": "r-1"
+    }));
+    let Message::Result(result) = parse_message(&raw).unwrap() else {
+        panic!("expected a result");
+    };
+    assert_eq!(result.permission_denials.len(), 1);
+    assert_eq!(result.permission_denials[0].tool_name, "Bash");
+    assert_eq!(
+        result.permission_denials[0].tool_input["command"],
+        "rm -rf /"
+    );
+    let sonnet = &result.model_usage["claude-sonnet-4-5"];
+    assert_eq!((sonnet.input_tokens, sonnet.output_tokens), (10, 20));
+    assert_eq!(sonnet.context_window, Some(200_000));
+    assert_eq!(result.model_usage["claude-haiku-4-5"].cost_usd, 0.005);
+    assert_eq!(
+        result.extra,
+        json!({"uuid": "r-1"}).as_object().cloned().unwrap()
+    );
+
+    let round_trip = serde_json::to_value(&result).unwrap();
+    assert_eq!(
+        round_trip["modelUsage"]["claude-haiku-4-5"]["costUSD"],
+        0.005
+    );
+    assert_eq!(round_trip["uuid"], "r-1");
+}
+
+#[test]
+fn malformed_extensions_are_kept_raw() {
+    let raw = result(json!({"permission_denials": "none"}));
+    let Message::Result(result) = parse_message(&raw).unwrap() else {
+        panic!("expected a result");
+    };
+    assert!(result.permission_denials.is_empty());
+    assert_eq!(result.extra["permission_denials"], "none");
+}
@@ -0,0 +1,203 @@
+//! Property tests feeding arbitrary CLI payloads through the parsing and
+//! conversion layers; any panic here is a bug.
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use proptest::prelude::*;
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::config::serialize_permission_updates;
+use sdk_claude_rust::error::ControlError;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::permission::PermissionUpdate;
+use sdk_claude_rust::testing::strategies::{
+    adversarial_control_response, adversarial_message, cli_line, json_value, message,
+};
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+/// Objects shaped like CLI messages: a known `type` plus random known/unknown fields.
+fn arb_message_like() -> impl Strategy<Value = Value> {
+    let kind = prop_oneof![
+        Just("user"),
+        Just("assistant"),
+        Just("system"),
+        Just("result"),
+        Just("stream_event"),
+    ];
+    let key = prop_oneof![
+        Just("message".to_string()),
+        Just("content".to_string()),
+        Just("subtype".to_string()),
+        Just("session_id".to_string()),
+        Just("num_turns".to_string()),
+        Just("is_error".to_string()),
+        Just("uuid".to_string()),
+        Just("event".to_string()),
+        "[a-z_]{1,8}",
+    ];
+    (kind, prop::collection::vec((key, json_value()), 0..10)).prop_map(|(kind, fields)| {
+        let mut object: Map<String, Value> = fields.into_iter().collect();
+        object.insert("type".into(), Value::String(kind.into()));
+        Value::Object(object)
+    })
+}
+
+proptest! {
+    #[test]
+    fn parse_message_never_panics(raw in json_value()) {
+        let _ = parse_message(&raw);
+    }
+
+    #[test]
+    fn parse_message_handles_partial_messages(raw in arb_message_like()) {
+        if let Ok(Message::Result(result)) = parse_message(&raw) {
+            let _ = result.limit_error(Some(1), Some(0.5));
+        }
+    }
+
+    #[test]
+    fn control_error_accepts_any_response(error in json_value(), code in json_value(), data in json_value()) {
+        let response = json!({"subtype": "error", "error": error, "code": code, "data": data});
+        let err = ControlError::from_response(response.as_object().unwrap());
+        prop_assert!(!err.message().is_empty());
+    }
+
+    #[test]
+    fn permission_updates_round_trip(raw in json_value()) {
+        if let Ok(update) = serde_json::from_value::<PermissionUpdate>(raw) {
+            let payloads = serialize_permission_updates(std::slice::from_ref(&update))
+                .expect("deserialized updates should serialize");
+            let restored: PermissionUpdate = serde_json::from_value(payloads[0].clone())
+                .expect("payload should deserialize");
+            prop_assert_eq!(restored, update);
+        }
+    }
+}
+
+const PLACEHOLDER_ID: &str = "req_placeholder";
+
+fn paused_runtime() -> tokio::runtime::Runtime {
+    tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .start_paused(true)
+        .build()
+        .expect("runtime should build")
+}
+
+/// Swap every occurrence of the placeholder request id for `request_id`.
+fn substitute_request_id(value: &mut Value, request_id: &str) {
+    match value {
+        Value::String(text) if text == PLACEHOLDER_ID => *text = request_id.to_string(),
+        Value::Array(items) => items
+            .iter_mut()
+            .for_each(|item| substitute_request_id(item, request_id)),
+        Value::Object(map) => map
+            .values_mut()
+            .for_each(|item| substitute_request_id(item, request_id)),
+        _ => {}
+    }
+}
+
+proptest! {
+    #[test]
+    fn well_formed_messages_parse(raw in message()) {
+        let parsed = parse_message(&raw);
+        prop_assert!(parsed.is_ok(), "rejected {raw}: {:?}", parsed.err());
+        let kind = match parsed.unwrap() {
+            Message::User(_) => "user",
+            Message::Assistant(_) => "assistant",
+            Message::System(_) => "system",
+            Message::Result(_) => "result",
+            Message::StreamEvent(_) => "stream_event",
+        };
+        prop_assert_eq!(Some(kind), raw["type"].as_str());
+    }
+
+    #[test]
+    fn adversarial_messages_never_panic(raw in adversarial_message()) {
+        let _ = parse_message(&raw);
+    }
+}
+
+proptest! {
+    #![proptest_config(ProptestConfig::with_cases(64))]
+
+    #[test]
+    fn router_survives_arbitrary_cli_output(lines in prop::collection::vec(cli_line(), 0..12)) {
+        paused_runtime().block_on(async move {
+            let transport = MockTransport::with_reads(lines.into_iter().map(|line| Ok(Some(line))));
+            let transport_arc: Arc<dyn Transport> = transport.clone();
+            let query = Query::new(transport_arc, true, None, None, HashMap::new());
+            query.start().await.expect("query should start");
+
+            let drained = tokio::time::timeout(Duration::from_secs(120), async {
+                while !matches!(query.next_message().await, Ok(None)) {}
+            })
+            .await;
+            assert!(drained.is_ok(), "message stream did not end");
+            query.close().await.expect("close should succeed");
+        });
+    }
+
+    #[test]
+    fn malformed_control_responses_never_strand_requests(
+        frame in adversarial_control_response(PLACEHOLDER_ID.to_string())
+    ) {
+        paused_runtime().block_on(async move {
+            let transport = MockTransport::new();
+            transport.set_keep_open(true);
+            transport.set_withhold_control_responses(true);
+            let transport_arc: Arc<dyn Transport> = transport.clone();
+            let query = Query::new(transport_arc, true, None, None, HashMap::new());
+            query.start().await.expect("query should start");
+
+            let started = tokio::time::Instant::now();
+            let pending = {
+                let query = query.clone();
+                tokio::spawn(async move { query.interrupt().await })
+            };
+            while query.pending_control_count().await == 0 {
+                tokio::task::yield_now().await;
+            }
+            let request_id = transport.writes().await[0]["request_id"]
+                .as_str()
+                .expect("request id")
+                .to_string();
+
+            let mut frame = frame;
+            substitute_request_id(&mut frame, &request_id);
+            transport.enqueue_read(Ok(Some(frame))).await;
+            transport.enqueue_read(Ok(None)).await;
+
+            let _ = pending.await.expect("interrupt task should not panic");
+            assert!(
+                started.elapsed() < Duration::from_secs(60),
+                "request waited for its timeout instead of failing when output ended"
+            );
+            query.close().await.expect("close should succeed");
+        });
+    }
+}
+
+#[test]
+fn permission_update_payload_uses_cli_field_names() {
+    let update = serde_json::from_value::<PermissionUpdate>(json!({
+        "type": "setMode",
+        "mode": "acceptEdits",
+        "destination": "session",
+    }))
+    .expect("update should deserialize");
+    let payload = update
+        .to_control_payload()
+        .expect("update should serialize");
+    assert_eq!(payload["type"], json!("setMode"));
+    assert_eq!(payload["mode"], json!("acceptEdits"));
+}
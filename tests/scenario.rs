@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::json;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::hooks::{
+    HookContext, HookEvent, HookInput, HookJsonOutput, HookMatcher, SyncHookJsonOutput,
+};
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::testing::Scenario;
+use sdk_claude_rust::transport::Transport;
+
+fn options() -> ClaudeAgentOptions {
+    let can_use_tool = Arc::new(
+        |tool: &str,
+         _input: serde_json::Map<String, serde_json::Value>,
+         _ctx: ToolPermissionContext| {
+            let allowed = tool == "Read";
+            Box::pin(async move {
+                if allowed {
+                    PermissionResult::Allow {
+                        updated_input: None,
+                        updated_permissions: None,
+                    }
+                } else {
+                    PermissionResult::Deny {
+                        message: "read-only session".into(),
+                        interrupt: false,
+                    }
+                }
+            })
+        },
+    );
+    let mut matcher = HookMatcher::new(None);
+    matcher.hooks.push(Arc::new(
+        |_input: HookInput, _tool_use_id: Option<String>, _ctx: HookContext| async {
+            HookJsonOutput::Sync(SyncHookJsonOutput {
+                system_message: Some("checked".into()),
+                ..Default::default()
+            })
+        },
+    ));
+    ClaudeAgentOptions {
+        can_use_tool: Some(can_use_tool),
+        hooks: Some(HashMap::from([(HookEvent::PreToolUse, vec![matcher])])),
+        ..Default::default()
+    }
+}
+
+#[tokio::test]
+async fn scenario_drives_permission_and_hook_round_trips() {
+    let scenario = Scenario::new()
+        .expect_user("read the config")
+        .respond_tool_use("tool-1", "Read", json!({"file_path": "config.toml"}))
+        .request_permission("Read", json!({"file_path": "config.toml"}))
+        .expect_allow()
+        .invoke_hook(
+            "hook_0",
+            json!({
+                "hookEventName": "PreToolUse",
+                "toolName": "Read",
+                "toolInput": {"file_path": "config.toml"},
+                "sessionId": "scenario-session",
+                "transcriptPath": "/tmp/transcript.jsonl",
+                "cwd": "/tmp"
+            }),
+        )
+        .expect_response(json!({"systemMessage": "checked"}))
+        .respond_tool_result("tool-1", json!("[server]"))
+        .respond_assistant("The config defines a server section.")
+        .respond_result("The config defines a server section.")
+        .expect_user("now delete it")
+        .request_permission("Bash", json!({"command": "rm config.toml"}))
+        .expect_deny()
+        .respond_result("I cannot delete files here.");
+    let transport = scenario.transport().await;
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(Some(options()), Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    for prompt in ["read the config", "now delete it"] {
+        client
+            .query(prompt, "scenario-session")
+            .await
+            .expect("query should be written");
+        let messages: Vec<_> = client
+            .receive_response()
+            .expect("stream should be available")
+            .collect()
+            .await;
+        assert!(matches!(messages.last(), Some(Ok(Message::Result(_)))));
+    }
+
+    scenario.assert_satisfied(&transport).await;
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
+
+#[tokio::test]
+async fn scenario_reports_unexpected_prompts() {
+    let scenario = Scenario::new().expect_user("hello").respond_result("hi");
+    let transport = scenario.transport().await;
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    client
+        .query("goodbye", "scenario-session")
+        .await
+        .expect("query should be written");
+
+    let err = scenario
+        .verify(&transport)
+        .await
+        .expect_err("prompt mismatch should be reported");
+    assert!(err.contains("goodbye"));
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+}
@@ -0,0 +1,74 @@
+#![cfg(feature = "tower")]
+
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+use tower_service::Service;
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::service::{QueryRequest, QueryResponse, QueryService};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn result(subtype: &str, is_error: bool, text: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": subtype,
+        "duration_ms": 1,
+        "duration_api_ms": 0,
+        "is_error": is_error,
+        "num_turns": 3,
+        "session_id": "sess-tower",
+        "result": text
+    })
+}
+
+fn service(reads: Vec<Value>) -> QueryService {
+    QueryService::new(ClaudeAgentOptions::default()).with_transport(move || {
+        MockTransport::with_reads(reads.clone().into_iter().map(|read| Ok(Some(read))))
+            as Arc<dyn Transport>
+    })
+}
+
+async fn call(service: &mut QueryService, prompt: &str) -> Result<QueryResponse, SdkError> {
+    std::future::poll_fn(|cx| service.poll_ready(cx)).await?;
+    service.call(QueryRequest::new(prompt)).await
+}
+
+#[tokio::test]
+async fn each_call_collects_a_full_response() {
+    let mut service = service(vec![
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [{"type": "text", "text": "Paris"}]}
+        }),
+        result("success", false, ""),
+    ]);
+
+    for _ in 0..2 {
+        let response = call(&mut service, "Capital of France?").await.unwrap();
+        assert_eq!(response.messages.len(), 2);
+        assert_eq!(response.text(), "Paris");
+        assert_eq!(response.session_id(), Some("sess-tower"));
+        assert!(!response.is_error());
+    }
+}
+
+#[tokio::test]
+async fn limit_results_fail_but_api_errors_are_responses() {
+    let mut limited = service(vec![result("error_max_turns", true, "")]);
+    let err = call(&mut limited, "Loop")
+        .await
+        .expect_err("limit should fail");
+    assert!(matches!(err, SdkError::MaxTurns { turns: 3, .. }));
+
+    let mut overloaded = service(vec![result(
+        "error_during_execution",
+        true,
+        "API Error: 529 overloaded",
+    )]);
+    let response = call(&mut overloaded, "Hi").await.unwrap();
+    assert!(response.is_error());
+    assert_eq!(response.text(), "API Error: 529 overloaded");
+}
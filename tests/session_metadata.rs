@@ -0,0 +1,65 @@
+use std::sync::Arc;
+
+use serde_json::json;
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::conversation::{Conversation, SessionMetadata};
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+#[tokio::test]
+async fn status_reports_labels_for_the_current_session() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user([json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "sess-42",
+            "model": "claude-test"
+        })])
+        .await;
+    let mut client = ClaudeSdkClient::new(None, Some(transport as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+    client.set_session_metadata("sess-42", Some("Nightly triage".into()), ["ci", "batch"]);
+    client.set_session_metadata("sess-7", None, Vec::<String>::new());
+
+    client.query("Go", "default").await.unwrap();
+    while client.status().await.session_id.is_none() {
+        tokio::task::yield_now().await;
+    }
+    let status = client.status().await;
+    client.disconnect().await.unwrap();
+
+    assert_eq!(
+        status.metadata,
+        Some(SessionMetadata::new(
+            Some("Nightly triage".into()),
+            vec!["ci".into(), "batch".into()]
+        ))
+    );
+    assert_eq!(
+        client.session_metadata("sess-7"),
+        Some(&SessionMetadata::default())
+    );
+}
+
+#[test]
+fn exports_use_the_title_and_list_tags() {
+    let says = parse_message(&json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": "Done."}]}
+    }))
+    .unwrap();
+    let conversation = Conversation::from_messages(vec![says]).with_metadata(SessionMetadata::new(
+        Some("Release <notes>".into()),
+        vec!["docs".into()],
+    ));
+
+    let markdown = conversation.export_markdown();
+    assert!(markdown.starts_with("# Release <notes>\n\nTags: `docs`\n"));
+    let html = conversation.export_html();
+    assert!(html.contains("<h1>Release &lt;notes&gt;</h1>"));
+    assert!(html.contains("<span class=\"tag\">docs</span>"));
+}
@@ -0,0 +1,160 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{ClaudeAgentOptions, SessionTurnLimit, TurnLimitAction};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::message::{ContentBlock, Message};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn reply(text: &str) -> Vec<Value> {
+    vec![
+        json!({"type": "assistant", "message": {"model": "claude-test",
+               "content": [{"type": "text", "text": text}]}}),
+        json!({"type": "result", "subtype": "success", "duration_ms": 1, "duration_api_ms": 1,
+               "is_error": false, "num_turns": 1, "session_id": "s"}),
+    ]
+}
+
+fn compaction() -> Vec<Value> {
+    let mut replies =
+        vec![json!({"type": "system", "subtype": "compact_boundary", "session_id": "s"})];
+    replies.extend(reply("compacted"));
+    replies
+}
+
+fn prompts(writes: Vec<Value>) -> Vec<Value> {
+    writes
+        .into_iter()
+        .filter(|payload| payload["type"] == "user")
+        .map(|payload| payload["message"]["content"].clone())
+        .collect()
+}
+
+async fn client(transport: &Arc<MockTransport>, on_limit: TurnLimitAction) -> ClaudeSdkClient {
+    let options = ClaudeAgentOptions {
+        session_turn_limit: Some(SessionTurnLimit {
+            max_turns: 2,
+            on_limit,
+        }),
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    client
+}
+
+async fn ask(client: &ClaudeSdkClient, prompt: &str, session_id: &str) -> Vec<String> {
+    client.query(prompt, session_id).await.unwrap();
+    let messages: Vec<_> = client.receive_response().unwrap().collect().await;
+    messages
+        .into_iter()
+        .filter_map(|message| match message.unwrap() {
+            Message::Assistant(assistant) => Some(assistant.content),
+            _ => None,
+        })
+        .flatten()
+        .filter_map(|block| match block {
+            ContentBlock::Text(text) => Some(text.text),
+            _ => None,
+        })
+        .collect()
+}
+
+#[tokio::test]
+async fn prompts_past_the_limit_are_refused_per_session() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    for text in ["one", "two", "other"] {
+        transport.reply_to_next_user(reply(text)).await;
+    }
+    let mut client = client(&transport, TurnLimitAction::Refuse).await;
+    ask(&client, "first", "user-a").await;
+    ask(&client, "second", "user-a").await;
+
+    let err = client.query("third", "user-a").await.unwrap_err();
+    assert!(matches!(
+        err,
+        SdkError::MaxTurns {
+            turns: 2,
+            limit: Some(2),
+            result: None
+        }
+    ));
+    assert_eq!(ask(&client, "hello", "user-b").await, ["other"]);
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn compact_action_compacts_before_the_next_prompt() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.reply_to_next_user(reply("one")).await;
+    transport.reply_to_next_user(reply("two")).await;
+    transport.reply_to_next_user(compaction()).await;
+    transport.reply_to_next_user(reply("three")).await;
+    let mut client = client(&transport, TurnLimitAction::Compact).await;
+    ask(&client, "first", "s").await;
+    ask(&client, "second", "s").await;
+    assert_eq!(ask(&client, "third", "s").await, ["three"]);
+
+    assert_eq!(
+        prompts(transport.writes().await),
+        ["first", "second", "/compact", "third"]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn compaction_waits_for_the_previous_response_to_be_read() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.reply_to_next_user(reply("one")).await;
+    transport.reply_to_next_user(reply("two")).await;
+    transport.reply_to_next_user(compaction()).await;
+    transport.reply_to_next_user(reply("three")).await;
+    let client = Arc::new(client(&transport, TurnLimitAction::Compact).await);
+    ask(&client, "first", "s").await;
+    client.query("second", "s").await.unwrap();
+
+    let third = tokio::spawn({
+        let client = Arc::clone(&client);
+        async move { client.query("third", "s").await }
+    });
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(prompts(transport.writes().await), ["first", "second"]);
+
+    let second: Vec<_> = client.receive_response().unwrap().collect().await;
+    assert!(matches!(&second[0], Ok(Message::Assistant(_))));
+    third.await.unwrap().unwrap();
+    let third: Vec<_> = client.receive_response().unwrap().collect().await;
+    let Ok(Message::Assistant(assistant)) = &third[0] else {
+        panic!("expected the third answer, got {third:?}");
+    };
+    assert!(matches!(&assistant.content[0], ContentBlock::Text(text) if text.text == "three"));
+}
+
+#[tokio::test]
+async fn compaction_without_a_boundary_keeps_the_turn_count() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    for text in ["one", "two", "not compacted"] {
+        transport.reply_to_next_user(reply(text)).await;
+    }
+    let mut client = client(&transport, TurnLimitAction::Compact).await;
+    ask(&client, "first", "s").await;
+    ask(&client, "second", "s").await;
+
+    let err = client.query("third", "s").await.unwrap_err();
+    assert!(matches!(err, SdkError::Protocol(_)));
+    assert_eq!(
+        prompts(transport.writes().await),
+        ["first", "second", "/compact"]
+    );
+    client.disconnect().await.unwrap();
+}
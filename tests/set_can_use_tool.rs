@@ -0,0 +1,94 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::permission::{CanUseToolHandle, PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::testing::{MockTransport, PermissionRequest};
+use sdk_claude_rust::transport::Transport;
+
+fn fixed(result: PermissionResult) -> CanUseToolHandle {
+    Arc::new(
+        move |_: &str, _: Map<String, Value>, _: ToolPermissionContext| {
+            let result = result.clone();
+            async move { result }
+        },
+    )
+}
+
+fn ask_first() -> CanUseToolHandle {
+    fixed(PermissionResult::Deny {
+        message: "needs approval".into(),
+        interrupt: false,
+    })
+}
+
+async fn behavior(transport: &MockTransport) -> String {
+    let request_id = transport
+        .request_permission(PermissionRequest::new("Bash", json!({"command": "ls"})))
+        .await;
+    let reply = transport
+        .wait_for_control_reply(&request_id, Duration::from_secs(2))
+        .await
+        .expect("the SDK should answer");
+    reply.behavior().unwrap_or("error").to_string()
+}
+
+#[tokio::test]
+async fn permission_callback_is_swapped_mid_session() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let options = ClaudeAgentOptions {
+        can_use_tool: Some(ask_first()),
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    assert_eq!(behavior(&transport).await, "deny");
+
+    let auto_approve = fixed(PermissionResult::Allow {
+        updated_input: None,
+        updated_permissions: None,
+    });
+    client.set_can_use_tool(Some(auto_approve)).await.unwrap();
+    assert_eq!(behavior(&transport).await, "allow");
+
+    client.set_can_use_tool(Some(ask_first())).await.unwrap();
+    assert_eq!(behavior(&transport).await, "deny");
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn sessions_without_a_callback_cannot_gain_one() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    let err = client
+        .set_can_use_tool(Some(ask_first()))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SdkError::InvalidConfig(_)));
+    client.disconnect().await.unwrap();
+
+    // Naming the stdio prompt tool by hand does not install a callback.
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let options = ClaudeAgentOptions {
+        permission_prompt_tool_name: Some("stdio".into()),
+        ..Default::default()
+    };
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    let err = client
+        .set_can_use_tool(Some(ask_first()))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, SdkError::InvalidConfig(_)));
+    client.disconnect().await.unwrap();
+}
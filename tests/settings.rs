@@ -0,0 +1,112 @@
+use serde_json::{json, Value};
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::hooks::HookEvent;
+use sdk_claude_rust::settings::{HookCommand, Settings};
+
+#[test]
+fn merge_unions_rules_and_hooks_and_overrides_scalars() {
+    let mut base = Settings::from_json(
+        r#"{
+            "permissions": {"allow": ["Read"], "defaultMode": "default"},
+            "hooks": {"PreToolUse": [{"matcher": "Bash", "hooks": [{"type": "command", "command": "lint.sh"}]}]},
+            "env": {"RUST_LOG": "info", "CI": "1"},
+            "model": "claude-sonnet",
+            "statusLine": {"type": "command", "command": "status.sh", "padding": 1}
+        }"#,
+    )
+    .unwrap();
+    let mut overlay = Settings::new()
+        .allow("Read")
+        .allow("Bash(cargo test:*)")
+        .deny("WebFetch")
+        .with_env("RUST_LOG", "debug")
+        .with_hook(
+            HookEvent::PreToolUse,
+            Some("Bash"),
+            HookCommand::new("lint.sh"),
+        )
+        .with_hook(
+            HookEvent::PreToolUse,
+            Some("Bash"),
+            HookCommand::new("audit.sh").with_timeout(5),
+        )
+        .with_hook(HookEvent::Stop, None, HookCommand::new("notify.sh"));
+    overlay
+        .other
+        .insert("statusLine".into(), json!({"padding": 0}));
+    base.merge(overlay);
+
+    assert_eq!(base.permissions.allow, ["Read", "Bash(cargo test:*)"]);
+    assert_eq!(base.permissions.deny, ["WebFetch"]);
+    assert_eq!(base.permissions.default_mode.as_deref(), Some("default"));
+    assert_eq!(base.env["RUST_LOG"], "debug");
+    assert_eq!(base.env["CI"], "1");
+
+    let json: Value = serde_json::from_str(&base.to_json().unwrap()).unwrap();
+    assert_eq!(
+        json["hooks"]["PreToolUse"],
+        json!([{"matcher": "Bash", "hooks": [
+            {"type": "command", "command": "lint.sh"},
+            {"type": "command", "command": "audit.sh", "timeout": 5}
+        ]}])
+    );
+    assert_eq!(
+        json["hooks"]["Stop"],
+        json!([{"hooks": [{"type": "command", "command": "notify.sh"}]}])
+    );
+    assert_eq!(json["model"], "claude-sonnet");
+    assert_eq!(
+        json["statusLine"],
+        json!({"type": "command", "command": "status.sh", "padding": 0})
+    );
+}
+
+#[test]
+fn save_and_load_keep_unknown_keys() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = Settings::project_path(dir.path());
+    assert_eq!(
+        Settings::load_or_default(&path).unwrap(),
+        Settings::default()
+    );
+
+    let mut settings = Settings::from_json(
+        r#"{"permissions": {"allow": ["Edit"], "disableBypassPermissionsMode": "disable"},
+            "enableAllProjectMcpServers": true}"#,
+    )
+    .unwrap();
+    settings.merge(Settings::new().ask("Bash(git push:*)"));
+    settings.save(&path).unwrap();
+
+    assert!(path.ends_with(".claude/settings.json"));
+    let loaded = Settings::load(&path).unwrap();
+    assert_eq!(loaded, settings);
+    let json: Value = serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+    assert_eq!(
+        json,
+        json!({
+            "permissions": {
+                "allow": ["Edit"],
+                "ask": ["Bash(git push:*)"],
+                "disableBypassPermissionsMode": "disable"
+            },
+            "enableAllProjectMcpServers": true
+        })
+    );
+    assert!(matches!(
+        Settings::load(dir.path().join("missing.json")),
+        Err(sdk_claude_rust::error::SdkError::Io(_))
+    ));
+}
+
+#[test]
+fn apply_to_passes_inline_settings() {
+    let mut options = ClaudeAgentOptions::default();
+    Settings::new()
+        .deny("Bash(rm:*)")
+        .apply_to(&mut options)
+        .unwrap();
+    let inline: Value = serde_json::from_str(options.settings.as_deref().unwrap()).unwrap();
+    assert_eq!(inline, json!({"permissions": {"deny": ["Bash(rm:*)"]}}));
+}
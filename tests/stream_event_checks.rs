@@ -0,0 +1,107 @@
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
+
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::partial::{PartialAssembler, StreamDiagnostic};
+use sdk_claude_rust::stream_ext::{CheckedMessage, MessageStreamExt};
+
+fn event(uuid: &str, event: Value) -> Value {
+    json!({
+        "type": "stream_event",
+        "uuid": uuid,
+        "session_id": "sess-partial",
+        "event": event
+    })
+}
+
+fn start(uuid: &str, index: u64) -> Value {
+    event(
+        uuid,
+        json!({"type": "content_block_start", "index": index, "content_block": {"type": "text", "text": ""}}),
+    )
+}
+
+fn text(uuid: &str, index: u64, text: &str) -> Value {
+    event(
+        uuid,
+        json!({"type": "content_block_delta", "index": index, "delta": {"type": "text_delta", "text": text}}),
+    )
+}
+
+#[test]
+fn replayed_events_are_assembled_once() {
+    let raw = [
+        event("e0", json!({"type": "message_start", "message": {}})),
+        start("e1", 0),
+        text("e2", 0, "Hello"),
+        text("e3", 0, ", world"),
+        // A reconnecting consumer replays the last two events.
+        text("e2", 0, "Hello"),
+        text("e3", 0, ", world"),
+        event("e4", json!({"type": "content_block_stop", "index": 0})),
+    ];
+    let mut assembler = PartialAssembler::new();
+    let diagnostics: Vec<_> = raw
+        .iter()
+        .filter_map(|raw| assembler.observe(&parse_message(raw).unwrap()))
+        .collect();
+
+    assert_eq!(
+        diagnostics,
+        vec![
+            StreamDiagnostic::Duplicate {
+                uuid: "e2".into(),
+                parent_tool_use_id: None
+            },
+            StreamDiagnostic::Duplicate {
+                uuid: "e3".into(),
+                parent_tool_use_id: None
+            },
+        ]
+    );
+    assert_eq!(assembler.text(None), "Hello, world");
+    assert!(assembler.blocks(None).unwrap()[&0].complete);
+}
+
+#[tokio::test]
+async fn gaps_are_reported_before_the_event_revealing_them() {
+    let raw = [
+        event("e0", json!({"type": "message_start", "message": {}})),
+        start("e1", 0),
+        text("e2", 0, "First."),
+        // The stop of block 0 and the start of block 1 were dropped.
+        text("e5", 1, " Second."),
+        start("e6", 3),
+        text("e6", 3, "dup"),
+    ];
+    let items: Vec<String> = stream::iter(raw.iter().map(parse_message))
+        .check_stream_events()
+        .map(|item| match item.unwrap() {
+            CheckedMessage::Message(_) => "message".to_string(),
+            CheckedMessage::Diagnostic(StreamDiagnostic::Gap {
+                uuid,
+                expected,
+                index,
+                ..
+            }) => format!("gap {uuid} {expected}..{index}"),
+            CheckedMessage::Diagnostic(StreamDiagnostic::Duplicate { uuid, .. }) => {
+                format!("duplicate {uuid}")
+            }
+        })
+        .collect()
+        .await;
+
+    assert_eq!(
+        items,
+        vec![
+            "message",
+            "message",
+            "message",
+            "gap e5 1..1",
+            "message",
+            "gap e6 2..3",
+            "message",
+            "duplicate e6",
+        ]
+    );
+}
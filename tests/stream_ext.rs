@@ -0,0 +1,124 @@
+use std::sync::Arc;
+
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::stream_ext::MessageStreamExt;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn says(text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn result(session_id: &str) -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": session_id
+    })
+}
+
+fn messages(raw: Vec<Value>) -> impl futures::Stream<Item = Result<Message, SdkError>> {
+    stream::iter(raw.into_iter().map(|raw| parse_message(&raw)))
+}
+
+fn describe(message: &Message) -> String {
+    match message {
+        Message::Assistant(assistant) => format!("{:?}", assistant.content),
+        Message::Result(result) => format!("result {}", result.session_id),
+        other => format!("{other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn until_result_stops_after_the_first_result() {
+    let collected: Vec<String> = messages(vec![says("one"), result("a"), says("two"), result("b")])
+        .until_result()
+        .map(|message| describe(&message.unwrap()))
+        .collect()
+        .await;
+    assert_eq!(collected.len(), 2);
+    assert_eq!(collected[1], "result a");
+}
+
+#[tokio::test]
+async fn split_responses_skips_unread_messages() {
+    let mut responses = messages(vec![
+        says("first"),
+        result("a"),
+        says("second"),
+        says("more"),
+        result("b"),
+        says("trailing"),
+    ])
+    .split_responses();
+
+    let mut first = responses.next().await.expect("first response");
+    assert!(matches!(
+        first.next().await,
+        Some(Ok(Message::Assistant(_)))
+    ));
+    // The rest of the first response is discarded.
+    let second: Vec<String> = responses
+        .next()
+        .await
+        .expect("second response")
+        .map(|message| describe(&message.unwrap()))
+        .collect()
+        .await;
+    assert_eq!(second.len(), 3);
+    assert_eq!(second[2], "result b");
+    assert!(first.next().await.is_none());
+
+    let trailing: Vec<_> = responses
+        .next()
+        .await
+        .expect("unterminated response")
+        .collect()
+        .await;
+    assert_eq!(trailing.len(), 1);
+    assert!(responses.next().await.is_none());
+}
+
+#[tokio::test]
+async fn client_messages_split_per_query() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![says("planned"), result("s")])
+        .await;
+    transport
+        .reply_to_next_user(vec![says("implemented"), result("s")])
+        .await;
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+    client.query("Plan", "default").await.unwrap();
+    client.query("Implement", "default").await.unwrap();
+
+    // The session stays open, so stop after the two queries.
+    let mut responses = client.receive_messages().unwrap().split_responses().take(2);
+    let mut texts = Vec::new();
+    while let Some(response) = responses.next().await {
+        let messages: Vec<Message> = response.map(Result::unwrap).collect().await;
+        assert!(matches!(messages.last(), Some(Message::Result(_))));
+        texts.push(describe(&messages[0]));
+    }
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+    assert_eq!(texts.len(), 2);
+    assert!(texts[0].contains("planned") && texts[1].contains("implemented"));
+}
@@ -0,0 +1,73 @@
+use std::sync::Arc;
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::sinks::{TextRendering, TextSink};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn delta(delta: Value) -> Value {
+    json!({"type": "stream_event", "uuid": "evt", "session_id": "s",
+           "event": {"type": "content_block_delta", "index": 0, "delta": delta}})
+}
+
+fn assistant(content: Value) -> Value {
+    json!({"type": "assistant", "message": {"model": "claude-test", "content": content}})
+}
+
+fn result() -> Value {
+    json!({"type": "result", "subtype": "success", "duration_ms": 1, "duration_api_ms": 1,
+           "is_error": false, "num_turns": 2, "session_id": "s"})
+}
+
+#[tokio::test]
+async fn response_text_is_written_to_the_sink_as_it_streams() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport
+        .reply_to_next_user(vec![
+            delta(json!({"type": "text_delta", "text": "Hello"})),
+            delta(json!({"type": "text_delta", "text": " world"})),
+            assistant(json!([{"type": "text", "text": "Hello world"}])),
+            assistant(json!([{"type": "text", "text": "Done."}])),
+            result(),
+        ])
+        .await;
+    let mut client = ClaudeSdkClient::new(None, Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.unwrap();
+    client.query("Greet me", "default").await.unwrap();
+
+    let mut output = Vec::new();
+    let messages = client.stream_response_to(&mut output).await.unwrap();
+    assert_eq!(String::from_utf8(output).unwrap(), "Hello world\nDone.\n");
+    assert_eq!(messages.len(), 5);
+    assert!(matches!(messages.last(), Some(Message::Result(_))));
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn ansi_rendering_styles_thinking_and_tool_calls() {
+    let raw = [
+        delta(json!({"type": "thinking_delta", "thinking": "Check the file"})),
+        delta(json!({"type": "text_delta", "text": "Reading it."})),
+        assistant(json!([
+            {"type": "thinking", "thinking": "Check the file", "signature": "sig"},
+            {"type": "text", "text": "Reading it."},
+            {"type": "tool_use", "id": "t1", "name": "Read", "input": {}}
+        ])),
+        assistant(json!([{"type": "text", "text": "It is empty."}])),
+        result(),
+    ];
+    let mut sink = TextSink::new(Vec::new()).with_rendering(TextRendering::Ansi);
+    sink.consume(stream::iter(raw.iter().map(parse_message)))
+        .await
+        .unwrap();
+    assert_eq!(
+        String::from_utf8(sink.into_inner()).unwrap(),
+        "\x1b[2;3mCheck the file\x1b[0m\nReading it.\n\x1b[36m→ Read\x1b[0m\nIt is empty.\n"
+    );
+}
@@ -0,0 +1,188 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{AgentDefinition, ClaudeAgentOptions};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::subagent::SubagentTask;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn task_call(id: &str, agent: &str, prompt: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "model": "claude-test",
+            "content": [{
+                "type": "tool_use",
+                "id": id,
+                "name": "Task",
+                "input": {"description": "subtask", "subagent_type": agent, "prompt": prompt}
+            }]
+        }
+    })
+}
+
+fn subagent_says(parent: &str, text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "parent_tool_use_id": parent,
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": text}]}
+    })
+}
+
+fn task_result(id: &str, text: &str) -> Value {
+    json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{"type": "tool_result", "tool_use_id": id, "content": [{"type": "text", "text": text}]}]
+        }
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-agents"
+    })
+}
+
+fn agents(max_concurrent_subagents: Option<usize>) -> ClaudeAgentOptions {
+    let agent = |description: &str| AgentDefinition {
+        description: description.into(),
+        prompt: format!("You are the {description}."),
+        tools: None,
+        model: None,
+    };
+    ClaudeAgentOptions {
+        agents: Some(HashMap::from([
+            ("reviewer".to_string(), agent("reviewer")),
+            ("executor".to_string(), agent("executor")),
+        ])),
+        max_concurrent_subagents,
+        ..Default::default()
+    }
+}
+
+async fn connected(transport: &Arc<MockTransport>, options: ClaudeAgentOptions) -> ClaudeSdkClient {
+    transport.set_keep_open(true);
+    let mut client =
+        ClaudeSdkClient::new(Some(options), Some(transport.clone() as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+    client
+}
+
+#[tokio::test]
+async fn parallel_tasks_are_matched_to_their_task_calls() {
+    let transport = MockTransport::new();
+    transport
+        .reply_to_next_user(vec![
+            // Calls arrive in a different order than the tasks were given.
+            task_call("task-b", "executor", "run the migrations"),
+            task_call("task-a", "reviewer", "review src/db.rs"),
+            subagent_says("task-a", "Looking at src/db.rs"),
+            subagent_says("task-b", "Running sqlx migrate"),
+            task_result("task-b", "Applied 3 migrations"),
+            task_result("task-a", "Two issues found"),
+            json!({"type": "assistant", "message": {"model": "claude-test", "content": [{"type": "text", "text": "Both done."}]}}),
+            result(),
+        ])
+        .await;
+    let mut client = connected(&transport, agents(None)).await;
+
+    let results = client
+        .spawn_subagents(
+            vec![
+                SubagentTask::new("reviewer", "review src/db.rs"),
+                SubagentTask::new("executor", "run the migrations"),
+            ],
+            "sess-agents",
+        )
+        .await
+        .expect("tasks should run");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    let review = results[0].as_ref().expect("review should succeed");
+    assert_eq!(
+        (review.agent.as_str(), review.tool_use_id.as_str()),
+        ("reviewer", "task-a")
+    );
+    assert_eq!(review.output, "Two issues found");
+    assert!(!review.is_error);
+    assert_eq!(review.messages.len(), 1);
+    let migrate = results[1].as_ref().expect("migration should succeed");
+    assert_eq!(migrate.output, "Applied 3 migrations");
+
+    let writes: Vec<Value> = transport
+        .writes()
+        .await
+        .into_iter()
+        .filter(|payload| payload["type"] == "user")
+        .collect();
+    assert_eq!(writes.len(), 1);
+    assert_eq!(writes[0]["session_id"], "sess-agents");
+    let prompts: Vec<&str> = writes
+        .iter()
+        .map(|payload| payload["message"]["content"].as_str().unwrap())
+        .collect();
+    assert!(prompts[0].contains("subagent_type: reviewer\n<task>\nreview src/db.rs\n</task>"));
+}
+
+#[tokio::test]
+async fn batches_respect_the_concurrency_limit() {
+    let transport = MockTransport::new();
+    transport
+        .reply_to_next_user(vec![
+            task_call("t1", "executor", "build"),
+            task_result("t1", "built"),
+            result(),
+        ])
+        .await;
+    // The second batch never starts its agent.
+    transport.reply_to_next_user(vec![result()]).await;
+    let mut client = connected(&transport, agents(Some(1))).await;
+
+    let results = client
+        .spawn_subagents(
+            vec![
+                SubagentTask::new("executor", "build"),
+                SubagentTask::new("executor", "deploy"),
+            ],
+            "sess-agents",
+        )
+        .await
+        .expect("batches should run");
+    assert_eq!(results[0].as_ref().unwrap().output, "built");
+    assert!(
+        matches!(&results[1], Err(SdkError::Message(message)) if message.contains("did not start"))
+    );
+
+    let err = client
+        .spawn_subagent("planner", "plan", "sess-agents")
+        .await
+        .expect_err("unknown agents are rejected");
+    assert!(matches!(err, SdkError::InvalidConfig(_)));
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    let user_prompts = transport
+        .writes()
+        .await
+        .iter()
+        .filter(|payload| payload["type"] == "user")
+        .count();
+    assert_eq!(user_prompts, 2);
+}
@@ -0,0 +1,139 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::{
+    ClaudeAgentOptions, SystemPrompt, SystemPromptPreset, SystemPromptPresetName,
+    SystemPromptPresetType,
+};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::client::PromptInput;
+use sdk_claude_rust::query::query;
+use sdk_claude_rust::raw::RawSession;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+/// Answer the `initialize` request written to `transport` with `response`.
+async fn answer_initialize(transport: &MockTransport, response: Value) {
+    loop {
+        let request_id = transport.writes().await.iter().find_map(|write| {
+            (write.pointer("/request/subtype") == Some(&json!("initialize")))
+                .then(|| write["request_id"].clone())
+        });
+        if let Some(request_id) = request_id {
+            let reply = json!({
+                "type": "control_response",
+                "response": {"subtype": "success", "request_id": request_id, "response": response}
+            });
+            transport.enqueue_read(Ok(Some(reply))).await;
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+}
+
+fn preset_options(name: &str) -> ClaudeAgentOptions {
+    ClaudeAgentOptions {
+        system_prompt: Some(SystemPrompt::Preset(SystemPromptPreset {
+            kind: SystemPromptPresetType::Preset,
+            preset: name.into(),
+            append: None,
+        })),
+        ..Default::default()
+    }
+}
+
+async fn connect_with(name: &str, initialize: Value) -> (ClaudeSdkClient, Result<(), SdkError>) {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let mut client = ClaudeSdkClient::new(
+        Some(preset_options(name)),
+        Some(transport.clone() as Arc<dyn Transport>),
+    );
+    let (connected, ()) = tokio::join!(
+        client.connect(None),
+        answer_initialize(&transport, initialize)
+    );
+    transport.set_withhold_control_responses(false);
+    (client, connected)
+}
+
+#[test]
+fn preset_names_round_trip_through_serde() {
+    let known: SystemPromptPresetName = serde_json::from_value(json!("claude_code")).unwrap();
+    assert_eq!(known, SystemPromptPresetName::ClaudeCode);
+
+    let newer: SystemPromptPresetName = serde_json::from_value(json!("claude_review")).unwrap();
+    assert_eq!(newer, SystemPromptPresetName::Other("claude_review".into()));
+    assert_eq!(
+        serde_json::to_value(&newer).unwrap(),
+        json!("claude_review")
+    );
+    assert_eq!(
+        SystemPromptPresetName::from("claude_code".to_string()),
+        SystemPromptPresetName::ClaudeCode
+    );
+}
+
+#[tokio::test]
+async fn presets_missing_from_the_initialize_response_fail_connect() {
+    let advertised = json!({"available_system_prompt_presets": ["claude_code", "claude_review"]});
+
+    let (client, connected) = connect_with("claude_cod", advertised.clone()).await;
+    let err = connected.unwrap_err();
+    assert!(
+        matches!(&err, SdkError::InvalidConfig(message)
+            if message.contains("did you mean claude_code?") && message.contains("claude_review")),
+        "{err}"
+    );
+    assert!(client.get_server_info().is_none());
+
+    let (mut client, connected) = connect_with("claude_review", advertised).await;
+    connected.unwrap();
+    assert_eq!(
+        client.available_system_prompt_presets(),
+        ["claude_code", "claude_review"]
+    );
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn presets_are_not_checked_when_none_are_advertised() {
+    let (mut client, connected) = connect_with("claude_review", json!({})).await;
+    connected.unwrap();
+    assert!(client.available_system_prompt_presets().is_empty());
+    client.disconnect().await.unwrap();
+}
+
+#[tokio::test]
+async fn one_shot_queries_and_raw_sessions_check_presets() {
+    let advertised = json!({"available_system_prompt_presets": ["claude_code"]});
+
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let prompt = PromptInput::from_stream(stream::iter(vec![json!({"type": "user"})]));
+    let (started, ()) = tokio::join!(
+        query(
+            prompt,
+            Some(preset_options("claude_review")),
+            Some(transport.clone() as Arc<dyn Transport>),
+        ),
+        answer_initialize(&transport, advertised.clone())
+    );
+    assert!(matches!(started, Err(SdkError::InvalidConfig(_))));
+
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.set_withhold_control_responses(true);
+    let options = preset_options("claude_review");
+    let (opened, ()) = tokio::join!(
+        RawSession::open(transport.clone() as Arc<dyn Transport>, &options),
+        answer_initialize(&transport, advertised)
+    );
+    assert!(matches!(opened, Err(SdkError::InvalidConfig(_))));
+}
@@ -0,0 +1,80 @@
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
+
+use sdk_claude_rust::config::{ClaudeAgentOptions, ThinkingConfig, DEFAULT_THINKING_BUDGET};
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::stream_ext::{MessageStreamExt, ThinkingPart};
+
+fn delta(kind: &str, field: &str, text: &str) -> Value {
+    json!({
+        "type": "stream_event",
+        "uuid": "evt",
+        "session_id": "sess-thinking",
+        "event": {"type": "content_block_delta", "delta": {"type": kind, field: text}}
+    })
+}
+
+fn assistant(thinking: &str, text: &str) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": [
+            {"type": "thinking", "thinking": thinking, "signature": "sig"},
+            {"type": "text", "text": text}
+        ]}
+    })
+}
+
+async fn parts(raw: Vec<Value>) -> Vec<String> {
+    stream::iter(raw.iter().map(parse_message))
+        .split_thinking()
+        .map(|part| match part.unwrap() {
+            ThinkingPart::Thinking(text) => format!("thinking {text}"),
+            ThinkingPart::Text(text) => format!("text {text}"),
+            ThinkingPart::Message(_) => "message".to_string(),
+        })
+        .collect()
+        .await
+}
+
+#[test]
+fn thinking_config_maps_to_max_thinking_tokens() {
+    let mut options = ClaudeAgentOptions {
+        max_thinking_tokens: Some(4096),
+        ..Default::default()
+    };
+    assert_eq!(options.effective_max_thinking_tokens(), Some(4096));
+
+    options.thinking = Some(ThinkingConfig::enabled());
+    assert_eq!(
+        options.effective_max_thinking_tokens(),
+        Some(DEFAULT_THINKING_BUDGET)
+    );
+    options.thinking = Some(ThinkingConfig::disabled());
+    assert_eq!(options.effective_max_thinking_tokens(), Some(0));
+    options.thinking = Some(ThinkingConfig::with_budget(2048));
+    assert_eq!(options.effective_max_thinking_tokens(), Some(2048));
+    assert!(options.validate().is_ok());
+
+    options.thinking = Some(ThinkingConfig::with_budget(100));
+    let err = options.validate().unwrap_err();
+    assert!(matches!(err, SdkError::InvalidConfig(message) if message.contains("at least 1024")));
+}
+
+#[tokio::test]
+async fn split_thinking_separates_reasoning_from_answer() {
+    let blocks = parts(vec![assistant("ponder", "answer")]).await;
+    assert_eq!(blocks, ["thinking ponder", "text answer", "message"]);
+
+    let streamed = parts(vec![
+        delta("thinking_delta", "thinking", "pon"),
+        delta("thinking_delta", "thinking", "der"),
+        delta("text_delta", "text", "answer"),
+        assistant("ponder", "answer"),
+    ])
+    .await;
+    assert_eq!(
+        streamed,
+        ["thinking pon", "thinking der", "text answer", "message"]
+    );
+}
@@ -0,0 +1,64 @@
+use sdk_claude_rust::prompts::{PromptTemplate, PromptVars};
+use sdk_claude_rust::tokens::{chunk, estimate};
+
+#[test]
+fn estimates_follow_word_digit_and_symbol_rules() {
+    assert_eq!(estimate(""), 0);
+    assert_eq!(estimate("Hello world"), 2);
+    assert_eq!(estimate("internationalization"), 4);
+    assert_eq!(estimate("1234567"), 3);
+    assert_eq!(estimate("fn main() {}\n"), 7);
+    assert_eq!(estimate("        indent"), 3);
+    assert_eq!(estimate("日本語"), 3);
+    assert_eq!(estimate("привет"), 2);
+    assert_eq!(estimate("ok 👍"), 3);
+
+    // Roughly four characters per token for English prose.
+    let prose = "The quick brown fox jumps over the lazy dog. ".repeat(100);
+    let ratio = prose.len() as f64 / estimate(&prose) as f64;
+    assert!((3.5..=5.0).contains(&ratio), "{ratio}");
+}
+
+#[test]
+fn chunks_break_at_lines_then_words_and_rejoin_losslessly() {
+    let text = "alpha beta gamma\ndelta epsilon\nzeta eta theta iota kappa lambda\n";
+    let chunks = chunk(text, 4);
+    assert_eq!(
+        chunks,
+        vec![
+            "alpha beta gamma\n",
+            "delta epsilon\n",
+            "zeta eta theta iota ",
+            "kappa lambda\n"
+        ]
+    );
+    assert_eq!(chunks.concat(), text);
+    assert!(chunks.iter().all(|piece| estimate(piece) <= 4));
+}
+
+#[test]
+fn words_longer_than_the_budget_are_split() {
+    let blob = format!("key: {}", "a".repeat(40));
+    let chunks = chunk(&blob, 3);
+    assert_eq!(chunks.concat(), blob);
+    assert!(
+        chunks.iter().all(|piece| estimate(piece) <= 3),
+        "{chunks:?}"
+    );
+    assert_eq!(chunks[0], "key: ");
+    assert_eq!(chunks[1].len(), 18);
+
+    assert!(chunk("", 10).is_empty());
+    assert_eq!(chunk("short", 100), vec!["short"]);
+}
+
+#[test]
+fn templates_estimate_their_rendered_size() {
+    let template = PromptTemplate::parse("review", "Review this code:\n{{code}}").unwrap();
+    let vars = PromptVars::new().with("code", "fn main() {}");
+    assert_eq!(
+        template.estimated_tokens(&vars).unwrap(),
+        estimate("Review this code:\nfn main() {}")
+    );
+    assert!(template.estimated_tokens(&PromptVars::new()).is_err());
+}
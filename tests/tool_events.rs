@@ -0,0 +1,140 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::tool_events::{ToolEvent, ToolEventTracker};
+use sdk_claude_rust::transport::Transport;
+
+fn tool_use(id: &str, name: &str, parent: Option<&str>) -> Value {
+    json!({
+        "type": "assistant",
+        "parent_tool_use_id": parent,
+        "message": {
+            "model": "claude-test",
+            "content": [{"type": "tool_use", "id": id, "name": name, "input": {"arg": id}}]
+        }
+    })
+}
+
+fn tool_result(id: &str, content: &str, is_error: bool) -> Value {
+    json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{"type": "tool_result", "tool_use_id": id, "content": content, "is_error": is_error}]
+        }
+    })
+}
+
+fn result() -> Value {
+    json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 1,
+        "duration_api_ms": 1,
+        "is_error": false,
+        "num_turns": 1,
+        "session_id": "sess-tools"
+    })
+}
+
+fn parsed(raw: Value) -> Message {
+    parse_message(&raw).expect("fixture should parse")
+}
+
+#[tokio::test(start_paused = true)]
+async fn results_are_correlated_with_their_calls() {
+    let mut tracker = ToolEventTracker::new();
+
+    let started = tracker.observe(&parsed(tool_use("t1", "Bash", None)));
+    assert_eq!(
+        started,
+        vec![ToolEvent::Started {
+            tool_use_id: "t1".into(),
+            name: "Bash".into(),
+            input: json!({"arg": "t1"}).as_object().unwrap().clone(),
+            parent_tool_use_id: None,
+        }]
+    );
+    tracker.observe(&parsed(tool_use("t2", "Read", Some("task-1"))));
+    assert_eq!(tracker.pending(), 2);
+
+    tokio::time::advance(Duration::from_millis(1500)).await;
+    let finished = tracker.observe(&parsed(tool_result("t2", "no such file", true)));
+    match &finished[..] {
+        [ToolEvent::Finished {
+            tool_use_id,
+            name,
+            parent_tool_use_id,
+            duration,
+            is_error,
+            content,
+            ..
+        }] => {
+            assert_eq!((tool_use_id.as_str(), name.as_str()), ("t2", "Read"));
+            assert_eq!(parent_tool_use_id.as_deref(), Some("task-1"));
+            assert_eq!(*duration, Duration::from_millis(1500));
+            assert!(*is_error);
+            assert_eq!(content, &Some(json!("no such file")));
+        }
+        other => panic!("unexpected events {other:?}"),
+    }
+
+    // Unknown results are ignored; unfinished calls end with the query.
+    assert!(tracker
+        .observe(&parsed(tool_result("t0", "late", false)))
+        .is_empty());
+    let abandoned = tracker.observe(&parsed(result()));
+    assert_eq!(abandoned.len(), 1);
+    assert!(
+        matches!(&abandoned[0], ToolEvent::Abandoned { tool_use_id, .. } if tool_use_id == "t1")
+    );
+    assert_eq!(tracker.pending(), 0);
+}
+
+#[tokio::test]
+async fn client_streams_tool_events() {
+    let transport = MockTransport::new();
+    transport
+        .reply_to_next_user(vec![
+            tool_use("t1", "Bash", None),
+            tool_result("t1", "ok", false),
+            result(),
+        ])
+        .await;
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let mut client = ClaudeSdkClient::new(None, Some(transport_arc));
+    client.connect(None).await.expect("connect should succeed");
+    let events = client
+        .tool_events()
+        .await
+        .expect("tool event stream should be available");
+    client
+        .query("List files", "default")
+        .await
+        .expect("query should be written");
+
+    let events: Vec<ToolEvent> =
+        tokio::time::timeout(Duration::from_secs(5), events.take(2).collect())
+            .await
+            .expect("tool events should arrive");
+    client
+        .disconnect()
+        .await
+        .expect("disconnect should succeed");
+
+    assert!(matches!(&events[0], ToolEvent::Started { name, .. } if name == "Bash"));
+    assert!(matches!(
+        &events[1],
+        ToolEvent::Finished { tool_use_id, is_error: false, .. } if tool_use_id == "t1"
+    ));
+    assert_eq!(events[1].name(), "Bash");
+}
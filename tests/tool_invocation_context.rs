@@ -0,0 +1,167 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use serde_json::{json, Map, Value};
+use tokio::sync::Mutex;
+
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::mcp::{
+    create_sdk_mcp_server, tool_with_context, McpToolCallResult, McpToolContent, SdkMcpServer,
+    SdkMcpTool, ToolInvocationContext,
+};
+use sdk_claude_rust::permission::PermissionMode;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn tool_call(request_id: &str, tool_use_id: &str) -> Value {
+    json!({
+        "type": "control_request",
+        "request_id": request_id,
+        "request": {
+            "subtype": "mcp_message",
+            "server_name": "notes",
+            "message": {
+                "jsonrpc": "2.0",
+                "id": 1,
+                "method": "tools/call",
+                "params": {
+                    "name": "save",
+                    "arguments": {},
+                    "_meta": {"claudecode/toolUseId": tool_use_id}
+                }
+            }
+        }
+    })
+}
+
+async fn wait_for_response(transport: &Arc<MockTransport>, request_id: &str) {
+    for _ in 0..100 {
+        let answered = transport
+            .writes()
+            .await
+            .iter()
+            .any(|payload| payload.pointer("/response/request_id") == Some(&json!(request_id)));
+        if answered {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("{request_id} was not answered");
+}
+
+#[tokio::test]
+async fn sdk_mcp_handlers_see_the_session_and_calling_agent() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let seen = Arc::new(Mutex::new(Vec::<ToolInvocationContext>::new()));
+    let recorded = Arc::clone(&seen);
+    let save = tool_with_context("save", "Save a note", json!({}), move |_, context| {
+        let recorded = Arc::clone(&recorded);
+        async move {
+            recorded.lock().await.push(context);
+            Ok(McpToolCallResult::new(vec![McpToolContent::text("saved")]))
+        }
+    });
+    let mut servers: HashMap<String, Arc<dyn SdkMcpServer>> = HashMap::new();
+    servers.insert(
+        "notes".into(),
+        create_sdk_mcp_server("notes", "1.0.0", vec![save]),
+    );
+
+    let query = Query::new(transport_arc, true, None, None, servers);
+    query
+        .record_permission_mode(Some(PermissionMode::AcceptEdits))
+        .await;
+    query.start().await.expect("query should start");
+
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "system",
+            "subtype": "init",
+            "session_id": "s-1"
+        }))))
+        .await;
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "assistant",
+            "message": {
+                "model": "claude-sonnet-4-5",
+                "content": [{
+                    "type": "tool_use",
+                    "id": "toolu_sub",
+                    "name": "mcp__notes__save",
+                    "input": {}
+                }]
+            },
+            "parent_tool_use_id": "toolu_task"
+        }))))
+        .await;
+    transport
+        .enqueue_read(Ok(Some(tool_call("sub", "toolu_sub"))))
+        .await;
+    wait_for_response(&transport, "sub").await;
+    transport
+        .enqueue_read(Ok(Some(tool_call("main", "toolu_main"))))
+        .await;
+    wait_for_response(&transport, "main").await;
+
+    let seen = seen.lock().await;
+    assert_eq!(
+        seen[0],
+        ToolInvocationContext {
+            session_id: Some("s-1".into()),
+            tool_use_id: Some("toolu_sub".into()),
+            parent_tool_use_id: Some("toolu_task".into()),
+            permission_mode: Some(PermissionMode::AcceptEdits),
+        }
+    );
+    assert_eq!(seen[1].tool_use_id.as_deref(), Some("toolu_main"));
+    assert_eq!(seen[1].parent_tool_use_id, None);
+    query.close().await.expect("close should succeed");
+}
+
+#[tokio::test]
+async fn struct_literal_tools_keep_the_plain_handler_signature() {
+    let echo = SdkMcpTool {
+        name: "echo".into(),
+        description: "Echo the session".into(),
+        input_schema: json!({}),
+        handler: Arc::new(|_args: Map<String, Value>| {
+            Box::pin(async { Ok(McpToolCallResult::new(vec![McpToolContent::text("plain")])) })
+        }),
+    };
+    let session = tool_with_context(
+        "session",
+        "Name the session",
+        json!({}),
+        |_, context| async move {
+            let session_id = context.session_id.unwrap_or_default();
+            Ok(McpToolCallResult::new(vec![McpToolContent::text(
+                session_id,
+            )]))
+        },
+    );
+    let server = create_sdk_mcp_server("tools", "1.0.0", vec![echo, session]);
+    let context = ToolInvocationContext {
+        session_id: Some("s-1".into()),
+        ..Default::default()
+    };
+
+    let text = |result: McpToolCallResult| match &result.content[0] {
+        McpToolContent::Text { text } => text.clone(),
+        other => panic!("expected text, got {other:?}"),
+    };
+    let plain = server
+        .call_tool_with_context("echo", Map::new(), context.clone())
+        .await
+        .unwrap();
+    assert_eq!(text(plain), "plain");
+    let named = server
+        .call_tool_with_context("session", Map::new(), context)
+        .await
+        .unwrap();
+    assert_eq!(text(named), "s-1");
+}
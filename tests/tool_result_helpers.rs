@@ -0,0 +1,45 @@
+use serde::Serialize;
+
+use sdk_claude_rust::mcp::{McpToolCallResult, McpToolContent};
+
+fn text(result: &McpToolCallResult) -> &str {
+    match result.content.as_slice() {
+        [McpToolContent::Text { text }] => text,
+        other => panic!("expected one text block, got {other:?}"),
+    }
+}
+
+#[test]
+fn structs_and_tables_become_text() {
+    #[derive(Serialize)]
+    struct Issue {
+        id: u32,
+        title: &'static str,
+    }
+    let result = McpToolCallResult::from_serialize(&Issue {
+        id: 7,
+        title: "Crash",
+    });
+    assert!(!result.is_error);
+    assert_eq!(text(&result), "{\n  \"id\": 7,\n  \"title\": \"Crash\"\n}");
+
+    let rows = vec![
+        vec!["file".to_string(), "lines".to_string()],
+        vec!["a|b.rs".to_string(), "12".to_string()],
+        vec!["c.rs".to_string()],
+    ];
+    assert_eq!(
+        text(&McpToolCallResult::from_table(rows)),
+        "| file | lines |\n| --- | --- |\n| a\\|b.rs | 12 |\n| c.rs |  |\n"
+    );
+}
+
+#[test]
+fn errors_are_flagged() {
+    let result = McpToolCallResult::error("repository not found");
+    assert!(result.is_error);
+    assert_eq!(text(&result), "repository not found");
+
+    let markdown = McpToolCallResult::from_markdown("# Done");
+    assert_eq!(text(&markdown), "# Done");
+}
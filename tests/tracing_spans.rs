@@ -0,0 +1,136 @@
+#![cfg(feature = "tracing")]
+
+mod common;
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use serde_json::json;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id, Record};
+use tracing::Subscriber;
+use tracing_subscriber::layer::{Context, Layer, SubscriberExt};
+use tracing_subscriber::registry::{LookupSpan, Registry};
+
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::permission::{PermissionResult, ToolPermissionContext};
+use sdk_claude_rust::transport::Transport;
+
+use common::MockTransport;
+
+type Spans = Arc<Mutex<Vec<(String, HashMap<String, String>)>>>;
+
+/// Layer collecting every span with the fields recorded on it.
+struct CollectSpans {
+    spans: Spans,
+}
+
+struct FieldVisitor<'a>(&'a mut HashMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.0.insert(
+            field.name().to_string(),
+            format!("{value:?}").trim_matches('"').to_string(),
+        );
+    }
+}
+
+#[derive(Default)]
+struct SpanIndex(usize);
+
+impl<S> Layer<S> for CollectSpans
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        let mut fields = HashMap::new();
+        attrs.record(&mut FieldVisitor(&mut fields));
+        let mut spans = self.spans.lock().unwrap();
+        spans.push((attrs.metadata().name().to_string(), fields));
+        if let Some(span) = ctx.span(id) {
+            span.extensions_mut().insert(SpanIndex(spans.len() - 1));
+        }
+    }
+
+    fn on_record(&self, id: &Id, values: &Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else { return };
+        let Some(index) = span.extensions().get::<SpanIndex>().map(|index| index.0) else {
+            return;
+        };
+        let mut spans = self.spans.lock().unwrap();
+        values.record(&mut FieldVisitor(&mut spans[index].1));
+    }
+}
+
+#[tokio::test]
+async fn control_requests_emit_spans_with_fields() {
+    let spans: Spans = Arc::default();
+    let subscriber = Registry::default().with(CollectSpans {
+        spans: spans.clone(),
+    });
+    let _guard = tracing::subscriber::set_default(subscriber);
+
+    let transport = MockTransport::with_reads(vec![Ok(Some(json!({
+        "type": "control_request",
+        "request_id": "cli-1",
+        "request": {"subtype": "can_use_tool", "tool_name": "Bash", "input": {}}
+    })))]);
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let callback = Arc::new(
+        |_tool: &str,
+         _input: serde_json::Map<String, serde_json::Value>,
+         _ctx: ToolPermissionContext| {
+            Box::pin(async move {
+                PermissionResult::Deny {
+                    message: "not in tests".into(),
+                    interrupt: false,
+                }
+            })
+        },
+    );
+
+    let query = Query::new(transport_arc, true, Some(callback), None, HashMap::new());
+    query.interrupt().await.expect("interrupt should succeed");
+    for _ in 0..100 {
+        if transport.writes().await.len() >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    query.close().await.expect("close should succeed");
+
+    let spans = spans.lock().unwrap().clone();
+    let control: Vec<_> = spans
+        .iter()
+        .filter(|(name, _)| name == "claude_sdk.control_request")
+        .map(|(_, fields)| fields)
+        .collect();
+
+    let outbound = control
+        .iter()
+        .find(|fields| fields.get("direction").map(String::as_str) == Some("outbound"))
+        .expect("outbound control span");
+    assert_eq!(outbound["subtype"], "interrupt");
+    assert!(outbound["request_id"].starts_with("req_"));
+    assert_eq!(outbound["outcome"], "success");
+    assert!(outbound.contains_key("duration_ms"));
+
+    let inbound = control
+        .iter()
+        .find(|fields| fields.get("direction").map(String::as_str) == Some("inbound"))
+        .expect("inbound control span");
+    assert_eq!(inbound["request_id"], "cli-1");
+    assert_eq!(inbound["subtype"], "can_use_tool");
+    assert_eq!(inbound["outcome"], "success");
+
+    let (_, permission) = spans
+        .iter()
+        .find(|(name, _)| name == "claude_sdk.can_use_tool")
+        .expect("permission callback span");
+    assert_eq!(permission["tool"], "Bash");
+    assert_eq!(permission["behavior"], "deny");
+}
@@ -0,0 +1,178 @@
+#![cfg(unix)]
+
+use std::os::unix::fs::PermissionsExt;
+use std::sync::Arc;
+use std::time::Duration;
+
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::transport::stderr::{StderrEvent, StderrLevel, StderrProgress};
+use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+use sdk_claude_rust::transport::Transport;
+
+fn fake_cli(dir: &std::path::Path) -> std::path::PathBuf {
+    write_cli(
+        dir,
+        "echo 'Invalid API key - please run /login' >&2\n\
+         exit 1\n",
+    )
+}
+
+fn write_cli(dir: &std::path::Path, body: &str) -> std::path::PathBuf {
+    let path = dir.join("claude");
+    std::fs::write(
+        &path,
+        format!(
+            "#!/bin/sh\n\
+             if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+             {body}"
+        ),
+    )
+    .expect("script should be written");
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o755))
+        .expect("script should be executable");
+    path
+}
+
+#[tokio::test]
+async fn failed_cli_exit_reports_diagnostics() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let options = ClaudeAgentOptions {
+        cli_path: Some(fake_cli(dir.path())),
+        cwd: Some(dir.path().to_path_buf()),
+        stderr: Some(Arc::new(|_: &str| {})),
+        ..Default::default()
+    };
+
+    let transport =
+        SubprocessCliTransport::new(PromptMode::Text("top secret prompt".into()), options)
+            .expect("transport should build");
+    transport.connect().await.expect("spawn should succeed");
+
+    let err = loop {
+        match transport.read().await {
+            Ok(Some(_)) => continue,
+            Ok(None) => panic!("expected the exit to be reported"),
+            Err(err) => break err,
+        }
+    };
+    transport.close().await.expect("close should succeed");
+
+    let SdkError::Process(process) = err else {
+        panic!("expected process error, got {err:?}");
+    };
+    assert_eq!(process.exit_code(), Some(1));
+    let diagnostics = process
+        .diagnostics()
+        .expect("diagnostics should be attached");
+    assert_eq!(
+        diagnostics.cli_version.as_deref(),
+        Some("2.0.5 (Claude Code)")
+    );
+    assert_eq!(diagnostics.cwd.as_deref(), Some(dir.path()));
+    assert_eq!(
+        diagnostics.stderr_head,
+        vec!["Invalid API key - please run /login".to_string()]
+    );
+    assert!(diagnostics.argv.iter().any(|arg| arg == "<prompt>"));
+    assert!(!diagnostics
+        .argv
+        .iter()
+        .any(|arg| arg.contains("top secret")));
+    assert!(diagnostics
+        .hint
+        .as_deref()
+        .is_some_and(|hint| hint.contains("claude login")));
+}
+
+#[tokio::test]
+async fn spawn_failure_names_missing_binary() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let missing = dir.path().join("no-such-claude");
+    let options = ClaudeAgentOptions {
+        cli_path: Some(missing.clone()),
+        ..Default::default()
+    };
+
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options)
+        .expect("transport should build");
+    let err = transport.connect().await.expect_err("spawn should fail");
+
+    let SdkError::CliConnection(connection) = err else {
+        panic!("expected connection error, got {err:?}");
+    };
+    let diagnostics = connection
+        .diagnostics()
+        .expect("diagnostics should be attached");
+    assert_eq!(diagnostics.cli_path.as_deref(), Some(missing.as_path()));
+    assert!(connection.message().contains("no-such-claude"));
+    assert!(connection.message().contains("hint:"));
+}
+
+#[tokio::test]
+async fn stderr_lines_are_published_as_events() {
+    let dir = tempfile::tempdir().expect("temp dir");
+    let cli = write_cli(
+        dir.path(),
+        "sleep 0.3\n\
+         echo '2025-06-01T10:00:00.000Z [ERROR] MCP server \"files\": Connection failed' >&2\n\
+         echo '[DEBUG] [compact] Compacting conversation' >&2\n",
+    );
+    let options = ClaudeAgentOptions {
+        cli_path: Some(cli),
+        stderr: Some(Arc::new(|_: &str| {})),
+        ..Default::default()
+    };
+
+    let transport = SubprocessCliTransport::new(PromptMode::Streaming, options)
+        .expect("transport should build");
+    transport.connect().await.expect("spawn should succeed");
+    let mut events = transport
+        .subscribe_stderr()
+        .expect("subprocess transport exposes stderr");
+
+    let mcp = next_event(&mut events).await;
+    assert_eq!(mcp.level, StderrLevel::Error);
+    assert_eq!(mcp.subsystem.as_deref(), Some("mcp"));
+    assert_eq!(
+        mcp.progress,
+        Some(StderrProgress::McpServerFailed {
+            server: "files".into()
+        })
+    );
+    assert_eq!(
+        mcp.progress.map(|progress| progress.to_string()).as_deref(),
+        Some("MCP server files failed to start")
+    );
+    let compact = next_event(&mut events).await;
+    assert_eq!(compact.level, StderrLevel::Debug);
+    assert_eq!(compact.subsystem.as_deref(), Some("compact"));
+    assert_eq!(compact.message, "Compacting conversation");
+    assert_eq!(compact.progress, Some(StderrProgress::CompactingContext));
+    transport.close().await.expect("close should succeed");
+}
+
+#[test]
+fn stderr_is_left_to_the_host_without_a_callback() {
+    let transport =
+        SubprocessCliTransport::new(PromptMode::Streaming, ClaudeAgentOptions::default())
+            .expect("transport should build");
+    assert!(transport.subscribe_stderr().is_none());
+}
+
+async fn next_event(events: &mut tokio::sync::broadcast::Receiver<StderrEvent>) -> StderrEvent {
+    tokio::time::timeout(Duration::from_secs(5), events.recv())
+        .await
+        .expect("event should arrive")
+        .expect("channel should stay open")
+}
+
+#[test]
+fn unstructured_stderr_keeps_the_whole_line() {
+    let event = StderrEvent::parse("something odd happened\n");
+    assert_eq!(event.level, StderrLevel::Info);
+    assert_eq!(event.subsystem, None);
+    assert_eq!(event.message, "something odd happened");
+    assert_eq!(event.raw, "something odd happened");
+    assert_eq!(event.progress, None);
+}
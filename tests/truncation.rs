@@ -0,0 +1,146 @@
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures::stream;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::internal::query::Query;
+use sdk_claude_rust::mcp::{
+    create_sdk_mcp_server, tool, McpToolCallResult, McpToolContent, SdkMcpServer,
+};
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+use sdk_claude_rust::truncation::{ToolResultLimit, TruncationStrategy};
+
+#[test]
+fn oversized_text_keeps_head_or_head_and_tail() {
+    let limit = ToolResultLimit::new(10);
+    assert_eq!(limit.truncate("short"), "short");
+    assert_eq!(
+        limit.truncate("0123456789abcdef"),
+        "0123456789\n[... truncated 6 bytes ...]"
+    );
+
+    let both = limit.with_strategy(TruncationStrategy::HeadAndTail);
+    assert_eq!(
+        both.truncate("0123456789abcdef"),
+        "01234\n[... truncated 6 bytes ...]\nbcdef"
+    );
+    // Cuts never split a character.
+    assert_eq!(
+        ToolResultLimit::new(4).truncate("ééé"),
+        "éé\n[... truncated 2 bytes ...]"
+    );
+}
+
+#[test]
+fn tool_result_blocks_in_user_messages_are_truncated() {
+    let long = "x".repeat(50);
+    let mut message = json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [
+                {"type": "text", "text": long},
+                {"type": "tool_result", "tool_use_id": "t1", "content": long},
+                {"type": "tool_result", "tool_use_id": "t2", "content": [
+                    {"type": "text", "text": long},
+                    {"type": "image", "source": {"data": long}}
+                ]}
+            ]
+        }
+    });
+    assert!(ToolResultLimit::new(20).apply_to_message(&mut message));
+
+    let blocks = message["message"]["content"].as_array().unwrap();
+    let marked = format!("{}\n[... truncated 30 bytes ...]", "x".repeat(20));
+    assert_eq!(blocks[0]["text"], long);
+    assert_eq!(blocks[1]["content"], marked);
+    assert_eq!(blocks[2]["content"][0]["text"], marked);
+    assert_eq!(blocks[2]["content"][1]["source"]["data"], long);
+
+    assert!(!ToolResultLimit::new(100).apply_to_message(&mut message));
+}
+
+#[tokio::test]
+async fn sdk_mcp_tool_results_are_truncated_before_reaching_the_cli() {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    let transport_arc: Arc<dyn Transport> = transport.clone();
+
+    let dump = tool("dump", "Dump a log", json!({}), |_| async {
+        Ok(McpToolCallResult::new(vec![
+            McpToolContent::text("y".repeat(64)),
+            McpToolContent::json(json!({"rows": vec!["z"; 20]})),
+            McpToolContent::text("ok"),
+        ]))
+    });
+    let mut servers: HashMap<String, Arc<dyn SdkMcpServer>> = HashMap::new();
+    servers.insert(
+        "logs".into(),
+        create_sdk_mcp_server("logs", "1.0.0", vec![dump]),
+    );
+
+    let query = Query::new(transport_arc, true, None, None, servers);
+    query
+        .set_tool_result_limit(Some(ToolResultLimit::new(16)))
+        .await;
+    query.start().await.expect("query should start");
+    transport
+        .enqueue_read(Ok(Some(json!({
+            "type": "control_request",
+            "request_id": "call",
+            "request": {
+                "subtype": "mcp_message",
+                "server_name": "logs",
+                "message": {
+                    "jsonrpc": "2.0",
+                    "id": 1,
+                    "method": "tools/call",
+                    "params": {"name": "dump", "arguments": {}}
+                }
+            }
+        }))))
+        .await;
+
+    let mut content = Value::Null;
+    for _ in 0..100 {
+        let writes = transport.writes().await;
+        if let Some(response) = writes
+            .iter()
+            .find(|payload| payload.pointer("/response/request_id") == Some(&json!("call")))
+        {
+            content = response["response"]["response"]["result"]["content"].clone();
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(
+        content,
+        json!([
+            {"type": "text", "text": format!("{}\n[... truncated 48 bytes ...]", "y".repeat(16))},
+            {"type": "text", "text": "{\"rows\":[\"z\",\"z\"\n[... truncated 74 bytes ...]"},
+            {"type": "text", "text": "ok"}
+        ])
+    );
+
+    // Tool results streamed as user messages pass through the same limit.
+    query
+        .stream_input(stream::iter(vec![json!({
+            "type": "user",
+            "message": {
+                "role": "user",
+                "content": [{"type": "tool_result", "tool_use_id": "t1", "content": "w".repeat(40)}]
+            }
+        })]))
+        .await
+        .expect("input should be written");
+    let writes = transport.writes().await;
+    let sent = writes.last().expect("user message written");
+    assert_eq!(
+        sent["message"]["content"][0]["content"],
+        format!("{}\n[... truncated 24 bytes ...]", "w".repeat(16))
+    );
+    query.close().await.expect("close should succeed");
+}
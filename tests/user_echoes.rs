@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use futures::StreamExt;
+use serde_json::{json, Value};
+
+use sdk_claude_rust::client::ClaudeSdkClient;
+use sdk_claude_rust::config::ClaudeAgentOptions;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::testing::MockTransport;
+use sdk_claude_rust::transport::Transport;
+
+fn turn() -> Vec<Value> {
+    vec![
+        json!({"type": "user", "message": {"role": "user", "content": "List files"}}),
+        json!({
+            "type": "assistant",
+            "message": {"model": "claude-test", "content": [
+                {"type": "tool_use", "id": "t1", "name": "Bash", "input": {"command": "ls"}}
+            ]}
+        }),
+        json!({"type": "user", "message": {"role": "user", "content": [
+            {"type": "tool_result", "tool_use_id": "t1", "content": "README.md"}
+        ]}}),
+        json!({
+            "type": "result",
+            "subtype": "success",
+            "duration_ms": 1,
+            "duration_api_ms": 0,
+            "is_error": false,
+            "num_turns": 1,
+            "session_id": "sess-echo"
+        }),
+    ]
+}
+
+async fn received(hide_user_echoes: bool) -> Vec<Message> {
+    let transport = MockTransport::new();
+    transport.set_keep_open(true);
+    transport.reply_to_next_user(turn()).await;
+    let options = ClaudeAgentOptions {
+        hide_user_echoes,
+        ..Default::default()
+    };
+    let mut client = ClaudeSdkClient::new(Some(options), Some(transport as Arc<dyn Transport>));
+    client.connect(None).await.expect("connect should succeed");
+    client.query("List files", "default").await.unwrap();
+    let messages = client
+        .receive_response()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+        .await;
+    client.disconnect().await.unwrap();
+    messages
+}
+
+#[tokio::test]
+async fn echoed_prompts_can_be_hidden() {
+    let all = received(false).await;
+    assert_eq!(all.len(), 4);
+    assert!(all[0].is_user_echo());
+    assert!(!all[2].is_user_echo());
+
+    let filtered = received(true).await;
+    assert_eq!(filtered.len(), 3);
+    assert!(matches!(&filtered[0], Message::Assistant(_)));
+    assert!(matches!(&filtered[1], Message::User(_)));
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn replay_flag_is_only_passed_when_streaming() {
+    use std::os::unix::fs::PermissionsExt;
+
+    use sdk_claude_rust::transport::subprocess_cli::{PromptMode, SubprocessCliTransport};
+
+    let dir = tempfile::tempdir().expect("temp dir");
+    let cli = dir.path().join("claude");
+    std::fs::write(
+        &cli,
+        "#!/bin/sh\n\
+         if [ \"$1\" = \"-v\" ]; then echo '2.0.5 (Claude Code)'; exit 0; fi\n\
+         printf '{\"args\":\"%s\"}\\n' \"$*\"\n",
+    )
+    .unwrap();
+    std::fs::set_permissions(&cli, std::fs::Permissions::from_mode(0o755)).unwrap();
+    let options = ClaudeAgentOptions {
+        cli_path: Some(cli),
+        replay_user_messages: true,
+        ..Default::default()
+    };
+
+    let mut args = Vec::new();
+    for prompt in [PromptMode::Streaming, PromptMode::Text("hi".into())] {
+        let transport =
+            SubprocessCliTransport::new(prompt, options.clone()).expect("transport should build");
+        transport.connect().await.expect("spawn should succeed");
+        let printed = transport.read().await.unwrap().expect("args line");
+        transport.close().await.unwrap();
+        args.push(printed["args"].as_str().unwrap().to_string());
+    }
+    assert!(args[0].contains("--replay-user-messages"));
+    assert!(!args[1].contains("--replay-user-messages"));
+}
@@ -0,0 +1,123 @@
+#![cfg(feature = "web")]
+
+use futures::{stream, StreamExt};
+use serde_json::{json, Value};
+
+use sdk_claude_rust::error::SdkError;
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::message::Message;
+use sdk_claude_rust::web::{sse_bytes, sse_events, SseFramer};
+
+fn parsed(raw: Value) -> Message {
+    parse_message(&raw).expect("fixture should parse")
+}
+
+fn text_delta(text: &str) -> Message {
+    parsed(json!({
+        "type": "stream_event",
+        "uuid": "evt",
+        "session_id": "sess-web",
+        "event": {
+            "type": "content_block_delta",
+            "index": 0,
+            "delta": {"type": "text_delta", "text": text}
+        }
+    }))
+}
+
+fn assistant(content: Value) -> Message {
+    parsed(json!({
+        "type": "assistant",
+        "message": {"model": "claude-test", "content": content}
+    }))
+}
+
+fn result() -> Message {
+    parsed(json!({
+        "type": "result",
+        "subtype": "success",
+        "duration_ms": 12,
+        "duration_api_ms": 10,
+        "is_error": false,
+        "num_turns": 2,
+        "session_id": "sess-web",
+        "total_cost_usd": 0.01,
+        "result": "Hello world"
+    }))
+}
+
+#[test]
+fn deltas_are_not_repeated_by_the_complete_assistant_message() {
+    let mut framer = SseFramer::new();
+    let mut events = Vec::new();
+    for message in [
+        text_delta("Hello"),
+        text_delta(" world"),
+        assistant(json!([
+            {"type": "text", "text": "Hello world"},
+            {"type": "tool_use", "id": "toolu_1", "name": "Read", "input": {"file_path": "a.rs"}}
+        ])),
+        parsed(json!({
+            "type": "user",
+            "message": {"role": "user", "content": [
+                {"type": "tool_result", "tool_use_id": "toolu_1", "content": "fn main() {}"}
+            ]}
+        })),
+        // Without deltas the complete text block is sent.
+        assistant(json!([{"type": "text", "text": "Done."}])),
+        result(),
+    ] {
+        events.extend(framer.frame(&message));
+    }
+
+    let summary: Vec<_> = events.iter().map(|sse| sse.event).collect();
+    assert_eq!(
+        summary,
+        vec!["text", "text", "tool_use", "tool_result", "text", "result"]
+    );
+    assert_eq!(events[1].data, json!({"text": " world"}));
+    assert_eq!(
+        events[2].data,
+        json!({"id": "toolu_1", "name": "Read", "input": {"file_path": "a.rs"}})
+    );
+    assert_eq!(
+        events[3].data,
+        json!({"tool_use_id": "toolu_1", "content": "fn main() {}", "is_error": false})
+    );
+    assert_eq!(events[4].data, json!({"text": "Done."}));
+    assert_eq!(events[5].data["session_id"], "sess-web");
+    assert_eq!(events[5].data["total_cost_usd"], 0.01);
+}
+
+#[tokio::test]
+async fn byte_stream_frames_events_and_ends_with_errors() {
+    let messages = stream::iter(vec![
+        Ok(text_delta("Hi")),
+        Err(SdkError::Protocol("stream closed".into())),
+        Ok(result()),
+    ]);
+    let chunks: Vec<_> = sse_bytes(messages)
+        .map(|chunk| String::from_utf8(chunk.unwrap().to_vec()).unwrap())
+        .collect()
+        .await;
+
+    assert_eq!(chunks.len(), 2);
+    assert_eq!(chunks[0], "event: text\ndata: {\"text\":\"Hi\"}\n\n");
+    assert!(chunks[1].starts_with("event: error\ndata: {\"message\":"));
+    assert!(chunks[1].contains("stream closed"));
+}
+
+#[tokio::test]
+async fn subagent_events_carry_their_parent() {
+    let message = parsed(json!({
+        "type": "assistant",
+        "parent_tool_use_id": "toolu_task",
+        "message": {"model": "claude-test", "content": [{"type": "text", "text": "sub"}]}
+    }));
+    let events: Vec<_> = sse_events(stream::iter(vec![Ok(message)])).collect().await;
+
+    assert_eq!(
+        events[0].data,
+        json!({"text": "sub", "parent_tool_use_id": "toolu_task"})
+    );
+}
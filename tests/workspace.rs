@@ -0,0 +1,96 @@
+use std::path::PathBuf;
+
+use serde_json::{json, Value};
+
+use sdk_claude_rust::config::{ClaudeAgentOptions, SystemPrompt};
+use sdk_claude_rust::internal::message_parser::parse_message;
+use sdk_claude_rust::workspace::Workspace;
+
+fn call(id: &str, name: &str, input: Value) -> Value {
+    json!({
+        "type": "assistant",
+        "message": {
+            "model": "claude-test",
+            "content": [{"type": "tool_use", "id": id, "name": name, "input": input}]
+        }
+    })
+}
+
+fn done(id: &str, is_error: bool) -> Value {
+    json!({
+        "type": "user",
+        "message": {
+            "role": "user",
+            "content": [{"type": "tool_result", "tool_use_id": id, "content": "ok", "is_error": is_error}]
+        }
+    })
+}
+
+#[test]
+fn apply_to_sets_directories_and_context() {
+    let workspace = Workspace::new("/srv/repo")
+        .with_add_dir("/srv/protos")
+        .with_context("Conventions", "Run `make check` before finishing.\n")
+        .with_context("Layout", "Services live in `svc/`.");
+    let mut options = ClaudeAgentOptions {
+        add_dirs: vec![PathBuf::from("/srv/protos")],
+        system_prompt: Some(SystemPrompt::Text("Be terse.".into())),
+        ..Default::default()
+    };
+    workspace.apply_to(&mut options);
+
+    assert_eq!(options.cwd, Some(PathBuf::from("/srv/repo")));
+    assert_eq!(options.add_dirs, vec![PathBuf::from("/srv/protos")]);
+    assert_eq!(
+        options.system_prompt,
+        Some(SystemPrompt::Text(
+            "Be terse.\n\n# Workspace context\n\n\
+             ## Conventions\n\nRun `make check` before finishing.\n\n\
+             ## Layout\n\nServices live in `svc/`.\n"
+                .into()
+        ))
+    );
+    assert_eq!(Workspace::new("/srv/repo").context_markdown(), None);
+}
+
+#[test]
+fn successful_file_tools_are_reported_as_changes() {
+    let workspace = Workspace::new("/srv/repo");
+    let shared = workspace.clone();
+    let messages = [
+        call("1", "Edit", json!({"file_path": "/srv/repo/src/lib.rs"})),
+        call("2", "Write", json!({"file_path": "/tmp/notes.md"})),
+        call("3", "Edit", json!({"file_path": "/srv/repo/src/broken.rs"})),
+        call("4", "Read", json!({"file_path": "/srv/repo/README.md"})),
+        call(
+            "5",
+            "NotebookEdit",
+            json!({"notebook_path": "/srv/repo/analysis.ipynb"}),
+        ),
+        done("1", false),
+        done("2", false),
+        done("3", true),
+        done("4", false),
+        done("5", false),
+        call(
+            "6",
+            "MultiEdit",
+            json!({"file_path": "/srv/repo/src/lib.rs"}),
+        ),
+        done("6", false),
+    ];
+    for raw in messages {
+        shared.observe(&parse_message(&raw).unwrap());
+    }
+
+    assert_eq!(
+        workspace.changed_files(),
+        vec![
+            PathBuf::from("/tmp/notes.md"),
+            PathBuf::from("analysis.ipynb"),
+            PathBuf::from("src/lib.rs"),
+        ]
+    );
+    workspace.clear_changes();
+    assert!(workspace.changed_files().is_empty());
+}